@@ -0,0 +1,106 @@
+use crate::LodChunkEvent;
+
+use building_blocks_storage::{ChunkKey3, LodChunkUpdate3, SmallKeyHashMap};
+
+use bevy::prelude::*;
+
+/// A resource that maps each chunk key to the entity that renders the chunk's mesh, so the mesh can be replaced when the chunk
+/// is edited, or despawned when the chunk changes level of detail.
+#[derive(Default)]
+pub struct ChunkMeshEntities {
+    entities: SmallKeyHashMap<ChunkKey3, Entity>,
+}
+
+impl ChunkMeshEntities {
+    pub fn get(&self, key: ChunkKey3) -> Option<Entity> {
+        self.entities.get(&key).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Spawns a `PbrBundle` for the chunk at `key`, despawning the chunk's old entity, if any. If `mesh` is `None`, e.g.
+    /// because the chunk has no surface, then the old entity is despawned and nothing replaces it.
+    ///
+    /// Returns the new entity.
+    pub fn set_mesh(
+        &mut self,
+        key: ChunkKey3,
+        mesh: Option<impl Into<Mesh>>,
+        material: Handle<StandardMaterial>,
+        commands: &mut Commands,
+        mesh_assets: &mut Assets<Mesh>,
+    ) -> Option<Entity> {
+        let new_entity = mesh.map(|mesh| {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh_assets.add(mesh.into()),
+                    material,
+                    ..Default::default()
+                })
+                .id()
+        });
+
+        let old_entity = if let Some(entity) = new_entity {
+            self.entities.insert(key, entity)
+        } else {
+            self.entities.remove(&key)
+        };
+        if let Some(entity) = old_entity {
+            commands.entity(entity).despawn();
+        }
+
+        new_entity
+    }
+
+    /// Despawns the entity for the chunk at `key`. Returns `true` if there was one.
+    pub fn despawn(&mut self, key: ChunkKey3, commands: &mut Commands) -> bool {
+        if let Some(entity) = self.entities.remove(&key) {
+            commands.entity(entity).despawn();
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Despawns the entities of all chunks that are replaced by `update`, and returns the keys of the chunks that replace them.
+    /// Those chunks need new meshes.
+    pub fn apply_lod_update(
+        &mut self,
+        update: &LodChunkUpdate3,
+        commands: &mut Commands,
+    ) -> Vec<ChunkKey3> {
+        match update {
+            LodChunkUpdate3::Split(split) => {
+                self.despawn(split.old_chunk, commands);
+
+                split.new_chunks.clone()
+            }
+            LodChunkUpdate3::Merge(merge) => {
+                for &key in merge.old_chunks.iter() {
+                    self.despawn(key, commands);
+                }
+
+                vec![merge.new_chunk]
+            }
+        }
+    }
+
+    /// Same as `apply_lod_update`, but also handles newly activated chunks, which just need meshes.
+    pub fn apply_lod_event(
+        &mut self,
+        event: &LodChunkEvent,
+        commands: &mut Commands,
+    ) -> Vec<ChunkKey3> {
+        match event {
+            LodChunkEvent::Activate(key) => vec![*key],
+            LodChunkEvent::Update(update) => self.apply_lod_update(update, commands),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{ChunkKey3, ChunkUnits, LodChunkUpdate3, OctreeChunkIndex};
+
+use bevy::prelude::*;
+
+/// Marks the entity whose position is the center of the clipmap, usually the camera. If there are multiple, only the first
+/// one is used.
+pub struct ClipmapFocus;
+
+/// The resource used by `clipmap_lod_system` to remember where the clipmap was centered.
+pub struct ClipmapLodState {
+    /// Only chunks in this extent are considered.
+    pub world_extent: Extent3i,
+    /// The radius (in chunks) of a clipbox at any level of detail.
+    pub clip_box_radius: u16,
+
+    lod0_center: Option<ChunkUnits<Point3i>>,
+}
+
+impl ClipmapLodState {
+    pub fn new(world_extent: Extent3i, clip_box_radius: u16) -> Self {
+        Self {
+            world_extent,
+            clip_box_radius,
+            lod0_center: None,
+        }
+    }
+
+    /// The center of the clipmap at LOD 0, or `None` if the clipmap hasn't been activated yet.
+    pub fn lod0_center(&self) -> Option<ChunkUnits<Point3i>> {
+        self.lod0_center
+    }
+
+    /// Forget the current center so that every active chunk is sent as `LodChunkEvent::Activate` on the next update, e.g.
+    /// after loading a new map.
+    pub fn reset(&mut self) {
+        self.lod0_center = None;
+    }
+}
+
+/// Sent by `clipmap_lod_system` when the chunks that should be rendered change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LodChunkEvent {
+    /// This chunk became active when the clipmap was first centered.
+    Activate(ChunkKey3),
+    /// Some chunks changed their level of detail because the clipmap moved.
+    Update(LodChunkUpdate3),
+}
+
+/// Adds the `LodChunkEvent` event and the `clipmap_lod_system`. The `ClipmapLodState` and `OctreeChunkIndex` resources must be
+/// inserted separately.
+pub struct ClipmapLodPlugin;
+
+impl Plugin for ClipmapLodPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<LodChunkEvent>()
+            .add_system(clipmap_lod_system.system());
+    }
+}
+
+/// Sends `LodChunkEvent`s whenever the `ClipmapFocus` entity moves into a different chunk.
+pub fn clipmap_lod_system(
+    chunk_index: Res<OctreeChunkIndex>,
+    focus: Query<&Transform, With<ClipmapFocus>>,
+    mut state: ResMut<ClipmapLodState>,
+    mut events: EventWriter<LodChunkEvent>,
+) {
+    let position = if let Some(tfm) = focus.iter().next() {
+        tfm.translation
+    } else {
+        return;
+    };
+
+    let lod0_center = ChunkUnits(
+        PointN([position.x, position.y, position.z]).in_voxel()
+            >> chunk_index.chunk_exponent() as i32,
+    );
+
+    match state.lod0_center {
+        None => chunk_index.active_clipmap_lod_chunks(
+            &state.world_extent,
+            state.clip_box_radius,
+            lod0_center,
+            |key| events.send(LodChunkEvent::Activate(key)),
+        ),
+        Some(old_lod0_center) => {
+            if old_lod0_center == lod0_center {
+                return;
+            }
+            chunk_index.find_clipmap_chunk_updates(
+                &state.world_extent,
+                state.clip_box_radius,
+                old_lod0_center,
+                lod0_center,
+                |update| events.send(LodChunkEvent::Update(update)),
+            );
+        }
+    }
+
+    state.lod0_center = Some(lod0_center);
+}
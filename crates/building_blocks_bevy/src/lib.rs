@@ -0,0 +1,49 @@
+//! Integrations between building-blocks and the [Bevy](https://bevyengine.org/) game engine.
+//!
+//!   - `PosNormMesh` and `PosNormTexMesh` convert into Bevy `Mesh`es with `From`
+//!   - `ChunkMeshEntities` keeps track of one mesh entity per chunk
+//!   - `ClipmapLodPlugin` sends `LodChunkEvent`s as the `ClipmapFocus` entity moves through a clipmap
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use building_blocks_bevy::*;
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! fn setup(mut commands: Commands) {
+//!     let chunk_index = OctreeChunkIndex::new_empty(8, 4, 4);
+//!     let world_extent = Extent3i::from_min_and_shape(Point3i::fill(-512), Point3i::fill(1024));
+//!     commands.insert_resource(ClipmapLodState::new(world_extent, 4));
+//!     commands.insert_resource(chunk_index);
+//!     commands.spawn_bundle(PerspectiveCameraBundle::default()).insert(ClipmapFocus);
+//! }
+//!
+//! fn mesh_chunks(
+//!     mut commands: Commands,
+//!     mut events: EventReader<LodChunkEvent>,
+//!     mut chunk_meshes: ResMut<ChunkMeshEntities>,
+//!     mut mesh_assets: ResMut<Assets<Mesh>>,
+//! ) {
+//!     for event in events.iter() {
+//!         for key in chunk_meshes.apply_lod_event(event, &mut commands) {
+//!             // ...generate a mesh for the chunk at `key` and spawn it with `ChunkMeshEntities::set_mesh`...
+//!         }
+//!     }
+//! }
+//!
+//! App::build()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugin(ClipmapLodPlugin)
+//!     .init_resource::<ChunkMeshEntities>()
+//!     .add_startup_system(setup.system())
+//!     .add_system(mesh_chunks.system())
+//!     .run();
+//! ```
+
+pub mod chunk_meshes;
+pub mod clipmap_lod;
+
+pub use chunk_meshes::*;
+pub use clipmap_lod::*;
+
+pub use bevy;
@@ -1,4 +1,4 @@
-use crate::{point::point_traits::*, Point2, Point2f, Point3, Point3f, PointN};
+use crate::{point::point_traits::*, Point2, Point2d, Point2f, Point3, Point3d, Point3f, PointN};
 
 use bytemuck::{Pod, Zeroable};
 use core::ops::{Add, AddAssign, Mul, Shl, Shr, Sub, SubAssign};
@@ -11,12 +11,20 @@ pub type Extent2<T> = ExtentN<[T; 2]>;
 pub type Extent2i = ExtentN<[i32; 2]>;
 /// A 2-dimensional extent with scalar type `f32`.
 pub type Extent2f = ExtentN<[f32; 2]>;
+/// A 2-dimensional extent with scalar type `f64`.
+pub type Extent2d = ExtentN<[f64; 2]>;
 /// A 3-dimensional extent with scalar type `T`.
 pub type Extent3<T> = ExtentN<[T; 3]>;
 /// A 3-dimensional extent with scalar type `i32`.
 pub type Extent3i = ExtentN<[i32; 3]>;
 /// A 3-dimensional extent with scalar type `f32`.
 pub type Extent3f = ExtentN<[f32; 3]>;
+/// A 3-dimensional extent with scalar type `f64`.
+pub type Extent3d = ExtentN<[f64; 3]>;
+/// A 4-dimensional extent with scalar type `T`.
+pub type Extent4<T> = ExtentN<[T; 4]>;
+/// A 4-dimensional extent with scalar type `i32`.
+pub type Extent4i = ExtentN<[i32; 4]>;
 
 /// An N-dimensional extent. This is mathematically the Cartesian product of a half-closed interval `[a, b)` in each dimension.
 /// You can also just think of it as an axis-aligned box with some shape and a minimum point. When doing queries against lattice
@@ -370,6 +378,20 @@ impl From<Extent3i> for Extent3f {
     }
 }
 
+impl From<Extent2i> for Extent2d {
+    #[inline]
+    fn from(other: Extent2i) -> Self {
+        Self::from_min_and_shape(Point2d::from(other.minimum), Point2d::from(other.shape))
+    }
+}
+
+impl From<Extent3i> for Extent3d {
+    #[inline]
+    fn from(other: Extent3i) -> Self {
+        Self::from_min_and_shape(Point3d::from(other.minimum), Point3d::from(other.shape))
+    }
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -428,4 +450,29 @@ mod test {
         assert_eq!(e1.intersection(&e2).shape, PointN([0; 2]));
         assert!(e1.intersection(&e2).is_empty());
     }
+
+    #[test]
+    fn f64_extent_contains_points_beyond_f32_precision() {
+        let far = 1.0e9;
+        let extent = Extent3d::from_min_and_shape(Point3d::fill(far), Point3d::fill(1.0));
+
+        assert!(extent.contains(Point3d::fill(far + 0.5)));
+        assert!(!extent.contains(Point3d::fill(far + 1.0)));
+
+        // In `f32`, these points would be indistinguishable from the minimum.
+        assert_eq!(far as f32 + 0.5, far as f32);
+    }
+
+    #[test]
+    fn f64_points_round_to_integer_lattice() {
+        let p: Point3d = PointN([-0.5, 1.5, 2.0]);
+
+        assert_eq!(p.in_voxel(), PointN([-1, 1, 2]));
+        assert_eq!(p.in_voxel_with_size(0.5), PointN([-1, 3, 4]));
+        assert_eq!(Point3d::from(Point3i::fill(-2)), Point3d::fill(-2.0));
+        assert_eq!(
+            Extent3d::from(Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::ONES)),
+            Extent3d::from_min_and_shape(Point3d::ZERO, Point3d::ONES)
+        );
+    }
 }
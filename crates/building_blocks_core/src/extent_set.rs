@@ -0,0 +1,293 @@
+//! Set algebra on integer extents, with results as lists of disjoint extents.
+//!
+//! The difference of two boxes is not a box, but it can always be split into at most `2 * N` disjoint boxes, one pair of
+//! slabs per axis. This is the core of most streaming logic, e.g. finding what moved into or out of view:
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//!
+//! let old_view = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(10));
+//! let new_view = old_view + PointN([2, 0, 0]);
+//!
+//! let entered = new_view.difference(&old_view);
+//! let exited = old_view.difference(&new_view);
+//! assert_eq!(entered, vec![Extent3i::from_min_and_shape(PointN([10, 0, 0]), PointN([2, 10, 10]))]);
+//! assert_eq!(exited, vec![Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 10, 10]))]);
+//!
+//! // An `ExtentSet` keeps an arbitrary set of points as disjoint extents.
+//! let mut loaded = ExtentSet::from(old_view);
+//! loaded.insert(new_view);
+//! assert_eq!(loaded.num_points(), 12 * 10 * 10);
+//! loaded.remove(&old_view);
+//! assert_eq!(loaded.extents(), &entered[..]);
+//! ```
+
+use crate::{point::point_traits::*, ExtentN, PointN};
+
+use alloc::{vec, vec::Vec};
+
+impl<N> ExtentN<N>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    /// All of the points in `self` that aren't in `other`, as at most `2 * N` disjoint extents.
+    #[inline]
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        let mut pieces = Vec::new();
+
+        let cut = self.intersection(other);
+        if cut.is_empty() {
+            if !self.is_empty() {
+                pieces.push(*self);
+            }
+            return pieces;
+        }
+
+        // Peel off the slabs on either side of `cut` one axis at a time. Whatever remains is inside of `cut`.
+        let mut remaining = *self;
+        for (i, unit) in PointN::<N>::basis().into_iter().enumerate() {
+            let rem_min = remaining.minimum;
+            let rem_lub = remaining.least_upper_bound();
+            let cut_min = cut.minimum.at(i);
+            let cut_lub = cut.least_upper_bound().at(i);
+
+            if cut_min > rem_min.at(i) {
+                let below_lub = rem_lub + unit * (cut_min - rem_lub.at(i));
+                pieces.push(Self::from_min_and_lub(rem_min, below_lub));
+            }
+            if cut_lub < rem_lub.at(i) {
+                let above_min = rem_min + unit * (cut_lub - rem_min.at(i));
+                pieces.push(Self::from_min_and_lub(above_min, rem_lub));
+            }
+
+            remaining = Self::from_min_and_lub(
+                rem_min + unit * (cut_min - rem_min.at(i)),
+                rem_lub + unit * (cut_lub - rem_lub.at(i)),
+            );
+        }
+
+        pieces
+    }
+
+    /// All of the points in either `self` or `other`, as at most `2 * N + 1` disjoint extents.
+    #[inline]
+    pub fn union(&self, other: &Self) -> Vec<Self> {
+        let mut pieces = other.difference(self);
+        if !self.is_empty() {
+            pieces.insert(0, *self);
+        }
+
+        pieces
+    }
+}
+
+/// A set of lattice points, stored as a list of disjoint extents.
+///
+/// This is meant for sets that are made of a few large boxes, like the regions of a map that are loaded. Every operation is
+/// linear in the number of extents.
+#[derive(Clone, Debug, Default)]
+pub struct ExtentSet<N> {
+    extents: Vec<ExtentN<N>>,
+}
+
+/// A 2-dimensional `ExtentSet`.
+pub type ExtentSet2i = ExtentSet<[i32; 2]>;
+/// A 3-dimensional `ExtentSet`.
+pub type ExtentSet3i = ExtentSet<[i32; 3]>;
+
+impl<N> ExtentSet<N> {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            extents: Vec::new(),
+        }
+    }
+
+    /// The disjoint extents that make up this set.
+    #[inline]
+    pub fn extents(&self) -> &[ExtentN<N>] {
+        &self.extents
+    }
+
+    #[inline]
+    pub fn into_extents(self) -> Vec<ExtentN<N>> {
+        self.extents
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.extents.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.extents.clear();
+    }
+}
+
+impl<N> ExtentSet<N>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    /// Adds all points of `extent` to the set.
+    #[inline]
+    pub fn insert(&mut self, extent: ExtentN<N>) {
+        let mut new_pieces = vec![extent];
+        for existing in self.extents.iter() {
+            new_pieces = new_pieces
+                .iter()
+                .flat_map(|piece| piece.difference(existing))
+                .collect();
+        }
+        self.extents
+            .extend(new_pieces.into_iter().filter(|e| !e.is_empty()));
+    }
+
+    /// Removes all points of `extent` from the set.
+    #[inline]
+    pub fn remove(&mut self, extent: &ExtentN<N>) {
+        self.extents = self
+            .extents
+            .iter()
+            .flat_map(|existing| existing.difference(extent))
+            .collect();
+    }
+
+    #[inline]
+    pub fn contains(&self, p: PointN<N>) -> bool {
+        self.extents.iter().any(|e| e.contains(p))
+    }
+
+    /// Returns `true` iff every point of `extent` is in the set.
+    #[inline]
+    pub fn contains_extent(&self, extent: &ExtentN<N>) -> bool {
+        let mut uncovered = vec![*extent];
+        for existing in self.extents.iter() {
+            uncovered = uncovered
+                .iter()
+                .flat_map(|piece| piece.difference(existing))
+                .collect();
+        }
+
+        uncovered.iter().all(|e| e.is_empty())
+    }
+
+    /// The set of points in both `self` and `extent`.
+    #[inline]
+    pub fn intersection(&self, extent: &ExtentN<N>) -> Self {
+        Self {
+            extents: self
+                .extents
+                .iter()
+                .map(|e| e.intersection(extent))
+                .filter(|e| !e.is_empty())
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub fn num_points(&self) -> usize {
+        self.extents.iter().map(|e| e.num_points()).sum()
+    }
+}
+
+impl<N> From<ExtentN<N>> for ExtentSet<N>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    #[inline]
+    fn from(extent: ExtentN<N>) -> Self {
+        let mut set = Self::new();
+        set.insert(extent);
+
+        set
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Extent2i, Extent3i, Point3i};
+
+    fn assert_disjoint_cover(
+        pieces: &[Extent3i],
+        expected: impl Fn(Point3i) -> bool,
+        bounds: Extent3i,
+    ) {
+        for p in bounds.iter_points() {
+            let count = pieces.iter().filter(|e| e.contains(p)).count();
+            assert_eq!(count, expected(p) as usize, "p = {:?}", p);
+        }
+    }
+
+    #[test]
+    fn difference_of_nested_extents_has_six_pieces() {
+        let outer = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(5));
+        let inner = Extent3i::from_min_and_shape(Point3i::fill(1), Point3i::fill(3));
+
+        let pieces = outer.difference(&inner);
+        assert_eq!(pieces.len(), 6);
+        assert_disjoint_cover(&pieces, |p| outer.contains(p) && !inner.contains(p), outer);
+
+        assert!(inner.difference(&outer).is_empty());
+    }
+
+    #[test]
+    fn difference_and_union_of_overlapping_extents() {
+        let a = Extent3i::from_min_and_shape(PointN([-2, 0, 1]), PointN([4, 3, 5]));
+        let b = Extent3i::from_min_and_shape(PointN([0, -1, 3]), PointN([5, 2, 4]));
+        let bounds = Extent3i::from_min_and_shape(Point3i::fill(-4), Point3i::fill(12));
+
+        assert_disjoint_cover(
+            &a.difference(&b),
+            |p| a.contains(p) && !b.contains(p),
+            bounds,
+        );
+        assert_disjoint_cover(&a.union(&b), |p| a.contains(p) || b.contains(p), bounds);
+    }
+
+    #[test]
+    fn difference_of_disjoint_extents_is_unchanged() {
+        let a = Extent2i::from_min_and_shape(PointN([0, 0]), PointN([2, 2]));
+        let b = Extent2i::from_min_and_shape(PointN([2, 0]), PointN([2, 2]));
+
+        assert_eq!(a.difference(&b), vec![a]);
+        assert_eq!(a.union(&b), vec![a, b]);
+    }
+
+    #[test]
+    fn extent_set_insert_and_remove() {
+        let a = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let b = a + Point3i::fill(2);
+        let hole = Extent3i::from_min_and_shape(Point3i::fill(1), Point3i::ONES);
+
+        let mut set = ExtentSet3i::from(a);
+        set.insert(b);
+        set.insert(a);
+        assert_eq!(set.num_points(), 64 + 64 - 8);
+        assert!(set.contains_extent(&a));
+        assert!(set.contains_extent(&b));
+
+        set.remove(&hole);
+        assert!(!set.contains(Point3i::fill(1)));
+        assert!(!set.contains_extent(&a));
+        assert_eq!(set.num_points(), 64 + 64 - 8 - 1);
+
+        let bounds = Extent3i::from_min_and_shape(Point3i::fill(-1), Point3i::fill(8));
+        assert_disjoint_cover(
+            set.extents(),
+            |p| (a.contains(p) || b.contains(p)) && !hole.contains(p),
+            bounds,
+        );
+
+        assert_eq!(set.intersection(&hole).num_points(), 0);
+        assert_eq!(set.intersection(&b).num_points(), 64);
+    }
+}
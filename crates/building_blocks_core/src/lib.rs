@@ -1,22 +1,38 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(clippy::missing_inline_in_public_items)]
 
 //! The core data types for defining 2D and 3D integer lattices:
 //! - `PointN`: an N-dimensional point, most importantly `Point2i` and `Point3i`
 //! - `ExtentN`: an N-dimensional extent, most importantly `Extent2i` and `Extent3i`
+//! - `ExtentSet`: a set of points made of disjoint extents, for union and difference
+//! - `OrthoTransform3`: a rotation by multiples of 90 degrees and/or mirror of the 3D lattice
+//!
+//! The "std" feature is enabled by default. Without it, this crate is `no_std` and only needs `alloc`, with floating point
+//! math provided by `libm`. The math library integrations still require "std".
+
+extern crate alloc;
 
 pub mod axis;
 pub mod extent;
+pub mod extent_set;
 pub mod morton;
 pub mod orthant;
+pub mod ortho_transform;
 pub mod point;
 
 pub use axis::{Axis2, Axis3, Axis3Permutation, SignedAxis2, SignedAxis3};
 pub use extent::{
-    bounding_extent, Extent2, Extent2f, Extent2i, Extent3, Extent3f, Extent3i, ExtentN,
+    bounding_extent, Extent2, Extent2d, Extent2f, Extent2i, Extent3, Extent3d, Extent3f, Extent3i,
+    Extent4, Extent4i, ExtentN,
 };
+pub use extent_set::{ExtentSet, ExtentSet2i, ExtentSet3i};
 pub use morton::*;
 pub use orthant::*;
-pub use point::{point_traits::*, Point2, Point2f, Point2i, Point3, Point3f, Point3i, PointN};
+pub use ortho_transform::OrthoTransform3;
+pub use point::{
+    point_traits::*, Point2, Point2d, Point2f, Point2i, Point3, Point3d, Point3f, Point3i, Point4,
+    Point4i, PointN,
+};
 
 pub use bytemuck;
 pub use num;
@@ -24,12 +40,17 @@ pub use num;
 pub mod prelude {
     pub use super::{
         point::point_traits::*, Axis2, Axis3, Bounded, ConstZero, Distance, DotProduct, Extent2,
-        Extent2f, Extent2i, Extent3, Extent3f, Extent3i, ExtentN, GetComponent, IntegerPoint,
-        MapComponents, Morton2, Morton3, Neighborhoods, Norm, Octant, Ones, Orthant, Point, Point2,
-        Point2f, Point2i, Point3, Point3f, Point3i, PointN, Quadrant,
+        Extent2d, Extent2f, Extent2i, Extent3, Extent3d, Extent3f, Extent3i, Extent4, Extent4i,
+        ExtentN, ExtentSet, ExtentSet2i, ExtentSet3i, GetComponent, IntegerPoint, MapComponents,
+        Morton2, Morton3, Neighborhoods, Norm, Octant, Ones, Orthant, OrthoTransform3, Point,
+        Point2, Point2d, Point2f, Point2i, Point3, Point3d, Point3f, Point3i, Point4, Point4i,
+        PointN, Quadrant,
     };
 }
 
+#[cfg(feature = "cgmath")]
+pub use cgmath;
+
 #[cfg(feature = "glam")]
 pub use glam;
 
@@ -1,7 +1,39 @@
-use crate::{Point2i, Point3i};
+//! Morton (Z-order) codes for `Point2i` and `Point3i`, covering the full range of `i32`.
+//!
+//! Morton order is the key order used by the chunk databases, since it keeps nearby points close together in key space. It's
+//! also useful for custom databases and spatial hashing. Any extent can be scanned with a set of contiguous ranges of Morton
+//! codes, where the gaps are skipped with the BIGMIN algorithm (see Tropf and Herzog, "Multidimensional Range Search in
+//! Dynamically Balanced Trees").
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//!
+//! let p = PointN([-1, 2, i32::MAX]);
+//! assert_eq!(Point3i::from(Morton3::from(p)), p);
+//!
+//! let extent = Extent3i::from_min_and_shape(PointN([-2, 0, 1]), PointN([3, 3, 2]));
+//! let mut num_points = 0;
+//! for range in Morton3::ranges_in_extent(&extent) {
+//!     for code in range.start().0..=range.end().0 {
+//!         assert!(extent.contains(Point3i::from(Morton3(code))));
+//!         num_points += 1;
+//!     }
+//! }
+//! assert_eq!(num_points, extent.num_points());
+//!
+//! // Skip from a code outside of the extent to the next code inside of it.
+//! let outside = Morton3::from(PointN([-2, 0, 0]));
+//! let next = outside.bigmin(&extent).unwrap();
+//! assert!(next > outside);
+//! assert!(extent.contains(Point3i::from(next)));
+//! ```
+
+use crate::{Extent2i, Extent3i, Point2i, Point3i};
 
 use bitintr::{Pdep, Pext};
-use std::fmt;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::RangeInclusive;
 
 // ██████╗ ██████╗
 // ╚════██╗██╔══██╗
@@ -10,10 +42,10 @@ use std::fmt;
 // ███████╗██████╔╝
 // ╚══════╝╚═════╝
 
-/// A Morton-encoded `Point2i`. Uses a `u64` to support the full set of `Point3i`s.
+/// A Morton-encoded `Point2i`. Uses a `u64` to support the full set of `Point2i`s.
 ///
 /// https://en.wikipedia.org/wiki/Z-order_curve
-#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Morton2(pub u64);
 
 impl Morton2 {
@@ -48,6 +80,47 @@ impl From<Morton2> for Point2i {
     }
 }
 
+impl Morton2 {
+    /// The big-endian bytes of this code, which sort in the same order as the codes.
+    #[inline]
+    pub fn to_be_bytes(self) -> [u8; 8] {
+        self.0.to_be_bytes()
+    }
+
+    #[inline]
+    pub fn from_be_bytes(bytes: [u8; 8]) -> Self {
+        Self(u64::from_be_bytes(bytes))
+    }
+
+    /// The least code of a point in `extent` that is greater than or equal to `self`, if there is one.
+    #[inline]
+    pub fn bigmin(self, extent: &Extent2i) -> Option<Self> {
+        MortonBox::from_extent2(extent)
+            .and_then(|b| b.bigmin(self.0 as u128))
+            .map(|bits| Self(bits as u64))
+    }
+
+    /// The greatest code of a point in `extent` that is less than or equal to `self`, if there is one.
+    #[inline]
+    pub fn litmax(self, extent: &Extent2i) -> Option<Self> {
+        MortonBox::from_extent2(extent)
+            .and_then(|b| b.litmax(self.0 as u128))
+            .map(|bits| Self(bits as u64))
+    }
+
+    /// The minimal set of contiguous ranges of codes that contain exactly the points of `extent`, in increasing order.
+    #[inline]
+    pub fn ranges_in_extent(extent: &Extent2i) -> MortonRanges<Self> {
+        MortonRanges::new(MortonBox::from_extent2(extent))
+    }
+
+    /// All of the codes for points in `extent`, in increasing order.
+    #[inline]
+    pub fn iter_extent(extent: &Extent2i) -> impl Iterator<Item = Self> {
+        Self::ranges_in_extent(extent).flat_map(|r| (r.start().0..=r.end().0).map(Self))
+    }
+}
+
 // ██████╗ ██████╗
 // ╚════██╗██╔══██╗
 //  █████╔╝██║  ██║
@@ -58,7 +131,7 @@ impl From<Morton2> for Point2i {
 /// A Morton-encoded `Point3i`. Uses a `u128` to support the full set of `Point3i`s.
 ///
 /// https://en.wikipedia.org/wiki/Z-order_curve
-#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Morton3(pub u128);
 
 impl fmt::Debug for Morton3 {
@@ -135,6 +208,54 @@ impl From<Morton3> for Point3i {
     }
 }
 
+impl Morton3 {
+    /// The big-endian bytes of the 96 bits used by this code, which sort in the same order as the codes.
+    #[inline]
+    pub fn to_be_bytes(self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        // The most significant 4 bytes of the u128 are not used.
+        bytes.copy_from_slice(&self.0.to_be_bytes()[4..]);
+
+        bytes
+    }
+
+    #[inline]
+    pub fn from_be_bytes(bytes: [u8; 12]) -> Self {
+        let mut all_bytes = [0; 16];
+        all_bytes[4..].copy_from_slice(&bytes);
+
+        Self(u128::from_be_bytes(all_bytes))
+    }
+
+    /// The least code of a point in `extent` that is greater than or equal to `self`, if there is one.
+    #[inline]
+    pub fn bigmin(self, extent: &Extent3i) -> Option<Self> {
+        MortonBox::from_extent3(extent)
+            .and_then(|b| b.bigmin(self.0))
+            .map(Self)
+    }
+
+    /// The greatest code of a point in `extent` that is less than or equal to `self`, if there is one.
+    #[inline]
+    pub fn litmax(self, extent: &Extent3i) -> Option<Self> {
+        MortonBox::from_extent3(extent)
+            .and_then(|b| b.litmax(self.0))
+            .map(Self)
+    }
+
+    /// The minimal set of contiguous ranges of codes that contain exactly the points of `extent`, in increasing order.
+    #[inline]
+    pub fn ranges_in_extent(extent: &Extent3i) -> MortonRanges<Self> {
+        MortonRanges::new(MortonBox::from_extent3(extent))
+    }
+
+    /// All of the codes for points in `extent`, in increasing order.
+    #[inline]
+    pub fn iter_extent(extent: &Extent3i) -> impl Iterator<Item = Self> {
+        Self::ranges_in_extent(extent).flat_map(|r| (r.start().0..=r.end().0).map(Self))
+    }
+}
+
 /// Send the supported range of i32 into the lower 32 bits of a u64 while preserving the total order.
 #[inline]
 fn translate(x: i32) -> u64 {
@@ -147,6 +268,203 @@ fn untranslate(x: u64) -> i32 {
     (x as i32).wrapping_add(i32::MIN)
 }
 
+// ██████╗  █████╗ ███╗   ██╗ ██████╗ ███████╗███████╗
+// ██╔══██╗██╔══██╗████╗  ██║██╔════╝ ██╔════╝██╔════╝
+// ██████╔╝███████║██╔██╗ ██║██║  ███╗█████╗  ███████╗
+// ██╔══██╗██╔══██║██║╚██╗██║██║   ██║██╔══╝  ╚════██║
+// ██║  ██║██║  ██║██║ ╚████║╚██████╔╝███████╗███████║
+// ╚═╝  ╚═╝╚═╝  ╚═╝╚═╝  ╚═══╝ ╚═════╝ ╚══════╝╚══════╝
+
+/// An iterator over the contiguous ranges of Morton codes that make up an extent. See `Morton2::ranges_in_extent` and
+/// `Morton3::ranges_in_extent`.
+#[derive(Clone)]
+pub struct MortonRanges<M> {
+    zbox: Option<MortonBox>,
+    next_start: Option<u128>,
+    marker: PhantomData<M>,
+}
+
+impl<M> MortonRanges<M> {
+    fn new(zbox: Option<MortonBox>) -> Self {
+        Self {
+            next_start: zbox.map(|b| b.min),
+            zbox,
+            marker: PhantomData,
+        }
+    }
+
+    fn next_range(&mut self) -> Option<(u128, u128)> {
+        let zbox = self.zbox?;
+        let start = self.next_start?;
+
+        // Grow the range by the largest aligned block of codes that fits in the box, until the next code is outside of it.
+        let mut end = start;
+        loop {
+            let next = end + 1;
+            if next > zbox.max {
+                self.next_start = None;
+                break;
+            }
+            if !zbox.contains(next) {
+                self.next_start = zbox.bigmin(next);
+                break;
+            }
+
+            let mut block_bits = 0;
+            while block_bits + zbox.dims() <= zbox.num_bits() {
+                let size_mask = (1 << (block_bits + zbox.dims())) - 1;
+                if next & size_mask != 0 || !zbox.contains(next | size_mask) {
+                    break;
+                }
+                block_bits += zbox.dims();
+            }
+            end = next | ((1 << block_bits) - 1);
+        }
+
+        Some((start, end))
+    }
+}
+
+impl Iterator for MortonRanges<Morton2> {
+    type Item = RangeInclusive<Morton2>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_range()
+            .map(|(start, end)| Morton2(start as u64)..=Morton2(end as u64))
+    }
+}
+
+impl Iterator for MortonRanges<Morton3> {
+    type Item = RangeInclusive<Morton3>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_range()
+            .map(|(start, end)| Morton3(start)..=Morton3(end))
+    }
+}
+
+const MORTON2_MASKS: [u128; 2] = [Morton2::X_MASK as u128, Morton2::Y_MASK as u128];
+
+const MORTON3_X_MASK: u128 = 0x2492_4924_9249_2492_4924_9249;
+const MORTON3_MASKS: [u128; 3] = [MORTON3_X_MASK, MORTON3_X_MASK << 1, MORTON3_X_MASK << 2];
+
+/// The Morton codes of the minimum and maximum of a nonempty extent, with one bit mask per dimension.
+///
+/// Each dimension's bits of a code compare in the same order as that dimension's coordinates, so all of the range logic works
+/// directly on the codes.
+#[derive(Clone, Copy)]
+struct MortonBox {
+    min: u128,
+    max: u128,
+    masks: &'static [u128],
+}
+
+impl MortonBox {
+    fn from_extent2(extent: &Extent2i) -> Option<Self> {
+        if extent.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            min: Morton2::from(extent.minimum).0 as u128,
+            max: Morton2::from(extent.max()).0 as u128,
+            masks: &MORTON2_MASKS,
+        })
+    }
+
+    fn from_extent3(extent: &Extent3i) -> Option<Self> {
+        if extent.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            min: Morton3::from(extent.minimum).0,
+            max: Morton3::from(extent.max()).0,
+            masks: &MORTON3_MASKS,
+        })
+    }
+
+    fn dims(&self) -> u32 {
+        self.masks.len() as u32
+    }
+
+    fn num_bits(&self) -> u32 {
+        32 * self.dims()
+    }
+
+    fn contains(&self, z: u128) -> bool {
+        self.masks
+            .iter()
+            .all(|&m| self.min & m <= z & m && z & m <= self.max & m)
+    }
+
+    /// BIGMIN: the least code in the box that is greater than or equal to `z`.
+    fn bigmin(&self, z: u128) -> Option<u128> {
+        if self.contains(z) {
+            return Some(z);
+        }
+
+        let (mut min, mut max) = (self.min, self.max);
+        let mut bigmin = None;
+        for i in (0..self.num_bits()).rev() {
+            let bit = 1 << i;
+            let mask = self.masks[(i % self.dims()) as usize];
+            match (z & bit != 0, min & bit != 0, max & bit != 0) {
+                (false, false, true) => {
+                    bigmin = Some(load_1000(min, bit, mask));
+                    max = load_0111(max, bit, mask);
+                }
+                (false, true, true) => return Some(min),
+                (true, false, false) => return bigmin,
+                (true, false, true) => min = load_1000(min, bit, mask),
+                (_, true, false) => unreachable!("box minimum is greater than maximum"),
+                _ => {}
+            }
+        }
+
+        bigmin
+    }
+
+    /// LITMAX: the greatest code in the box that is less than or equal to `z`.
+    fn litmax(&self, z: u128) -> Option<u128> {
+        if self.contains(z) {
+            return Some(z);
+        }
+
+        let (mut min, mut max) = (self.min, self.max);
+        let mut litmax = None;
+        for i in (0..self.num_bits()).rev() {
+            let bit = 1 << i;
+            let mask = self.masks[(i % self.dims()) as usize];
+            match (z & bit != 0, min & bit != 0, max & bit != 0) {
+                (false, false, true) => max = load_0111(max, bit, mask),
+                (false, true, true) => return litmax,
+                (true, false, false) => return Some(max),
+                (true, false, true) => {
+                    litmax = Some(load_0111(max, bit, mask));
+                    min = load_1000(min, bit, mask);
+                }
+                (_, true, false) => unreachable!("box minimum is greater than maximum"),
+                _ => {}
+            }
+        }
+
+        litmax
+    }
+}
+
+/// Sets `bit` and clears all of the less significant bits in the same dimension (`dim_mask`).
+fn load_1000(z: u128, bit: u128, dim_mask: u128) -> u128 {
+    (z & !(dim_mask & (bit | (bit - 1)))) | bit
+}
+
+/// Clears `bit` and sets all of the less significant bits in the same dimension (`dim_mask`).
+fn load_0111(z: u128, bit: u128, dim_mask: u128) -> u128 {
+    (z & !(dim_mask & (bit | (bit - 1)))) | (dim_mask & (bit - 1))
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -205,6 +523,94 @@ mod test {
         }
     }
 
+    #[test]
+    fn encode_decode_full_range_of_i32() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut random_i32 = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 32) as i32
+        };
+
+        for _ in 0..1000 {
+            let p2 = PointN([random_i32(), random_i32()]);
+            let p3 = PointN([random_i32(), random_i32(), random_i32()]);
+            let q3 = PointN([random_i32(), random_i32(), random_i32()]);
+            assert_eq!(p2, Point2i::from(Morton2::from(p2)));
+            assert_eq!(p3, Point3i::from(Morton3::from(p3)));
+
+            let m2 = Morton2::from(p2);
+            assert_eq!(Morton2::from_be_bytes(m2.to_be_bytes()), m2);
+            let (m3, n3) = (Morton3::from(p3), Morton3::from(q3));
+            assert_eq!(Morton3::from_be_bytes(m3.to_be_bytes()), m3);
+            assert_eq!(m3.cmp(&n3), m3.to_be_bytes().cmp(&n3.to_be_bytes()));
+        }
+    }
+
+    #[test]
+    fn bigmin_and_litmax_match_brute_force() {
+        let extent = Extent3i::from_min_and_shape(PointN([-3, 1, -2]), PointN([4, 3, 5]));
+        let bounds = extent.padded(3);
+        let mut codes: Vec<_> = extent.iter_points().map(Morton3::from).collect();
+        codes.sort();
+
+        for p in bounds.iter_points() {
+            let z = Morton3::from(p);
+            assert_eq!(z.bigmin(&extent), codes.iter().cloned().find(|&c| c >= z));
+            assert_eq!(
+                z.litmax(&extent),
+                codes.iter().cloned().rev().find(|&c| c <= z)
+            );
+        }
+
+        let extent = Extent2i::from_min_and_shape(PointN([-5, 2]), PointN([7, 3]));
+        let mut codes: Vec<_> = extent.iter_points().map(Morton2::from).collect();
+        codes.sort();
+
+        for p in extent.padded(4).iter_points() {
+            let z = Morton2::from(p);
+            assert_eq!(z.bigmin(&extent), codes.iter().cloned().find(|&c| c >= z));
+            assert_eq!(
+                z.litmax(&extent),
+                codes.iter().cloned().rev().find(|&c| c <= z)
+            );
+        }
+    }
+
+    #[test]
+    fn ranges_cover_extent_exactly() {
+        let extent = Extent3i::from_min_and_shape(PointN([-3, 1, -2]), PointN([5, 6, 7]));
+        let mut codes: Vec<_> = extent.iter_points().map(Morton3::from).collect();
+        codes.sort();
+
+        let ranges: Vec<_> = Morton3::ranges_in_extent(&extent).collect();
+        for pair in ranges.windows(2) {
+            // Ranges are maximal, so there is a gap between each of them.
+            assert!(pair[0].end().0 + 1 < pair[1].start().0);
+        }
+        assert_eq!(Morton3::iter_extent(&extent).collect::<Vec<_>>(), codes);
+
+        let extent = Extent2i::from_min_and_shape(PointN([-5, 2]), PointN([7, 3]));
+        let mut codes: Vec<_> = extent.iter_points().map(Morton2::from).collect();
+        codes.sort();
+        assert_eq!(Morton2::iter_extent(&extent).collect::<Vec<_>>(), codes);
+    }
+
+    #[test]
+    fn orthant_is_one_range() {
+        let orthant = Extent3i::from_min_and_shape(Point3i::fill(-4), Point3i::fill(4));
+        let ranges: Vec<_> = Morton3::ranges_in_extent(&orthant).collect();
+        assert_eq!(
+            ranges,
+            vec![Morton3::from(orthant.minimum)..=Morton3::from(orthant.max())]
+        );
+
+        let empty = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 0, 2]));
+        assert_eq!(Morton3::ranges_in_extent(&empty).count(), 0);
+        assert_eq!(Morton3::from(Point3i::ZERO).bigmin(&empty), None);
+    }
+
     fn mortons_are_contiguous(mortons: &[Morton3]) -> bool {
         let min = mortons[0].0;
         let upper_bound = min + mortons.len() as u128;
@@ -3,7 +3,7 @@ use crate::{
     PointN,
 };
 
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
 /// An extent for which, given some fixed power of 2 called P, satisfies:
 /// - each component of the minimum is a multiple of P
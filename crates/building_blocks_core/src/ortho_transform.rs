@@ -0,0 +1,199 @@
+//! Rotations by multiples of 90 degrees and mirrors of the 3D integer lattice.
+//!
+//! An `OrthoTransform3` maps each axis to a signed axis, so it always maps lattice points to lattice points. Points are
+//! transformed about the origin, so the voxel at the origin stays in place.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//!
+//! let rotate = OrthoTransform3::rotate_90(Axis3::Z);
+//! assert_eq!(rotate.transform_point(PointN([1, 2, 3])), PointN([-2, 1, 3]));
+//! assert_eq!(rotate.then(rotate), OrthoTransform3::rotate_180(Axis3::Z));
+//! assert_eq!(rotate.then(rotate.inverse()), OrthoTransform3::IDENTITY);
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 2, 1]));
+//! assert_eq!(
+//!     rotate.transform_extent(&extent),
+//!     Extent3i::from_min_and_shape(PointN([-1, 0, 0]), PointN([2, 4, 1]))
+//! );
+//!
+//! let mirror = OrthoTransform3::mirror(Axis3::X);
+//! assert!(mirror.is_mirror());
+//! assert_eq!(mirror.transform_point(PointN([1, 2, 3])), PointN([-1, 2, 3]));
+//! ```
+
+use crate::{Axis3, DotProduct, Extent3i, LatticeOrder, Point3i, PointN, SignedAxis3};
+
+/// One of the 48 transforms of the 3D lattice that map axes onto signed axes, i.e. all combinations of rotations by multiples of
+/// 90 degrees and mirrors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OrthoTransform3 {
+    /// The images of +X, +Y, and +Z.
+    images: [SignedAxis3; 3],
+}
+
+impl OrthoTransform3 {
+    pub const IDENTITY: Self = Self {
+        images: [
+            SignedAxis3::new(1, Axis3::X),
+            SignedAxis3::new(1, Axis3::Y),
+            SignedAxis3::new(1, Axis3::Z),
+        ],
+    };
+
+    /// The transform that maps +X, +Y, and +Z onto `images`. Returns `None` unless `images` are along 3 different axes.
+    #[inline]
+    pub fn from_images(images: [SignedAxis3; 3]) -> Option<Self> {
+        let [x, y, z] = images;
+        let distinct_axes = x.axis != y.axis && y.axis != z.axis && z.axis != x.axis;
+        let unit_signs = images.iter().all(|a| a.sign == 1 || a.sign == -1);
+
+        if distinct_axes && unit_signs {
+            Some(Self { images })
+        } else {
+            None
+        }
+    }
+
+    /// The images of +X, +Y, and +Z.
+    #[inline]
+    pub fn images(&self) -> [SignedAxis3; 3] {
+        self.images
+    }
+
+    /// A counter-clockwise (right-handed) rotation by 90 degrees about `axis`.
+    #[inline]
+    pub fn rotate_90(axis: Axis3) -> Self {
+        let images = match axis {
+            Axis3::X => [
+                SignedAxis3::new(1, Axis3::X),
+                SignedAxis3::new(1, Axis3::Z),
+                SignedAxis3::new(-1, Axis3::Y),
+            ],
+            Axis3::Y => [
+                SignedAxis3::new(-1, Axis3::Z),
+                SignedAxis3::new(1, Axis3::Y),
+                SignedAxis3::new(1, Axis3::X),
+            ],
+            Axis3::Z => [
+                SignedAxis3::new(1, Axis3::Y),
+                SignedAxis3::new(-1, Axis3::X),
+                SignedAxis3::new(1, Axis3::Z),
+            ],
+        };
+
+        Self { images }
+    }
+
+    /// A rotation by 180 degrees about `axis`.
+    #[inline]
+    pub fn rotate_180(axis: Axis3) -> Self {
+        let r = Self::rotate_90(axis);
+
+        r.then(r)
+    }
+
+    /// A counter-clockwise (right-handed) rotation by 270 degrees about `axis`, i.e. a clockwise rotation by 90 degrees.
+    #[inline]
+    pub fn rotate_270(axis: Axis3) -> Self {
+        Self::rotate_90(axis).inverse()
+    }
+
+    /// A reflection across the plane perpendicular to `axis`.
+    #[inline]
+    pub fn mirror(axis: Axis3) -> Self {
+        let mut images = Self::IDENTITY.images;
+        images[axis.index()].sign = -1;
+
+        Self { images }
+    }
+
+    /// The transform that applies `self` and then `other`.
+    #[inline]
+    pub fn then(&self, other: Self) -> Self {
+        let mut images = self.images;
+        for image in images.iter_mut() {
+            *image = other.transform_signed_axis(*image);
+        }
+
+        Self { images }
+    }
+
+    /// The transform that undoes `self`.
+    #[inline]
+    pub fn inverse(&self) -> Self {
+        let mut images = Self::IDENTITY.images;
+        for (i, image) in self.images.iter().enumerate() {
+            images[image.axis.index()] = SignedAxis3::new(image.sign, axis_from_index(i));
+        }
+
+        Self { images }
+    }
+
+    /// Returns `true` iff this transform flips handedness, so triangles transformed by it must have their winding reversed.
+    #[inline]
+    pub fn is_mirror(&self) -> bool {
+        let [x, y, z] = self.images;
+
+        x.get_vector().cross(y.get_vector()).dot(z.get_vector()) < 0
+    }
+
+    #[inline]
+    pub fn transform_signed_axis(&self, axis: SignedAxis3) -> SignedAxis3 {
+        let image = self.images[axis.axis.index()];
+
+        SignedAxis3::new(image.sign * axis.sign, image.axis)
+    }
+
+    /// Transforms the point (or vector) `p` about the origin.
+    #[inline]
+    pub fn transform_point(&self, p: Point3i) -> Point3i {
+        let mut transformed = [0; 3];
+        for (c, image) in p.0.iter().zip(self.images.iter()) {
+            transformed[image.axis.index()] = image.sign * c;
+        }
+
+        PointN(transformed)
+    }
+
+    /// Transforms a corner of the voxel lattice, like a mesh vertex, such that it stays on the same corner of the transformed
+    /// voxels. Voxel `p` covers the corners from `p` to `p + 1`, so unlike `transform_point`, this rotates about the center of
+    /// the voxel at the origin.
+    #[inline]
+    pub fn transform_corner(&self, corner: Point3i) -> Point3i {
+        let mut transformed = self.transform_point(corner);
+        for image in self.images.iter() {
+            if image.sign < 0 {
+                transformed.0[image.axis.index()] += 1;
+            }
+        }
+
+        transformed
+    }
+
+    /// The extent covering all of the transformed points of `extent`.
+    #[inline]
+    pub fn transform_extent(&self, extent: &Extent3i) -> Extent3i {
+        let a = self.transform_point(extent.minimum);
+        let b = self.transform_point(extent.max());
+
+        Extent3i::from_min_and_max(a.meet(b), a.join(b))
+    }
+}
+
+impl Default for OrthoTransform3 {
+    #[inline]
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[inline]
+fn axis_from_index(i: usize) -> Axis3 {
+    match i {
+        0 => Axis3::X,
+        1 => Axis3::Y,
+        2 => Axis3::Z,
+        _ => panic!("Invalid axis index {}", i),
+    }
+}
@@ -1,6 +1,8 @@
 #[macro_use]
 pub mod point_traits;
 
+#[cfg(feature = "cgmath")]
+mod cgmath_conversions;
 #[cfg(feature = "glam")]
 mod glam_conversions;
 #[cfg(feature = "mint")]
@@ -12,10 +14,12 @@ mod sdfu_integration;
 
 mod point2;
 mod point3;
+mod point4;
 
 use bytemuck::{Pod, Zeroable};
 pub use point2::*;
 pub use point3::*;
+pub use point4::*;
 
 use point_traits::*;
 
@@ -23,7 +27,7 @@ use core::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 use num::{Signed, Zero};
 use serde::{Deserialize, Serialize};
 
-/// An N-dimensional point (where N=2 or N=3), which is usually just a primitive array like
+/// An N-dimensional point (where N=2, N=3, or N=4), which is usually just a primitive array like
 /// `[i32; 2]` or `[i32; 3]`. It is most convenient to construct points of any dimension as:
 ///
 /// ```
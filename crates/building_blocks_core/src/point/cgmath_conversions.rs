@@ -0,0 +1,93 @@
+use super::*;
+
+impl<T> From<cgmath::Point2<T>> for Point2<T> {
+    #[inline]
+    fn from(p: cgmath::Point2<T>) -> Self {
+        PointN([p.x, p.y])
+    }
+}
+
+impl<T> From<Point2<T>> for cgmath::Point2<T> {
+    #[inline]
+    fn from(p: Point2<T>) -> Self {
+        let PointN([x, y]) = p;
+
+        cgmath::Point2::new(x, y)
+    }
+}
+
+impl<T> From<cgmath::Vector2<T>> for Point2<T> {
+    #[inline]
+    fn from(p: cgmath::Vector2<T>) -> Self {
+        PointN([p.x, p.y])
+    }
+}
+
+impl<T> From<Point2<T>> for cgmath::Vector2<T> {
+    #[inline]
+    fn from(p: Point2<T>) -> Self {
+        let PointN([x, y]) = p;
+
+        cgmath::Vector2::new(x, y)
+    }
+}
+
+impl<T> From<cgmath::Point3<T>> for Point3<T> {
+    #[inline]
+    fn from(p: cgmath::Point3<T>) -> Self {
+        PointN([p.x, p.y, p.z])
+    }
+}
+
+impl<T> From<Point3<T>> for cgmath::Point3<T> {
+    #[inline]
+    fn from(p: Point3<T>) -> Self {
+        let PointN([x, y, z]) = p;
+
+        cgmath::Point3::new(x, y, z)
+    }
+}
+
+impl<T> From<cgmath::Vector3<T>> for Point3<T> {
+    #[inline]
+    fn from(p: cgmath::Vector3<T>) -> Self {
+        PointN([p.x, p.y, p.z])
+    }
+}
+
+impl<T> From<Point3<T>> for cgmath::Vector3<T> {
+    #[inline]
+    fn from(p: Point3<T>) -> Self {
+        let PointN([x, y, z]) = p;
+
+        cgmath::Vector3::new(x, y, z)
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let p2: Point2i = PointN([1, -2]);
+        assert_eq!(Point2i::from(cgmath::Point2::from(p2)), p2);
+        assert_eq!(Point2i::from(cgmath::Vector2::from(p2)), p2);
+        assert_eq!(cgmath::Point2::from(p2), cgmath::Point2::new(1, -2));
+
+        let p3: Point3f = PointN([1.5, -2.0, 3.25]);
+        assert_eq!(Point3f::from(cgmath::Point3::from(p3)), p3);
+        assert_eq!(Point3f::from(cgmath::Vector3::from(p3)), p3);
+        assert_eq!(
+            cgmath::Vector3::from(p3),
+            cgmath::Vector3::new(1.5, -2.0, 3.25)
+        );
+    }
+}
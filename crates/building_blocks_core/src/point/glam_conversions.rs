@@ -43,3 +43,86 @@ impl From<Point3f> for gl::Vec3A {
         gl::Vec3A::new(p.x(), p.y(), p.z())
     }
 }
+
+impl From<gl::IVec2> for Point2i {
+    #[inline]
+    fn from(p: gl::IVec2) -> Self {
+        PointN([p.x, p.y])
+    }
+}
+
+impl From<Point2i> for gl::IVec2 {
+    #[inline]
+    fn from(p: Point2i) -> Self {
+        gl::IVec2::new(p.x(), p.y())
+    }
+}
+
+impl From<gl::IVec3> for Point3i {
+    #[inline]
+    fn from(p: gl::IVec3) -> Self {
+        PointN([p.x, p.y, p.z])
+    }
+}
+
+impl From<Point3i> for gl::IVec3 {
+    #[inline]
+    fn from(p: Point3i) -> Self {
+        gl::IVec3::new(p.x(), p.y(), p.z())
+    }
+}
+
+impl From<Point2i> for gl::Vec2 {
+    #[inline]
+    fn from(p: Point2i) -> Self {
+        gl::Vec2::new(p.x() as f32, p.y() as f32)
+    }
+}
+
+impl From<Point3i> for gl::Vec3 {
+    #[inline]
+    fn from(p: Point3i) -> Self {
+        gl::Vec3::new(p.x() as f32, p.y() as f32, p.z() as f32)
+    }
+}
+
+impl From<Point3i> for gl::Vec3A {
+    #[inline]
+    fn from(p: Point3i) -> Self {
+        gl::Vec3A::new(p.x() as f32, p.y() as f32, p.z() as f32)
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_round_trips() {
+        let p2: Point2i = PointN([1, -2]);
+        assert_eq!(Point2i::from(gl::IVec2::from(p2)), p2);
+        assert_eq!(gl::Vec2::from(p2), gl::Vec2::new(1.0, -2.0));
+
+        let p3: Point3i = PointN([1, -2, 3]);
+        assert_eq!(Point3i::from(gl::IVec3::from(p3)), p3);
+        assert_eq!(gl::Vec3::from(p3), gl::Vec3::new(1.0, -2.0, 3.0));
+        assert_eq!(gl::Vec3A::from(p3), gl::Vec3A::new(1.0, -2.0, 3.0));
+    }
+
+    #[test]
+    fn float_round_trips() {
+        let p2: Point2f = PointN([1.5, -2.0]);
+        assert_eq!(Point2f::from(gl::Vec2::from(p2)), p2);
+
+        let p3: Point3f = PointN([1.5, -2.0, 3.25]);
+        assert_eq!(Point3f::from(gl::Vec3::from(p3)), p3);
+        assert_eq!(Point3f::from(gl::Vec3A::from(p3)), p3);
+    }
+}
@@ -2,10 +2,13 @@ use crate::Axis2;
 
 use super::{point_traits::*, PointN};
 
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
 use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Range, Rem, Shl, Shr};
 use itertools::{iproduct, Product};
+#[cfg(not(feature = "std"))]
+use num::Float;
 use num::{traits::Pow, Integer, Signed};
-use std::cmp::Ordering;
 
 /// A 2-dimensional point with scalar type `T`.
 pub type Point2<T> = PointN<[T; 2]>;
@@ -13,6 +16,8 @@ pub type Point2<T> = PointN<[T; 2]>;
 pub type Point2i = PointN<[i32; 2]>;
 /// A 2-dimensional point with scalar type `f32`.
 pub type Point2f = PointN<[f32; 2]>;
+/// A 2-dimensional point with scalar type `f64`. Useful for world coordinates that are too large for `f32` precision.
+pub type Point2d = PointN<[f64; 2]>;
 
 impl<T> Point2<T> {
     #[inline]
@@ -91,6 +96,64 @@ impl Point2f {
     }
 }
 
+impl Point2d {
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map_components_unary(|c| c.round())
+    }
+
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map_components_unary(|c| c.floor())
+    }
+
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map_components_unary(|c| c.ceil())
+    }
+
+    #[inline]
+    pub fn fract(self) -> Self {
+        self.map_components_unary(|c| c.fract())
+    }
+
+    /// Ensures that you floor before casting to integers, since this is not the default behavior for negative integers.
+    #[inline]
+    pub fn floor_int(self) -> Point2i {
+        self.floor().into_int()
+    }
+
+    /// Returns the coordinates of the pixel containing `self`.
+    #[inline]
+    pub fn in_pixel(self) -> Point2i {
+        self.floor_int()
+    }
+
+    /// Returns the coordinates of the pixel containing `self`, where each pixel has edge length `pixel_size`.
+    #[inline]
+    pub fn in_pixel_with_size(self, pixel_size: f64) -> Point2i {
+        (self / pixel_size).floor_int()
+    }
+
+    /// Returns `self - origin` with `f32` precision. Subtracting before converting avoids losing precision when both points
+    /// are far from zero, e.g. to get coordinates relative to a camera.
+    #[inline]
+    pub fn relative_to(self, origin: Self) -> Point2f {
+        let d = self - origin;
+
+        PointN([d.x() as f32, d.y() as f32])
+    }
+}
+
+impl IntoIntegerPoint for Point2d {
+    type IntPoint = Point2i;
+
+    #[inline]
+    fn into_int(self) -> Self::IntPoint {
+        PointN([self.x() as i32, self.y() as i32])
+    }
+}
+
 impl IntoIntegerPoint for Point2f {
     type IntPoint = Point2i;
 
@@ -155,6 +218,19 @@ impl MinMaxComponent for Point2f {
     }
 }
 
+impl MinMaxComponent for Point2d {
+    type Scalar = f64;
+
+    #[inline]
+    fn min_component(self) -> Self::Scalar {
+        self.x().min(self.y())
+    }
+    #[inline]
+    fn max_component(self) -> Self::Scalar {
+        self.x().max(self.y())
+    }
+}
+
 impl<T> GetComponent for Point2<T>
 where
     T: Copy,
@@ -205,6 +281,25 @@ impl Point for Point2f {
     }
 }
 
+impl Point for Point2d {
+    type Scalar = f64;
+
+    #[inline]
+    fn fill(value: f64) -> Self {
+        Self([value; 2])
+    }
+
+    #[inline]
+    fn basis() -> Vec<Self> {
+        vec![PointN([1.0, 0.0]), PointN([0.0, 1.0])]
+    }
+
+    #[inline]
+    fn volume(self) -> <Self as Point>::Scalar {
+        self.x() * self.y()
+    }
+}
+
 impl<T> ConstZero for Point2<T>
 where
     T: ConstZero,
@@ -253,6 +348,14 @@ impl Norm for Point2f {
     }
 }
 
+impl Norm for Point2d {
+    /// This is only `f32` precision. Use `DotProduct` or `Distance` if you need the full precision of `f64`.
+    #[inline]
+    fn norm_squared(self) -> f32 {
+        self.dot(self) as f32
+    }
+}
+
 impl<T> DotProduct for Point2<T>
 where
     T: Copy + Add<Output = T> + Mul<Output = T>,
@@ -377,11 +480,27 @@ impl From<Point2i> for Point2f {
     }
 }
 
+impl From<Point2i> for Point2d {
+    #[inline]
+    fn from(p: Point2i) -> Self {
+        PointN([p.x() as f64, p.y() as f64])
+    }
+}
+
+impl From<Point2f> for Point2d {
+    #[inline]
+    fn from(p: Point2f) -> Self {
+        PointN([p.x() as f64, p.y() as f64])
+    }
+}
+
 impl_unary_ops!(Point2f, f32);
+impl_unary_ops!(Point2d, f64);
 impl_unary_ops!(Point2i, i32);
 
 impl_binary_ops!(Point2i, i32);
 impl_binary_ops!(Point2f, f32);
+impl_binary_ops!(Point2d, f64);
 
 impl_unary_integer_ops!(Point2i, i32);
 
@@ -395,4 +514,5 @@ impl_shr_shl!(Point2i, u32);
 impl_binary_integer_ops!(Point2i);
 
 impl_float_div!(Point2f, f32);
+impl_float_div!(Point2d, f64);
 impl_integer_div!(Point2i, i32);
@@ -2,10 +2,13 @@ use crate::Axis3;
 
 use super::{point_traits::*, Point2, PointN};
 
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
 use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Range, Rem, Shl, Shr, Sub};
 use itertools::{iproduct, ConsTuples, Product};
+#[cfg(not(feature = "std"))]
+use num::Float;
 use num::{traits::Pow, Integer, Signed};
-use std::cmp::Ordering;
 
 /// A 3-dimensional point with scalar type `T`.
 pub type Point3<T> = PointN<[T; 3]>;
@@ -13,6 +16,8 @@ pub type Point3<T> = PointN<[T; 3]>;
 pub type Point3i = PointN<[i32; 3]>;
 /// A 3-dimensional point with scalar type `f32`.
 pub type Point3f = PointN<[f32; 3]>;
+/// A 3-dimensional point with scalar type `f64`. Useful for world coordinates that are too large for `f32` precision.
+pub type Point3d = PointN<[f64; 3]>;
 
 impl<T> Point3<T> {
     #[inline]
@@ -180,6 +185,64 @@ impl Point3f {
     }
 }
 
+impl Point3d {
+    #[inline]
+    pub fn round(self) -> Self {
+        self.map_components_unary(|c| c.round())
+    }
+
+    #[inline]
+    pub fn floor(self) -> Self {
+        self.map_components_unary(|c| c.floor())
+    }
+
+    #[inline]
+    pub fn ceil(self) -> Self {
+        self.map_components_unary(|c| c.ceil())
+    }
+
+    #[inline]
+    pub fn fract(self) -> Self {
+        self.map_components_unary(|c| c.fract())
+    }
+
+    /// Ensures that you floor before casting to integers, since this is not the default behavior for negative integers.
+    #[inline]
+    pub fn floor_int(self) -> Point3i {
+        self.floor().into_int()
+    }
+
+    /// Returns the coordinates of the voxel containing `self`.
+    #[inline]
+    pub fn in_voxel(self) -> Point3i {
+        self.floor_int()
+    }
+
+    /// Returns the coordinates of the voxel containing `self`, where each voxel has edge length `voxel_size`.
+    #[inline]
+    pub fn in_voxel_with_size(self, voxel_size: f64) -> Point3i {
+        (self / voxel_size).floor_int()
+    }
+
+    /// Returns `self - origin` with `f32` precision. Subtracting before converting avoids losing precision when both points
+    /// are far from zero, e.g. to get coordinates relative to a camera.
+    #[inline]
+    pub fn relative_to(self, origin: Self) -> Point3f {
+        let d = self - origin;
+
+        PointN([d.x() as f32, d.y() as f32, d.z() as f32])
+    }
+}
+
+impl IntoIntegerPoint for Point3d {
+    type IntPoint = Point3i;
+
+    #[inline]
+    fn into_int(self) -> Self::IntPoint {
+        PointN([self.x() as i32, self.y() as i32, self.z() as i32])
+    }
+}
+
 impl IntoIntegerPoint for Point3f {
     type IntPoint = Point3i;
 
@@ -248,6 +311,19 @@ impl MinMaxComponent for Point3f {
     }
 }
 
+impl MinMaxComponent for Point3d {
+    type Scalar = f64;
+
+    #[inline]
+    fn min_component(self) -> Self::Scalar {
+        self.x().min(self.y()).min(self.z())
+    }
+    #[inline]
+    fn max_component(self) -> Self::Scalar {
+        self.x().max(self.y()).max(self.z())
+    }
+}
+
 impl<T> GetComponent for Point3<T>
 where
     T: Copy,
@@ -302,6 +378,29 @@ impl Point for Point3f {
     }
 }
 
+impl Point for Point3d {
+    type Scalar = f64;
+
+    #[inline]
+    fn fill(value: f64) -> Self {
+        Self([value; 3])
+    }
+
+    #[inline]
+    fn basis() -> Vec<Self> {
+        vec![
+            PointN([1.0, 0.0, 0.0]),
+            PointN([0.0, 1.0, 0.0]),
+            PointN([0.0, 0.0, 1.0]),
+        ]
+    }
+
+    #[inline]
+    fn volume(self) -> <Self as Point>::Scalar {
+        self.x() * self.y() * self.z()
+    }
+}
+
 impl<T> ConstZero for Point3<T>
 where
     T: ConstZero,
@@ -350,6 +449,14 @@ impl Norm for Point3f {
     }
 }
 
+impl Norm for Point3d {
+    /// This is only `f32` precision. Use `DotProduct` or `Distance` if you need the full precision of `f64`.
+    #[inline]
+    fn norm_squared(self) -> f32 {
+        self.dot(self) as f32
+    }
+}
+
 impl<T> DotProduct for Point3<T>
 where
     T: Copy + Add<Output = T> + Mul<Output = T>,
@@ -480,11 +587,27 @@ impl From<Point3i> for Point3f {
     }
 }
 
+impl From<Point3i> for Point3d {
+    #[inline]
+    fn from(p: Point3i) -> Self {
+        PointN([p.x() as f64, p.y() as f64, p.z() as f64])
+    }
+}
+
+impl From<Point3f> for Point3d {
+    #[inline]
+    fn from(p: Point3f) -> Self {
+        PointN([p.x() as f64, p.y() as f64, p.z() as f64])
+    }
+}
+
 impl_unary_ops!(Point3f, f32);
+impl_unary_ops!(Point3d, f64);
 impl_unary_ops!(Point3i, i32);
 
 impl_binary_ops!(Point3i, i32);
 impl_binary_ops!(Point3f, f32);
+impl_binary_ops!(Point3d, f64);
 
 impl_unary_integer_ops!(Point3i, i32);
 
@@ -498,4 +621,5 @@ impl_shr_shl!(Point3i, u32);
 impl_binary_integer_ops!(Point3i);
 
 impl_float_div!(Point3f, f32);
+impl_float_div!(Point3d, f64);
 impl_integer_div!(Point3i, i32);
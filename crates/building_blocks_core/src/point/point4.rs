@@ -0,0 +1,366 @@
+use super::{point_traits::*, Point3, PointN};
+
+use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
+use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Range, Rem, Shl, Shr};
+use itertools::{iproduct, ConsTuples, Product};
+use num::{traits::Pow, Integer, Signed};
+
+/// A 4-dimensional point with scalar type `T`. This is usually 3 spatial dimensions plus one more for time or channels.
+pub type Point4<T> = PointN<[T; 4]>;
+/// A 4-dimensional point with scalar type `i32`.
+pub type Point4i = PointN<[i32; 4]>;
+
+impl<T> Point4<T> {
+    #[inline]
+    pub fn x_mut(&mut self) -> &mut T {
+        &mut self.0[0]
+    }
+
+    #[inline]
+    pub fn y_mut(&mut self) -> &mut T {
+        &mut self.0[1]
+    }
+
+    #[inline]
+    pub fn z_mut(&mut self) -> &mut T {
+        &mut self.0[2]
+    }
+
+    #[inline]
+    pub fn w_mut(&mut self) -> &mut T {
+        &mut self.0[3]
+    }
+}
+
+impl<T> Point4<T>
+where
+    T: Copy,
+{
+    #[inline]
+    pub fn x(self) -> T {
+        self.0[0]
+    }
+
+    #[inline]
+    pub fn y(self) -> T {
+        self.0[1]
+    }
+
+    #[inline]
+    pub fn z(self) -> T {
+        self.0[2]
+    }
+
+    #[inline]
+    pub fn w(self) -> T {
+        self.0[3]
+    }
+
+    /// Drops the `w` component.
+    #[inline]
+    pub fn xyz(self) -> Point3<T> {
+        PointN([self.x(), self.y(), self.z()])
+    }
+
+    /// Appends `w` to the 3D point `p`.
+    #[inline]
+    pub fn from_xyz_w(p: Point3<T>, w: T) -> Self {
+        PointN([p.x(), p.y(), p.z(), w])
+    }
+}
+
+impl Point4i {
+    pub const VON_NEUMANN_OFFSETS: [Self; 8] = [
+        PointN([-1, 0, 0, 0]),
+        PointN([1, 0, 0, 0]),
+        PointN([0, -1, 0, 0]),
+        PointN([0, 1, 0, 0]),
+        PointN([0, 0, -1, 0]),
+        PointN([0, 0, 1, 0]),
+        PointN([0, 0, 0, -1]),
+        PointN([0, 0, 0, 1]),
+    ];
+}
+
+impl<T> Bounded for Point4<T>
+where
+    T: Bounded,
+{
+    const MAX: Self = PointN([T::MAX; 4]);
+    const MIN: Self = PointN([T::MIN; 4]);
+}
+
+impl<T> MapComponents for Point4<T>
+where
+    T: Copy,
+{
+    type Scalar = T;
+
+    #[inline]
+    fn map_components_unary(self, f: impl Fn(Self::Scalar) -> Self::Scalar) -> Self {
+        PointN([f(self.x()), f(self.y()), f(self.z()), f(self.w())])
+    }
+
+    #[inline]
+    fn map_components_binary(
+        self,
+        other: Self,
+        f: impl Fn(Self::Scalar, Self::Scalar) -> Self::Scalar,
+    ) -> Self {
+        PointN([
+            f(self.x(), other.x()),
+            f(self.y(), other.y()),
+            f(self.z(), other.z()),
+            f(self.w(), other.w()),
+        ])
+    }
+}
+
+impl MinMaxComponent for Point4i {
+    type Scalar = i32;
+
+    #[inline]
+    fn min_component(self) -> Self::Scalar {
+        self.x().min(self.y()).min(self.z()).min(self.w())
+    }
+    #[inline]
+    fn max_component(self) -> Self::Scalar {
+        self.x().max(self.y()).max(self.z()).max(self.w())
+    }
+}
+
+impl<T> GetComponent for Point4<T>
+where
+    T: Copy,
+{
+    type Scalar = T;
+
+    #[inline]
+    fn at(self, component_index: usize) -> T {
+        self.0[component_index]
+    }
+}
+
+impl Point for Point4i {
+    type Scalar = i32;
+
+    #[inline]
+    fn fill(value: i32) -> Self {
+        Self([value; 4])
+    }
+
+    #[inline]
+    fn basis() -> Vec<Self> {
+        vec![
+            PointN([1, 0, 0, 0]),
+            PointN([0, 1, 0, 0]),
+            PointN([0, 0, 1, 0]),
+            PointN([0, 0, 0, 1]),
+        ]
+    }
+
+    #[inline]
+    fn volume(self) -> <Self as Point>::Scalar {
+        self.x() * self.y() * self.z() * self.w()
+    }
+}
+
+impl<T> ConstZero for Point4<T>
+where
+    T: ConstZero,
+{
+    const ZERO: Self = PointN([T::ZERO; 4]);
+}
+
+impl<T> Ones for Point4<T>
+where
+    T: ConstOne,
+{
+    const ONES: Self = PointN([T::ONE; 4]);
+}
+
+impl<T> Distance for Point4<T>
+where
+    T: Copy + Signed + Add<Output = T> + Pow<u16, Output = T>,
+    Point4<T>: Point<Scalar = T>,
+{
+    #[inline]
+    fn l1_distance(self, other: Self) -> T {
+        let diff = self - other;
+
+        diff.x().abs() + diff.y().abs() + diff.z().abs() + diff.w().abs()
+    }
+
+    #[inline]
+    fn l2_distance_squared(self, other: Self) -> T {
+        let diff = self - other;
+
+        diff.x().pow(2) + diff.y().pow(2) + diff.z().pow(2) + diff.w().pow(2)
+    }
+}
+
+impl Norm for Point4i {
+    #[inline]
+    fn norm_squared(self) -> f32 {
+        self.dot(self) as f32
+    }
+}
+
+impl<T> DotProduct for Point4<T>
+where
+    T: Copy + Add<Output = T> + Mul<Output = T>,
+{
+    type Scalar = T;
+
+    #[inline]
+    fn dot(self, other: Self) -> Self::Scalar {
+        self.x() * other.x() + self.y() * other.y() + self.z() * other.z() + self.w() * other.w()
+    }
+}
+
+impl IntegerPoint<[i32; 4]> for Point4i {
+    #[inline]
+    fn dimensions_are_powers_of_2(self) -> bool {
+        self.x().is_positive()
+            && self.y().is_positive()
+            && self.z().is_positive()
+            && self.w().is_positive()
+            && (self.x() as u32).is_power_of_two()
+            && (self.y() as u32).is_power_of_two()
+            && (self.z() as u32).is_power_of_two()
+            && (self.w() as u32).is_power_of_two()
+    }
+
+    #[inline]
+    fn is_cube(self) -> bool {
+        self.x() == self.y() && self.x() == self.z() && self.x() == self.w()
+    }
+}
+
+impl Neighborhoods for Point4i {
+    #[inline]
+    fn corner_offsets() -> Vec<Self> {
+        Self::iter_extent(PointN([0; 4]), PointN([2; 4])).collect()
+    }
+
+    #[inline]
+    fn von_neumann_offsets() -> Vec<Self> {
+        Self::VON_NEUMANN_OFFSETS.to_vec()
+    }
+
+    #[inline]
+    fn moore_offsets() -> Vec<Self> {
+        Self::iter_extent(PointN([-1; 4]), PointN([2; 4]))
+            .filter(|p| *p != PointN([0; 4]))
+            .collect()
+    }
+}
+
+/// An iterator over all points in an `Extent4<T>`.
+pub struct Extent4PointIter<T>
+where
+    T: Clone,
+    Range<T>: Iterator<Item = T>,
+{
+    product_iter: ConsTuples<RangeProduct4<T>, (((T, T), T), T)>,
+}
+
+type RangeProduct2<T> = Product<Range<T>, Range<T>>;
+type RangeProduct3<T> = Product<RangeProduct2<T>, Range<T>>;
+type RangeProduct4<T> = Product<RangeProduct3<T>, Range<T>>;
+
+impl<T> Iterator for Extent4PointIter<T>
+where
+    T: Clone,
+    Range<T>: Iterator<Item = T>,
+{
+    type Item = Point4<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.product_iter
+            .next()
+            .map(|(w, z, y, x)| PointN([x, y, z, w]))
+    }
+}
+
+impl IterExtent<[i32; 4]> for Point4i {
+    type PointIter = Extent4PointIter<i32>;
+
+    #[inline(always)]
+    fn iter_extent(min: Self, lub: Self) -> Self::PointIter {
+        Extent4PointIter {
+            // iproduct is opposite of row-major order.
+            product_iter: iproduct!(
+                min.w()..lub.w(),
+                min.z()..lub.z(),
+                min.y()..lub.y(),
+                min.x()..lub.x()
+            ),
+        }
+    }
+}
+
+// This particular partial order allows us to say that an `Extent4i` e contains a `Point4i` p iff p
+// is GEQ the minimum of e and p is LEQ the maximum of e.
+impl<T> PartialOrd for Point4<T>
+where
+    T: Copy + PartialOrd,
+{
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self < other {
+            Some(Ordering::Less)
+        } else if self > other {
+            Some(Ordering::Greater)
+        } else if self.0 == other.0 {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn lt(&self, other: &Self) -> bool {
+        self.x() < other.x() && self.y() < other.y() && self.z() < other.z() && self.w() < other.w()
+    }
+
+    #[inline]
+    fn gt(&self, other: &Self) -> bool {
+        self.x() > other.x() && self.y() > other.y() && self.z() > other.z() && self.w() > other.w()
+    }
+
+    #[inline]
+    fn le(&self, other: &Self) -> bool {
+        self.x() <= other.x()
+            && self.y() <= other.y()
+            && self.z() <= other.z()
+            && self.w() <= other.w()
+    }
+
+    #[inline]
+    fn ge(&self, other: &Self) -> bool {
+        self.x() >= other.x()
+            && self.y() >= other.y()
+            && self.z() >= other.z()
+            && self.w() >= other.w()
+    }
+}
+
+impl_unary_ops!(Point4i, i32);
+
+impl_binary_ops!(Point4i, i32);
+
+impl_unary_integer_ops!(Point4i, i32);
+
+impl_shr_shl!(Point4i, i8);
+impl_shr_shl!(Point4i, i16);
+impl_shr_shl!(Point4i, i32);
+impl_shr_shl!(Point4i, u8);
+impl_shr_shl!(Point4i, u16);
+impl_shr_shl!(Point4i, u32);
+
+impl_binary_integer_ops!(Point4i);
+
+impl_integer_div!(Point4i, i32);
@@ -1,9 +1,12 @@
 use crate::PointN;
 
+use alloc::vec::Vec;
 use bytemuck::Pod;
 use core::ops::{
     Add, AddAssign, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub, SubAssign,
 };
+#[cfg(not(feature = "std"))]
+use num::Float;
 use num::Zero;
 
 /// A trait that bundles op traits that all `PointN<N>` (and its components) should have.
@@ -481,17 +484,29 @@ impl ConstOne for f32 {
     const ONE: f32 = 1.0;
 }
 
+impl ConstZero for f64 {
+    const ZERO: f64 = 0.0;
+}
+impl ConstOne for f64 {
+    const ONE: f64 = 1.0;
+}
+
 pub trait Bounded: Copy {
     const MIN: Self;
     const MAX: Self;
 }
 
 impl Bounded for i32 {
-    const MIN: Self = std::i32::MIN;
-    const MAX: Self = std::i32::MAX;
+    const MIN: Self = core::i32::MIN;
+    const MAX: Self = core::i32::MAX;
 }
 
 impl Bounded for f32 {
-    const MIN: Self = std::f32::MIN;
-    const MAX: Self = std::f32::MAX;
+    const MIN: Self = core::f32::MIN;
+    const MAX: Self = core::f32::MAX;
+}
+
+impl Bounded for f64 {
+    const MIN: Self = core::f64::MIN;
+    const MAX: Self = core::f64::MAX;
 }
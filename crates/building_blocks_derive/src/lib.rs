@@ -0,0 +1,438 @@
+//! Derive macros for [`building-blocks`](https://docs.rs/building-blocks).
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Ident, Lit, Meta, NestedMeta,
+    Path, Type,
+};
+
+/// `Channels` and the access traits are only implemented for tuples of up to this many channels, and the derived impls
+/// delegate to those tuples.
+const MAX_CHANNELS: usize = 8;
+
+/// Makes a struct whose fields are channels act like a tuple of those channels, so it can be used as the `Chan` of an `Array`
+/// or a `ChunkMap`. Unlike a tuple, each channel has a name.
+///
+/// The struct must have between 1 and 8 named fields and no generic parameters. The `Channels::Data` of the struct is the tuple
+/// of the fields' `Data` types, in field order, so `Array::get` and `Array::fill` still work with tuples.
+///
+/// Along with the channel traits, this generates:
+/// - for every field `foo`, the associated functions `foo(&array)` and `foo_mut(&mut array)`, which borrow that channel of an
+///   `Array` as a single-channel array
+/// - a `{Struct}Compression<By>` that compresses every channel with the same `BytesCompression`, like
+///   `FastChannelsCompression`
+/// - a hidden `{Struct}Uninit` type, for `UninitChannels`
+///
+/// The generated code refers to the `building_blocks_storage` crate. If it's only available through the `building_blocks`
+/// crate, use `#[channels(crate = "building_blocks::storage")]`.
+///
+/// See the `building_blocks_storage::array::channels::multichannel` module for an example.
+#[proc_macro_derive(Channels, attributes(channels))]
+pub fn derive_channels(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_channels(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand_channels(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let krate = crate_path(input)?;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Channels)] doesn't support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &data.fields,
+                    "#[derive(Channels)] requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "#[derive(Channels)] only supports structs",
+            ))
+        }
+    };
+    if fields.is_empty() || fields.len() > MAX_CHANNELS {
+        return Err(Error::new_spanned(
+            fields,
+            format!(
+                "#[derive(Channels)] requires between 1 and {} fields",
+                MAX_CHANNELS
+            ),
+        ));
+    }
+
+    let names: Vec<&Ident> = fields
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap())
+        .collect();
+    let types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+    let vars: Vec<Ident> = (0..fields.len()).map(|i| format_ident!("c{}", i)).collect();
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let uninit_name = format_ident!("{}Uninit", name);
+    let compression_name = format_ident!("{}Compression", name);
+
+    let accessors = names.iter().zip(types.iter()).map(|(field, ty)| {
+        let field_mut = format_ident!("{}_mut", field);
+        let doc = format!(
+            "Borrows the `{}` channel of `array` as a single-channel array.",
+            field
+        );
+        let doc_mut = format!(
+            "Mutably borrows the `{}` channel of `array` as a single-channel array.",
+            field
+        );
+
+        quote! {
+            #[doc = #doc]
+            #[inline]
+            #vis fn #field<'a, N>(
+                array: &'a #krate::Array<N, Self>,
+            ) -> #krate::Array<N, <#ty as #krate::BorrowChannels<'a>>::Borrowed>
+            where
+                N: Copy,
+                #ty: #krate::BorrowChannels<'a>,
+            {
+                #krate::Array::new(
+                    *array.extent(),
+                    #krate::BorrowChannels::borrow(&array.channels().#field),
+                )
+            }
+
+            #[doc = #doc_mut]
+            #[inline]
+            #vis fn #field_mut<'a, N>(
+                array: &'a mut #krate::Array<N, Self>,
+            ) -> #krate::Array<N, <#ty as #krate::BorrowChannelsMut<'a>>::Borrowed>
+            where
+                N: Copy,
+                #ty: #krate::BorrowChannelsMut<'a>,
+            {
+                let extent = *array.extent();
+
+                #krate::Array::new(
+                    extent,
+                    #krate::BorrowChannelsMut::borrow_mut(&mut array.channels_mut().#field),
+                )
+            }
+        }
+    });
+
+    let channel_impls = quote! {
+        #[allow(dead_code)]
+        impl #name {
+            #(#accessors)*
+        }
+
+        impl #krate::Channels for #name {
+            type Data = (#(<#types as #krate::Channels>::Data,)*);
+            type Ptr = (#(<#types as #krate::Channels>::Ptr,)*);
+            type UninitSelf = #uninit_name;
+
+            fn estimate_bytes(&self, num_values: usize) -> usize {
+                0 #(+ #krate::Channels::estimate_bytes(&self.#names, num_values))*
+            }
+        }
+
+        impl<'a> #krate::Slices<'a> for #name
+        where
+            #(#types: #krate::Slices<'a>,)*
+        {
+            type Target = (#(<#types as #krate::Slices<'a>>::Target,)*);
+
+            fn slices(&'a self) -> Self::Target {
+                (#(#krate::Slices::slices(&self.#names),)*)
+            }
+        }
+
+        impl<'a> #krate::SlicesMut<'a> for #name
+        where
+            #(#types: #krate::SlicesMut<'a>,)*
+        {
+            type Target = (#(<#types as #krate::SlicesMut<'a>>::Target,)*);
+
+            fn slices_mut(&'a mut self) -> Self::Target {
+                (#(#krate::SlicesMut::slices_mut(&mut self.#names),)*)
+            }
+        }
+
+        impl<'a> #krate::CopySlices<'a> for #name
+        where
+            #(#types: #krate::CopySlices<'a>,)*
+        {
+            type Src = (#(<#types as #krate::CopySlices<'a>>::Src,)*);
+
+            fn copy_slices(&mut self, src: Self::Src) {
+                let (#(#vars,)*) = src;
+
+                #(#krate::CopySlices::copy_slices(&mut self.#names, #vars);)*
+            }
+        }
+
+        impl<'a> #krate::BorrowChannels<'a> for #name
+        where
+            #(#types: #krate::BorrowChannels<'a>,)*
+        {
+            type Borrowed = (#(<#types as #krate::BorrowChannels<'a>>::Borrowed,)*);
+
+            fn borrow(&'a self) -> Self::Borrowed {
+                (#(#krate::BorrowChannels::borrow(&self.#names),)*)
+            }
+        }
+
+        impl<'a> #krate::BorrowChannelsMut<'a> for #name
+        where
+            #(#types: #krate::BorrowChannelsMut<'a>,)*
+        {
+            type Borrowed = (#(<#types as #krate::BorrowChannelsMut<'a>>::Borrowed,)*);
+
+            fn borrow_mut(&'a mut self) -> Self::Borrowed {
+                (#(#krate::BorrowChannelsMut::borrow_mut(&mut self.#names),)*)
+            }
+        }
+
+        impl #krate::FillChannels for #name {
+            fn fill(value: Self::Data, length: usize) -> Self {
+                let (#(#vars,)*) = value;
+
+                Self {
+                    #(#names: <#types as #krate::FillChannels>::fill(#vars, length),)*
+                }
+            }
+        }
+
+        impl #krate::ResetChannels for #name {
+            fn reset_values(&mut self, value: Self::Data) {
+                let (#(#vars,)*) = value;
+
+                #(#krate::ResetChannels::reset_values(&mut self.#names, #vars);)*
+            }
+        }
+
+        impl #krate::NumValues for #name
+        where
+            #(#types: #krate::NumValues,)*
+        {
+            fn num_values(&self) -> usize {
+                usize::MAX #(.min(#krate::NumValues::num_values(&self.#names)))*
+            }
+        }
+    };
+
+    let access_impls = quote! {
+        impl<Coord> #krate::Get<Coord> for #name
+        where
+            Coord: Copy,
+            #(#types: #krate::Get<Coord>,)*
+        {
+            type Item = (#(<#types as #krate::Get<Coord>>::Item,)*);
+
+            #[inline]
+            fn get(&self, offset: Coord) -> Self::Item {
+                (#(#krate::Get::get(&self.#names, offset),)*)
+            }
+        }
+
+        impl<'a, Coord> #krate::GetRef<'a, Coord> for #name
+        where
+            Coord: Copy,
+            #(#types: #krate::GetRef<'a, Coord>,)*
+        {
+            type Item = (#(<#types as #krate::GetRef<'a, Coord>>::Item,)*);
+
+            #[inline]
+            fn get_ref(&'a self, offset: Coord) -> Self::Item {
+                (#(#krate::GetRef::get_ref(&self.#names, offset),)*)
+            }
+        }
+
+        impl<'a, Coord> #krate::GetMut<'a, Coord> for #name
+        where
+            Coord: Copy,
+            #(#types: #krate::GetMut<'a, Coord>,)*
+        {
+            type Item = (#(<#types as #krate::GetMut<'a, Coord>>::Item,)*);
+
+            #[inline]
+            fn get_mut(&'a mut self, offset: Coord) -> Self::Item {
+                (#(#krate::GetMut::get_mut(&mut self.#names, offset),)*)
+            }
+        }
+
+        impl<Coord> #krate::GetMutPtr<Coord> for #name
+        where
+            Coord: Copy,
+            #(#types: #krate::GetMutPtr<Coord>,)*
+        {
+            type Item = (#(<#types as #krate::GetMutPtr<Coord>>::Item,)*);
+
+            #[inline]
+            unsafe fn get_mut_ptr(&mut self, offset: Coord) -> Self::Item {
+                (#(#krate::GetMutPtr::get_mut_ptr(&mut self.#names, offset),)*)
+            }
+        }
+    };
+
+    let uninit_doc = format!("The uninitialized version of `{}`.", name);
+    let uninit_impls = quote! {
+        #[doc = #uninit_doc]
+        #[doc(hidden)]
+        #vis struct #uninit_name((#(<#types as #krate::Channels>::UninitSelf,)*));
+
+        impl #krate::Channels for #uninit_name {
+            type Data = (#(<<#types as #krate::Channels>::UninitSelf as #krate::Channels>::Data,)*);
+            type Ptr = (#(<<#types as #krate::Channels>::UninitSelf as #krate::Channels>::Ptr,)*);
+            type UninitSelf = Self;
+        }
+
+        impl #krate::UninitChannels for #uninit_name {
+            type InitSelf = #name;
+
+            unsafe fn maybe_uninit(size: usize) -> Self {
+                Self((#(
+                    <<#types as #krate::Channels>::UninitSelf as #krate::UninitChannels>::maybe_uninit(size),
+                )*))
+            }
+
+            unsafe fn assume_init(self) -> Self::InitSelf {
+                let (#(#vars,)*) = self.0;
+
+                #name {
+                    #(#names: #krate::UninitChannels::assume_init(#vars),)*
+                }
+            }
+        }
+
+        impl<Coord> #krate::GetMutPtr<Coord> for #uninit_name
+        where
+            Coord: Copy,
+            #(<#types as #krate::Channels>::UninitSelf: #krate::GetMutPtr<Coord>,)*
+        {
+            type Item = (#(<<#types as #krate::Channels>::UninitSelf as #krate::GetMutPtr<Coord>>::Item,)*);
+
+            #[inline]
+            unsafe fn get_mut_ptr(&mut self, offset: Coord) -> Self::Item {
+                let (#(#vars,)*) = &mut self.0;
+
+                (#(#krate::GetMutPtr::get_mut_ptr(#vars, offset),)*)
+            }
+        }
+    };
+
+    let compression_doc = format!(
+        "Compresses `{}` with the same `BytesCompression` for every channel, like `FastChannelsCompression`.",
+        name
+    );
+    let compression_impls = quote! {
+        #[doc = #compression_doc]
+        #[allow(dead_code)]
+        #[derive(Clone, Copy, Debug)]
+        #vis struct #compression_name<By> {
+            /// The compression used for the bytes of every channel.
+            #vis bytes_compression: By,
+        }
+
+        #[allow(dead_code)]
+        impl<By> #compression_name<By> {
+            /// Compresses every channel with `bytes_compression`.
+            #vis fn new(bytes_compression: By) -> Self {
+                Self { bytes_compression }
+            }
+        }
+
+        impl<By> #krate::FromBytesCompression<By> for #compression_name<By> {
+            fn from_bytes_compression(bytes_compression: By) -> Self {
+                Self::new(bytes_compression)
+            }
+        }
+
+        impl<By> #krate::Compression for #compression_name<By>
+        where
+            By: Clone,
+            #(#krate::FastChannelsCompression<By, #types>: #krate::Compression<Data = #types>,)*
+        {
+            type Data = #name;
+
+            fn compress_to_writer(
+                &self,
+                data: &Self::Data,
+                mut compressed_bytes: impl ::std::io::Write,
+            ) -> ::std::io::Result<()> {
+                // Compress each channel in field order.
+                #(
+                    #krate::Compression::compress_to_writer(
+                        &#krate::FastChannelsCompression::<By, #types>::new(self.bytes_compression.clone()),
+                        &data.#names,
+                        &mut compressed_bytes,
+                    )?;
+                )*
+
+                Ok(())
+            }
+
+            fn decompress_from_reader(
+                mut compressed_bytes: impl ::std::io::Read,
+            ) -> ::std::io::Result<Self::Data> {
+                // Decompress each channel in field order.
+                #(
+                    let #vars = <#krate::FastChannelsCompression<By, #types> as #krate::Compression>::decompress_from_reader(
+                        &mut compressed_bytes,
+                    )?;
+                )*
+
+                Ok(#name { #(#names: #vars,)* })
+            }
+        }
+    };
+
+    Ok(quote! {
+        #channel_impls
+        #access_impls
+        #uninit_impls
+        #compression_impls
+    })
+}
+
+/// The path of the `building_blocks_storage` crate, which can be overridden with `#[channels(crate = "path")]`.
+fn crate_path(input: &DeriveInput) -> Result<Path, Error> {
+    let mut path = parse_quote!(::building_blocks_storage);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("channels"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[channels(...)]")),
+        };
+        for nested in list.nested.iter() {
+            path = match nested {
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("crate") => {
+                    match &pair.lit {
+                        Lit::Str(lit) => lit.parse()?,
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    }
+                }
+                _ => return Err(Error::new_spanned(nested, "expected `crate = \"path\"`")),
+            };
+        }
+    }
+
+    Ok(path)
+}
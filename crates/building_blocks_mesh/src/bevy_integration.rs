@@ -0,0 +1,136 @@
+use super::{PosNormMesh, PosNormTexMesh};
+
+use bevy_render::{
+    mesh::{Indices, Mesh},
+    pipeline::PrimitiveTopology,
+};
+
+impl From<PosNormMesh> for Mesh {
+    /// The normals are normalized, since Bevy doesn't do it before interpolation. Bevy's PBR shader requires texture
+    /// coordinates, so they are all set to zero.
+    fn from(mesh: PosNormMesh) -> Self {
+        let PosNormMesh {
+            positions,
+            mut normals,
+            indices,
+        } = mesh;
+        assert_eq!(positions.len(), normals.len());
+        let num_vertices = positions.len();
+
+        normalize_all(&mut normals);
+
+        let mut render_mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0f32; 2]; num_vertices]);
+        render_mesh.set_indices(Some(Indices::U32(indices)));
+
+        render_mesh
+    }
+}
+
+impl From<PosNormTexMesh> for Mesh {
+    /// The normals are normalized, since Bevy doesn't do it before interpolation.
+    fn from(mesh: PosNormTexMesh) -> Self {
+        let PosNormTexMesh {
+            positions,
+            mut normals,
+            tex_coords,
+            indices,
+        } = mesh;
+        assert_eq!(positions.len(), normals.len());
+        assert_eq!(positions.len(), tex_coords.len());
+
+        normalize_all(&mut normals);
+
+        let mut render_mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        render_mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, tex_coords);
+        render_mesh.set_indices(Some(Indices::U32(indices)));
+
+        render_mesh
+    }
+}
+
+fn normalize_all(normals: &mut [[f32; 3]]) {
+    for n in normals.iter_mut() {
+        let norm = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if norm > 0.0 {
+            n[0] /= norm;
+            n[1] /= norm;
+            n[2] /= norm;
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bevy_render::mesh::VertexAttributeValues;
+
+    fn triangle_pos_norm_mesh() -> PosNormMesh {
+        PosNormMesh {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            normals: vec![[0.0, 0.0, 2.0]; 3],
+            indices: vec![0, 1, 2],
+        }
+    }
+
+    fn assert_attribute_len(mesh: &Mesh, name: &'static str, len: usize) {
+        let values = mesh
+            .attribute(name)
+            .unwrap_or_else(|| panic!("missing {}", name));
+        assert_eq!(values.len(), len, "{}", name);
+    }
+
+    #[test]
+    fn pos_norm_mesh_attribute_counts() {
+        let mesh = Mesh::from(triangle_pos_norm_mesh());
+
+        assert_eq!(mesh.count_vertices(), 3);
+        assert_attribute_len(&mesh, Mesh::ATTRIBUTE_POSITION, 3);
+        assert_attribute_len(&mesh, Mesh::ATTRIBUTE_NORMAL, 3);
+        assert_attribute_len(&mesh, Mesh::ATTRIBUTE_UV_0, 3);
+        assert!(matches!(mesh.indices(), Some(Indices::U32(i)) if i == &[0, 1, 2]));
+
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float3(normals)) => {
+                assert!(normals.iter().all(|n| *n == [0.0, 0.0, 1.0]))
+            }
+            _ => panic!("normals should be Float3"),
+        }
+    }
+
+    #[test]
+    fn pos_norm_tex_mesh_attribute_counts() {
+        let PosNormMesh {
+            positions,
+            normals,
+            indices,
+        } = triangle_pos_norm_mesh();
+        let tex_coords = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let mesh = Mesh::from(PosNormTexMesh {
+            positions,
+            normals,
+            tex_coords: tex_coords.clone(),
+            indices,
+        });
+
+        assert_eq!(mesh.count_vertices(), 3);
+        assert_attribute_len(&mesh, Mesh::ATTRIBUTE_POSITION, 3);
+        assert_attribute_len(&mesh, Mesh::ATTRIBUTE_NORMAL, 3);
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float2(uvs)) => assert_eq!(uvs, &tex_coords),
+            _ => panic!("UVs should be Float2"),
+        }
+    }
+}
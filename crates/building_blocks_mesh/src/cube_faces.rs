@@ -0,0 +1,289 @@
+//! A "blocky" mesher that makes one quad for every visible voxel face, without any merging.
+//!
+//! Greedy meshing produces far fewer quads, but a merged quad can only have one value of any per-face data. When every face
+//! needs its own ambient occlusion, light level, or texture variation, the greedy merge hardly ever succeeds, and a simple
+//! face-culling mesher is faster and easier to work with.
+//!
+//! Instead of requiring a padded copy of the chunk, `culled_cube_faces` takes the voxels of just the chunk extent, plus a
+//! function for looking up any voxel outside of that extent. Faces on the chunk borders are culled against the neighboring
+//! voxels it returns, e.g. from a `ChunkMapLodView`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//! use building_blocks_mesh::*;
+//!
+//! #[derive(Clone)]
+//! struct CubeVoxel(bool);
+//!
+//! impl IsEmpty for CubeVoxel {
+//!     fn is_empty(&self) -> bool { !self.0 }
+//! }
+//!
+//! impl IsOpaque for CubeVoxel {
+//!     fn is_opaque(&self) -> bool { true }
+//! }
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+//! let chunk = Array3x1::fill(extent, CubeVoxel(true));
+//!
+//! // The chunk at +X is also solid, and everything else is empty.
+//! let neighbor_extent = extent + PointN([4, 0, 0]);
+//! let neighbors = |p: Point3i| CubeVoxel(neighbor_extent.contains(p));
+//!
+//! let mut buffer = CubeFacesBuffer::new(RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+//! culled_cube_faces(&chunk, &extent, neighbors, &mut buffer);
+//! assert_eq!(buffer.num_quads(), 5 * 16);
+//! assert!(buffer.quad_groups[3].quads.is_empty());
+//!
+//! culled_cube_faces_with_ambient_occlusion(&chunk, &extent, neighbors, &mut buffer);
+//! let top = &buffer.quad_groups[4];
+//! assert_eq!(top.ambient_occlusion.len(), top.quads.len());
+//! ```
+
+use super::{
+    greedy_quads::{corner_ambient_occlusion, face_is_visible},
+    IsOpaque, QuadGroup, UnorientedQuad,
+};
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::prelude::*;
+
+/// Contains the output from the `culled_cube_faces` algorithm. Every quad is a single voxel face.
+///
+/// This buffer can be reused between multiple calls of `culled_cube_faces` in order to avoid reallocations.
+pub struct CubeFacesBuffer {
+    /// One group of quads per cube face.
+    pub quad_groups: [QuadGroup; 6],
+}
+
+impl CubeFacesBuffer {
+    pub fn new(quad_groups: [QuadGroup; 6]) -> Self {
+        Self { quad_groups }
+    }
+
+    pub fn reset(&mut self) {
+        for group in self.quad_groups.iter_mut() {
+            group.quads.clear();
+            group.ambient_occlusion.clear();
+        }
+    }
+
+    /// Returns the total count of quads across all groups.
+    pub fn num_quads(&self) -> usize {
+        self.quad_groups.iter().map(|group| group.quads.len()).sum()
+    }
+}
+
+/// Makes one quad for each visible face of the voxels in `extent`. `voxels` only needs to contain the points in `extent`, and
+/// `neighbors` is called for the adjacent points outside of `extent`.
+pub fn culled_cube_faces<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    neighbors: impl Fn(Point3i) -> T,
+    output: &mut CubeFacesBuffer,
+) where
+    A: Get<Point3i, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    trace_span!("culled_cube_faces", num_points = extent.num_points());
+
+    cube_faces(voxels, extent, neighbors, false, output)
+}
+
+/// Like `culled_cube_faces`, but also computes the ambient occlusion at each corner of each face, like
+/// `greedy_quads_with_ambient_occlusion`. `neighbors` is also called for the points outside of `extent` that share an edge or
+/// corner with a face.
+pub fn culled_cube_faces_with_ambient_occlusion<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    neighbors: impl Fn(Point3i) -> T,
+    output: &mut CubeFacesBuffer,
+) where
+    A: Get<Point3i, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    trace_span!(
+        "culled_cube_faces_with_ambient_occlusion",
+        num_points = extent.num_points()
+    );
+
+    cube_faces(voxels, extent, neighbors, true, output)
+}
+
+fn cube_faces<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    neighbors: impl Fn(Point3i) -> T,
+    with_ambient_occlusion: bool,
+    output: &mut CubeFacesBuffer,
+) where
+    A: Get<Point3i, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    output.reset();
+
+    let sample = |p: Point3i| {
+        if extent.contains(p) {
+            voxels.get(p)
+        } else {
+            neighbors(p)
+        }
+    };
+    let occludes = |p: Point3i| {
+        let voxel = sample(p);
+
+        !voxel.is_empty() && voxel.is_opaque()
+    };
+
+    for p in extent.iter_points() {
+        let voxel = voxels.get(p);
+        if voxel.is_empty() {
+            continue;
+        }
+
+        for group in output.quad_groups.iter_mut() {
+            let face = group.face;
+            let adjacent = p + face.signed_normal();
+            if !face_is_visible(&voxel, &sample(adjacent)) {
+                continue;
+            }
+            group.quads.push(UnorientedQuad::from_voxel(p));
+
+            if with_ambient_occlusion {
+                let ambient_occlusion_at_corner = |u_offset: Point3i, v_offset: Point3i| {
+                    corner_ambient_occlusion(
+                        occludes(adjacent + u_offset),
+                        occludes(adjacent + v_offset),
+                        || occludes(adjacent + u_offset + v_offset),
+                    )
+                };
+                group.ambient_occlusion.push([
+                    ambient_occlusion_at_corner(-face.u, -face.v),
+                    ambient_occlusion_at_corner(face.u, -face.v),
+                    ambient_occlusion_at_corner(-face.u, face.v),
+                    ambient_occlusion_at_corner(face.u, face.v),
+                ]);
+            }
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::RIGHT_HANDED_Y_UP_CONFIG;
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum Block {
+        Empty,
+        Solid,
+        Glass,
+    }
+
+    impl IsEmpty for Block {
+        fn is_empty(&self) -> bool {
+            *self == Block::Empty
+        }
+    }
+
+    impl IsOpaque for Block {
+        fn is_opaque(&self) -> bool {
+            *self == Block::Solid
+        }
+    }
+
+    fn group_facing(buffer: &CubeFacesBuffer, normal: Point3i) -> &QuadGroup {
+        buffer
+            .quad_groups
+            .iter()
+            .find(|group| group.face.signed_normal() == normal)
+            .unwrap()
+    }
+
+    #[test]
+    fn faces_are_culled_against_neighbors() {
+        // A solid voxel at x == 0 and a glass voxel at x == 1. Outside of the chunk, there is a solid voxel at -X and a glass
+        // voxel at +X.
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 1, 1]));
+        let chunk = Array3x1::fill_with(extent, |p: Point3i| {
+            if p.x() == 0 {
+                Block::Solid
+            } else {
+                Block::Glass
+            }
+        });
+        let neighbors = |p: Point3i| {
+            assert!(!extent.contains(p));
+            if p == PointN([-1, 0, 0]) {
+                Block::Solid
+            } else if p == PointN([2, 0, 0]) {
+                Block::Glass
+            } else {
+                Block::Empty
+            }
+        };
+
+        let mut buffer = CubeFacesBuffer::new(RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+        culled_cube_faces(&chunk, &extent, neighbors, &mut buffer);
+
+        // The solid voxel is only hidden by the solid neighbor, and the glass voxel is hidden on both sides.
+        assert_eq!(buffer.num_quads(), 5 + 4);
+        assert!(group_facing(&buffer, PointN([-1, 0, 0])).quads.is_empty());
+        let pos_x: Vec<Point3i> = group_facing(&buffer, PointN([1, 0, 0]))
+            .quads
+            .iter()
+            .map(|q| q.minimum)
+            .collect();
+        assert_eq!(pos_x, vec![Point3i::ZERO]);
+        for group in buffer.quad_groups.iter() {
+            assert!(group.ambient_occlusion.is_empty());
+            assert!(group.quads.iter().all(|q| q.width == 1 && q.height == 1));
+        }
+    }
+
+    #[test]
+    fn ambient_occlusion_uses_neighbors() {
+        // A single voxel with a neighbor above its +X edge.
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(1));
+        let chunk = Array3x1::fill(extent, Block::Solid);
+        let neighbors = |p: Point3i| {
+            if p == PointN([1, 1, 0]) {
+                Block::Solid
+            } else {
+                Block::Empty
+            }
+        };
+
+        let mut buffer = CubeFacesBuffer::new(RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+        culled_cube_faces_with_ambient_occlusion(&chunk, &extent, neighbors, &mut buffer);
+        assert_eq!(buffer.num_quads(), 6);
+
+        let top = group_facing(&buffer, PointN([0, 1, 0]));
+        assert_eq!(top.ambient_occlusion.len(), 1);
+        for (corner, &ao) in top
+            .face
+            .quad_corners(&top.quads[0])
+            .iter()
+            .zip(top.ambient_occlusion[0].iter())
+        {
+            let expected = if corner.x() == 1 { 2 } else { 3 };
+            assert_eq!(ao, expected, "corner {:?}", corner);
+        }
+
+        // Reusing the buffer without ambient occlusion clears it.
+        culled_cube_faces(&chunk, &extent, neighbors, &mut buffer);
+        assert!(buffer
+            .quad_groups
+            .iter()
+            .all(|g| g.ambient_occlusion.is_empty()));
+    }
+}
@@ -0,0 +1,317 @@
+//! The Dual Contouring isosurface extraction algorithm, as described by Ju et al. in "Dual Contouring of Hermite Data."
+//!
+//! Dual Contouring produces the same mesh topology as `surface_nets`, with one vertex in every cube that intersects the
+//! isosurface. But instead of placing each vertex at the centroid of the cube's edge crossings, it uses the surface normals at
+//! those crossings (the "Hermite data") to find the point that best fits all of the tangent planes. This is done by minimizing
+//! a quadratic error function (QEF). The result is that sharp edges and corners of the surface are preserved, while
+//! `surface_nets` would round them off.
+//!
+//! The normals are provided by a closure, which is usually the analytic gradient of the SDF that was sampled.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_mesh::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! // An axis-aligned cube with sharp edges.
+//! let half_width = 5.5;
+//! let cube_sdf = |p: Point3f| {
+//!     let d = p.abs() - Point3f::fill(half_width);
+//!
+//!     d.x().max(d.y()).max(d.z())
+//! };
+//! let cube_normal = |p: Point3f| {
+//!     let a = p.abs();
+//!     let (x, y, z) = (p.x().signum(), p.y().signum(), p.z().signum());
+//!     if a.x() >= a.y() && a.x() >= a.z() {
+//!         PointN([x, 0.0, 0.0])
+//!     } else if a.y() >= a.z() {
+//!         PointN([0.0, y, 0.0])
+//!     } else {
+//!         PointN([0.0, 0.0, z])
+//!     }
+//! };
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-10), Point3i::fill(21));
+//! let mut samples = Array3x1::fill(extent, 1.0);
+//! copy_extent(&extent, &Func(|p: Point3i| cube_sdf(Point3f::from(p))), &mut samples);
+//!
+//! let mut buffer = SurfaceNetsBuffer::default();
+//! dual_contouring(&samples, &extent, 1.0, cube_normal, &mut buffer);
+//! assert!(!buffer.mesh.is_empty());
+//! ```
+
+use super::{
+    surface_nets::{estimate_surface_edge_intersection, make_all_quads},
+    SurfaceNetsBuffer,
+};
+
+use building_blocks_core::{prelude::*, EDGES_3};
+use building_blocks_storage::prelude::*;
+
+/// Pads the given chunk extent with exactly the amount of space required for running the `dual_contouring` algorithm.
+pub fn padded_dual_contouring_chunk_extent(chunk_extent: &Extent3i) -> Extent3i {
+    chunk_extent.padded(1)
+}
+
+/// Extracts an isosurface mesh from the signed distance field `sdf`, placing each vertex by solving a QEF built from the edge
+/// crossings of its cube and the surface normals given by `normal` at those crossings.
+///
+/// `normal` is called with lattice coordinates, i.e. before scaling by `voxel_size`. The normals don't need to be unit
+/// vectors.
+///
+/// The output, and the set of corners sampled, are the same as for `surface_nets`; see that function for details. The mesh
+/// normal of each vertex is the sum of the unit normals at its edge crossings.
+pub fn dual_contouring<A, T>(
+    sdf: &A,
+    extent: &Extent3i,
+    voxel_size: f32,
+    normal: impl Fn(Point3f) -> Point3f,
+    output: &mut SurfaceNetsBuffer,
+) where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    trace_span!("dual_contouring", num_points = extent.num_points());
+
+    output.reset(sdf.extent().num_points());
+
+    estimate_surface(sdf, extent, voxel_size, &normal, output);
+    make_all_quads(sdf, extent, output);
+}
+
+fn estimate_surface<A, T>(
+    sdf: &A,
+    extent: &Extent3i,
+    voxel_size: f32,
+    normal: &impl Fn(Point3f) -> Point3f,
+    output: &mut SurfaceNetsBuffer,
+) where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    // Precalculate these offsets to do faster linear indexing.
+    let mut corner_offset_strides = [Stride(0); 8];
+    let corner_offsets = Local::localize_points_array(&Point3i::CUBE_CORNER_OFFSETS);
+    sdf.strides_from_local_points(&corner_offsets, &mut corner_offset_strides);
+
+    // Avoid accessing out of bounds with a 2x2x2 kernel.
+    let iter_extent = extent.add_to_shape(Point3i::fill(-1));
+
+    let visitor = sdf.array_for_each(iter_extent);
+    visitor.for_each(|p, p_stride| {
+        let mut corner_dists = [0.0; 8];
+        let mut num_negative = 0;
+        for (dist, offset) in corner_dists.iter_mut().zip(corner_offset_strides.iter()) {
+            *dist = sdf.get(p_stride + *offset).into();
+            if *dist < 0.0 {
+                num_negative += 1;
+            }
+        }
+
+        if num_negative == 0 || num_negative == 8 {
+            // No crossings.
+            return;
+        }
+
+        let cube_min = Point3f::from(p);
+        let mut qef = Qef::default();
+        let mut normal_sum = Point3f::ZERO;
+        for [corner1, corner2] in EDGES_3.iter() {
+            let d1 = corner_dists[*corner1];
+            let d2 = corner_dists[*corner2];
+            if (d1 < 0.0) != (d2 < 0.0) {
+                let crossing = estimate_surface_edge_intersection(*corner1, *corner2, d1, d2);
+                let n = normal(cube_min + crossing);
+                let n_norm = n.norm();
+                if n_norm > 0.0 {
+                    let unit_n = n / n_norm;
+                    qef.add_plane(crossing, unit_n);
+                    normal_sum += unit_n;
+                } else {
+                    qef.add_mass_point(crossing);
+                }
+            }
+        }
+
+        // Keep the vertex inside of its cube, which bounds the error when the QEF solution is far away, e.g. for nearly
+        // parallel planes.
+        let local_position = qef.solve().map_components_unary(|c| c.max(0.0).min(1.0));
+        let position = voxel_size * (cube_min + local_position + Point3f::fill(0.5));
+
+        output.stride_to_index[p_stride.0] = output.mesh.positions.len() as u32;
+        output.surface_points.push(p);
+        output.surface_strides.push(p_stride);
+        output.mesh.positions.push(position.0);
+        output.mesh.normals.push(normal_sum.0);
+    });
+}
+
+// Below this fraction of the largest singular value, the singular values of the QEF are considered zero. This keeps the
+// solution near the mass point along directions that aren't constrained by any plane, like the direction of a sharp edge.
+const SINGULAR_VALUE_THRESHOLD: f32 = 0.1;
+
+const NUM_JACOBI_SWEEPS: usize = 8;
+
+/// A quadratic error function, measuring the sum of squared distances from a point to a set of planes. Stored as the normal
+/// equations `A^T A x = A^T b` of the least squares problem.
+#[derive(Default)]
+struct Qef {
+    ata: [[f32; 3]; 3],
+    atb: [f32; 3],
+    mass_point_sum: Point3f,
+    num_points: u32,
+}
+
+impl Qef {
+    /// Add the plane through `point` with unit normal `n`.
+    fn add_plane(&mut self, point: Point3f, n: Point3f) {
+        let d = n.dot(point);
+        for (i, (atb_i, ata_i)) in self.atb.iter_mut().zip(self.ata.iter_mut()).enumerate() {
+            *atb_i += n.at(i) * d;
+            for (j, ata_ij) in ata_i.iter_mut().enumerate() {
+                *ata_ij += n.at(i) * n.at(j);
+            }
+        }
+        self.add_mass_point(point);
+    }
+
+    /// Add a point that only contributes to the mass point, which is the solution along unconstrained directions.
+    fn add_mass_point(&mut self, point: Point3f) {
+        self.mass_point_sum += point;
+        self.num_points += 1;
+    }
+
+    /// Minimize the error with the pseudo-inverse of `A^T A`, relative to the mass point.
+    fn solve(&self) -> Point3f {
+        let mass_point = self.mass_point_sum / self.num_points as f32;
+
+        // Residual of the normal equations at the mass point.
+        let mut residual = self.atb;
+        for (r, ata_i) in residual.iter_mut().zip(self.ata.iter()) {
+            *r -= ata_i[0] * mass_point.x() + ata_i[1] * mass_point.y() + ata_i[2] * mass_point.z();
+        }
+
+        // The eigenvalues of A^T A are the squares of the singular values of A.
+        let (eigenvalues, eigenvectors) = symmetric_eigen(self.ata);
+        let max_eigenvalue = eigenvalues.iter().fold(0.0f32, |m, e| m.max(e.abs()));
+        let min_eigenvalue = SINGULAR_VALUE_THRESHOLD * SINGULAR_VALUE_THRESHOLD * max_eigenvalue;
+
+        let mut solution = mass_point;
+        for (k, &eigenvalue) in eigenvalues.iter().enumerate() {
+            if max_eigenvalue == 0.0 || eigenvalue.abs() <= min_eigenvalue {
+                continue;
+            }
+            let v = PointN([eigenvectors[0][k], eigenvectors[1][k], eigenvectors[2][k]]);
+            let r = v.x() * residual[0] + v.y() * residual[1] + v.z() * residual[2];
+            solution += v * (r / eigenvalue);
+        }
+
+        solution
+    }
+}
+
+/// Diagonalizes the symmetric matrix `a` with cyclic Jacobi rotations. Returns the eigenvalues and a matrix whose columns are
+/// the corresponding eigenvectors.
+fn symmetric_eigen(mut a: [[f32; 3]; 3]) -> ([f32; 3], [[f32; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    for _ in 0..NUM_JACOBI_SWEEPS {
+        for &(p, q) in [(0, 1), (0, 2), (1, 2)].iter() {
+            if a[p][q].abs() < std::f32::EPSILON {
+                continue;
+            }
+
+            // Choose the rotation angle that zeroes a[p][q].
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            // a = J^T a J
+            for row in a.iter_mut() {
+                let (akp, akq) = (row[p], row[q]);
+                row[p] = c * akp - s * akq;
+                row[q] = s * akp + c * akq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            for (k, (apk, aqk)) in row_p.iter().zip(row_q.iter()).enumerate() {
+                a[p][k] = c * apk - s * aqk;
+                a[q][k] = s * apk + c * aqk;
+            }
+            // v = v J
+            for row in v.iter_mut() {
+                let (vkp, vkq) = (row[p], row[q]);
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_corners_are_sharp() {
+        let half_width = 5.5;
+        let box_sdf = |p: Point3f| {
+            let d = p.abs() - Point3f::fill(half_width);
+
+            d.x().max(d.y()).max(d.z())
+        };
+        let box_normal = |p: Point3f| {
+            let a = p.abs();
+            let (x, y, z) = (p.x().signum(), p.y().signum(), p.z().signum());
+            if a.x() >= a.y() && a.x() >= a.z() {
+                PointN([x, 0.0, 0.0])
+            } else if a.y() >= a.z() {
+                PointN([0.0, y, 0.0])
+            } else {
+                PointN([0.0, 0.0, z])
+            }
+        };
+
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-10), Point3i::fill(21));
+        let samples = Array3x1::fill_with(extent, |p: Point3i| box_sdf(Point3f::from(p)));
+
+        let mut buffer = SurfaceNetsBuffer::default();
+        dual_contouring(&samples, &extent, 1.0, box_normal, &mut buffer);
+        assert!(!buffer.mesh.is_empty());
+
+        // Vertex positions are offset by half of a voxel from the lattice.
+        let lattice_positions: Vec<Point3f> = buffer
+            .mesh
+            .positions
+            .iter()
+            .map(|p| PointN(*p) - Point3f::fill(0.5))
+            .collect();
+
+        // Every vertex lies on the surface of the box, including those in the cubes along its edges.
+        for p in lattice_positions.iter() {
+            assert!(box_sdf(*p).abs() < 1e-4, "{:?} is off the surface", p);
+        }
+
+        // And each box corner has a vertex exactly on it.
+        for offset in Point3i::CUBE_CORNER_OFFSETS.iter() {
+            let corner = (Point3f::from(*offset) * 2.0 - Point3f::ONES) * half_width;
+            assert!(
+                lattice_positions
+                    .iter()
+                    .any(|p| (*p - corner).norm() < 1e-4),
+                "no vertex at corner {:?}",
+                corner
+            );
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Lightweight writers for dumping meshes to Wavefront OBJ and ASCII PLY files, which can be opened in almost any mesh viewer.
+//! This is handy for debugging, e.g. to tell whether a bad mesh came from a bad signed distance field or a bad mesher.
+//!
+//! Any `DumpMesh` can be written, which includes `PosNormMesh`, `PosNormTexMesh`, the buffers of all of the triangle meshers,
+//! and (with the `bytemuck` feature) `InterleavedMesh`. Quads from `greedy_quads` can be dumped after adding them to a
+//! `PosNormMesh` with `OrientedCubeFace::add_quad_to_pos_norm_mesh`.
+//!
+//! ```
+//! use building_blocks_mesh::*;
+//!
+//! let mesh = PosNormMesh {
+//!     positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+//!     normals: vec![[0.0, 0.0, 1.0]; 3],
+//!     indices: vec![0, 1, 2],
+//! };
+//!
+//! let mut obj = Vec::new();
+//! write_obj(&mesh, &mut obj).unwrap();
+//! assert!(String::from_utf8(obj).unwrap().contains("f 1//1 2//2 3//3"));
+//!
+//! // PLY can also include any number of extra per-vertex attributes, e.g. the signed distance at each vertex.
+//! let distance: [f32; 3] = [0.0, 0.5, -0.5];
+//! let mut ply = Vec::new();
+//! write_ply(&mesh, &[VertexAttribute::new("distance", &distance)], &mut ply).unwrap();
+//! let ply = String::from_utf8(ply).unwrap();
+//! assert!(ply.contains("property float distance"));
+//! assert!(ply.contains("3 0 1 2"));
+//!
+//! // Or write straight to a file.
+//! // write_obj(&mesh, std::io::BufWriter::new(std::fs::File::create("chunk.obj")?))?;
+//! ```
+
+use super::{
+    HeightMapMeshBuffer, MarchingCubesBuffer, PosNormMesh, PosNormTexMesh, SurfaceNetsBuffer,
+    TransitionStripBuffer,
+};
+
+use std::io::{self, Write};
+
+/// A triangle mesh that can be written with `write_obj` or `write_ply`.
+pub trait DumpMesh {
+    fn num_vertices(&self) -> usize;
+
+    fn position(&self, vertex: usize) -> [f32; 3];
+
+    fn normal(&self, vertex: usize) -> [f32; 3];
+
+    /// Texture coordinates, if the mesh has them.
+    fn tex_coord(&self, _vertex: usize) -> Option<[f32; 2]> {
+        None
+    }
+
+    /// All of the triangles in the mesh.
+    fn indices(&self) -> &[u32];
+}
+
+/// An extra PLY vertex property, with one value per mesh vertex.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexAttribute<'a> {
+    pub name: &'a str,
+    pub values: &'a [f32],
+}
+
+impl<'a> VertexAttribute<'a> {
+    pub fn new(name: &'a str, values: &'a [f32]) -> Self {
+        Self { name, values }
+    }
+}
+
+/// Writes `mesh` in the Wavefront OBJ format, including normals and any texture coordinates.
+pub fn write_obj(mesh: &impl DumpMesh, mut writer: impl Write) -> io::Result<()> {
+    let num_vertices = mesh.num_vertices();
+    let has_tex_coords = num_vertices > 0 && mesh.tex_coord(0).is_some();
+
+    for i in 0..num_vertices {
+        let [x, y, z] = mesh.position(i);
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    for i in 0..num_vertices {
+        let [x, y, z] = mesh.normal(i);
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+    if has_tex_coords {
+        for i in 0..num_vertices {
+            let [u, v] = mesh.tex_coord(i).unwrap_or([0.0; 2]);
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+    }
+
+    // OBJ indices start at 1.
+    for triangle in mesh.indices().chunks_exact(3) {
+        write!(writer, "f")?;
+        for &index in triangle.iter() {
+            let index = index + 1;
+            if has_tex_coords {
+                write!(writer, " {}/{}/{}", index, index, index)?;
+            } else {
+                write!(writer, " {}//{}", index, index)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `mesh` in the ASCII PLY format, including normals, any texture coordinates, and the extra per-vertex `attributes`.
+///
+/// Panics if any of the `attributes` doesn't have exactly one value per vertex.
+pub fn write_ply(
+    mesh: &impl DumpMesh,
+    attributes: &[VertexAttribute],
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let num_vertices = mesh.num_vertices();
+    let has_tex_coords = num_vertices > 0 && mesh.tex_coord(0).is_some();
+    for attribute in attributes.iter() {
+        assert_eq!(
+            attribute.values.len(),
+            num_vertices,
+            "PLY attribute {:?} has the wrong number of values",
+            attribute.name
+        );
+    }
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment building_blocks_mesh")?;
+    writeln!(writer, "element vertex {}", num_vertices)?;
+    for property in ["x", "y", "z", "nx", "ny", "nz"].iter() {
+        writeln!(writer, "property float {}", property)?;
+    }
+    if has_tex_coords {
+        writeln!(writer, "property float s")?;
+        writeln!(writer, "property float t")?;
+    }
+    for attribute in attributes.iter() {
+        writeln!(writer, "property float {}", attribute.name)?;
+    }
+    writeln!(writer, "element face {}", mesh.indices().len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for i in 0..num_vertices {
+        let [x, y, z] = mesh.position(i);
+        let [nx, ny, nz] = mesh.normal(i);
+        write!(writer, "{} {} {} {} {} {}", x, y, z, nx, ny, nz)?;
+        if has_tex_coords {
+            let [s, t] = mesh.tex_coord(i).unwrap_or([0.0; 2]);
+            write!(writer, " {} {}", s, t)?;
+        }
+        for attribute in attributes.iter() {
+            write!(writer, " {}", attribute.values[i])?;
+        }
+        writeln!(writer)?;
+    }
+
+    for triangle in mesh.indices().chunks_exact(3) {
+        writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?;
+    }
+
+    Ok(())
+}
+
+impl DumpMesh for PosNormMesh {
+    fn num_vertices(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn position(&self, vertex: usize) -> [f32; 3] {
+        self.positions[vertex]
+    }
+
+    fn normal(&self, vertex: usize) -> [f32; 3] {
+        self.normals[vertex]
+    }
+
+    fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
+impl DumpMesh for PosNormTexMesh {
+    fn num_vertices(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn position(&self, vertex: usize) -> [f32; 3] {
+        self.positions[vertex]
+    }
+
+    fn normal(&self, vertex: usize) -> [f32; 3] {
+        self.normals[vertex]
+    }
+
+    fn tex_coord(&self, vertex: usize) -> Option<[f32; 2]> {
+        Some(self.tex_coords[vertex])
+    }
+
+    fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
+macro_rules! impl_dump_mesh_for_buffer {
+    ($buffer:ty) => {
+        impl DumpMesh for $buffer {
+            fn num_vertices(&self) -> usize {
+                self.mesh.num_vertices()
+            }
+
+            fn position(&self, vertex: usize) -> [f32; 3] {
+                self.mesh.position(vertex)
+            }
+
+            fn normal(&self, vertex: usize) -> [f32; 3] {
+                self.mesh.normal(vertex)
+            }
+
+            fn indices(&self) -> &[u32] {
+                self.mesh.indices()
+            }
+        }
+    };
+}
+
+impl_dump_mesh_for_buffer!(SurfaceNetsBuffer);
+impl_dump_mesh_for_buffer!(MarchingCubesBuffer);
+impl_dump_mesh_for_buffer!(HeightMapMeshBuffer);
+impl_dump_mesh_for_buffer!(TransitionStripBuffer);
+
+#[cfg(feature = "bytemuck")]
+mod interleaved {
+    use super::DumpMesh;
+
+    use crate::{InterleavedMesh, PosNormTexMatVertex, PosNormTexVertex, PosNormVertex};
+
+    impl DumpMesh for InterleavedMesh<PosNormVertex> {
+        fn num_vertices(&self) -> usize {
+            self.vertices.len()
+        }
+
+        fn position(&self, vertex: usize) -> [f32; 3] {
+            self.vertices[vertex].position
+        }
+
+        fn normal(&self, vertex: usize) -> [f32; 3] {
+            self.vertices[vertex].normal
+        }
+
+        fn indices(&self) -> &[u32] {
+            &self.indices
+        }
+    }
+
+    macro_rules! impl_dump_mesh_for_textured_vertex {
+        ($vertex:ty) => {
+            impl DumpMesh for InterleavedMesh<$vertex> {
+                fn num_vertices(&self) -> usize {
+                    self.vertices.len()
+                }
+
+                fn position(&self, vertex: usize) -> [f32; 3] {
+                    self.vertices[vertex].position
+                }
+
+                fn normal(&self, vertex: usize) -> [f32; 3] {
+                    self.vertices[vertex].normal
+                }
+
+                fn tex_coord(&self, vertex: usize) -> Option<[f32; 2]> {
+                    Some(self.vertices[vertex].tex_coord)
+                }
+
+                fn indices(&self) -> &[u32] {
+                    &self.indices
+                }
+            }
+        };
+    }
+
+    impl_dump_mesh_for_textured_vertex!(PosNormTexVertex);
+    impl_dump_mesh_for_textured_vertex!(PosNormTexMatVertex);
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_quad() -> PosNormTexMesh {
+        PosNormTexMesh {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+            normals: vec![[0.0, 0.0, 1.0]; 4],
+            tex_coords: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]],
+            indices: vec![0, 1, 2, 1, 3, 2],
+        }
+    }
+
+    fn untextured(mesh: PosNormTexMesh) -> PosNormMesh {
+        PosNormMesh {
+            positions: mesh.positions,
+            normals: mesh.normals,
+            indices: mesh.indices,
+        }
+    }
+
+    #[test]
+    fn obj_of_quad() {
+        let mut obj = Vec::new();
+        write_obj(&untextured(unit_quad()), &mut obj).unwrap();
+
+        assert_eq!(
+            String::from_utf8(obj).unwrap(),
+            "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+f 1//1 2//2 3//3
+f 2//2 4//4 3//3
+"
+        );
+    }
+
+    #[test]
+    fn textured_obj_of_quad() {
+        let mut obj = Vec::new();
+        write_obj(&unit_quad(), &mut obj).unwrap();
+
+        assert_eq!(
+            String::from_utf8(obj).unwrap(),
+            "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+vt 0 0
+vt 1 0
+vt 0 1
+vt 1 1
+f 1/1/1 2/2/2 3/3/3
+f 2/2/2 4/4/4 3/3/3
+"
+        );
+    }
+
+    #[test]
+    fn ply_of_quad_with_attribute() {
+        let distance = [0.0, 0.5, -0.5, 1.0];
+        let mut ply = Vec::new();
+        write_ply(
+            &unit_quad(),
+            &[VertexAttribute::new("distance", &distance)],
+            &mut ply,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(ply).unwrap(),
+            "\
+ply
+format ascii 1.0
+comment building_blocks_mesh
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+property float distance
+element face 2
+property list uchar uint vertex_indices
+end_header
+0 0 0 0 0 1 0 0 0
+1 0 0 0 0 1 1 0 0.5
+0 1 0 0 0 1 0 1 -0.5
+1 1 0 0 0 1 1 1 1
+3 0 1 2
+3 1 3 2
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "PLY attribute \"distance\" has the wrong number of values")]
+    fn ply_attribute_length_is_checked() {
+        let distance = [0.0; 3];
+        write_ply(
+            &unit_quad(),
+            &[VertexAttribute::new("distance", &distance)],
+            io::sink(),
+        )
+        .unwrap();
+    }
+}
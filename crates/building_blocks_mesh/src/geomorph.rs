@@ -0,0 +1,127 @@
+//! Geomorphing between the `surface_nets` meshes of adjacent levels of detail.
+//!
+//! When a clipmap merges chunks into their parent, the finer mesh is replaced by the coarser one in a single frame, and the
+//! vertices visibly "pop." To hide this, every vertex of a chunk mesh at LOD `n` can carry a morph target: the position (and
+//! normal) that the LOD `n + 1` mesh has in the same place. The vertex shader then blends each vertex toward its target as the
+//! chunk approaches the outer edge of its LOD, using a factor like `ClipMapConfig3::geomorph_factor`, so that by the time the
+//! chunk is merged, it already looks like its parent.
+//!
+//! The parent samples come straight from LOD `n + 1` of the same `ChunkMap`, so the LODs must be downsampled first, e.g. with
+//! `ChunkMap::downsample_chunks_with_index`. For each chunk:
+//!
+//!   1. mesh the padded chunk at LOD `n` with `surface_nets`
+//!   2. copy the `padded_geomorph_parent_extent` of the padded chunk extent from LOD `n + 1` into an array
+//!   3. call `surface_nets_morph_targets` with that array and the mesh
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//! use building_blocks_mesh::*;
+//!
+//! // A plane at y = 5.3 in LOD0 voxel coordinates.
+//! let plane = |p: Point3f| p.y() - 5.3;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8)).padded(1);
+//! let sdf = Array3x1::fill_with(extent, |p: Point3i| plane(Point3f::from(p) + Point3f::fill(0.5)));
+//! let mut buffer = SurfaceNetsBuffer::default();
+//! surface_nets(&sdf, &extent, 1.0, &mut buffer);
+//!
+//! // The parent samples are twice as far apart.
+//! let parent_extent = padded_geomorph_parent_extent(&extent);
+//! let parent_sdf = Array3x1::fill_with(parent_extent, |p: Point3i| {
+//!     plane(2.0 * (Point3f::from(p) + Point3f::fill(0.5)))
+//! });
+//!
+//! let mut targets = MorphTargets::default();
+//! surface_nets_morph_targets(&parent_sdf, 1.0, &buffer.mesh, &mut targets);
+//!
+//! assert_eq!(targets.positions.len(), buffer.mesh.positions.len());
+//! for (position, target) in buffer.mesh.positions.iter().zip(targets.positions.iter()) {
+//!     // The plane is flat, so the parent vertices are on the same plane.
+//!     assert!((target[1] - 5.3).abs() < 0.001);
+//!     assert!((PointN(*target) - PointN(*position)).norm() < 2.0 * 3.0f32.sqrt());
+//! }
+//! ```
+
+use super::{surface_nets::estimate_surface_in_cube, PosNormMesh};
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::prelude::*;
+
+/// The morph targets of a mesh, parallel to the mesh's positions and normals.
+#[derive(Clone, Default)]
+pub struct MorphTargets {
+    /// The position of each vertex in the parent level of detail.
+    pub positions: Vec<[f32; 3]>,
+    /// The surface normal of each vertex in the parent level of detail. Not guaranteed to be normalized.
+    pub normals: Vec<[f32; 3]>,
+}
+
+impl MorphTargets {
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.normals.clear();
+    }
+}
+
+/// Returns the extent of the parent level of detail that `surface_nets_morph_targets` needs to sample, given the padded extent
+/// that was meshed with `surface_nets`.
+pub fn padded_geomorph_parent_extent(padded_chunk_extent: &Extent3i) -> Extent3i {
+    Extent3i::from_min_and_max(
+        (padded_chunk_extent.minimum - Point3i::fill(1)) >> 1,
+        (padded_chunk_extent.max() >> 1) + Point3i::fill(1),
+    )
+}
+
+/// Finds the morph target of each vertex of a `surface_nets` `mesh` with `voxel_size`, by estimating the surface in the cube of
+/// `parent_sdf` that contains the vertex, exactly as `surface_nets` would with twice the voxel size.
+///
+/// `parent_sdf` should contain the `padded_geomorph_parent_extent` of the meshed extent, from the next level of detail. Where
+/// detail is lost and the parent cube has no surface, the vertex targets its own position and normal, so it won't move.
+pub fn surface_nets_morph_targets<A, T>(
+    parent_sdf: &A,
+    voxel_size: f32,
+    mesh: &PosNormMesh,
+    output: &mut MorphTargets,
+) where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    trace_span!(
+        "surface_nets_morph_targets",
+        num_vertices = mesh.positions.len()
+    );
+
+    output.clear();
+
+    let parent_voxel_size = 2.0 * voxel_size;
+    let parent_extent = *parent_sdf.extent();
+    // Every cube needs all 8 of its corners.
+    let cube_max = parent_extent.max() - Point3i::fill(1);
+
+    let mut corner_offset_strides = [Stride(0); 8];
+    let corner_offsets = Local::localize_points_array(&Point3i::CUBE_CORNER_OFFSETS);
+    parent_sdf.strides_from_local_points(&corner_offsets, &mut corner_offset_strides);
+
+    for (&position, &normal) in mesh.positions.iter().zip(mesh.normals.iter()) {
+        // Invert the vertex position formula of `surface_nets` to find the cube.
+        let cube_min = (PointN(position) / parent_voxel_size - Point3f::fill(0.5))
+            .floor_int()
+            .join(parent_extent.minimum)
+            .meet(cube_max);
+        let cube_stride =
+            parent_sdf.stride_from_local_point(Local(cube_min - parent_extent.minimum));
+        let mut corner_strides = [Stride(0); 8];
+        for (corner_stride, offset_stride) in
+            corner_strides.iter_mut().zip(corner_offset_strides.iter())
+        {
+            *corner_stride = cube_stride + *offset_stride;
+        }
+
+        let (target_position, target_normal) =
+            estimate_surface_in_cube(parent_sdf, parent_voxel_size, &cube_min, &corner_strides)
+                .unwrap_or((position, normal));
+        output.positions.push(target_position);
+        output.normals.push(target_normal);
+    }
+}
@@ -0,0 +1,521 @@
+//! Exporting meshes to binary glTF (`.glb`) files, e.g. for inspecting the output of a mesher in a model viewer, or for
+//! shipping static assets without an external converter.
+//!
+//! A `GlbExporter` collects any number of primitives, each with its own optional material and vertex colors, and writes them
+//! as a single glTF mesh. Quad groups from `greedy_quads` or `greedy_quads_with_materials` can be added directly.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//! use building_blocks_mesh::*;
+//!
+//! #[derive(Clone)]
+//! struct CubeVoxel(bool);
+//!
+//! impl MergeVoxel for CubeVoxel {
+//!     type VoxelValue = bool;
+//!
+//!     fn voxel_merge_value(&self) -> Self::VoxelValue { self.0 }
+//! }
+//!
+//! impl IsEmpty for CubeVoxel {
+//!     fn is_empty(&self) -> bool { !self.0 }
+//! }
+//!
+//! impl IsOpaque for CubeVoxel {
+//!     fn is_opaque(&self) -> bool { true }
+//! }
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+//! let voxels = Array3x1::fill_with(extent, |p| CubeVoxel(extent.padded(-2).contains(p)));
+//! let mut buffer = GreedyQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+//! greedy_quads(&voxels, &extent, &mut buffer);
+//!
+//! let mut exporter = GlbExporter::default();
+//! let stone = exporter.add_material(GlbMaterial::new("stone", [0.5, 0.5, 0.5, 1.0]));
+//! exporter.add_quad_groups(&buffer.quad_groups, 1.0, Some(stone));
+//!
+//! let triangle = PosNormMesh {
+//!     positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+//!     normals: vec![[0.0, 0.0, 1.0]; 3],
+//!     indices: vec![0, 1, 2],
+//! };
+//! exporter.add_mesh_with_colors(triangle, vec![[1.0, 0.0, 0.0, 1.0]; 3], None);
+//!
+//! let mut glb = Vec::new();
+//! exporter.write_glb(&mut glb).unwrap();
+//! assert_eq!(&glb[0..4], b"glTF");
+//! assert_eq!(glb.len() % 4, 0);
+//! assert_eq!(u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize, glb.len());
+//!
+//! // Or write straight to a file.
+//! // exporter.write_glb(std::fs::File::create("chunk.glb")?)?;
+//! ```
+
+use super::{PosNormMesh, QuadGroup};
+
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const CHUNK_TYPE_JSON: u32 = 0x4E4F_534A; // "JSON"
+const CHUNK_TYPE_BIN: u32 = 0x004E_4942; // "BIN\0"
+
+const COMPONENT_TYPE_FLOAT: u32 = 5126;
+const COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const MODE_TRIANGLES: u32 = 4;
+
+/// A simple PBR material with a constant base color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlbMaterial {
+    pub name: String,
+    /// Linear RGBA. An alpha less than 1 enables alpha blending.
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl GlbMaterial {
+    /// A fully-rough, non-metallic material.
+    pub fn new(name: impl Into<String>, base_color: [f32; 4]) -> Self {
+        Self {
+            name: name.into(),
+            base_color,
+            metallic: 0.0,
+            roughness: 1.0,
+        }
+    }
+}
+
+/// One glTF primitive, i.e. a triangle mesh drawn with a single material.
+#[derive(Clone, Default)]
+pub struct GlbPrimitive {
+    pub mesh: PosNormMesh,
+    /// Optional linear RGBA color for each vertex, which is multiplied with the material's base color.
+    pub colors: Option<Vec<[f32; 4]>>,
+    /// Index into `GlbExporter::materials`. Primitives without a material use the glTF default material.
+    pub material: Option<usize>,
+}
+
+/// Collects mesh primitives and materials, then writes them to a `.glb` file with `write_glb`.
+#[derive(Clone, Default)]
+pub struct GlbExporter {
+    pub materials: Vec<GlbMaterial>,
+    pub primitives: Vec<GlbPrimitive>,
+}
+
+impl GlbExporter {
+    /// Returns the index of the new material.
+    pub fn add_material(&mut self, material: GlbMaterial) -> usize {
+        self.materials.push(material);
+
+        self.materials.len() - 1
+    }
+
+    pub fn add_mesh(&mut self, mesh: PosNormMesh, material: Option<usize>) {
+        self.primitives.push(GlbPrimitive {
+            mesh,
+            colors: None,
+            material,
+        });
+    }
+
+    /// Like `add_mesh`, but with a color for each vertex of `mesh`.
+    pub fn add_mesh_with_colors(
+        &mut self,
+        mesh: PosNormMesh,
+        colors: Vec<[f32; 4]>,
+        material: Option<usize>,
+    ) {
+        assert_eq!(colors.len(), mesh.positions.len());
+
+        self.primitives.push(GlbPrimitive {
+            mesh,
+            colors: Some(colors),
+            material,
+        });
+    }
+
+    /// Adds all of the quads in `groups` as a single primitive with `material`.
+    pub fn add_quad_groups(
+        &mut self,
+        groups: &[QuadGroup],
+        voxel_size: f32,
+        material: Option<usize>,
+    ) {
+        let mut mesh = PosNormMesh::default();
+        for group in groups.iter() {
+            for quad in group.quads.iter() {
+                group
+                    .face
+                    .add_quad_to_pos_norm_mesh(quad, voxel_size, &mut mesh);
+            }
+        }
+        self.add_mesh(mesh, material);
+    }
+
+    /// Writes all of the primitives as a single mesh in the binary glTF 2.0 format. Primitives without any triangles are
+    /// skipped, and normals are normalized, since glTF requires unit normals.
+    pub fn write_glb(&self, mut writer: impl Write) -> io::Result<()> {
+        let (json, mut bin) = self.encode();
+
+        let mut json = json.into_bytes();
+        pad_to_4_bytes(&mut json, b' ');
+        pad_to_4_bytes(&mut bin, 0);
+
+        let mut total_len = 12 + 8 + json.len();
+        if !bin.is_empty() {
+            total_len += 8 + bin.len();
+        }
+
+        write_u32(&mut writer, GLB_MAGIC)?;
+        write_u32(&mut writer, GLB_VERSION)?;
+        write_u32(&mut writer, total_len as u32)?;
+
+        write_u32(&mut writer, json.len() as u32)?;
+        write_u32(&mut writer, CHUNK_TYPE_JSON)?;
+        writer.write_all(&json)?;
+
+        if !bin.is_empty() {
+            write_u32(&mut writer, bin.len() as u32)?;
+            write_u32(&mut writer, CHUNK_TYPE_BIN)?;
+            writer.write_all(&bin)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the JSON document and the binary buffer that it refers to.
+    fn encode(&self) -> (String, Vec<u8>) {
+        let mut bin = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut primitives = Vec::new();
+
+        for primitive in self.primitives.iter().filter(|p| !p.mesh.is_empty()) {
+            let mesh = &primitive.mesh;
+
+            let (min, max) = bounds(&mesh.positions);
+            let positions = push_accessor(
+                &mut bin,
+                &mut buffer_views,
+                &mut accessors,
+                flatten(&mesh.positions),
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_TYPE_FLOAT,
+                "VEC3",
+                mesh.positions.len(),
+                Some((&min, &max)),
+            );
+
+            let normals: Vec<[f32; 3]> = mesh.normals.iter().map(normalize).collect();
+            let normals = push_accessor(
+                &mut bin,
+                &mut buffer_views,
+                &mut accessors,
+                flatten(&normals),
+                TARGET_ARRAY_BUFFER,
+                COMPONENT_TYPE_FLOAT,
+                "VEC3",
+                normals.len(),
+                None,
+            );
+
+            let colors = primitive.colors.as_ref().map(|colors| {
+                push_accessor(
+                    &mut bin,
+                    &mut buffer_views,
+                    &mut accessors,
+                    flatten(colors),
+                    TARGET_ARRAY_BUFFER,
+                    COMPONENT_TYPE_FLOAT,
+                    "VEC4",
+                    colors.len(),
+                    None,
+                )
+            });
+
+            let indices = push_accessor(
+                &mut bin,
+                &mut buffer_views,
+                &mut accessors,
+                mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect(),
+                TARGET_ELEMENT_ARRAY_BUFFER,
+                COMPONENT_TYPE_UNSIGNED_INT,
+                "SCALAR",
+                mesh.indices.len(),
+                None,
+            );
+
+            let mut json = format!(
+                "{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{}",
+                positions, normals
+            );
+            if let Some(colors) = colors {
+                write!(json, ",\"COLOR_0\":{}", colors).unwrap();
+            }
+            write!(json, "}},\"indices\":{}", indices).unwrap();
+            if let Some(material) = primitive.material {
+                debug_assert!(material < self.materials.len());
+                write!(json, ",\"material\":{}", material).unwrap();
+            }
+            write!(json, ",\"mode\":{}}}", MODE_TRIANGLES).unwrap();
+            primitives.push(json);
+        }
+
+        let mut json = String::from(
+            "{\"asset\":{\"version\":\"2.0\",\"generator\":\"building_blocks_mesh\"},\"scene\":0",
+        );
+        if primitives.is_empty() {
+            // glTF doesn't allow meshes without primitives or empty buffers.
+            json.push_str(",\"scenes\":[{\"nodes\":[]}]");
+        } else {
+            write!(
+                json,
+                ",\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],\"meshes\":[{{\"primitives\":[{}]}}]",
+                primitives.join(",")
+            )
+            .unwrap();
+            write!(
+                json,
+                ",\"buffers\":[{{\"byteLength\":{}}}],\"bufferViews\":[{}],\"accessors\":[{}]",
+                bin.len(),
+                buffer_views.join(","),
+                accessors.join(",")
+            )
+            .unwrap();
+        }
+        if !self.materials.is_empty() {
+            let materials: Vec<_> = self.materials.iter().map(material_json).collect();
+            write!(json, ",\"materials\":[{}]", materials.join(",")).unwrap();
+        }
+        json.push('}');
+
+        (json, bin)
+    }
+}
+
+// Appends `bytes` to the binary buffer with a new buffer view and accessor, returning the index of the accessor.
+fn push_accessor(
+    bin: &mut Vec<u8>,
+    buffer_views: &mut Vec<String>,
+    accessors: &mut Vec<String>,
+    bytes: Vec<u8>,
+    target: u32,
+    component_type: u32,
+    accessor_type: &str,
+    count: usize,
+    min_max: Option<(&[f32; 3], &[f32; 3])>,
+) -> usize {
+    // All components are 4 bytes, so every view is already aligned.
+    buffer_views.push(format!(
+        "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+        bin.len(),
+        bytes.len(),
+        target
+    ));
+    bin.extend_from_slice(&bytes);
+
+    let mut accessor = format!(
+        "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"{}\"",
+        buffer_views.len() - 1,
+        component_type,
+        count,
+        accessor_type
+    );
+    if let Some((min, max)) = min_max {
+        write!(
+            accessor,
+            ",\"min\":[{},{},{}],\"max\":[{},{},{}]",
+            min[0], min[1], min[2], max[0], max[1], max[2]
+        )
+        .unwrap();
+    }
+    accessor.push('}');
+    accessors.push(accessor);
+
+    accessors.len() - 1
+}
+
+fn material_json(material: &GlbMaterial) -> String {
+    let [r, g, b, a] = material.base_color;
+    let mut json = format!(
+        "{{\"name\":\"{}\",\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{},{},{},{}],\"metallicFactor\":{},\"roughnessFactor\":{}}}",
+        escape_json(&material.name),
+        r,
+        g,
+        b,
+        a,
+        material.metallic,
+        material.roughness
+    );
+    if a < 1.0 {
+        json.push_str(",\"alphaMode\":\"BLEND\"");
+    }
+    json.push('}');
+
+    json
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn flatten<const D: usize>(vectors: &[[f32; D]]) -> Vec<u8> {
+    vectors
+        .iter()
+        .flat_map(|v| v.iter().flat_map(|c| c.to_le_bytes()))
+        .collect()
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions.iter() {
+        for ((min, max), &c) in min.iter_mut().zip(max.iter_mut()).zip(p.iter()) {
+            *min = min.min(c);
+            *max = max.max(c);
+        }
+    }
+
+    (min, max)
+}
+
+fn normalize(n: &[f32; 3]) -> [f32; 3] {
+    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if length > 0.0 {
+        [n[0] / length, n[1] / length, n[2] / length]
+    } else {
+        *n
+    }
+}
+
+fn pad_to_4_bytes(bytes: &mut Vec<u8>, padding: u8) {
+    while bytes.len() % 4 != 0 {
+        bytes.push(padding);
+    }
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    }
+
+    fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_bits(read_u32(bytes, offset))
+    }
+
+    #[test]
+    fn glb_layout_of_primitives_with_materials() {
+        let mut exporter = GlbExporter::default();
+        let stone = exporter.add_material(GlbMaterial::new("stone", [0.5, 0.5, 0.5, 1.0]));
+        let glass =
+            exporter.add_material(GlbMaterial::new("glass \"clear\"", [1.0, 1.0, 1.0, 0.5]));
+
+        let triangle = PosNormMesh {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            normals: vec![[0.0, 0.0, 1.0]; 3],
+            indices: vec![0, 1, 2],
+        };
+        exporter.add_mesh(triangle, Some(stone));
+        // Empty primitives are skipped.
+        exporter.add_mesh(PosNormMesh::default(), Some(stone));
+        let quad = PosNormMesh {
+            positions: vec![
+                [0.0, 0.0, 2.0],
+                [1.0, 0.0, 2.0],
+                [0.0, 1.0, 2.0],
+                [1.0, 1.0, 2.0],
+            ],
+            normals: vec![[0.0, 0.0, 2.0]; 4],
+            indices: vec![0, 1, 2, 1, 3, 2],
+        };
+        exporter.add_mesh_with_colors(quad, vec![[1.0, 0.0, 0.0, 1.0]; 4], Some(glass));
+
+        let mut glb = Vec::new();
+        exporter.write_glb(&mut glb).unwrap();
+
+        // Header.
+        assert_eq!(read_u32(&glb, 0), GLB_MAGIC);
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+
+        // JSON chunk.
+        let json_len = read_u32(&glb, 12) as usize;
+        assert_eq!(json_len % 4, 0);
+        assert_eq!(read_u32(&glb, 16), CHUNK_TYPE_JSON);
+        let json = std::str::from_utf8(&glb[20..20 + json_len])
+            .unwrap()
+            .trim_end();
+
+        // BIN chunk. The triangle has 36 bytes each of positions and normals and 12 bytes of indices. The quad has 48 bytes
+        // each of positions and normals, 64 bytes of colors, and 24 bytes of indices.
+        let bin_start = 20 + json_len;
+        let bin_len = read_u32(&glb, bin_start) as usize;
+        assert_eq!(bin_len, 84 + 184);
+        assert_eq!(read_u32(&glb, bin_start + 4), CHUNK_TYPE_BIN);
+        let bin = &glb[bin_start + 8..];
+        assert_eq!(bin.len(), bin_len);
+
+        assert!(json.contains(&format!("\"buffers\":[{{\"byteLength\":{}}}]", bin_len)));
+        assert!(json.contains(
+            "\"primitives\":[\
+            {\"attributes\":{\"POSITION\":0,\"NORMAL\":1},\"indices\":2,\"material\":0,\"mode\":4},\
+            {\"attributes\":{\"POSITION\":3,\"NORMAL\":4,\"COLOR_0\":5},\"indices\":6,\"material\":1,\"mode\":4}]"
+        ));
+        assert!(
+            json.contains("{\"buffer\":0,\"byteOffset\":244,\"byteLength\":24,\"target\":34963}")
+        );
+        assert!(json.contains("\"count\":4,\"type\":\"VEC3\",\"min\":[0,0,2],\"max\":[1,1,2]"));
+        assert!(json.contains("\"name\":\"glass \\\"clear\\\"\""));
+        assert!(json.contains("\"alphaMode\":\"BLEND\""));
+        assert_eq!(json.matches("alphaMode").count(), 1);
+
+        // The quad's normals were normalized.
+        assert_eq!(read_f32(bin, 132 + 8), 1.0);
+        // And the last index is 2.
+        assert_eq!(read_u32(bin, bin_len - 4), 2);
+    }
+
+    #[test]
+    fn empty_exporter_has_no_bin_chunk() {
+        let mut glb = Vec::new();
+        GlbExporter::default().write_glb(&mut glb).unwrap();
+
+        let json_len = read_u32(&glb, 12) as usize;
+        assert_eq!(glb.len(), 20 + json_len);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+    }
+}
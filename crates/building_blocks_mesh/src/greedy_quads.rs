@@ -4,7 +4,9 @@ use super::{
 };
 
 use building_blocks_core::{prelude::*, Axis3Permutation};
-use building_blocks_storage::prelude::*;
+use building_blocks_storage::{prelude::*, ChunkIndexer};
+
+use core::hash::Hash;
 
 /// Contains the output from the `greedy_quads` algorithm. The quads can be used to generate a mesh. See the methods on
 /// `OrientedCubeFace` and `UnorientedQuad` for details.
@@ -27,6 +29,11 @@ pub struct QuadGroup {
     /// When using these values for materials and lighting, you can access them using either the quad's minimum voxel
     /// coordinates or the vertex coordinates given by `OrientedCubeFace::quad_corners`.
     pub quads: Vec<UnorientedQuad>,
+    /// The ambient occlusion at each corner of the quad at the same index in `quads`, in the order given by
+    /// `OrientedCubeFace::quad_corners`. Values range from `0` (fully occluded) to `3` (not occluded).
+    ///
+    /// This is only filled by `greedy_quads_with_ambient_occlusion`, otherwise it's empty.
+    pub ambient_occlusion: Vec<[u8; 4]>,
     /// One of 6 cube faces. All quads in this struct are comprised of only this face.
     pub face: OrientedCubeFace,
 }
@@ -35,6 +42,7 @@ impl QuadGroup {
     pub fn new(face: OrientedCubeFace) -> Self {
         Self {
             quads: Vec::new(),
+            ambient_occlusion: Vec::new(),
             face,
         }
     }
@@ -66,19 +74,23 @@ pub const RIGHT_HANDED_Y_UP_CONFIG: QuadCoordinateConfig = QuadCoordinateConfig
 
 impl QuadCoordinateConfig {
     pub fn quad_groups(self) -> [QuadGroup; 6] {
-        let [f0, f1, f2, f3, f4, f5] = self.faces;
-
-        [
-            QuadGroup::new(f0),
-            QuadGroup::new(f1),
-            QuadGroup::new(f2),
-            QuadGroup::new(f3),
-            QuadGroup::new(f4),
-            QuadGroup::new(f5),
-        ]
+        quad_groups_for_faces(self.faces)
     }
 }
 
+fn quad_groups_for_faces(faces: [OrientedCubeFace; 6]) -> [QuadGroup; 6] {
+    let [f0, f1, f2, f3, f4, f5] = faces;
+
+    [
+        QuadGroup::new(f0),
+        QuadGroup::new(f1),
+        QuadGroup::new(f2),
+        QuadGroup::new(f3),
+        QuadGroup::new(f4),
+        QuadGroup::new(f5),
+    ]
+}
+
 impl GreedyQuadsBuffer {
     pub fn new(extent: Extent3i, quad_groups: [QuadGroup; 6]) -> Self {
         Self {
@@ -90,6 +102,7 @@ impl GreedyQuadsBuffer {
     pub fn reset(&mut self, extent: Extent3i) {
         for group in self.quad_groups.iter_mut() {
             group.quads.clear();
+            group.ambient_occlusion.clear();
         }
 
         if extent.shape != self.visited.extent().shape {
@@ -146,6 +159,50 @@ pub fn greedy_quads_with_merge_strategy<A, T, Merger>(
     T: IsEmpty + IsOpaque,
     Merger: MergeStrategy<Voxel = T>,
 {
+    trace_span!("greedy_quads", num_points = extent.num_points());
+
+    output.reset(*extent);
+    let GreedyQuadsBuffer {
+        visited,
+        quad_groups,
+    } = output;
+
+    let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
+
+    for group in quad_groups.iter_mut() {
+        let QuadGroup { quads, face, .. } = group;
+        greedy_quads_for_face::<_, _, Merger>(
+            voxels,
+            interior,
+            visited,
+            face,
+            |quad, _strides, _voxel| quads.push(quad),
+        );
+    }
+}
+
+/// Like `greedy_quads`, but also computes the ambient occlusion at each corner of each quad, using the classic technique of
+/// darkening a corner for each of the 3 opaque voxels that surround it (see the [0fps
+/// article](https://0fps.net/2013/07/03/ambient-occlusion-for-minecraft-like-worlds/)). The values are written to
+/// `QuadGroup::ambient_occlusion`.
+///
+/// Quads will only merge faces that have identical ambient occlusion, so the values can be interpolated across each quad
+/// without any visible errors. Use `OrientedCubeFace::quad_mesh_indices_with_ambient_occlusion` to triangulate the quads.
+pub fn greedy_quads_with_ambient_occlusion<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    output: &mut GreedyQuadsBuffer,
+) where
+    A: IndexedArray<[i32; 3]>
+        + ForEach<[i32; 3], (Point3i, Stride), Item = T>
+        + Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque + MergeVoxel,
+{
+    trace_span!(
+        "greedy_quads_with_ambient_occlusion",
+        num_points = extent.num_points()
+    );
+
     output.reset(*extent);
     let GreedyQuadsBuffer {
         visited,
@@ -155,15 +212,304 @@ pub fn greedy_quads_with_merge_strategy<A, T, Merger>(
     let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
 
     for group in quad_groups.iter_mut() {
-        greedy_quads_for_group::<_, _, Merger>(voxels, interior, visited, group);
+        let QuadGroup {
+            quads,
+            ambient_occlusion,
+            face,
+        } = group;
+        greedy_quads_for_face::<_, _, AmbientOcclusionVoxelMerger<T>>(
+            voxels,
+            interior,
+            visited,
+            face,
+            |quad, (min_stride, face_strides), _voxel| {
+                quads.push(quad);
+                ambient_occlusion.push(face_ambient_occlusion(voxels, min_stride, face_strides));
+            },
+        );
+    }
+}
+
+/// Contains the output from the `greedy_quads_with_materials` algorithm. This is like a `GreedyQuadsBuffer`, but the quads are
+/// grouped by material as well as by cube face.
+///
+/// This buffer can be reused between multiple calls of `greedy_quads_with_materials` in order to avoid reallocations.
+pub struct MaterialQuadsBuffer<M> {
+    /// For each material, one group of quads per cube face, in the same order as the `faces` given on construction.
+    ///
+    /// Groups are not removed on `reset`, so a material that no longer appears in the voxels will have empty groups.
+    pub material_groups: SmallKeyHashMap<M, [QuadGroup; 6]>,
+
+    faces: [OrientedCubeFace; 6],
+    visited: Array3x1<bool>,
+}
+
+impl<M> MaterialQuadsBuffer<M> {
+    /// `faces` is usually the `faces` of a `QuadCoordinateConfig`, like `RIGHT_HANDED_Y_UP_CONFIG`.
+    pub fn new(extent: Extent3i, faces: [OrientedCubeFace; 6]) -> Self {
+        Self {
+            material_groups: SmallKeyHashMap::default(),
+            faces,
+            visited: Array3x1::fill(extent, false),
+        }
+    }
+
+    pub fn reset(&mut self, extent: Extent3i) {
+        for groups in self.material_groups.values_mut() {
+            for group in groups.iter_mut() {
+                group.quads.clear();
+                group.ambient_occlusion.clear();
+            }
+        }
+
+        if extent.shape != self.visited.extent().shape {
+            self.visited = Array3x1::fill(extent, false);
+        }
+        self.visited.set_minimum(extent.minimum);
     }
+
+    /// Returns the total count of quads across all materials and groups.
+    pub fn num_quads(&self) -> usize {
+        let mut sum = 0;
+        for groups in self.material_groups.values() {
+            for group in groups.iter() {
+                sum += group.quads.len();
+            }
+        }
+
+        sum
+    }
+}
+
+/// Like `greedy_quads`, but the quads are also split by the `material` of their voxels, producing one `QuadGroup` per material
+/// per cube face. This is useful for building a separate mesh for each material.
+///
+/// Quads will never merge voxels with different materials, even if they have the same "merge value".
+pub fn greedy_quads_with_materials<A, T, M>(
+    voxels: &A,
+    extent: &Extent3i,
+    material: impl Fn(&T) -> M,
+    output: &mut MaterialQuadsBuffer<M>,
+) where
+    A: IndexedArray<[i32; 3]>
+        + ForEach<[i32; 3], (Point3i, Stride), Item = T>
+        + Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque + MergeVoxel,
+    M: Copy + Eq + Hash,
+{
+    trace_span!(
+        "greedy_quads_with_materials",
+        num_points = extent.num_points()
+    );
+
+    output.reset(*extent);
+    let MaterialQuadsBuffer {
+        material_groups,
+        faces,
+        visited,
+    } = output;
+
+    let material_voxels = TransformMap::new(voxels, |voxel: T| MaterialVoxel {
+        material: material(&voxel),
+        voxel,
+    });
+
+    let interior = extent.padded(-1); // Avoid accessing out of bounds with a 3x3x3 kernel.
+
+    for (face_index, face) in faces.iter().enumerate() {
+        greedy_quads_for_face::<_, _, VoxelMerger<MaterialVoxel<T, M>>>(
+            &material_voxels,
+            interior,
+            visited,
+            face,
+            |quad, _strides, voxel| {
+                material_groups
+                    .entry(voxel.material)
+                    .or_insert_with(|| quad_groups_for_faces(*faces))[face_index]
+                    .quads
+                    .push(quad)
+            },
+        );
+    }
+}
+
+/// A voxel paired with its material, so that quads only merge voxels of the same material.
+struct MaterialVoxel<T, M> {
+    voxel: T,
+    material: M,
+}
+
+impl<T, M> IsEmpty for MaterialVoxel<T, M>
+where
+    T: IsEmpty,
+{
+    fn is_empty(&self) -> bool {
+        self.voxel.is_empty()
+    }
+}
+
+impl<T, M> IsOpaque for MaterialVoxel<T, M>
+where
+    T: IsOpaque,
+{
+    fn is_opaque(&self) -> bool {
+        self.voxel.is_opaque()
+    }
+}
+
+impl<T, M> MergeVoxel for MaterialVoxel<T, M>
+where
+    T: MergeVoxel,
+    M: Copy + Eq,
+{
+    type VoxelValue = (T::VoxelValue, M);
+
+    fn voxel_merge_value(&self) -> Self::VoxelValue {
+        (self.voxel.voxel_merge_value(), self.material)
+    }
+}
+
+/// Contains the output from `greedy_quads_across_chunks` and `greedy_quads_with_ambient_occlusion_across_chunks`. The quads
+/// of the whole extent are in `quads`, and they're also split up by the chunk that contains their minimum in `chunk_groups`.
+///
+/// This buffer can be reused between multiple calls in order to avoid reallocations.
+pub struct ChunkQuadsBuffer {
+    /// The quads for the whole extent.
+    pub quads: GreedyQuadsBuffer,
+    /// For each chunk minimum, one group of quads per cube face, in the same order as `quads.quad_groups`.
+    ///
+    /// Groups are not removed when the buffer is reused, so a chunk that no longer has any quads will have empty groups.
+    pub chunk_groups: SmallKeyHashMap<Point3i, [QuadGroup; 6]>,
+}
+
+impl ChunkQuadsBuffer {
+    pub fn new(extent: Extent3i, quad_groups: [QuadGroup; 6]) -> Self {
+        Self {
+            quads: GreedyQuadsBuffer::new(extent, quad_groups),
+            chunk_groups: SmallKeyHashMap::default(),
+        }
+    }
+
+    /// Returns the total count of quads across all chunks and groups.
+    pub fn num_quads(&self) -> usize {
+        self.quads.num_quads()
+    }
+
+    /// Copies every quad in `quads` into the groups of the chunk that contains its minimum.
+    fn split_into_chunks(&mut self, indexer: &ChunkIndexer<[i32; 3]>) {
+        let Self {
+            quads,
+            chunk_groups,
+        } = self;
+
+        for groups in chunk_groups.values_mut() {
+            for group in groups.iter_mut() {
+                group.quads.clear();
+                group.ambient_occlusion.clear();
+            }
+        }
+
+        let [g0, g1, g2, g3, g4, g5] = &quads.quad_groups;
+        let faces = [g0.face, g1.face, g2.face, g3.face, g4.face, g5.face];
+        for (face_index, group) in quads.quad_groups.iter().enumerate() {
+            for (i, quad) in group.quads.iter().enumerate() {
+                let chunk_min = indexer.min_of_chunk_containing_point(quad.minimum);
+                let chunk_group = &mut chunk_groups
+                    .entry(chunk_min)
+                    .or_insert_with(|| quad_groups_for_faces(faces))[face_index];
+                chunk_group.quads.push(*quad);
+                if let Some(ambient_occlusion) = group.ambient_occlusion.get(i) {
+                    chunk_group.ambient_occlusion.push(*ambient_occlusion);
+                }
+            }
+        }
+    }
+}
+
+/// Like `greedy_quads`, but `extent` can span many chunks, and quads are merged across the borders between them. Meshing each
+/// chunk separately leaves a seam of unmerged quads along every chunk border, which can double the triangle count there.
+///
+/// Each quad is attributed to the chunk that contains its minimum, so it can extend into the neighboring chunks. The quads of
+/// a chunk therefore depend on the whole `extent`, and when any chunk changes, all of the chunks in the same extent need to be
+/// meshed again. A good compromise is to mesh fixed, aligned groups of chunks (e.g. `2x2x2`), so that there are only seams
+/// between the groups. Like with `greedy_quads`, `extent` must be padded by `padded_greedy_quads_chunk_extent`.
+///
+/// ```
+/// use building_blocks_core::prelude::*;
+/// use building_blocks_storage::{prelude::*, ChunkIndexer};
+/// use building_blocks_mesh::*;
+///
+/// #[derive(Clone)]
+/// struct CubeVoxel(bool);
+///
+/// impl MergeVoxel for CubeVoxel {
+///     type VoxelValue = bool;
+///
+///     fn voxel_merge_value(&self) -> Self::VoxelValue { self.0 }
+/// }
+///
+/// impl IsEmpty for CubeVoxel {
+///     fn is_empty(&self) -> bool { !self.0 }
+/// }
+///
+/// impl IsOpaque for CubeVoxel {
+///     fn is_opaque(&self) -> bool { true }
+/// }
+///
+/// // A floor that spans two chunks.
+/// let indexer = ChunkIndexer::new(Point3i::fill(16));
+/// let chunks_extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([32, 16, 16]));
+/// let extent = padded_greedy_quads_chunk_extent(&chunks_extent);
+/// let voxels = Array3x1::fill_with(extent, |p| CubeVoxel(chunks_extent.contains(p) && p.y() == 0));
+///
+/// let mut buffer = ChunkQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+/// greedy_quads_across_chunks(&voxels, &extent, &indexer, &mut buffer);
+///
+/// // The top of the floor is a single quad that belongs to the first chunk.
+/// let top_quads = |chunk_min| buffer.chunk_groups.get(&chunk_min).map_or(0, |groups| groups[4].quads.len());
+/// assert_eq!(top_quads(Point3i::ZERO), 1);
+/// assert_eq!(top_quads(PointN([16, 0, 0])), 0);
+/// let quad = buffer.chunk_groups[&Point3i::ZERO][4].quads[0];
+/// assert_eq!(quad.width * quad.height, 32 * 16);
+/// ```
+pub fn greedy_quads_across_chunks<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    indexer: &ChunkIndexer<[i32; 3]>,
+    output: &mut ChunkQuadsBuffer,
+) where
+    A: IndexedArray<[i32; 3]>
+        + ForEach<[i32; 3], (Point3i, Stride), Item = T>
+        + Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque + MergeVoxel,
+{
+    greedy_quads(voxels, extent, &mut output.quads);
+    output.split_into_chunks(indexer);
 }
 
-fn greedy_quads_for_group<A, T, Merger>(
+/// Like `greedy_quads_across_chunks`, but also computes ambient occlusion like `greedy_quads_with_ambient_occlusion`. The
+/// ambient occlusion of each quad is split into the chunk groups along with the quad.
+pub fn greedy_quads_with_ambient_occlusion_across_chunks<A, T>(
+    voxels: &A,
+    extent: &Extent3i,
+    indexer: &ChunkIndexer<[i32; 3]>,
+    output: &mut ChunkQuadsBuffer,
+) where
+    A: IndexedArray<[i32; 3]>
+        + ForEach<[i32; 3], (Point3i, Stride), Item = T>
+        + Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque + MergeVoxel,
+{
+    greedy_quads_with_ambient_occlusion(voxels, extent, &mut output.quads);
+    output.split_into_chunks(indexer);
+}
+
+fn greedy_quads_for_face<A, T, Merger>(
     voxels: &A,
     interior: Extent3i,
     visited: &mut Array3x1<bool>,
-    quad_group: &mut QuadGroup,
+    face: &OrientedCubeFace,
+    mut push_quad: impl FnMut(UnorientedQuad, (Stride, &FaceStrides), &T),
 ) where
     A: IndexedArray<[i32; 3]>
         + ForEach<[i32; 3], (Point3i, Stride), Item = T>
@@ -173,18 +519,13 @@ fn greedy_quads_for_group<A, T, Merger>(
 {
     visited.reset_values(false);
 
-    let QuadGroup {
-        quads,
-        face:
-            OrientedCubeFace {
-                n_sign,
-                permutation,
-                n,
-                u,
-                v,
-                ..
-            },
-    } = quad_group;
+    let OrientedCubeFace {
+        n_sign,
+        permutation,
+        n,
+        u,
+        v,
+    } = face;
 
     let [n_axis, u_axis, v_axis] = permutation.axes();
     let i_n = n_axis.index();
@@ -247,16 +588,22 @@ fn greedy_quads_for_group<A, T, Merger>(
                 debug_assert!(quad_height >= 1);
                 debug_assert!(quad_height <= max_height);
 
-                // Mark the quad as visited.
+                // Mark the quad as visited, with the same strides as the voxels array.
                 let quad_extent =
                     Extent3i::from_min_and_shape(quad_min, *n + *u * quad_width + *v * quad_height);
-                visited.fill_extent(&quad_extent, true);
+                voxels
+                    .array_for_each(quad_extent)
+                    .for_each(|_, stride| *visited.get_mut(stride) = true);
 
-                quads.push(UnorientedQuad {
-                    minimum: quad_min,
-                    width: quad_width,
-                    height: quad_height,
-                });
+                push_quad(
+                    UnorientedQuad {
+                        minimum: quad_min,
+                        width: quad_width,
+                        height: quad_height,
+                    },
+                    (quad_min_stride, &face_strides),
+                    &quad_min_voxel,
+                );
             },
         );
 
@@ -283,8 +630,15 @@ where
         return false;
     }
 
-    let adjacent_voxel = voxels.get(voxel_stride + visibility_offset);
+    face_is_visible(voxel, &voxels.get(voxel_stride + visibility_offset))
+}
 
+/// Returns true iff the face of the non-empty `voxel` that it shares with `adjacent_voxel` is visible.
+#[inline]
+pub(crate) fn face_is_visible<T>(voxel: &T, adjacent_voxel: &T) -> bool
+where
+    T: IsEmpty + IsOpaque,
+{
     if adjacent_voxel.is_empty() {
         // Must be visible, opaque or transparent.
         return true;
@@ -300,6 +654,68 @@ where
     voxel.is_opaque()
 }
 
+/// Returns the ambient occlusion at each corner of the visible face of the voxel at `voxel_stride`, in the order given by
+/// `OrientedCubeFace::quad_corners`. Each corner is darkened by the opaque voxels that share an edge or corner with it in the
+/// layer of voxels adjacent to the face.
+#[inline]
+fn face_ambient_occlusion<A, T>(
+    voxels: &A,
+    voxel_stride: Stride,
+    face_strides: &FaceStrides,
+) -> [u8; 4]
+where
+    A: Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    let FaceStrides {
+        u_stride,
+        v_stride,
+        visibility_offset,
+        ..
+    } = *face_strides;
+
+    let occludes = |stride: Stride| {
+        let voxel = voxels.get(stride);
+
+        !voxel.is_empty() && voxel.is_opaque()
+    };
+
+    let adjacent_stride = voxel_stride + visibility_offset;
+    let ambient_occlusion_at_corner = |u_offset: Stride, v_offset: Stride| {
+        corner_ambient_occlusion(
+            occludes(adjacent_stride + u_offset),
+            occludes(adjacent_stride + v_offset),
+            || occludes(adjacent_stride + u_offset + v_offset),
+        )
+    };
+
+    let neg_u_stride = Stride(0) - u_stride;
+    let neg_v_stride = Stride(0) - v_stride;
+
+    [
+        ambient_occlusion_at_corner(neg_u_stride, neg_v_stride),
+        ambient_occlusion_at_corner(u_stride, neg_v_stride),
+        ambient_occlusion_at_corner(neg_u_stride, v_stride),
+        ambient_occlusion_at_corner(u_stride, v_stride),
+    ]
+}
+
+/// The ambient occlusion at one corner of a face, given whether the two voxels on the sides of the corner and the voxel
+/// diagonal to the corner (all in the layer adjacent to the face) are opaque.
+#[inline]
+pub(crate) fn corner_ambient_occlusion(
+    side1: bool,
+    side2: bool,
+    corner: impl FnOnce() -> bool,
+) -> u8 {
+    if side1 && side2 {
+        // The corner voxel can't be seen, so it's fully occluded.
+        return 0;
+    }
+
+    3 - (side1 as u8 + side2 as u8 + corner() as u8)
+}
+
 // ███╗   ███╗███████╗██████╗  ██████╗ ███████╗██████╗ ███████╗
 // ████╗ ████║██╔════╝██╔══██╗██╔════╝ ██╔════╝██╔══██╗██╔════╝
 // ██╔████╔██║█████╗  ██████╔╝██║  ███╗█████╗  ██████╔╝███████╗
@@ -370,7 +786,7 @@ where
     fn find_quad<A>(
         min_stride: Stride,
         min_value: &T,
-        mut max_width: i32,
+        max_width: i32,
         max_height: i32,
         face_strides: &FaceStrides,
         voxels: &A,
@@ -382,82 +798,339 @@ where
         // Greedily search for the biggest visible quad where all merge values are the same.
         let quad_value = min_value.voxel_merge_value();
 
-        // Start by finding the widest quad in the U direction.
-        let mut row_start_stride = min_stride;
-        let quad_width = Self::get_row_width(
+        find_greedy_quad(
+            min_stride,
+            max_width,
+            max_height,
+            face_strides,
+            voxels,
+            visited,
+            |_stride, voxel| voxel.voxel_merge_value().eq(&quad_value),
+        )
+    }
+}
+
+/// Like `VoxelMerger`, but faces must also have identical ambient occlusion in order to be merged.
+struct AmbientOcclusionVoxelMerger<T> {
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> MergeStrategy for AmbientOcclusionVoxelMerger<T>
+where
+    T: MergeVoxel + IsEmpty + IsOpaque,
+{
+    type Voxel = T;
+
+    fn find_quad<A>(
+        min_stride: Stride,
+        min_value: &T,
+        max_width: i32,
+        max_height: i32,
+        face_strides: &FaceStrides,
+        voxels: &A,
+        visited: &Array3x1<bool>,
+    ) -> (i32, i32)
+    where
+        A: Get<Stride, Item = T>,
+    {
+        let quad_value = min_value.voxel_merge_value();
+        let quad_ambient_occlusion = face_ambient_occlusion(voxels, min_stride, face_strides);
+
+        find_greedy_quad(
+            min_stride,
+            max_width,
+            max_height,
+            face_strides,
             voxels,
             visited,
-            &quad_value,
+            |stride, voxel| {
+                voxel.voxel_merge_value().eq(&quad_value)
+                    && face_ambient_occlusion(voxels, stride, face_strides)
+                        == quad_ambient_occlusion
+            },
+        )
+    }
+}
+
+/// Finds the biggest visible quad starting at `min_stride` where `can_merge` is true for all voxels, by first finding the
+/// widest row in the U direction, then seeing how tall we can make the quad in the V direction without changing the width.
+fn find_greedy_quad<A, T>(
+    min_stride: Stride,
+    mut max_width: i32,
+    max_height: i32,
+    face_strides: &FaceStrides,
+    voxels: &A,
+    visited: &Array3x1<bool>,
+    can_merge: impl Fn(Stride, &T) -> bool,
+) -> (i32, i32)
+where
+    A: Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    // Start by finding the widest quad in the U direction.
+    let mut row_start_stride = min_stride;
+    let quad_width = get_row_width(
+        voxels,
+        visited,
+        &can_merge,
+        face_strides.visibility_offset,
+        row_start_stride,
+        face_strides.u_stride,
+        max_width,
+    );
+
+    // Now see how tall we can make the quad in the V direction without changing the width.
+    max_width = max_width.min(quad_width);
+    row_start_stride += face_strides.v_stride;
+    let mut quad_height = 1;
+    while quad_height < max_height {
+        let row_width = get_row_width(
+            voxels,
+            visited,
+            &can_merge,
             face_strides.visibility_offset,
             row_start_stride,
             face_strides.u_stride,
             max_width,
         );
-
-        // Now see how tall we can make the quad in the V direction without changing the width.
-        max_width = max_width.min(quad_width);
+        if row_width < quad_width {
+            break;
+        }
+        quad_height += 1;
         row_start_stride += face_strides.v_stride;
-        let mut quad_height = 1;
-        while quad_height < max_height {
-            let row_width = Self::get_row_width(
-                voxels,
-                visited,
-                &quad_value,
-                face_strides.visibility_offset,
-                row_start_stride,
-                face_strides.u_stride,
-                max_width,
-            );
-            if row_width < quad_width {
-                break;
-            }
-            quad_height += 1;
-            row_start_stride += face_strides.v_stride;
+    }
+
+    (quad_width, quad_height)
+}
+
+fn get_row_width<A, T>(
+    voxels: &A,
+    visited: &Array3x1<bool>,
+    can_merge: &impl Fn(Stride, &T) -> bool,
+    visibility_offset: Stride,
+    start_stride: Stride,
+    delta_stride: Stride,
+    max_width: i32,
+) -> i32
+where
+    A: Get<Stride, Item = T>,
+    T: IsEmpty + IsOpaque,
+{
+    let mut quad_width = 0;
+    let mut row_stride = start_stride;
+    while quad_width < max_width {
+        if visited.get(row_stride) {
+            // Already have a quad for this voxel face.
+            break;
+        }
+
+        let voxel = voxels.get(row_stride);
+
+        if !face_needs_mesh(&voxel, row_stride, visibility_offset, voxels, visited) {
+            break;
         }
 
-        (quad_width, quad_height)
+        if !can_merge(row_stride, &voxel) {
+            // Voxel needs to be non-empty and match the quad merge value.
+            break;
+        }
+
+        quad_width += 1;
+        row_stride += delta_stride;
     }
+
+    quad_width
 }
 
-impl<T> VoxelMerger<T> {
-    fn get_row_width<A>(
-        voxels: &A,
-        visited: &Array3x1<bool>,
-        quad_merge_voxel_value: &T::VoxelValue,
-        visibility_offset: Stride,
-        start_stride: Stride,
-        delta_stride: Stride,
-        max_width: i32,
-    ) -> i32
-    where
-        A: Get<Stride, Item = T>,
-        T: IsEmpty + IsOpaque + MergeVoxel,
-    {
-        let mut quad_width = 0;
-        let mut row_stride = start_stride;
-        while quad_width < max_width {
-            if visited.get(row_stride) {
-                // Already have a quad for this voxel face.
-                break;
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    struct MaterialBlock(u8);
+
+    impl MergeVoxel for MaterialBlock {
+        // All solid blocks have the same merge value, so only their materials keep them apart.
+        type VoxelValue = bool;
+
+        fn voxel_merge_value(&self) -> Self::VoxelValue {
+            self.0 != 0
+        }
+    }
+
+    impl IsEmpty for MaterialBlock {
+        fn is_empty(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    impl IsOpaque for MaterialBlock {
+        fn is_opaque(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn quads_do_not_merge_across_materials() {
+        // A slab on y == 0 with material 1 where x < 4 and material 2 where x >= 4.
+        let chunk = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let slab = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 1, 8]));
+        let extent = padded_greedy_quads_chunk_extent(&chunk);
+        let voxels = Array3x1::fill_with(extent, |p: Point3i| {
+            if !slab.contains(p) {
+                MaterialBlock(0)
+            } else if p.x() < 4 {
+                MaterialBlock(1)
+            } else {
+                MaterialBlock(2)
             }
+        });
 
-            let voxel = voxels.get(row_stride);
+        let mut buffer = MaterialQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.faces);
+        greedy_quads_with_materials(&voxels, &extent, |v: &MaterialBlock| v.0, &mut buffer);
 
-            if !face_needs_mesh(&voxel, row_stride, visibility_offset, voxels, visited) {
-                break;
+        let mut materials: Vec<u8> = buffer.material_groups.keys().copied().collect();
+        materials.sort_unstable();
+        assert_eq!(materials, vec![1, 2]);
+
+        for (material, groups) in buffer.material_groups.iter() {
+            let x_range = if *material == 1 { 0..=4 } else { 4..=8 };
+            for group in groups.iter() {
+                for quad in group.quads.iter() {
+                    for corner in group.face.quad_corners(quad).iter() {
+                        assert!(x_range.contains(&corner.x()));
+                    }
+                }
             }
 
-            if !voxel.voxel_merge_value().eq(quad_merge_voxel_value) {
-                // Voxel needs to be non-empty and match the quad merge value.
-                break;
+            // Each half is a 4x1x8 box with its face against the other half hidden.
+            assert_eq!(groups.iter().map(|g| g.quads.len()).sum::<usize>(), 5);
+            let top = &groups[4];
+            assert_eq!(top.face.signed_normal(), PointN([0, 1, 0]));
+            assert_eq!(top.quads.len(), 1);
+            assert_eq!(top.quads[0].width * top.quads[0].height, 4 * 8);
+        }
+
+        // Without materials, the whole slab is a single box.
+        let mut plain = GreedyQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+        greedy_quads(&voxels, &extent, &mut plain);
+        assert_eq!(plain.num_quads(), 6);
+        assert_eq!(buffer.num_quads(), 10);
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_inside_corners() {
+        // An 8x8 floor on y == 0 with a wall along its x == 0 edge.
+        let chunk = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let floor = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 1, 8]));
+        let wall = Extent3i::from_min_and_shape(PointN([0, 1, 0]), PointN([1, 1, 8]));
+        let extent = padded_greedy_quads_chunk_extent(&chunk);
+        let voxels = Array3x1::fill_with(extent, |p: Point3i| {
+            MaterialBlock((floor.contains(p) || wall.contains(p)) as u8)
+        });
+
+        let mut buffer = GreedyQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+        greedy_quads_with_ambient_occlusion(&voxels, &extent, &mut buffer);
+
+        let up = &buffer.quad_groups[4];
+        assert_eq!(up.face.signed_normal(), PointN([0, 1, 0]));
+        assert_eq!(up.quads.len(), up.ambient_occlusion.len());
+
+        let mut num_floor_quads = 0;
+        for (quad, ambient_occlusion) in up.quads.iter().zip(up.ambient_occlusion.iter()) {
+            if quad.minimum.y() != 0 {
+                // The top of the wall.
+                assert_eq!(ambient_occlusion, &[3; 4]);
+                continue;
             }
+            num_floor_quads += 1;
 
-            quad_width += 1;
-            row_stride += delta_stride;
+            for (corner, &ao) in up
+                .face
+                .quad_corners(quad)
+                .iter()
+                .zip(ambient_occlusion.iter())
+            {
+                let expected = if corner.x() > 1 {
+                    // Open floor.
+                    3
+                } else if corner.z() == 0 || corner.z() == 8 {
+                    // Only the side of the wall is next to the corner, not the voxel diagonal to it.
+                    2
+                } else {
+                    // The inside corner between the floor and the wall.
+                    1
+                };
+                assert_eq!(ao, expected, "corner {:?} of {:?}", corner, quad);
+            }
         }
 
-        quad_width
+        // Faces with different ambient occlusion don't merge, so the floor along the wall is split up.
+        assert!(num_floor_quads > 1);
     }
-}
 
-// TODO: implement a MergeStrategy for voxels with an ambient occlusion value at each vertex
+    #[test]
+    fn quads_merge_across_chunks_and_belong_to_the_chunk_of_their_minimum() {
+        // A box filling two chunks along X.
+        let indexer = ChunkIndexer::new(Point3i::fill(16));
+        let chunks_extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([32, 16, 16]));
+        let extent = padded_greedy_quads_chunk_extent(&chunks_extent);
+        let voxels = Array3x1::fill_with(extent, |p: Point3i| {
+            MaterialBlock(chunks_extent.contains(p) as u8)
+        });
+
+        let mut buffer = ChunkQuadsBuffer::new(extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+        greedy_quads_with_ambient_occlusion_across_chunks(&voxels, &extent, &indexer, &mut buffer);
+        assert_eq!(buffer.num_quads(), 6);
+
+        let mut num_chunk_quads = 0;
+        for (chunk_min, groups) in buffer.chunk_groups.iter() {
+            let chunk = Extent3i::from_min_and_shape(*chunk_min, Point3i::fill(16));
+            for group in groups.iter() {
+                assert_eq!(group.quads.len(), group.ambient_occlusion.len());
+                for (quad, ao) in group.quads.iter().zip(group.ambient_occlusion.iter()) {
+                    assert!(chunk.contains(quad.minimum));
+                    assert_eq!(ao, &[3; 4]);
+                }
+                num_chunk_quads += group.quads.len();
+            }
+        }
+        assert_eq!(num_chunk_quads, 6);
+
+        // Only the +X face has its minimum in the second chunk.
+        for group in buffer.chunk_groups[&PointN([16, 0, 0])].iter() {
+            let expected = (group.face.signed_normal() == PointN([1, 0, 0])) as usize;
+            assert_eq!(group.quads.len(), expected);
+        }
+
+        // Meshing the chunks separately leaves the faces split at the chunk border.
+        let mut num_separate_quads = 0;
+        for chunk_min in [Point3i::ZERO, PointN([16, 0, 0])].iter() {
+            let chunk = Extent3i::from_min_and_shape(*chunk_min, Point3i::fill(16));
+            let chunk_extent = padded_greedy_quads_chunk_extent(&chunk);
+            let mut chunk_buffer =
+                GreedyQuadsBuffer::new(chunk_extent, RIGHT_HANDED_Y_UP_CONFIG.quad_groups());
+            greedy_quads(&voxels, &chunk_extent, &mut chunk_buffer);
+            num_separate_quads += chunk_buffer.num_quads();
+        }
+        assert_eq!(num_separate_quads, 10);
+
+        // Reusing the buffer clears the groups of every chunk.
+        let empty = Array3x1::fill(extent, MaterialBlock(0));
+        greedy_quads_across_chunks(&empty, &extent, &indexer, &mut buffer);
+        assert_eq!(buffer.num_quads(), 0);
+        assert_eq!(buffer.chunk_groups.len(), 2);
+        for groups in buffer.chunk_groups.values() {
+            assert!(groups
+                .iter()
+                .all(|g| g.quads.is_empty() && g.ambient_occlusion.is_empty()));
+        }
+    }
+}
@@ -64,6 +64,8 @@ pub fn triangulate_height_map<A, H>(
         + Get<Stride, Item = H>,
     H: Height,
 {
+    trace_span!("triangulate_height_map", num_points = extent.num_points());
+
     output.reset(height_map.extent().num_points());
 
     // Avoid accessing out of bounds with a 3x3x3 kernel.
@@ -128,3 +130,121 @@ pub fn triangulate_height_map<A, H>(
             .extend_from_slice(&[bl_index, tl_index, tr_index, bl_index, tr_index, br_index]);
     });
 }
+
+/// Same as `triangulate_height_map`, but also adds a "skirt" around the border of the mesh: a vertical strip of triangles that
+/// hangs `skirt_depth` below the border vertices. When adjacent tiles are meshed at different levels of detail, their border
+/// vertices don't line up, and the skirts hide the cracks between them.
+///
+/// The skirt vertices copy the normals of the border vertices above them, so the skirts are shaded like the terrain.
+pub fn triangulate_height_map_with_skirt<A, H>(
+    height_map: &A,
+    extent: &Extent2i,
+    skirt_depth: f32,
+    output: &mut HeightMapMeshBuffer,
+) where
+    A: IndexedArray<[i32; 2]>
+        + ForEach<[i32; 2], (Point2i, Stride), Item = H>
+        + Get<Stride, Item = H>,
+    H: Height,
+{
+    triangulate_height_map(height_map, extent, output);
+
+    let interior_extent = extent.padded(-1);
+    if interior_extent.shape.x() < 2 || interior_extent.shape.y() < 2 {
+        // There are no quads, so there's no border.
+        return;
+    }
+
+    // Walk the border counter-clockwise, so each skirt quad faces outward.
+    let min = interior_extent.minimum;
+    let max = interior_extent.max();
+    let border = (min.x()..max.x())
+        .map(|x| PointN([x, min.y()]))
+        .chain((min.y()..max.y()).map(|y| PointN([max.x(), y])))
+        .chain((min.x() + 1..=max.x()).rev().map(|x| PointN([x, max.y()])))
+        .chain((min.y() + 1..=max.y()).rev().map(|y| PointN([min.x(), y])));
+
+    let array_min = height_map.extent().minimum;
+    let mut first_skirt_pair = None;
+    let mut prev_skirt_pair: Option<(u32, u32)> = None;
+    for p in border {
+        let stride = height_map.stride_from_local_point(Local(p - array_min));
+        let top_index = output.stride_to_index[stride.0];
+        let [x, y, z] = output.mesh.positions[top_index as usize];
+        let normal = output.mesh.normals[top_index as usize];
+
+        let skirt_index = output.mesh.positions.len() as u32;
+        output.mesh.positions.push([x, y - skirt_depth, z]);
+        output.mesh.normals.push(normal);
+
+        if let Some(prev) = prev_skirt_pair {
+            push_skirt_quad(&mut output.mesh.indices, prev, (top_index, skirt_index));
+        } else {
+            first_skirt_pair = Some((top_index, skirt_index));
+        }
+        prev_skirt_pair = Some((top_index, skirt_index));
+    }
+    if let (Some(first), Some(last)) = (first_skirt_pair, prev_skirt_pair) {
+        push_skirt_quad(&mut output.mesh.indices, last, first);
+    }
+}
+
+/// Each pair is `(top_index, skirt_index)`, where `a` comes before `b` when walking counter-clockwise around the border.
+fn push_skirt_quad(indices: &mut Vec<u32>, a: (u32, u32), b: (u32, u32)) {
+    let (a_top, a_skirt) = a;
+    let (b_top, b_skirt) = b;
+    indices.extend_from_slice(&[a_top, b_top, b_skirt, a_top, b_skirt, a_skirt]);
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skirt_hangs_below_border() {
+        let chunk_extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(4));
+        let extent = padded_height_map_chunk_extent(&chunk_extent);
+        let height_map = Array2x1::fill(extent, 2.0f32);
+
+        let mut plain = HeightMapMeshBuffer::default();
+        triangulate_height_map(&height_map, &extent, &mut plain);
+        // A 5x5 grid of vertices makes 4x4 quads.
+        assert_eq!(plain.mesh.positions.len(), 25);
+        assert_eq!(plain.mesh.indices.len(), 16 * 6);
+
+        let skirt_depth = 1.5;
+        let mut buffer = HeightMapMeshBuffer::default();
+        triangulate_height_map_with_skirt(&height_map, &extent, skirt_depth, &mut buffer);
+
+        // One skirt vertex and one skirt quad for each of the 16 border vertices.
+        assert_eq!(buffer.mesh.positions.len(), 25 + 16);
+        assert_eq!(buffer.mesh.normals.len(), 25 + 16);
+        assert_eq!(buffer.mesh.indices.len(), (16 + 16) * 6);
+        assert_eq!(&buffer.mesh.positions[..25], &plain.mesh.positions[..]);
+
+        for &[x, y, z] in buffer.mesh.positions[25..].iter() {
+            assert_eq!(y, 2.0 - skirt_depth);
+            assert!(x == 0.0 || x == 4.0 || z == 0.0 || z == 4.0);
+            assert!(buffer.mesh.positions[..25].contains(&[x, 2.0, z]));
+        }
+
+        // Every skirt triangle faces away from the center of the tile.
+        let center = [2.0, 2.0];
+        for tri in buffer.mesh.indices[16 * 6..].chunks(3) {
+            let vertex = |i: u32| PointN(buffer.mesh.positions[i as usize]);
+            let (a, b, c) = (vertex(tri[0]), vertex(tri[1]), vertex(tri[2]));
+            let normal: Point3f = (b - a).cross(c - a);
+            let centroid: Point3f = (a + b + c) / 3.0;
+            let outward =
+                normal.x() * (centroid.x() - center[0]) + normal.z() * (centroid.z() - center[1]);
+            assert!(outward > 0.0);
+        }
+    }
+}
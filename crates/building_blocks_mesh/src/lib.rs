@@ -7,7 +7,10 @@
 //! Algorithms for generating triangle meshes from:
 //!   - height maps
 //!   - signed distance fields
-//!   - voxel occupancy grids
+//!   - voxel occupancy grids, either greedily merged or with one quad per visible face
+//!
+//! Meshes can also be simplified with `simplify_mesh`, e.g. for distant levels of detail, and triangle meshes can be turned
+//! back into voxels with `voxelize_mesh`, e.g. for importing game assets.
 //!
 //! All of the algorithms are designed to be used with a `ChunkMap`, such that each chunk will have its own mesh. In order to
 //! update the mesh for a chunk, you must copy not only the chunk, but also some adjacent points, into an array before running
@@ -80,16 +83,74 @@
 //! let mut hm_buffer = HeightMapMeshBuffer::default();
 //! triangulate_height_map(&tfm_array, &extent, &mut hm_buffer);
 //! ```
+//!
+//! The 3D meshers also respect the stride math of an `AxisOrderedArray3`, so data in another axis ordering can be meshed
+//! without copying it into an `Array` first.
+//!
+//! ```
+//! # use building_blocks_core::{prelude::*, Axis3Permutation};
+//! # use building_blocks_storage::prelude::*;
+//! # use building_blocks_mesh::*;
+//! #
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+//! let array = Array3x1::fill_with(extent, |p: Point3i| Point3f::from(p).norm() - 5.0);
+//! let mut column_major = AxisOrderedArray3x1::fill(extent, Axis3Permutation::Zyx, 0.0);
+//! copy_extent(&extent, &array, &mut column_major);
+//!
+//! let mut expected = SurfaceNetsBuffer::default();
+//! surface_nets(&array, &extent, 1.0, &mut expected);
+//! let mut buffer = SurfaceNetsBuffer::default();
+//! surface_nets(&column_major, &extent, 1.0, &mut buffer);
+//! assert_eq!(buffer.mesh.positions, expected.mesh.positions);
+//! assert_eq!(buffer.mesh.indices, expected.mesh.indices);
+//! ```
+
+/// Enters a `tracing` span until the end of the enclosing scope. This expands to nothing without the "tracing" feature.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        let _span = tracing::trace_span!($($args)*).entered();
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($args:tt)*) => {};
+}
 
+pub mod cube_faces;
+pub mod dual_contouring;
+pub mod dump;
+pub mod geomorph;
+pub mod glb;
 pub mod greedy_quads;
 pub mod height_map;
+pub mod marching_cubes;
 pub mod quad;
+pub mod simplify;
 pub mod surface_nets;
+pub mod transitions;
+pub mod voxelize;
+#[cfg(feature = "bytemuck")]
+pub mod vertex;
+
+#[cfg(feature = "bevy")]
+mod bevy_integration;
 
+pub use cube_faces::*;
+pub use dual_contouring::*;
+pub use dump::*;
+pub use geomorph::*;
+pub use glb::*;
 pub use greedy_quads::*;
 pub use height_map::*;
+pub use marching_cubes::*;
 pub use quad::*;
+pub use simplify::*;
 pub use surface_nets::*;
+pub use transitions::*;
+pub use voxelize::*;
+#[cfg(feature = "bytemuck")]
+pub use vertex::*;
 
 #[derive(Clone, Default)]
 pub struct PosNormMesh {
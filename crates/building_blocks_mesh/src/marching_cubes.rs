@@ -0,0 +1,463 @@
+//! The classic Marching Cubes isosurface extraction algorithm.
+//!
+//! Compared to `surface_nets`, Marching Cubes places triangle vertices directly on the edges of the sample lattice rather than
+//! in the dual cells, and every cube is triangulated independently from a fixed 256-case table.
+//!
+//! The table in this module resolves ambiguous cube faces by always separating the negative (interior) corners. Since that
+//! decision only depends on the 4 corners of a face, adjacent cubes always agree on how to connect their shared face. Each
+//! cube's polygons are then triangulated without any diagonals that lie in a cube face, so the resulting mesh is watertight and
+//! manifold.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_mesh::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-10), Point3i::fill(21));
+//! let mut samples = Array3x1::fill(extent, 1.0);
+//! copy_extent(&extent, &Func(|p: Point3i| p.norm() - 8.0), &mut samples);
+//!
+//! let mut buffer = MarchingCubesBuffer::default();
+//! marching_cubes(&samples, &extent, 1.0, &mut buffer);
+//! assert!(!buffer.mesh.is_empty());
+//! ```
+
+use super::{
+    surface_nets::{estimate_surface_edge_intersection, sdf_gradient},
+    PosNormMesh,
+};
+
+use building_blocks_core::{prelude::*, EDGES_3};
+use building_blocks_storage::prelude::*;
+
+/// Pads the given chunk extent with exactly the amount of space required for running the `marching_cubes` algorithm.
+///
+/// Only the maximal faces need padding, since every cube is identified by its minimal corner.
+pub fn padded_marching_cubes_chunk_extent(chunk_extent: &Extent3i) -> Extent3i {
+    chunk_extent.add_to_shape(Point3i::fill(1))
+}
+
+/// The output buffers used by `marching_cubes`. These buffers can be reused to avoid reallocating memory.
+#[derive(Default)]
+pub struct MarchingCubesBuffer {
+    /// The isosurface positions and normals. The normals are *not* normalized, since that is done most efficiently on the GPU.
+    pub mesh: PosNormMesh,
+
+    // Used to map from a lattice edge (minimal corner stride and axis) to the vertex on that edge, so that vertices are shared
+    // between adjacent cubes.
+    edge_to_index: Vec<u32>,
+}
+
+const NULL_VERTEX: u32 = std::u32::MAX;
+
+impl MarchingCubesBuffer {
+    /// Clears all of the buffers, but keeps the memory allocated for reuse.
+    pub fn reset(&mut self, array_size: usize) {
+        self.mesh.clear();
+
+        self.edge_to_index.clear();
+        self.edge_to_index.resize(3 * array_size, NULL_VERTEX);
+    }
+}
+
+/// Extracts an isosurface mesh from the [signed distance field](https://en.wikipedia.org/wiki/Signed_distance_function) `sdf`
+/// using Marching Cubes. Negative values are considered "interior" of the surface volume, and positive values are considered
+/// "exterior."
+///
+/// The set of corners sampled is exactly the set of points in `extent`. `sdf` must contain all of those points. Vertex
+/// positions are offset by half of a voxel, just like `surface_nets`, so the two algorithms produce surfaces in the same place.
+pub fn marching_cubes<A, T>(
+    sdf: &A,
+    extent: &Extent3i,
+    voxel_size: f32,
+    output: &mut MarchingCubesBuffer,
+) where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    trace_span!("marching_cubes", num_points = extent.num_points());
+
+    output.reset(sdf.extent().num_points());
+
+    // Precalculate these offsets to do faster linear indexing.
+    let mut corner_offset_strides = [Stride(0); 8];
+    let corner_offsets = Local::localize_points_array(&Point3i::CUBE_CORNER_OFFSETS);
+    sdf.strides_from_local_points(&corner_offsets, &mut corner_offset_strides);
+
+    // Avoid accessing out of bounds with a 2x2x2 kernel.
+    let iter_extent = extent.add_to_shape(Point3i::fill(-1));
+
+    let visitor = sdf.array_for_each(iter_extent);
+    visitor.for_each(|p, p_stride| {
+        let mut corner_dists = [0.0; 8];
+        let mut cube_case = 0;
+        for (i, dist) in corner_dists.iter_mut().enumerate() {
+            let d = sdf.get(p_stride + corner_offset_strides[i]).into();
+            *dist = d;
+            cube_case |= ((d < 0.0) as usize) << i;
+        }
+
+        for &edge in TRIANGLE_TABLE[cube_case]
+            .iter()
+            .take_while(|&&edge| edge != NO_EDGE)
+        {
+            let [corner1, corner2] = EDGES_3[edge as usize];
+            let axis = (corner1 ^ corner2).trailing_zeros() as usize;
+            let edge_key = 3 * (p_stride + corner_offset_strides[corner1]).0 + axis;
+
+            let mut vertex = output.edge_to_index[edge_key];
+            if vertex == NULL_VERTEX {
+                let crossing = estimate_surface_edge_intersection(
+                    corner1,
+                    corner2,
+                    corner_dists[corner1],
+                    corner_dists[corner2],
+                );
+                let position = voxel_size * (Point3f::from(p) + crossing + Point3f::fill(0.5));
+                let normal = sdf_gradient(&corner_dists, &crossing);
+
+                vertex = output.mesh.positions.len() as u32;
+                output.edge_to_index[edge_key] = vertex;
+                output.mesh.positions.push(position.0);
+                output.mesh.normals.push(normal);
+            }
+            output.mesh.indices.push(vertex);
+        }
+    });
+}
+
+const NO_EDGE: i8 = -1;
+
+/// For each of the 256 cube cases (bit `i` is set iff corner `i` is negative), the list of triangles as triples of indices into
+/// `EDGES_3`, wound counter-clockwise when viewed from the positive side. Terminated by `NO_EDGE`.
+#[rustfmt::skip]
+const TRIANGLE_TABLE: [[i8; 16]; 256] = [
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 2, 4, 1, 2, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 0, 5, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 1, 5, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 5, 4, 3, 2, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [3, 7, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 7, 0, 4, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 7, 5, 2, 4, 1, 2, 5, -1, -1, -1, -1, -1, -1, -1],
+    [6, 3, 7, 1, 3, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 3, 7, 2, 3, 6, 0, 3, 2, -1, -1, -1, -1, -1, -1, -1],
+    [1, 7, 6, 1, 4, 7, 0, 4, 1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 4, 7, 2, 4, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 9, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 9, 0, 1, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 2, 9, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 4, 3, 4, 9, 8, 1, 9, 4, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, 2, 9, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 9, 8, 9, 5, 6, 0, 5, 9, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 1, 5, 6, 2, 9, 8, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 8, 9, 5, 6, 4, 5, 9, 3, 5, 4, -1, -1, -1, -1],
+    [2, 9, 8, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 9, 0, 1, 8, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 7, 0, 4, 5, 2, 9, 8, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 8, 7, 9, 4, 5, 9, 7, 1, 9, 5, -1, -1, -1, -1],
+    [6, 3, 7, 1, 3, 6, 2, 9, 8, -1, -1, -1, -1, -1, -1, -1],
+    [8, 6, 9, 6, 3, 7, 8, 3, 6, 0, 3, 8, -1, -1, -1, -1],
+    [1, 7, 6, 1, 4, 7, 0, 4, 1, 2, 9, 8, -1, -1, -1, -1],
+    [9, 7, 6, 8, 7, 9, 4, 7, 8, -1, -1, -1, -1, -1, -1, -1],
+    [4, 8, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 8, 10, 0, 8, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 2, 8, 3, 2, 10, 1, 2, 3, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 0, 5, 2, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1],
+    [3, 8, 10, 0, 8, 3, 1, 5, 6, -1, -1, -1, -1, -1, -1, -1],
+    [6, 3, 5, 3, 8, 10, 6, 8, 3, 2, 8, 6, -1, -1, -1, -1],
+    [3, 7, 5, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 3, 7, 5, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1],
+    [0, 7, 5, 7, 8, 10, 0, 8, 7, -1, -1, -1, -1, -1, -1, -1],
+    [5, 10, 7, 10, 2, 8, 5, 2, 10, 1, 2, 5, -1, -1, -1, -1],
+    [6, 3, 7, 1, 3, 6, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1],
+    [6, 3, 7, 2, 3, 6, 0, 3, 2, 4, 8, 10, -1, -1, -1, -1],
+    [7, 8, 10, 6, 8, 7, 1, 8, 6, 0, 8, 1, -1, -1, -1, -1],
+    [7, 8, 10, 6, 8, 7, 2, 8, 6, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 10, 2, 9, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 10, 4, 1, 9, 0, 1, 4, -1, -1, -1, -1, -1, -1, -1],
+    [10, 2, 9, 3, 2, 10, 0, 2, 3, -1, -1, -1, -1, -1, -1, -1],
+    [3, 9, 10, 1, 9, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, 4, 9, 10, 2, 9, 4, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 6, 10, 5, 9, 4, 5, 10, 0, 5, 4, -1, -1, -1, -1],
+    [10, 2, 9, 3, 2, 10, 0, 2, 3, 1, 5, 6, -1, -1, -1, -1],
+    [9, 5, 6, 10, 5, 9, 3, 5, 10, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 10, 2, 9, 4, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1],
+    [4, 9, 10, 4, 1, 9, 0, 1, 4, 3, 7, 5, -1, -1, -1, -1],
+    [5, 10, 7, 10, 2, 9, 5, 2, 10, 0, 2, 5, -1, -1, -1, -1],
+    [7, 9, 10, 5, 9, 7, 1, 9, 5, -1, -1, -1, -1, -1, -1, -1],
+    [6, 3, 7, 1, 3, 6, 4, 9, 10, 2, 9, 4, -1, -1, -1, -1],
+    [0, 10, 4, 10, 6, 9, 0, 6, 10, 6, 3, 7, 0, 3, 6, -1],
+    [0, 6, 1, 6, 10, 7, 0, 10, 6, 10, 2, 9, 0, 2, 10, -1],
+    [7, 9, 10, 6, 9, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 2, 4, 1, 2, 3, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 11, 1, 5, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 11, 2, 5, 9, 0, 5, 2, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 9, 5, 11, 1, 5, 9, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 3, 11, 4, 5, 9, 4, 11, 2, 4, 9, -1, -1, -1, -1],
+    [3, 7, 5, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 3, 7, 5, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 7, 0, 4, 5, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [5, 4, 7, 5, 2, 4, 1, 2, 5, 6, 11, 9, -1, -1, -1, -1],
+    [1, 11, 9, 11, 3, 7, 1, 3, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 11, 9, 11, 3, 7, 2, 3, 11, 0, 3, 2, -1, -1, -1, -1],
+    [11, 4, 7, 9, 4, 11, 1, 4, 9, 0, 4, 1, -1, -1, -1, -1],
+    [11, 4, 7, 9, 4, 11, 2, 4, 9, -1, -1, -1, -1, -1, -1, -1],
+    [8, 6, 11, 2, 6, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 6, 8, 1, 11, 0, 1, 8, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 8, 6, 11, 2, 6, 8, -1, -1, -1, -1, -1, -1, -1],
+    [3, 8, 4, 8, 6, 11, 3, 6, 8, 1, 6, 3, -1, -1, -1, -1],
+    [2, 11, 8, 2, 5, 11, 1, 5, 2, -1, -1, -1, -1, -1, -1, -1],
+    [8, 5, 11, 0, 5, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 2, 11, 8, 2, 5, 11, 1, 5, 2, -1, -1, -1, -1],
+    [4, 11, 8, 4, 5, 11, 3, 5, 4, -1, -1, -1, -1, -1, -1, -1],
+    [8, 6, 11, 2, 6, 8, 3, 7, 5, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 6, 8, 1, 11, 0, 1, 8, 3, 7, 5, -1, -1, -1, -1],
+    [5, 4, 7, 0, 4, 5, 8, 6, 11, 2, 6, 8, -1, -1, -1, -1],
+    [1, 7, 5, 7, 8, 4, 1, 8, 7, 8, 6, 11, 1, 6, 8, -1],
+    [11, 3, 7, 8, 3, 11, 2, 3, 8, 1, 3, 2, -1, -1, -1, -1],
+    [11, 3, 7, 8, 3, 11, 0, 3, 8, -1, -1, -1, -1, -1, -1, -1],
+    [2, 11, 8, 1, 11, 2, 11, 4, 7, 1, 4, 11, 0, 4, 1, -1],
+    [8, 7, 11, 4, 7, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 8, 10, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 4, 8, 10, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [3, 8, 10, 0, 8, 3, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 2, 8, 3, 2, 10, 1, 2, 3, 6, 11, 9, -1, -1, -1, -1],
+    [9, 5, 11, 1, 5, 9, 4, 8, 10, -1, -1, -1, -1, -1, -1, -1],
+    [9, 5, 11, 2, 5, 9, 0, 5, 2, 4, 8, 10, -1, -1, -1, -1],
+    [3, 8, 10, 0, 8, 3, 9, 5, 11, 1, 5, 9, -1, -1, -1, -1],
+    [2, 11, 9, 11, 3, 5, 2, 3, 11, 3, 8, 10, 2, 8, 3, -1],
+    [3, 7, 5, 4, 8, 10, 6, 11, 9, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 3, 7, 5, 4, 8, 10, 6, 11, 9, -1, -1, -1, -1],
+    [0, 7, 5, 7, 8, 10, 0, 8, 7, 6, 11, 9, -1, -1, -1, -1],
+    [5, 10, 7, 10, 2, 8, 5, 2, 10, 1, 2, 5, 6, 11, 9, -1],
+    [1, 11, 9, 11, 3, 7, 1, 3, 11, 4, 8, 10, -1, -1, -1, -1],
+    [2, 11, 9, 11, 3, 7, 2, 3, 11, 0, 3, 2, 4, 8, 10, -1],
+    [9, 7, 11, 1, 7, 9, 7, 8, 10, 1, 8, 7, 0, 8, 1, -1],
+    [9, 7, 11, 2, 7, 9, 7, 8, 10, 2, 8, 7, -1, -1, -1, -1],
+    [2, 10, 4, 10, 6, 11, 2, 6, 10, -1, -1, -1, -1, -1, -1, -1],
+    [4, 11, 10, 11, 1, 6, 4, 1, 11, 0, 1, 4, -1, -1, -1, -1],
+    [11, 2, 6, 10, 2, 11, 3, 2, 10, 0, 2, 3, -1, -1, -1, -1],
+    [10, 6, 11, 3, 6, 10, 1, 6, 3, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 11, 4, 5, 10, 2, 5, 4, 1, 5, 2, -1, -1, -1, -1],
+    [10, 5, 11, 4, 5, 10, 0, 5, 4, -1, -1, -1, -1, -1, -1, -1],
+    [5, 2, 1, 11, 2, 5, 10, 2, 11, 3, 2, 10, 0, 2, 3, -1],
+    [10, 5, 11, 3, 5, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 10, 4, 10, 6, 11, 2, 6, 10, 3, 7, 5, -1, -1, -1, -1],
+    [4, 11, 10, 11, 1, 6, 4, 1, 11, 0, 1, 4, 3, 7, 5, -1],
+    [5, 10, 7, 11, 2, 6, 10, 2, 11, 5, 2, 10, 0, 2, 5, -1],
+    [5, 10, 7, 1, 10, 5, 10, 6, 11, 1, 6, 10, -1, -1, -1, -1],
+    [4, 11, 10, 2, 11, 4, 11, 3, 7, 2, 3, 11, 1, 3, 2, -1],
+    [4, 11, 10, 0, 11, 4, 11, 3, 7, 0, 3, 11, -1, -1, -1, -1],
+    [0, 2, 1, 7, 11, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 11, 10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 10, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [3, 2, 4, 1, 2, 3, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 0, 5, 2, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 1, 5, 6, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 5, 4, 3, 2, 4, 5, 7, 10, 11, -1, -1, -1, -1],
+    [5, 10, 11, 3, 10, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 5, 10, 11, 3, 10, 5, -1, -1, -1, -1, -1, -1, -1],
+    [11, 4, 10, 5, 4, 11, 0, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [10, 2, 4, 11, 2, 10, 5, 2, 11, 1, 2, 5, -1, -1, -1, -1],
+    [11, 3, 10, 6, 3, 11, 1, 3, 6, -1, -1, -1, -1, -1, -1, -1],
+    [11, 3, 10, 6, 3, 11, 2, 3, 6, 0, 3, 2, -1, -1, -1, -1],
+    [1, 11, 6, 11, 4, 10, 1, 4, 11, 0, 4, 1, -1, -1, -1, -1],
+    [2, 11, 6, 11, 4, 10, 2, 4, 11, -1, -1, -1, -1, -1, -1, -1],
+    [2, 9, 8, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 9, 0, 1, 8, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 2, 9, 8, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [1, 4, 3, 4, 9, 8, 1, 9, 4, 7, 10, 11, -1, -1, -1, -1],
+    [1, 5, 6, 2, 9, 8, 7, 10, 11, -1, -1, -1, -1, -1, -1, -1],
+    [0, 9, 8, 9, 5, 6, 0, 5, 9, 7, 10, 11, -1, -1, -1, -1],
+    [0, 4, 3, 1, 5, 6, 2, 9, 8, 7, 10, 11, -1, -1, -1, -1],
+    [4, 9, 8, 9, 5, 6, 4, 5, 9, 3, 5, 4, 7, 10, 11, -1],
+    [2, 9, 8, 5, 10, 11, 3, 10, 5, -1, -1, -1, -1, -1, -1, -1],
+    [8, 1, 9, 0, 1, 8, 5, 10, 11, 3, 10, 5, -1, -1, -1, -1],
+    [11, 4, 10, 5, 4, 11, 0, 4, 5, 2, 9, 8, -1, -1, -1, -1],
+    [11, 4, 10, 5, 4, 11, 4, 9, 8, 5, 9, 4, 1, 9, 5, -1],
+    [11, 3, 10, 6, 3, 11, 1, 3, 6, 2, 9, 8, -1, -1, -1, -1],
+    [8, 6, 9, 11, 3, 10, 6, 3, 11, 8, 3, 6, 0, 3, 8, -1],
+    [1, 11, 6, 11, 4, 10, 1, 4, 11, 0, 4, 1, 2, 9, 8, -1],
+    [8, 6, 9, 4, 6, 8, 6, 10, 11, 4, 10, 6, -1, -1, -1, -1],
+    [7, 8, 11, 4, 8, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 7, 8, 11, 4, 8, 7, -1, -1, -1, -1, -1, -1, -1],
+    [7, 8, 11, 3, 8, 7, 0, 8, 3, -1, -1, -1, -1, -1, -1, -1],
+    [3, 11, 7, 11, 2, 8, 3, 2, 11, 1, 2, 3, -1, -1, -1, -1],
+    [1, 5, 6, 7, 8, 11, 4, 8, 7, -1, -1, -1, -1, -1, -1, -1],
+    [2, 5, 6, 0, 5, 2, 7, 8, 11, 4, 8, 7, -1, -1, -1, -1],
+    [7, 8, 11, 3, 8, 7, 0, 8, 3, 1, 5, 6, -1, -1, -1, -1],
+    [6, 3, 5, 7, 8, 11, 3, 8, 7, 6, 8, 3, 2, 8, 6, -1],
+    [11, 4, 8, 5, 4, 11, 3, 4, 5, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 11, 4, 8, 5, 4, 11, 3, 4, 5, -1, -1, -1, -1],
+    [5, 8, 11, 0, 8, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 2, 8, 5, 2, 11, 1, 2, 5, -1, -1, -1, -1, -1, -1, -1],
+    [8, 3, 4, 11, 3, 8, 6, 3, 11, 1, 3, 6, -1, -1, -1, -1],
+    [8, 3, 4, 11, 3, 8, 6, 3, 11, 2, 3, 6, 0, 3, 2, -1],
+    [6, 8, 11, 1, 8, 6, 0, 8, 1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 8, 11, 2, 8, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 9, 11, 4, 9, 7, 2, 9, 4, -1, -1, -1, -1, -1, -1, -1],
+    [11, 1, 9, 7, 1, 11, 4, 1, 7, 0, 1, 4, -1, -1, -1, -1],
+    [3, 11, 7, 11, 2, 9, 3, 2, 11, 0, 2, 3, -1, -1, -1, -1],
+    [1, 7, 3, 7, 9, 11, 1, 9, 7, -1, -1, -1, -1, -1, -1, -1],
+    [1, 5, 6, 7, 9, 11, 4, 9, 7, 2, 9, 4, -1, -1, -1, -1],
+    [7, 9, 11, 4, 9, 7, 9, 5, 6, 4, 5, 9, 0, 5, 4, -1],
+    [3, 11, 7, 11, 2, 9, 3, 2, 11, 0, 2, 3, 1, 5, 6, -1],
+    [7, 9, 11, 3, 9, 7, 9, 5, 6, 3, 5, 9, -1, -1, -1, -1],
+    [4, 5, 3, 5, 9, 11, 4, 9, 5, 2, 9, 4, -1, -1, -1, -1],
+    [3, 11, 5, 4, 11, 3, 11, 1, 9, 4, 1, 11, 0, 1, 4, -1],
+    [11, 2, 9, 5, 2, 11, 0, 2, 5, -1, -1, -1, -1, -1, -1, -1],
+    [5, 9, 11, 1, 9, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [11, 2, 9, 2, 3, 4, 11, 3, 2, 6, 3, 11, 1, 3, 6, -1],
+    [0, 3, 4, 6, 9, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 11, 6, 0, 11, 1, 11, 2, 9, 0, 2, 11, -1, -1, -1, -1],
+    [6, 9, 11, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 7, 10, 6, 7, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 9, 7, 10, 6, 7, 9, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 9, 7, 10, 6, 7, 9, -1, -1, -1, -1, -1, -1, -1],
+    [3, 2, 4, 1, 2, 3, 9, 7, 10, 6, 7, 9, -1, -1, -1, -1],
+    [10, 5, 7, 9, 5, 10, 1, 5, 9, -1, -1, -1, -1, -1, -1, -1],
+    [10, 5, 7, 9, 5, 10, 2, 5, 9, 0, 5, 2, -1, -1, -1, -1],
+    [0, 4, 3, 10, 5, 7, 9, 5, 10, 1, 5, 9, -1, -1, -1, -1],
+    [10, 5, 7, 9, 5, 10, 5, 4, 3, 9, 4, 5, 2, 4, 9, -1],
+    [6, 10, 9, 5, 10, 6, 3, 10, 5, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 6, 10, 9, 5, 10, 6, 3, 10, 5, -1, -1, -1, -1],
+    [5, 9, 6, 9, 4, 10, 5, 4, 9, 0, 4, 5, -1, -1, -1, -1],
+    [6, 10, 9, 5, 10, 6, 10, 2, 4, 5, 2, 10, 1, 2, 5, -1],
+    [9, 3, 10, 1, 3, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 10, 9, 2, 3, 10, 0, 3, 2, -1, -1, -1, -1, -1, -1, -1],
+    [9, 4, 10, 1, 4, 9, 0, 4, 1, -1, -1, -1, -1, -1, -1, -1],
+    [9, 4, 10, 2, 4, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [10, 6, 7, 8, 6, 10, 2, 6, 8, -1, -1, -1, -1, -1, -1, -1],
+    [8, 7, 10, 7, 1, 6, 8, 1, 7, 0, 1, 8, -1, -1, -1, -1],
+    [0, 4, 3, 10, 6, 7, 8, 6, 10, 2, 6, 8, -1, -1, -1, -1],
+    [3, 8, 4, 10, 6, 7, 8, 6, 10, 3, 6, 8, 1, 6, 3, -1],
+    [2, 10, 8, 10, 5, 7, 2, 5, 10, 1, 5, 2, -1, -1, -1, -1],
+    [0, 10, 8, 10, 5, 7, 0, 5, 10, -1, -1, -1, -1, -1, -1, -1],
+    [0, 4, 3, 2, 10, 8, 10, 5, 7, 2, 5, 10, 1, 5, 2, -1],
+    [3, 8, 4, 10, 5, 7, 8, 5, 10, 3, 5, 8, -1, -1, -1, -1],
+    [3, 6, 5, 10, 6, 3, 8, 6, 10, 2, 6, 8, -1, -1, -1, -1],
+    [3, 6, 5, 10, 6, 3, 8, 6, 10, 8, 1, 6, 0, 1, 8, -1],
+    [2, 10, 8, 6, 10, 2, 5, 10, 6, 5, 4, 10, 0, 4, 5, -1],
+    [1, 6, 5, 4, 10, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [8, 3, 10, 2, 3, 8, 1, 3, 2, -1, -1, -1, -1, -1, -1, -1],
+    [8, 3, 10, 0, 3, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 10, 8, 1, 10, 2, 1, 4, 10, 0, 4, 1, -1, -1, -1, -1],
+    [4, 10, 8, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, 8, 9, 7, 8, 6, 4, 8, 7, -1, -1, -1, -1, -1, -1, -1],
+    [0, 1, 2, 6, 8, 9, 7, 8, 6, 4, 8, 7, -1, -1, -1, -1],
+    [6, 8, 9, 7, 8, 6, 3, 8, 7, 0, 8, 3, -1, -1, -1, -1],
+    [6, 8, 9, 7, 8, 6, 3, 8, 7, 3, 2, 8, 1, 2, 3, -1],
+    [9, 4, 8, 4, 5, 7, 9, 5, 4, 1, 5, 9, -1, -1, -1, -1],
+    [9, 4, 8, 4, 5, 7, 9, 5, 4, 2, 5, 9, 0, 5, 2, -1],
+    [7, 1, 5, 1, 8, 9, 7, 8, 1, 3, 8, 7, 0, 8, 3, -1],
+    [2, 8, 9, 3, 5, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [5, 9, 6, 9, 4, 8, 5, 4, 9, 3, 4, 5, -1, -1, -1, -1],
+    [0, 1, 2, 5, 9, 6, 9, 4, 8, 5, 4, 9, 3, 4, 5, -1],
+    [0, 6, 5, 6, 8, 9, 0, 8, 6, -1, -1, -1, -1, -1, -1, -1],
+    [6, 8, 9, 5, 8, 6, 5, 2, 8, 1, 2, 5, -1, -1, -1, -1],
+    [1, 8, 9, 8, 3, 4, 1, 3, 8, -1, -1, -1, -1, -1, -1, -1],
+    [0, 9, 2, 8, 3, 4, 9, 3, 8, 0, 3, 9, -1, -1, -1, -1],
+    [1, 8, 9, 0, 8, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 8, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 6, 7, 2, 6, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [7, 1, 6, 4, 1, 7, 0, 1, 4, -1, -1, -1, -1, -1, -1, -1],
+    [3, 6, 7, 3, 2, 6, 0, 2, 3, -1, -1, -1, -1, -1, -1, -1],
+    [3, 6, 7, 1, 6, 3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [4, 5, 7, 2, 5, 4, 1, 5, 2, -1, -1, -1, -1, -1, -1, -1],
+    [4, 5, 7, 0, 5, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 7, 3, 5, 2, 1, 7, 2, 5, 0, 2, 7, -1, -1, -1, -1],
+    [3, 5, 7, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 4, 3, 6, 5, 2, 6, 3, -1, -1, -1, -1, -1, -1, -1],
+    [3, 6, 5, 4, 6, 3, 4, 1, 6, 0, 1, 4, -1, -1, -1, -1],
+    [5, 2, 6, 0, 2, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [1, 6, 5, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, 3, 4, 1, 3, 2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 3, 4, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [0, 2, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+];
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn assert_closed_manifold(mesh: &PosNormMesh) {
+        assert!(!mesh.indices.is_empty());
+
+        let mut edge_counts = HashMap::new();
+        for tri in mesh.indices.chunks(3) {
+            for i in 0..3 {
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                *edge_counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        for (edge, count) in edge_counts {
+            assert_eq!(count, 2, "edge {:?} is shared by {} triangles", edge, count);
+        }
+    }
+
+    fn mesh_closed_sdf(sdf: impl Fn(Point3i) -> f32) -> PosNormMesh {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-12), Point3i::fill(25));
+        // Keep the border positive so the surface is closed.
+        let interior = extent.padded(-1);
+        let samples = Array3x1::fill_with(
+            extent,
+            |p: Point3i| {
+                if interior.contains(p) {
+                    sdf(p)
+                } else {
+                    1.0
+                }
+            },
+        );
+
+        let mut buffer = MarchingCubesBuffer::default();
+        marching_cubes(&samples, &extent, 1.0, &mut buffer);
+
+        buffer.mesh
+    }
+
+    #[test]
+    fn sphere_is_closed_manifold() {
+        assert_closed_manifold(&mesh_closed_sdf(|p| p.norm() - 9.5));
+    }
+
+    #[test]
+    fn noise_is_closed_manifold() {
+        // Random signs hit every cube case, including all of the ambiguous faces, with many different neighbors.
+        assert_closed_manifold(&mesh_closed_sdf(|p| {
+            let h = (p.x().wrapping_mul(73_856_093)
+                ^ p.y().wrapping_mul(19_349_663)
+                ^ p.z().wrapping_mul(83_492_791)) as u32;
+            let h = h.wrapping_mul(0x9E37_79B9) >> 16;
+
+            if h % 2 == 0 {
+                -0.5
+            } else {
+                0.5
+            }
+        }));
+    }
+}
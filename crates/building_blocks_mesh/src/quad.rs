@@ -55,6 +55,50 @@ impl OrientedCubeFace {
         self.quad_from_extent(&Extent3i::from_corners(corner1, corner2))
     }
 
+    /// Rotates and/or mirrors `quad` (on this face) by `transform`, returning the transformed face and quad. The `u` and `v`
+    /// axes of the new face are the transformed `u` and `v` axes (ignoring sign), so the quad keeps its width and height.
+    ///
+    /// ```
+    /// use building_blocks_core::{axis::SignedAxis3, prelude::*};
+    /// use building_blocks_mesh::*;
+    ///
+    /// let face = OrientedCubeFace::canonical(SignedAxis3::new(1, Axis3::Z));
+    /// let quad = UnorientedQuad { minimum: Point3i::ZERO, width: 3, height: 2 };
+    ///
+    /// let (new_face, new_quad) = face.transform_quad(&quad, &OrthoTransform3::rotate_90(Axis3::X));
+    /// assert_eq!(new_face.signed_normal(), PointN([0, -1, 0]));
+    /// assert_eq!(new_quad.minimum, PointN([0, 0, 0]));
+    /// assert_eq!((new_quad.width, new_quad.height), (3, 2));
+    /// ```
+    pub fn transform_quad(
+        &self,
+        quad: &UnorientedQuad,
+        transform: &OrthoTransform3,
+    ) -> (Self, UnorientedQuad) {
+        let [n_axis, u_axis, v_axis] = self.permutation.axes();
+        let n = transform.transform_signed_axis(SignedAxis3::new(self.n_sign, n_axis));
+        let images = transform.images();
+        let u = images[u_axis.index()].axis;
+        let v = images[v_axis.index()].axis;
+
+        let even = Axis3Permutation::even_with_normal_axis(n.axis);
+        let permutation = if even.axes() == [n.axis, u, v] {
+            even
+        } else {
+            Axis3Permutation::odd_with_normal_axis(n.axis)
+        };
+        let face = Self::new(n.sign, permutation);
+
+        let voxels = Extent3i::from_min_and_shape(
+            quad.minimum,
+            self.u * quad.width + self.v * quad.height + self.n,
+        );
+
+        let new_quad = face.quad_from_extent(&transform.transform_extent(&voxels));
+
+        (face, new_quad)
+    }
+
     pub fn signed_normal(&self) -> Point3i {
         self.n * self.n_sign
     }
@@ -115,6 +159,22 @@ impl OrientedCubeFace {
         quad_indices(start, self.n_sign * self.permutation.sign() > 0)
     }
 
+    /// Same as `quad_mesh_indices`, but the quad is split along the diagonal that makes the `ambient_occlusion` values
+    /// interpolate smoothly, which avoids anisotropic artifacts. `ambient_occlusion` comes from `QuadGroup::ambient_occlusion`.
+    pub fn quad_mesh_indices_with_ambient_occlusion(
+        &self,
+        start: u32,
+        ambient_occlusion: [u8; 4],
+    ) -> [u32; 6] {
+        let [ao0, ao1, ao2, ao3] = ambient_occlusion;
+        let counter_clockwise = self.n_sign * self.permutation.sign() > 0;
+        if ao0 + ao3 > ao1 + ao2 {
+            flipped_quad_indices(start, counter_clockwise)
+        } else {
+            quad_indices(start, counter_clockwise)
+        }
+    }
+
     /// Returns the UV coordinates of the 4 corners of the quad. Returns vertices in the same order as
     /// `OrientedCubeFace::quad_corners`.
     ///
@@ -124,7 +184,7 @@ impl OrientedCubeFace {
     /// wrapping coordinates, and each voxel face should show the entire texture. It also assumes a particular orientation for
     /// the texture. This should be sufficient for minecraft-style meshing.
     ///
-    /// If you need to use a texture atlas, you must calculate your own coordinates from the `Quad`.
+    /// If you need to use a texture atlas, see `OrientedCubeFace::atlas_tex_coords`.
     pub fn tex_coords(
         &self,
         u_flip_face: Axis3,
@@ -166,6 +226,30 @@ impl OrientedCubeFace {
         }
     }
 
+    /// Returns the texture coordinates of the 4 corners of the quad for the tile at `tile_index` in `atlas`. Returns vertices
+    /// in the same order as `OrientedCubeFace::quad_corners`.
+    ///
+    /// `u_flip_face` and `flip_v` have the same meaning as for `OrientedCubeFace::tex_coords`, and `flip_v` also determines
+    /// whether tile rows are counted from the top (`true`) or bottom (`false`) of the UV space, so tile `0` is always the
+    /// top-left tile of the atlas image.
+    ///
+    /// The tile usually depends on the voxel's material and this face, e.g. grass blocks might use a different tile for +Y
+    /// than for the sides. Quads from `greedy_quads_with_materials` are already grouped by material.
+    pub fn atlas_tex_coords(
+        &self,
+        u_flip_face: Axis3,
+        flip_v: bool,
+        quad: &UnorientedQuad,
+        atlas: &TextureAtlas,
+        tile_index: u32,
+    ) -> AtlasTexCoords {
+        AtlasTexCoords {
+            tile_coords: self.tex_coords(u_flip_face, flip_v, quad),
+            tile_min: atlas.tile_min_uv(tile_index, flip_v),
+            tile_size: atlas.tile_uv_size(),
+        }
+    }
+
     /// Extends `mesh` with the given `quad` that belongs to this face.
     pub fn add_quad_to_pos_norm_mesh(
         &self,
@@ -213,6 +297,105 @@ fn quad_indices(start: u32, counter_clockwise: bool) -> [u32; 6] {
     }
 }
 
+/// Same as `quad_indices`, but the triangles share the diagonal from corner 0 to corner 3 instead of from corner 1 to corner 2.
+fn flipped_quad_indices(start: u32, counter_clockwise: bool) -> [u32; 6] {
+    if counter_clockwise {
+        [start, start + 1, start + 3, start, start + 3, start + 2]
+    } else {
+        [start, start + 3, start + 1, start, start + 2, start + 3]
+    }
+}
+
+/// A texture atlas image made of equally sized tiles. Tiles are indexed in row-major order, starting from the top-left tile of
+/// the image.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TextureAtlas {
+    /// The size of the entire atlas image in pixels.
+    pub atlas_size: [u32; 2],
+    /// The size of a single tile in pixels.
+    pub tile_size: [u32; 2],
+}
+
+impl TextureAtlas {
+    pub fn new(atlas_size: [u32; 2], tile_size: [u32; 2]) -> Self {
+        debug_assert!(atlas_size[0] % tile_size[0] == 0 && atlas_size[1] % tile_size[1] == 0);
+
+        Self {
+            atlas_size,
+            tile_size,
+        }
+    }
+
+    pub fn num_columns(&self) -> u32 {
+        self.atlas_size[0] / self.tile_size[0]
+    }
+
+    pub fn num_rows(&self) -> u32 {
+        self.atlas_size[1] / self.tile_size[1]
+    }
+
+    pub fn num_tiles(&self) -> u32 {
+        self.num_columns() * self.num_rows()
+    }
+
+    /// The size of a single tile in normalized UV space.
+    pub fn tile_uv_size(&self) -> [f32; 2] {
+        [
+            self.tile_size[0] as f32 / self.atlas_size[0] as f32,
+            self.tile_size[1] as f32 / self.atlas_size[1] as f32,
+        ]
+    }
+
+    /// The minimum UV coordinates of the tile at `tile_index`. When `flip_v` is `true`, (0,0) is the top left of UV space,
+    /// otherwise it's the bottom left.
+    pub fn tile_min_uv(&self, tile_index: u32, flip_v: bool) -> [f32; 2] {
+        debug_assert!(tile_index < self.num_tiles());
+
+        let column = tile_index % self.num_columns();
+        let row = tile_index / self.num_columns();
+        let [tile_u, tile_v] = self.tile_uv_size();
+
+        let min_v = if flip_v {
+            row as f32 * tile_v
+        } else {
+            1.0 - (row + 1) as f32 * tile_v
+        };
+
+        [column as f32 * tile_u, min_v]
+    }
+}
+
+/// Texture coordinates for one quad in a `TextureAtlas`, returned by `OrientedCubeFace::atlas_tex_coords`.
+///
+/// A merged quad should repeat its tile once per voxel, which can't be done by interpolating atlas coordinates alone, since
+/// it would cover the neighboring tiles. Instead, pass all of these values to your shader as vertex attributes and compute
+/// `tile_min + fract(tile_coords) * tile_size` for each fragment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasTexCoords {
+    /// The texture coordinates of each corner, measured in tiles, so they range from 0 to the width or height of the quad.
+    pub tile_coords: [[f32; 2]; 4],
+    /// The minimum UV coordinates of the tile in the atlas.
+    pub tile_min: [f32; 2],
+    /// The size of the tile in UV space.
+    pub tile_size: [f32; 2],
+}
+
+impl AtlasTexCoords {
+    /// The atlas UV coordinates of each corner. These are only correct for quads of size 1x1, since they don't require any
+    /// repetition of the tile.
+    pub fn unit_quad_uvs(&self) -> [[f32; 2]; 4] {
+        let [min_u, min_v] = self.tile_min;
+        let [size_u, size_v] = self.tile_size;
+        let mut uvs = self.tile_coords;
+        for [u, v] in uvs.iter_mut() {
+            *u = min_u + *u * size_u;
+            *v = min_v + *v * size_v;
+        }
+
+        uvs
+    }
+}
+
 /// The minimum voxel and size of a quad, without an orientation. To get the actual corners of the quad, combine with an
 /// `OrientedCubeFace`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -234,3 +417,52 @@ impl UnorientedQuad {
         }
     }
 }
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_tile_uvs() {
+        // 8 columns and 4 rows of tiles.
+        let atlas = TextureAtlas::new([256, 128], [32, 32]);
+        assert_eq!((atlas.num_columns(), atlas.num_rows()), (8, 4));
+        assert_eq!(atlas.num_tiles(), 32);
+        assert_eq!(atlas.tile_uv_size(), [0.125, 0.25]);
+
+        // Tile 10 is in column 2 of row 1, counting rows from the top of the image.
+        assert_eq!(atlas.tile_min_uv(10, true), [0.25, 0.25]);
+        assert_eq!(atlas.tile_min_uv(10, false), [0.25, 0.5]);
+        assert_eq!(atlas.tile_min_uv(0, true), [0.0, 0.0]);
+        assert_eq!(atlas.tile_min_uv(0, false), [0.0, 0.75]);
+
+        let up = OrientedCubeFace::new(1, Axis3Permutation::Yzx);
+        let unit_quad = UnorientedQuad::from_voxel(Point3i::ZERO);
+        let coords = up.atlas_tex_coords(Axis3::X, true, &unit_quad, &atlas, 10);
+        assert_eq!(coords.tile_min, [0.25, 0.25]);
+        assert_eq!(coords.tile_size, [0.125, 0.25]);
+        assert_eq!(
+            coords.unit_quad_uvs(),
+            [[0.25, 0.5], [0.375, 0.5], [0.25, 0.25], [0.375, 0.25]]
+        );
+
+        // Merged quads repeat the tile once per voxel.
+        let quad = UnorientedQuad {
+            minimum: Point3i::ZERO,
+            width: 3,
+            height: 2,
+        };
+        let coords = up.atlas_tex_coords(Axis3::X, true, &quad, &atlas, 10);
+        assert_eq!(
+            coords.tile_coords,
+            [[0.0, 2.0], [3.0, 2.0], [0.0, 0.0], [3.0, 0.0]]
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Mesh simplification by iterative edge collapse, using the quadric error metrics (QEM) described by Garland and Heckbert in
+//! "Surface Simplification Using Quadric Error Metrics."
+//!
+//! Each vertex accumulates a quadric that measures the sum of squared distances to the planes of the triangles around it. The
+//! edge whose collapse introduces the least error is always collapsed first, and the new vertex is placed where it minimizes
+//! the combined quadric of both endpoints. This is useful for reducing the size of meshes for distant chunks, even after the
+//! voxels have been downsampled.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_mesh::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-10), Point3i::fill(21));
+//! let mut samples = Array3x1::fill(extent, 1.0);
+//! copy_extent(&extent, &Func(|p: Point3i| Point3f::from(p).norm() - 8.0), &mut samples);
+//!
+//! let mut buffer = SurfaceNetsBuffer::default();
+//! surface_nets(&samples, &extent, 1.0, &mut buffer);
+//!
+//! let num_triangles = buffer.mesh.indices.len() / 3;
+//! let mut simplified = PosNormMesh::default();
+//! simplify_mesh(
+//!     &buffer.mesh,
+//!     SimplifyMeshConfig::with_target_num_triangles(num_triangles / 4),
+//!     &mut simplified,
+//! );
+//! assert!(simplified.indices.len() < buffer.mesh.indices.len());
+//! ```
+
+use super::PosNormMesh;
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{SmallKeyHashMap, SmallKeyHashSet};
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Determines when `simplify_mesh` stops collapsing edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimplifyMeshConfig {
+    /// Stop collapsing edges once the mesh has at most this many triangles.
+    pub target_num_triangles: usize,
+    /// Never collapse an edge if the new vertex would have an error greater than this. The error is the sum of squared
+    /// distances from the new vertex to the planes of the original triangles around both endpoints.
+    pub max_error: f32,
+    /// If `true`, vertices on the boundary of the mesh (edges with only one adjacent triangle) are never moved or removed.
+    /// This keeps the boundaries of adjacent chunk meshes in agreement, so simplifying them doesn't open up any seams.
+    pub lock_boundary: bool,
+}
+
+impl SimplifyMeshConfig {
+    /// Simplify down to `target_num_triangles` regardless of the error, with a locked boundary.
+    pub fn with_target_num_triangles(target_num_triangles: usize) -> Self {
+        Self {
+            target_num_triangles,
+            max_error: std::f32::INFINITY,
+            lock_boundary: true,
+        }
+    }
+
+    /// Simplify as much as possible without exceeding `max_error`, with a locked boundary.
+    pub fn with_max_error(max_error: f32) -> Self {
+        Self {
+            target_num_triangles: 0,
+            max_error,
+            lock_boundary: true,
+        }
+    }
+}
+
+/// Simplifies `mesh` by collapsing edges in order of least quadric error until either bound in `config` is reached. The result
+/// is written to `output`, which is cleared first.
+///
+/// The normal of each new vertex is the (normalized) sum of the normals of both endpoints of the collapsed edge. Collapses that
+/// would flip a triangle or make the mesh non-manifold are skipped, so the target triangle count might not be reached.
+pub fn simplify_mesh(mesh: &PosNormMesh, config: SimplifyMeshConfig, output: &mut PosNormMesh) {
+    trace_span!("simplify_mesh", num_indices = mesh.indices.len());
+
+    output.clear();
+
+    let mut simplifier = MeshSimplifier::new(mesh, config.lock_boundary);
+    simplifier.collapse_edges(config.target_num_triangles, config.max_error as f64);
+    simplifier.write_mesh(output);
+}
+
+struct MeshSimplifier {
+    positions: Vec<Point3f>,
+    normals: Vec<Point3f>,
+    quadrics: Vec<Quadric>,
+    /// Vertices that can't be moved.
+    locked: Vec<bool>,
+    /// Incremented every time a vertex changes, so we can detect stale collapse candidates.
+    versions: Vec<u32>,
+    vertex_triangles: Vec<Vec<usize>>,
+    triangles: Vec<[u32; 3]>,
+    triangle_removed: Vec<bool>,
+    num_triangles: usize,
+    heap: BinaryHeap<CollapseCandidate>,
+}
+
+impl MeshSimplifier {
+    fn new(mesh: &PosNormMesh, lock_boundary: bool) -> Self {
+        let num_vertices = mesh.positions.len();
+        let positions: Vec<Point3f> = mesh.positions.iter().cloned().map(PointN).collect();
+        let normals = mesh.normals.iter().cloned().map(PointN).collect();
+
+        let triangles: Vec<[u32; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+
+        let mut quadrics = vec![Quadric::default(); num_vertices];
+        let mut vertex_triangles = vec![Vec::new(); num_vertices];
+        let mut edge_counts = SmallKeyHashMap::default();
+        for (t, triangle) in triangles.iter().enumerate() {
+            if let Some(plane) = triangle_plane(triangle.map_positions(&positions)) {
+                for &i in triangle.iter() {
+                    quadrics[i as usize] += plane;
+                }
+            }
+            for &i in triangle.iter() {
+                vertex_triangles[i as usize].push(t);
+            }
+            for &(a, b) in triangle_edges(triangle).iter() {
+                *edge_counts.entry(sorted_edge(a, b)).or_insert(0) += 1;
+            }
+        }
+
+        let mut locked = vec![false; num_vertices];
+        if lock_boundary {
+            for (&(a, b), &count) in edge_counts.iter() {
+                if count == 1 {
+                    locked[a as usize] = true;
+                    locked[b as usize] = true;
+                }
+            }
+        }
+
+        let num_triangles = triangles.len();
+        let mut simplifier = Self {
+            positions,
+            normals,
+            quadrics,
+            locked,
+            versions: vec![0; num_vertices],
+            vertex_triangles,
+            triangle_removed: vec![false; num_triangles],
+            triangles,
+            num_triangles,
+            heap: BinaryHeap::new(),
+        };
+
+        for &(a, b) in edge_counts.keys() {
+            simplifier.push_candidate(a, b);
+        }
+
+        simplifier
+    }
+
+    fn push_candidate(&mut self, u: u32, v: u32) {
+        let (iu, iv) = (u as usize, v as usize);
+        if self.locked[iu] && self.locked[iv] {
+            return;
+        }
+
+        let quadric = self.quadrics[iu] + self.quadrics[iv];
+        let (pu, pv) = (self.positions[iu], self.positions[iv]);
+        let target = if self.locked[iu] {
+            pu
+        } else if self.locked[iv] {
+            pv
+        } else {
+            let midpoint = (pu + pv) / 2.0;
+            let mut candidates = vec![pu, pv, midpoint];
+            if let Some(optimal) = quadric.minimizer() {
+                // Near-singular quadrics can put the minimizer very far away, which is never what we want.
+                if (optimal - midpoint).norm() <= 2.0 * (pu - pv).norm() {
+                    candidates.push(optimal);
+                }
+            }
+            candidates
+                .into_iter()
+                .min_by(|a, b| {
+                    quadric
+                        .error(*a)
+                        .partial_cmp(&quadric.error(*b))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap()
+        };
+
+        self.heap.push(CollapseCandidate {
+            error: quadric.error(target).max(0.0),
+            edge: (u, v),
+            versions: (self.versions[iu], self.versions[iv]),
+            target,
+        });
+    }
+
+    fn collapse_edges(&mut self, target_num_triangles: usize, max_error: f64) {
+        while self.num_triangles > target_num_triangles {
+            let candidate = if let Some(c) = self.heap.pop() {
+                c
+            } else {
+                break;
+            };
+            if candidate.error > max_error {
+                // Every other candidate has at least as much error.
+                break;
+            }
+
+            let (u, v) = candidate.edge;
+            if (self.versions[u as usize], self.versions[v as usize]) != candidate.versions
+                || self.vertex_triangles[u as usize].is_empty()
+                || self.vertex_triangles[v as usize].is_empty()
+            {
+                // Stale.
+                continue;
+            }
+
+            if self.can_collapse(u, v, candidate.target) {
+                self.collapse(u, v, candidate.target);
+            }
+        }
+    }
+
+    /// Returns `true` iff moving both `u` and `v` to `target` doesn't flip any triangles or pinch the mesh.
+    fn can_collapse(&self, u: u32, v: u32, target: Point3f) -> bool {
+        // Link condition: the only vertices adjacent to both endpoints must be those of the triangles on the edge.
+        let u_neighbors = self.neighbors(u);
+        let v_neighbors = self.neighbors(v);
+        let num_shared_neighbors = u_neighbors.intersection(&v_neighbors).count();
+        let num_edge_triangles = self.vertex_triangles[u as usize]
+            .iter()
+            .filter(|&&t| self.triangles[t].contains(&v))
+            .count();
+        if num_shared_neighbors != num_edge_triangles {
+            return false;
+        }
+
+        for &moved in [u, v].iter() {
+            for &t in self.vertex_triangles[moved as usize].iter() {
+                let triangle = self.triangles[t];
+                if triangle.contains(&u) && triangle.contains(&v) {
+                    // This triangle will be removed.
+                    continue;
+                }
+                let old_corners = triangle.map_positions(&self.positions);
+                let mut new_corners = old_corners;
+                for (corner, &i) in new_corners.iter_mut().zip(triangle.iter()) {
+                    if i == moved {
+                        *corner = target;
+                    }
+                }
+                let old_normal = triangle_normal(old_corners);
+                let new_normal = triangle_normal(new_corners);
+                if old_normal.dot(new_normal) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Merges `v` into `u`, which is moved to `target`.
+    fn collapse(&mut self, u: u32, v: u32, target: Point3f) {
+        let (iu, iv) = (u as usize, v as usize);
+
+        self.positions[iu] = target;
+        let normal_sum = normalized(self.normals[iu]) + normalized(self.normals[iv]);
+        self.normals[iu] = normalized(normal_sum);
+        self.quadrics[iu] = self.quadrics[iu] + self.quadrics[iv];
+        self.locked[iu] |= self.locked[iv];
+        self.versions[iu] += 1;
+        self.versions[iv] += 1;
+
+        let v_triangles = std::mem::take(&mut self.vertex_triangles[iv]);
+        for t in v_triangles.into_iter() {
+            let triangle = &mut self.triangles[t];
+            if triangle.contains(&u) {
+                // Degenerate after the collapse.
+                self.triangle_removed[t] = true;
+                self.num_triangles -= 1;
+                for &other in triangle.iter() {
+                    if other != v {
+                        self.vertex_triangles[other as usize].retain(|&ot| ot != t);
+                    }
+                }
+            } else {
+                for i in triangle.iter_mut() {
+                    if *i == v {
+                        *i = u;
+                    }
+                }
+                self.vertex_triangles[iu].push(t);
+            }
+        }
+
+        for w in self.neighbors(u).into_iter() {
+            self.push_candidate(u, w);
+        }
+    }
+
+    fn neighbors(&self, u: u32) -> SmallKeyHashSet<u32> {
+        self.vertex_triangles[u as usize]
+            .iter()
+            .flat_map(|&t| self.triangles[t].iter().cloned())
+            .filter(|&w| w != u)
+            .collect()
+    }
+
+    fn write_mesh(&self, output: &mut PosNormMesh) {
+        let mut new_index = vec![None; self.positions.len()];
+        for (triangle, _) in self
+            .triangles
+            .iter()
+            .zip(self.triangle_removed.iter())
+            .filter(|(_, removed)| !**removed)
+        {
+            for &i in triangle.iter() {
+                let index = new_index[i as usize].get_or_insert_with(|| {
+                    output.positions.push(self.positions[i as usize].0);
+                    output.normals.push(self.normals[i as usize].0);
+
+                    output.positions.len() as u32 - 1
+                });
+                output.indices.push(*index);
+            }
+        }
+    }
+}
+
+trait MapPositions {
+    fn map_positions(&self, positions: &[Point3f]) -> [Point3f; 3];
+}
+
+impl MapPositions for [u32; 3] {
+    fn map_positions(&self, positions: &[Point3f]) -> [Point3f; 3] {
+        [
+            positions[self[0] as usize],
+            positions[self[1] as usize],
+            positions[self[2] as usize],
+        ]
+    }
+}
+
+fn triangle_edges(t: &[u32; 3]) -> [(u32, u32); 3] {
+    [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]
+}
+
+fn sorted_edge(a: u32, b: u32) -> (u32, u32) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn triangle_normal([a, b, c]: [Point3f; 3]) -> Point3f {
+    (b - a).cross(c - a)
+}
+
+fn normalized(p: Point3f) -> Point3f {
+    let norm = p.norm();
+    if norm > 0.0 {
+        p / norm
+    } else {
+        p
+    }
+}
+
+/// The quadric for the plane containing `corners`, or `None` if the triangle is degenerate.
+fn triangle_plane(corners: [Point3f; 3]) -> Option<Quadric> {
+    let n = triangle_normal(corners);
+    let norm = n.norm();
+    if norm == 0.0 {
+        return None;
+    }
+    let n = n / norm;
+
+    Some(Quadric::from_plane(n, -n.dot(corners[0])))
+}
+
+/// A symmetric 4x4 matrix `Q` such that the error of point `p` is `[p, 1]^T Q [p, 1]`. Only the upper triangle is stored, in
+/// row-major order.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// The squared distance to the plane `n . p + d = 0`, where `n` is a unit vector.
+    fn from_plane(n: Point3f, d: f32) -> Self {
+        let [a, b, c] = [n.x() as f64, n.y() as f64, n.z() as f64];
+        let d = d as f64;
+
+        Self([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+
+    fn error(&self, p: Point3f) -> f64 {
+        let [xx, xy, xz, xw, yy, yz, yw, zz, zw, ww] = self.0;
+        let [x, y, z] = [p.x() as f64, p.y() as f64, p.z() as f64];
+
+        x * x * xx
+            + 2.0 * x * y * xy
+            + 2.0 * x * z * xz
+            + 2.0 * x * xw
+            + y * y * yy
+            + 2.0 * y * z * yz
+            + 2.0 * y * yw
+            + z * z * zz
+            + 2.0 * z * zw
+            + ww
+    }
+
+    /// The point with the least error, if the quadric isn't singular.
+    fn minimizer(&self) -> Option<Point3f> {
+        let [xx, xy, xz, xw, yy, yz, yw, zz, zw, _] = self.0;
+
+        // Solve the 3x3 system with Cramer's rule.
+        let det = xx * (yy * zz - yz * yz) - xy * (xy * zz - yz * xz) + xz * (xy * yz - yy * xz);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (bx, by, bz) = (-xw, -yw, -zw);
+        let x =
+            (bx * (yy * zz - yz * yz) - xy * (by * zz - yz * bz) + xz * (by * yz - yy * bz)) / det;
+        let y =
+            (xx * (by * zz - bz * yz) - bx * (xy * zz - yz * xz) + xz * (xy * bz - by * xz)) / det;
+        let z =
+            (xx * (yy * bz - yz * by) - xy * (xy * bz - by * xz) + bx * (xy * yz - yy * xz)) / det;
+
+        Some(PointN([x as f32, y as f32, z as f32]))
+    }
+}
+
+impl std::ops::Add for Quadric {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+
+        self
+    }
+}
+
+impl std::ops::AddAssign for Quadric {
+    fn add_assign(&mut self, other: Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+    }
+}
+
+struct CollapseCandidate {
+    error: f64,
+    edge: (u32, u32),
+    /// The versions of both endpoints when this candidate was created.
+    versions: (u32, u32),
+    target: Point3f,
+}
+
+impl PartialEq for CollapseCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.error == other.error
+    }
+}
+
+impl Eq for CollapseCandidate {}
+
+impl PartialOrd for CollapseCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CollapseCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the max-heap pops the least error first.
+        other
+            .error
+            .partial_cmp(&self.error)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A flat grid of `n x n` quads in the XZ plane, facing +Y.
+    fn flat_grid(n: u32) -> PosNormMesh {
+        let mut mesh = PosNormMesh::default();
+        for z in 0..=n {
+            for x in 0..=n {
+                mesh.positions.push([x as f32, 0.0, z as f32]);
+                mesh.normals.push([0.0, 1.0, 0.0]);
+            }
+        }
+        let index = |x: u32, z: u32| x + (n + 1) * z;
+        for z in 0..n {
+            for x in 0..n {
+                let (bl, br) = (index(x, z), index(x + 1, z));
+                let (tl, tr) = (index(x, z + 1), index(x + 1, z + 1));
+                mesh.indices.extend_from_slice(&[bl, tl, tr, bl, tr, br]);
+            }
+        }
+
+        mesh
+    }
+
+    #[test]
+    fn flat_grid_loses_triangles_and_keeps_boundary() {
+        let n = 8;
+        let grid = flat_grid(n);
+        let mut simplified = PosNormMesh::default();
+        simplify_mesh(
+            &grid,
+            SimplifyMeshConfig::with_max_error(1e-6),
+            &mut simplified,
+        );
+
+        // Every interior vertex can be removed without any error.
+        assert!(simplified.indices.len() < grid.indices.len());
+
+        // The boundary vertices are locked.
+        let is_boundary = |c: f32| c == 0.0 || c == n as f32;
+        for p in grid.positions.iter() {
+            if is_boundary(p[0]) || is_boundary(p[2]) {
+                assert!(simplified.positions.contains(p), "lost {:?}", p);
+            }
+        }
+
+        // The remaining triangles still face +Y and exactly cover the original square.
+        let mut area = 0.0;
+        for t in simplified.indices.chunks(3) {
+            let corner = |i: u32| PointN(simplified.positions[i as usize]);
+            let normal = triangle_normal([corner(t[0]), corner(t[1]), corner(t[2])]);
+            assert_eq!(normal.x(), 0.0);
+            assert_eq!(normal.z(), 0.0);
+            assert!(normal.y() > 0.0);
+            area += normal.y() / 2.0;
+        }
+        assert!((area - (n * n) as f32).abs() < 1e-3);
+        for p in simplified.positions.iter() {
+            assert_eq!(p[1], 0.0);
+        }
+    }
+}
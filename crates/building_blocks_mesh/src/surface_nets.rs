@@ -1,7 +1,7 @@
 use super::PosNormMesh;
 
 use building_blocks_core::{prelude::*, EDGES_3};
-use building_blocks_storage::{prelude::*, ArrayForEach};
+use building_blocks_storage::prelude::*;
 
 /// Pads the given chunk extent with exactly the amount of space required for running the `surface_nets` algorithm.
 pub fn padded_surface_nets_chunk_extent(chunk_extent: &Extent3i) -> Extent3i {
@@ -20,7 +20,7 @@ pub struct SurfaceNetsBuffer {
     pub surface_strides: Vec<Stride>,
 
     // Used to map back from voxel stride to vertex index.
-    stride_to_index: Vec<u32>,
+    pub(crate) stride_to_index: Vec<u32>,
 }
 
 impl SurfaceNetsBuffer {
@@ -67,6 +67,8 @@ pub fn surface_nets<A, T>(
     A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
     T: SignedDistance,
 {
+    trace_span!("surface_nets", num_points = extent.num_points());
+
     output.reset(sdf.extent().num_points());
 
     estimate_surface(sdf, extent, voxel_size, output);
@@ -92,7 +94,7 @@ fn estimate_surface<A, T>(
     // Avoid accessing out of bounds with a 2x2x2 kernel.
     let iter_extent = extent.add_to_shape(Point3i::fill(-1));
 
-    let visitor = ArrayForEach::new_global(*sdf.extent(), iter_extent);
+    let visitor = sdf.array_for_each(iter_extent);
     visitor.for_each(|p, p_stride| {
         // Get the corners of the cube with minimal corner p.
         let mut corner_strides = [Stride(0); 8];
@@ -117,7 +119,7 @@ fn estimate_surface<A, T>(
 //
 // This is done by estimating, for each cube edge, where the isosurface crosses the edge (if it does at all). Then the estimated
 // surface point is the average of these edge crossings.
-fn estimate_surface_in_cube<A, T>(
+pub(crate) fn estimate_surface_in_cube<A, T>(
     sdf: &A,
     voxel_size: f32,
     cube_min_corner: &Point3i,
@@ -166,7 +168,7 @@ fn centroid_of_edge_intersections(dists: &[f32; 8]) -> Point3f {
 }
 
 // Given two cube corners, find the point between them where the SDF is zero. (This might not exist).
-fn estimate_surface_edge_intersection(
+pub(crate) fn estimate_surface_edge_intersection(
     corner1: usize,
     corner2: usize,
     value1: f32,
@@ -187,7 +189,7 @@ fn estimate_surface_edge_intersection(
 ///
 /// For each dimension, there are 4 cube edges along that axis. This will do bilinear interpolation between the differences
 /// along those edges based on the position of the surface (s).
-fn sdf_gradient(dists: &[f32; 8], s: &Point3f) -> [f32; 3] {
+pub(crate) fn sdf_gradient(dists: &[f32; 8], s: &Point3f) -> [f32; 3] {
     let nx = 1.0 - s.x();
     let ny = 1.0 - s.y();
     let nz = 1.0 - s.z();
@@ -210,7 +212,7 @@ fn sdf_gradient(dists: &[f32; 8], s: &Point3f) -> [f32; 3] {
 // For every edge that crosses the isosurface, make a quad between the "centers" of the four cubes touching that surface. The
 // "centers" are actually the vertex positions found earlier. Also, make sure the triangles are facing the right way. See the
 // comments on `maybe_make_quad` to help with understanding the indexing.
-fn make_all_quads<A, T>(sdf: &A, extent: &Extent3i, output: &mut SurfaceNetsBuffer)
+pub(crate) fn make_all_quads<A, T>(sdf: &A, extent: &Extent3i, output: &mut SurfaceNetsBuffer)
 where
     A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
     T: SignedDistance,
@@ -0,0 +1,436 @@
+//! Crack-free seams between `surface_nets` meshes of different levels of detail.
+//!
+//! When two adjacent chunks are meshed at different levels of detail, their boundary vertices don't line up, which leaves
+//! cracks along the shared face. In the spirit of [Transvoxel](https://transvoxel.org/), this module fills those cracks with a
+//! "transition strip" of triangles that connects the boundary vertices of the fine mesh to the boundary vertices of the coarse
+//! mesh.
+//!
+//! For a fine chunk at LOD `n` that shares a face with a coarse chunk at LOD `n + 1`, you should:
+//!
+//!   1. mesh the fine chunk with `surface_nets_with_transitions`, where the shared face is one of the `transition_faces`
+//!   2. mesh the coarse chunk with `surface_nets_with_transitions` and twice the voxel size, where the shared face (as seen from
+//!      the coarse chunk) is one of the `transition_faces`
+//!   3. copy the `padded_transition_strip_coarse_extent` from LOD `n + 1` into an array
+//!   4. call `surface_nets_transition_strip` with the padded fine chunk and that coarse array
+//!
+//! The strip is built by dual contouring the 2:1 grid of fine and coarse cubes on either side of the shared face. Strip vertices
+//! are computed exactly as `surface_nets` computes them, so the strip is watertight with both chunk meshes. Where the fine LOD
+//! contains detail that the coarse LOD can't represent, the strip may have non-manifold edges, but it will not have cracks.
+//!
+//! This relies on the coarse samples having the same signs as the fine samples they coincide with, as is the case with
+//! `PointDownsampler`. Only a single LOD change across a face is handled; chunks that only meet along an edge or a corner are not
+//! stitched.
+
+use super::{
+    surface_nets,
+    surface_nets::{estimate_surface_in_cube, sdf_gradient},
+    PosNormMesh, SurfaceNetsBuffer,
+};
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::prelude::*;
+
+/// Runs `surface_nets` on a padded chunk, but leaves room for a transition strip on each of the `transition_faces`, which are the
+/// faces of the chunk that border a chunk at a different level of detail.
+///
+/// `extent` should be the padded chunk extent, as returned by `padded_surface_nets_chunk_extent`.
+pub fn surface_nets_with_transitions<A, T>(
+    sdf: &A,
+    extent: &Extent3i,
+    transition_faces: &[SignedAxis3],
+    voxel_size: f32,
+    output: &mut SurfaceNetsBuffer,
+) where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    // `surface_nets` already stops at the last layer of cubes on the maximal faces, but the padding on the minimal faces would
+    // overlap the transition strip.
+    let mut trimmed_extent = *extent;
+    for face in transition_faces.iter() {
+        if face.sign < 0 {
+            let unit = face.axis.get_unit_vector();
+            trimmed_extent = Extent3i::from_min_and_shape(
+                trimmed_extent.minimum + unit,
+                trimmed_extent.shape - unit,
+            );
+        }
+    }
+
+    surface_nets(sdf, &trimmed_extent, voxel_size, output);
+}
+
+/// Returns the extent, in the coordinates of the coarse LOD, that must be copied from the coarse LOD in order to mesh the
+/// transition strip on `face` of the fine chunk at `fine_chunk_extent`.
+pub fn padded_transition_strip_coarse_extent(
+    fine_chunk_extent: &Extent3i,
+    face: SignedAxis3,
+) -> Extent3i {
+    let a = face.axis.index();
+    let padded = (*fine_chunk_extent >> 1).padded(1);
+    let mut minimum = padded.minimum;
+    let mut shape = padded.shape;
+    let (_, _, coarse_layer) = transition_layers(fine_chunk_extent, face);
+    minimum.0[a] = coarse_layer;
+    shape.0[a] = 2;
+
+    Extent3i::from_min_and_shape(minimum, shape)
+}
+
+/// The output buffers used by `surface_nets_transition_strip`. These buffers can be reused to avoid reallocating memory.
+#[derive(Default)]
+pub struct TransitionStripBuffer {
+    /// The strip positions and normals. The normals are *not* normalized, since that is done most efficiently on the GPU.
+    pub mesh: PosNormMesh,
+
+    // Used to share vertices between strip triangles.
+    fine_cube_to_index: SmallKeyHashMap<Point3i, u32>,
+    coarse_cube_to_index: SmallKeyHashMap<Point3i, u32>,
+}
+
+impl TransitionStripBuffer {
+    /// Clears all of the buffers, but keeps the memory allocated for reuse.
+    pub fn reset(&mut self) {
+        self.mesh.clear();
+        self.fine_cube_to_index.clear();
+        self.coarse_cube_to_index.clear();
+    }
+}
+
+/// Generates the triangles that connect the `surface_nets_with_transitions` mesh of the fine chunk at `fine_chunk_extent` with
+/// the mesh of the coarser neighbor across `face`.
+///
+/// `fine_sdf` must contain the padded fine chunk extent, and `coarse_sdf` must contain the
+/// `padded_transition_strip_coarse_extent`, in the coordinates of the coarse LOD. `voxel_size` is the size of a voxel at the fine
+/// LOD.
+pub fn surface_nets_transition_strip<Af, Ac, Tf, Tc>(
+    fine_sdf: &Af,
+    coarse_sdf: &Ac,
+    fine_chunk_extent: &Extent3i,
+    face: SignedAxis3,
+    voxel_size: f32,
+    output: &mut TransitionStripBuffer,
+) where
+    Af: IndexedArray<[i32; 3]> + Get<Stride, Item = Tf>,
+    Ac: IndexedArray<[i32; 3]> + Get<Stride, Item = Tc>,
+    Tf: SignedDistance,
+    Tc: SignedDistance,
+{
+    trace_span!("surface_nets_transition_strip");
+
+    output.reset();
+
+    let mut fine_corner_strides = [Stride(0); 8];
+    let corner_offsets = Local::localize_points_array(&Point3i::CUBE_CORNER_OFFSETS);
+    fine_sdf.strides_from_local_points(&corner_offsets, &mut fine_corner_strides);
+    let mut coarse_corner_strides = [Stride(0); 8];
+    coarse_sdf.strides_from_local_points(&corner_offsets, &mut coarse_corner_strides);
+
+    // Where a coarse cube has no sign changes of its own, its strip vertex goes in the center of the face that it shares with
+    // the fine cubes.
+    let a = face.axis.index();
+    let mut coarse_face_center = Point3f::fill(0.5);
+    coarse_face_center.0[a] = if face.sign > 0 { 0.0 } else { 1.0 };
+
+    let (plane, fine_layer, coarse_layer) = transition_layers(fine_chunk_extent, face);
+    let min = fine_chunk_extent.minimum;
+    let max = fine_chunk_extent.max();
+
+    // Every fine edge lying in the shared plane is surrounded by 2 fine cubes and either 1 or 2 coarse cubes, depending on whether
+    // the edge falls on a coarse edge. We use the same ranges of edges as `surface_nets` to avoid redundant triangles between
+    // strips.
+    for &u in [(a + 1) % 3, (a + 2) % 3].iter() {
+        let w = 3 - a - u;
+        // The orientation of the polygon winding around the edge, if we visit the fine cubes before the coarse cubes.
+        let permutation_sign = if w == (a + 1) % 3 { 1 } else { -1 };
+        let winds_positive = -face.sign * permutation_sign > 0;
+
+        for pu in (min.0[u] - 1)..=max.0[u] {
+            for pw in min.0[w]..=max.0[w] {
+                let p1 = point_from_axes([(a, plane), (u, pu), (w, pw)]);
+                let p2 = point_from_axes([(a, plane), (u, pu + 1), (w, pw)]);
+                let d1 = fine_sdf.get(stride_of_point(fine_sdf, p1));
+                let d2 = fine_sdf.get(stride_of_point(fine_sdf, p2));
+                let negative_start = match (d1.is_negative(), d2.is_negative()) {
+                    (true, false) => true,
+                    (false, true) => false,
+                    _ => continue, // No face.
+                };
+
+                let mut polygon = [0; 4];
+                let mut num_vertices = 0;
+                for &cube_w in [pw - 1, pw].iter() {
+                    let cube = point_from_axes([(a, fine_layer), (u, pu), (w, cube_w)]);
+                    if let Some(v) = fine_cube_vertex(
+                        fine_sdf,
+                        voxel_size,
+                        cube,
+                        &fine_corner_strides,
+                        &mut output.fine_cube_to_index,
+                        &mut output.mesh,
+                    ) {
+                        polygon[num_vertices] = v;
+                        num_vertices += 1;
+                    }
+                }
+                if num_vertices != 2 {
+                    // Unreachable, since both fine cubes contain the edge.
+                    continue;
+                }
+                let coarse_ws = if pw % 2 == 0 {
+                    [Some(pw >> 1), Some((pw >> 1) - 1)]
+                } else {
+                    [Some(pw >> 1), None]
+                };
+                for &cube_w in coarse_ws.iter().flatten() {
+                    let cube = point_from_axes([(a, coarse_layer), (u, pu >> 1), (w, cube_w)]);
+                    polygon[num_vertices] = coarse_cube_vertex(
+                        coarse_sdf,
+                        2.0 * voxel_size,
+                        cube,
+                        &coarse_corner_strides,
+                        &coarse_face_center,
+                        &mut output.coarse_cube_to_index,
+                        &mut output.mesh,
+                    );
+                    num_vertices += 1;
+                }
+
+                let polygon = &mut polygon[..num_vertices];
+                if winds_positive != negative_start {
+                    polygon.reverse();
+                }
+                for pair in polygon[1..].windows(2) {
+                    output
+                        .mesh
+                        .indices
+                        .extend_from_slice(&[polygon[0], pair[0], pair[1]]);
+                }
+            }
+        }
+    }
+}
+
+// Returns the fine coordinate of the shared plane, the fine coordinate of the layer of fine cubes touching that plane, and the
+// coarse coordinate of the layer of coarse cubes touching that plane, all along the axis of `face`.
+fn transition_layers(fine_chunk_extent: &Extent3i, face: SignedAxis3) -> (i32, i32, i32) {
+    let a = face.axis.index();
+    if face.sign > 0 {
+        let plane = fine_chunk_extent.max().0[a] + 1;
+
+        (plane, plane - 1, plane >> 1)
+    } else {
+        let plane = fine_chunk_extent.minimum.0[a];
+
+        (plane, plane, (plane >> 1) - 1)
+    }
+}
+
+fn point_from_axes(components: [(usize, i32); 3]) -> Point3i {
+    let mut p = Point3i::ZERO;
+    for &(axis, value) in components.iter() {
+        p.0[axis] = value;
+    }
+
+    p
+}
+
+fn stride_of_point<A>(sdf: &A, p: Point3i) -> Stride
+where
+    A: IndexedArray<[i32; 3]>,
+{
+    sdf.stride_from_local_point(Local(p - sdf.extent().minimum))
+}
+
+fn fine_cube_vertex<A, T>(
+    sdf: &A,
+    voxel_size: f32,
+    cube: Point3i,
+    corner_offset_strides: &[Stride; 8],
+    cube_to_index: &mut SmallKeyHashMap<Point3i, u32>,
+    mesh: &mut PosNormMesh,
+) -> Option<u32>
+where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    if let Some(&index) = cube_to_index.get(&cube) {
+        return Some(index);
+    }
+
+    let corner_strides = cube_corner_strides(sdf, cube, corner_offset_strides);
+    let (position, normal) = estimate_surface_in_cube(sdf, voxel_size, &cube, &corner_strides)?;
+
+    Some(push_vertex(cube, position, normal, cube_to_index, mesh))
+}
+
+fn coarse_cube_vertex<A, T>(
+    sdf: &A,
+    voxel_size: f32,
+    cube: Point3i,
+    corner_offset_strides: &[Stride; 8],
+    face_center: &Point3f,
+    cube_to_index: &mut SmallKeyHashMap<Point3i, u32>,
+    mesh: &mut PosNormMesh,
+) -> u32
+where
+    A: IndexedArray<[i32; 3]> + Get<Stride, Item = T>,
+    T: SignedDistance,
+{
+    if let Some(&index) = cube_to_index.get(&cube) {
+        return index;
+    }
+
+    let corner_strides = cube_corner_strides(sdf, cube, corner_offset_strides);
+    let (position, normal) = estimate_surface_in_cube(sdf, voxel_size, &cube, &corner_strides)
+        .unwrap_or_else(|| {
+            let mut corner_dists = [0.0; 8];
+            for (dist, stride) in corner_dists.iter_mut().zip(corner_strides.iter()) {
+                *dist = sdf.get(*stride).into();
+            }
+            let position = voxel_size * (Point3f::from(cube) + *face_center + Point3f::fill(0.5));
+
+            (position.0, sdf_gradient(&corner_dists, face_center))
+        });
+
+    push_vertex(cube, position, normal, cube_to_index, mesh)
+}
+
+fn cube_corner_strides<A>(
+    sdf: &A,
+    cube: Point3i,
+    corner_offset_strides: &[Stride; 8],
+) -> [Stride; 8]
+where
+    A: IndexedArray<[i32; 3]>,
+{
+    let cube_stride = stride_of_point(sdf, cube);
+    let mut corner_strides = [Stride(0); 8];
+    for (corner, offset) in corner_strides.iter_mut().zip(corner_offset_strides.iter()) {
+        *corner = cube_stride + *offset;
+    }
+
+    corner_strides
+}
+
+fn push_vertex(
+    cube: Point3i,
+    position: [f32; 3],
+    normal: [f32; 3],
+    cube_to_index: &mut SmallKeyHashMap<Point3i, u32>,
+    mesh: &mut PosNormMesh,
+) -> u32 {
+    let index = mesh.positions.len() as u32;
+    cube_to_index.insert(cube, index);
+    mesh.positions.push(position);
+    mesh.normals.push(normal);
+
+    index
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::padded_surface_nets_chunk_extent;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn transition_strip_closes_seam_between_lods() {
+        // A flat surface crossing the +X face of a fine chunk, where it meets a coarse chunk. The coarse samples are the fine
+        // samples at even points, scaled by the coarse voxel size, so they have the same signs.
+        let fine_sdf = |p: Point3i| p.y() as f32 - 7.3;
+        let coarse_sdf = |p: Point3i| p.y() as f32 - 3.65;
+
+        let fine_chunk = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let coarse_chunk = Extent3i::from_min_and_shape(PointN([8, 0, 0]), Point3i::fill(8));
+        let face = SignedAxis3::new(1, Axis3::X);
+
+        let fine_padded = padded_surface_nets_chunk_extent(&fine_chunk);
+        let fine_samples = Array3x1::fill_with(fine_padded, fine_sdf);
+        let mut fine = SurfaceNetsBuffer::default();
+        surface_nets_with_transitions(&fine_samples, &fine_padded, &[face], 1.0, &mut fine);
+
+        let coarse_padded = padded_surface_nets_chunk_extent(&coarse_chunk);
+        let coarse_samples = Array3x1::fill_with(coarse_padded, coarse_sdf);
+        let mut coarse = SurfaceNetsBuffer::default();
+        surface_nets_with_transitions(
+            &coarse_samples,
+            &coarse_padded,
+            &[SignedAxis3::new(-1, Axis3::X)],
+            2.0,
+            &mut coarse,
+        );
+
+        let strip_extent = padded_transition_strip_coarse_extent(&fine_chunk, face);
+        assert!(strip_extent.is_subset_of(&coarse_padded));
+        let mut strip = TransitionStripBuffer::default();
+        surface_nets_transition_strip(
+            &fine_samples,
+            &coarse_samples,
+            &fine_chunk,
+            face,
+            1.0,
+            &mut strip,
+        );
+        assert!(!strip.mesh.is_empty());
+
+        // Strip vertices are exactly the boundary vertices of the two chunk meshes.
+        let key = |p: &[f32; 3]| [p[0].to_bits(), p[1].to_bits(), p[2].to_bits()];
+        for p in strip.mesh.positions.iter() {
+            assert!(
+                fine.mesh.positions.iter().any(|q| key(q) == key(p))
+                    || coarse.mesh.positions.iter().any(|q| key(q) == key(p)),
+                "strip vertex {:?} is not on either chunk mesh",
+                p
+            );
+        }
+
+        // Weld all three meshes and make sure the only open edges are on the outer border of the two chunks.
+        let mut edge_counts = HashMap::new();
+        let mut bounds_min = [f32::MAX; 3];
+        let mut bounds_max = [f32::MIN; 3];
+        for mesh in [&fine.mesh, &coarse.mesh, &strip.mesh].iter() {
+            for p in mesh.positions.iter() {
+                for i in 0..3 {
+                    bounds_min[i] = bounds_min[i].min(p[i]);
+                    bounds_max[i] = bounds_max[i].max(p[i]);
+                }
+            }
+            for tri in mesh.indices.chunks(3) {
+                for i in 0..3 {
+                    let a = key(&mesh.positions[tri[i] as usize]);
+                    let b = key(&mesh.positions[tri[(i + 1) % 3] as usize]);
+                    *edge_counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                }
+            }
+        }
+        let near_border = |p: [u32; 3]| {
+            // Only X and Z, since the surface is a plane of constant Y.
+            [0, 2].iter().any(|&i| {
+                let x = f32::from_bits(p[i]);
+
+                x - bounds_min[i] < 2.5 || bounds_max[i] - x < 2.5
+            })
+        };
+        for ((a, b), count) in edge_counts {
+            assert!(count <= 2);
+            if count == 1 {
+                assert!(
+                    near_border(a) || near_border(b),
+                    "open edge {:?} -> {:?} in the seam",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Interleaved vertex layouts that can be uploaded to the GPU directly, e.g. with `wgpu`, without copying each attribute into
+//! its own buffer. Requires the `bytemuck` feature.
+//!
+//! ```
+//! use building_blocks_mesh::*;
+//!
+//! let mesh = PosNormMesh {
+//!     positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+//!     normals: vec![[0.0, 0.0, 1.0]; 3],
+//!     indices: vec![0, 1, 2],
+//! };
+//! let interleaved = InterleavedMesh::from(&mesh);
+//! assert_eq!(interleaved.vertex_bytes().len(), 3 * std::mem::size_of::<PosNormVertex>());
+//! ```
+
+use super::{PosNormMesh, PosNormTexMesh, QuadGroup};
+
+use building_blocks_core::prelude::*;
+
+use bytemuck::{cast_slice, Pod, Zeroable};
+
+/// A vertex with a position and normal.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosNormVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+/// A vertex with a position, normal, and texture coordinates.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosNormTexVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+}
+
+/// A vertex with a position, normal, texture coordinates, and a material index, e.g. for selecting a layer of a texture array
+/// in the shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosNormTexMatVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub material: u32,
+}
+
+/// A triangle mesh with interleaved vertices of type `V`.
+#[derive(Clone, Debug, Default)]
+pub struct InterleavedMesh<V> {
+    pub vertices: Vec<V>,
+    /// All of the triangles in the mesh, wound counter-clockwise (right-hand rule).
+    pub indices: Vec<u32>,
+}
+
+impl<V> InterleavedMesh<V> {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+}
+
+impl<V> InterleavedMesh<V>
+where
+    V: Pod,
+{
+    /// The vertex buffer contents.
+    pub fn vertex_bytes(&self) -> &[u8] {
+        cast_slice(&self.vertices)
+    }
+
+    /// The index buffer contents, with `u32` indices.
+    pub fn index_bytes(&self) -> &[u8] {
+        cast_slice(&self.indices)
+    }
+}
+
+impl From<&PosNormMesh> for InterleavedMesh<PosNormVertex> {
+    fn from(mesh: &PosNormMesh) -> Self {
+        let vertices = mesh
+            .positions
+            .iter()
+            .zip(mesh.normals.iter())
+            .map(|(&position, &normal)| PosNormVertex { position, normal })
+            .collect();
+
+        Self {
+            vertices,
+            indices: mesh.indices.clone(),
+        }
+    }
+}
+
+impl From<&PosNormTexMesh> for InterleavedMesh<PosNormTexVertex> {
+    fn from(mesh: &PosNormTexMesh) -> Self {
+        let vertices = mesh
+            .positions
+            .iter()
+            .zip(mesh.normals.iter())
+            .zip(mesh.tex_coords.iter())
+            .map(|((&position, &normal), &tex_coord)| PosNormTexVertex {
+                position,
+                normal,
+                tex_coord,
+            })
+            .collect();
+
+        Self {
+            vertices,
+            indices: mesh.indices.clone(),
+        }
+    }
+}
+
+impl InterleavedMesh<PosNormTexMatVertex> {
+    /// Extends this mesh with all of the quads in `group`, which are given the same `material`. This is usually called for
+    /// each group in a `GreedyQuadsBuffer`, or for each material's groups in a `MaterialQuadsBuffer`.
+    ///
+    /// `u_flip_face` and `flip_v` are used to get the texture coordinates from `OrientedCubeFace::tex_coords`.
+    pub fn extend_with_quad_group(
+        &mut self,
+        group: &QuadGroup,
+        material: u32,
+        u_flip_face: Axis3,
+        flip_v: bool,
+        voxel_size: f32,
+    ) {
+        let face = &group.face;
+        let normals = face.quad_mesh_normals();
+        for quad in group.quads.iter() {
+            let start_index = self.vertices.len() as u32;
+            let positions = face.quad_mesh_positions(quad, voxel_size);
+            let tex_coords = face.tex_coords(u_flip_face, flip_v, quad);
+            for ((&position, &normal), &tex_coord) in
+                positions.iter().zip(normals.iter()).zip(tex_coords.iter())
+            {
+                self.vertices.push(PosNormTexMatVertex {
+                    position,
+                    normal,
+                    tex_coord,
+                    material,
+                });
+            }
+            self.indices
+                .extend_from_slice(&face.quad_mesh_indices(start_index));
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+    use std::mem::size_of;
+
+    fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn interleaved_bytes_round_trip() {
+        let mesh = PosNormTexMesh {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            normals: vec![[0.0, 0.0, 1.0], [0.0, 0.5, 0.5], [1.0, 0.0, 0.0]],
+            tex_coords: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+            indices: vec![0, 1, 2],
+        };
+        let interleaved = InterleavedMesh::from(&mesh);
+
+        // Attributes are tightly packed in declaration order.
+        let stride = size_of::<PosNormTexVertex>();
+        assert_eq!(stride, 8 * size_of::<f32>());
+        let (normal_offset, tex_coord_offset) = (12, 24);
+
+        let bytes = interleaved.vertex_bytes();
+        assert_eq!(bytes.len(), 3 * stride);
+        for (i, ((position, normal), tex_coord)) in mesh
+            .positions
+            .iter()
+            .zip(mesh.normals.iter())
+            .zip(mesh.tex_coords.iter())
+            .enumerate()
+        {
+            let vertex = i * stride;
+            for (c, (&p, &n)) in position.iter().zip(normal.iter()).enumerate() {
+                assert_eq!(read_f32(bytes, vertex + 4 * c), p);
+                assert_eq!(read_f32(bytes, vertex + normal_offset + 4 * c), n);
+            }
+            for (c, &t) in tex_coord.iter().enumerate() {
+                assert_eq!(read_f32(bytes, vertex + tex_coord_offset + 4 * c), t);
+            }
+        }
+
+        let vertices: &[PosNormTexVertex] = cast_slice(bytes);
+        assert_eq!(vertices, &interleaved.vertices[..]);
+        let indices: &[u32] = cast_slice(interleaved.index_bytes());
+        assert_eq!(indices, &mesh.indices[..]);
+
+        // The material index comes right after the texture coordinates.
+        assert_eq!(size_of::<PosNormTexMatVertex>(), stride + size_of::<u32>());
+        let vertex = PosNormTexMatVertex {
+            material: 7,
+            ..Default::default()
+        };
+        let material: [u8; 4] = bytemuck::bytes_of(&vertex)[stride..].try_into().unwrap();
+        assert_eq!(u32::from_ne_bytes(material), 7);
+    }
+}
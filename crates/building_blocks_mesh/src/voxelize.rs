@@ -0,0 +1,614 @@
+//! Voxelization of indexed triangle meshes, e.g. for importing game assets into a voxel world.
+//!
+//! The voxel at point `p` covers the box from `p * voxel_size` to `(p + 1) * voxel_size` in mesh space. A shell only contains
+//! the voxels touched by triangles, while a solid contains the voxels whose centers are inside of the mesh. Solids are found by
+//! casting rays along the Z axis and counting crossings, so the mesh must be closed (watertight), but the triangles can be
+//! wound either way.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_mesh::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! // A box from 0.25 to 9.75 on each axis.
+//! let (lo, hi) = (0.25, 9.75);
+//! let positions = [
+//!     [lo, lo, lo], [hi, lo, lo], [lo, hi, lo], [hi, hi, lo],
+//!     [lo, lo, hi], [hi, lo, hi], [lo, hi, hi], [hi, hi, hi],
+//! ];
+//! let indices = [
+//!     0, 2, 1, 1, 2, 3, // -Z
+//!     4, 5, 6, 5, 7, 6, // +Z
+//!     0, 1, 4, 1, 5, 4, // -Y
+//!     2, 6, 3, 3, 6, 7, // +Y
+//!     0, 4, 2, 2, 4, 6, // -X
+//!     1, 3, 5, 3, 7, 5, // +X
+//! ];
+//!
+//! let solid = voxelize_mesh(&positions, &indices, &VoxelizeConfig::solid(1.0));
+//! assert_eq!(solid.extent().num_points(), 1000);
+//! assert!(solid.get(PointN([5, 5, 5])));
+//!
+//! let shell = voxelize_mesh(
+//!     &positions,
+//!     &indices,
+//!     &VoxelizeConfig::shell(1.0, TriangleCoverage::Conservative),
+//! );
+//! assert!(shell.get(PointN([0, 5, 5])));
+//! assert!(!shell.get(PointN([5, 5, 5])));
+//!
+//! // Voxels can also be written straight into a `ChunkMap3`.
+//! let builder = ChunkMapBuilder3x1::new(PointN([16; 3]), 0u8);
+//! let mut map = builder.build_with_hash_map_storage();
+//! voxelize_mesh_with(&positions, &indices, &VoxelizeConfig::solid(1.0), |p| {
+//!     *map.get_mut_point(0, p) = 1;
+//! });
+//! assert_eq!(map.clone_point(0, PointN([5, 5, 5])), 1);
+//!
+//! // Or as a signed distance field, for smooth meshing.
+//! let sdf = voxelize_mesh_sdf(&positions, &indices, 1.0, 3.0);
+//! assert!(sdf.get(PointN([5, 5, 5])) < 0.0);
+//! assert!(sdf.get(PointN([-2, 5, 5])) > 0.0);
+//! ```
+
+use building_blocks_core::{bounding_extent, prelude::*};
+use building_blocks_storage::prelude::*;
+
+/// Which voxels a shell includes for each triangle.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TriangleCoverage {
+    /// Every voxel that the triangle touches. Shells are watertight, but they can be more than one voxel thick.
+    Conservative,
+    /// One voxel per line along the axis that the triangle faces most, for each line whose center passes through the
+    /// triangle. Shells are thin, and no 6-connected path can pass through the middle of a triangle, but there can be gaps at
+    /// sharp edges between triangles, and triangles that are thinner than a voxel can be missed entirely.
+    SixSeparating,
+}
+
+/// Whether to voxelize the shell or the solid interior of a mesh.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VoxelizeFill {
+    /// The voxels touched by triangles.
+    Shell(TriangleCoverage),
+    /// The voxels whose centers are inside of the mesh.
+    Solid,
+}
+
+/// Determines the output of `voxelize_mesh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelizeConfig {
+    /// The edge length of each voxel in mesh space.
+    pub voxel_size: f32,
+    pub fill: VoxelizeFill,
+}
+
+impl VoxelizeConfig {
+    pub fn shell(voxel_size: f32, coverage: TriangleCoverage) -> Self {
+        Self {
+            voxel_size,
+            fill: VoxelizeFill::Shell(coverage),
+        }
+    }
+
+    pub fn solid(voxel_size: f32) -> Self {
+        Self {
+            voxel_size,
+            fill: VoxelizeFill::Solid,
+        }
+    }
+}
+
+/// Returns the smallest extent of voxels (with edge length `voxel_size`) that contains all of the `positions`.
+pub fn mesh_voxel_extent(positions: &[[f32; 3]], voxel_size: f32) -> Extent3i {
+    let voxels = positions
+        .iter()
+        .map(|p| (PointN(*p) / voxel_size).in_voxel());
+
+    if positions.is_empty() {
+        Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::ZERO)
+    } else {
+        bounding_extent(voxels)
+    }
+}
+
+/// Voxelizes the triangle mesh made of `positions` and `indices` (3 per triangle) into an array that covers
+/// `mesh_voxel_extent(positions, config.voxel_size)`.
+pub fn voxelize_mesh(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    config: &VoxelizeConfig,
+) -> Array3x1<bool> {
+    let extent = mesh_voxel_extent(positions, config.voxel_size);
+    let mut voxels = Array3x1::fill(extent, false);
+    voxelize_mesh_with(positions, indices, config, |p| *voxels.get_mut(p) = true);
+
+    voxels
+}
+
+/// Calls `visitor` on each voxel of the voxelized mesh, without allocating an array. All of the visited points are in
+/// `mesh_voxel_extent(positions, config.voxel_size)`. For shells, `visitor` can be called more than once on the same voxel.
+pub fn voxelize_mesh_with(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    config: &VoxelizeConfig,
+    mut visitor: impl FnMut(Point3i),
+) {
+    let triangles = voxel_space_triangles(positions, indices, config.voxel_size);
+
+    match config.fill {
+        VoxelizeFill::Shell(TriangleCoverage::Conservative) => {
+            for triangle in triangles.iter() {
+                for p in triangle_voxel_extent(triangle).iter_points() {
+                    if triangle_overlaps_voxel(triangle, p) {
+                        visitor(p);
+                    }
+                }
+            }
+        }
+        VoxelizeFill::Shell(TriangleCoverage::SixSeparating) => {
+            for triangle in triangles.iter() {
+                visit_six_separating_voxels(triangle, &mut visitor);
+            }
+        }
+        VoxelizeFill::Solid => {
+            let extent = mesh_voxel_extent(positions, config.voxel_size);
+            visit_solid_voxels(&triangles, &extent, &mut visitor);
+        }
+    }
+}
+
+/// Samples the signed distance from each voxel center to the mesh, in units of voxels, where voxels inside of the mesh are
+/// negative. The extent is `mesh_voxel_extent` padded by `band` voxels, and distances are only computed exactly within `band`
+/// of the surface; farther voxels get `band` or `-band`. Dividing by `band` gives values in `[-1.0, 1.0]` that convert
+/// directly into `Sd8` or `Sd16`.
+///
+/// Like `VoxelizeFill::Solid`, the sign is only correct if the mesh is closed.
+pub fn voxelize_mesh_sdf(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    voxel_size: f32,
+    band: f32,
+) -> Array3x1<f32> {
+    let pad = band.ceil() as i32;
+    let extent = mesh_voxel_extent(positions, voxel_size).padded(pad);
+    let triangles = voxel_space_triangles(positions, indices, voxel_size);
+
+    let mut distances = Array3x1::fill(extent, band);
+    for triangle in triangles.iter() {
+        let near_extent = triangle_voxel_extent(triangle)
+            .padded(pad)
+            .intersection(&extent);
+        for p in near_extent.iter_points() {
+            let d = point_triangle_distance(voxel_center(p), triangle);
+            let distance = distances.get_mut(p);
+            *distance = distance.min(d);
+        }
+    }
+
+    visit_solid_voxels(&triangles, &extent, &mut |p| {
+        let distance = distances.get_mut(p);
+        *distance = -*distance;
+    });
+
+    distances
+}
+
+type Triangle = [Point3f; 3];
+
+fn voxel_space_triangles(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    voxel_size: f32,
+) -> Vec<Triangle> {
+    indices
+        .chunks_exact(3)
+        .map(|tri| {
+            let vertex = |i: u32| PointN(positions[i as usize]) / voxel_size;
+
+            [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])]
+        })
+        .collect()
+}
+
+fn voxel_center(p: Point3i) -> Point3f {
+    Point3f::from(p) + Point3f::fill(0.5)
+}
+
+fn triangle_voxel_extent(triangle: &Triangle) -> Extent3i {
+    bounding_extent(triangle.iter().map(|v| v.in_voxel()))
+}
+
+// The separating axis test of Akenine-Möller, "Fast 3D Triangle-Box Overlap Testing."
+fn triangle_overlaps_voxel(triangle: &Triangle, p: Point3i) -> bool {
+    let half = 0.5;
+    let center = voxel_center(p);
+    let v = [
+        triangle[0] - center,
+        triangle[1] - center,
+        triangle[2] - center,
+    ];
+
+    let separated_by = |axis: Point3f| {
+        let proj = [axis.dot(v[0]), axis.dot(v[1]), axis.dot(v[2])];
+        let min = proj[0].min(proj[1]).min(proj[2]);
+        let max = proj[0].max(proj[1]).max(proj[2]);
+        let radius = half * (axis.x().abs() + axis.y().abs() + axis.z().abs());
+
+        min > radius || max < -radius
+    };
+
+    let edges = [v[1] - v[0], v[2] - v[1], v[0] - v[2]];
+    let basis = [
+        PointN([1.0, 0.0, 0.0]),
+        PointN([0.0, 1.0, 0.0]),
+        PointN([0.0, 0.0, 1.0]),
+    ];
+
+    for &b in basis.iter() {
+        if separated_by(b) {
+            return false;
+        }
+        for &e in edges.iter() {
+            if separated_by(b.cross(e)) {
+                return false;
+            }
+        }
+    }
+
+    !separated_by(edges[0].cross(edges[1]))
+}
+
+fn visit_six_separating_voxels(triangle: &Triangle, visitor: &mut impl FnMut(Point3i)) {
+    let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+    let abs_normal = normal.map_components_unary(|c| c.abs());
+    let d = if abs_normal.x() >= abs_normal.y() && abs_normal.x() >= abs_normal.z() {
+        0
+    } else if abs_normal.y() >= abs_normal.z() {
+        1
+    } else {
+        2
+    };
+    if normal.at(d) == 0.0 {
+        // Degenerate triangle.
+        return;
+    }
+    let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+
+    let project = |p: Point3f| [p.at(u), p.at(v)];
+    let tri_2d = [
+        project(triangle[0]),
+        project(triangle[1]),
+        project(triangle[2]),
+    ];
+
+    let extent = triangle_voxel_extent(triangle);
+    let (min, max) = (extent.minimum, extent.max());
+    for pu in min.at(u)..=max.at(u) {
+        for pv in min.at(v)..=max.at(v) {
+            let center = [pu as f32 + 0.5, pv as f32 + 0.5];
+            // Include the edges so adjacent triangles don't leave gaps between them.
+            if !point_in_triangle_2d(center, &tri_2d, true) {
+                continue;
+            }
+
+            // Solve the plane equation for the coordinate along the dominant axis.
+            let w = triangle[0].at(d)
+                - (normal.at(u) * (center[0] - triangle[0].at(u))
+                    + normal.at(v) * (center[1] - triangle[0].at(v)))
+                    / normal.at(d);
+            let pw = (w.floor() as i32).max(min.at(d)).min(max.at(d));
+
+            let mut p = [0; 3];
+            p[d] = pw;
+            p[u] = pu;
+            p[v] = pv;
+            visitor(PointN(p));
+        }
+    }
+}
+
+// Casts a ray in +Z through the center of each column of `extent` and visits the voxels whose centers lie between an odd
+// number of crossings.
+fn visit_solid_voxels(
+    triangles: &[Triangle],
+    extent: &Extent3i,
+    visitor: &mut impl FnMut(Point3i),
+) {
+    let (min, max) = (extent.minimum, extent.max());
+
+    let mut crossings = Vec::new();
+    for y in min.y()..=max.y() {
+        for x in min.x()..=max.x() {
+            let ray = [x as f32 + 0.5, y as f32 + 0.5];
+
+            crossings.clear();
+            for triangle in triangles.iter() {
+                let tri_2d = [
+                    [triangle[0].x(), triangle[0].y()],
+                    [triangle[1].x(), triangle[1].y()],
+                    [triangle[2].x(), triangle[2].y()],
+                ];
+                // Exclude some of the edges so that a ray through an edge shared by two triangles only crosses one of them.
+                if let Some(z) = ray_crossing(ray, triangle, &tri_2d) {
+                    crossings.push(z);
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            for pair in crossings.chunks_exact(2) {
+                // A voxel is inside if its center is in [enter, exit).
+                let z_start = ((pair[0] - 0.5).ceil() as i32).max(min.z());
+                let z_end = ((pair[1] - 0.5).ceil() as i32).min(max.z() + 1);
+                for z in z_start..z_end {
+                    visitor(PointN([x, y, z]));
+                }
+            }
+        }
+    }
+}
+
+// Returns the Z coordinate where the vertical ray through `ray` crosses the triangle, if it does.
+fn ray_crossing(ray: [f32; 2], triangle: &Triangle, tri_2d: &[[f32; 2]; 3]) -> Option<f32> {
+    if !point_in_triangle_2d(ray, tri_2d, false) {
+        return None;
+    }
+
+    let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+    if normal.z() == 0.0 {
+        return None;
+    }
+
+    Some(
+        triangle[0].z()
+            - (normal.x() * (ray[0] - triangle[0].x()) + normal.y() * (ray[1] - triangle[0].y()))
+                / normal.z(),
+    )
+}
+
+// If `include_edges` is false, this uses the "top-left" rule, so a point on an edge shared by two triangles is only inside of
+// one of them.
+fn point_in_triangle_2d(p: [f32; 2], triangle: &[[f32; 2]; 3], include_edges: bool) -> bool {
+    let [mut a, mut b, c] = *triangle;
+    let area = cross_2d(a, b, c);
+    if area == 0.0 {
+        return false;
+    }
+    if area < 0.0 {
+        // Make it counter-clockwise.
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    [(a, b), (b, c), (c, a)].iter().all(|&(e0, e1)| {
+        let w = cross_2d(e0, e1, p);
+        if include_edges {
+            w >= 0.0
+        } else {
+            let is_top = e0[1] == e1[1] && e1[0] < e0[0];
+            let is_left = e1[1] < e0[1];
+
+            w > 0.0 || (w == 0.0 && (is_top || is_left))
+        }
+    })
+}
+
+fn cross_2d(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// From Ericson, "Real-Time Collision Detection," section 5.1.5.
+fn point_triangle_distance(p: Point3f, triangle: &Triangle) -> f32 {
+    let [a, b, c] = *triangle;
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return ap.norm();
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return bp.norm();
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let t = d1 / (d1 - d3);
+        return (p - (a + ab * t)).norm();
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return cp.norm();
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let t = d2 / (d2 - d6);
+        return (p - (a + ac * t)).norm();
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (p - (b + (c - b) * t)).norm();
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+
+    (p - (a + ab * v + ac * w)).norm()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A closed box from `lo` to `hi` on each axis, appended to `positions` and `indices`.
+    fn push_box(lo: [f32; 3], hi: [f32; 3], positions: &mut Vec<[f32; 3]>, indices: &mut Vec<u32>) {
+        let base = positions.len() as u32;
+        for &z in [lo[2], hi[2]].iter() {
+            for &y in [lo[1], hi[1]].iter() {
+                for &x in [lo[0], hi[0]].iter() {
+                    positions.push([x, y, z]);
+                }
+            }
+        }
+        let box_indices = [
+            0, 2, 1, 1, 2, 3, // -Z
+            4, 5, 6, 5, 7, 6, // +Z
+            0, 1, 4, 1, 5, 4, // -Y
+            2, 6, 3, 3, 6, 7, // +Y
+            0, 4, 2, 2, 4, 6, // -X
+            1, 3, 5, 3, 7, 5, // +X
+        ];
+        indices.extend(box_indices.iter().map(|i| base + i));
+    }
+
+    // A quad whose height rises by 1 for every 2 units of X.
+    fn ramp() -> (Vec<[f32; 3]>, Vec<u32>) {
+        let positions = vec![
+            [0.0, 0.0, 0.5],
+            [8.0, 0.0, 4.5],
+            [0.0, 8.0, 0.5],
+            [8.0, 8.0, 4.5],
+        ];
+        let indices = vec![0, 1, 2, 1, 3, 2];
+
+        (positions, indices)
+    }
+
+    #[test]
+    fn conservative_shell_of_box() {
+        let (mut positions, mut indices) = (Vec::new(), Vec::new());
+        push_box([0.25; 3], [9.75; 3], &mut positions, &mut indices);
+
+        let shell = voxelize_mesh(
+            &positions,
+            &indices,
+            &VoxelizeConfig::shell(1.0, TriangleCoverage::Conservative),
+        );
+
+        assert_eq!(
+            shell.extent(),
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(10))
+        );
+        shell.for_each(shell.extent(), |p: Point3i, v: bool| {
+            let on_border = p.0.iter().any(|&c| c == 0 || c == 9);
+            assert_eq!(v, on_border, "{:?}", p);
+        });
+    }
+
+    #[test]
+    fn six_separating_shell_is_one_voxel_per_column() {
+        let (positions, indices) = ramp();
+
+        let thin = voxelize_mesh(
+            &positions,
+            &indices,
+            &VoxelizeConfig::shell(1.0, TriangleCoverage::SixSeparating),
+        );
+        let thick = voxelize_mesh(
+            &positions,
+            &indices,
+            &VoxelizeConfig::shell(1.0, TriangleCoverage::Conservative),
+        );
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let filled: Vec<i32> = (0..=4).filter(|&z| thin.get(PointN([x, y, z]))).collect();
+                // The height at the center of the column.
+                let expected_z = (0.5 + 0.5 * (x as f32 + 0.5)).floor() as i32;
+                assert_eq!(filled, vec![expected_z], "column {:?}", [x, y]);
+            }
+        }
+
+        let mut num_thin = 0;
+        let mut num_thick = 0;
+        thin.for_each(thin.extent(), |p: Point3i, v: bool| {
+            if v {
+                num_thin += 1;
+                assert!(thick.get(p));
+            }
+            if thick.get(p) {
+                num_thick += 1;
+            }
+        });
+        assert_eq!(num_thin, 64);
+        assert!(num_thick > num_thin);
+    }
+
+    #[test]
+    fn solid_fill_of_separate_boxes() {
+        let (mut positions, mut indices) = (Vec::new(), Vec::new());
+        push_box([0.25; 3], [2.75; 3], &mut positions, &mut indices);
+        push_box(
+            [6.25, 0.25, 0.25],
+            [8.75, 2.75, 2.75],
+            &mut positions,
+            &mut indices,
+        );
+
+        let solid = voxelize_mesh(&positions, &indices, &VoxelizeConfig::solid(1.0));
+
+        // The winding doesn't matter.
+        let flipped: Vec<u32> = indices
+            .chunks_exact(3)
+            .flat_map(|tri| vec![tri[0], tri[2], tri[1]])
+            .collect();
+        let flipped_solid = voxelize_mesh(&positions, &flipped, &VoxelizeConfig::solid(1.0));
+
+        let mut num_inside = 0;
+        solid.for_each(solid.extent(), |p: Point3i, v: bool| {
+            let expected = p.x() <= 2 || p.x() >= 6;
+            assert_eq!(v, expected, "{:?}", p);
+            assert_eq!(flipped_solid.get(p), v);
+            if v {
+                num_inside += 1;
+            }
+        });
+        assert_eq!(num_inside, 54);
+    }
+
+    #[test]
+    fn sdf_of_box() {
+        let (mut positions, mut indices) = (Vec::new(), Vec::new());
+        push_box([0.25; 3], [4.75; 3], &mut positions, &mut indices);
+
+        let band = 2.0;
+        let sdf = voxelize_mesh_sdf(&positions, &indices, 1.0, band);
+
+        assert_eq!(
+            sdf.extent(),
+            &Extent3i::from_min_and_shape(Point3i::fill(-2), Point3i::fill(9))
+        );
+
+        let assert_distance = |p: [i32; 3], expected: f32| {
+            let actual = sdf.get(PointN(p));
+            assert!((actual - expected).abs() < 1e-5, "{:?}: {}", p, actual);
+        };
+        // Just inside and outside of the -X face.
+        assert_distance([0, 2, 2], -0.25);
+        assert_distance([-1, 2, 2], 0.75);
+        assert_distance([-2, 2, 2], 1.75);
+        // Nearest to a corner.
+        assert_distance([-1, -1, -1], 0.75 * 3.0f32.sqrt());
+        // Farther than the band.
+        assert_distance([2, 2, 2], -band);
+        assert_distance([-2, -2, -2], band);
+    }
+}
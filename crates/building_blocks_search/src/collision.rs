@@ -1,8 +1,12 @@
 pub mod ball;
+pub mod capsule;
 pub mod ray;
+pub mod shape;
 
 pub use ball::*;
+pub use capsule::*;
 pub use ray::*;
+pub use shape::*;
 
 use building_blocks_core::prelude::*;
 
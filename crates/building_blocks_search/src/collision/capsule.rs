@@ -0,0 +1,108 @@
+use crate::{cast_shape_at_voxels, cast_shape_at_voxels_in_extent, OctreeDbvt, VoxelShapeImpact};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use nalgebra::UnitQuaternion;
+use ncollide3d::{query::Ray, shape::Capsule};
+
+/// The impact of a capsule with an `OctreeDbvt`.
+pub type VoxelCapsuleImpact = VoxelShapeImpact;
+
+/// Casts an upright capsule along `ray` and returns the coordinates of the first voxel that intersects the capsule. Voxels are
+/// modeled as axis-aligned bounding boxes (AABBs). This is the usual query for moving a character controller.
+///
+/// The capsule is centered at `ray.origin`, and its segment is parallel to the Y axis with length `2 * half_height`. Use
+/// `cast_shape_at_voxels` for capsules with other orientations.
+///
+/// `ray.dir` is the velocity vector of the capsule, and any collisions that would occur after `max_toi` will not be
+/// considered.
+///
+/// `predicate` can be used to filter voxels by returning `false`.
+pub fn cast_capsule_at_voxels<K>(
+    octree: &OctreeDbvt<K>,
+    half_height: f32,
+    radius: f32,
+    ray: Ray<f32>,
+    max_toi: f32,
+    predicate: impl Fn(Point3i) -> bool,
+) -> Option<VoxelCapsuleImpact>
+where
+    K: Eq + Hash,
+{
+    cast_shape_at_voxels(
+        octree,
+        &Capsule::new(half_height, radius),
+        UnitQuaternion::identity(),
+        ray,
+        max_toi,
+        predicate,
+    )
+}
+
+/// Same as `cast_capsule_at_voxels`, but the solid voxels are all points `p` in `extent` where `is_solid(p)` is `true`. See
+/// `cast_shape_at_voxels_in_extent`.
+pub fn cast_capsule_at_voxels_in_extent(
+    extent: &Extent3i,
+    half_height: f32,
+    radius: f32,
+    ray: Ray<f32>,
+    max_toi: f32,
+    is_solid: impl Fn(Point3i) -> bool,
+) -> Option<VoxelCapsuleImpact> {
+    cast_shape_at_voxels_in_extent(
+        extent,
+        &Capsule::new(half_height, radius),
+        UnitQuaternion::identity(),
+        ray,
+        max_toi,
+        is_solid,
+    )
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::test_util::*;
+
+    use ncollide3d::na;
+
+    #[test]
+    fn capsule_cast_hits_wall() {
+        // A wall at x = 10 that only reaches up to the capsule's waist.
+        let wall: Vec<_> = (0..16)
+            .flat_map(|z| (0..2).map(move |y| PointN([10, y, z])))
+            .collect();
+        let bvt = bvt_with_voxels_filled(&wall);
+
+        // The capsule spans from y = 1 to y = 4.
+        let start = na::Point3::new(5.5, 2.5, 8.5);
+        let (half_height, radius) = (1.0, 0.5);
+        let ray = Ray::new(start, na::Vector3::new(1.0, 0.0, 0.0));
+
+        let result =
+            cast_capsule_at_voxels(&bvt, half_height, radius, ray, 10.0, |_| true).unwrap();
+        assert_eq!(result.point, PointN([10, 1, 8]));
+        assert!((result.impact.toi - 4.0).abs() < 1e-2);
+        assert!((result.impact.normal2.x + 1.0).abs() < 1e-2);
+
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let is_solid = |p: Point3i| p.x() == 10 && p.y() < 2;
+        let result =
+            cast_capsule_at_voxels_in_extent(&extent, half_height, radius, ray, 10.0, is_solid)
+                .unwrap();
+        assert_eq!(result.point, PointN([10, 1, 8]));
+        assert!((result.impact.toi - 4.0).abs() < 1e-2);
+
+        // A ball at the height of the capsule's upper half passes over the wall.
+        let ball_ray = Ray::new(start + na::Vector3::new(0.0, 1.0, 0.0), ray.dir);
+        assert!(crate::cast_ball_at_voxels(&bvt, radius, ball_ray, 10.0, |_| true).is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use super::impact_with_leaf_octant;
+
+use crate::{OctreeDbvt, OctreeDbvtVisitor, VoxelImpact};
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::VisitStatus;
+
+use core::hash::Hash;
+use nalgebra::{self as na, zero, Isometry3, Translation3, UnitQuaternion};
+use ncollide3d::{
+    bounding_volume::{BoundingVolume, HasBoundingVolume, AABB},
+    query::{time_of_impact, DefaultTOIDispatcher, Ray, TOIStatus, TOI},
+    shape::{Cuboid, Shape},
+};
+
+/// The impact of a swept shape with voxels. The `TOI` contains the time of impact, as well as the contact normals and witness
+/// points.
+pub type VoxelShapeImpact = VoxelImpact<TOI<f32>>;
+
+/// Sweeps `shape` along `ray` and returns the coordinates of the first voxel that intersects the shape. Voxels are modeled as
+/// axis-aligned bounding boxes (AABBs).
+///
+/// The shape is centered at `ray.origin` with the given `rotation`, and `ray.dir` is the velocity vector of the shape. Any
+/// collisions that would occur after `max_toi` will not be considered.
+///
+/// `predicate` can be used to filter voxels by returning `false`.
+pub fn cast_shape_at_voxels<K, G>(
+    octree: &OctreeDbvt<K>,
+    shape: &G,
+    rotation: UnitQuaternion<f32>,
+    ray: Ray<f32>,
+    max_toi: f32,
+    predicate: impl Fn(Point3i) -> bool,
+) -> Option<VoxelShapeImpact>
+where
+    K: Eq + Hash,
+    G: Shape<f32> + HasBoundingVolume<f32, AABB<f32>>,
+{
+    let mut visitor = VoxelShapeCast::new(shape, rotation, ray, max_toi, predicate);
+    octree.visit(&mut visitor);
+
+    visitor.earliest_impact
+}
+
+/// Same as `cast_shape_at_voxels`, but instead of an `OctreeDbvt`, the solid voxels are all points `p` in `extent` where
+/// `is_solid(p)` is `true`. This is convenient for sweeping directly against an array or chunk map, but every voxel that's
+/// near the path of the shape is tested, so it's only fast for short sweeps.
+pub fn cast_shape_at_voxels_in_extent<G>(
+    extent: &Extent3i,
+    shape: &G,
+    rotation: UnitQuaternion<f32>,
+    ray: Ray<f32>,
+    max_toi: f32,
+    is_solid: impl Fn(Point3i) -> bool,
+) -> Option<VoxelShapeImpact>
+where
+    G: Shape<f32> + HasBoundingVolume<f32, AABB<f32>>,
+{
+    let cast = VoxelShapeCast::new(shape, rotation, ray, max_toi, is_solid);
+
+    let path_min = Point3f::from(*cast.shape_path_aabb.mins()).floor_int();
+    let path_max = Point3f::from(*cast.shape_path_aabb.maxs()).floor_int();
+    let path_extent = Extent3i::from_min_and_max(path_min, path_max).intersection(extent);
+
+    let mut earliest_impact: Option<VoxelShapeImpact> = None;
+    for point in path_extent.iter_points() {
+        if !(cast.predicate)(point) {
+            continue;
+        }
+        let earliest_toi = earliest_impact
+            .as_ref()
+            .map(|i| i.impact.toi)
+            .unwrap_or(cast.max_toi);
+        if let Some(impact) = cast.time_of_impact(
+            &Extent3i::from_min_and_shape(point, Point3i::ONES),
+            earliest_toi,
+        ) {
+            if impact.status == TOIStatus::Converged {
+                earliest_impact = Some(VoxelImpact { point, impact });
+            }
+        }
+    }
+
+    earliest_impact
+}
+
+struct VoxelShapeCast<'a, G, F> {
+    earliest_impact: Option<VoxelShapeImpact>,
+    shape: &'a G,
+    shape_start_isom: Isometry3<f32>,
+    ray: Ray<f32>,
+    max_toi: f32,
+    shape_path_aabb: AABB<f32>,
+    predicate: F,
+}
+
+impl<'a, G, F> VoxelShapeCast<'a, G, F>
+where
+    G: Shape<f32> + HasBoundingVolume<f32, AABB<f32>>,
+{
+    fn new(
+        shape: &'a G,
+        rotation: UnitQuaternion<f32>,
+        ray: Ray<f32>,
+        max_toi: f32,
+        predicate: F,
+    ) -> Self {
+        let start = ray.origin;
+        let end = ray.point_at(max_toi);
+
+        let shape_start_isom = Isometry3::from_parts(Translation3::from(start.coords), rotation);
+        let shape_end_isom = Isometry3::from_parts(Translation3::from(end.coords), rotation);
+
+        // Make an AABB that bounds the shape through its entire path.
+        let shape_start_aabb: AABB<f32> = shape.bounding_volume(&shape_start_isom);
+        let shape_end_aabb: AABB<f32> = shape.bounding_volume(&shape_end_isom);
+        let shape_path_aabb = shape_start_aabb.merged(&shape_end_aabb);
+
+        Self {
+            earliest_impact: None,
+            shape,
+            shape_start_isom,
+            ray,
+            max_toi,
+            shape_path_aabb,
+            predicate,
+        }
+    }
+
+    fn earliest_toi(&self) -> f32 {
+        self.earliest_impact
+            .as_ref()
+            .map(|i| i.impact.toi)
+            .unwrap_or(std::f32::INFINITY)
+    }
+
+    fn time_of_impact(&self, extent: &Extent3i, max_toi: f32) -> Option<TOI<f32>> {
+        let voxel_velocity = na::Vector3::zeros();
+        let target_distance = 0.0;
+
+        time_of_impact(
+            &DefaultTOIDispatcher,
+            &self.shape_start_isom,
+            &self.ray.dir,
+            self.shape,
+            &extent3i_cuboid_transform(extent),
+            &voxel_velocity,
+            &extent3i_cuboid(extent),
+            max_toi,
+            target_distance,
+        )
+        // Unsupported shape queries return Err
+        .unwrap()
+    }
+}
+
+impl<'a, G, F> OctreeDbvtVisitor for VoxelShapeCast<'a, G, F>
+where
+    G: Shape<f32> + HasBoundingVolume<f32, AABB<f32>>,
+    F: Fn(Point3i) -> bool,
+{
+    fn visit(&mut self, aabb: &AABB<f32>, octant: Option<&Octant>, is_leaf: bool) -> VisitStatus {
+        if !self.shape_path_aabb.intersects(aabb) {
+            // The shape couldn't intersect any voxels in this AABB, because it doesn't even intersect the AABB that bounds the
+            // shape's path.
+            return VisitStatus::Stop;
+        }
+
+        if let Some(octant) = octant {
+            // Cast the shape at this octant.
+            if let Some(impact) = self.time_of_impact(&Extent3i::from(*octant), self.max_toi) {
+                if impact.status != TOIStatus::Converged {
+                    // Something bad happened with the TOI algorithm. Let's just keep going down this branch and hope it gets
+                    // better. If we're at a leaf, we won't consider this a legitimate impact.
+                    return VisitStatus::Continue;
+                }
+
+                if is_leaf && impact.toi < self.earliest_toi() {
+                    // The contact point is the shape's "local witness" transformed into world space at the time of impact.
+                    let impact_isom = Isometry3::from_parts(
+                        Translation3::from(self.ray.point_at(impact.toi).coords),
+                        self.shape_start_isom.rotation,
+                    );
+                    let contact = impact_isom * impact.witness1;
+
+                    let point = impact_with_leaf_octant(&octant, &contact, &impact.normal2);
+                    if (self.predicate)(point) {
+                        self.earliest_impact = Some(VoxelImpact { point, impact });
+                    }
+                }
+            } else {
+                // The shape won't intersect this octant.
+                return VisitStatus::Stop;
+            }
+        }
+
+        VisitStatus::Continue
+    }
+}
+
+fn extent3i_cuboid(e: &Extent3i) -> Cuboid<f32> {
+    Cuboid::new(half_extent(e.shape))
+}
+
+fn extent3i_cuboid_transform(e: &Extent3i) -> Isometry3<f32> {
+    let min = na::Point3::from(Point3f::from(e.minimum));
+    let center = min + half_extent(e.shape);
+
+    Isometry3::new(center.coords, zero())
+}
+
+fn half_extent(shape: Point3i) -> na::Vector3<f32> {
+    na::Vector3::from(Point3f::from(shape)) / 2.0
+}
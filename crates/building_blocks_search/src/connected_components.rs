@@ -0,0 +1,159 @@
+use crate::pathfinding::Connectivity;
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{prelude::*, ChunkMap3};
+
+/// The connected components found by `label_components`.
+pub struct ComponentLabels {
+    /// The label of each voxel. Voxels that don't satisfy the predicate get label `0`, and the components are labeled
+    /// `1..=num_components` in the order they are first encountered.
+    pub labels: Array3x1<u32>,
+    /// The number of voxels in each component, where the size of component `label` is at index `label - 1`.
+    pub component_sizes: Vec<usize>,
+}
+
+impl ComponentLabels {
+    #[inline]
+    pub fn num_components(&self) -> usize {
+        self.component_sizes.len()
+    }
+
+    /// The number of voxels in the component with `label`, which must not be `0`.
+    #[inline]
+    pub fn component_size(&self, label: u32) -> usize {
+        self.component_sizes[label as usize - 1]
+    }
+
+    /// The label of the largest component, if there are any components.
+    pub fn largest_component(&self) -> Option<u32> {
+        self.component_sizes
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, size)| **size)
+            .map(|(i, _)| i as u32 + 1)
+    }
+}
+
+/// Labels the connected regions of points in `extent` that satisfy `predicate`. Regions don't connect through points outside
+/// of `extent`. This is useful for finding "floating islands" that aren't connected to the ground, or pieces of a structure
+/// that would fall apart after an edit.
+///
+/// `predicate` is called exactly once for each point in `extent`.
+pub fn label_components(
+    extent: &Extent3i,
+    connectivity: &impl Connectivity<[i32; 3]>,
+    predicate: impl Fn(Point3i) -> bool,
+) -> ComponentLabels {
+    let offsets = connectivity.offsets();
+    let matches = Array3x1::fill_with(*extent, predicate);
+    let mut labels = Array3x1::fill(*extent, 0u32);
+    let mut component_sizes = Vec::new();
+
+    let mut stack = Vec::new();
+    for seed in extent.iter_points() {
+        if !matches.get(seed) || labels.get(seed) != 0 {
+            continue;
+        }
+
+        let label = component_sizes.len() as u32 + 1;
+        let mut size = 0;
+        *labels.get_mut(seed) = label;
+        stack.push(seed);
+        while let Some(p) = stack.pop() {
+            size += 1;
+            for &offset in offsets.iter() {
+                let neighbor = p + offset;
+                if extent.contains(neighbor) && matches.get(neighbor) && labels.get(neighbor) == 0 {
+                    *labels.get_mut(neighbor) = label;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        component_sizes.push(size);
+    }
+
+    ComponentLabels {
+        labels,
+        component_sizes,
+    }
+}
+
+/// Same as `label_components`, but for all voxels of `map` at level of detail `lod` that satisfy `predicate`. The labels
+/// cover the bounding extent of all chunks at `lod`, and vacant chunks are treated as ambient.
+pub fn label_chunk_map_components<T, Bldr, Store>(
+    map: &ChunkMap3<T, Bldr, Store>,
+    lod: u8,
+    connectivity: &impl Connectivity<[i32; 3]>,
+    predicate: impl Fn(T) -> bool,
+) -> ComponentLabels
+where
+    T: Clone,
+    Bldr: ChunkMapBuilder<[i32; 3], T>,
+    <Bldr::Chunk as Chunk>::Array: Get<Point3i, Item = T>,
+    Store: ChunkReadStorage<[i32; 3], Bldr::Chunk> + for<'r> IterChunkKeys<'r, [i32; 3]>,
+{
+    label_components(&map.bounding_extent(lod), connectivity, |p| {
+        predicate(map.clone_point(lod, p))
+    })
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pathfinding::Connectivity3;
+
+    #[test]
+    fn diagonal_voxels_only_connect_with_twenty_six_connectivity() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let solid = [
+            PointN([0, 0, 0]),
+            PointN([1, 0, 0]),
+            // Touches the previous voxel on a corner.
+            PointN([2, 1, 1]),
+            // Not connected to any other voxel.
+            PointN([3, 3, 3]),
+        ];
+        let predicate = |p| solid.contains(&p);
+
+        let six = label_components(&extent, &Connectivity3::Six, predicate);
+        assert_eq!(six.component_sizes, vec![2, 1, 1]);
+        assert_eq!(six.labels.get(PointN([1, 0, 0])), 1);
+        assert_eq!(six.labels.get(PointN([2, 1, 1])), 2);
+        assert_eq!(six.labels.get(PointN([3, 3, 3])), 3);
+        assert_eq!(six.labels.get(PointN([0, 1, 0])), 0);
+        assert_eq!(six.largest_component(), Some(1));
+
+        let twenty_six = label_components(&extent, &Connectivity3::TwentySix, predicate);
+        assert_eq!(twenty_six.component_sizes, vec![3, 1]);
+        assert_eq!(twenty_six.labels.get(PointN([2, 1, 1])), 1);
+        assert_eq!(twenty_six.component_size(2), 1);
+    }
+
+    #[test]
+    fn find_floating_island_in_chunk_map() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0u8);
+        let mut map = builder.build_with_hash_map_storage();
+        let ground = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([32, 2, 32]));
+        map.fill_extent(0, &ground, 1);
+        // This island spans two chunks.
+        let island = Extent3i::from_min_and_shape(PointN([14, 8, 4]), PointN([4, 2, 2]));
+        map.fill_extent(0, &island, 1);
+
+        let components =
+            label_chunk_map_components(&map, 0, &Connectivity3::Six, |value| value != 0);
+        assert_eq!(components.num_components(), 2);
+        let ground_label = components.labels.get(Point3i::ZERO);
+        assert_eq!(components.component_size(ground_label), ground.num_points());
+        let island_label = components.labels.get(island.minimum);
+        assert_ne!(island_label, ground_label);
+        assert_eq!(components.component_size(island_label), island.num_points());
+    }
+}
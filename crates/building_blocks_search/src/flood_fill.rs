@@ -1,4 +1,7 @@
-use building_blocks_core::prelude::*;
+use crate::pathfinding::{Connectivity, Connectivity3};
+
+use building_blocks_core::{bounding_extent, prelude::*};
+use building_blocks_storage::{prelude::*, SmallKeyHashSet};
 
 // This is the naive implementation. Kept around just for a baseline measurement.
 //
@@ -168,6 +171,103 @@ impl ScanLine {
     }
 }
 
+/// Options for `flood_fill3`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FloodFill3 {
+    /// Which neighbors of a point are filled from it.
+    pub connectivity: Connectivity3,
+    /// If set, points outside of this extent are never visited.
+    pub clamp: Option<Extent3i>,
+    /// If set, the fill stops after visiting this many points.
+    pub max_visited: Option<usize>,
+}
+
+impl Default for FloodFill3 {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity3::Six,
+            clamp: None,
+            max_visited: None,
+        }
+    }
+}
+
+/// The region visited by `flood_fill3`.
+pub struct FloodFillResult {
+    /// `true` for each visited point. The extent is the bounding box of the visited points.
+    pub visited: Array3x1<bool>,
+    /// The number of visited points.
+    pub num_visited: usize,
+    /// `true` iff the fill stopped early because it reached `max_visited`, so the connected region might be larger.
+    pub reached_max_visited: bool,
+}
+
+impl FloodFillResult {
+    /// Returns `true` iff `p` was visited.
+    #[inline]
+    pub fn is_visited(&self, p: Point3i) -> bool {
+        self.visited.extent().contains(p) && self.visited.get(p)
+    }
+}
+
+/// Visits the region connected to `seed` by `options.connectivity`, where all points in the region satisfy `predicate` (i.e.
+/// `predicate` returns `true`). Unlike `von_neumann_flood_fill3`, this remembers which points have been visited, so
+/// `predicate` is called at most once per point.
+///
+/// Without a `clamp` or a `max_visited` budget, the fill never ends if `predicate` is satisfied by infinitely many
+/// connected points.
+pub fn flood_fill3(
+    seed: Point3i,
+    options: &FloodFill3,
+    mut predicate: impl FnMut(Point3i) -> bool,
+) -> FloodFillResult {
+    let in_clamp = |p: Point3i| options.clamp.map_or(true, |clamp| clamp.contains(p));
+    let offsets = options.connectivity.offsets();
+
+    let mut seen = SmallKeyHashSet::default();
+    let mut visited = Vec::new();
+    let mut reached_max_visited = false;
+
+    let mut stack = Vec::new();
+    if in_clamp(seed) {
+        seen.insert(seed);
+        stack.push(seed);
+    }
+    while let Some(p) = stack.pop() {
+        if options.max_visited == Some(visited.len()) {
+            reached_max_visited = true;
+            break;
+        }
+        if !predicate(p) {
+            continue;
+        }
+        visited.push(p);
+
+        for &offset in offsets.iter() {
+            let neighbor = p + offset;
+            if in_clamp(neighbor) && seen.insert(neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    let extent = if visited.is_empty() {
+        Extent3i::from_min_and_shape(seed, Point3i::ZERO)
+    } else {
+        bounding_extent(visited.iter().cloned())
+    };
+    let mut visited_array = Array3x1::fill(extent, false);
+    for &p in visited.iter() {
+        *visited_array.get_mut(p) = true;
+    }
+
+    FloodFillResult {
+        visited: visited_array,
+        num_visited: visited.len(),
+        reached_max_visited,
+    }
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -223,4 +323,89 @@ mod tests {
 
         test_print(&format!("# flood fill visits = {}\n", num_visits));
     }
+
+    #[test]
+    fn fill_sphere_with_each_connectivity() {
+        let (map, sphere_radius) = sphere_bit_array(32, Color(1), Color(0));
+        let extent = *map.extent();
+        let is_sphere = |p: Point3i| extent.contains(p) && map.get(p) == Color(1);
+
+        let six = flood_fill3(Point3i::ZERO, &FloodFill3::default(), is_sphere);
+        assert!(!six.reached_max_visited);
+        for p in extent.iter_points() {
+            assert_eq!(six.is_visited(p), p.norm() < sphere_radius as f32);
+        }
+
+        for &connectivity in [Connectivity3::Eighteen, Connectivity3::TwentySix].iter() {
+            let options = FloodFill3 {
+                connectivity,
+                ..Default::default()
+            };
+            let result = flood_fill3(Point3i::ZERO, &options, is_sphere);
+            assert_eq!(result.num_visited, six.num_visited);
+        }
+    }
+
+    #[test]
+    fn diagonal_neighbors_depend_on_connectivity() {
+        // A diagonal line of points that only touch at edges.
+        let is_diagonal = |p: Point3i| p.x() == p.y() && p.z() == 0;
+        let clamp = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+
+        let six = flood_fill3(
+            Point3i::ZERO,
+            &FloodFill3 {
+                clamp: Some(clamp),
+                ..Default::default()
+            },
+            is_diagonal,
+        );
+        assert_eq!(six.num_visited, 1);
+
+        let eighteen = flood_fill3(
+            Point3i::ZERO,
+            &FloodFill3 {
+                connectivity: Connectivity3::Eighteen,
+                clamp: Some(clamp),
+                ..Default::default()
+            },
+            is_diagonal,
+        );
+        assert_eq!(eighteen.num_visited, 4);
+        assert_eq!(
+            *eighteen.visited.extent(),
+            Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 4, 1]))
+        );
+    }
+
+    #[test]
+    fn fill_stops_at_max_visited() {
+        let clamp = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(10));
+        let options = FloodFill3 {
+            clamp: Some(clamp),
+            max_visited: Some(100),
+            ..Default::default()
+        };
+
+        let result = flood_fill3(Point3i::ZERO, &options, |_| true);
+        assert!(result.reached_max_visited);
+        assert_eq!(result.num_visited, 100);
+        assert!(result.visited.extent().is_subset_of(&clamp));
+
+        // A budget that's big enough for the whole region isn't reached.
+        let options = FloodFill3 {
+            max_visited: Some(1000),
+            ..options
+        };
+        let result = flood_fill3(Point3i::ZERO, &options, |_| true);
+        assert!(!result.reached_max_visited);
+        assert_eq!(result.num_visited, 1000);
+    }
+
+    #[test]
+    fn seed_outside_of_region_visits_nothing() {
+        let result = flood_fill3(PointN([5, 5, 5]), &FloodFill3::default(), |_| false);
+        assert_eq!(result.num_visited, 0);
+        assert!(!result.is_visited(PointN([5, 5, 5])));
+    }
 }
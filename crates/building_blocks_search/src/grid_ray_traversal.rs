@@ -1,4 +1,5 @@
 use building_blocks_core::prelude::*;
+use building_blocks_storage::{ChunkKey, ChunkKey3, ChunkMap3, ChunkMapBuilder, ChunkReadStorage};
 
 /// Grid traversal algorithm by Amanatides and Woo. Visits every voxel intersecting the given ray.
 pub struct GridRayTraversal<Ni, Nf> {
@@ -10,6 +11,8 @@ pub struct GridRayTraversal<Ni, Nf> {
     t_delta: PointN<Nf>,
     // The next time when each axis will cross a pixel boundary.
     t_max: PointN<Nf>,
+    // The time when the ray entered the current pixel.
+    t: f32,
 }
 
 /// 2D `GridRayTraversal`.
@@ -26,7 +29,19 @@ impl<Ni, Nf> GridRayTraversal<Ni, Nf> {
         PointN<Nf>: IntoIntegerPoint<IntPoint = PointN<Ni>> + FloatPoint<Nf>,
         PointN<Nf>: From<PointN<Ni>>,
     {
-        let current_grid_point: PointN<Ni> = start.into_int();
+        Self::new_in_grid_cell(start, velocity, start.into_int())
+    }
+
+    /// Same as `new`, but the traversal begins in `start_cell`, which should contain `start`. This is useful when `start` lies
+    /// on a cell boundary, or when rounding `start` toward zero doesn't give the desired cell, e.g. for negative coordinates.
+    #[inline]
+    pub fn new_in_grid_cell(start: PointN<Nf>, velocity: PointN<Nf>, start_cell: PointN<Ni>) -> Self
+    where
+        PointN<Ni>: IntegerPoint<Ni>,
+        PointN<Nf>: IntoIntegerPoint<IntPoint = PointN<Ni>> + FloatPoint<Nf>,
+        PointN<Nf>: From<PointN<Ni>>,
+    {
+        let current_grid_point = start_cell;
         let vel_signs = velocity.signum();
         let step = vel_signs.into_int();
         let t_delta = vel_signs / velocity;
@@ -42,8 +57,16 @@ impl<Ni, Nf> GridRayTraversal<Ni, Nf> {
             step,
             t_delta,
             t_max,
+            t: 0.0,
         }
     }
+
+    /// The time along the ray when it entered the current cell, i.e. the current cell was entered at
+    /// `start + t * velocity`. This is `0.0` for the starting cell.
+    #[inline]
+    pub fn t(&self) -> f32 {
+        self.t
+    }
 }
 
 impl GridRayTraversal2 {
@@ -51,9 +74,11 @@ impl GridRayTraversal2 {
     #[inline]
     pub fn step(&mut self) {
         if self.t_max.x() < self.t_max.y() {
+            self.t = self.t_max.x();
             *self.current_grid_point.x_mut() += self.step.x();
             *self.t_max.x_mut() += self.t_delta.x();
         } else {
+            self.t = self.t_max.y();
             *self.current_grid_point.y_mut() += self.step.y();
             *self.t_max.y_mut() += self.t_delta.y();
         }
@@ -72,16 +97,20 @@ impl GridRayTraversal3 {
     pub fn step(&mut self) {
         if self.t_max.x() < self.t_max.y() {
             if self.t_max.x() < self.t_max.z() {
+                self.t = self.t_max.x();
                 *self.current_grid_point.x_mut() += self.step.x();
                 *self.t_max.x_mut() += self.t_delta.x();
             } else {
+                self.t = self.t_max.z();
                 *self.current_grid_point.z_mut() += self.step.z();
                 *self.t_max.z_mut() += self.t_delta.z();
             }
         } else if self.t_max.y() < self.t_max.z() {
+            self.t = self.t_max.y();
             *self.current_grid_point.y_mut() += self.step.y();
             *self.t_max.y_mut() += self.t_delta.y();
         } else {
+            self.t = self.t_max.z();
             *self.current_grid_point.z_mut() += self.step.z();
             *self.t_max.z_mut() += self.t_delta.z();
         }
@@ -94,6 +123,67 @@ impl GridRayTraversal3 {
     }
 }
 
+/// Visits every voxel of `map` that intersects the ray starting at `start` and moving along `velocity`, in order, until the ray
+/// time exceeds `max_t`. Positions are in the voxel coordinates of level of detail `lod`.
+///
+/// Rather than stepping through every voxel along the ray, this first steps through the chunks along the ray, and only steps
+/// through the voxels of the chunks that are occupied. A chunk is skipped if it's absent from `map` or `chunk_filter` returns
+/// `false` for its key, e.g. because an `OctreeChunkIndex` says the chunk is empty. This makes long rays through sparse maps
+/// cheap.
+///
+/// `visitor` is given each voxel, the ray time when the voxel was entered, and the chunk containing the voxel. The traversal
+/// stops early if `visitor` returns `false`.
+pub fn traverse_chunk_map_ray3<T, Bldr, Store>(
+    map: &ChunkMap3<T, Bldr, Store>,
+    lod: u8,
+    start: Point3f,
+    velocity: Point3f,
+    max_t: f32,
+    mut chunk_filter: impl FnMut(ChunkKey3) -> bool,
+    mut visitor: impl FnMut(Point3i, f32, &Bldr::Chunk) -> bool,
+) where
+    Bldr: ChunkMapBuilder<[i32; 3], T>,
+    Store: ChunkReadStorage<[i32; 3], Bldr::Chunk>,
+{
+    let chunk_shape = map.indexer.chunk_shape();
+    let chunk_shape_f = Point3f::from(chunk_shape);
+
+    // Time is the same in chunk units as in voxel units.
+    let start_in_chunks = start / chunk_shape_f;
+    let mut chunk_traversal = GridRayTraversal3::new_in_grid_cell(
+        start_in_chunks,
+        velocity / chunk_shape_f,
+        start_in_chunks.floor_int(),
+    );
+    while chunk_traversal.t() <= max_t {
+        let chunk_min = chunk_traversal.current_voxel() * chunk_shape;
+        let key = ChunkKey::new(lod, chunk_min);
+        if chunk_filter(key) {
+            if let Some(chunk) = map.get_chunk(key) {
+                let t_enter = chunk_traversal.t();
+                let entry = start + velocity * t_enter;
+                let chunk_extent = map.indexer.extent_for_chunk_with_min(chunk_min);
+                // Rounding error could put the entry point just outside of the chunk.
+                let entry_voxel = entry
+                    .floor_int()
+                    .join(chunk_extent.minimum)
+                    .meet(chunk_extent.max());
+
+                let mut voxel_traversal =
+                    GridRayTraversal3::new_in_grid_cell(entry, velocity, entry_voxel);
+                while chunk_extent.contains(voxel_traversal.current_voxel()) {
+                    let t = t_enter + voxel_traversal.t();
+                    if t > max_t || !visitor(voxel_traversal.current_voxel(), t, chunk) {
+                        return;
+                    }
+                    voxel_traversal.step();
+                }
+            }
+        }
+        chunk_traversal.step();
+    }
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -105,6 +195,8 @@ impl GridRayTraversal3 {
 mod tests {
     use super::*;
 
+    use building_blocks_storage::prelude::*;
+
     #[test]
     fn test_move_along_x_axis() {
         let mut traversal =
@@ -155,4 +247,81 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn chunk_map_traversal_skips_absent_chunks() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        map.fill_extent(
+            0,
+            &Extent3i::from_min_and_shape(PointN([64, 0, 0]), Point3i::fill(16)),
+            1,
+        );
+
+        let mut chunks_checked = 0;
+        let mut voxels = Vec::new();
+        traverse_chunk_map_ray3(
+            &map,
+            0,
+            PointN([0.5, 0.5, 0.5]),
+            PointN([1.0, 0.0, 0.0]),
+            100.0,
+            |_key| {
+                chunks_checked += 1;
+                true
+            },
+            |p, t, _chunk| {
+                voxels.push((p, t));
+                true
+            },
+        );
+
+        // The ray is at x = 100.5 when it stops, in the 7th chunk.
+        assert_eq!(chunks_checked, 7);
+        let expected: Vec<_> = (64..80)
+            .map(|x| (PointN([x, 0, 0]), x as f32 - 0.5))
+            .collect();
+        assert_eq!(voxels, expected);
+    }
+
+    #[test]
+    fn chunk_map_traversal_stops_at_first_hit() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        let hit = PointN([-20, 2, 1]);
+        map.fill_extent(0, &Extent3i::from_min_and_shape(hit, Point3i::ONES), 1);
+        // This chunk is present, but the index doesn't know about it, so it gets skipped.
+        let unindexed = PointN([-40, 2, 1]);
+        map.fill_extent(
+            0,
+            &Extent3i::from_min_and_shape(unindexed, Point3i::ONES),
+            1,
+        );
+        let index = OctreeChunkIndex::index_lod0_chunks(
+            8,
+            4,
+            1,
+            [ChunkKey::new(0, PointN([-32, 0, 0]))].iter(),
+        );
+
+        let mut voxels = Vec::new();
+        traverse_chunk_map_ray3(
+            &map,
+            0,
+            PointN([10.5, 2.5, 1.5]),
+            PointN([-1.0, 0.0, 0.0]),
+            100.0,
+            |key| index.contains_chunk(key),
+            |p, t, chunk| {
+                voxels.push((p, t));
+                chunk.get(p) == 0
+            },
+        );
+
+        let expected: Vec<_> = (-20..-16)
+            .rev()
+            .map(|x| (PointN([x, 2, 1]), 10.5 - (x + 1) as f32))
+            .collect();
+        assert_eq!(voxels, expected);
+    }
 }
@@ -0,0 +1,511 @@
+//! Hierarchical path-finding A* (HPA*) for big maps, where a flat `astar_path` would visit far too many points.
+//!
+//! The map is divided into clusters, usually the same shape as the chunks of a `ChunkMap`. A `HierarchicalPathGraph` is
+//! precomputed with one "portal" for each walkable opening between adjacent clusters, and the cost of moving between any two
+//! portals of the same cluster. Path searches run over this much smaller graph, and then each step is refined with a local
+//! search inside a single cluster.
+//!
+//! Paths are not always optimal, since they are forced through the portals, but they are usually close. Moves that cross a
+//! cluster border diagonally are only used inside of refined steps, so a path that could only squeeze diagonally between
+//! clusters will not be found.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_search::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let chunk_shape = Point3i::fill(16);
+//! let builder = ChunkMapBuilder3x1::new(chunk_shape, false);
+//! let mut map = builder.build_with_hash_map_storage();
+//! // A wall with a single opening.
+//! map.fill_extent(0, &Extent3i::from_min_and_shape(PointN([20, 0, 0]), PointN([1, 16, 16])), true);
+//! *map.get_mut_point(0, PointN([20, 8, 8])) = false;
+//!
+//! let bounds = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([64, 16, 16]));
+//! let is_wall = Func(|p| map.clone_point(0, p));
+//! let cost = |_from: &Point3i, _to: &Point3i, wall: bool| if wall { None } else { Some(1) };
+//!
+//! let graph = HierarchicalPathGraph::build(&is_wall, bounds, chunk_shape, &Connectivity3::Six, cost);
+//! let (path, _cost) = graph
+//!     .find_path(&is_wall, PointN([0, 0, 0]), PointN([63, 15, 15]), cost, |a, b| a.l1_distance(*b))
+//!     .unwrap();
+//! assert!(path.contains(&PointN([20, 8, 8])));
+//! ```
+
+use crate::pathfinding::Connectivity;
+
+use building_blocks_core::{bounding_extent, num::Zero, prelude::*};
+use building_blocks_storage::Get;
+
+use core::hash::Hash;
+use pathfinding::directed::{astar::astar, dijkstra::dijkstra_all};
+use std::collections::{HashMap, HashSet};
+
+/// The abstract graph used for hierarchical path searches. See the [module docs](self).
+///
+/// The graph must be rebuilt when the map or the `cost` function changes.
+pub struct HierarchicalPathGraph<N, C> {
+    bounds: ExtentN<N>,
+    cluster_shape: PointN<N>,
+    offsets: Vec<PointN<N>>,
+    /// The portals in each cluster, keyed by the minimum of the cluster.
+    cluster_portals: HashMap<PointN<N>, Vec<PointN<N>>>,
+    /// Edges between portals, both within a cluster and across cluster borders.
+    edges: HashMap<PointN<N>, Vec<(PointN<N>, C)>>,
+}
+
+impl<N, C> HierarchicalPathGraph<N, C>
+where
+    PointN<N>: Hash + Eq + Distance + IntegerPoint<N>,
+    C: Zero + Copy + Ord,
+{
+    /// Finds the portals and intra-cluster costs for all clusters of `cluster_shape` in `bounds`.
+    ///
+    /// Like `astar_path`, `cost` is given the point being moved from, the point being moved to, and the value of `map` at the
+    /// destination. It must return the cost of that move, or `None` if the move is not allowed.
+    pub fn build<M>(
+        map: &M,
+        bounds: ExtentN<N>,
+        cluster_shape: PointN<N>,
+        connectivity: &impl Connectivity<N>,
+        cost: impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+    ) -> Self
+    where
+        M: Get<PointN<N>>,
+    {
+        let mut graph = Self {
+            bounds,
+            cluster_shape,
+            offsets: connectivity.offsets(),
+            cluster_portals: HashMap::new(),
+            edges: HashMap::new(),
+        };
+
+        let cluster_mins: Vec<_> = graph.cluster_mins().collect();
+        for &cluster_min in cluster_mins.iter() {
+            for axis in PointN::<N>::basis() {
+                graph.add_portals(map, &cost, cluster_min, axis);
+            }
+        }
+        for &cluster_min in cluster_mins.iter() {
+            graph.connect_cluster_portals(map, &cost, cluster_min);
+        }
+
+        graph
+    }
+
+    /// The minimum of the cluster that contains `p`.
+    pub fn cluster_min(&self, p: PointN<N>) -> PointN<N> {
+        p.vector_div_floor(self.cluster_shape) * self.cluster_shape
+    }
+
+    /// The portals of the cluster with minimum `cluster_min`.
+    pub fn cluster_portals(&self, cluster_min: PointN<N>) -> &[PointN<N>] {
+        self.cluster_portals
+            .get(&cluster_min)
+            .map(|portals| portals.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn num_portals(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Finds a path from `start` to `finish`. The `map` and `cost` must be the same as when the graph was built.
+    ///
+    /// `heuristic` estimates the cost of moving between two points, and it must not return a cost greater than the real cost.
+    /// Returns the path and its total cost iff the path reaches `finish`.
+    pub fn find_path<M>(
+        &self,
+        map: &M,
+        start: PointN<N>,
+        finish: PointN<N>,
+        cost: impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+        heuristic: impl Fn(&PointN<N>, &PointN<N>) -> C,
+    ) -> Option<(Vec<PointN<N>>, C)>
+    where
+        M: Get<PointN<N>>,
+    {
+        if !self.bounds.contains(start) || !self.bounds.contains(finish) {
+            return None;
+        }
+
+        let start_cluster = self.cluster_min(start);
+        let finish_cluster = self.cluster_min(finish);
+        if start_cluster == finish_cluster {
+            // The path might not need to leave the cluster at all.
+            if let Some(found) = self.local_path(map, &cost, &heuristic, start, finish) {
+                return Some(found);
+            }
+        }
+
+        // Temporarily connect the start and finish to the portals of their clusters.
+        let start_cluster_extent = self.cluster_extent(start_cluster);
+        let start_reach = dijkstra_all(&start, |p| {
+            local_successors(map, &cost, &self.offsets, &start_cluster_extent, p)
+        });
+        let mut start_edges: Vec<_> = self
+            .cluster_portals(start_cluster)
+            .iter()
+            .filter_map(|portal| start_reach.get(portal).map(|(_, c)| (*portal, *c)))
+            .collect();
+        if let Some(portal_edges) = self.edges.get(&start) {
+            start_edges.extend_from_slice(portal_edges);
+        }
+
+        let finish_cluster_extent = self.cluster_extent(finish_cluster);
+        let finish_reach = dijkstra_all(&finish, |p| {
+            local_predecessors(map, &cost, &self.offsets, &finish_cluster_extent, p)
+        });
+        let finish_costs: HashMap<PointN<N>, C> = self
+            .cluster_portals(finish_cluster)
+            .iter()
+            .filter_map(|portal| finish_reach.get(portal).map(|(_, c)| (*portal, *c)))
+            .collect();
+
+        let abstract_successors = |p: &PointN<N>| {
+            let mut successors = if *p == start {
+                start_edges.clone()
+            } else {
+                self.edges.get(p).cloned().unwrap_or_default()
+            };
+            if let Some(c) = finish_costs.get(p) {
+                successors.push((finish, *c));
+            }
+
+            successors
+        };
+        let (abstract_path, _) = astar(
+            &start,
+            abstract_successors,
+            |p| heuristic(p, &finish),
+            |p| *p == finish,
+        )?;
+
+        // Refine each abstract step into a path on the lattice.
+        let mut path = vec![start];
+        let mut total_cost = C::zero();
+        for step in abstract_path.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            if self.cluster_min(from) == self.cluster_min(to) {
+                let (local_path, local_cost) = self.local_path(map, &cost, &heuristic, from, to)?;
+                path.extend_from_slice(&local_path[1..]);
+                total_cost = total_cost + local_cost;
+            } else {
+                // Portals in different clusters are always connected by a single move.
+                total_cost = total_cost + cost(&from, &to, map.get(to))?;
+                path.push(to);
+            }
+        }
+
+        Some((path, total_cost))
+    }
+
+    fn cluster_mins(&self) -> impl Iterator<Item = PointN<N>> {
+        let cluster_shape = self.cluster_shape;
+        let min_cluster = self.bounds.minimum.vector_div_floor(cluster_shape);
+        let max_cluster = self.bounds.max().vector_div_floor(cluster_shape);
+
+        ExtentN::from_min_and_max(min_cluster, max_cluster)
+            .iter_points()
+            .map(move |c| c * cluster_shape)
+    }
+
+    fn cluster_extent(&self, cluster_min: PointN<N>) -> ExtentN<N> {
+        ExtentN::from_min_and_shape(cluster_min, self.cluster_shape).intersection(&self.bounds)
+    }
+
+    /// Adds one portal for each connected opening between the cluster at `cluster_min` and its neighbor in the direction of
+    /// `axis`.
+    fn add_portals<M>(
+        &mut self,
+        map: &M,
+        cost: &impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+        cluster_min: PointN<N>,
+        axis: PointN<N>,
+    ) where
+        M: Get<PointN<N>>,
+    {
+        let neighbor_min = cluster_min + self.cluster_shape * axis;
+        let neighbor = self.cluster_extent(neighbor_min);
+        if neighbor.is_empty() {
+            return;
+        }
+        let cluster = self.cluster_extent(cluster_min);
+
+        // The points of this cluster that are adjacent to the neighbor cluster.
+        let face = cluster.intersection(&ExtentN::from_min_and_shape(
+            neighbor.minimum - axis,
+            neighbor.shape,
+        ));
+        let crossing_costs = |p: PointN<N>| {
+            let q = p + axis;
+
+            (cost(&p, &q, map.get(q)), cost(&q, &p, map.get(p)))
+        };
+        let is_open = |p: PointN<N>| {
+            let (forward, backward) = crossing_costs(p);
+
+            forward.is_some() || backward.is_some()
+        };
+
+        // Each connected component of open points on the face is one opening.
+        let face_offsets = PointN::<N>::von_neumann_offsets();
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        for seed in face.iter_points() {
+            if visited.contains(&seed) || !is_open(seed) {
+                continue;
+            }
+
+            let mut opening = Vec::new();
+            visited.insert(seed);
+            stack.push(seed);
+            while let Some(p) = stack.pop() {
+                opening.push(p);
+                for &offset in face_offsets.iter() {
+                    let neighbor = p + offset;
+                    if face.contains(neighbor) && !visited.contains(&neighbor) && is_open(neighbor)
+                    {
+                        visited.insert(neighbor);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            // Put the portal in the middle of the opening.
+            let opening_extent = bounding_extent(opening.iter().cloned());
+            let center = opening_extent.minimum + opening_extent.shape.scalar_div_floor(2);
+            let portal = *opening
+                .iter()
+                .min_by_key(|p| p.l1_distance(center))
+                .unwrap();
+            let other_portal = portal + axis;
+
+            self.insert_portal(cluster_min, portal);
+            self.insert_portal(neighbor_min, other_portal);
+            let (forward, backward) = crossing_costs(portal);
+            if let Some(c) = forward {
+                self.edges.get_mut(&portal).unwrap().push((other_portal, c));
+            }
+            if let Some(c) = backward {
+                self.edges.get_mut(&other_portal).unwrap().push((portal, c));
+            }
+        }
+    }
+
+    fn insert_portal(&mut self, cluster_min: PointN<N>, portal: PointN<N>) {
+        let portals = self.cluster_portals.entry(cluster_min).or_default();
+        if !portals.contains(&portal) {
+            portals.push(portal);
+        }
+        self.edges.entry(portal).or_default();
+    }
+
+    /// Adds an edge between every pair of portals in the cluster that are connected without leaving the cluster.
+    fn connect_cluster_portals<M>(
+        &mut self,
+        map: &M,
+        cost: &impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+        cluster_min: PointN<N>,
+    ) where
+        M: Get<PointN<N>>,
+    {
+        let portals = self.cluster_portals(cluster_min).to_vec();
+        let cluster = self.cluster_extent(cluster_min);
+        for &from in portals.iter() {
+            let reach = dijkstra_all(&from, |p| {
+                local_successors(map, cost, &self.offsets, &cluster, p)
+            });
+            let new_edges: Vec<_> = portals
+                .iter()
+                .filter_map(|to| reach.get(to).map(|(_, c)| (*to, *c)))
+                .collect();
+            self.edges.get_mut(&from).unwrap().extend(new_edges);
+        }
+    }
+
+    /// A path from `start` to `finish` that stays inside of the cluster containing `start`.
+    fn local_path<M>(
+        &self,
+        map: &M,
+        cost: &impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+        heuristic: &impl Fn(&PointN<N>, &PointN<N>) -> C,
+        start: PointN<N>,
+        finish: PointN<N>,
+    ) -> Option<(Vec<PointN<N>>, C)>
+    where
+        M: Get<PointN<N>>,
+    {
+        let cluster = self.cluster_extent(self.cluster_min(start));
+
+        astar(
+            &start,
+            |p| local_successors(map, cost, &self.offsets, &cluster, p),
+            |p| heuristic(p, &finish),
+            |p| *p == finish,
+        )
+    }
+}
+
+fn local_successors<N, M, C>(
+    map: &M,
+    cost: &impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+    offsets: &[PointN<N>],
+    extent: &ExtentN<N>,
+    p: &PointN<N>,
+) -> Vec<(PointN<N>, C)>
+where
+    M: Get<PointN<N>>,
+    PointN<N>: IntegerPoint<N>,
+{
+    offsets
+        .iter()
+        .map(|offset| *p + *offset)
+        .filter(|s| extent.contains(*s))
+        .filter_map(|s| cost(p, &s, map.get(s)).map(|c| (s, c)))
+        .collect()
+}
+
+/// Like `local_successors`, but for searching backwards from a destination. The cost is still for moving from the predecessor
+/// to `p`.
+fn local_predecessors<N, M, C>(
+    map: &M,
+    cost: &impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
+    offsets: &[PointN<N>],
+    extent: &ExtentN<N>,
+    p: &PointN<N>,
+) -> Vec<(PointN<N>, C)>
+where
+    M: Get<PointN<N>>,
+    PointN<N>: IntegerPoint<N>,
+{
+    offsets
+        .iter()
+        .map(|offset| *p - *offset)
+        .filter(|s| extent.contains(*s))
+        .filter_map(|s| cost(&s, p, map.get(*p)).map(|c| (s, c)))
+        .collect()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::{astar_path, Connectivity2};
+
+    use building_blocks_storage::prelude::*;
+
+    fn unit_cost(_from: &Point2i, _to: &Point2i, is_wall: bool) -> Option<i32> {
+        if is_wall {
+            None
+        } else {
+            Some(1)
+        }
+    }
+
+    fn assert_valid_path(map: &Array2x1<bool>, path: &[Point2i], start: Point2i, finish: Point2i) {
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&finish));
+        assert!(path.iter().all(|p| !map.get(*p)));
+        for step in path.windows(2) {
+            assert_eq!(step[0].l1_distance(step[1]), 1, "{:?}", step);
+        }
+    }
+
+    #[test]
+    fn path_through_openings_between_clusters() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(16));
+        let mut map = Array2x1::fill(extent, false);
+        // Two walls, each with one opening at opposite ends.
+        for y in 0..16 {
+            *map.get_mut(PointN([5, y])) = y != 15;
+            *map.get_mut(PointN([10, y])) = y != 0;
+        }
+
+        let graph = HierarchicalPathGraph::build(
+            &map,
+            extent,
+            Point2i::fill(4),
+            &Connectivity2::Four,
+            unit_cost,
+        );
+        assert_eq!(graph.cluster_min(PointN([5, 15])), PointN([4, 12]));
+        assert!(graph.num_portals() > 0);
+
+        let start = PointN([0, 0]);
+        let finish = PointN([15, 15]);
+        let heuristic = |a: &Point2i, b: &Point2i| a.l1_distance(*b);
+        let (path, cost) = graph
+            .find_path(&map, start, finish, unit_cost, heuristic)
+            .unwrap();
+
+        assert_valid_path(&map, &path, start, finish);
+        assert_eq!(cost, path.len() as i32 - 1);
+        assert!(path.contains(&PointN([5, 15])));
+        assert!(path.contains(&PointN([10, 0])));
+
+        // The hierarchical path is never better than the optimal one.
+        let (_, optimal_cost) = astar_path(
+            &map,
+            extent,
+            start,
+            finish,
+            &Connectivity2::Four,
+            unit_cost,
+            |p| p.l1_distance(finish),
+        )
+        .unwrap();
+        assert!(cost >= optimal_cost);
+    }
+
+    #[test]
+    fn path_within_one_cluster_stays_local() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(8));
+        let map = Array2x1::fill(extent, false);
+        let graph = HierarchicalPathGraph::build(
+            &map,
+            extent,
+            Point2i::fill(4),
+            &Connectivity2::Four,
+            unit_cost,
+        );
+
+        let start = PointN([1, 1]);
+        let finish = PointN([2, 3]);
+        let (path, cost) = graph
+            .find_path(&map, start, finish, unit_cost, |a, b| a.l1_distance(*b))
+            .unwrap();
+
+        assert_valid_path(&map, &path, start, finish);
+        assert_eq!(cost, 3);
+    }
+
+    #[test]
+    fn no_path_to_enclosed_finish() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(8));
+        let mut map = Array2x1::fill(extent, false);
+        for y in 0..8 {
+            *map.get_mut(PointN([5, y])) = true;
+        }
+        let graph = HierarchicalPathGraph::build(
+            &map,
+            extent,
+            Point2i::fill(4),
+            &Connectivity2::Four,
+            unit_cost,
+        );
+
+        let result = graph.find_path(&map, PointN([0, 0]), PointN([7, 7]), unit_cost, |a, b| {
+            a.l1_distance(*b)
+        });
+
+        assert!(result.is_none());
+    }
+}
@@ -4,15 +4,25 @@
     clippy::too_many_arguments
 )]
 
+pub mod connected_components;
 pub mod find_surface;
 pub mod flood_fill;
 pub mod grid_ray_traversal;
+pub mod hierarchical_pathfinding;
+pub mod light_propagation;
+pub mod nearest_points;
 pub mod pathfinding;
+pub mod surface_pathfinding;
 
+pub use connected_components::*;
 pub use find_surface::*;
 pub use flood_fill::*;
 pub use grid_ray_traversal::*;
+pub use hierarchical_pathfinding::*;
+pub use light_propagation::*;
+pub use nearest_points::*;
 pub use self::pathfinding::*;
+pub use surface_pathfinding::*;
 
 #[cfg(feature = "ncollide")]
 pub mod collision;
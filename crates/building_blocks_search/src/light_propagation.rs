@@ -0,0 +1,492 @@
+//! Minecraft-style voxel lighting, with sunlight and point lights flood-filled over a map.
+//!
+//! Every voxel has a `Light`, which packs a sunlight level and a point light level in `0..=MAX_LIGHT_LEVEL` into a single byte.
+//! Lights are usually stored in their own map that covers the same space as the voxels, like a `ChunkMap3x1<Light>`, so the
+//! mesher can read them as vertex colors. A `LightPropagator` fills that map from the `LightProperties` of each voxel, and it
+//! updates only the affected lights when a few voxels change.
+//!
+//! Sunlight enters through the top face of the propagator's bounds at full strength, and it shines straight down through
+//! transparent voxels without getting any weaker. Otherwise, light loses one level per step, plus the opacity of the voxel it
+//! enters.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//! use building_blocks_search::{Light, LightPropagator, LightProperties};
+//!
+//! const AIR: u8 = 0;
+//! const STONE: u8 = 1;
+//! const TORCH: u8 = 2;
+//! let properties = |voxel: u8| match voxel {
+//!     STONE => LightProperties::OPAQUE,
+//!     TORCH => LightProperties::emitting(14),
+//!     _ => LightProperties::TRANSPARENT,
+//! };
+//!
+//! let chunk_shape = Point3i::fill(16);
+//! let mut voxels = ChunkMapBuilder3x1::new(chunk_shape, AIR).build_with_hash_map_storage();
+//! let mut lights = ChunkMapBuilder3x1::new(chunk_shape, Light::DARK).build_with_hash_map_storage();
+//!
+//! // A stone roof with a torch underneath.
+//! let bounds = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+//! let roof = Extent3i::from_min_and_shape(PointN([0, 20, 0]), PointN([32, 1, 32]));
+//! let torch = PointN([16, 10, 16]);
+//! voxels.lod_view_mut(0).fill_extent(&roof, STONE);
+//! *voxels.lod_view_mut(0).get_mut(torch) = TORCH;
+//!
+//! let mut propagator = LightPropagator::new(bounds);
+//! propagator.relight_extent(&bounds, &mut lights.lod_view_mut(0), |p| {
+//!     properties(voxels.clone_point(0, p))
+//! });
+//! assert_eq!(lights.clone_point(0, PointN([16, 25, 16])).sun(), 15);
+//! assert_eq!(lights.clone_point(0, torch).sun(), 0);
+//! assert_eq!(lights.clone_point(0, torch).point(), 14);
+//! assert_eq!(lights.clone_point(0, PointN([16, 10, 18])).point(), 12);
+//!
+//! // Dig a hole in the roof and take the torch away.
+//! let hole = PointN([16, 20, 16]);
+//! *voxels.lod_view_mut(0).get_mut(hole) = AIR;
+//! *voxels.lod_view_mut(0).get_mut(torch) = AIR;
+//! let changed = propagator.update(vec![hole, torch], &mut lights.lod_view_mut(0), |p| {
+//!     properties(voxels.clone_point(0, p))
+//! });
+//! assert!(changed.unwrap().contains(torch));
+//! assert_eq!(lights.clone_point(0, torch), Light::new(15, 0));
+//! assert_eq!(lights.clone_point(0, PointN([16, 10, 18])), Light::new(13, 0));
+//! ```
+//!
+//! The returned extent bounds every light that was written, which tells you which chunks need to be remeshed. Alternatively,
+//! wrap the storage of the light map in `DirtyTracking`; lights are only mutably borrowed when they're written.
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{Get, GetMut};
+
+use std::collections::VecDeque;
+
+/// The brightest level of both sunlight and point light.
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+const DOWN: Point3i = PointN([0, -1, 0]);
+
+/// The sunlight and point light levels at a voxel, packed into one byte. Each level is in `0..=MAX_LIGHT_LEVEL`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Light(pub u8);
+
+impl Light {
+    /// No sunlight and no point light.
+    pub const DARK: Self = Self(0);
+
+    /// Levels greater than `MAX_LIGHT_LEVEL` are clamped.
+    #[inline]
+    pub fn new(sun: u8, point: u8) -> Self {
+        Self::DARK.with_sun(sun).with_point(point)
+    }
+
+    #[inline]
+    pub fn sun(self) -> u8 {
+        self.0 >> 4
+    }
+
+    #[inline]
+    pub fn point(self) -> u8 {
+        self.0 & 0x0F
+    }
+
+    /// The level of whichever kind of light is brighter.
+    #[inline]
+    pub fn brightest(self) -> u8 {
+        self.sun().max(self.point())
+    }
+
+    #[inline]
+    pub fn with_sun(self, level: u8) -> Self {
+        Self((self.0 & 0x0F) | (level.min(MAX_LIGHT_LEVEL) << 4))
+    }
+
+    #[inline]
+    pub fn with_point(self, level: u8) -> Self {
+        Self((self.0 & 0xF0) | level.min(MAX_LIGHT_LEVEL))
+    }
+}
+
+/// How a voxel interacts with light.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LightProperties {
+    /// The level of point light emitted by this voxel.
+    pub emission: u8,
+    /// The number of extra levels that light loses when it enters this voxel. Voxels with an opacity of at least
+    /// `MAX_LIGHT_LEVEL` block all light, and only sunlight passing through voxels with an opacity of `0` can shine straight
+    /// down without getting weaker.
+    pub opacity: u8,
+}
+
+impl LightProperties {
+    /// Like air.
+    pub const TRANSPARENT: Self = Self {
+        emission: 0,
+        opacity: 0,
+    };
+
+    /// Like stone.
+    pub const OPAQUE: Self = Self {
+        emission: 0,
+        opacity: MAX_LIGHT_LEVEL,
+    };
+
+    /// A transparent voxel that emits point light at `level`, like a torch.
+    #[inline]
+    pub fn emitting(level: u8) -> Self {
+        Self {
+            emission: level,
+            opacity: 0,
+        }
+    }
+}
+
+/// Computes `Light` levels for the voxels in some bounded region, and keeps them up to date as voxels change. See the
+/// [module docs](self) for an example.
+///
+/// The propagator only owns the queues used by the flood fill, so it can be reused across updates without reallocating.
+pub struct LightPropagator {
+    bounds: Extent3i,
+    removal_queue: VecDeque<(Point3i, u8)>,
+    increase_queue: VecDeque<Point3i>,
+    removed: Vec<Point3i>,
+}
+
+impl LightPropagator {
+    /// Light never spreads outside of `bounds`, and sunlight enters through the top face of `bounds`.
+    pub fn new(bounds: Extent3i) -> Self {
+        Self {
+            bounds,
+            removal_queue: VecDeque::new(),
+            increase_queue: VecDeque::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    pub fn bounds(&self) -> &Extent3i {
+        &self.bounds
+    }
+
+    /// Recomputes the lights of all voxels in `extent`, e.g. when a chunk is loaded. Light that spreads out of `extent` is
+    /// also updated, and light from outside of `extent` spreads in. Returns the bounding extent of all lights that were
+    /// written, if any.
+    ///
+    /// `properties` returns the `LightProperties` of the voxel at a point. It may be called many times for the same point.
+    pub fn relight_extent<L>(
+        &mut self,
+        extent: &Extent3i,
+        lights: &mut L,
+        properties: impl Fn(Point3i) -> LightProperties,
+    ) -> Option<Extent3i>
+    where
+        L: Get<Point3i, Item = Light> + for<'r> GetMut<'r, Point3i, Item = &'r mut Light>,
+    {
+        let region = extent.intersection(&self.bounds);
+
+        self.update(region.iter_points(), lights, properties)
+    }
+
+    /// Updates the lights after the voxels at `changed_voxels` have changed their `LightProperties`. Only the lights that could
+    /// have been affected are visited. Returns the bounding extent of all lights that were written, if any.
+    ///
+    /// `properties` returns the `LightProperties` of the voxel at a point. It may be called many times for the same point.
+    pub fn update<L>(
+        &mut self,
+        changed_voxels: impl IntoIterator<Item = Point3i>,
+        lights: &mut L,
+        properties: impl Fn(Point3i) -> LightProperties,
+    ) -> Option<Extent3i>
+    where
+        L: Get<Point3i, Item = Light> + for<'r> GetMut<'r, Point3i, Item = &'r mut Light>,
+    {
+        let bounds = self.bounds;
+        let changed_voxels: Vec<Point3i> = changed_voxels
+            .into_iter()
+            .filter(|p| bounds.contains(*p))
+            .collect();
+
+        let mut written = WrittenBounds::default();
+        for &channel in [LightChannel::Sun, LightChannel::Point].iter() {
+            self.update_channel(channel, &changed_voxels, lights, &properties, &mut written);
+        }
+
+        written.extent()
+    }
+
+    fn update_channel<L>(
+        &mut self,
+        channel: LightChannel,
+        changed_voxels: &[Point3i],
+        lights: &mut L,
+        properties: &impl Fn(Point3i) -> LightProperties,
+        written: &mut WrittenBounds,
+    ) where
+        L: Get<Point3i, Item = Light> + for<'r> GetMut<'r, Point3i, Item = &'r mut Light>,
+    {
+        // Remove all light that might have passed through the changed voxels. Any lit neighbor that could not have been lit
+        // by a removed voxel has another source, so it becomes a seed for spreading light back into the removed region.
+        for &p in changed_voxels.iter() {
+            let old_level = channel.level(lights.get(p));
+            if old_level > 0 {
+                set_level(lights, channel, p, 0, written);
+            }
+            self.removal_queue.push_back((p, old_level));
+            self.removed.push(p);
+        }
+        while let Some((p, old_level)) = self.removal_queue.pop_front() {
+            for &offset in Point3i::VON_NEUMANN_OFFSETS.iter() {
+                let neighbor = p + offset;
+                if !self.bounds.contains(neighbor) {
+                    continue;
+                }
+                let neighbor_level = channel.level(lights.get(neighbor));
+                if neighbor_level == 0 {
+                    continue;
+                }
+                if neighbor_level < old_level
+                    || channel.is_unattenuated(old_level, neighbor_level, offset)
+                {
+                    set_level(lights, channel, neighbor, 0, written);
+                    self.removal_queue.push_back((neighbor, neighbor_level));
+                    self.removed.push(neighbor);
+                } else {
+                    self.increase_queue.push_back(neighbor);
+                }
+            }
+        }
+
+        // Removed voxels might be sources themselves.
+        let sky_y = self.bounds.max().y();
+        for p in self.removed.drain(..) {
+            let source_level = match channel {
+                LightChannel::Sun if p.y() == sky_y => {
+                    channel.propagate(MAX_LIGHT_LEVEL, DOWN, properties(p))
+                }
+                LightChannel::Sun => 0,
+                LightChannel::Point => properties(p).emission.min(MAX_LIGHT_LEVEL),
+            };
+            if source_level > channel.level(lights.get(p)) {
+                set_level(lights, channel, p, source_level, written);
+                self.increase_queue.push_back(p);
+            }
+        }
+
+        // Spread light from all of the seeds.
+        while let Some(p) = self.increase_queue.pop_front() {
+            let level = channel.level(lights.get(p));
+            if level <= 1 {
+                continue;
+            }
+            for &offset in Point3i::VON_NEUMANN_OFFSETS.iter() {
+                let neighbor = p + offset;
+                if !self.bounds.contains(neighbor) {
+                    continue;
+                }
+                let new_level = channel.propagate(level, offset, properties(neighbor));
+                if new_level > channel.level(lights.get(neighbor)) {
+                    set_level(lights, channel, neighbor, new_level, written);
+                    self.increase_queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum LightChannel {
+    Sun,
+    Point,
+}
+
+impl LightChannel {
+    fn level(self, light: Light) -> u8 {
+        match self {
+            LightChannel::Sun => light.sun(),
+            LightChannel::Point => light.point(),
+        }
+    }
+
+    fn with_level(self, light: Light, level: u8) -> Light {
+        match self {
+            LightChannel::Sun => light.with_sun(level),
+            LightChannel::Point => light.with_point(level),
+        }
+    }
+
+    /// Returns `true` iff light at `level` moving by `offset` reaches a voxel at the same level.
+    fn is_unattenuated(self, level: u8, neighbor_level: u8, offset: Point3i) -> bool {
+        self == LightChannel::Sun
+            && offset == DOWN
+            && level == MAX_LIGHT_LEVEL
+            && neighbor_level == MAX_LIGHT_LEVEL
+    }
+
+    /// The level of light that enters a voxel with `properties` from a neighbor at `level`, moving by `offset`.
+    fn propagate(self, level: u8, offset: Point3i, properties: LightProperties) -> u8 {
+        if properties.opacity >= MAX_LIGHT_LEVEL {
+            0
+        } else if self == LightChannel::Sun
+            && offset == DOWN
+            && level == MAX_LIGHT_LEVEL
+            && properties.opacity == 0
+        {
+            MAX_LIGHT_LEVEL
+        } else {
+            level.saturating_sub(1 + properties.opacity)
+        }
+    }
+}
+
+fn set_level<L>(
+    lights: &mut L,
+    channel: LightChannel,
+    p: Point3i,
+    level: u8,
+    written: &mut WrittenBounds,
+) where
+    L: for<'r> GetMut<'r, Point3i, Item = &'r mut Light>,
+{
+    let light = lights.get_mut(p);
+    *light = channel.with_level(*light, level);
+    written.add(p);
+}
+
+#[derive(Default)]
+struct WrittenBounds {
+    min_max: Option<(Point3i, Point3i)>,
+}
+
+impl WrittenBounds {
+    fn add(&mut self, p: Point3i) {
+        self.min_max = Some(match self.min_max {
+            Some((min, max)) => (min.meet(p), max.join(p)),
+            None => (p, p),
+        });
+    }
+
+    fn extent(&self) -> Option<Extent3i> {
+        self.min_max
+            .map(|(min, max)| Extent3i::from_min_and_max(min, max))
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_storage::prelude::*;
+
+    fn voxel_properties(voxel: u8) -> LightProperties {
+        match voxel {
+            0 => LightProperties::TRANSPARENT,
+            1 => LightProperties::OPAQUE,
+            2 => LightProperties {
+                emission: 0,
+                opacity: 2,
+            },
+            level => LightProperties::emitting(level - 2),
+        }
+    }
+
+    #[test]
+    fn point_light_falls_off_with_distance() {
+        let bounds = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+        let mut voxels = Array3x1::fill(bounds, 0u8);
+        // Block the sky, so only the point light is visible.
+        let roof = Extent3i::from_min_and_shape(PointN([0, 31, 0]), PointN([32, 1, 32]));
+        voxels.fill_extent(&roof, 1);
+        let torch = Point3i::fill(16);
+        *voxels.get_mut(torch) = 2 + MAX_LIGHT_LEVEL;
+
+        let mut lights = Array3x1::fill(bounds, Light::DARK);
+        let mut propagator = LightPropagator::new(bounds);
+        propagator.relight_extent(&bounds, &mut lights, |p| voxel_properties(voxels.get(p)));
+
+        for p in bounds.iter_points() {
+            let distance = (p - torch).abs().dot(Point3i::ONES) as u8;
+            let expected = if voxels.get(p) == 1 {
+                0
+            } else {
+                MAX_LIGHT_LEVEL.saturating_sub(distance)
+            };
+            assert_eq!(lights.get(p), Light::new(0, expected), "at {:?}", p);
+        }
+    }
+
+    #[test]
+    fn sunlight_shines_down_and_through_translucent_voxels() {
+        let bounds = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut voxels = Array3x1::fill(bounds, 0u8);
+        *voxels.get_mut(PointN([0, 7, 0])) = 1;
+        *voxels.get_mut(PointN([4, 5, 4])) = 2;
+
+        let mut lights = Array3x1::fill(bounds, Light::DARK);
+        let mut propagator = LightPropagator::new(bounds);
+        propagator.relight_extent(&bounds, &mut lights, |p| voxel_properties(voxels.get(p)));
+
+        assert_eq!(lights.get(PointN([4, 0, 0])).sun(), MAX_LIGHT_LEVEL);
+        assert_eq!(lights.get(PointN([0, 7, 0])).sun(), 0);
+        // Under the stone, the light comes from the side.
+        assert_eq!(lights.get(PointN([0, 0, 0])).sun(), MAX_LIGHT_LEVEL - 1);
+        // Under the translucent voxel, the light comes from the side, because it's brighter than what shines through.
+        assert_eq!(lights.get(PointN([4, 5, 4])).sun(), MAX_LIGHT_LEVEL - 3);
+        assert_eq!(lights.get(PointN([4, 4, 4])).sun(), MAX_LIGHT_LEVEL - 1);
+    }
+
+    #[test]
+    fn incremental_updates_match_relighting_everything() {
+        let bounds = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(24));
+        let mut voxels = Array3x1::fill(bounds, 0u8);
+        let floor = Extent3i::from_min_and_shape(PointN([0, 4, 0]), PointN([24, 1, 24]));
+        voxels.fill_extent(&floor, 1);
+        let ceiling = Extent3i::from_min_and_shape(PointN([0, 16, 0]), PointN([24, 1, 24]));
+        voxels.fill_extent(&ceiling, 1);
+
+        let mut lights = Array3x1::fill(bounds, Light::DARK);
+        let mut propagator = LightPropagator::new(bounds);
+        propagator.relight_extent(&bounds, &mut lights, |p| voxel_properties(voxels.get(p)));
+
+        let edits = [
+            // Place two torches and a translucent block between them.
+            (PointN([6, 8, 6]), 2 + 12),
+            (PointN([10, 8, 6]), 2 + 15),
+            (PointN([8, 8, 6]), 2),
+            // Open holes in the ceiling and floor.
+            (PointN([12, 16, 12]), 0),
+            (PointN([12, 4, 12]), 0),
+            // Remove a torch and cover one of the holes.
+            (PointN([10, 8, 6]), 0),
+            (PointN([12, 16, 12]), 1),
+            // Place a wall next to the remaining torch.
+            (PointN([7, 8, 6]), 1),
+        ];
+        for &(p, voxel) in edits.iter() {
+            *voxels.get_mut(p) = voxel;
+            let changed = propagator
+                .update(Some(p), &mut lights, |p| voxel_properties(voxels.get(p)))
+                .unwrap();
+            assert!(changed.contains(p));
+
+            let mut expected = Array3x1::fill(bounds, Light::DARK);
+            LightPropagator::new(bounds)
+                .relight_extent(&bounds, &mut expected, |p| voxel_properties(voxels.get(p)));
+            assert_eq!(lights, expected, "after setting {:?} to {}", p, voxel);
+        }
+
+        // Nothing changes if the voxel didn't change.
+        let before = lights.clone();
+        propagator.update(Some(PointN([20, 20, 20])), &mut lights, |p| {
+            voxel_properties(voxels.get(p))
+        });
+        assert_eq!(lights, before);
+    }
+}
@@ -0,0 +1,309 @@
+//! Nearest-point queries on an `OctreeSet`, e.g. for snapping tools, spawn placement, or AI target selection.
+//!
+//! The queries do a best-first traversal of the octree, ordered by the distance from the query point to each octant, so only
+//! the octants that could contain one of the nearest points are ever visited. An array can be queried by first converting it
+//! with `OctreeSet::from_array3`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//! use building_blocks_search::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+//! let mut octree = OctreeSet::new_empty(extent);
+//! octree.add_extent(&Extent3i::from_min_and_shape(Point3i::fill(8), Point3i::fill(8)));
+//!
+//! assert_eq!(nearest_occupied(&octree, PointN([0, 10, 10])), Some(PointN([8, 10, 10])));
+//! assert_eq!(k_nearest_occupied(&octree, PointN([0, 8, 8]), 3).len(), 3);
+//!
+//! // From inside of the solid, the nearest surface point is on the closest face.
+//! assert_eq!(nearest_surface_point(&octree, PointN([9, 11, 11])), Some(PointN([8, 11, 11])));
+//! ```
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{OctreeNode, OctreeOctant, OctreeSet};
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Returns the point of `octree` nearest to `p` (by Euclidean distance), if `octree` is not empty.
+pub fn nearest_occupied(octree: &OctreeSet, p: Point3i) -> Option<Point3i> {
+    k_nearest_occupied(octree, p, 1).pop()
+}
+
+/// Returns the `k` points of `octree` nearest to `p` (by Euclidean distance), sorted from nearest to farthest. Fewer points are
+/// returned if `octree` has fewer than `k` points. Points at equal distances are returned in an arbitrary order.
+pub fn k_nearest_occupied(octree: &OctreeSet, p: Point3i, k: usize) -> Vec<Point3i> {
+    nearest_points(octree, p, k, |_| true, |_| true)
+}
+
+/// Returns the surface point of `octree` nearest to `p` (by Euclidean distance), if there is one. A surface point is in
+/// `octree` and has a face neighbor that is not. Points outside of the octree's extent are considered empty.
+pub fn nearest_surface_point(octree: &OctreeSet, p: Point3i) -> Option<Point3i> {
+    nearest_points(
+        octree,
+        p,
+        1,
+        |point| {
+            Point3i::VON_NEUMANN_OFFSETS
+                .iter()
+                .any(|offset| !octree.contains_point(point + *offset))
+        },
+        // Only points on the boundary of a full octant can have an empty neighbor.
+        |sub_octant| sub_octant.touches_boundary(),
+    )
+    .pop()
+}
+
+fn nearest_points(
+    octree: &OctreeSet,
+    p: Point3i,
+    k: usize,
+    accept_point: impl Fn(Point3i) -> bool,
+    keep_full_octant: impl Fn(&FullOctant) -> bool,
+) -> Vec<Point3i> {
+    let mut nearest = Vec::new();
+    if k == 0 {
+        return nearest;
+    }
+
+    let mut queue = BinaryHeap::new();
+    if let Some(root) = octree.root_node() {
+        queue.push(QueueItem::new(p, Candidate::Node(root)));
+    }
+
+    while let Some(QueueItem { candidate, .. }) = queue.pop() {
+        match candidate {
+            Candidate::Point(point) => {
+                nearest.push(point);
+                if nearest.len() == k {
+                    break;
+                }
+            }
+            Candidate::Node(node) => {
+                if node.is_full() {
+                    queue.push(QueueItem::new(
+                        p,
+                        Candidate::FullOctant(FullOctant {
+                            octant: OctreeOctant(*node.octant()),
+                            full_extent: Extent3i::from(*node.octant()),
+                        }),
+                    ));
+                } else {
+                    for child_index in 0..8 {
+                        if let Some(child) = octree.get_child(&node, child_index) {
+                            queue.push(QueueItem::new(p, Candidate::Node(child)));
+                        }
+                    }
+                }
+            }
+            Candidate::FullOctant(full) => {
+                if full.octant.is_single_voxel() {
+                    let point = full.octant.minimum();
+                    if accept_point(point) {
+                        queue.push(QueueItem::new(p, Candidate::Point(point)));
+                    }
+                } else {
+                    for child_index in 0..8 {
+                        let child = FullOctant {
+                            octant: full.octant.child(child_index),
+                            full_extent: full.full_extent,
+                        };
+                        if keep_full_octant(&child) {
+                            queue.push(QueueItem::new(p, Candidate::FullOctant(child)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    nearest
+}
+
+// An octant inside of a full octree node, which spans `full_extent`.
+struct FullOctant {
+    octant: OctreeOctant,
+    full_extent: Extent3i,
+}
+
+impl FullOctant {
+    fn touches_boundary(&self) -> bool {
+        let extent = Extent3i::from(self.octant.0);
+        let (min, max) = (extent.minimum, extent.max());
+        let (full_min, full_max) = (self.full_extent.minimum, self.full_extent.max());
+
+        (0..3).any(|i| min.at(i) == full_min.at(i) || max.at(i) == full_max.at(i))
+    }
+}
+
+enum Candidate {
+    Node(OctreeNode),
+    FullOctant(FullOctant),
+    Point(Point3i),
+}
+
+// Ordered so that `BinaryHeap` pops the nearest candidate first. The distance is a lower bound on the distance to any point in
+// the candidate, which is exact for points.
+struct QueueItem {
+    distance_squared: i64,
+    candidate: Candidate,
+}
+
+impl QueueItem {
+    fn new(p: Point3i, candidate: Candidate) -> Self {
+        let distance_squared = match &candidate {
+            Candidate::Node(node) => octant_distance_squared(p, node.octant()),
+            Candidate::FullOctant(full) => octant_distance_squared(p, &full.octant),
+            Candidate::Point(point) => {
+                octant_distance_squared(p, &Octant::new_unchecked(*point, 1))
+            }
+        };
+
+        Self {
+            distance_squared,
+            candidate,
+        }
+    }
+
+    fn is_point(&self) -> bool {
+        matches!(self.candidate, Candidate::Point(_))
+    }
+}
+
+impl PartialEq for QueueItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueueItem {}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Break ties in favor of points, so the search can stop sooner.
+        other
+            .distance_squared
+            .cmp(&self.distance_squared)
+            .then_with(|| self.is_point().cmp(&other.is_point()))
+    }
+}
+
+// The squared distance from `p` to the nearest point of `octant`.
+fn octant_distance_squared(p: Point3i, octant: &Octant) -> i64 {
+    let min = octant.minimum();
+    let max = min + Point3i::fill(octant.edge_length() - 1);
+
+    (0..3)
+        .map(|i| {
+            let d = (min.at(i) - p.at(i)).max(p.at(i) - max.at(i)).max(0) as i64;
+
+            d * d
+        })
+        .sum()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_storage::prelude::*;
+
+    fn distance_squared(a: Point3i, b: Point3i) -> i32 {
+        (a - b).dot(a - b)
+    }
+
+    fn scattered_octree() -> OctreeSet {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let array = Array3x1::fill_with(extent, |p: Point3i| {
+            (p.x() * 7 + p.y() * 3 + p.z() * 5) % 23 == 0 || p.y() < 2
+        });
+
+        OctreeSet::from_array3(&array, extent)
+    }
+
+    #[test]
+    fn k_nearest_matches_brute_force() {
+        let octree = scattered_octree();
+        let all_points = octree.collect_all_points();
+
+        for &query in [PointN([8, 8, 8]), PointN([0, 15, 3]), PointN([-5, 20, 30])].iter() {
+            let k = 10;
+            let nearest = k_nearest_occupied(&octree, query, k);
+            assert_eq!(nearest.len(), k);
+
+            let mut brute_force: Vec<i32> = all_points
+                .iter()
+                .map(|q| distance_squared(*q, query))
+                .collect();
+            brute_force.sort_unstable();
+
+            let distances: Vec<i32> = nearest
+                .iter()
+                .map(|q| distance_squared(*q, query))
+                .collect();
+            assert_eq!(distances, brute_force[..k].to_vec());
+            for q in nearest.iter() {
+                assert!(octree.contains_point(*q));
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_than_k_points() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut octree = OctreeSet::new_empty(extent);
+        assert_eq!(nearest_occupied(&octree, Point3i::ZERO), None);
+
+        octree.add_extent(&Extent3i::from_min_and_shape(
+            Point3i::fill(2),
+            Point3i::fill(1),
+        ));
+        octree.add_extent(&Extent3i::from_min_and_shape(
+            Point3i::fill(5),
+            Point3i::fill(1),
+        ));
+        assert_eq!(
+            k_nearest_occupied(&octree, Point3i::ZERO, 5),
+            vec![Point3i::fill(2), Point3i::fill(5)]
+        );
+    }
+
+    #[test]
+    fn nearest_surface_point_of_full_cube() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+        let mut octree = OctreeSet::new_empty(extent);
+        let cube = Extent3i::from_min_and_shape(Point3i::fill(8), Point3i::fill(16));
+        octree.add_extent(&cube);
+
+        // Outside of the cube.
+        assert_eq!(
+            nearest_surface_point(&octree, PointN([30, 12, 12])),
+            Some(PointN([23, 12, 12]))
+        );
+        // Inside of the cube, nearest to the -Z face.
+        assert_eq!(
+            nearest_surface_point(&octree, PointN([14, 15, 10])),
+            Some(PointN([14, 15, 8]))
+        );
+        // A cube that fills the whole octree has surface points on the boundary of the extent.
+        let full = OctreeSet::new_full(extent);
+        assert_eq!(
+            nearest_surface_point(&full, PointN([1, 16, 16])),
+            Some(PointN([0, 16, 16]))
+        );
+    }
+}
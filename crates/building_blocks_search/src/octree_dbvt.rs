@@ -61,6 +61,85 @@ where
         self.leaf_ids.contains_key(key)
     }
 
+    /// Removes the octree at `key` and inserts it again, which lets the DBVT choose a better place for it in the current tree.
+    /// Returns `false` if there is no octree at `key`.
+    pub fn remove_and_reinsert(&mut self, key: &K) -> bool {
+        if let Some(leaf_id) = self.leaf_ids.get_mut(key) {
+            let leaf = self.dbvt.remove(*leaf_id);
+            *leaf_id = self
+                .dbvt
+                .insert(DBVTLeaf::new(leaf.bounding_volume, leaf.data));
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Rebalances the tree by reinserting all of the octrees in Morton order. Incremental insertions and removals, e.g. from
+    /// loading and unloading chunks, can leave the tree unbalanced over time, which slows down queries. This is a good idea
+    /// when `stats` shows that the depth or SAH cost has grown much larger than it would be for a freshly built tree.
+    pub fn refit(&mut self) {
+        let dbvt = &mut self.dbvt;
+        let octrees: Vec<(K, OctreeSet)> = self
+            .leaf_ids
+            .drain()
+            .map(|(key, leaf_id)| (key, dbvt.remove(leaf_id).data))
+            .collect();
+        self.rebuild_from_iter(octrees);
+    }
+
+    /// Replaces all of the octrees with `octrees`. Inserting them in Morton order makes neighboring octrees end up close
+    /// together in the tree, which builds a much better tree than inserting them one at a time in an arbitrary order.
+    pub fn rebuild_from_iter(&mut self, octrees: impl IntoIterator<Item = (K, OctreeSet)>) {
+        let mut octrees: Vec<(K, OctreeSet)> = octrees.into_iter().collect();
+        octrees.sort_by_key(|(_, octree)| {
+            let extent = octree.extent();
+
+            Morton3::from(extent.minimum + extent.shape / 2)
+        });
+
+        self.dbvt = DBVT::new();
+        self.leaf_ids.clear();
+        self.leaf_ids.reserve(octrees.len());
+        for (key, octree) in octrees.into_iter() {
+            self.insert(key, octree);
+        }
+    }
+
+    /// Measures the quality of the tree.
+    pub fn stats(&self) -> OctreeDbvtStats {
+        let mut stats = OctreeDbvtStats::default();
+
+        let root = if let Some(root) = self.dbvt.root() {
+            root
+        } else {
+            return stats;
+        };
+        let root_area = aabb_surface_area(self.dbvt.content(root).0);
+
+        let mut total_depth = 0;
+        let mut stack = vec![(root, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            let (aabb, octree) = self.dbvt.content(node);
+            if root_area > 0.0 {
+                stats.sah_cost += aabb_surface_area(aabb) / root_area;
+            }
+            if octree.is_some() {
+                stats.num_octrees += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                total_depth += depth;
+            } else {
+                for i in 0..self.dbvt.num_children(node) {
+                    stack.push((self.dbvt.child(i, node), depth + 1));
+                }
+            }
+        }
+        stats.mean_depth = total_depth as f32 / stats.num_octrees as f32;
+
+        stats
+    }
+
     /// Visit every bounding volume (AABB) in the DBVT. This is a heterogeneous tree, meaning that not all nodes have the same
     /// representation. Upper nodes simply store a bounding volume (AABB), while octree nodes will provide both a bounding
     /// volume and an `Octant`, which is completely full for leaf nodes.
@@ -69,6 +148,26 @@ where
     }
 }
 
+/// Measurements of the quality of an `OctreeDbvt`, from `OctreeDbvt::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OctreeDbvtStats {
+    pub num_octrees: usize,
+    /// The greatest number of edges between the root and an octree.
+    pub max_depth: usize,
+    /// The average number of edges between the root and an octree.
+    pub mean_depth: f32,
+    /// The surface area heuristic (SAH) cost of the tree, i.e. the sum of the surface areas of all of the nodes relative to the
+    /// root, with a unit cost for traversing an internal node and for testing an octree. This approximates the expected cost of
+    /// a query, so lower is better.
+    pub sah_cost: f32,
+}
+
+fn aabb_surface_area(aabb: &AABB<f32>) -> f32 {
+    let d = aabb.maxs() - aabb.mins();
+
+    2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+}
+
 struct DbvtVisitorImpl<'a, V>(&'a mut V);
 
 impl<'a, V> OctreeVisitor for DbvtVisitorImpl<'a, V>
@@ -113,3 +212,116 @@ pub fn octant_aabb(octant: &Octant) -> AABB<f32> {
 
     AABB::new(aabb_min, aabb_max)
 }
+
+/// Returns the axis-aligned bounding box that bounds all voxels in `extent`.
+pub fn extent3i_aabb(extent: &Extent3i) -> AABB<f32> {
+    extent3f_aabb(&Extent3f::from(*extent))
+}
+
+/// Returns the axis-aligned bounding box with the same bounds as `extent`.
+pub fn extent3f_aabb(extent: &Extent3f) -> AABB<f32> {
+    AABB::new(extent.minimum.into(), extent.least_upper_bound().into())
+}
+
+/// Returns the extent with the same bounds as `aabb`.
+pub fn aabb_extent3f(aabb: &AABB<f32>) -> Extent3f {
+    let min = Point3f::from(*aabb.mins());
+    let max = Point3f::from(*aabb.maxs());
+
+    Extent3f::from_min_and_shape(min, max - min)
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_octree(key: Point3i) -> OctreeSet {
+        OctreeSet::new_full(Extent3i::from_min_and_shape(key * 16, Point3i::fill(16)))
+    }
+
+    // Keys in an order that makes incremental insertion build a poor tree.
+    fn scattered_keys() -> Vec<Point3i> {
+        let mut keys: Vec<Point3i> = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(6))
+            .iter_points()
+            .collect();
+        keys.sort_by_key(|p| (p.x() * 7 + p.y() * 13 + p.z() * 29) % 31);
+
+        keys
+    }
+
+    #[test]
+    fn stats_of_empty_tree() {
+        let dbvt = OctreeDbvt::<Point3i>::default();
+
+        assert_eq!(dbvt.stats(), OctreeDbvtStats::default());
+    }
+
+    #[test]
+    fn rebuild_keeps_all_octrees() {
+        let keys = scattered_keys();
+
+        let mut dbvt = OctreeDbvt::default();
+        dbvt.rebuild_from_iter(keys.iter().map(|&key| (key, chunk_octree(key))));
+
+        let stats = dbvt.stats();
+        assert_eq!(stats.num_octrees, keys.len());
+        assert!(stats.max_depth as f32 >= (keys.len() as f32).log2());
+        assert!(stats.mean_depth <= stats.max_depth as f32);
+        assert!(stats.sah_cost > 1.0);
+        for key in keys.iter() {
+            assert_eq!(dbvt.get(key).unwrap().extent(), chunk_octree(*key).extent());
+        }
+    }
+
+    #[test]
+    fn refit_is_no_worse_than_a_fresh_rebuild() {
+        let keys = scattered_keys();
+
+        let mut incremental = OctreeDbvt::default();
+        for &key in keys.iter() {
+            incremental.insert(key, chunk_octree(key));
+        }
+        incremental.refit();
+
+        let mut rebuilt = OctreeDbvt::default();
+        rebuilt.rebuild_from_iter(keys.iter().map(|&key| (key, chunk_octree(key))));
+
+        assert_eq!(incremental.stats(), rebuilt.stats());
+        for key in keys.iter() {
+            assert!(incremental.contains_key(key));
+        }
+    }
+
+    #[test]
+    fn remove_and_reinsert() {
+        let mut dbvt = OctreeDbvt::default();
+        dbvt.insert(Point3i::ZERO, chunk_octree(Point3i::ZERO));
+        dbvt.insert(Point3i::fill(1), chunk_octree(Point3i::fill(1)));
+
+        assert!(dbvt.remove_and_reinsert(&Point3i::ZERO));
+        assert!(!dbvt.remove_and_reinsert(&Point3i::fill(2)));
+
+        assert_eq!(dbvt.stats().num_octrees, 2);
+        assert!(dbvt.remove(&Point3i::ZERO).is_some());
+        assert_eq!(dbvt.stats().num_octrees, 1);
+    }
+
+    #[test]
+    fn extent_aabb_round_trip() {
+        let extent = Extent3i::from_min_and_shape(PointN([1, -2, 3]), PointN([4, 5, 6]));
+
+        let aabb = extent3i_aabb(&extent);
+        assert_eq!(Point3f::from(*aabb.mins()), PointN([1.0, -2.0, 3.0]));
+        assert_eq!(Point3f::from(*aabb.maxs()), PointN([5.0, 3.0, 9.0]));
+
+        assert_eq!(aabb_extent3f(&aabb), Extent3f::from(extent));
+        assert_eq!(extent3f_aabb(&aabb_extent3f(&aabb)), aabb);
+    }
+}
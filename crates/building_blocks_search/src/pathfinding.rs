@@ -1,4 +1,5 @@
 use building_blocks_core::{num::Zero, prelude::*, Point};
+use building_blocks_storage::Get;
 
 use core::cmp::Ordering;
 use core::hash::Hash;
@@ -7,29 +8,90 @@ use indexmap::IndexMap;
 use pathfinding::directed::astar::astar;
 use std::collections::BinaryHeap;
 
-/// Uses the given heuristic to do an a-star search from `start` to `finish`. All points on the path must satisfy
-/// `predicate`. Returns `Some` iff the path reaches `finish`. Otherwise, `None` is returned. The `predicate` must
-/// return the cost of moving from the node to the successor if the successor is valid. The `heuristic` function
-/// must not return a cost greater than the real cost.
-pub fn astar_path<N, C>(
+/// The set of neighbors that are considered adjacent to a point when searching a lattice.
+pub trait Connectivity<N> {
+    /// The offsets from a point to each of its neighbors.
+    fn offsets(&self) -> Vec<PointN<N>>;
+}
+
+/// Connectivity of 2D lattice points.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Connectivity2 {
+    /// Neighbors that share an edge.
+    Four,
+    /// Neighbors that share an edge or a corner.
+    Eight,
+}
+
+impl Connectivity<[i32; 2]> for Connectivity2 {
+    fn offsets(&self) -> Vec<Point2i> {
+        match self {
+            Connectivity2::Four => Point2i::VON_NEUMANN_OFFSETS.to_vec(),
+            Connectivity2::Eight => Point2i::MOORE_OFFSETS.to_vec(),
+        }
+    }
+}
+
+/// Connectivity of 3D lattice points.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Connectivity3 {
+    /// Neighbors that share a face.
+    Six,
+    /// Neighbors that share a face or an edge.
+    Eighteen,
+    /// Neighbors that share a face, an edge, or a corner.
+    TwentySix,
+}
+
+impl Connectivity<[i32; 3]> for Connectivity3 {
+    fn offsets(&self) -> Vec<Point3i> {
+        match self {
+            Connectivity3::Six => Point3i::VON_NEUMANN_OFFSETS.to_vec(),
+            Connectivity3::Eighteen => Point3i::MOORE_OFFSETS
+                .iter()
+                .filter(|p| p.x().abs() + p.y().abs() + p.z().abs() < 3)
+                .cloned()
+                .collect(),
+            Connectivity3::TwentySix => Point3i::MOORE_OFFSETS.to_vec(),
+        }
+    }
+}
+
+/// Uses the given heuristic to do an a-star search of `map` from `start` to `finish`, moving between neighbors as defined by
+/// `connectivity`. The search space is bounded by `bounds`.
+///
+/// `cost` is given the point being moved from, the point being moved to, and the value of `map` at the destination. It must
+/// return the cost of that move, or `None` if the move is not allowed. The `heuristic` function must not return a cost greater
+/// than the real cost. Returns the path and its total cost iff the path reaches `finish`.
+///
+/// `start` itself must be passable, i.e. `cost(&start, &start, map.get(start))` must not be `None`, or else there is no path.
+pub fn astar_path<N, M, C>(
+    map: &M,
+    bounds: ExtentN<N>,
     start: PointN<N>,
     finish: PointN<N>,
-    predicate: impl Fn(&PointN<N>) -> Option<C>,
+    connectivity: &impl Connectivity<N>,
+    cost: impl Fn(&PointN<N>, &PointN<N>, M::Item) -> Option<C>,
     heuristic: impl Fn(&PointN<N>) -> C,
 ) -> Option<(Vec<PointN<N>>, C)>
 where
+    M: Get<PointN<N>>,
     C: Zero + Copy + Ord,
     PointN<N>: core::hash::Hash + Eq + IntegerPoint<N>,
 {
-    predicate(&start)?;
+    if !bounds.contains(start) {
+        return None;
+    }
+    cost(&start, &start, map.get(start))?;
 
-    let vn_offsets = PointN::<N>::von_neumann_offsets();
+    let offsets = connectivity.offsets();
 
     let successors = |p: &PointN<N>| {
-        vn_offsets
+        offsets
             .iter()
             .map(|offset| *p + *offset)
-            .filter_map(|p| predicate(&p).map(|c| (p, c)))
+            .filter(|s| bounds.contains(*s))
+            .filter_map(|s| cost(p, &s, map.get(s)).map(|c| (s, c)))
             .collect::<Vec<(PointN<N>, C)>>()
     };
 
@@ -211,3 +273,125 @@ impl<K: Ord> Ord for HeuristicCostHolder<K> {
         other.estimated_cost.cmp(&self.estimated_cost)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_storage::prelude::*;
+
+    fn walled_map() -> Array2x1<bool> {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(5));
+        let mut map = Array2x1::fill(extent, false);
+        for y in 0..4 {
+            *map.get_mut(PointN([2, y])) = true;
+        }
+
+        map
+    }
+
+    fn unit_cost(_from: &Point2i, _to: &Point2i, is_wall: bool) -> Option<i32> {
+        if is_wall {
+            None
+        } else {
+            Some(1)
+        }
+    }
+
+    #[test]
+    fn astar_path_around_wall_with_four_connectivity() {
+        let map = walled_map();
+        let start = PointN([0, 0]);
+        let finish = PointN([4, 0]);
+
+        let (path, cost) = astar_path(
+            &map,
+            *map.extent(),
+            start,
+            finish,
+            &Connectivity2::Four,
+            unit_cost,
+            |p| p.l1_distance(finish),
+        )
+        .unwrap();
+
+        assert_eq!(cost, 12);
+        assert_eq!(path.len(), 13);
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&finish));
+        assert!(path.iter().all(|p| !map.get(*p)));
+    }
+
+    #[test]
+    fn astar_path_around_wall_with_eight_connectivity() {
+        let map = walled_map();
+        let start = PointN([0, 0]);
+        let finish = PointN([4, 0]);
+
+        let (path, cost) = astar_path(
+            &map,
+            *map.extent(),
+            start,
+            finish,
+            &Connectivity2::Eight,
+            unit_cost,
+            |p| {
+                let d = (*p - finish).abs();
+
+                d.x().max(d.y())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(cost, 8);
+        assert_eq!(path.last(), Some(&finish));
+    }
+
+    #[test]
+    fn astar_path_fails_when_start_is_blocked() {
+        let map = walled_map();
+        let start = PointN([2, 0]);
+        let finish = PointN([4, 0]);
+
+        let path = astar_path(
+            &map,
+            *map.extent(),
+            start,
+            finish,
+            &Connectivity2::Four,
+            unit_cost,
+            |p| p.l1_distance(finish),
+        );
+
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn astar_path_fails_when_finish_is_enclosed() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(5));
+        let mut map = Array2x1::fill(extent, false);
+        for y in 0..5 {
+            *map.get_mut(PointN([2, y])) = true;
+        }
+        let finish = PointN([4, 0]);
+
+        let result = astar_path(
+            &map,
+            extent,
+            PointN([0, 0]),
+            finish,
+            &Connectivity2::Eight,
+            unit_cost,
+            |p| p.l1_distance(finish),
+        );
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn connectivity3_offset_counts() {
+        assert_eq!(Connectivity3::Six.offsets().len(), 6);
+        assert_eq!(Connectivity3::Eighteen.offsets().len(), 18);
+        assert_eq!(Connectivity3::TwentySix.offsets().len(), 26);
+    }
+}
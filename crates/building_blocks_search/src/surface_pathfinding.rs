@@ -0,0 +1,324 @@
+use crate::pathfinding::{Connectivity, Connectivity2};
+
+use building_blocks_core::{num::Zero, prelude::*};
+use building_blocks_storage::Get;
+
+use pathfinding::directed::astar::astar;
+
+/// The limits on how an agent standing on the ground can move. The agent always stands in an empty voxel directly above a
+/// solid voxel, with `agent_height` empty voxels of clearance (including the one it's standing in).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SurfaceMovement {
+    /// Which horizontal (XZ) neighbors the agent can move to. With `Eight`, the agent can't cut corners.
+    pub horizontal: Connectivity2,
+    /// The number of empty voxels the agent needs above the ground.
+    pub agent_height: i32,
+    /// The greatest height the agent can walk up in one move, e.g. `1` for stairs.
+    pub max_step_up: i32,
+    /// The greatest height the agent can walk down in one move.
+    pub max_step_down: i32,
+    /// The greatest height the agent can jump up in one move. Rises greater than `max_step_up` and up to `jump_height` are
+    /// jumps.
+    pub jump_height: i32,
+}
+
+impl Default for SurfaceMovement {
+    fn default() -> Self {
+        Self {
+            horizontal: Connectivity2::Four,
+            agent_height: 2,
+            max_step_up: 1,
+            max_step_down: 3,
+            jump_height: 1,
+        }
+    }
+}
+
+/// The kind of move between two standing points, as given to the cost function of `surface_path`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SurfaceMove {
+    /// A move that stays at the same height.
+    Walk,
+    /// A move up by the given height, at most `max_step_up`.
+    StepUp(i32),
+    /// A move down by the given height, at most `max_step_down`.
+    StepDown(i32),
+    /// A move up by the given height, greater than `max_step_up` and at most `jump_height`.
+    Jump(i32),
+}
+
+impl SurfaceMovement {
+    /// Returns `true` iff the agent can stand at `p`, i.e. `p` is above solid ground and has `agent_height` empty voxels of
+    /// clearance. All of those voxels must be inside of `bounds`.
+    pub fn can_stand<M>(
+        &self,
+        map: &M,
+        bounds: &Extent3i,
+        is_solid: impl Fn(M::Item) -> bool,
+        p: Point3i,
+    ) -> bool
+    where
+        M: Get<Point3i>,
+    {
+        let ground = p - PointN([0, 1, 0]);
+
+        bounds.contains(ground)
+            && is_solid(map.get(ground))
+            && self.is_clear(map, bounds, &is_solid, p, p.y(), p.y() + self.agent_height)
+    }
+
+    /// Returns `true` iff the column of voxels at `p` from `min_y` up to (but not including) `lub_y` is inside of `bounds` and
+    /// empty.
+    fn is_clear<M>(
+        &self,
+        map: &M,
+        bounds: &Extent3i,
+        is_solid: &impl Fn(M::Item) -> bool,
+        p: Point3i,
+        min_y: i32,
+        lub_y: i32,
+    ) -> bool
+    where
+        M: Get<Point3i>,
+    {
+        (min_y..lub_y).all(|y| {
+            let q = PointN([p.x(), y, p.z()]);
+
+            bounds.contains(q) && !is_solid(map.get(q))
+        })
+    }
+
+    /// All of the moves the agent can make from the standing point `p`.
+    fn moves<M>(
+        &self,
+        map: &M,
+        bounds: &Extent3i,
+        is_solid: &impl Fn(M::Item) -> bool,
+        horizontal_offsets: &[Point2i],
+        p: Point3i,
+    ) -> Vec<(Point3i, SurfaceMove)>
+    where
+        M: Get<Point3i>,
+    {
+        let max_rise = self.max_step_up.max(self.jump_height);
+
+        let mut moves = Vec::new();
+        for offset in horizontal_offsets.iter() {
+            let (dx, dz) = (offset.x(), offset.y());
+            for dy in (-self.max_step_down..=max_rise).rev() {
+                let q = p + PointN([dx, dy, dz]);
+                if !self.can_stand(map, bounds, is_solid, q) {
+                    continue;
+                }
+
+                // The agent rises in its own column, moves horizontally, then descends in the destination column, so it needs
+                // clearance along the way.
+                let top = p.y().max(q.y()) + self.agent_height;
+                let mut clear = self.is_clear(map, bounds, is_solid, p, p.y(), top)
+                    && self.is_clear(map, bounds, is_solid, q, q.y(), top);
+                if dx != 0 && dz != 0 {
+                    // Don't cut corners.
+                    let bottom = p.y().max(q.y());
+                    clear = clear
+                        && self.is_clear(
+                            map,
+                            bounds,
+                            is_solid,
+                            p + PointN([dx, 0, 0]),
+                            bottom,
+                            top,
+                        )
+                        && self.is_clear(
+                            map,
+                            bounds,
+                            is_solid,
+                            p + PointN([0, 0, dz]),
+                            bottom,
+                            top,
+                        );
+                }
+                if !clear {
+                    continue;
+                }
+
+                let kind = if dy == 0 {
+                    SurfaceMove::Walk
+                } else if dy < 0 {
+                    SurfaceMove::StepDown(-dy)
+                } else if dy <= self.max_step_up {
+                    SurfaceMove::StepUp(dy)
+                } else {
+                    SurfaceMove::Jump(dy)
+                };
+                moves.push((q, kind));
+            }
+        }
+
+        moves
+    }
+}
+
+/// Uses the given heuristic to do an a-star search from `start` to `finish`, only visiting points where an agent can stand
+/// according to `movement`. A voxel of `map` is solid ground iff `is_solid` returns `true`, and the search space is bounded by
+/// `bounds`.
+///
+/// `cost` is given the point being moved from, the point being moved to, and the kind of move. It must return the cost of that
+/// move, or `None` if the move is not allowed, e.g. to make jumps more expensive or forbid stepping down onto lava. The
+/// `heuristic` function must not return a cost greater than the real cost. Returns the path and its total cost iff the path
+/// reaches `finish`.
+pub fn surface_path<M, C>(
+    map: &M,
+    bounds: Extent3i,
+    start: Point3i,
+    finish: Point3i,
+    movement: &SurfaceMovement,
+    is_solid: impl Fn(M::Item) -> bool,
+    cost: impl Fn(&Point3i, &Point3i, SurfaceMove) -> Option<C>,
+    heuristic: impl Fn(&Point3i) -> C,
+) -> Option<(Vec<Point3i>, C)>
+where
+    M: Get<Point3i>,
+    C: Zero + Copy + Ord,
+{
+    if !movement.can_stand(map, &bounds, &is_solid, start) {
+        return None;
+    }
+
+    let horizontal_offsets = movement.horizontal.offsets();
+
+    let successors = |p: &Point3i| {
+        movement
+            .moves(map, &bounds, &is_solid, &horizontal_offsets, *p)
+            .into_iter()
+            .filter_map(|(q, kind)| cost(p, &q, kind).map(|c| (q, c)))
+            .collect::<Vec<(Point3i, C)>>()
+    };
+
+    let success = |p: &Point3i| *p == finish;
+
+    astar(&start, successors, heuristic, success)
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_storage::prelude::*;
+
+    // A floor at y = 0, with room to stand at y = 1.
+    fn floor_map() -> Array3x1<bool> {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 6, 3]));
+        let mut map = Array3x1::fill(extent, false);
+        map.fill_extent(
+            &Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 1, 3])),
+            true,
+        );
+
+        map
+    }
+
+    fn find_path(
+        map: &Array3x1<bool>,
+        movement: &SurfaceMovement,
+        start: Point3i,
+        finish: Point3i,
+    ) -> Option<(Vec<Point3i>, i32)> {
+        surface_path(
+            map,
+            *map.extent(),
+            start,
+            finish,
+            movement,
+            |solid| solid,
+            |_from, _to, kind| match kind {
+                SurfaceMove::Jump(_) => Some(3),
+                _ => Some(1),
+            },
+            // Stepping down can cost less than the vertical distance, so only count the horizontal distance.
+            |p| (p.x() - finish.x()).abs() + (p.z() - finish.z()).abs(),
+        )
+    }
+
+    #[test]
+    fn walk_along_flat_floor() {
+        let map = floor_map();
+        let movement = SurfaceMovement::default();
+
+        let (path, cost) =
+            find_path(&map, &movement, PointN([0, 1, 1]), PointN([7, 1, 1])).unwrap();
+
+        assert_eq!(cost, 7);
+        assert!(path.iter().all(|p| p.y() == 1));
+    }
+
+    #[test]
+    fn cannot_stand_in_the_air_or_in_the_ground() {
+        let map = floor_map();
+        let movement = SurfaceMovement::default();
+
+        assert!(movement.can_stand(&map, map.extent(), |s| s, PointN([0, 1, 0])));
+        assert!(!movement.can_stand(&map, map.extent(), |s| s, PointN([0, 2, 0])));
+        assert!(!movement.can_stand(&map, map.extent(), |s| s, PointN([0, 0, 0])));
+        assert!(find_path(&map, &movement, PointN([0, 3, 1]), PointN([7, 1, 1])).is_none());
+    }
+
+    #[test]
+    fn step_up_and_jump_limits() {
+        let mut map = floor_map();
+        // A wall across the whole floor, 2 voxels high.
+        map.fill_extent(
+            &Extent3i::from_min_and_shape(PointN([4, 1, 0]), PointN([1, 2, 3])),
+            true,
+        );
+        let start = PointN([0, 1, 1]);
+        let finish = PointN([7, 1, 1]);
+
+        let walker = SurfaceMovement::default();
+        assert!(find_path(&map, &walker, start, finish).is_none());
+
+        let jumper = SurfaceMovement {
+            jump_height: 2,
+            ..Default::default()
+        };
+        let (path, cost) = find_path(&map, &jumper, start, finish).unwrap();
+        assert!(path.contains(&PointN([4, 3, 1])));
+        // Walk 3, jump onto the wall, step down 2, walk 2.
+        assert_eq!(cost, 3 + 3 + 1 + 2);
+
+        // The agent can't get back down if the drop is too far.
+        let cautious_jumper = SurfaceMovement {
+            jump_height: 2,
+            max_step_down: 1,
+            ..Default::default()
+        };
+        assert!(find_path(&map, &cautious_jumper, start, finish).is_none());
+    }
+
+    #[test]
+    fn clearance_is_needed_overhead() {
+        let mut map = floor_map();
+        // A low ceiling over the middle of the floor.
+        map.fill_extent(
+            &Extent3i::from_min_and_shape(PointN([3, 2, 0]), PointN([2, 1, 3])),
+            true,
+        );
+        let start = PointN([0, 1, 1]);
+        let finish = PointN([7, 1, 1]);
+
+        assert!(find_path(&map, &SurfaceMovement::default(), start, finish).is_none());
+
+        let short_agent = SurfaceMovement {
+            agent_height: 1,
+            ..Default::default()
+        };
+        let (_, cost) = find_path(&map, &short_agent, start, finish).unwrap();
+        assert_eq!(cost, 7);
+    }
+}
@@ -56,8 +56,23 @@
 //! // You can even copy from a `Fn(Point3i) -> T`.
 //! copy_extent(&subextent, &Func(|p: Point3i| p.x()), &mut map);
 //!```
+//!
+//! # Write Sparse Points
+//!
+//! Scattered edits are much faster with `WritePoints` than with one `get_mut` per point, because the points are grouped by
+//! chunk and sorted by stride before they are written.
+//! ```
+//! # use building_blocks_core::prelude::*;
+//! # use building_blocks_storage::prelude::*;
+//! # let chunk_shape = Point3i::fill(16);
+//! # let builder = ChunkMapBuilder3x1::new(chunk_shape, 0);
+//! # let mut other_map = builder.build_with_hash_map_storage();
+//! let edits = vec![(PointN([1, 2, 3]), 1), (PointN([-50, 0, 70]), 2), (PointN([1, 2, 4]), 3)];
+//! other_map.lod_view_mut(0).write_points(edits);
+//! assert_eq!(other_map.lod_view(0).get(PointN([-50, 0, 70])), 2);
+//!```
 
-use building_blocks_core::ExtentN;
+use building_blocks_core::{ExtentN, PointN};
 
 use auto_impl::auto_impl;
 
@@ -189,6 +204,8 @@ impl_get_for_tuple! { a: A, b: B, c: C }
 impl_get_for_tuple! { a: A, b: B, c: C, d: D }
 impl_get_for_tuple! { a: A, b: B, c: C, d: D, e: E }
 impl_get_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_get_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_get_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
 
 // ███████╗ ██████╗ ██████╗     ███████╗ █████╗  ██████╗██╗  ██╗
 // ██╔════╝██╔═══██╗██╔══██╗    ██╔════╝██╔══██╗██╔════╝██║  ██║
@@ -230,6 +247,16 @@ pub trait FillExtent<N> {
     fn fill_extent(&mut self, extent: &ExtentN<N>, value: Self::Item);
 }
 
+#[auto_impl(&mut)]
+pub trait WritePoints<N> {
+    type Item;
+
+    /// Writes the value of each `(point, value)` pair. If the same point is written more than once, the last value wins.
+    fn write_points<I>(&mut self, points: I)
+    where
+        I: IntoIterator<Item = (PointN<N>, Self::Item)>;
+}
+
 //  ██████╗ ██████╗ ██████╗ ██╗   ██╗
 // ██╔════╝██╔═══██╗██╔══██╗╚██╗ ██╔╝
 // ██║     ██║   ██║██████╔╝ ╚████╔╝
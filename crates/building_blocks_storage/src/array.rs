@@ -1,7 +1,7 @@
-//! N-dimensional arrays, where N is 2 or 3.
+//! N-dimensional arrays, where N is 2, 3, or 4.
 //!
-//! The domains of all arrays are located within an ambient space, a signed integer lattice where the elements are `Point2i` or
-//! `Point3i`. This means they contain data at exactly the set of points in an `ExtentN`, and no more.
+//! The domains of all arrays are located within an ambient space, a signed integer lattice where the elements are `Point2i`,
+//! `Point3i`, or `Point4i`. This means they contain data at exactly the set of points in an `ExtentN`, and no more.
 //!
 //! # Indexing
 //!
@@ -72,6 +72,20 @@
 //! This means you keep the performance of simple array indexing, as opposed to indexing with a `Point3i`, which requires 2
 //! multiplications to convert to a `Stride`. You'd be surprised how important this difference can be in tight loops.
 //!
+//! # Morton Layout
+//!
+//! `Array` stores its values in row-major order. For algorithms that visit whole neighborhoods in no particular direction,
+//! like flood fills, a `MortonArray` stores the values of a power-of-two cube along the Z-order curve instead, so that nearby
+//! points are more likely to share a cache line. It supports the same `Get*` and `ForEach*` traits (except for `Stride`
+//! coordinates) and `copy_extent` in both directions between the two layouts.
+//!
+//! # Axis Orderings
+//!
+//! Data imported from other engines or file formats may not be in row-major order. An `AxisOrderedArray3` wraps a buffer with
+//! any ordering of the X, Y, and Z axes without copying it, and it supports `Stride` coordinates with the same stride math as
+//! the meshers, so it can be meshed in place. It only supports 3D arrays, and it's a separate type so that the stride math of
+//! `Array` stays fixed and fast.
+//!
 //! # Storage
 //!
 //! By default, `Array` uses a `Vec` to store elements. But any type that implements `Deref<Target = [T]>` or `DerefMut<Target =
@@ -98,6 +112,10 @@
 //! box_array.for_each(&extent, |p: Point3i, value| assert_eq!(value, 1));
 //! ```
 //!
+//! `new_one_channel` panics if the length of the values doesn't match the extent, while `try_new_one_channel` gives the values
+//! back. For bytes, like a memory-mapped file, `from_bytes` wraps them in a `PodBytes` store that casts them to `[T]`. See the
+//! [`pod_bytes`](crate::array::channels::pod_bytes) module.
+//!
 //! # Multichannel
 //!
 //! It's often the case that you have multiple data types to store per spatial dimension. For example, you might store geometry
@@ -138,12 +156,19 @@
 //! copy_extent(&extent, &src, &mut dst);
 //! ```
 
+mod axis_order;
 mod coords;
 #[macro_use]
 mod for_each;
 mod indexer;
+mod morton_array;
+mod ortho_transform;
+mod slice;
+mod sparse;
+mod strips;
 
 pub mod channels;
+#[cfg(feature = "std")]
 pub mod compression;
 
 #[cfg(feature = "dot_vox")]
@@ -151,22 +176,34 @@ mod dot_vox_conversions;
 #[cfg(feature = "image")]
 mod image_conversions;
 
+pub use axis_order::*;
 pub use channels::*;
+#[cfg(feature = "std")]
 pub use compression::*;
 pub use coords::*;
 pub use for_each::*;
 pub use indexer::*;
+pub use morton_array::*;
+pub use ortho_transform::*;
+pub use slice::*;
 
+#[cfg(feature = "std")]
+use crate::{ChunkCopySrc, TransformMap};
 use crate::{
-    ChunkCopySrc, FillExtent, ForEach, ForEachMut, ForEachMutPtr, Get, GetMut, GetMutPtr, GetRef,
-    IntoMultiMut, IntoMultiMutPtr, MultiMutPtr, ReadExtent, TransformMap, WriteExtent,
+    FillExtent, ForEach, ForEachMut, ForEachMutPtr, Get, GetMut, GetMutPtr, GetRef, IntoMultiMut,
+    IntoMultiMutPtr, MultiMutPtr, ReadExtent, WriteExtent,
 };
 
 use building_blocks_core::prelude::*;
 
+use alloc::vec::Vec;
+use bytemuck::Pod;
 use core::iter::{once, Once};
 use core::ops::{Add, Deref};
+#[cfg(feature = "std")]
 use either::Either;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 /// A map from lattice location `PointN<N>` to data `T`, stored as a flat array.
@@ -194,16 +231,30 @@ array_n_type_alias!(ArrayNx3, A: S1, B: S2, C: S3);
 array_n_type_alias!(ArrayNx4, A: S1, B: S2, C: S3, D: S4);
 array_n_type_alias!(ArrayNx5, A: S1, B: S2, C: S3, D: S4, E: S5);
 array_n_type_alias!(ArrayNx6, A: S1, B: S2, C: S3, D: S4, E: S5, F: S6);
+array_n_type_alias!(ArrayNx7, A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7);
+array_n_type_alias!(ArrayNx8, A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7, H: S8);
+
+/// An array of `bool`s packed into bits.
+pub type BitArrayN<N, Store = Vec<u64>> = Array<N, BitChannel<Store>>;
+/// An array of palette indices.
+pub type PaletteArrayN<N, T> = Array<N, PaletteChannel<T>>;
 
 pub mod multichannel_aliases {
     use super::*;
 
+    pub type BitArray2<Store = Vec<u64>> = Array<[i32; 2], BitChannel<Store>>;
+    pub type BitArray3<Store = Vec<u64>> = Array<[i32; 3], BitChannel<Store>>;
+    pub type PaletteArray2<T> = Array<[i32; 2], PaletteChannel<T>>;
+    pub type PaletteArray3<T> = Array<[i32; 3], PaletteChannel<T>>;
+
     pub type Array2x1<A, S1 = Vec<A>> = Array<[i32; 2], Channel<A, S1>>;
     array_type_alias!(Array2x2, [i32; 2], A: S1, B: S2);
     array_type_alias!(Array2x3, [i32; 2], A: S1, B: S2, C: S3);
     array_type_alias!(Array2x4, [i32; 2], A: S1, B: S2, C: S3, D: S4);
     array_type_alias!(Array2x5, [i32; 2], A: S1, B: S2, C: S3, D: S4, E: S5);
     array_type_alias!(Array2x6, [i32; 2], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6);
+    array_type_alias!(Array2x7, [i32; 2], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7);
+    array_type_alias!(Array2x8, [i32; 2], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7, H: S8);
 
     pub type Array3x1<A, S1 = Vec<A>> = Array<[i32; 3], Channel<A, S1>>;
     array_type_alias!(Array3x2, [i32; 3], A: S1, B: S2);
@@ -211,6 +262,24 @@ pub mod multichannel_aliases {
     array_type_alias!(Array3x4, [i32; 3], A: S1, B: S2, C: S3, D: S4);
     array_type_alias!(Array3x5, [i32; 3], A: S1, B: S2, C: S3, D: S4, E: S5);
     array_type_alias!(Array3x6, [i32; 3], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6);
+    array_type_alias!(Array3x7, [i32; 3], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7);
+    array_type_alias!(Array3x8, [i32; 3], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7, H: S8);
+
+    pub type Array4x1<A, S1 = Vec<A>> = Array<[i32; 4], Channel<A, S1>>;
+    array_type_alias!(Array4x2, [i32; 4], A: S1, B: S2);
+    array_type_alias!(Array4x3, [i32; 4], A: S1, B: S2, C: S3);
+    array_type_alias!(Array4x4, [i32; 4], A: S1, B: S2, C: S3, D: S4);
+    array_type_alias!(Array4x5, [i32; 4], A: S1, B: S2, C: S3, D: S4, E: S5);
+    array_type_alias!(Array4x6, [i32; 4], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6);
+    array_type_alias!(Array4x7, [i32; 4], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7);
+    array_type_alias!(Array4x8, [i32; 4], A: S1, B: S2, C: S3, D: S4, E: S5, F: S6, G: S7, H: S8);
+
+    pub type MortonArray2x1<A, S1 = Vec<A>> = MortonArray<[i32; 2], Channel<A, S1>>;
+    pub type MortonArray3x1<A, S1 = Vec<A>> = MortonArray<[i32; 3], Channel<A, S1>>;
+    pub type MortonArray3x2<A, B, S1 = Vec<A>, S2 = Vec<B>> =
+        MortonArray<[i32; 3], (Channel<A, S1>, Channel<B, S2>)>;
+
+    pub type AxisOrderedArray3x1<A, S1 = Vec<A>> = AxisOrderedArray3<Channel<A, S1>>;
 }
 
 pub use multichannel_aliases::*;
@@ -410,6 +479,31 @@ where
 
         Self::new(extent, Channel::new(values))
     }
+
+    /// Like `new_one_channel`, but gives `values` back instead of panicking if its length doesn't match the number of points in
+    /// `extent`.
+    pub fn try_new_one_channel(extent: ExtentN<N>, values: Store) -> Result<Self, Store> {
+        if extent.num_points() != values.len() {
+            return Err(values);
+        }
+
+        Ok(Self::new(extent, Channel::new(values)))
+    }
+}
+
+impl<N, T, S> ArrayNx1<N, T, PodBytes<S, T>>
+where
+    PointN<N>: IntegerPoint<N>,
+    S: Deref<Target = [u8]>,
+    T: Pod,
+{
+    /// Views `bytes`, e.g. a memory-mapped file, as the values of an array, without copying them. Gives `bytes` back if it isn't
+    /// aligned for `T` or if it isn't exactly the size of one `T` for every point in `extent`.
+    pub fn from_bytes(extent: ExtentN<N>, bytes: S) -> Result<Self, S> {
+        let values = PodBytes::new(bytes)?;
+
+        Self::try_new_one_channel(extent, values).map_err(PodBytes::into_bytes)
+    }
 }
 
 //  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
@@ -636,6 +730,49 @@ impl_array_for_each!(
     forwarder = |_p, _stride| ();
 );
 
+#[cfg(feature = "rayon")]
+impl<N, Chan> Array<N, Chan>
+where
+    Self: ForEach<N, PointN<N>> + Sync,
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+{
+    /// Like `ForEach::for_each`, but `iter_extent` is split into slabs along the outermost axis (Y in 2D, Z in 3D), and the
+    /// slabs are iterated in parallel on the `rayon` thread pool.
+    #[inline]
+    pub fn par_for_each(
+        &self,
+        iter_extent: &ExtentN<N>,
+        f: impl Fn(PointN<N>, <Self as ForEach<N, PointN<N>>>::Item) + Sync,
+    ) {
+        let iter_extent = self.extent().intersection(iter_extent);
+        outer_axis_slabs(&iter_extent)
+            .into_par_iter()
+            .for_each(|slab| self.for_each(&slab, |p: PointN<N>, value| f(p, value)));
+    }
+}
+
+/// Splits `extent` into slabs of thickness 1 along the outermost axis of the array layout. When an extent spans the full width
+/// of an array, each of these slabs is contiguous in memory.
+#[cfg(feature = "rayon")]
+pub(crate) fn outer_axis_slabs<N>(extent: &ExtentN<N>) -> Vec<ExtentN<N>>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    if extent.is_empty() {
+        return Vec::new();
+    }
+
+    let basis = PointN::<N>::basis();
+    let outer_axis = basis.len() - 1;
+    let outer_unit = basis[outer_axis];
+    let num_slabs = extent.shape.at(outer_axis);
+    let slab_shape = extent.shape - outer_unit * (num_slabs - 1);
+
+    (0..num_slabs)
+        .map(|i| ExtentN::from_min_and_shape(extent.minimum + outer_unit * i, slab_shape))
+        .collect()
+}
+
 //  ██████╗ ██████╗ ██████╗ ██╗   ██╗
 // ██╔════╝██╔═══██╗██╔══██╗╚██╗ ██╔╝
 // ██║     ██║   ██║██████╔╝ ╚████╔╝
@@ -693,6 +830,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a, N, Chan, Delegate, F> WriteExtent<N, ArrayCopySrc<TransformMap<'a, Delegate, F>>>
     for Array<N, Chan>
 where
@@ -738,6 +876,7 @@ fn unchecked_copy_extent_between_arrays<Dst, Src, N, Ptr>(
     });
 }
 
+#[cfg(feature = "std")]
 impl<N, Chan, Ch> WriteExtent<N, ChunkCopySrc<N, Chan::Data, Ch>> for Array<N, Chan>
 where
     Self: ForEachMutPtr<N, (), Item = Chan::Ptr> + WriteExtent<N, ArrayCopySrc<Ch>>,
@@ -776,7 +915,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{copy_extent, Array2x1, Array3x1, Get};
+    use crate::{copy_extent, Array2x1, Array3x1, Array4x1, Get};
     use core::mem::MaybeUninit;
 
     #[test]
@@ -796,6 +935,30 @@ mod tests {
         assert_eq!(array.get_mut(PointN([1, 1])), &mut 1);
     }
 
+    #[test]
+    fn constructors_validate_length() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+
+        let mut values = [0u32; 8];
+        let mut array = Array3x1::try_new_one_channel(extent, &mut values[..]).unwrap();
+        *array.get_mut(Point3i::fill(1)) = 5;
+        assert_eq!(values[7], 5);
+
+        let values: Box<[u32]> = vec![0; 9].into_boxed_slice();
+        let values = Array3x1::try_new_one_channel(extent, values).unwrap_err();
+        assert_eq!(values.len(), 9);
+
+        let mut words = [0u32; 9];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut words[..]);
+        // Misaligned for u32.
+        assert!(Array3x1::<u32, _>::from_bytes(extent, &mut bytes[1..33]).is_err());
+        // Too long.
+        assert!(Array3x1::<u32, _>::from_bytes(extent, &mut bytes[..]).is_err());
+        let mut array = Array3x1::<u32, _>::from_bytes(extent, &mut bytes[..32]).unwrap();
+        *array.get_mut(Point3i::fill(1)) = 6;
+        assert_eq!(words[7], 6);
+    }
+
     #[test]
     fn fill_and_get_3d() {
         let extent = Extent3i::from_min_and_shape(Point3i::fill(1), Point3i::fill(10));
@@ -845,6 +1008,73 @@ mod tests {
         });
     }
 
+    #[test]
+    fn fill_and_get_4d() {
+        let extent = Extent4i::from_min_and_shape(Point4i::fill(1), Point4i::fill(5));
+        let mut array = Array4x1::fill(extent, 0);
+        assert_eq!(array.extent.num_points(), 625);
+        *array.get_mut(Stride(0)) = 1;
+
+        assert_eq!(array.get(Stride(0)), 1);
+        assert_eq!(array.get(Local(Point4i::ZERO)), 1);
+        assert_eq!(array.get(PointN([1, 1, 1, 1])), 1);
+
+        // The last axis has the largest stride.
+        *array.get_mut(PointN([1, 1, 1, 2])) = 2;
+        assert_eq!(array.get(Stride(125)), 2);
+    }
+
+    #[test]
+    fn for_each_and_copy_4d() {
+        let extent = Extent4i::from_min_and_shape(Point4i::ZERO, Point4i::fill(6));
+        let array =
+            Array4x1::fill_with(extent, |p| p.x() + 10 * p.y() + 100 * p.z() + 1000 * p.w());
+
+        let subextent = Extent4i::from_min_and_shape(Point4i::fill(1), PointN([2, 3, 4, 5]));
+        let mut num_visited = 0;
+        array.for_each(&subextent, |(p, s): (Point4i, Stride), value| {
+            assert!(subextent.contains(p));
+            assert_eq!(array.get(s), value);
+            assert_eq!(value, p.x() + 10 * p.y() + 100 * p.z() + 1000 * p.w());
+            num_visited += 1;
+        });
+        assert_eq!(num_visited, subextent.num_points());
+
+        let mut other_array = Array4x1::fill(extent, 0);
+        copy_extent(&subextent, &array, &mut other_array);
+        other_array.for_each(&extent, |p: Point4i, value| {
+            if subextent.contains(p) {
+                assert_eq!(value, array.get(p));
+            } else {
+                assert_eq!(value, 0);
+            }
+        });
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_for_each_visits_every_point_once() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-5), Point3i::fill(10));
+        let array = Array3x1::fill_with(extent, |p| p.x() + p.y() + p.z());
+
+        let iter_extent = Extent3i::from_min_and_shape(Point3i::fill(-10), Point3i::fill(12));
+        let num_points = AtomicI32::new(0);
+        let sum = AtomicI32::new(0);
+        array.par_for_each(&iter_extent, |p, value| {
+            assert_eq!(value, p.x() + p.y() + p.z());
+            num_points.fetch_add(1, Ordering::Relaxed);
+            sum.fetch_add(value, Ordering::Relaxed);
+        });
+
+        let mut expected_sum = 0;
+        array.for_each(&iter_extent, |_: (), value| expected_sum += value);
+
+        assert_eq!(num_points.into_inner(), 7 * 7 * 7);
+        assert_eq!(sum.into_inner(), expected_sum);
+    }
+
     #[test]
     fn uninitialized() {
         let extent = Extent3i::from_min_and_shape(Point3i::fill(1), Point3i::fill(10));
@@ -0,0 +1,667 @@
+//! 3D arrays that store their values with a custom axis ordering.
+//!
+//! An `Array` always stores its values with X as the fastest-varying axis, then Y, then Z. Other engines and file formats often
+//! disagree, e.g. Minecraft sections store X, then Z, then Y, and many scientific formats are "column-major" with Z varying
+//! fastest. An `AxisOrderedArray3` wraps an existing buffer in any of those layouts without copying it, by choosing an
+//! `Axis3Permutation` where `axes()[0]` is the fastest-varying axis and `axes()[2]` is the slowest.
+//!
+//! Since every ordering is still linear, `AxisOrderedArray3` supports `Stride` coordinates as well as the usual `Get*` and
+//! `ForEach*` traits. It overrides the stride math of `IndexedArray`, so the `Stride`-based meshers in `building_blocks_mesh`
+//! work with it directly. Iteration still visits points with X innermost, which may not be the order of memory addresses.
+//!
+//! Use `copy_extent` to convert to or from the standard layout.
+//!
+//! This is a separate type rather than a parameter of `Array` on purpose. `Array` computes strides from its shape alone,
+//! and every mesher and `ForEach` loop relies on that being cheap and branch-free, so adding a runtime ordering would slow
+//! down every `Array` for the sake of a few imported buffers. Orderings are also only defined for 3D with `Axis3Permutation`,
+//! so there is no 2D or 4D equivalent; copy those into an `Array` instead.
+//!
+//! ```
+//! use building_blocks_core::{prelude::*, Axis3Permutation};
+//! use building_blocks_storage::prelude::*;
+//!
+//! // A 16x16x16 section in XZY order, i.e. index = (y * 16 + z) * 16 + x.
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut section = vec![0u16; 16 * 16 * 16];
+//! section[(2 * 16 + 1) * 16] = 7;
+//!
+//! let array = AxisOrderedArray3x1::new_one_channel(extent, Axis3Permutation::Xzy, &section[..]);
+//! assert_eq!(array.get(PointN([0, 2, 1])), 7);
+//! assert_eq!(array.stride_from_local_point(Local(PointN([0, 0, 1]))), Stride(16));
+//!
+//! let mut row_major = Array3x1::fill(extent, 0);
+//! copy_extent(&extent, &array, &mut row_major);
+//! assert_eq!(row_major.get(PointN([0, 2, 1])), 7);
+//! ```
+
+use crate::{
+    Array3ForEach, ArrayCopySrc, ArrayForEach, ArrayStrideIter, BorrowChannels, BorrowChannelsMut,
+    Channel, Channels, CopySlices, FillChannels, FillExtent, ForEach, ForEachMut, ForEachMutPtr,
+    Get, GetMut, GetMutPtr, GetRef, IndexedArray, IntoMultiMut, Local, Local3i, MultiMutPtr,
+    ReadExtent, ResetChannels, Slices, Stride, WriteExtent,
+};
+
+#[cfg(feature = "std")]
+use crate::ChunkCopySrc;
+
+use super::Array;
+
+use building_blocks_core::{prelude::*, Axis3Permutation};
+
+use core::iter::{once, Once};
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use either::Either;
+
+/// A map from lattice location `Point3i` to data `T`, stored as a flat array with the axis ordering of an `Axis3Permutation`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AxisOrderedArray3<Chan> {
+    channels: Chan,
+    extent: Extent3i,
+    order: Axis3Permutation,
+    axis_strides: Point3i,
+}
+
+impl<Chan> AxisOrderedArray3<Chan> {
+    /// Create a new `AxisOrderedArray3` directly from the extent and values, which must already be in the given `order`.
+    pub fn new(extent: Extent3i, order: Axis3Permutation, channels: Chan) -> Self {
+        Self {
+            channels,
+            extent,
+            order,
+            axis_strides: axis_strides(extent.shape, order),
+        }
+    }
+
+    /// Moves the raw extent, axis ordering, and values storage out of `self`.
+    #[inline]
+    pub fn into_parts(self) -> (Extent3i, Axis3Permutation, Chan) {
+        (self.extent, self.order, self.channels)
+    }
+
+    /// The extent of the array.
+    #[inline]
+    pub fn extent(&self) -> &Extent3i {
+        &self.extent
+    }
+
+    /// The axis ordering of the values, from the fastest-varying axis to the slowest.
+    #[inline]
+    pub fn order(&self) -> Axis3Permutation {
+        self.order
+    }
+
+    /// The stride of a single step along each axis.
+    #[inline]
+    pub fn axis_strides(&self) -> Point3i {
+        self.axis_strides
+    }
+
+    #[inline]
+    pub fn channels(&self) -> &Chan {
+        &self.channels
+    }
+
+    #[inline]
+    pub fn channels_mut(&mut self) -> &mut Chan {
+        &mut self.channels
+    }
+
+    /// Creates a new `AxisOrderedArray3` from the return value of `selector`. `selector` takes a tuple of `Channel`s that
+    /// borrow their storage.
+    #[inline]
+    pub fn borrow_channels<'a, NewChan>(
+        &'a self,
+        selector: impl Fn(Chan::Borrowed) -> NewChan,
+    ) -> AxisOrderedArray3<NewChan>
+    where
+        Chan: BorrowChannels<'a>,
+    {
+        AxisOrderedArray3::new(self.extent, self.order, selector(self.channels.borrow()))
+    }
+
+    /// Creates a new `AxisOrderedArray3` from the return value of `selector`. `selector` takes a tuple of `Channel`s that
+    /// mutably borrow their storage.
+    #[inline]
+    pub fn borrow_channels_mut<'a, NewChan>(
+        &'a mut self,
+        selector: impl Fn(Chan::Borrowed) -> NewChan,
+    ) -> AxisOrderedArray3<NewChan>
+    where
+        Chan: BorrowChannelsMut<'a>,
+    {
+        AxisOrderedArray3::new(
+            self.extent,
+            self.order,
+            selector(self.channels.borrow_mut()),
+        )
+    }
+}
+
+impl<T, Store> AxisOrderedArray3<Channel<T, Store>>
+where
+    Store: Deref<Target = [T]>,
+{
+    /// Wraps `values`, e.g. a borrowed slice, a `Box<[T]>`, or a `Vec<T>`, without copying it. This asserts that the number of
+    /// points in the extent matches the length of `values`.
+    pub fn new_one_channel(extent: Extent3i, order: Axis3Permutation, values: Store) -> Self {
+        assert_eq!(extent.num_points(), values.len());
+
+        Self::new(extent, order, Channel::new(values))
+    }
+}
+
+impl<Chan> AxisOrderedArray3<Chan>
+where
+    Chan: FillChannels,
+{
+    /// Creates a map that fills the entire `extent` with the same `value`.
+    pub fn fill(extent: Extent3i, order: Axis3Permutation, value: Chan::Data) -> Self
+    where
+        Chan::Data: Clone,
+    {
+        Self::new(extent, order, Chan::fill(value, extent.num_points()))
+    }
+}
+
+/// The fastest-varying axis has stride 1, and each following axis steps over all of the previous axes.
+fn axis_strides(shape: Point3i, order: Axis3Permutation) -> Point3i {
+    let [a, b, c] = order.axes();
+    let mut strides = Point3i::ZERO;
+    strides.0[a.index()] = 1;
+    strides.0[b.index()] = shape.at(a.index());
+    strides.0[c.index()] = shape.at(a.index()) * shape.at(b.index());
+
+    strides
+}
+
+impl<Chan> IndexedArray<[i32; 3]> for AxisOrderedArray3<Chan> {
+    type Indexer = [i32; 3];
+
+    #[inline]
+    fn extent(&self) -> &Extent3i {
+        &self.extent
+    }
+
+    #[inline]
+    fn stride_from_local_point(&self, p: Local3i) -> Stride {
+        Stride(p.0.dot(self.axis_strides) as usize)
+    }
+
+    #[inline]
+    fn strides_from_local_points(&self, points: &[Local3i], strides: &mut [Stride]) {
+        for (stride, p) in strides.iter_mut().zip(points.iter()) {
+            *stride = self.stride_from_local_point(*p);
+        }
+    }
+
+    #[inline]
+    fn array_for_each(&self, iter_extent: Extent3i) -> Array3ForEach {
+        // Make sure we don't index out of array bounds.
+        let iter_extent = iter_extent.intersection(&self.extent);
+        let origin = Local(iter_extent.minimum - self.extent.minimum);
+
+        ArrayForEach {
+            iter_extent,
+            iter: ArrayStrideIter::new_3d_with_strides(self.axis_strides, origin, Point3i::ONES),
+        }
+    }
+}
+
+impl<Chan> FillExtent<[i32; 3]> for AxisOrderedArray3<Chan>
+where
+    Self: ForEachMutPtr<[i32; 3], Stride, Item = Chan::Ptr>,
+    Chan: ResetChannels,
+    Chan::Data: Clone,
+{
+    type Item = Chan::Data;
+
+    /// Fill the entire `extent` with the same `value`.
+    fn fill_extent(&mut self, extent: &Extent3i, value: Self::Item) {
+        if self.extent.eq(extent) {
+            self.channels.reset_values(value);
+        } else {
+            unsafe {
+                self.for_each_mut_ptr(extent, |_: Stride, v| v.write(value.clone()));
+            }
+        }
+    }
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<Chan> Get<Stride> for AxisOrderedArray3<Chan>
+where
+    Chan: Get<usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get(&self, stride: Stride) -> Self::Item {
+        self.channels.get(stride.0)
+    }
+}
+
+impl<'a, Chan> GetRef<'a, Stride> for AxisOrderedArray3<Chan>
+where
+    Chan: GetRef<'a, usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get_ref(&'a self, stride: Stride) -> Self::Item {
+        self.channels.get_ref(stride.0)
+    }
+}
+
+impl<'a, Chan> GetMut<'a, Stride> for AxisOrderedArray3<Chan>
+where
+    Chan: GetMut<'a, usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, stride: Stride) -> Self::Item {
+        self.channels.get_mut(stride.0)
+    }
+}
+
+impl<Chan> GetMutPtr<Stride> for AxisOrderedArray3<Chan>
+where
+    Chan: GetMutPtr<usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    unsafe fn get_mut_ptr(&mut self, stride: Stride) -> Self::Item {
+        self.channels.get_mut_ptr(stride.0)
+    }
+}
+
+impl<Chan> Get<Local3i> for AxisOrderedArray3<Chan>
+where
+    Self: Get<Stride>,
+{
+    type Item = <Self as Get<Stride>>::Item;
+
+    #[inline]
+    fn get(&self, p: Local3i) -> Self::Item {
+        self.get(self.stride_from_local_point(p))
+    }
+}
+
+impl<'a, Chan> GetRef<'a, Local3i> for AxisOrderedArray3<Chan>
+where
+    Self: GetRef<'a, Stride>,
+{
+    type Item = <Self as GetRef<'a, Stride>>::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: Local3i) -> Self::Item {
+        self.get_ref(self.stride_from_local_point(p))
+    }
+}
+
+impl<'a, Chan> GetMut<'a, Local3i> for AxisOrderedArray3<Chan>
+where
+    Self: GetMut<'a, Stride>,
+{
+    type Item = <Self as GetMut<'a, Stride>>::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: Local3i) -> Self::Item {
+        let stride = self.stride_from_local_point(p);
+
+        self.get_mut(stride)
+    }
+}
+
+impl<Chan> Get<Point3i> for AxisOrderedArray3<Chan>
+where
+    Self: Get<Local3i>,
+{
+    type Item = <Self as Get<Local3i>>::Item;
+
+    #[inline]
+    fn get(&self, p: Point3i) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get(Local(local_p))
+    }
+}
+
+impl<'a, Chan> GetRef<'a, Point3i> for AxisOrderedArray3<Chan>
+where
+    Self: GetRef<'a, Local3i>,
+{
+    type Item = <Self as GetRef<'a, Local3i>>::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: Point3i) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get_ref(Local(local_p))
+    }
+}
+
+impl<'a, Chan> GetMut<'a, Point3i> for AxisOrderedArray3<Chan>
+where
+    Self: GetMut<'a, Local3i>,
+{
+    type Item = <Self as GetMut<'a, Local3i>>::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: Point3i) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get_mut(Local(local_p))
+    }
+}
+
+// ███████╗ ██████╗ ██████╗     ███████╗ █████╗  ██████╗██╗  ██╗
+// ██╔════╝██╔═══██╗██╔══██╗    ██╔════╝██╔══██╗██╔════╝██║  ██║
+// █████╗  ██║   ██║██████╔╝    █████╗  ███████║██║     ███████║
+// ██╔══╝  ██║   ██║██╔══██╗    ██╔══╝  ██╔══██║██║     ██╔══██║
+// ██║     ╚██████╔╝██║  ██║    ███████╗██║  ██║╚██████╗██║  ██║
+// ╚═╝      ╚═════╝ ╚═╝  ╚═╝    ╚══════╝╚═╝  ╚═╝ ╚═════╝╚═╝  ╚═╝
+
+macro_rules! impl_axis_ordered_for_each {
+    (coords: $coords:ty; forwarder = |$p:ident, $stride:ident| $forward_coords:expr;) => {
+        impl<Chan> ForEach<[i32; 3], $coords> for AxisOrderedArray3<Chan>
+        where
+            Self: Get<Stride>,
+        {
+            type Item = <Self as Get<Stride>>::Item;
+
+            #[inline]
+            fn for_each(&self, iter_extent: &Extent3i, mut f: impl FnMut($coords, Self::Item)) {
+                let visitor = self.array_for_each(*iter_extent);
+                visitor.for_each(|$p, $stride| f($forward_coords, self.get($stride)));
+            }
+        }
+
+        impl<Chan> ForEachMutPtr<[i32; 3], $coords> for AxisOrderedArray3<Chan>
+        where
+            Self: GetMutPtr<Stride, Item = Chan::Ptr>,
+            Chan: Channels,
+        {
+            type Item = Chan::Ptr;
+
+            #[inline]
+            unsafe fn for_each_mut_ptr(
+                &mut self,
+                iter_extent: &Extent3i,
+                mut f: impl FnMut($coords, Self::Item),
+            ) {
+                let visitor = self.array_for_each(*iter_extent);
+                visitor.for_each(|$p, $stride| {
+                    f($forward_coords, self.get_mut_ptr($stride));
+                });
+            }
+        }
+
+        impl<'a, Chan> ForEachMut<'a, [i32; 3], $coords> for AxisOrderedArray3<Chan>
+        where
+            Self: ForEachMutPtr<[i32; 3], $coords, Item = Chan::Ptr>,
+            Chan: Channels,
+            Chan::Ptr: IntoMultiMut<'a>,
+        {
+            type Item = <Chan::Ptr as IntoMultiMut<'a>>::MultiMut;
+
+            #[inline]
+            fn for_each_mut(
+                &'a mut self,
+                iter_extent: &Extent3i,
+                mut f: impl FnMut($coords, Self::Item),
+            ) {
+                unsafe {
+                    self.for_each_mut_ptr(iter_extent, |c, ptr| f(c, ptr.into_multi_mut()));
+                }
+            }
+        }
+    };
+}
+
+impl_axis_ordered_for_each!(
+    coords: (Point3i, Stride);
+    forwarder = |p, stride| (p, stride);
+);
+impl_axis_ordered_for_each!(
+    coords: Stride;
+    forwarder = |_p, stride| stride;
+);
+impl_axis_ordered_for_each!(
+    coords: Point3i;
+    forwarder = |p, _stride| p;
+);
+impl_axis_ordered_for_each!(
+    coords: ();
+    forwarder = |_p, _stride| ();
+);
+
+//  ██████╗ ██████╗ ██████╗ ██╗   ██╗
+// ██╔════╝██╔═══██╗██╔══██╗╚██╗ ██╔╝
+// ██║     ██║   ██║██████╔╝ ╚████╔╝
+// ██║     ██║   ██║██╔═══╝   ╚██╔╝
+// ╚██████╗╚██████╔╝██║        ██║
+//  ╚═════╝ ╚═════╝ ╚═╝        ╚═╝
+
+impl<'a, Chan: 'a> ReadExtent<'a, [i32; 3]> for AxisOrderedArray3<Chan> {
+    type Src = ArrayCopySrc<&'a AxisOrderedArray3<Chan>>;
+    type SrcIter = Once<(Extent3i, Self::Src)>;
+
+    fn read_extent(&'a self, extent: &Extent3i) -> Self::SrcIter {
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        once((in_bounds_extent, ArrayCopySrc(self)))
+    }
+}
+
+impl<'a, Data, SrcSlices, ChanSrc, ChanDst>
+    WriteExtent<[i32; 3], ArrayCopySrc<&'a AxisOrderedArray3<ChanSrc>>>
+    for AxisOrderedArray3<ChanDst>
+where
+    Self: ForEachMutPtr<[i32; 3], Point3i, Item = ChanDst::Ptr>,
+    AxisOrderedArray3<ChanSrc>: Get<Point3i, Item = Data>,
+    ChanSrc: Channels<Data = Data> + Slices<'a, Target = SrcSlices>,
+    ChanDst: Channels<Data = Data> + CopySlices<'a, Src = SrcSlices>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &Extent3i,
+        src_array: ArrayCopySrc<&'a AxisOrderedArray3<ChanSrc>>,
+    ) {
+        // It is assumed by the interface that extent is a subset of the src array, so we only need to intersect with the
+        // destination.
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        if in_bounds_extent.eq(&self.extent)
+            && in_bounds_extent.eq(src_array.0.extent())
+            && self.order == src_array.0.order
+        {
+            // Fast path: both arrays have exactly the same layout.
+            self.channels.copy_slices(src_array.0.channels.slices());
+        } else {
+            unsafe {
+                self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| ptr.write(src_array.0.get(p)));
+            }
+        }
+    }
+}
+
+impl<'a, Data, ChanSrc, ChanDst> WriteExtent<[i32; 3], ArrayCopySrc<&'a Array<[i32; 3], ChanSrc>>>
+    for AxisOrderedArray3<ChanDst>
+where
+    Self: ForEachMutPtr<[i32; 3], Point3i, Item = ChanDst::Ptr>,
+    Array<[i32; 3], ChanSrc>: Get<Point3i, Item = Data>,
+    ChanDst: Channels<Data = Data>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &Extent3i,
+        src_array: ArrayCopySrc<&'a Array<[i32; 3], ChanSrc>>,
+    ) {
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        unsafe {
+            self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| ptr.write(src_array.0.get(p)));
+        }
+    }
+}
+
+impl<'a, Data, ChanSrc, ChanDst> WriteExtent<[i32; 3], ArrayCopySrc<&'a AxisOrderedArray3<ChanSrc>>>
+    for Array<[i32; 3], ChanDst>
+where
+    Self: ForEachMutPtr<[i32; 3], Point3i, Item = ChanDst::Ptr>,
+    AxisOrderedArray3<ChanSrc>: Get<Point3i, Item = Data>,
+    ChanDst: Channels<Data = Data>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &Extent3i,
+        src_array: ArrayCopySrc<&'a AxisOrderedArray3<ChanSrc>>,
+    ) {
+        let in_bounds_extent = extent.intersection(self.extent());
+
+        unsafe {
+            self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| ptr.write(src_array.0.get(p)));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Chan, Ch> WriteExtent<[i32; 3], ChunkCopySrc<[i32; 3], Chan::Data, Ch>>
+    for AxisOrderedArray3<Chan>
+where
+    Self: FillExtent<[i32; 3], Item = Chan::Data> + WriteExtent<[i32; 3], ArrayCopySrc<Ch>>,
+    Chan: Channels,
+    Chan::Data: Clone,
+{
+    fn write_extent(&mut self, extent: &Extent3i, src: ChunkCopySrc<[i32; 3], Chan::Data, Ch>) {
+        match src {
+            Either::Left(array) => self.write_extent(extent, array),
+            Either::Right(ambient) => self.fill_extent(extent, ambient.get()),
+        }
+    }
+}
+
+impl<Chan, F> WriteExtent<[i32; 3], F> for AxisOrderedArray3<Chan>
+where
+    Self: ForEachMutPtr<[i32; 3], Point3i, Item = Chan::Ptr>,
+    F: Fn(Point3i) -> Chan::Data,
+    Chan: Channels,
+{
+    fn write_extent(&mut self, extent: &Extent3i, src: F) {
+        unsafe {
+            self.for_each_mut_ptr(extent, |p, v| v.write((src)(p)));
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    const ALL_ORDERS: [Axis3Permutation; 6] = [
+        Axis3Permutation::Xyz,
+        Axis3Permutation::Zxy,
+        Axis3Permutation::Yzx,
+        Axis3Permutation::Zyx,
+        Axis3Permutation::Xzy,
+        Axis3Permutation::Yxz,
+    ];
+
+    #[test]
+    fn strides_follow_the_axis_order() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 3, 4]));
+
+        let xyz = AxisOrderedArray3x1::fill(extent, Axis3Permutation::Xyz, 0);
+        assert_eq!(xyz.axis_strides(), PointN([1, 2, 6]));
+        let zyx = AxisOrderedArray3x1::fill(extent, Axis3Permutation::Zyx, 0);
+        assert_eq!(zyx.axis_strides(), PointN([12, 4, 1]));
+        let xzy = AxisOrderedArray3x1::fill(extent, Axis3Permutation::Xzy, 0);
+        assert_eq!(xzy.axis_strides(), PointN([1, 8, 2]));
+
+        // Every ordering visits each stride exactly once.
+        for &order in ALL_ORDERS.iter() {
+            let array = AxisOrderedArray3x1::fill(extent, order, 0);
+            let mut strides = Vec::new();
+            array.for_each(&extent, |(p, stride): (Point3i, Stride), _| {
+                assert_eq!(stride, array.stride_from_local_point(Local(p)));
+                strides.push(stride.0);
+            });
+            strides.sort_unstable();
+            assert_eq!(strides, (0..extent.num_points()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn copy_between_layouts() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-2), PointN([3, 4, 5]));
+        let row_major = Array3x1::fill_with(extent, |p| p.x() * 100 + p.y() * 10 + p.z());
+
+        for &order in ALL_ORDERS.iter() {
+            let mut ordered = AxisOrderedArray3x1::fill(extent, order, 0);
+            copy_extent(&extent, &row_major, &mut ordered);
+            ordered.for_each(&extent, |p: Point3i, value| {
+                assert_eq!(value, row_major.get(p));
+            });
+
+            // Partial writes only touch the requested extent.
+            let subextent = Extent3i::from_min_and_shape(Point3i::fill(-1), Point3i::fill(2));
+            ordered.fill_extent(&subextent, -1);
+            ordered.for_each(&extent, |p: Point3i, value| {
+                let expected = if subextent.contains(p) {
+                    -1
+                } else {
+                    row_major.get(p)
+                };
+                assert_eq!(value, expected);
+            });
+
+            let mut back = Array3x1::fill(extent, 0);
+            copy_extent(&extent, &ordered, &mut back);
+            let mut other = AxisOrderedArray3x1::fill(extent, Axis3Permutation::Yzx, 0);
+            copy_extent(&extent, &ordered, &mut other);
+            other.for_each(&extent, |p: Point3i, value| assert_eq!(value, back.get(p)));
+        }
+    }
+
+    #[test]
+    fn wraps_foreign_buffer_without_copying() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 3, 4]));
+        // Column-major: Z varies fastest.
+        let mut data: Vec<i32> = (0..24).collect();
+
+        {
+            let mut array =
+                AxisOrderedArray3x1::new_one_channel(extent, Axis3Permutation::Zyx, &mut data[..]);
+            assert_eq!(array.get(PointN([0, 0, 1])), 1);
+            assert_eq!(array.get(PointN([0, 1, 0])), 4);
+            assert_eq!(array.get(PointN([1, 0, 0])), 12);
+            *array.get_mut(PointN([1, 2, 3])) = -1;
+        }
+        assert_eq!(data[12 + 2 * 4 + 3], -1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_length_panics() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+        AxisOrderedArray3x1::new_one_channel(extent, Axis3Permutation::Zyx, vec![0; 7]);
+    }
+}
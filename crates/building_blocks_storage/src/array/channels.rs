@@ -1,10 +1,18 @@
+pub mod bit_channel;
 pub mod channel;
+#[cfg(feature = "std")]
 pub mod compression;
 pub mod multichannel;
+pub mod palette_channel;
+pub mod pod_bytes;
 
+pub use bit_channel::*;
 pub use channel::*;
+#[cfg(feature = "std")]
 pub use compression::*;
 pub use multichannel::*;
+pub use palette_channel::*;
+pub use pod_bytes::*;
 
 use crate::MultiMutPtr;
 
@@ -13,6 +21,13 @@ pub trait Channels {
     type Data;
     type Ptr: MultiMutPtr<Data = Self::Data>;
     type UninitSelf: UninitChannels;
+
+    /// An estimate of the number of bytes of memory used by `num_values` values of these channels. By default, every value
+    /// takes `size_of::<Self::Data>()` bytes, which is exact for `Channel`s of plain data. Packed channels report their
+    /// actual storage.
+    fn estimate_bytes(&self, num_values: usize) -> usize {
+        num_values * core::mem::size_of::<Self::Data>()
+    }
 }
 
 /// Converts a tuple of channels into a tuple of slices.
@@ -49,6 +64,12 @@ pub trait BorrowChannelsMut<'a> {
     fn borrow_mut(&'a mut self) -> Self::Borrowed;
 }
 
+/// The number of values stored by a tuple of channels. If the channels have different lengths, this is the shortest one, so
+/// every offset below it is valid in every channel.
+pub trait NumValues {
+    fn num_values(&self) -> usize;
+}
+
 pub trait ResetChannels: Channels {
     fn reset_values(&mut self, value: Self::Data);
 }
@@ -0,0 +1,573 @@
+//! A `Channel`-like store for `bool` data that packs 64 values into each `u64` word.
+//!
+//! Since individual bits are not addressable, `GetMut` returns a `BitMut` proxy instead of a `&mut bool`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut solid = BitArray3::fill(extent, false);
+//!
+//! solid.get_mut(Point3i::fill(1)).set(true);
+//! assert_eq!(solid.get(Point3i::fill(1)), true);
+//! assert_eq!(solid.get(Point3i::fill(2)), false);
+//!
+//! solid.for_each_mut(&extent, |p: Point3i, mut bit| bit.set(p.x() % 2 == 0));
+//! assert_eq!(solid.get(PointN([2, 0, 0])), true);
+//! assert_eq!(solid.get(PointN([3, 0, 0])), false);
+//! ```
+
+use crate::{
+    BorrowChannels, BorrowChannelsMut, Channels, CopySlices, FillChannels, Get, GetMut, GetMutPtr,
+    GetRef, IntoMultiMut, IntoMultiMutPtr, MultiMutPtr, NumValues, ResetChannels, Slices,
+    SlicesMut, UninitChannels,
+};
+
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+const WORD_BITS: usize = 64;
+
+/// Stores one `bool` per bit of a `[u64]` store.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct BitChannel<Store = Vec<u64>> {
+    store: Store,
+    len: usize,
+}
+
+impl<'de, Store> Deserialize<'de> for BitChannel<Store>
+where
+    Store: Deserialize<'de> + Deref<Target = [u64]>,
+{
+    /// Fails if `store` doesn't have exactly `num_bit_words(len)` words, or if any bit past `len` is set.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Same format as a derived implementation.
+        #[derive(Deserialize)]
+        #[serde(rename = "BitChannel", bound = "Store: Deserialize<'de>")]
+        struct Parts<Store> {
+            store: Store,
+            len: usize,
+        }
+
+        let Parts { store, len } = Parts::deserialize(deserializer)?;
+
+        if store.len() != num_bit_words(len) {
+            return Err(de::Error::invalid_length(
+                store.len(),
+                &"num_bit_words(len) words in the BitChannel store",
+            ));
+        }
+        let used_bits_in_last_word = len % WORD_BITS;
+        if used_bits_in_last_word != 0 && store[store.len() - 1] >> used_bits_in_last_word != 0 {
+            return Err(de::Error::custom("BitChannel bits past len must be zero"));
+        }
+
+        Ok(Self { store, len })
+    }
+}
+
+/// The number of `u64` words required to store `len` bits.
+#[inline]
+pub fn num_bit_words(len: usize) -> usize {
+    (len + WORD_BITS - 1) / WORD_BITS
+}
+
+#[inline]
+fn word_and_mask(offset: usize) -> (usize, u64) {
+    (offset / WORD_BITS, 1 << (offset % WORD_BITS))
+}
+
+impl<Store> BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    /// Wrap `store` as a channel of `len` bits. `store` must have exactly `num_bit_words(len)` words.
+    #[inline]
+    pub fn new(store: Store, len: usize) -> Self {
+        assert_eq!(store.len(), num_bit_words(len));
+
+        Self { store, len }
+    }
+
+    /// The number of `bool` values in the channel.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of `true` values in the channel.
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        // Unused trailing bits are always zero.
+        self.store.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    #[inline]
+    pub fn take_store(self) -> Store {
+        self.store
+    }
+
+    #[inline]
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    #[inline]
+    fn bit(&self, offset: usize) -> bool {
+        debug_assert!(offset < self.len);
+        let (word, mask) = word_and_mask(offset);
+
+        let word = if cfg!(debug_assertions) {
+            self.store[word]
+        } else {
+            unsafe { *self.store.get_unchecked(word) }
+        };
+
+        word & mask != 0
+    }
+}
+
+impl BitChannel<Vec<u64>> {
+    pub fn fill(value: bool, length: usize) -> Self {
+        let word = if value { !0 } else { 0 };
+        let mut channel = Self::new(vec![word; num_bit_words(length)], length);
+        channel.clear_unused_bits();
+
+        channel
+    }
+}
+
+impl<Store> BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    #[inline]
+    pub fn reset_values(&mut self, value: bool) {
+        let word = if value { !0 } else { 0 };
+        self.store.fill(word);
+        self.clear_unused_bits();
+    }
+
+    // Keeps the bits past `len` zeroed so that `count_ones` and equality work on whole words.
+    fn clear_unused_bits(&mut self) {
+        let used_bits_in_last_word = self.len % WORD_BITS;
+        if used_bits_in_last_word != 0 {
+            if let Some(last) = self.store.last_mut() {
+                *last &= (1 << used_bits_in_last_word) - 1;
+            }
+        }
+    }
+}
+
+impl<Store> Channels for BitChannel<Store> {
+    type Data = bool;
+    type Ptr = BitPtr;
+    type UninitSelf = BitChannel;
+
+    fn estimate_bytes(&self, num_values: usize) -> usize {
+        num_bit_words(num_values) * core::mem::size_of::<u64>()
+    }
+}
+
+impl<Store> NumValues for BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    #[inline]
+    fn num_values(&self) -> usize {
+        // A deserialized channel might not have enough words for `len`.
+        self.len.min(self.store.len() * WORD_BITS)
+    }
+}
+
+impl<'a, Store> Slices<'a> for BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    type Target = &'a [u64];
+
+    fn slices(&'a self) -> Self::Target {
+        self.store.deref()
+    }
+}
+
+impl<'a, Store> SlicesMut<'a> for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    type Target = &'a mut [u64];
+
+    fn slices_mut(&'a mut self) -> Self::Target {
+        self.store.deref_mut()
+    }
+}
+
+impl<'a, Store> CopySlices<'a> for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    type Src = &'a [u64];
+
+    fn copy_slices(&mut self, src: Self::Src) {
+        self.store.copy_from_slice(src)
+    }
+}
+
+impl<'a, Store> BorrowChannels<'a> for BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    type Borrowed = BitChannel<&'a [u64]>;
+
+    fn borrow(&'a self) -> Self::Borrowed {
+        BitChannel::new(self.store.deref(), self.len)
+    }
+}
+
+impl<'a, Store> BorrowChannelsMut<'a> for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    type Borrowed = BitChannel<&'a mut [u64]>;
+
+    fn borrow_mut(&'a mut self) -> Self::Borrowed {
+        let len = self.len;
+
+        BitChannel::new(self.store.deref_mut(), len)
+    }
+}
+
+impl FillChannels for BitChannel {
+    fn fill(value: Self::Data, length: usize) -> Self {
+        Self::fill(value, length)
+    }
+}
+
+impl<Store> ResetChannels for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    fn reset_values(&mut self, value: Self::Data) {
+        self.reset_values(value)
+    }
+}
+
+impl UninitChannels for BitChannel {
+    type InitSelf = Self;
+
+    /// Bits don't have an uninitialized representation, so this just zeroes the store.
+    unsafe fn maybe_uninit(size: usize) -> Self {
+        Self::fill(false, size)
+    }
+
+    unsafe fn assume_init(self) -> Self::InitSelf {
+        self
+    }
+}
+
+/// A mutable pointer to a single bit in a `BitChannel`.
+#[derive(Clone, Copy, Debug)]
+pub struct BitPtr {
+    word: *mut u64,
+    mask: u64,
+}
+
+impl MultiMutPtr for BitPtr {
+    type Data = bool;
+
+    #[inline]
+    unsafe fn write(self, data: Self::Data) {
+        if data {
+            *self.word |= self.mask;
+        } else {
+            *self.word &= !self.mask;
+        }
+    }
+}
+
+impl IntoMultiMutPtr for BitPtr {
+    type Data = bool;
+    type Ptr = Self;
+
+    #[inline]
+    unsafe fn into_multi_mut_ptr(self) -> Self::Ptr {
+        self
+    }
+}
+
+impl<'a> IntoMultiMut<'a> for BitPtr {
+    type MultiMut = BitMut<'a>;
+
+    #[inline]
+    fn into_multi_mut(self) -> Self::MultiMut {
+        BitMut {
+            word: self.word,
+            mask: self.mask,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A proxy for a mutable reference to a single bit in a `BitChannel`.
+///
+/// Neighboring bits share a word, so this only holds a pointer to the word; a `&mut u64` would alias the other proxies handed
+/// out by `for_each_mut`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct BitMut<'a> {
+    word: *mut u64,
+    mask: u64,
+    marker: PhantomData<&'a mut u64>,
+}
+
+impl<'a> BitMut<'a> {
+    #[inline]
+    pub fn get(&self) -> bool {
+        unsafe { *self.word & self.mask != 0 }
+    }
+
+    #[inline]
+    pub fn set(&mut self, value: bool) {
+        unsafe {
+            if value {
+                *self.word |= self.mask;
+            } else {
+                *self.word &= !self.mask;
+            }
+        }
+    }
+
+    /// Sets the bit to `value` and returns the old value.
+    #[inline]
+    pub fn replace(&mut self, value: bool) -> bool {
+        let old = self.get();
+        self.set(value);
+
+        old
+    }
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<Store> Get<usize> for BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    type Item = bool;
+
+    #[inline]
+    fn get(&self, offset: usize) -> Self::Item {
+        self.bit(offset)
+    }
+}
+
+impl<'a, Store> GetRef<'a, usize> for BitChannel<Store>
+where
+    Store: Deref<Target = [u64]>,
+{
+    type Item = &'a bool;
+
+    #[inline]
+    fn get_ref(&'a self, offset: usize) -> Self::Item {
+        // Promoted to 'static, so we don't need any storage for the referent.
+        if self.bit(offset) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl<'a, Store> GetMut<'a, usize> for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    type Item = BitMut<'a>;
+
+    #[inline]
+    fn get_mut(&'a mut self, offset: usize) -> Self::Item {
+        debug_assert!(offset < self.len);
+        let (word, mask) = word_and_mask(offset);
+
+        BitMut {
+            word: &mut self.store[word],
+            mask,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Store> GetMutPtr<usize> for BitChannel<Store>
+where
+    Store: DerefMut<Target = [u64]>,
+{
+    type Item = BitPtr;
+
+    #[inline]
+    unsafe fn get_mut_ptr(&mut self, offset: usize) -> Self::Item {
+        debug_assert!(offset < self.len);
+        let (word, mask) = word_and_mask(offset);
+
+        BitPtr {
+            word: self.store.as_mut_ptr().add(word),
+            mask,
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        copy_extent, Array, Array3x1, Array3x2, BitArray3, Channel, FillExtent, ForEach,
+        ForEachMut, TransformMap,
+    };
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn fill_and_set_bits() {
+        let mut ch = BitChannel::fill(false, 100);
+        assert_eq!(ch.store().len(), 2);
+        assert_eq!(ch.count_ones(), 0);
+
+        ch.get_mut(3).set(true);
+        ch.get_mut(70).set(true);
+        assert!(ch.get(3));
+        assert!(ch.get(70));
+        assert!(!ch.get(4));
+        assert_eq!(ch.get_ref(70), &true);
+        assert_eq!(ch.count_ones(), 2);
+
+        assert!(ch.get_mut(3).replace(false));
+        assert_eq!(ch.count_ones(), 1);
+
+        ch.reset_values(true);
+        assert_eq!(ch.count_ones(), 100);
+        assert_eq!(ch, BitChannel::fill(true, 100));
+    }
+
+    #[test]
+    fn array_of_bits_matches_array_of_bools() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-3), Point3i::fill(7));
+        let is_solid = |p: Point3i| p.dot(p) < 9;
+
+        let bools = Array3x1::fill_with(extent, is_solid);
+        let bits = BitArray3::fill_with(extent, is_solid);
+
+        bits.for_each(&extent, |p: Point3i, bit| assert_eq!(bit, bools.get(p)));
+
+        let mut copied = BitArray3::fill(extent, false);
+        copy_extent(&extent, &bits, &mut copied);
+        assert_eq!(copied, bits);
+
+        let mut from_bools = BitArray3::fill(extent, false);
+        copy_extent(
+            &extent,
+            &TransformMap::new(&bools, |b: bool| b),
+            &mut from_bools,
+        );
+        assert_eq!(from_bools, bits);
+
+        let subextent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+        from_bools.fill_extent(&subextent, false);
+        from_bools.for_each(&extent, |p: Point3i, bit| {
+            assert_eq!(bit, is_solid(p) && !subextent.contains(p))
+        });
+    }
+
+    #[test]
+    fn multichannel_with_bits() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut array = Array3x2::fill(extent, (0u8, false));
+        let mut mixed = Array::new(
+            extent,
+            (
+                Channel::fill(0u8, extent.num_points()),
+                BitChannel::fill(false, extent.num_points()),
+            ),
+        );
+
+        mixed.for_each_mut(&extent, |p: Point3i, (id, mut solid)| {
+            *id = p.x() as u8;
+            solid.set(p.y() > 1);
+        });
+        array.for_each_mut(&extent, |p: Point3i, (id, solid)| {
+            *id = p.x() as u8;
+            *solid = p.y() > 1;
+        });
+
+        mixed.for_each(&extent, |p: Point3i, value| assert_eq!(value, array.get(p)));
+    }
+
+    #[test]
+    fn for_each_mut_on_neighboring_bits_of_one_word() {
+        // All 64 bits share a single word.
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut bits = BitArray3::fill_with(extent, |p: Point3i| p.x() % 2 == 0);
+
+        bits.for_each_mut(&extent, |p: Point3i, mut bit| {
+            assert_eq!(bit.replace(p.y() % 2 == 0), p.x() % 2 == 0);
+            assert_eq!(bit.get(), p.y() % 2 == 0);
+        });
+
+        bits.for_each(&extent, |p: Point3i, bit| assert_eq!(bit, p.y() % 2 == 0));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn compress_bits() {
+        use crate::{Compression, FastChannelsCompression, FromBytesCompression, Lz4};
+
+        let mut ch = BitChannel::fill(false, 1000);
+        ch.get_mut(999).set(true);
+
+        let compression =
+            FastChannelsCompression::<_, BitChannel>::from_bytes_compression(Lz4 { level: 10 });
+        let decompressed = compression.compress(&ch).decompress();
+
+        assert_eq!(ch, decompressed);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deserialize_checks_store_length() {
+        let mut ch = BitChannel::fill(false, 100);
+        ch.get_mut(99).set(true);
+        let bytes = bincode::serialize(&ch).unwrap();
+        assert_eq!(bincode::deserialize::<BitChannel>(&bytes).unwrap(), ch);
+
+        // The derived format of a struct is the same as a tuple of its fields.
+        let deserialize_parts = |store: Vec<u64>, len: usize| {
+            let bytes = bincode::serialize(&(store, len)).unwrap();
+            bincode::deserialize::<BitChannel>(&bytes)
+        };
+
+        assert!(deserialize_parts(vec![0; 1], 100).is_err());
+        assert!(deserialize_parts(vec![0; 3], 100).is_err());
+        assert!(deserialize_parts(vec![0, 1 << 36], 100).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     BorrowChannels, BorrowChannelsMut, Channels, CopySlices, FillChannels, GetMut, GetMutPtr,
-    GetRef, ResetChannels, Slices, SlicesMut, UninitChannels,
+    GetRef, NumValues, ResetChannels, Slices, SlicesMut, UninitChannels,
 };
 
+use alloc::{vec, vec::Vec};
 use core::mem::MaybeUninit;
 use core::ops::{Deref, DerefMut};
 use serde::{Deserialize, Serialize};
@@ -10,7 +11,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Channel<T, Store = Vec<T>> {
     store: Store,
-    marker: std::marker::PhantomData<T>,
+    marker: core::marker::PhantomData<T>,
 }
 
 impl<T, Store> Channel<T, Store> {
@@ -66,6 +67,16 @@ impl<T, Store> Channels for Channel<T, Store> {
     type UninitSelf = Channel<MaybeUninit<T>>;
 }
 
+impl<T, Store> NumValues for Channel<T, Store>
+where
+    Store: Deref<Target = [T]>,
+{
+    #[inline]
+    fn num_values(&self) -> usize {
+        self.store.len()
+    }
+}
+
 impl<'a, T: 'a, Store> Slices<'a> for Channel<T, Store>
 where
     Store: Deref<Target = [T]>,
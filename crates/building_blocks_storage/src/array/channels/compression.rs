@@ -1,9 +1,37 @@
-use crate::{BytesCompression, Channel, Compression, FromBytesCompression};
+use crate::{
+    num_bit_words, BitChannel, BytesCompression, Channel, Compression, FromBytesCompression,
+    PaletteChannel,
+};
 
-use bytemuck::{bytes_of, bytes_of_mut, cast_slice, cast_slice_mut, Pod};
+use bytemuck::{bytes_of, bytes_of_mut, cast_slice, cast_slice_mut, Pod, Zeroable};
 use std::io;
 
 /// Compresses a tuple of `Channel`s into a tuple of `FastCompressedChannel`s.
+///
+/// This uses the same `BytesCompression` for every channel. To choose a codec and level for each channel, use a tuple of
+/// single-channel compressions instead, which also implements `Compression`:
+///
+/// ```
+/// # #[cfg(feature = "lz4")]
+/// # {
+/// use building_blocks_core::prelude::*;
+/// use building_blocks_storage::{prelude::*, FastArrayCompression, FastChannelsCompression1};
+///
+/// let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+/// let chunk = Array3x2::fill(extent, (Sd8::ONE, 1u8));
+///
+/// let compression = FastArrayCompression::new((
+///     // The SDF is rewritten often, so keep it fast.
+///     FastChannelsCompression1::<_, Sd8>::new(Lz4 { level: 0 }),
+///     // The material channel is rarely touched, so compress it harder.
+///     FastChannelsCompression1::<_, u8>::new(Rle::new(Lz4 { level: 10 })),
+/// ));
+/// let mut storage = CompressibleChunkStorage::new(compression);
+/// storage.insert_chunk(ChunkKey::new(0, Point3i::ZERO), chunk);
+/// storage.compress_lru();
+/// assert_eq!(storage.len_compressed(), 1);
+/// # }
+/// ```
 pub struct FastChannelsCompression<By, Chan> {
     bytes_compression: By,
     marker: std::marker::PhantomData<Chan>,
@@ -62,8 +90,11 @@ where
         compressed_bytes.write_all(bytes_of(&data.store().len()))?;
 
         // Compress the values.
-        self.bytes_compression
-            .compress_bytes(cast_slice(data.store().as_slice()), compressed_bytes)
+        self.bytes_compression.compress_values(
+            cast_slice(data.store().as_slice()),
+            std::mem::size_of::<T>(),
+            compressed_bytes,
+        )
     }
 
     fn decompress_from_reader(mut compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
@@ -84,3 +115,83 @@ where
         Ok(Channel::new(decompressed_values))
     }
 }
+
+impl<By> Compression for FastChannelsCompression<By, BitChannel>
+where
+    By: BytesCompression,
+{
+    type Data = BitChannel;
+
+    // WARNING: Like the `Channel<T>` impl, this does not account for endianness.
+    fn compress_to_writer(
+        &self,
+        data: &Self::Data,
+        mut compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        // Start with the number of bits so we can reconstruct the channel during decompression.
+        compressed_bytes.write_all(bytes_of(&data.len()))?;
+
+        self.bytes_compression.compress_values(
+            cast_slice(data.store().as_slice()),
+            std::mem::size_of::<u64>(),
+            compressed_bytes,
+        )
+    }
+
+    fn decompress_from_reader(mut compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
+        let mut num_bits = 0usize;
+        compressed_bytes.read_exact(bytes_of_mut(&mut num_bits))?;
+
+        let mut words = vec![0u64; num_bit_words(num_bits)];
+        By::decompress_bytes(compressed_bytes, cast_slice_mut(words.as_mut_slice()))?;
+
+        Ok(BitChannel::new(words, num_bits))
+    }
+}
+
+impl<By, T> Compression for FastChannelsCompression<By, PaletteChannel<T>>
+where
+    By: BytesCompression,
+    T: Pod,
+{
+    type Data = PaletteChannel<T>;
+
+    // WARNING: Like the `Channel<T>` impl, this does not account for endianness.
+    fn compress_to_writer(
+        &self,
+        data: &Self::Data,
+        mut compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        // Start with everything but the index words. The palette is small, so it's stored uncompressed.
+        compressed_bytes.write_all(bytes_of(&data.len()))?;
+        compressed_bytes.write_all(bytes_of(&data.bits_per_index()))?;
+        compressed_bytes.write_all(bytes_of(&data.palette().len()))?;
+        compressed_bytes.write_all(cast_slice(data.palette()))?;
+        compressed_bytes.write_all(bytes_of(&data.words().len()))?;
+
+        self.bytes_compression.compress_values(
+            cast_slice(data.words()),
+            std::mem::size_of::<u64>(),
+            compressed_bytes,
+        )
+    }
+
+    fn decompress_from_reader(mut compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
+        let mut len = 0usize;
+        compressed_bytes.read_exact(bytes_of_mut(&mut len))?;
+        let mut bits_per_index = 0u8;
+        compressed_bytes.read_exact(bytes_of_mut(&mut bits_per_index))?;
+        let mut palette_len = 0usize;
+        compressed_bytes.read_exact(bytes_of_mut(&mut palette_len))?;
+        let mut palette = vec![T::zeroed(); palette_len];
+        compressed_bytes.read_exact(cast_slice_mut(palette.as_mut_slice()))?;
+
+        let mut num_words = 0usize;
+        compressed_bytes.read_exact(bytes_of_mut(&mut num_words))?;
+        let mut words = vec![0u64; num_words];
+        By::decompress_bytes(compressed_bytes, cast_slice_mut(words.as_mut_slice()))?;
+
+        PaletteChannel::from_parts(palette, bits_per_index, words, len)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
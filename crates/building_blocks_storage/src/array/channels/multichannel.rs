@@ -1,10 +1,45 @@
+//! Multiple channels of data per point, stored as a tuple of channels or as a struct that derives `Channels`.
+//!
+//! A tuple like `(Channel<Sd8>, Channel<u8>)` is the simplest way to store more than one value per point, and the `ArrayNxM` and
+//! `ChunkMapBuilderNxM` aliases use tuples of up to 8 channels. When a voxel has many channels, a struct with
+//! `#[derive(Channels)]` gives each of them a name, so code doesn't depend on the order of a tuple.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Array, Channel, Channels, ChunkMapBuilderNxM};
+//!
+//! #[derive(Channels, Clone, Debug, PartialEq)]
+//! struct Voxel {
+//!     sdf: Channel<Sd8>,
+//!     material: Channel<u8>,
+//!     light: Channel<u8>,
+//! }
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut chunk = Array::<_, Voxel>::fill(extent, (Sd8::ONE, 1, 0));
+//! *Voxel::light_mut(&mut chunk).get_mut(Point3i::fill(1)) = 15;
+//! assert_eq!(Voxel::light(&chunk).get(Point3i::fill(1)), 15);
+//! assert_eq!(chunk.get(Point3i::fill(1)), (Sd8::ONE, 1, 15));
+//!
+//! // Maps of `Voxel` chunks are built like any other multichannel map.
+//! let builder = ChunkMapBuilderNxM::<[i32; 3], _, Voxel>::new(Point3i::fill(16), (Sd8::ONE, 0, 0));
+//! let mut map = builder.build_with_hash_map_storage();
+//! map.write_chunk(ChunkKey::new(0, Point3i::ZERO), chunk);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)).2, 15);
+//! ```
+
 use crate::{
-    BorrowChannels, BorrowChannelsMut, Channel, Channels, Compression, CopySlices,
-    FastChannelsCompression, FillChannels, ResetChannels, Slices, SlicesMut, UninitChannels,
+    BorrowChannels, BorrowChannelsMut, Channel, Channels, CopySlices, FillChannels, NumValues,
+    ResetChannels, Slices, SlicesMut, UninitChannels,
 };
+#[cfg(feature = "std")]
+use crate::{Compression, FastChannelsCompression};
 
+#[cfg(feature = "std")]
 use std::io;
 
+pub use building_blocks_derive::Channels;
+
 macro_rules! impl_channels_for_tuple {
     ( $( $var1:ident, $var2:ident : $t:ident ),+ ) => {
 
@@ -13,8 +48,14 @@ macro_rules! impl_channels_for_tuple {
             $($t: Channels),+
         {
             type Data = ($($t::Data,)+);
-            type Ptr = ($(*mut $t::Data,)+);
+            type Ptr = ($($t::Ptr,)+);
             type UninitSelf = ($($t::UninitSelf,)+);
+
+            fn estimate_bytes(&self, num_values: usize) -> usize {
+                let ($($var1,)+) = self;
+
+                0$(+ $var1.estimate_bytes(num_values))+
+            }
         }
 
         impl<'a, $($t),+> Slices<'a> for ($($t,)+)
@@ -94,6 +135,17 @@ macro_rules! impl_channels_for_tuple {
             }
         }
 
+        impl<$($t),+> NumValues for ($($t,)+)
+        where
+            $($t: NumValues),+
+        {
+            fn num_values(&self) -> usize {
+                let ($($var1,)+) = self;
+
+                usize::MAX$(.min($var1.num_values()))+
+            }
+        }
+
         impl<$($t),+> ResetChannels for ($($t,)+)
         where
             $($t: ResetChannels),+
@@ -123,6 +175,7 @@ macro_rules! impl_channels_for_tuple {
             }
         }
 
+        #[cfg(feature = "std")]
         impl<$($t),+, By> Compression for FastChannelsCompression<By, ($(Channel<$t>,)+)>
         where
             $( FastChannelsCompression<By, Channel<$t>>: Compression<Data = Channel<$t>>, )+
@@ -149,6 +202,32 @@ macro_rules! impl_channels_for_tuple {
                 Ok(($($var1,)+))
             }
         }
+
+        // A tuple of compressions, one for each channel, so that each channel can have its own codec and level.
+        #[cfg(feature = "std")]
+        impl<$($t),+> Compression for ($($t,)+)
+        where
+            $($t: Compression),+
+        {
+            type Data = ($($t::Data,)+);
+
+            fn compress_to_writer(&self, data: &Self::Data, mut compressed_bytes: impl std::io::Write) -> io::Result<()> {
+                let ($($var1,)+) = self;
+                let ($($var2,)+) = data;
+
+                // Compress each channel in tuple order.
+                $( $var1.compress_to_writer($var2, &mut compressed_bytes)?; )+
+
+                Ok(())
+            }
+
+            fn decompress_from_reader(mut compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
+                // Decompress each channel in tuple order.
+                $( let $var1 = $t::decompress_from_reader(&mut compressed_bytes)?; )+
+
+                Ok(($($var1,)+))
+            }
+        }
     }
 }
 
@@ -158,7 +237,98 @@ impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C }
 impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D }
 impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E }
 impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F }
+impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F, g1, g2: G }
+impl_channels_for_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F, g1, g2: G, h1, h2: H }
+
+/// Selects one element out of a tuple, like one channel of a multichannel value, reference, or pointer.
+///
+/// The selectors `Ch0` through `Ch7` are used to project a multichannel `ChunkMap` onto a single channel with
+/// `ChunkMap::lod_channel_view`. The same selector works for every tuple type that the access traits return, so it can be used
+/// for values, references, mutable references, and pointers alike.
+pub trait SelectChannel<Tuple> {
+    type Selected;
+
+    fn select(tuple: Tuple) -> Self::Selected;
+}
+
+/// Selects the first channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch0;
+/// Selects the second channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch1;
+/// Selects the third channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch2;
+/// Selects the fourth channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch3;
+/// Selects the fifth channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch4;
+/// Selects the sixth channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch5;
+/// Selects the seventh channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch6;
+/// Selects the eighth channel of a tuple.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ch7;
+
+macro_rules! impl_select_channel {
+    ( $sel:ident => $out_var:ident, $out:ident; $( $var:ident : $t:ident ),+ ) => {
+        impl<$($t),+> SelectChannel<($($t,)+)> for $sel {
+            type Selected = $out;
+
+            #[inline]
+            fn select(tuple: ($($t,)+)) -> Self::Selected {
+                #[allow(unused_variables)]
+                let ($($var,)+) = tuple;
+
+                $out_var
+            }
+        }
+    };
+}
 
+impl_select_channel! { Ch0 => a, A; a: A, b: B }
+impl_select_channel! { Ch1 => b, B; a: A, b: B }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C, d: D }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C, d: D }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C, d: D }
+impl_select_channel! { Ch3 => d, D; a: A, b: B, c: C, d: D }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C, d: D, e: E }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C, d: D, e: E }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C, d: D, e: E }
+impl_select_channel! { Ch3 => d, D; a: A, b: B, c: C, d: D, e: E }
+impl_select_channel! { Ch4 => e, E; a: A, b: B, c: C, d: D, e: E }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch3 => d, D; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch4 => e, E; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch5 => f, F; a: A, b: B, c: C, d: D, e: E, f: F }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch3 => d, D; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch4 => e, E; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch5 => f, F; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch6 => g, G; a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_select_channel! { Ch0 => a, A; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch1 => b, B; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch2 => c, C; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch3 => d, D; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch4 => e, E; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch5 => f, F; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch6 => g, G; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+impl_select_channel! { Ch7 => h, H; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+
+#[cfg(feature = "std")]
 pub mod multichannel_aliases {
     use super::*;
 
@@ -174,8 +344,11 @@ pub mod multichannel_aliases {
     multichannel_compression_type_alias!(FastChannelsCompression4, A, B, C, D);
     multichannel_compression_type_alias!(FastChannelsCompression5, A, B, C, D, E);
     multichannel_compression_type_alias!(FastChannelsCompression6, A, B, C, D, E, F);
+    multichannel_compression_type_alias!(FastChannelsCompression7, A, B, C, D, E, F, G);
+    multichannel_compression_type_alias!(FastChannelsCompression8, A, B, C, D, E, F, G, H);
 }
 
+#[cfg(feature = "std")]
 pub use multichannel_aliases::*;
 
 // ████████╗███████╗███████╗████████╗
@@ -189,7 +362,12 @@ pub use multichannel_aliases::*;
 mod test {
     use super::*;
 
-    use crate::{FromBytesCompression, Get, GetMut, GetRef};
+    use crate::{
+        Array, Array3x3, Array3x8, FastArrayCompression, FillExtent, FromBytesCompression, Get,
+        GetMut, GetRef, NoCompression, Sd8,
+    };
+
+    use building_blocks_core::prelude::*;
 
     #[test]
     fn tuple_of_channels_can_get() {
@@ -221,4 +399,76 @@ mod test {
 
         assert_eq!(channels, decompressed_channels);
     }
+
+    #[test]
+    fn per_channel_compression() {
+        use crate::{NoCompression, Rle};
+
+        let channels = (Channel::fill(0.5f32, 10), Channel::fill(b'a', 10));
+
+        let compression = (
+            FastChannelsCompression1::<_, f32>::new(NoCompression),
+            FastChannelsCompression1::<_, u8>::new(Rle::new(NoCompression)),
+        );
+
+        let compressed_channels = compression.compress(&channels);
+        let decompressed_channels = compressed_channels.decompress();
+
+        assert_eq!(channels, decompressed_channels);
+    }
+
+    #[test]
+    fn eight_channel_tuple() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut array = Array3x8::fill(extent, (0u8, 1u16, 2u32, 3u64, 4i8, 5i16, 6i32, 7.0f32));
+        *array.get_mut(Point3i::fill(1)).6 = 8;
+
+        assert_eq!(array.get(Point3i::ZERO), (0, 1, 2, 3, 4, 5, 6, 7.0));
+        assert_eq!(array.get(Point3i::fill(1)).6, 8);
+    }
+
+    #[test]
+    fn select_one_channel_of_an_array() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut array = Array3x3::fill(extent, (0u8, 1u16, 'a'));
+
+        *array
+            .borrow_channels_mut(Ch2::select)
+            .get_mut(Point3i::fill(1)) = 'b';
+        array
+            .borrow_channels_mut(Ch0::select)
+            .fill_extent(&extent, 2);
+
+        assert_eq!(array.get(Point3i::fill(1)), (2, 1, 'b'));
+        assert_eq!(
+            array.borrow_channels(Ch2::select).get(Point3i::fill(1)),
+            'b'
+        );
+        assert_eq!(Ch1::select(array.get_ref(Point3i::ZERO)), &1);
+    }
+
+    #[derive(Channels, Clone, Debug, PartialEq)]
+    struct Voxel {
+        sdf: Channel<Sd8>,
+        material: Channel<u8>,
+        light: Channel<u8>,
+        biome: Channel<u16>,
+        wetness: Channel<u8>,
+        temperature: Channel<f32>,
+        color: Channel<[u8; 3]>,
+    }
+
+    #[test]
+    fn derived_channels_fill_with_and_compress() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let array = Array::<_, Voxel>::fill_with(extent, |p| {
+            (Sd8::ONE, p.x() as u8, 0, 7, 0, 0.5, [1, 2, 3])
+        });
+        assert_eq!(Voxel::material(&array).get(PointN([3, 0, 0])), 3);
+        assert_eq!(array.get(PointN([2, 1, 1])).3, 7);
+
+        let compression = FastArrayCompression::new(VoxelCompression::new(NoCompression));
+        let decompressed = compression.compress(&array).decompress();
+        assert_eq!(decompressed, array);
+    }
 }
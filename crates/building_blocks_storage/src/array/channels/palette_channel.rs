@@ -0,0 +1,693 @@
+//! A channel that stores each value as an index into a palette of distinct values.
+//!
+//! The indices are bit-packed, and the number of bits per index grows as new values are added to the palette. A channel with
+//! only one distinct value doesn't store any indices at all. This is a good fit for "block type" data, where a chunk usually
+//! contains only a handful of distinct values.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+//! enum Block { Air, Dirt, Stone }
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut blocks = PaletteArray3::fill(extent, Block::Air);
+//! assert_eq!(blocks.channels().bits_per_index(), 0);
+//!
+//! blocks.get_mut(Point3i::fill(1)).set(Block::Dirt);
+//! blocks.get_mut(Point3i::fill(2)).set(Block::Stone);
+//! assert_eq!(blocks.channels().bits_per_index(), 2);
+//!
+//! assert_eq!(blocks.get(Point3i::fill(0)), Block::Air);
+//! assert_eq!(blocks.get(Point3i::fill(1)), Block::Dirt);
+//! assert_eq!(blocks.get_ref(Point3i::fill(2)), &Block::Stone);
+//! ```
+
+use crate::{
+    BorrowChannels, BorrowChannelsMut, Channels, CopySlices, FillChannels, GetMut, GetMutPtr,
+    GetRef, IntoMultiMut, IntoMultiMutPtr, MultiMutPtr, NumValues, ResetChannels, Slices,
+    SlicesMut, UninitChannels,
+};
+
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+const WORD_BITS: usize = 64;
+const MAX_BITS_PER_INDEX: u8 = 16;
+
+/// Stores `len` values of type `T` as bit-packed indices into a palette.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PaletteChannel<T> {
+    palette: Vec<T>,
+    // Always a power of 2 (or 0) so that indices never straddle words.
+    bits_per_index: u8,
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl<T> PaletteChannel<T> {
+    /// The number of values in the channel.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The distinct values that may be referenced by this channel. Some of these may no longer be referenced; see
+    /// `compact_palette`.
+    #[inline]
+    pub fn palette(&self) -> &[T] {
+        &self.palette
+    }
+
+    #[inline]
+    pub fn bits_per_index(&self) -> u8 {
+        self.bits_per_index
+    }
+
+    /// The bit-packed palette indices, `bits_per_index` bits each.
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Reassembles a channel from the parts of an existing one, e.g. after decompression. Fails unless `bits_per_index` is a
+    /// supported index width that can address the whole palette, `words` holds exactly `len` indices, and every index is in
+    /// the palette.
+    pub(crate) fn from_parts(
+        palette: Vec<T>,
+        bits_per_index: u8,
+        words: Vec<u64>,
+        len: usize,
+    ) -> Result<Self, &'static str> {
+        if bits_per_index > MAX_BITS_PER_INDEX
+            || (bits_per_index != 0 && !bits_per_index.is_power_of_two())
+        {
+            return Err("PaletteChannel bits_per_index must be 0 or a power of 2 up to 16");
+        }
+        if palette.len() > 1 << MAX_BITS_PER_INDEX
+            || bits_per_index < Self::bits_for_palette_len(palette.len())
+        {
+            return Err("PaletteChannel bits_per_index is too small to address the palette");
+        }
+        if words.len() != Self::num_words(bits_per_index, len) {
+            return Err("PaletteChannel words must hold exactly len indices");
+        }
+        if (0..len).any(|offset| Self::read_index(&words, bits_per_index, offset) >= palette.len())
+        {
+            return Err("PaletteChannel index out of palette bounds");
+        }
+
+        Ok(Self {
+            palette,
+            bits_per_index,
+            words,
+            len,
+        })
+    }
+
+    /// The palette index of the value at `offset`.
+    #[inline]
+    pub fn palette_index(&self, offset: usize) -> usize {
+        debug_assert!(offset < self.len);
+
+        Self::read_index(&self.words, self.bits_per_index, offset)
+    }
+
+    #[inline]
+    fn read_index(words: &[u64], bits_per_index: u8, offset: usize) -> usize {
+        if bits_per_index == 0 {
+            return 0;
+        }
+        let bits = bits_per_index as usize;
+        let per_word = WORD_BITS / bits;
+        let shift = (offset % per_word) * bits;
+        let mask = (1u64 << bits) - 1;
+
+        ((words[offset / per_word] >> shift) & mask) as usize
+    }
+
+    #[inline]
+    fn write_index(words: &mut [u64], bits_per_index: u8, offset: usize, index: usize) {
+        if bits_per_index == 0 {
+            debug_assert_eq!(index, 0);
+            return;
+        }
+        let bits = bits_per_index as usize;
+        let per_word = WORD_BITS / bits;
+        let shift = (offset % per_word) * bits;
+        let mask = (1u64 << bits) - 1;
+
+        let word = &mut words[offset / per_word];
+        *word = (*word & !(mask << shift)) | ((index as u64) << shift);
+    }
+
+    fn num_words(bits_per_index: u8, len: usize) -> usize {
+        if bits_per_index == 0 {
+            0
+        } else {
+            let per_word = WORD_BITS / bits_per_index as usize;
+
+            (len + per_word - 1) / per_word
+        }
+    }
+
+    fn bits_for_palette_len(palette_len: usize) -> u8 {
+        if palette_len <= 1 {
+            return 0;
+        }
+        let min_bits = (WORD_BITS as u32 - ((palette_len - 1) as u64).leading_zeros()) as u8;
+        let bits = min_bits.next_power_of_two();
+        assert!(
+            bits <= MAX_BITS_PER_INDEX,
+            "PaletteChannel supports at most 2^{} distinct values",
+            MAX_BITS_PER_INDEX
+        );
+
+        bits
+    }
+
+    /// Re-encode all indices with `new_bits` bits each.
+    fn repack(&mut self, new_bits: u8, remap: impl Fn(usize) -> usize) {
+        let mut new_words = vec![0; Self::num_words(new_bits, self.len)];
+        for offset in 0..self.len {
+            let index = Self::read_index(&self.words, self.bits_per_index, offset);
+            Self::write_index(&mut new_words, new_bits, offset, remap(index));
+        }
+        self.words = new_words;
+        self.bits_per_index = new_bits;
+    }
+}
+
+impl<'de, T> Deserialize<'de> for PaletteChannel<T>
+where
+    T: Deserialize<'de>,
+{
+    /// Fails if the parts are inconsistent; see `from_parts`.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Same format as a derived implementation.
+        #[derive(Deserialize)]
+        #[serde(rename = "PaletteChannel", bound = "T: Deserialize<'de>")]
+        struct Parts<T> {
+            palette: Vec<T>,
+            bits_per_index: u8,
+            words: Vec<u64>,
+            len: usize,
+        }
+
+        let Parts {
+            palette,
+            bits_per_index,
+            words,
+            len,
+        } = Parts::deserialize(deserializer)?;
+
+        Self::from_parts(palette, bits_per_index, words, len).map_err(de::Error::custom)
+    }
+}
+
+impl<T> PaletteChannel<T>
+where
+    T: Clone + PartialEq,
+{
+    /// A channel with `length` copies of `value`. This only allocates the palette.
+    pub fn fill(value: T, length: usize) -> Self {
+        Self {
+            palette: vec![value],
+            bits_per_index: 0,
+            words: Vec::new(),
+            len: length,
+        }
+    }
+
+    /// Set all values to `value`, freeing the index storage.
+    #[inline]
+    pub fn reset_values(&mut self, value: T) {
+        *self = Self::fill(value, self.len);
+    }
+
+    /// Set the value at `offset`, adding `value` to the palette if necessary.
+    #[inline]
+    pub fn set(&mut self, offset: usize, value: T) {
+        debug_assert!(offset < self.len);
+
+        let index = self.find_or_insert_in_palette(value);
+        Self::write_index(&mut self.words, self.bits_per_index, offset, index);
+    }
+
+    /// Grows the index width so that `additional` more distinct values can be added without re-encoding the indices, e.g.
+    /// before writing new values with `for_each_mut`.
+    pub fn reserve_palette(&mut self, additional: usize) {
+        self.palette.reserve(additional);
+        let needed_bits = Self::bits_for_palette_len(self.palette.len() + additional);
+        if needed_bits > self.bits_per_index {
+            self.repack(needed_bits, |i| i);
+        }
+    }
+
+    fn find_or_insert_in_palette(&mut self, value: T) -> usize {
+        if let Some(index) = self.palette.iter().position(|v| *v == value) {
+            return index;
+        }
+
+        let index = self.palette.len();
+        self.palette.push(value);
+        let needed_bits = Self::bits_for_palette_len(self.palette.len());
+        if needed_bits > self.bits_per_index {
+            self.repack(needed_bits, |i| i);
+        }
+
+        index
+    }
+
+    /// Remove palette entries that are no longer referenced, shrinking the index width if possible.
+    pub fn compact_palette(&mut self) {
+        if self.palette.is_empty() {
+            return;
+        }
+
+        let mut used = vec![false; self.palette.len()];
+        for offset in 0..self.len {
+            used[Self::read_index(&self.words, self.bits_per_index, offset)] = true;
+        }
+
+        let mut remap = vec![0; self.palette.len()];
+        let mut new_palette = Vec::new();
+        for (i, value) in self.palette.drain(..).enumerate() {
+            if used[i] {
+                remap[i] = new_palette.len();
+                new_palette.push(value);
+            }
+        }
+        self.palette = new_palette;
+
+        let new_bits = Self::bits_for_palette_len(self.palette.len());
+        self.repack(new_bits, |i| remap[i]);
+    }
+}
+
+impl<T> Channels for PaletteChannel<T>
+where
+    T: Clone + PartialEq,
+{
+    type Data = T;
+    type Ptr = PalettePtr<T>;
+    type UninitSelf = Self;
+
+    fn estimate_bytes(&self, _num_values: usize) -> usize {
+        self.palette.len() * core::mem::size_of::<T>()
+            + self.words.len() * core::mem::size_of::<u64>()
+    }
+}
+
+impl<T> NumValues for PaletteChannel<T> {
+    #[inline]
+    fn num_values(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, T: 'a> Slices<'a> for PaletteChannel<T> {
+    type Target = &'a Self;
+
+    fn slices(&'a self) -> Self::Target {
+        self
+    }
+}
+
+impl<'a, T: 'a> SlicesMut<'a> for PaletteChannel<T> {
+    type Target = &'a mut Self;
+
+    fn slices_mut(&'a mut self) -> Self::Target {
+        self
+    }
+}
+
+impl<'a, T: 'a> CopySlices<'a> for PaletteChannel<T>
+where
+    T: Clone,
+{
+    type Src = &'a Self;
+
+    fn copy_slices(&mut self, src: Self::Src) {
+        self.clone_from(src)
+    }
+}
+
+impl<'a, T: 'a> BorrowChannels<'a> for PaletteChannel<T> {
+    type Borrowed = &'a Self;
+
+    fn borrow(&'a self) -> Self::Borrowed {
+        self
+    }
+}
+
+impl<'a, T: 'a> BorrowChannelsMut<'a> for PaletteChannel<T> {
+    type Borrowed = &'a mut Self;
+
+    fn borrow_mut(&'a mut self) -> Self::Borrowed {
+        self
+    }
+}
+
+impl<T> FillChannels for PaletteChannel<T>
+where
+    T: Clone + PartialEq,
+{
+    fn fill(value: Self::Data, length: usize) -> Self {
+        Self::fill(value, length)
+    }
+}
+
+impl<T> ResetChannels for PaletteChannel<T>
+where
+    T: Clone + PartialEq,
+{
+    fn reset_values(&mut self, value: Self::Data) {
+        self.reset_values(value)
+    }
+}
+
+impl<T> UninitChannels for PaletteChannel<T>
+where
+    T: Clone + PartialEq,
+{
+    type InitSelf = Self;
+
+    /// Creates a channel with an empty palette. Any value read before being written will panic.
+    unsafe fn maybe_uninit(size: usize) -> Self {
+        Self {
+            palette: Vec::new(),
+            bits_per_index: 0,
+            words: Vec::new(),
+            len: size,
+        }
+    }
+
+    unsafe fn assume_init(self) -> Self::InitSelf {
+        self
+    }
+}
+
+/// A mutable pointer to a single value of a `PaletteChannel`.
+pub struct PalettePtr<T> {
+    channel: *mut PaletteChannel<T>,
+    offset: usize,
+}
+
+impl<T> Clone for PalettePtr<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PalettePtr<T> {}
+
+impl<T> MultiMutPtr for PalettePtr<T>
+where
+    T: Clone + PartialEq,
+{
+    type Data = T;
+
+    #[inline]
+    unsafe fn write(self, data: Self::Data) {
+        (*self.channel).set(self.offset, data)
+    }
+}
+
+impl<T> IntoMultiMutPtr for PalettePtr<T>
+where
+    T: Clone + PartialEq,
+{
+    type Data = T;
+    type Ptr = Self;
+
+    #[inline]
+    unsafe fn into_multi_mut_ptr(self) -> Self::Ptr {
+        self
+    }
+}
+
+impl<'a, T> IntoMultiMut<'a> for PalettePtr<T>
+where
+    T: 'a,
+{
+    type MultiMut = PaletteMut<'a, T>;
+
+    #[inline]
+    fn into_multi_mut(self) -> Self::MultiMut {
+        PaletteMut {
+            channel: self.channel,
+            offset: self.offset,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A proxy for a mutable reference to a single value of a `PaletteChannel`.
+///
+/// Every value of the channel shares the same palette and index words, so this only holds a pointer to the channel, and each
+/// access goes through it. No references into the palette or the indices outlive a call, so a `set` that grows the palette or
+/// re-encodes the indices can't invalidate any other live proxy, like the ones handed out by `for_each_mut`.
+pub struct PaletteMut<'a, T> {
+    channel: *mut PaletteChannel<T>,
+    offset: usize,
+    marker: PhantomData<&'a mut PaletteChannel<T>>,
+}
+
+impl<'a, T> PaletteMut<'a, T>
+where
+    T: Clone,
+{
+    #[inline]
+    pub fn get(&self) -> T {
+        let channel = unsafe { &*self.channel };
+
+        channel.palette[channel.palette_index(self.offset)].clone()
+    }
+}
+
+impl<'a, T> PaletteMut<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    #[inline]
+    pub fn set(&mut self, value: T) {
+        unsafe { (*self.channel).set(self.offset, value) }
+    }
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<'a, T> GetRef<'a, usize> for PaletteChannel<T>
+where
+    T: 'a,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn get_ref(&'a self, offset: usize) -> Self::Item {
+        &self.palette[self.palette_index(offset)]
+    }
+}
+
+impl<'a, T> GetMut<'a, usize> for PaletteChannel<T>
+where
+    T: 'a,
+{
+    type Item = PaletteMut<'a, T>;
+
+    #[inline]
+    fn get_mut(&'a mut self, offset: usize) -> Self::Item {
+        debug_assert!(offset < self.len);
+
+        PaletteMut {
+            channel: self as *mut _,
+            offset,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> GetMutPtr<usize> for PaletteChannel<T> {
+    type Item = PalettePtr<T>;
+
+    #[inline]
+    unsafe fn get_mut_ptr(&mut self, offset: usize) -> Self::Item {
+        PalettePtr {
+            channel: self as *mut _,
+            offset,
+        }
+    }
+}
+
+impl_get_via_get_ref_and_clone!(PaletteChannel<T>, T);
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{copy_extent, Array3x1, ForEach, ForEachMut, Get, PaletteArray3};
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn index_width_grows_with_palette() {
+        let mut ch = PaletteChannel::fill(0u32, 100);
+        assert_eq!(ch.bits_per_index(), 0);
+        assert_eq!(ch.words.len(), 0);
+
+        ch.set(5, 1);
+        assert_eq!(ch.bits_per_index(), 1);
+        ch.set(6, 2);
+        assert_eq!(ch.bits_per_index(), 2);
+        for i in 3..20 {
+            ch.set(i as usize + 10, i);
+        }
+        assert_eq!(ch.bits_per_index(), 8);
+
+        assert_eq!(ch.get(0), 0);
+        assert_eq!(ch.get(5), 1);
+        assert_eq!(ch.get(6), 2);
+        for i in 3..20 {
+            assert_eq!(ch.get(i as usize + 10), i);
+        }
+    }
+
+    #[test]
+    fn compact_palette_removes_unused_values() {
+        let mut ch = PaletteChannel::fill('a', 10);
+        ch.set(0, 'b');
+        ch.set(1, 'c');
+        ch.set(0, 'a');
+        ch.set(1, 'a');
+        ch.set(2, 'd');
+        assert_eq!(ch.palette(), &['a', 'b', 'c', 'd']);
+
+        ch.compact_palette();
+        assert_eq!(ch.palette(), &['a', 'd']);
+        assert_eq!(ch.bits_per_index(), 1);
+        for i in 0..10 {
+            assert_eq!(ch.get(i), if i == 2 { 'd' } else { 'a' });
+        }
+    }
+
+    #[test]
+    fn palette_array_matches_plain_array() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-4), Point3i::fill(8));
+        let material = |p: Point3i| (p.dot(p) / 8) as u8;
+
+        let plain = Array3x1::fill_with(extent, material);
+        let palette = PaletteArray3::fill_with(extent, material);
+        palette.for_each(&extent, |p: Point3i, v| assert_eq!(v, plain.get(p)));
+
+        let mut copied = PaletteArray3::fill(extent, 0);
+        copy_extent(&extent, &palette, &mut copied);
+        copied.for_each(&extent, |p: Point3i, v| assert_eq!(v, plain.get(p)));
+
+        let mut sub = PaletteArray3::fill(extent, 0);
+        let subextent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+        copy_extent(&subextent, &palette, &mut sub);
+        sub.for_each(&extent, |p: Point3i, v| {
+            if subextent.contains(p) {
+                assert_eq!(v, plain.get(p));
+            } else {
+                assert_eq!(v, 0);
+            }
+        });
+    }
+
+    #[test]
+    fn for_each_mut_can_grow_the_palette() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let material = |p: Point3i| (p.x() + 8 * p.y()) as u16;
+
+        // Every write adds to the palette, so the indices are re-encoded several times while proxies are live.
+        let mut palette = PaletteArray3::fill(extent, 0u16);
+        palette.for_each_mut(&extent, |p: Point3i, mut v| {
+            assert_eq!(v.get(), 0);
+            v.set(material(p));
+            assert_eq!(v.get(), material(p));
+        });
+        assert_eq!(palette.channels().bits_per_index(), 8);
+        palette.for_each(&extent, |p: Point3i, v| assert_eq!(v, material(p)));
+
+        // With enough reserved bits, no re-encoding happens.
+        let mut reserved = PaletteArray3::fill(extent, 0u16);
+        reserved.channels_mut().reserve_palette(64);
+        assert_eq!(reserved.channels().bits_per_index(), 8);
+        reserved.for_each_mut(&extent, |p: Point3i, mut v| v.set(material(p)));
+        assert_eq!(reserved.channels().bits_per_index(), 8);
+        reserved.for_each(&extent, |p: Point3i, v| assert_eq!(v, material(p)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deserialize_checks_parts() {
+        let mut ch = PaletteChannel::fill(0u32, 100);
+        ch.set(5, 1);
+        ch.set(6, 2);
+        let bytes = bincode::serialize(&ch).unwrap();
+        assert_eq!(
+            bincode::deserialize::<PaletteChannel<u32>>(&bytes).unwrap(),
+            ch
+        );
+
+        // The derived format of a struct is the same as a tuple of its fields.
+        let deserialize_parts =
+            |palette: Vec<u32>, bits_per_index: u8, words: Vec<u64>, len: usize| {
+                let bytes = bincode::serialize(&(palette, bits_per_index, words, len)).unwrap();
+                bincode::deserialize::<PaletteChannel<u32>>(&bytes)
+            };
+
+        assert!(deserialize_parts(vec![0, 1, 2], 2, ch.words.clone(), 100).is_ok());
+        // Not a supported index width.
+        assert!(deserialize_parts(vec![0, 1, 2], 3, vec![0; 5], 100).is_err());
+        // Too narrow to address the palette.
+        assert!(deserialize_parts(vec![0, 1, 2], 1, vec![0; 2], 100).is_err());
+        // The wrong number of words.
+        assert!(deserialize_parts(vec![0, 1, 2], 2, vec![0; 1], 100).is_err());
+        // An index outside of the palette.
+        assert!(deserialize_parts(vec![0, 1, 2], 2, vec![3; 4], 100).is_err());
+    }
+
+    #[cfg(feature = "snap")]
+    #[test]
+    fn compress_palette() {
+        use crate::{Compression, FastChannelsCompression, FromBytesCompression, Snappy};
+
+        let compression =
+            FastChannelsCompression::<_, PaletteChannel<u16>>::from_bytes_compression(Snappy);
+
+        let uniform = PaletteChannel::fill(7u16, 1000);
+        assert_eq!(compression.compress(&uniform).decompress(), uniform);
+
+        let mut mixed = PaletteChannel::fill(0u16, 1000);
+        for i in 0..1000 {
+            mixed.set(i, (i % 5) as u16);
+        }
+        assert_eq!(compression.compress(&mixed).decompress(), mixed);
+    }
+}
@@ -0,0 +1,86 @@
+//! Viewing a buffer of bytes, like a memory-mapped file, as a slice of plain-old-data values.
+//!
+//! Any type that derefs to `[u8]`, e.g. `memmap2::Mmap`, can be wrapped in `PodBytes` and used as the store of a `Channel`, so
+//! an `Array` can be built directly on top of bytes that came from somewhere else, without copying them into a `Vec`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! // Pretend these bytes were memory-mapped from a file of little-endian `u16`s.
+//! let file_values: Vec<u16> = (0..16 * 16 * 16).collect();
+//! let bytes: &[u8] = bytemuck::cast_slice(&file_values);
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let scan = Array3x1::<u16, _>::from_bytes(extent, bytes).unwrap();
+//! assert_eq!(scan.get(PointN([1, 1, 0])), 17);
+//!
+//! // The wrong number of bytes is rejected.
+//! let small_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+//! assert!(Array3x1::<u16, _>::from_bytes(small_extent, bytes).is_err());
+//! ```
+
+use bytemuck::{cast_slice, cast_slice_mut, try_cast_slice, Pod};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// Reinterprets the bytes of `S` as a slice of `T`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PodBytes<S, T> {
+    bytes: S,
+    marker: PhantomData<T>,
+}
+
+impl<S, T> PodBytes<S, T>
+where
+    S: Deref<Target = [u8]>,
+    T: Pod,
+{
+    /// Returns `Err(bytes)` if `bytes` is not aligned for `T` or the length of `bytes` is not a multiple of the size of `T`.
+    pub fn new(bytes: S) -> Result<Self, S> {
+        if try_cast_slice::<u8, T>(&bytes).is_err() {
+            return Err(bytes);
+        }
+
+        Ok(Self {
+            bytes,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<S, T> PodBytes<S, T> {
+    #[inline]
+    pub fn bytes(&self) -> &S {
+        &self.bytes
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> S {
+        self.bytes
+    }
+}
+
+impl<S, T> Deref for PodBytes<S, T>
+where
+    S: Deref<Target = [u8]>,
+    T: Pod,
+{
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        cast_slice(&self.bytes)
+    }
+}
+
+impl<S, T> DerefMut for PodBytes<S, T>
+where
+    S: DerefMut<Target = [u8]>,
+    T: Pod,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        cast_slice_mut(&mut self.bytes)
+    }
+}
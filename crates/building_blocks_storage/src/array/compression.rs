@@ -47,6 +47,8 @@ where
         data: &Self::Data,
         mut compressed_bytes: impl io::Write,
     ) -> io::Result<()> {
+        trace_span!("compress_array", num_points = data.extent().num_points());
+
         // First write the extent.
         compressed_bytes.write_all(bytes_of(data.extent()))?;
 
@@ -56,6 +58,8 @@ where
     }
 
     fn decompress_from_reader(mut compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
+        trace_span!("decompress_array");
+
         // First read the extent.
         let mut extent = ExtentN::from_min_and_shape(PointN::ZERO, PointN::ZERO);
         compressed_bytes.read_exact(bytes_of_mut(&mut extent))?;
@@ -83,6 +87,10 @@ pub mod multichannel_aliases {
         FastArrayCompression<N, FastChannelsCompression5<By, A, B, C, D, E>>;
     pub type FastArrayCompressionNx6<N, By, A, B, C, D, E, F> =
         FastArrayCompression<N, FastChannelsCompression6<By, A, B, C, D, E, F>>;
+    pub type FastArrayCompressionNx7<N, By, A, B, C, D, E, F, G> =
+        FastArrayCompression<N, FastChannelsCompression7<By, A, B, C, D, E, F, G>>;
+    pub type FastArrayCompressionNx8<N, By, A, B, C, D, E, F, G, H> =
+        FastArrayCompression<N, FastChannelsCompression8<By, A, B, C, D, E, F, G, H>>;
 }
 
 pub use multichannel_aliases::*;
@@ -106,6 +114,8 @@ mod test {
     use crate::Lz4;
     #[cfg(feature = "snap")]
     use crate::Snappy;
+    #[cfg(feature = "zstd")]
+    use crate::Zstd;
 
     #[cfg(feature = "snap")]
     #[test]
@@ -139,6 +149,22 @@ mod test {
         homogeneous_array_compression_rate(Lz4 { level: 10 }, 128);
     }
 
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn sphere_array_compression_rate_zstd() {
+        sphere_array_compression_rate(Zstd { level: 19 }, 32);
+        sphere_array_compression_rate(Zstd { level: 19 }, 64);
+        sphere_array_compression_rate(Zstd { level: 19 }, 128);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn homogeneous_array_compression_rate_zstd() {
+        homogeneous_array_compression_rate(Zstd { level: 19 }, 32);
+        homogeneous_array_compression_rate(Zstd { level: 19 }, 64);
+        homogeneous_array_compression_rate(Zstd { level: 19 }, 128);
+    }
+
     fn homogeneous_array_compression_rate<B: BytesCompression>(compression: B, side_length: i32) {
         let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(side_length));
         let array = Array3x1::fill_with(extent, |_p| 0u16);
@@ -1,5 +1,6 @@
 use building_blocks_core::{ConstZero, IntegerPoint, PointN};
 
+use alloc::vec::Vec;
 use core::ops::{Add, AddAssign, Deref, Mul, Sub, SubAssign};
 use num::Zero;
 use serde::{Deserialize, Serialize};
@@ -16,6 +17,8 @@ pub struct Local<N>(pub PointN<N>);
 pub type Local2i = Local<[i32; 2]>;
 /// Map-local coordinates, wrapping a `Point3i`.
 pub type Local3i = Local<[i32; 3]>;
+/// Map-local coordinates, wrapping a `Point4i`.
+pub type Local4i = Local<[i32; 4]>;
 
 impl<N> Clone for Local<N>
 where
@@ -1,25 +1,43 @@
-use crate::{prelude::*, VoxColor};
+use crate::{prelude::*, vox_model_extent, vox_model_voxels, VoxColor};
 
 pub use dot_vox;
 
 use building_blocks_core::prelude::*;
 
 use dot_vox::*;
+use std::convert::TryFrom;
 
 impl Array3x1<VoxColor> {
     pub fn decode_vox(vox_data: &DotVoxData, model_index: usize) -> Self {
-        let Model {
-            size: Size { x, y, z },
-            voxels,
-        } = &vox_data.models[model_index];
-        let shape = PointN([*x as i32, *y as i32, *z as i32]);
-        let extent = Extent3i::from_min_and_shape(PointN([0, 0, 0]), shape);
-        let mut map = Array3x1::fill(extent, VoxColor::Empty);
-        for Voxel { x, y, z, i } in voxels.iter() {
-            let point = PointN([*x as i32, *y as i32, *z as i32]);
-            *map.get_mut(point) = VoxColor::Color(*i);
+        match decode_vox_model(vox_data, model_index) {
+            Ok(map) => map,
+            Err(never) => match never {},
         }
+    }
+}
+
+impl Array3x1<u8> {
+    /// Like `decode_vox`, but each voxel stores the raw color index used by the VOX file format, where 0 is empty and `i > 0`
+    /// is palette entry `i - 1`.
+    ///
+    /// Returns `Err(VoxColor::Color(255))` if the model uses the last palette entry, since it has no raw index.
+    pub fn decode_vox_raw_indices(
+        vox_data: &DotVoxData,
+        model_index: usize,
+    ) -> Result<Self, VoxColor> {
+        decode_vox_model(vox_data, model_index)
+    }
+}
 
-        map
+fn decode_vox_model<T>(vox_data: &DotVoxData, model_index: usize) -> Result<Array3x1<T>, T::Error>
+where
+    T: Copy + TryFrom<VoxColor>,
+{
+    let extent = vox_model_extent(vox_data, model_index);
+    let mut map = Array3x1::fill(extent, T::try_from(VoxColor::Empty)?);
+    for (point, color) in vox_model_voxels(vox_data, model_index) {
+        *map.get_mut(point) = T::try_from(color)?;
     }
+
+    Ok(map)
 }
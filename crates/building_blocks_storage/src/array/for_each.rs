@@ -2,6 +2,8 @@
 mod for_each2;
 #[macro_use]
 mod for_each3;
+#[macro_use]
+mod for_each4;
 
 mod lock_step;
 mod single_array;
@@ -12,4 +14,5 @@ pub use single_array::*;
 
 pub(crate) use for_each2::*;
 pub(crate) use for_each3::*;
+pub(crate) use for_each4::*;
 pub(crate) use stride_iter::*;
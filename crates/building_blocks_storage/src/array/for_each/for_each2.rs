@@ -80,3 +80,5 @@ impl_iter2_for_tuple! { a: A, b: B, c: C }
 impl_iter2_for_tuple! { a: A, b: B, c: C, d: D }
 impl_iter2_for_tuple! { a: A, b: B, c: C, d: D, e: E }
 impl_iter2_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_iter2_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_iter2_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
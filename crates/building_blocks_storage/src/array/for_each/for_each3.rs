@@ -96,3 +96,5 @@ impl_iter3_for_tuple! { a: A, b: B, c: C }
 impl_iter3_for_tuple! { a: A, b: B, c: C, d: D }
 impl_iter3_for_tuple! { a: A, b: B, c: C, d: D, e: E }
 impl_iter3_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_iter3_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_iter3_for_tuple! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
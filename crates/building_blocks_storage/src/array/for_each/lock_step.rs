@@ -16,6 +16,7 @@ pub struct LockStepArrayForEach<N> {
 
 pub type LockStepArrayForEach2 = LockStepArrayForEach<[i32; 2]>;
 pub type LockStepArrayForEach3 = LockStepArrayForEach<[i32; 3]>;
+pub type LockStepArrayForEach4 = LockStepArrayForEach<[i32; 4]>;
 
 impl<N> LockStepArrayForEach<N>
 where
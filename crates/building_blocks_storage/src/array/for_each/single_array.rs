@@ -13,6 +13,8 @@ pub struct ArrayForEach<N> {
 pub type Array2ForEach = ArrayForEach<[i32; 2]>;
 /// A 3D `ArrayForEach`.
 pub type Array3ForEach = ArrayForEach<[i32; 3]>;
+/// A 4D `ArrayForEach`.
+pub type Array4ForEach = ArrayForEach<[i32; 4]>;
 
 impl<N> ArrayForEach<N>
 where
@@ -1,19 +1,22 @@
-use crate::{Iter2, Iter3, Local2i, Local3i, Stride};
+use crate::{Iter2, Iter3, Iter4, Local2i, Local3i, Local4i, Stride};
 
 use building_blocks_core::prelude::*;
 
-/// Used for both 2D and 3D strided iteration. (Z only used for 3D).
+/// Used for 2D, 3D, and 4D strided iteration. (Z only used for 3D and 4D, W only used for 4D).
 #[derive(Clone)]
 pub struct ArrayStrideIter {
     x_stride: usize,
     y_stride: usize,
     z_stride: usize,
+    w_stride: usize,
     x_start: usize,
     y_start: usize,
     z_start: usize,
+    w_start: usize,
     x_i: usize,
     y_i: usize,
     z_i: usize,
+    w_i: usize,
 }
 
 impl ArrayStrideIter {
@@ -35,42 +38,95 @@ impl ArrayStrideIter {
             x_stride,
             y_stride,
             z_stride: 0,
+            w_stride: 0,
             x_start,
             y_start,
             z_start: 0,
+            w_start: 0,
             x_i: 0,
             y_i: 0,
             z_i: 0,
+            w_i: 0,
         }
     }
 
     pub fn new_3d(array_shape: Point3i, origin: Local3i, step: Point3i) -> Self {
         debug_assert!(array_shape >= Point3i::ONES);
+
+        Self::new_3d_with_strides(
+            PointN([1, array_shape.x(), array_shape.y() * array_shape.x()]),
+            origin,
+            step,
+        )
+    }
+
+    /// Like `new_3d`, but `axis_strides` can be any linear layout, e.g. with Y as the fastest-varying axis.
+    pub fn new_3d_with_strides(axis_strides: Point3i, origin: Local3i, step: Point3i) -> Self {
+        debug_assert!(axis_strides >= Point3i::ONES);
         debug_assert!(origin.0 >= Point3i::ZERO);
         debug_assert!(step >= Point3i::ONES);
 
+        let mut x_stride = axis_strides.x() as usize;
+        let mut y_stride = axis_strides.y() as usize;
+        let mut z_stride = axis_strides.z() as usize;
+
+        let x_start = x_stride * origin.0.x() as usize;
+        let y_start = y_stride * origin.0.y() as usize;
+        let z_start = z_stride * origin.0.z() as usize;
+
+        x_stride *= step.x() as usize;
+        y_stride *= step.y() as usize;
+        z_stride *= step.z() as usize;
+
+        Self {
+            x_stride,
+            y_stride,
+            z_stride,
+            w_stride: 0,
+            x_start,
+            y_start,
+            z_start,
+            w_start: 0,
+            x_i: 0,
+            y_i: 0,
+            z_i: 0,
+            w_i: 0,
+        }
+    }
+
+    pub fn new_4d(array_shape: Point4i, origin: Local4i, step: Point4i) -> Self {
+        debug_assert!(array_shape >= Point4i::ONES);
+        debug_assert!(origin.0 >= Point4i::ZERO);
+        debug_assert!(step >= Point4i::ONES);
+
         let mut x_stride = 1usize;
         let mut y_stride = array_shape.x() as usize;
         let mut z_stride = (array_shape.y() * array_shape.x()) as usize;
+        let mut w_stride = (array_shape.z() * array_shape.y() * array_shape.x()) as usize;
 
         let x_start = x_stride * origin.0.x() as usize;
         let y_start = y_stride * origin.0.y() as usize;
         let z_start = z_stride * origin.0.z() as usize;
+        let w_start = w_stride * origin.0.w() as usize;
 
         x_stride *= step.x() as usize;
         y_stride *= step.y() as usize;
         z_stride *= step.z() as usize;
+        w_stride *= step.w() as usize;
 
         Self {
             x_stride,
             y_stride,
             z_stride,
+            w_stride,
             x_start,
             y_start,
             z_start,
+            w_start,
             x_i: 0,
             y_i: 0,
             z_i: 0,
+            w_i: 0,
         }
     }
 }
@@ -136,3 +192,46 @@ impl Iter3 for ArrayStrideIter {
         self.z_i += self.z_stride;
     }
 }
+
+impl Iter4 for ArrayStrideIter {
+    type Coords = Stride;
+
+    #[inline]
+    fn coords(&self) -> Self::Coords {
+        Stride(self.x_i)
+    }
+
+    #[inline]
+    fn start_w(&mut self) {
+        self.w_i = self.w_start;
+    }
+    #[inline]
+    fn start_z(&mut self) {
+        self.z_i = self.w_i + self.z_start;
+    }
+    #[inline]
+    fn start_y(&mut self) {
+        self.y_i = self.z_i + self.y_start;
+    }
+    #[inline]
+    fn start_x(&mut self) {
+        self.x_i = self.y_i + self.x_start;
+    }
+
+    #[inline]
+    fn incr_x(&mut self) {
+        self.x_i += self.x_stride;
+    }
+    #[inline]
+    fn incr_y(&mut self) {
+        self.y_i += self.y_stride;
+    }
+    #[inline]
+    fn incr_z(&mut self) {
+        self.z_i += self.z_stride;
+    }
+    #[inline]
+    fn incr_w(&mut self) {
+        self.w_i += self.w_stride;
+    }
+}
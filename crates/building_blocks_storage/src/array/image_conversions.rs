@@ -3,7 +3,7 @@ use crate::{prelude::*, Local};
 use building_blocks_core::prelude::*;
 
 use core::mem::MaybeUninit;
-use image::GenericImageView;
+use image::{GenericImageView, ImageBuffer, Luma, Pixel};
 
 impl<Im> From<&Im> for Array2x1<<Im as GenericImageView>::Pixel>
 where
@@ -24,3 +24,88 @@ where
         unsafe { map.assume_init() }
     }
 }
+
+impl<P> Array2x1<P>
+where
+    P: Pixel + 'static,
+{
+    /// Copies the pixels into a new `ImageBuffer`. The minimum of the array's extent is at pixel `(0, 0)`.
+    pub fn to_image_buffer(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let shape = self.extent().shape;
+
+        ImageBuffer::from_fn(shape.x() as u32, shape.y() as u32, |x, y| {
+            self.get(Local(PointN([x as i32, y as i32])))
+        })
+    }
+}
+
+macro_rules! impl_gray_image_conversions {
+    ($t:ty, $to:ident, $from:ident) => {
+        impl Array2x1<$t> {
+            /// Copies the values into a new grayscale `ImageBuffer`. The minimum of the array's extent is at pixel `(0, 0)`.
+            pub fn $to(&self) -> ImageBuffer<Luma<$t>, Vec<$t>> {
+                let shape = self.extent().shape;
+
+                ImageBuffer::from_raw(
+                    shape.x() as u32,
+                    shape.y() as u32,
+                    self.channels().store().to_vec(),
+                )
+                .unwrap()
+            }
+
+            /// Creates an array from the values of a grayscale `image`, with pixel `(0, 0)` at the origin.
+            pub fn $from(image: &ImageBuffer<Luma<$t>, Vec<$t>>) -> Self {
+                let shape = PointN([image.width() as i32, image.height() as i32]);
+
+                Self::new_one_channel(
+                    Extent2i::from_min_and_shape(Point2i::ZERO, shape),
+                    image.as_raw().clone(),
+                )
+            }
+        }
+    };
+}
+
+impl_gray_image_conversions!(u8, to_luma8, from_luma8);
+impl_gray_image_conversions!(u16, to_luma16, from_luma16);
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    use image::Rgba;
+
+    #[test]
+    fn gray_image_round_trip() {
+        let extent = Extent2i::from_min_and_shape(PointN([-3, 5]), PointN([7, 4]));
+        let array = Array2x1::fill_with(extent, |p| (p.x() * 10 + p.y()) as u16);
+
+        let image = array.to_luma16();
+        assert_eq!(image.dimensions(), (7, 4));
+        assert_eq!(image.get_pixel(1, 2).0, [array.get(PointN([-2, 7]))]);
+
+        let mut round_trip = Array2x1::from_luma16(&image);
+        round_trip.set_minimum(extent.minimum);
+        assert_eq!(round_trip, array);
+    }
+
+    #[test]
+    fn rgba_image_round_trip() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, PointN([5, 3]));
+        let array = Array2x1::fill_with(extent, |p| Rgba([p.x() as u8, p.y() as u8, 0, 255]));
+
+        let image = array.to_image_buffer();
+        assert_eq!(*image.get_pixel(4, 2), Rgba([4, 2, 0, 255]));
+        assert_eq!(Array2x1::from(&image), array);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    for_each2, for_each3, Array2ForEach, Array3ForEach, ArrayForEach, ArrayStrideIter, Local,
-    Local2i, Local3i, LockStepArrayForEach, LockStepArrayForEach2, LockStepArrayForEach3, Stride,
+    for_each2, for_each3, for_each4, Array2ForEach, Array3ForEach, Array4ForEach, ArrayForEach,
+    ArrayStrideIter, Local, Local2i, Local3i, Local4i, LockStepArrayForEach, LockStepArrayForEach2,
+    LockStepArrayForEach3, LockStepArrayForEach4, Stride,
 };
 
 use building_blocks_core::prelude::*;
@@ -94,10 +95,46 @@ impl ArrayIndexer<[i32; 3]> for [i32; 3] {
     }
 }
 
+impl ArrayIndexer<[i32; 4]> for [i32; 4] {
+    #[inline]
+    fn stride_from_local_point(s: Point4i, p: Local4i) -> Stride {
+        Stride(
+            (p.w() * s.z() * s.y() * s.x() + p.z() * s.y() * s.x() + p.y() * s.x() + p.x())
+                as usize,
+        )
+    }
+
+    #[inline]
+    fn make_stride_iter(array_shape: Point4i, origin: Local4i, step: Point4i) -> ArrayStrideIter {
+        ArrayStrideIter::new_4d(array_shape, origin, step)
+    }
+
+    #[inline]
+    fn for_each(for_each: Array4ForEach, f: impl FnMut(Point4i, Stride)) {
+        let Array4ForEach { iter_extent, iter } = for_each;
+        for_each4(iter, &iter_extent, f);
+    }
+
+    #[inline]
+    fn for_each_lockstep_unchecked(
+        for_each: LockStepArrayForEach4,
+        f: impl FnMut(Point4i, (Stride, Stride)),
+    ) {
+        let LockStepArrayForEach4 {
+            iter_extent,
+            iter1,
+            iter2,
+        } = for_each;
+        for_each4((iter1, iter2), &iter_extent, f)
+    }
+}
+
 /// When a lattice map implements `IndexedArray`, that means there is some underlying array with the location and shape dictated
 /// by the extent.
 ///
-/// For the sake of generic impls, if the same map also implements `Get*<Stride>`, it must use the same data layout as `Array`.
+/// For the sake of generic impls, if the same map also implements `Get*<Stride>`, its strides must agree with
+/// `stride_from_local_point`, `strides_from_local_points`, and `array_for_each`. These default to the same data layout as
+/// `Array`, but they can be overridden for other linear layouts, like `AxisOrderedArray3`.
 pub trait IndexedArray<N> {
     type Indexer: ArrayIndexer<N>;
 
@@ -118,4 +155,14 @@ pub trait IndexedArray<N> {
     {
         Self::Indexer::strides_from_local_points(self.extent().shape, points, strides)
     }
+
+    /// Visits the points of `iter_extent` that are in this array, along with their strides.
+    #[inline]
+    fn array_for_each(&self, iter_extent: ExtentN<N>) -> ArrayForEach<N>
+    where
+        N: ArrayIndexer<N>,
+        PointN<N>: IntegerPoint<N>,
+    {
+        ArrayForEach::new_global(*self.extent(), iter_extent)
+    }
 }
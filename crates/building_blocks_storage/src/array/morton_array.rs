@@ -0,0 +1,776 @@
+//! Arrays that store their values in Morton (Z-order) layout.
+//!
+//! A row-major `Array` keeps neighbors along the X axis adjacent in memory, but neighbors along the Y and Z axes are a whole
+//! row or slice apart. A `MortonArray` instead orders its values along the
+//! [Z-order curve](https://en.wikipedia.org/wiki/Z-order_curve), so every power-of-two-sized sub-cube is contiguous in memory.
+//! This improves cache behavior for algorithms that touch whole neighborhoods at a time, like surface nets or flood fills.
+//!
+//! The layout is a property of the `MortonArray` type itself, not of its `N` type parameter, which only chooses the
+//! dimension. The `MortonIndexer` trait implements the Z-order curve for each dimension, like `ArrayIndexer` implements the
+//! row-major layout of an `Array`. Because Morton order isn't linear, there are no `Stride` coordinates; use `Array` for
+//! kernels that rely on `Stride` arithmetic.
+//!
+//! The shape of a `MortonArray` must be a cube with power-of-two edge lengths, which is always true of chunks. This is also
+//! checked on deserialization, along with the number of values in each channel.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+//! let row_major = Array3x1::fill_with(extent, |p| p.x() + p.y() + p.z());
+//!
+//! // Convert between layouts with `copy_extent`.
+//! let mut morton = MortonArray3x1::fill(extent, 0);
+//! copy_extent(&extent, &row_major, &mut morton);
+//! assert_eq!(morton.get(Point3i::fill(1)), 3);
+//!
+//! morton.for_each_mut(&extent, |_: Point3i, value| *value *= 2);
+//!
+//! let mut back = Array3x1::fill(extent, 0);
+//! copy_extent(&extent, &morton, &mut back);
+//! assert_eq!(back.get(PointN([1, 2, 3])), 12);
+//! ```
+
+use crate::{
+    ArrayCopySrc, BorrowChannels, BorrowChannelsMut, Channel, Channels, CopySlices, FillChannels,
+    FillExtent, ForEach, ForEachMut, ForEachMutPtr, Get, GetMut, GetMutPtr, GetRef, IntoMultiMut,
+    Local, Local2i, Local3i, MultiMutPtr, NumValues, ReadExtent, ResetChannels, Slices,
+    WriteExtent,
+};
+
+#[cfg(feature = "std")]
+use crate::ChunkCopySrc;
+
+use super::Array;
+
+use building_blocks_core::prelude::*;
+
+use alloc::vec::Vec;
+use core::iter::{once, Once};
+#[cfg(feature = "std")]
+use either::Either;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+/// Maps local points of a power-of-two cube to offsets along the Z-order curve, and back.
+pub trait MortonIndexer<N> {
+    fn index_from_local_point(p: Local<N>) -> usize;
+
+    fn local_point_from_index(index: usize) -> Local<N>;
+}
+
+impl MortonIndexer<[i32; 2]> for [i32; 2] {
+    #[inline]
+    fn index_from_local_point(p: Local2i) -> usize {
+        (split_by_2(p.x() as u64) | split_by_2(p.y() as u64) << 1) as usize
+    }
+
+    #[inline]
+    fn local_point_from_index(index: usize) -> Local2i {
+        let i = index as u64;
+
+        Local(PointN([
+            compact_by_2(i) as i32,
+            compact_by_2(i >> 1) as i32,
+        ]))
+    }
+}
+
+impl MortonIndexer<[i32; 3]> for [i32; 3] {
+    #[inline]
+    fn index_from_local_point(p: Local3i) -> usize {
+        (split_by_3(p.x() as u64) | split_by_3(p.y() as u64) << 1 | split_by_3(p.z() as u64) << 2)
+            as usize
+    }
+
+    #[inline]
+    fn local_point_from_index(index: usize) -> Local3i {
+        let i = index as u64;
+
+        Local(PointN([
+            compact_by_3(i) as i32,
+            compact_by_3(i >> 1) as i32,
+            compact_by_3(i >> 2) as i32,
+        ]))
+    }
+}
+
+/// Inserts a 0 bit between each of the low 32 bits of `x`.
+#[inline]
+fn split_by_2(mut x: u64) -> u64 {
+    x &= 0xffff_ffff;
+    x = (x | x << 16) & 0x0000_ffff_0000_ffff;
+    x = (x | x << 8) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+
+    (x | x << 1) & 0x5555_5555_5555_5555
+}
+
+/// The inverse of `split_by_2`.
+#[inline]
+fn compact_by_2(mut x: u64) -> u64 {
+    x &= 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x >> 4) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x >> 8) & 0x0000_ffff_0000_ffff;
+
+    (x | x >> 16) & 0xffff_ffff
+}
+
+/// Inserts two 0 bits between each of the low 21 bits of `x`.
+#[inline]
+fn split_by_3(mut x: u64) -> u64 {
+    x &= 0x1f_ffff;
+    x = (x | x << 32) & 0x001f_0000_0000_ffff;
+    x = (x | x << 16) & 0x001f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+
+    (x | x << 2) & 0x1249_2492_4924_9249
+}
+
+/// The inverse of `split_by_3`.
+#[inline]
+fn compact_by_3(mut x: u64) -> u64 {
+    x &= 0x1249_2492_4924_9249;
+    x = (x | x >> 2) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x >> 4) & 0x100f_00f0_0f00_f00f;
+    x = (x | x >> 8) & 0x001f_0000_ff00_00ff;
+    x = (x | x >> 16) & 0x001f_0000_0000_ffff;
+
+    (x | x >> 32) & 0x1f_ffff
+}
+
+/// A map from lattice location `PointN<N>` to data `T`, stored as a flat array in Morton order.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct MortonArray<N, Chan> {
+    channels: Chan,
+    extent: ExtentN<N>,
+}
+
+impl<'de, N, Chan> Deserialize<'de> for MortonArray<N, Chan>
+where
+    ExtentN<N>: Deserialize<'de>,
+    PointN<N>: IntegerPoint<N>,
+    Chan: Deserialize<'de> + NumValues,
+{
+    /// Fails if the shape is not a cube with power-of-two edge lengths, or if the channels don't have a value for every point.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Same format as a derived implementation.
+        #[derive(Deserialize)]
+        #[serde(
+            rename = "MortonArray",
+            bound = "ExtentN<N>: Deserialize<'de>, Chan: Deserialize<'de>"
+        )]
+        struct Parts<N, Chan> {
+            channels: Chan,
+            extent: ExtentN<N>,
+        }
+
+        let Parts { channels, extent } = Parts::deserialize(deserializer)?;
+
+        if !(extent.shape.is_cube() && extent.shape.dimensions_are_powers_of_2()) {
+            return Err(de::Error::custom(
+                "MortonArray shape must be a cube with power-of-two edge lengths",
+            ));
+        }
+        if channels.num_values() != extent.num_points() {
+            return Err(de::Error::invalid_length(
+                channels.num_values(),
+                &"one value per point of the MortonArray extent",
+            ));
+        }
+
+        Ok(Self::new(extent, channels))
+    }
+}
+
+pub type MortonArrayNx1<N, A, S1 = Vec<A>> = MortonArray<N, Channel<A, S1>>;
+
+impl<N, Chan> MortonArray<N, Chan>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    /// Create a new `MortonArray` directly from the extent and values, which must already be in Morton order.
+    ///
+    /// # Panics
+    /// If the shape of `extent` is not a cube with power-of-two edge lengths.
+    pub fn new(extent: ExtentN<N>, channels: Chan) -> Self {
+        assert!(extent.shape.is_cube() && extent.shape.dimensions_are_powers_of_2());
+
+        Self { channels, extent }
+    }
+
+    /// Moves the raw extent and values storage out of `self`.
+    #[inline]
+    pub fn into_parts(self) -> (ExtentN<N>, Chan) {
+        (self.extent, self.channels)
+    }
+
+    /// The extent of the array.
+    #[inline]
+    pub fn extent(&self) -> &ExtentN<N> {
+        &self.extent
+    }
+
+    #[inline]
+    pub fn channels(&self) -> &Chan {
+        &self.channels
+    }
+
+    #[inline]
+    pub fn channels_mut(&mut self) -> &mut Chan {
+        &mut self.channels
+    }
+
+    /// Creates a new `MortonArray` from the return value of `selector`. `selector` takes a tuple of `Channel`s that borrow
+    /// their storage.
+    #[inline]
+    pub fn borrow_channels<'a, NewChan>(
+        &'a self,
+        selector: impl Fn(Chan::Borrowed) -> NewChan,
+    ) -> MortonArray<N, NewChan>
+    where
+        Chan: BorrowChannels<'a>,
+    {
+        MortonArray::new(self.extent, selector(self.channels.borrow()))
+    }
+
+    /// Creates a new `MortonArray` from the return value of `selector`. `selector` takes a tuple of `Channel`s that mutably
+    /// borrow their storage.
+    #[inline]
+    pub fn borrow_channels_mut<'a, NewChan>(
+        &'a mut self,
+        selector: impl Fn(Chan::Borrowed) -> NewChan,
+    ) -> MortonArray<N, NewChan>
+    where
+        Chan: BorrowChannelsMut<'a>,
+    {
+        MortonArray::new(self.extent, selector(self.channels.borrow_mut()))
+    }
+}
+
+impl<N, Chan> MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+{
+    /// The offset of the point `p` in the flat storage.
+    #[inline]
+    pub fn index_from_point(&self, p: PointN<N>) -> usize {
+        N::index_from_local_point(Local(p - self.extent.minimum))
+    }
+
+    /// The point stored at `index` in the flat storage.
+    #[inline]
+    pub fn point_from_index(&self, index: usize) -> PointN<N> {
+        self.extent.minimum + N::local_point_from_index(index).0
+    }
+}
+
+impl<N, Chan> MortonArray<N, Chan>
+where
+    PointN<N>: IntegerPoint<N>,
+    Chan: FillChannels,
+{
+    /// Creates a map that fills the entire `extent` with the same `value`.
+    pub fn fill(extent: ExtentN<N>, value: Chan::Data) -> Self
+    where
+        Chan::Data: Clone,
+    {
+        Self::new(extent, Chan::fill(value, extent.num_points()))
+    }
+}
+
+impl<N, Chan> FillExtent<N> for MortonArray<N, Chan>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    PointN<N>: IntegerPoint<N>,
+    Chan: ResetChannels,
+    Chan::Data: Clone,
+{
+    type Item = Chan::Data;
+
+    /// Fill the entire `extent` with the same `value`.
+    fn fill_extent(&mut self, extent: &ExtentN<N>, value: Self::Item) {
+        if self.extent.eq(extent) {
+            self.channels.reset_values(value);
+        } else {
+            unsafe {
+                self.for_each_mut_ptr(extent, |_: PointN<N>, v| v.write(value.clone()));
+            }
+        }
+    }
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<N, Chan> Get<Local<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    Chan: Get<usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get(&self, p: Local<N>) -> Self::Item {
+        self.channels.get(N::index_from_local_point(p))
+    }
+}
+
+impl<'a, N, Chan> GetRef<'a, Local<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    Chan: GetRef<'a, usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: Local<N>) -> Self::Item {
+        self.channels.get_ref(N::index_from_local_point(p))
+    }
+}
+
+impl<'a, N, Chan> GetMut<'a, Local<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    Chan: GetMut<'a, usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: Local<N>) -> Self::Item {
+        self.channels.get_mut(N::index_from_local_point(p))
+    }
+}
+
+impl<N, Chan> GetMutPtr<Local<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    Chan: GetMutPtr<usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    unsafe fn get_mut_ptr(&mut self, p: Local<N>) -> Self::Item {
+        self.channels.get_mut_ptr(N::index_from_local_point(p))
+    }
+}
+
+impl<N, Chan> Get<PointN<N>> for MortonArray<N, Chan>
+where
+    Self: Get<Local<N>>,
+    PointN<N>: Point,
+{
+    type Item = <Self as Get<Local<N>>>::Item;
+
+    #[inline]
+    fn get(&self, p: PointN<N>) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get(Local(local_p))
+    }
+}
+
+impl<'a, N, Chan> GetRef<'a, PointN<N>> for MortonArray<N, Chan>
+where
+    Self: GetRef<'a, Local<N>>,
+    PointN<N>: Point,
+{
+    type Item = <Self as GetRef<'a, Local<N>>>::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: PointN<N>) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get_ref(Local(local_p))
+    }
+}
+
+impl<'a, N, Chan> GetMut<'a, PointN<N>> for MortonArray<N, Chan>
+where
+    Self: GetMut<'a, Local<N>>,
+    PointN<N>: Point,
+{
+    type Item = <Self as GetMut<'a, Local<N>>>::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: PointN<N>) -> Self::Item {
+        let local_p = p - self.extent.minimum;
+
+        self.get_mut(Local(local_p))
+    }
+}
+
+// ███████╗ ██████╗ ██████╗     ███████╗ █████╗  ██████╗██╗  ██╗
+// ██╔════╝██╔═══██╗██╔══██╗    ██╔════╝██╔══██╗██╔════╝██║  ██║
+// █████╗  ██║   ██║██████╔╝    █████╗  ███████║██║     ███████║
+// ██╔══╝  ██║   ██║██╔══██╗    ██╔══╝  ██╔══██║██║     ██╔══██║
+// ██║     ╚██████╔╝██║  ██║    ███████╗██║  ██║╚██████╗██║  ██║
+// ╚═╝      ╚═════╝ ╚═╝  ╚═╝    ╚══════╝╚═╝  ╚═╝ ╚═════╝╚═╝  ╚═╝
+
+/// Calls `f` with the global point and storage offset of every point in the intersection of `iter_extent` and `array_extent`.
+///
+/// When `iter_extent` contains the entire array, the points are visited in Morton order, i.e. in order of memory address.
+/// Otherwise they are visited in row-major order.
+#[inline]
+fn for_each_index<N>(
+    array_extent: &ExtentN<N>,
+    iter_extent: &ExtentN<N>,
+    mut f: impl FnMut(PointN<N>, usize),
+) where
+    N: MortonIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+{
+    let in_bounds_extent = iter_extent.intersection(array_extent);
+
+    if in_bounds_extent.eq(array_extent) {
+        for i in 0..array_extent.num_points() {
+            f(array_extent.minimum + N::local_point_from_index(i).0, i);
+        }
+    } else {
+        for p in in_bounds_extent.iter_points() {
+            f(
+                p,
+                N::index_from_local_point(Local(p - array_extent.minimum)),
+            );
+        }
+    }
+}
+
+impl<N, Chan> ForEach<N, PointN<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    Chan: Get<usize>,
+{
+    type Item = Chan::Item;
+
+    #[inline]
+    fn for_each(&self, iter_extent: &ExtentN<N>, mut f: impl FnMut(PointN<N>, Self::Item)) {
+        for_each_index(&self.extent, iter_extent, |p, i| f(p, self.channels.get(i)));
+    }
+}
+
+impl<N, Chan> ForEachMutPtr<N, PointN<N>> for MortonArray<N, Chan>
+where
+    N: MortonIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels + GetMutPtr<usize, Item = <Chan as Channels>::Ptr>,
+{
+    type Item = Chan::Ptr;
+
+    #[inline]
+    unsafe fn for_each_mut_ptr(
+        &mut self,
+        iter_extent: &ExtentN<N>,
+        mut f: impl FnMut(PointN<N>, Self::Item),
+    ) {
+        let Self { channels, extent } = self;
+        for_each_index(extent, iter_extent, |p, i| f(p, channels.get_mut_ptr(i)));
+    }
+}
+
+impl<'a, N, Chan> ForEachMut<'a, N, PointN<N>> for MortonArray<N, Chan>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    Chan: Channels,
+    Chan::Ptr: IntoMultiMut<'a>,
+{
+    type Item = <Chan::Ptr as IntoMultiMut<'a>>::MultiMut;
+
+    #[inline]
+    fn for_each_mut(
+        &'a mut self,
+        iter_extent: &ExtentN<N>,
+        mut f: impl FnMut(PointN<N>, Self::Item),
+    ) {
+        unsafe {
+            self.for_each_mut_ptr(iter_extent, |p, ptr| f(p, ptr.into_multi_mut()));
+        }
+    }
+}
+
+//  ██████╗ ██████╗ ██████╗ ██╗   ██╗
+// ██╔════╝██╔═══██╗██╔══██╗╚██╗ ██╔╝
+// ██║     ██║   ██║██████╔╝ ╚████╔╝
+// ██║     ██║   ██║██╔═══╝   ╚██╔╝
+// ╚██████╗╚██████╔╝██║        ██║
+//  ╚═════╝ ╚═════╝ ╚═╝        ╚═╝
+
+impl<'a, N: 'a, Chan: 'a> ReadExtent<'a, N> for MortonArray<N, Chan>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    type Src = ArrayCopySrc<&'a MortonArray<N, Chan>>;
+    type SrcIter = Once<(ExtentN<N>, Self::Src)>;
+
+    fn read_extent(&'a self, extent: &ExtentN<N>) -> Self::SrcIter {
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        once((in_bounds_extent, ArrayCopySrc(self)))
+    }
+}
+
+impl<'a, N, Data, SrcSlices, ChanSrc, ChanDst>
+    WriteExtent<N, ArrayCopySrc<&'a MortonArray<N, ChanSrc>>> for MortonArray<N, ChanDst>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = ChanDst::Ptr>,
+    MortonArray<N, ChanSrc>: Get<Local<N>, Item = Data>,
+    PointN<N>: IntegerPoint<N>,
+    ChanSrc: Channels<Data = Data> + Slices<'a, Target = SrcSlices>,
+    ChanDst: Channels<Data = Data> + CopySlices<'a, Src = SrcSlices>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &ExtentN<N>,
+        src_array: ArrayCopySrc<&'a MortonArray<N, ChanSrc>>,
+    ) {
+        // It is assumed by the interface that extent is a subset of the src array, so we only need to intersect with the
+        // destination.
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        if in_bounds_extent.eq(&self.extent) && in_bounds_extent.eq(src_array.0.extent()) {
+            // Fast path: both arrays have exactly the same layout.
+            self.channels.copy_slices(src_array.0.channels.slices());
+        } else {
+            let src_min = src_array.0.extent.minimum;
+            unsafe {
+                self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| {
+                    ptr.write(src_array.0.get(Local(p - src_min)))
+                });
+            }
+        }
+    }
+}
+
+impl<'a, N, Data, ChanSrc, ChanDst> WriteExtent<N, ArrayCopySrc<&'a Array<N, ChanSrc>>>
+    for MortonArray<N, ChanDst>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = ChanDst::Ptr>,
+    Array<N, ChanSrc>: Get<PointN<N>, Item = Data>,
+    PointN<N>: IntegerPoint<N>,
+    ChanDst: Channels<Data = Data>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &ExtentN<N>,
+        src_array: ArrayCopySrc<&'a Array<N, ChanSrc>>,
+    ) {
+        let in_bounds_extent = extent.intersection(&self.extent);
+
+        unsafe {
+            self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| ptr.write(src_array.0.get(p)));
+        }
+    }
+}
+
+impl<'a, N, Data, ChanSrc, ChanDst> WriteExtent<N, ArrayCopySrc<&'a MortonArray<N, ChanSrc>>>
+    for Array<N, ChanDst>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = ChanDst::Ptr>,
+    MortonArray<N, ChanSrc>: Get<PointN<N>, Item = Data>,
+    PointN<N>: IntegerPoint<N>,
+    ChanDst: Channels<Data = Data>,
+{
+    fn write_extent(
+        &mut self,
+        extent: &ExtentN<N>,
+        src_array: ArrayCopySrc<&'a MortonArray<N, ChanSrc>>,
+    ) {
+        let in_bounds_extent = extent.intersection(self.extent());
+
+        unsafe {
+            self.for_each_mut_ptr(&in_bounds_extent, |p, ptr| ptr.write(src_array.0.get(p)));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N, Chan, Ch> WriteExtent<N, ChunkCopySrc<N, Chan::Data, Ch>> for MortonArray<N, Chan>
+where
+    Self: FillExtent<N, Item = Chan::Data> + WriteExtent<N, ArrayCopySrc<Ch>>,
+    Chan: Channels,
+    Chan::Data: Clone,
+{
+    fn write_extent(&mut self, extent: &ExtentN<N>, src: ChunkCopySrc<N, Chan::Data, Ch>) {
+        match src {
+            Either::Left(array) => self.write_extent(extent, array),
+            Either::Right(ambient) => self.fill_extent(extent, ambient.get()),
+        }
+    }
+}
+
+impl<N, Chan, F> WriteExtent<N, F> for MortonArray<N, Chan>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    F: Fn(PointN<N>) -> Chan::Data,
+    Chan: Channels,
+{
+    fn write_extent(&mut self, extent: &ExtentN<N>, src: F) {
+        unsafe {
+            self.for_each_mut_ptr(extent, |p, v| v.write((src)(p)));
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn morton_index_round_trip() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut indices: Vec<_> = extent
+            .iter_points()
+            .map(|p| {
+                let i = <[i32; 3]>::index_from_local_point(Local(p));
+                assert_eq!(<[i32; 3]>::local_point_from_index(i), Local(p));
+
+                i
+            })
+            .collect();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..extent.num_points()).collect::<Vec<_>>());
+
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(16));
+        for p in extent.iter_points() {
+            let i = <[i32; 2]>::index_from_local_point(Local(p));
+            assert!(i < extent.num_points());
+            assert_eq!(<[i32; 2]>::local_point_from_index(i), Local(p));
+        }
+    }
+
+    #[test]
+    fn neighbors_in_small_cubes_are_contiguous() {
+        assert_eq!(
+            <[i32; 3]>::index_from_local_point(Local(PointN([1, 0, 0]))),
+            1
+        );
+        assert_eq!(
+            <[i32; 3]>::index_from_local_point(Local(PointN([0, 1, 0]))),
+            2
+        );
+        assert_eq!(
+            <[i32; 3]>::index_from_local_point(Local(PointN([0, 0, 1]))),
+            4
+        );
+        assert_eq!(
+            <[i32; 3]>::index_from_local_point(Local(Point3i::fill(1))),
+            7
+        );
+        assert_eq!(
+            <[i32; 3]>::index_from_local_point(Local(PointN([2, 0, 0]))),
+            8
+        );
+    }
+
+    #[test]
+    fn copy_between_layouts() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-4), Point3i::fill(8));
+        let row_major = Array3x1::fill_with(extent, |p| p.x() * 100 + p.y() * 10 + p.z());
+
+        let mut morton = MortonArray3x1::fill(extent, 0);
+        copy_extent(&extent, &row_major, &mut morton);
+        morton.for_each(&extent, |p: Point3i, value| {
+            assert_eq!(value, row_major.get(p));
+        });
+
+        // Partial copies only touch the requested extent.
+        let mut other = MortonArray3x1::fill(extent, 0);
+        let subextent = Extent3i::from_min_and_shape(Point3i::fill(-1), Point3i::fill(3));
+        copy_extent(&subextent, &morton, &mut other);
+        other.for_each(&extent, |p: Point3i, value| {
+            if subextent.contains(p) {
+                assert_eq!(value, row_major.get(p));
+            } else {
+                assert_eq!(value, 0);
+            }
+        });
+
+        let mut back = Array3x1::fill(extent, 0);
+        copy_extent(&extent, &morton, &mut back);
+        assert_eq!(back, row_major);
+    }
+
+    #[test]
+    fn for_each_mut_subextent() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(4));
+        let mut array = MortonArray2x1::fill(extent, 0);
+
+        let subextent = Extent2i::from_min_and_shape(Point2i::fill(1), Point2i::fill(2));
+        array.for_each_mut(&subextent, |_: Point2i, value| *value = 1);
+        array.fill_extent(
+            &Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(1)),
+            2,
+        );
+
+        array.for_each(&extent, |p: Point2i, value| {
+            let expected = if subextent.contains(p) {
+                1
+            } else if p == Point2i::ZERO {
+                2
+            } else {
+                0
+            };
+            assert_eq!(value, expected);
+            assert_eq!(array.get(p), expected);
+        });
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn deserialize_checks_shape_and_length() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-2), Point3i::fill(4));
+        let mut array = MortonArray3x1::fill(extent, 0);
+        array.for_each_mut(&extent, |p: Point3i, value| *value = p.x() + 10 * p.y());
+        let bytes = bincode::serialize(&array).unwrap();
+        assert_eq!(
+            bincode::deserialize::<MortonArray3x1<i32>>(&bytes).unwrap(),
+            array
+        );
+
+        // The derived format of a struct is the same as a tuple of its fields.
+        let deserialize_parts = |channel: Channel<i32>, extent: Extent3i| {
+            let bytes = bincode::serialize(&(channel, extent)).unwrap();
+            bincode::deserialize::<MortonArray3x1<i32>>(&bytes)
+        };
+
+        let truncated = Channel::fill(0, extent.num_points() - 1);
+        assert!(deserialize_parts(truncated, extent).is_err());
+
+        let not_a_cube = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 4, 8]));
+        let channel = Channel::fill(0, not_a_cube.num_points());
+        assert!(deserialize_parts(channel, not_a_cube).is_err());
+
+        let not_a_power_of_2 = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(3));
+        let channel = Channel::fill(0, not_a_power_of_2.num_points());
+        assert!(deserialize_parts(channel, not_a_power_of_2).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn non_cube_shape_panics() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 4, 8]));
+        MortonArray3x1::fill(extent, 0);
+    }
+}
@@ -0,0 +1,255 @@
+//! Rotating and mirroring 3D arrays, either lazily with an `OrthoTransformView3` or by copying into a new `Array`.
+//!
+//! Points are transformed about the origin (see `OrthoTransform3`), so the transformed extent usually has a different minimum.
+//! This is mostly useful for placing prefabricated structures into a chunk map with any orientation.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! // A 4x2x1 "prefab" with a marker at its minimum.
+//! let prefab_extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 2, 1]));
+//! let prefab = Array3x1::fill_with(prefab_extent, |p| if p == Point3i::ZERO { 2 } else { 1 });
+//!
+//! // Lazily rotate it by 90 degrees about Z. Nothing is copied.
+//! let rotation = OrthoTransform3::rotate_90(Axis3::Z);
+//! let view = prefab.transformed_view(rotation);
+//! assert_eq!(view.extent().shape, PointN([2, 4, 1]));
+//! assert_eq!(view.get(PointN([-1, 3, 0])), prefab.get(PointN([3, 1, 0])));
+//!
+//! // Stamp the rotated prefab into a chunk map at some offset.
+//! let offset = PointN([10, 10, 10]);
+//! let mut map = ChunkMapBuilder3x1::new(Point3i::fill(8), 0).build_with_hash_map_storage();
+//! map.lod_view_mut(0)
+//!     .write_extent(&(view.extent() + offset), |p: Point3i| view.get(p - offset));
+//! assert_eq!(map.lod_view(0).get(PointN([10, 10, 10])), 2);
+//!
+//! // Or make a transformed copy, which is faster to read repeatedly.
+//! let mirrored = prefab.mirrored(Axis3::X);
+//! assert_eq!(mirrored.extent().minimum, PointN([-3, 0, 0]));
+//! assert_eq!(mirrored.get(Point3i::ZERO), 2);
+//! ```
+
+use crate::{
+    Array, Channels, ForEach, ForEachMutPtr, Get, GetRef, IntoMultiMutPtr, UninitChannels,
+};
+
+use building_blocks_core::prelude::*;
+
+use core::ops::Deref;
+
+/// A view of a 3D `Array` that has been rotated and/or mirrored by an `OrthoTransform3`. Supports `Get` and `ForEach` in the
+/// transformed coordinates.
+#[derive(Clone, Copy)]
+pub struct OrthoTransformView3<Delegate> {
+    pub delegate: Delegate,
+    transform: OrthoTransform3,
+    inverse: OrthoTransform3,
+    extent: Extent3i,
+}
+
+impl<Delegate, Chan> OrthoTransformView3<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+{
+    #[inline]
+    pub fn new(delegate: Delegate, transform: OrthoTransform3) -> Self {
+        let extent = transform.transform_extent(delegate.extent());
+
+        Self {
+            delegate,
+            transform,
+            inverse: transform.inverse(),
+            extent,
+        }
+    }
+
+    #[inline]
+    pub fn transform(&self) -> OrthoTransform3 {
+        self.transform
+    }
+
+    /// The transformed extent of the delegate array.
+    #[inline]
+    pub fn extent(&self) -> Extent3i {
+        self.extent
+    }
+
+    /// The point in the delegate array that appears at `p` in this view.
+    #[inline]
+    pub fn source_point(&self, p: Point3i) -> Point3i {
+        self.inverse.transform_point(p)
+    }
+}
+
+impl<Chan> Array<[i32; 3], Chan> {
+    /// A view of this array with all points transformed by `transform`.
+    pub fn transformed_view(&self, transform: OrthoTransform3) -> OrthoTransformView3<&Self> {
+        OrthoTransformView3::new(self, transform)
+    }
+}
+
+impl<Chan, UninitChan> Array<[i32; 3], Chan>
+where
+    Self: Get<Point3i, Item = Chan::Data>,
+    Array<[i32; 3], UninitChan>: ForEachMutPtr<[i32; 3], Point3i, Item = UninitChan::Ptr>,
+    Chan: Channels<UninitSelf = UninitChan>,
+    UninitChan: UninitChannels<InitSelf = Chan>,
+    UninitChan::Ptr: IntoMultiMutPtr<Data = Chan::Data>,
+{
+    /// Copies this array with all points transformed by `transform`.
+    pub fn transformed(&self, transform: OrthoTransform3) -> Self {
+        let view = self.transformed_view(transform);
+
+        Self::fill_with(view.extent(), |p| view.get(p))
+    }
+
+    /// Copies this array rotated by 90 degrees about `axis`. See `OrthoTransform3::rotate_90`.
+    pub fn rotated_90(&self, axis: Axis3) -> Self {
+        self.transformed(OrthoTransform3::rotate_90(axis))
+    }
+
+    /// Copies this array rotated by 180 degrees about `axis`.
+    pub fn rotated_180(&self, axis: Axis3) -> Self {
+        self.transformed(OrthoTransform3::rotate_180(axis))
+    }
+
+    /// Copies this array rotated by 270 degrees about `axis`.
+    pub fn rotated_270(&self, axis: Axis3) -> Self {
+        self.transformed(OrthoTransform3::rotate_270(axis))
+    }
+
+    /// Copies this array reflected across the plane perpendicular to `axis`.
+    pub fn mirrored(&self, axis: Axis3) -> Self {
+        self.transformed(OrthoTransform3::mirror(axis))
+    }
+}
+
+impl<Delegate, Chan> Get<Point3i> for OrthoTransformView3<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: Get<Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as Get<Point3i>>::Item;
+
+    #[inline]
+    fn get(&self, p: Point3i) -> Self::Item {
+        self.delegate.get(self.source_point(p))
+    }
+}
+
+impl<'a, Delegate, Chan> GetRef<'a, Point3i> for OrthoTransformView3<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: 'a + GetRef<'a, Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as GetRef<'a, Point3i>>::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: Point3i) -> Self::Item {
+        let p = self.source_point(p);
+
+        self.delegate.deref().get_ref(p)
+    }
+}
+
+impl<Delegate, Chan> ForEach<[i32; 3], Point3i> for OrthoTransformView3<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: ForEach<[i32; 3], Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as ForEach<[i32; 3], Point3i>>::Item;
+
+    #[inline]
+    fn for_each(&self, extent: &Extent3i, mut f: impl FnMut(Point3i, Self::Item)) {
+        // Iterate over the delegate in its own (cache-friendly) order.
+        let transform = self.transform;
+        let src_extent = self.inverse.transform_extent(extent);
+        self.delegate.for_each(&src_extent, |p: Point3i, value| {
+            f(transform.transform_point(p), value)
+        });
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    fn func(p: Point3i) -> i32 {
+        p.x() + 100 * p.y() + 10000 * p.z()
+    }
+
+    const AXES: [Axis3; 3] = [Axis3::X, Axis3::Y, Axis3::Z];
+
+    #[test]
+    fn four_quarter_turns_are_identity() {
+        let extent = Extent3i::from_min_and_shape(PointN([-2, 3, 1]), PointN([4, 5, 6]));
+        let array = Array3x1::fill_with(extent, func);
+
+        for &axis in AXES.iter() {
+            let mut rotated = array.clone();
+            for _ in 0..4 {
+                rotated = rotated.rotated_90(axis);
+            }
+            assert_eq!(rotated, array);
+
+            assert_eq!(
+                array.rotated_90(axis).rotated_270(axis),
+                array,
+                "axis = {:?}",
+                axis
+            );
+            assert_eq!(array.mirrored(axis).mirrored(axis), array);
+        }
+    }
+
+    #[test]
+    fn view_matches_copy() {
+        let extent = Extent3i::from_min_and_shape(PointN([-2, 3, 1]), PointN([4, 5, 6]));
+        let array = Array3x1::fill_with(extent, func);
+
+        for &axis in AXES.iter() {
+            let transform =
+                OrthoTransform3::rotate_90(axis).then(OrthoTransform3::mirror(Axis3::Y));
+            let copy = array.transformed(transform);
+            let view = array.transformed_view(transform);
+            assert_eq!(copy.extent(), &view.extent());
+            assert_eq!(copy.extent().num_points(), extent.num_points());
+
+            let mut num_points = 0;
+            view.for_each(&view.extent(), |p: Point3i, value| {
+                assert_eq!(value, copy.get(p));
+                assert_eq!(value, view.get(p));
+                assert_eq!(view.get_ref(p), &value);
+                assert_eq!(value, func(view.source_point(p)));
+                num_points += 1;
+            });
+            assert_eq!(num_points, extent.num_points());
+        }
+    }
+
+    #[test]
+    fn view_for_each_sub_extent() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([4, 3, 5]));
+        let array = Array3x1::fill_with(extent, func);
+        let view = array.transformed_view(OrthoTransform3::rotate_90(Axis3::Y));
+
+        let sub_extent = view.extent().padded(-1);
+        let mut visited = Vec::new();
+        view.for_each(&sub_extent, |p: Point3i, value| {
+            assert!(sub_extent.contains(p));
+            assert_eq!(value, view.get(p));
+            visited.push(p);
+        });
+        assert_eq!(visited.len(), sub_extent.num_points());
+    }
+}
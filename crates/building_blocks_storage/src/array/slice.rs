@@ -0,0 +1,343 @@
+//! 2D views of a single layer of a 3D `Array`, without copying.
+//!
+//! The 2D coordinates of a slice are the two remaining 3D coordinates, in order. So a slice perpendicular to X is indexed by
+//! `[y, z]`, a slice perpendicular to Y by `[x, z]`, and a slice perpendicular to Z by `[x, y]`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut array = Array3x1::fill_with(extent, |p| p.x() + p.y() + p.z());
+//!
+//! let layer = array.slice_z(5);
+//! assert_eq!(layer.extent(), Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(16)));
+//! assert_eq!(layer.get(PointN([1, 2])), 8);
+//!
+//! // Any per-layer processing can use the 2D access traits.
+//! let mut layer_sum = 0;
+//! layer.for_each(&layer.extent(), |_p: Point2i, value| layer_sum += value);
+//!
+//! // Writes through a mutable slice go straight to the 3D array.
+//! let mut layer = array.slice_mut(Axis3::X, 0);
+//! let layer_extent = layer.extent();
+//! layer.for_each_mut(&layer_extent, |_p: Point2i, value| *value = -1);
+//! assert_eq!(array.get(PointN([0, 3, 4])), -1);
+//! ```
+
+use crate::{Array, ForEach, ForEachMut, Get, GetMut, GetRef};
+
+use building_blocks_core::prelude::*;
+
+use core::ops::{Deref, DerefMut};
+
+/// A 2D view of the layer of a 3D `Array` at `coordinate` along `axis`. Supports the `Get*` and `ForEach*` traits with
+/// `Point2i` coordinates.
+#[derive(Clone, Copy)]
+pub struct Array3Slice<Delegate> {
+    pub delegate: Delegate,
+    axis: Axis3,
+    coordinate: i32,
+}
+
+impl<Chan> Array<[i32; 3], Chan> {
+    /// A view of the layer at `coordinate` along `axis`.
+    ///
+    /// Panics if the layer is outside of this array's extent.
+    pub fn slice(&self, axis: Axis3, coordinate: i32) -> Array3Slice<&Self> {
+        Array3Slice::new(self, axis, coordinate)
+    }
+
+    /// A mutable view of the layer at `coordinate` along `axis`.
+    ///
+    /// Panics if the layer is outside of this array's extent.
+    pub fn slice_mut(&mut self, axis: Axis3, coordinate: i32) -> Array3Slice<&mut Self> {
+        Array3Slice::new(self, axis, coordinate)
+    }
+
+    /// A view of the layer at `x`, indexed by `[y, z]`.
+    pub fn slice_x(&self, x: i32) -> Array3Slice<&Self> {
+        self.slice(Axis3::X, x)
+    }
+
+    /// A view of the layer at `y`, indexed by `[x, z]`.
+    pub fn slice_y(&self, y: i32) -> Array3Slice<&Self> {
+        self.slice(Axis3::Y, y)
+    }
+
+    /// A view of the layer at `z`, indexed by `[x, y]`.
+    pub fn slice_z(&self, z: i32) -> Array3Slice<&Self> {
+        self.slice(Axis3::Z, z)
+    }
+}
+
+impl<Delegate, Chan> Array3Slice<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+{
+    fn new(delegate: Delegate, axis: Axis3, coordinate: i32) -> Self {
+        let extent = delegate.extent();
+        let i = axis.index();
+        assert!(
+            extent.minimum.0[i] <= coordinate && coordinate <= extent.max().0[i],
+            "Slice at {} along {:?} is outside of {:?}",
+            coordinate,
+            axis,
+            extent
+        );
+
+        Self {
+            delegate,
+            axis,
+            coordinate,
+        }
+    }
+
+    #[inline]
+    pub fn axis(&self) -> Axis3 {
+        self.axis
+    }
+
+    #[inline]
+    pub fn coordinate(&self) -> i32 {
+        self.coordinate
+    }
+
+    /// The 2D extent of this slice.
+    #[inline]
+    pub fn extent(&self) -> Extent2i {
+        let extent = self.delegate.extent();
+
+        Extent2i::from_min_and_shape(
+            project_point(self.axis, extent.minimum),
+            project_point(self.axis, extent.shape),
+        )
+    }
+
+    /// The 3D point in the array for the 2D point `p` in this slice.
+    #[inline]
+    pub fn lift_point(&self, p: Point2i) -> Point3i {
+        lift_point(self.axis, self.coordinate, p)
+    }
+
+    // The 3D extent, one layer thick, that covers `extent` of this slice.
+    fn lift_extent(&self, extent: &Extent2i) -> Extent3i {
+        Extent3i::from_min_and_shape(
+            self.lift_point(extent.minimum),
+            lift_point(self.axis, 1, extent.shape),
+        )
+    }
+}
+
+// The 2 in-plane axes of a slice perpendicular to `axis`, in order.
+#[inline]
+fn plane_axes(axis: Axis3) -> [usize; 2] {
+    match axis {
+        Axis3::X => [1, 2],
+        Axis3::Y => [0, 2],
+        Axis3::Z => [0, 1],
+    }
+}
+
+#[inline]
+fn project_point(axis: Axis3, p: Point3i) -> Point2i {
+    let [a, b] = plane_axes(axis);
+
+    PointN([p.0[a], p.0[b]])
+}
+
+#[inline]
+fn lift_point(axis: Axis3, coordinate: i32, p: Point2i) -> Point3i {
+    let [a, b] = plane_axes(axis);
+    let mut lifted = [0; 3];
+    lifted[axis.index()] = coordinate;
+    lifted[a] = p.x();
+    lifted[b] = p.y();
+
+    PointN(lifted)
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<Delegate, Chan> Get<Point2i> for Array3Slice<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: Get<Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as Get<Point3i>>::Item;
+
+    #[inline]
+    fn get(&self, p: Point2i) -> Self::Item {
+        self.delegate.get(self.lift_point(p))
+    }
+}
+
+impl<'a, Delegate, Chan> GetRef<'a, Point2i> for Array3Slice<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: 'a + GetRef<'a, Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as GetRef<'a, Point3i>>::Item;
+
+    #[inline]
+    fn get_ref(&'a self, p: Point2i) -> Self::Item {
+        let p = self.lift_point(p);
+
+        self.delegate.deref().get_ref(p)
+    }
+}
+
+impl<'a, Delegate, Chan> GetMut<'a, Point2i> for Array3Slice<Delegate>
+where
+    Delegate: DerefMut<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: 'a + GetMut<'a, Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as GetMut<'a, Point3i>>::Item;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: Point2i) -> Self::Item {
+        let p = self.lift_point(p);
+
+        self.delegate.deref_mut().get_mut(p)
+    }
+}
+
+// ███████╗ ██████╗ ██████╗     ███████╗ █████╗  ██████╗██╗  ██╗
+// ██╔════╝██╔═══██╗██╔══██╗    ██╔════╝██╔══██╗██╔════╝██║  ██║
+// █████╗  ██║   ██║██████╔╝    █████╗  ███████║██║     ███████║
+// ██╔══╝  ██║   ██║██╔══██╗    ██╔══╝  ██╔══██║██║     ██╔══██║
+// ██║     ╚██████╔╝██║  ██║    ███████╗██║  ██║╚██████╗██║  ██║
+// ╚═╝      ╚═════╝ ╚═╝  ╚═╝    ╚══════╝╚═╝  ╚═╝ ╚═════╝╚═╝  ╚═╝
+
+impl<Delegate, Chan> ForEach<[i32; 2], Point2i> for Array3Slice<Delegate>
+where
+    Delegate: Deref<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: ForEach<[i32; 3], Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as ForEach<[i32; 3], Point3i>>::Item;
+
+    #[inline]
+    fn for_each(&self, extent: &Extent2i, mut f: impl FnMut(Point2i, Self::Item)) {
+        let axis = self.axis;
+        self.delegate
+            .for_each(&self.lift_extent(extent), |p: Point3i, value| {
+                f(project_point(axis, p), value)
+            });
+    }
+}
+
+impl<'a, Delegate, Chan> ForEachMut<'a, [i32; 2], Point2i> for Array3Slice<Delegate>
+where
+    Delegate: DerefMut<Target = Array<[i32; 3], Chan>>,
+    Array<[i32; 3], Chan>: 'a + ForEachMut<'a, [i32; 3], Point3i>,
+{
+    type Item = <Array<[i32; 3], Chan> as ForEachMut<'a, [i32; 3], Point3i>>::Item;
+
+    #[inline]
+    fn for_each_mut(&'a mut self, extent: &Extent2i, mut f: impl FnMut(Point2i, Self::Item)) {
+        let axis = self.axis;
+        let lifted_extent = self.lift_extent(extent);
+        self.delegate
+            .deref_mut()
+            .for_each_mut(&lifted_extent, |p: Point3i, value| {
+                f(project_point(axis, p), value)
+            });
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    fn func(p: Point3i) -> i32 {
+        p.x() + 100 * p.y() + 10000 * p.z()
+    }
+
+    #[test]
+    fn slices_match_array_on_every_axis() {
+        let extent = Extent3i::from_min_and_shape(PointN([-2, 3, -5]), PointN([4, 5, 6]));
+        let array = Array3x1::fill_with(extent, func);
+
+        for &axis in [Axis3::X, Axis3::Y, Axis3::Z].iter() {
+            let i = axis.index();
+            for coordinate in extent.minimum.0[i]..extent.least_upper_bound().0[i] {
+                let slice = array.slice(axis, coordinate);
+
+                let mut num_points = 0;
+                slice.for_each(&slice.extent(), |p: Point2i, value| {
+                    let p3 = slice.lift_point(p);
+                    assert_eq!(p3.0[i], coordinate);
+                    assert_eq!(value, func(p3));
+                    assert_eq!(slice.get(p), value);
+                    assert_eq!(slice.get_ref(p), &value);
+                    num_points += 1;
+                });
+                assert_eq!(num_points, slice.extent().num_points());
+            }
+        }
+    }
+
+    #[test]
+    fn slice_z_is_indexed_by_x_and_y() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let array = Array3x1::fill_with(extent, func);
+
+        assert_eq!(
+            array.slice_z(2).get(PointN([1, 3])),
+            func(PointN([1, 3, 2]))
+        );
+        assert_eq!(
+            array.slice_y(2).get(PointN([1, 3])),
+            func(PointN([1, 2, 3]))
+        );
+        assert_eq!(
+            array.slice_x(2).get(PointN([1, 3])),
+            func(PointN([2, 1, 3]))
+        );
+    }
+
+    #[test]
+    fn mutable_slice_only_writes_its_layer() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut array = Array3x1::fill(extent, 0);
+
+        let mut slice = array.slice_mut(Axis3::Y, 1);
+        let slice_extent = slice.extent();
+        slice.for_each_mut(&slice_extent, |_p: Point2i, value| *value = 1);
+        *slice.get_mut(PointN([0, 0])) = 2;
+
+        array.for_each(&extent, |p: Point3i, value| {
+            let expected = if p == PointN([0, 1, 0]) {
+                2
+            } else if p.y() == 1 {
+                1
+            } else {
+                0
+            };
+            assert_eq!(value, expected);
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_outside_of_array_panics() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let array = Array3x1::fill(extent, 0);
+
+        array.slice_z(4);
+    }
+}
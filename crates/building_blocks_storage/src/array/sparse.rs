@@ -0,0 +1,89 @@
+use crate::{Array, Channels, GetMutPtr, IndexedArray, Local, MultiMutPtr, Stride, WritePoints};
+
+use building_blocks_core::prelude::*;
+
+use alloc::vec::Vec;
+
+impl<N, Chan> WritePoints<N> for Array<N, Chan>
+where
+    Self: IndexedArray<N> + GetMutPtr<Stride, Item = Chan::Ptr>,
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels,
+{
+    type Item = Chan::Data;
+
+    /// Points outside of this array's extent are skipped. The values are sorted by stride first, so the array is written in
+    /// memory order.
+    fn write_points<I>(&mut self, points: I)
+    where
+        I: IntoIterator<Item = (PointN<N>, Self::Item)>,
+    {
+        let extent = *self.extent();
+        let mut writes: Vec<_> = points
+            .into_iter()
+            .filter(|(p, _)| extent.contains(*p))
+            .map(|(p, value)| {
+                (
+                    self.stride_from_local_point(Local(p - extent.minimum)),
+                    value,
+                )
+            })
+            .collect();
+
+        // A stable sort keeps duplicate points in order, so the last value wins.
+        writes.sort_by_key(|(stride, _)| stride.0);
+
+        for (stride, value) in writes.into_iter() {
+            unsafe {
+                self.get_mut_ptr(stride).write(value);
+            }
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn write_points_skips_out_of_bounds_and_last_value_wins() {
+        let extent = Extent3i::from_min_and_shape(PointN([-2, -2, -2]), Point3i::fill(4));
+        let mut array = Array3x1::fill(extent, 0);
+
+        array.write_points(vec![
+            (PointN([1, 1, 1]), 1),
+            (PointN([-2, 0, 1]), 2),
+            (PointN([5, 0, 0]), 3),
+            (PointN([1, 1, 1]), 4),
+        ]);
+
+        array.for_each(&extent, |p: Point3i, value| {
+            let expected = match p.0 {
+                [1, 1, 1] => 4,
+                [-2, 0, 1] => 2,
+                _ => 0,
+            };
+            assert_eq!(value, expected);
+        });
+    }
+
+    #[test]
+    fn write_points_to_multichannel_array() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut array = Array3x2::fill(extent, (0, 'a'));
+
+        array.write_points(vec![(PointN([3, 2, 1]), (7, 'b'))]);
+
+        assert_eq!(array.get(PointN([3, 2, 1])), (7, 'b'));
+        assert_eq!(array.get(PointN([1, 2, 3])), (0, 'a'));
+    }
+}
@@ -0,0 +1,131 @@
+//! Filling single-channel arrays one X-strip at a time.
+//!
+//! SIMD noise libraries work best when they generate many values at once, rather than being called through a scalar
+//! `Fn(Point) -> T` closure for every point. The X axis is the fastest-changing axis of the array layout, so each strip of
+//! points along X is a contiguous slice of the array, which can be given directly to a batch noise function.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//!
+//! // This would normally call into a SIMD noise function for the whole strip.
+//! let array = Array3x1::fill_with_strips(extent, |strip_min: Point3i, strip: &mut [f32]| {
+//!     for (i, value) in strip.iter_mut().enumerate() {
+//!         *value = (strip_min.x() + i as i32 + strip_min.y()) as f32;
+//!     }
+//! });
+//!
+//! assert_eq!(array.get(PointN([3, 4, 5])), 7.0);
+//! ```
+
+use crate::{ArrayIndexer, ArrayNx1, Channel, IndexedArray, Local};
+
+use building_blocks_core::prelude::*;
+
+impl<N, T> ArrayNx1<N, T>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    T: Clone + Default,
+{
+    /// Create a new array for `extent`, where `filler` writes the values of each X-strip. `filler` is given the minimum point
+    /// of the strip and the slice of values for all points from the minimum up to the maximum X of `extent`.
+    pub fn fill_with_strips(extent: ExtentN<N>, filler: impl FnMut(PointN<N>, &mut [T])) -> Self {
+        let mut array = Self::new(extent, Channel::fill(T::default(), extent.num_points()));
+        array.write_strips(&extent, filler);
+
+        array
+    }
+}
+
+impl<N, T> ArrayNx1<N, T>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+{
+    /// Calls `filler` on each X-strip of the intersection of `extent` and this array's extent, overwriting the values of that
+    /// strip. See `fill_with_strips`.
+    pub fn write_strips(
+        &mut self,
+        extent: &ExtentN<N>,
+        mut filler: impl FnMut(PointN<N>, &mut [T]),
+    ) {
+        let write_extent = self.extent().intersection(extent);
+        if write_extent.is_empty() {
+            return;
+        }
+
+        let strip_len = write_extent.shape.x() as usize;
+        for strip_min in x_strip_minimums(&write_extent) {
+            let start = self
+                .stride_from_local_point(Local(strip_min - self.extent().minimum))
+                .0;
+            let values = &mut self.channels_mut().store_mut()[start..start + strip_len];
+            filler(strip_min, values);
+        }
+    }
+}
+
+// The minimum of each X-strip of `extent`, in the same order as the strips are laid out in memory.
+fn x_strip_minimums<N>(extent: &ExtentN<N>) -> impl Iterator<Item = PointN<N>>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    let x_unit = PointN::<N>::basis()[0];
+    let face_shape = extent.shape - x_unit * (extent.shape.x() - 1);
+
+    ExtentN::from_min_and_shape(extent.minimum, face_shape).iter_points()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    fn func(p: Point3i) -> i32 {
+        p.x() + 100 * p.y() + 10000 * p.z()
+    }
+
+    fn strip_func(strip_min: Point3i, strip: &mut [i32]) {
+        for (i, value) in strip.iter_mut().enumerate() {
+            *value = func(strip_min + PointN([i as i32, 0, 0]));
+        }
+    }
+
+    #[test]
+    fn fill_with_strips_matches_fill_with() {
+        let extent = Extent3i::from_min_and_shape(PointN([-3, 2, -7]), PointN([5, 6, 7]));
+
+        let strips = Array3x1::fill_with_strips(extent, strip_func);
+
+        assert_eq!(strips, Array3x1::fill_with(extent, func));
+    }
+
+    #[test]
+    fn write_strips_only_writes_inside_of_extent() {
+        let array_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut array = Array3x1::fill(array_extent, -1);
+
+        let write_extent = Extent3i::from_min_and_shape(PointN([2, -4, 3]), PointN([10, 6, 2]));
+        array.write_strips(&write_extent, strip_func);
+
+        for p in array_extent.iter_points() {
+            let expected = if write_extent.contains(p) {
+                func(p)
+            } else {
+                -1
+            };
+            assert_eq!(array.get(p), expected);
+        }
+    }
+}
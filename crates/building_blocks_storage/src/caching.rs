@@ -1,5 +1,7 @@
+mod eviction;
 mod local_cache;
 mod lru_cache;
 
+pub use eviction::*;
 pub use local_cache::*;
 pub use lru_cache::*;
@@ -0,0 +1,337 @@
+use crate::SmallKeyHashMap;
+
+use core::hash::Hash;
+use std::collections::BTreeMap;
+
+/// Decides which cached key should be evicted next.
+///
+/// The owner of the cache is responsible for notifying the policy about every key that enters or leaves the cache, and about
+/// accesses to cached keys.
+pub trait EvictionPolicy<K> {
+    /// `key` entered the cache. Inserting a key that is already tracked counts as an access.
+    fn insert(&mut self, key: K);
+
+    /// `key` was accessed while cached.
+    fn touch(&mut self, key: &K);
+
+    /// `key` left the cache by some means other than `pop_victim`.
+    fn remove(&mut self, key: &K);
+
+    /// Chooses the next key to evict and stops tracking it. Returns `None` iff no keys are tracked.
+    fn pop_victim(&mut self) -> Option<K>;
+
+    /// The number of tracked keys.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Evicts the Least Recently Used key.
+#[derive(Clone, Debug)]
+pub struct LruPolicy<K> {
+    last_used: SmallKeyHashMap<K, u64>,
+    order: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K> Default for LruPolicy<K> {
+    fn default() -> Self {
+        Self {
+            last_used: SmallKeyHashMap::default(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<K> EvictionPolicy<K> for LruPolicy<K>
+where
+    K: Clone + Hash + Eq,
+{
+    fn insert(&mut self, key: K) {
+        self.clock += 1;
+        if let Some(old_time) = self.last_used.insert(key.clone(), self.clock) {
+            self.order.remove(&old_time);
+        }
+        self.order.insert(self.clock, key);
+    }
+
+    fn touch(&mut self, key: &K) {
+        if self.last_used.contains_key(key) {
+            self.insert(key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(time) = self.last_used.remove(key) {
+            self.order.remove(&time);
+        }
+    }
+
+    fn pop_victim(&mut self) -> Option<K> {
+        let oldest_time = *self.order.keys().next()?;
+        let key = self.order.remove(&oldest_time).unwrap();
+        self.last_used.remove(&key);
+
+        Some(key)
+    }
+
+    fn len(&self) -> usize {
+        self.last_used.len()
+    }
+}
+
+/// Evicts the Least Frequently Used key. Ties are broken by evicting the least recently used of those keys.
+///
+/// Access counts are only kept while a key is tracked, so a key that gets evicted and reinserted starts over.
+#[derive(Clone, Debug)]
+pub struct LfuPolicy<K> {
+    usage: SmallKeyHashMap<K, Usage>,
+    order: BTreeMap<Usage, K>,
+    clock: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Usage {
+    count: u64,
+    last_used: u64,
+}
+
+impl<K> Default for LfuPolicy<K> {
+    fn default() -> Self {
+        Self {
+            usage: SmallKeyHashMap::default(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<K> EvictionPolicy<K> for LfuPolicy<K>
+where
+    K: Clone + Hash + Eq,
+{
+    fn insert(&mut self, key: K) {
+        self.clock += 1;
+        let clock = self.clock;
+        let usage = self.usage.entry(key.clone()).or_insert(Usage {
+            count: 0,
+            last_used: 0,
+        });
+        self.order.remove(usage);
+        usage.count += 1;
+        usage.last_used = clock;
+        self.order.insert(*usage, key);
+    }
+
+    fn touch(&mut self, key: &K) {
+        if self.usage.contains_key(key) {
+            self.insert(key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(usage) = self.usage.remove(key) {
+            self.order.remove(&usage);
+        }
+    }
+
+    fn pop_victim(&mut self) -> Option<K> {
+        let least_usage = *self.order.keys().next()?;
+        let key = self.order.remove(&least_usage).unwrap();
+        self.usage.remove(&key);
+
+        Some(key)
+    }
+
+    fn len(&self) -> usize {
+        self.usage.len()
+    }
+}
+
+/// The CLOCK (second chance) approximation of LRU. Keys sit in a circular buffer with a "referenced" bit that is set on every
+/// access. The clock hand sweeps the buffer, clearing referenced bits, until it finds an unreferenced key to evict.
+///
+/// This is cheaper than `LruPolicy` when accesses are much more frequent than evictions, since an access only sets a bit.
+#[derive(Clone, Debug)]
+pub struct ClockPolicy<K> {
+    slots: Vec<Option<ClockSlot<K>>>,
+    slot_of_key: SmallKeyHashMap<K, usize>,
+    free_slots: Vec<usize>,
+    hand: usize,
+}
+
+#[derive(Clone, Debug)]
+struct ClockSlot<K> {
+    key: K,
+    referenced: bool,
+}
+
+impl<K> Default for ClockPolicy<K> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            slot_of_key: SmallKeyHashMap::default(),
+            free_slots: Vec::new(),
+            hand: 0,
+        }
+    }
+}
+
+impl<K> EvictionPolicy<K> for ClockPolicy<K>
+where
+    K: Clone + Hash + Eq,
+{
+    fn insert(&mut self, key: K) {
+        if let Some(&i) = self.slot_of_key.get(&key) {
+            self.slots[i].as_mut().unwrap().referenced = true;
+
+            return;
+        }
+
+        let slot = ClockSlot {
+            key: key.clone(),
+            referenced: true,
+        };
+        let i = if let Some(i) = self.free_slots.pop() {
+            self.slots[i] = Some(slot);
+
+            i
+        } else {
+            self.slots.push(Some(slot));
+
+            self.slots.len() - 1
+        };
+        self.slot_of_key.insert(key, i);
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some(&i) = self.slot_of_key.get(key) {
+            self.slots[i].as_mut().unwrap().referenced = true;
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(i) = self.slot_of_key.remove(key) {
+            self.slots[i] = None;
+            self.free_slots.push(i);
+        }
+    }
+
+    fn pop_victim(&mut self) -> Option<K> {
+        if self.slot_of_key.is_empty() {
+            return None;
+        }
+
+        // Every referenced bit gets cleared in the first revolution, so this terminates in at most two.
+        loop {
+            let i = self.hand;
+            self.hand = (self.hand + 1) % self.slots.len();
+
+            if let Some(slot) = &mut self.slots[i] {
+                if slot.referenced {
+                    slot.referenced = false;
+                } else {
+                    let slot = self.slots[i].take().unwrap();
+                    self.slot_of_key.remove(&slot.key);
+                    self.free_slots.push(i);
+
+                    return Some(slot.key);
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.slot_of_key.len()
+    }
+}
+
+// ████████╗███████╗███████╗████████╗███████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝██╔════╝
+//    ██║   █████╗  ███████╗   ██║   ███████╗
+//    ██║   ██╔══╝  ╚════██║   ██║   ╚════██║
+//    ██║   ███████╗███████║   ██║   ███████║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝   ╚══════╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(policy: &mut impl EvictionPolicy<i32>) -> Vec<i32> {
+        std::iter::from_fn(|| policy.pop_victim()).collect()
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut policy = LruPolicy::default();
+        policy.insert(1);
+        policy.insert(2);
+        policy.insert(3);
+        policy.touch(&1);
+        policy.remove(&2);
+
+        assert_eq!(policy.len(), 2);
+        assert_eq!(drain(&mut policy), vec![3, 1]);
+        assert!(policy.is_empty());
+    }
+
+    #[test]
+    fn lfu_evicts_least_frequently_used() {
+        let mut policy = LfuPolicy::default();
+        policy.insert(1);
+        policy.insert(2);
+        policy.insert(3);
+        policy.touch(&1);
+        policy.touch(&1);
+        policy.touch(&3);
+
+        // 2 was used once, 3 twice, and 1 three times.
+        assert_eq!(drain(&mut policy), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn lfu_breaks_ties_by_recency() {
+        let mut policy = LfuPolicy::default();
+        policy.insert(1);
+        policy.insert(2);
+        policy.touch(&2);
+        policy.touch(&1);
+
+        assert_eq!(drain(&mut policy), vec![2, 1]);
+    }
+
+    #[test]
+    fn clock_gives_referenced_keys_a_second_chance() {
+        let mut policy = ClockPolicy::default();
+        policy.insert(1);
+        policy.insert(2);
+        policy.insert(3);
+
+        // All keys start referenced, so the first sweep clears every bit and evicts the first key.
+        assert_eq!(policy.pop_victim(), Some(1));
+
+        // Now 2 gets a second chance.
+        policy.touch(&2);
+        assert_eq!(policy.pop_victim(), Some(3));
+        assert_eq!(policy.pop_victim(), Some(2));
+        assert_eq!(policy.pop_victim(), None);
+    }
+
+    #[test]
+    fn clock_reuses_free_slots() {
+        let mut policy = ClockPolicy::default();
+        policy.insert(1);
+        policy.insert(2);
+        policy.remove(&1);
+        policy.insert(3);
+
+        assert_eq!(policy.slots.len(), 2);
+        assert_eq!(policy.len(), 2);
+        let mut victims = drain(&mut policy);
+        victims.sort_unstable();
+        assert_eq!(victims, vec![2, 3]);
+    }
+}
@@ -239,6 +239,12 @@ where
         Some((key, entry.unwrap_value()))
     }
 
+    /// The key of the least-recently used value, if any values are cached.
+    #[inline]
+    pub fn lru_key(&self) -> Option<&K> {
+        self.order.back_value()
+    }
+
     /// Removes the least-recently used value, leaving no trace.
     #[inline]
     pub fn remove_lru(&mut self) -> Option<(K, V)> {
@@ -506,6 +512,10 @@ impl<T> LruList<T> {
         self.entries[Self::OCCUPIED].prev
     }
 
+    fn back_value(&self) -> Option<&T> {
+        self.entries[self.back()].value.as_ref()
+    }
+
     fn pop_back(&mut self) -> T {
         let index = self.back();
 
@@ -598,10 +608,12 @@ mod tests {
         cache.insert(4, 5);
         cache.insert(2, 5);
 
+        assert_eq!(cache.lru_key(), Some(&1));
         assert_eq!(cache.evict_lru(()), Some((1, 2)));
         assert_eq!(cache.evict_lru(()), Some((3, 4)));
         assert_eq!(cache.evict_lru(()), Some((4, 5)));
         assert_eq!(cache.evict_lru(()), Some((2, 5)));
+        assert_eq!(cache.lru_key(), None);
 
         assert!(cache.len_cached() == 0);
         assert!(cache.len_evicted() == 4);
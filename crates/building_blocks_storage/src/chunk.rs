@@ -1,7 +1,9 @@
+pub mod delta;
 pub mod indexer;
 pub mod map;
 pub mod storage;
 
+pub use delta::*;
 pub use indexer::*;
 pub use map::*;
 pub use storage::*;
@@ -0,0 +1,404 @@
+//! Delta encoding of chunks, for sending only the changed voxels over a network.
+//!
+//! A `ChunkDelta` is a compact patch that turns one version of an array chunk into another. It only stores the runs of values
+//! that changed, so a small edit to a large chunk makes a small delta. Deltas are serializable with `serde`, and they usually
+//! compress well with any `BytesCompression` after serialization.
+//!
+//! A server typically keeps a `LastSyncedChunks` per client, which remembers the last version of each chunk that the client
+//! received, then sends the client the deltas returned by `LastSyncedChunks::sync`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, ChunkDelta, LastSyncedChunks};
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let key = ChunkKey::new(0, Point3i::ZERO);
+//! let mut server_chunk = Array3x1::fill(extent, 0u8);
+//! let mut client_chunk = Array3x1::fill(extent, 0u8);
+//!
+//! let mut synced = LastSyncedChunks::default();
+//!
+//! // The first sync sends every value.
+//! *server_chunk.get_mut(Point3i::fill(1)) = 1;
+//! let delta = synced.sync(key, &server_chunk);
+//! assert_eq!(delta.num_values(), extent.num_points());
+//! delta.apply(&mut client_chunk).unwrap();
+//!
+//! // Later syncs only send the changes.
+//! *server_chunk.get_mut(Point3i::fill(2)) = 2;
+//! let delta = synced.sync(key, &server_chunk);
+//! assert_eq!(delta.num_values(), 1);
+//!
+//! let bytes = bincode::serialize(&delta).unwrap();
+//! let received: ChunkDelta<[i32; 3], u8> = bincode::deserialize(&bytes).unwrap();
+//! received.apply(&mut client_chunk).unwrap();
+//! assert_eq!(client_chunk, server_chunk);
+//! ```
+
+use crate::{Array, Channels, ChunkKey, Get, GetMutPtr, MultiMutPtr, SmallKeyHashMap};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use serde::{Deserialize, Serialize};
+
+/// A patch that turns one version of an `Array` chunk into another with the same extent. See the [module docs](self).
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChunkDelta<N, T> {
+    extent: ExtentN<N>,
+    runs: Vec<DeltaRun<T>>,
+}
+
+/// A run of consecutive values, in array order, that replace the values starting at `start`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DeltaRun<T> {
+    /// The array index of the first value.
+    pub start: u32,
+    pub values: Vec<T>,
+}
+
+/// The reason that a `ChunkDelta` can't be applied to a chunk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChunkDeltaError {
+    /// The chunk doesn't have the same extent as the chunks the delta was made from.
+    ExtentMismatch,
+    /// A run would write past the last value of the chunk, e.g. because the delta was deserialized from corrupt data.
+    RunOutOfBounds,
+}
+
+/// A 2D `ChunkDelta`.
+pub type ChunkDelta2<T> = ChunkDelta<[i32; 2], T>;
+/// A 3D `ChunkDelta`.
+pub type ChunkDelta3<T> = ChunkDelta<[i32; 3], T>;
+
+impl<N, T> ChunkDelta<N, T> {
+    /// The extent of the chunks that this delta applies to.
+    pub fn extent(&self) -> &ExtentN<N> {
+        &self.extent
+    }
+
+    pub fn runs(&self) -> &[DeltaRun<T>] {
+        &self.runs
+    }
+
+    /// Returns `true` iff applying this delta doesn't change anything.
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// The total number of values stored in all runs.
+    pub fn num_values(&self) -> usize {
+        self.runs.iter().map(|run| run.values.len()).sum()
+    }
+}
+
+impl<N, T> ChunkDelta<N, T>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    /// Returns the delta that transforms `old` into `new`.
+    ///
+    /// # Panics
+    ///
+    /// If `old` and `new` have different extents.
+    pub fn diff<Chan>(old: &Array<N, Chan>, new: &Array<N, Chan>) -> Self
+    where
+        Chan: Get<usize, Item = T>,
+        T: PartialEq,
+    {
+        assert!(
+            old.extent() == new.extent(),
+            "Can't diff chunks with different extents"
+        );
+
+        let mut runs: Vec<DeltaRun<T>> = Vec::new();
+        for i in 0..new.extent().num_points() {
+            let new_value = new.channels().get(i);
+            if old.channels().get(i) == new_value {
+                continue;
+            }
+
+            match runs.last_mut() {
+                Some(run) if run.start as usize + run.values.len() == i => {
+                    run.values.push(new_value)
+                }
+                _ => runs.push(DeltaRun {
+                    start: i as u32,
+                    values: vec![new_value],
+                }),
+            }
+        }
+
+        Self {
+            extent: *new.extent(),
+            runs,
+        }
+    }
+
+    /// Returns a delta that sets every value of a chunk to the values of `chunk`. This can be applied to any chunk with the
+    /// same extent, like a freshly allocated ambient chunk.
+    pub fn full<Chan>(chunk: &Array<N, Chan>) -> Self
+    where
+        Chan: Get<usize, Item = T>,
+    {
+        let values: Vec<_> = (0..chunk.extent().num_points())
+            .map(|i| chunk.channels().get(i))
+            .collect();
+        let runs = if values.is_empty() {
+            Vec::new()
+        } else {
+            vec![DeltaRun { start: 0, values }]
+        };
+
+        Self {
+            extent: *chunk.extent(),
+            runs,
+        }
+    }
+
+    /// Checks that `chunk` has the extent of this delta and that every run fits inside of it.
+    fn check_bounds<Chan>(&self, chunk: &Array<N, Chan>) -> Result<(), ChunkDeltaError> {
+        if *chunk.extent() != self.extent {
+            return Err(ChunkDeltaError::ExtentMismatch);
+        }
+
+        let num_points = self.extent.num_points();
+        for run in self.runs.iter() {
+            let end = (run.start as usize).checked_add(run.values.len());
+            if !matches!(end, Some(end) if end <= num_points) {
+                return Err(ChunkDeltaError::RunOutOfBounds);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the values of this delta into `chunk`. Nothing is written if this returns an error.
+    pub fn apply<Chan>(&self, chunk: &mut Array<N, Chan>) -> Result<(), ChunkDeltaError>
+    where
+        Chan: Channels<Data = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+        T: Clone,
+    {
+        self.check_bounds(chunk)?;
+
+        let channels = chunk.channels_mut();
+        for run in self.runs.iter() {
+            let start = run.start as usize;
+            for (i, value) in run.values.iter().enumerate() {
+                // SAFE: `check_bounds` found every run to be inside of the chunk.
+                unsafe {
+                    channels.get_mut_ptr(start + i).write(value.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like `apply`, but also returns the delta that undoes this one, i.e. the values of `chunk` that were overwritten.
+    pub fn apply_and_invert<Chan>(
+        &self,
+        chunk: &mut Array<N, Chan>,
+    ) -> Result<Self, ChunkDeltaError>
+    where
+        Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+        T: Clone,
+    {
+        self.check_bounds(chunk)?;
+
+        let channels = chunk.channels_mut();
+        let mut inverse_runs = Vec::with_capacity(self.runs.len());
+        for run in self.runs.iter() {
+            let start = run.start as usize;
+            let mut old_values = Vec::with_capacity(run.values.len());
+            for (i, value) in run.values.iter().enumerate() {
+                old_values.push(channels.get(start + i));
+                // SAFE: `check_bounds` found every run to be inside of the chunk.
+                unsafe {
+                    channels.get_mut_ptr(start + i).write(value.clone());
+                }
+            }
+            inverse_runs.push(DeltaRun {
+                start: run.start,
+                values: old_values,
+            });
+        }
+
+        Ok(Self {
+            extent: self.extent,
+            runs: inverse_runs,
+        })
+    }
+}
+
+/// Remembers the last version of each chunk that was synchronized with a peer, so that only deltas need to be sent.
+pub struct LastSyncedChunks<N, Chan> {
+    chunks: SmallKeyHashMap<ChunkKey<N>, Array<N, Chan>>,
+}
+
+impl<N, Chan> Default for LastSyncedChunks<N, Chan> {
+    fn default() -> Self {
+        Self {
+            chunks: SmallKeyHashMap::default(),
+        }
+    }
+}
+
+impl<N, Chan> LastSyncedChunks<N, Chan>
+where
+    ChunkKey<N>: Hash + Eq,
+{
+    /// The last version of the chunk at `key` that was synchronized.
+    pub fn get(&self, key: ChunkKey<N>) -> Option<&Array<N, Chan>> {
+        self.chunks.get(&key)
+    }
+
+    /// Forget the chunk at `key`, e.g. because the peer unloaded it. The next sync of this chunk will be a full delta.
+    pub fn forget(&mut self, key: ChunkKey<N>) {
+        self.chunks.remove(&key);
+    }
+
+    /// Returns the delta from the last synced version of the chunk at `key` to `chunk`, then remembers `chunk` as the last
+    /// synced version. If this chunk was never synced (or its extent changed), then the delta is `ChunkDelta::full`.
+    pub fn sync<T>(&mut self, key: ChunkKey<N>, chunk: &Array<N, Chan>) -> ChunkDelta<N, T>
+    where
+        PointN<N>: IntegerPoint<N>,
+        Array<N, Chan>: Clone,
+        Chan: Get<usize, Item = T>,
+        T: PartialEq,
+    {
+        match self.chunks.get_mut(&key) {
+            Some(synced) if synced.extent() == chunk.extent() => {
+                let delta = ChunkDelta::diff(synced, chunk);
+                *synced = chunk.clone();
+
+                delta
+            }
+            _ => {
+                self.chunks.insert(key, chunk.clone());
+
+                ChunkDelta::full(chunk)
+            }
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn diff_and_apply_multichannel() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let old = Array3x2::fill(extent, (0u8, 0.0f32));
+        let mut new = old.clone();
+        for p in [PointN([0, 0, 0]), PointN([1, 0, 0]), PointN([3, 3, 3])].iter() {
+            *new.get_mut(*p).0 = 1;
+        }
+        *new.get_mut(PointN([2, 0, 0])).1 = 2.0;
+
+        let delta = ChunkDelta::diff(&old, &new);
+        assert_eq!(
+            delta.runs(),
+            &[
+                DeltaRun {
+                    start: 0,
+                    values: vec![(1, 0.0), (1, 0.0), (0, 2.0)]
+                },
+                DeltaRun {
+                    start: 63,
+                    values: vec![(1, 0.0)]
+                }
+            ]
+        );
+
+        let mut patched = old.clone();
+        delta.apply(&mut patched).unwrap();
+        assert_eq!(patched, new);
+
+        let mut inverted = old.clone();
+        let inverse = delta.apply_and_invert(&mut inverted).unwrap();
+        assert_eq!(inverted, new);
+        inverse.apply(&mut inverted).unwrap();
+        assert_eq!(inverted, old);
+
+        assert!(ChunkDelta::diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn sync_sends_full_chunk_then_deltas() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let mut chunk = Array3x1::fill(extent, 0u16);
+        let mut synced = LastSyncedChunks::default();
+
+        assert_eq!(synced.sync(key, &chunk).num_values(), 64);
+        assert!(synced.sync(key, &chunk).is_empty());
+
+        *chunk.get_mut(Point3i::fill(1)) = 5;
+        let delta = synced.sync(key, &chunk);
+        assert_eq!(delta.num_values(), 1);
+        assert_eq!(synced.get(key), Some(&chunk));
+
+        synced.forget(key);
+        assert_eq!(synced.sync(key, &chunk).num_values(), 64);
+    }
+
+    #[test]
+    fn malformed_deltas_are_rejected() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        let mut chunk = Array3x1::fill(extent, 0u8);
+        let mut last_point = chunk.clone();
+        *last_point.get_mut(Point3i::fill(3)) = 1;
+        let delta = ChunkDelta::diff(&chunk, &last_point);
+
+        // A run that extends past the last value.
+        let overflowing: ChunkDelta3<u8> = {
+            let mut runs = delta.runs().to_vec();
+            runs[0].values.push(1);
+            ChunkDelta { extent, runs }
+        };
+        assert_eq!(
+            overflowing.apply(&mut chunk),
+            Err(ChunkDeltaError::RunOutOfBounds)
+        );
+        assert_eq!(
+            overflowing.apply_and_invert(&mut chunk),
+            Err(ChunkDeltaError::RunOutOfBounds)
+        );
+        let far_start = ChunkDelta {
+            extent,
+            runs: vec![DeltaRun {
+                start: u32::MAX,
+                values: vec![1u8],
+            }],
+        };
+        assert_eq!(
+            far_start.apply(&mut chunk),
+            Err(ChunkDeltaError::RunOutOfBounds)
+        );
+
+        // Truncated bytes fail to deserialize.
+        let mut bytes = bincode::serialize(&delta).unwrap();
+        bytes.pop();
+        assert!(bincode::deserialize::<ChunkDelta3<u8>>(&bytes).is_err());
+
+        // Nothing was written by the failed applications.
+        assert_eq!(chunk, Array3x1::fill(extent, 0));
+
+        let mut other_extent = Array3x1::fill(extent + Point3i::fill(1), 0u8);
+        assert_eq!(
+            delta.apply(&mut other_extent),
+            Err(ChunkDeltaError::ExtentMismatch)
+        );
+    }
+}
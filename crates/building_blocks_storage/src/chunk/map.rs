@@ -32,6 +32,28 @@
 //! random access. It could also be something more memory efficient like `FastCompressibleChunkStorage` or
 //! `CompressibleChunkStorageReader`, which perform nearly as well but involve some extra management of the cache.
 //!
+//! Any storage can be wrapped in `DirtyTracking` to record which chunks were mutated, then `ChunkMap::drain_dirty` returns
+//! those keys, e.g. for remeshing or persistence. Wrapping it in `UniformChunkFolding` stores chunks that have the same value
+//! at every point as just that value, until they're mutated.
+//!
+//! For culling and ray marching, `OccupancyTracking` keeps a 64-bit summary of which parts of each chunk are empty, without
+//! reading the chunk.
+//!
+//! With `SharedChunkStorage`, clones of a map share their chunks until they're mutated, which makes snapshots cheap.
+//!
+//! # Chunk Generation
+//!
+//! A builder can be given a chunk generator with `ChunkMapBuilder::with_generator`. Then missing chunks are synthesized on
+//! demand instead of taking the ambient value, which makes it easy to represent an infinite procedural world. See
+//! `ChunkMapBuilderWithGenerator` for which accesses store the generated chunks.
+//!
+//! # Floating Origin
+//!
+//! Far from the origin, `f32` meshes and transforms lose precision. `ChunkMap::shift_origin` moves all chunks by a chunk-aligned
+//! delta without copying any voxels, and `FloatingOrigin3` tracks where the lattice origin is in 64-bit world coordinates. If
+//! the storage implements `TranslateChunkKeys`, `ChunkMap::translate_keys` moves the chunks in place, and an `OctreeChunkIndex`
+//! can follow along with `OctreeChunkIndex::translate`.
+//!
 //! # Serialization
 //!
 //! In order to efficiently serialize a `ChunkMap`, you can first use `SerializableChunks::from_iter` to create a compact
@@ -99,7 +121,7 @@
 //! let ambient_value = 0;
 //! let builder = ChunkMapBuilder3x1::new(chunk_shape, ambient_value);
 //! let mut map = builder.build_with_write_storage(
-//!     FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 })
+//!     FastCompressibleChunkStorageNx1::with_bytes_compression(Snappy)
 //! );
 //! let mut lod0 = map.lod_view_mut(0);
 //!
@@ -131,21 +153,45 @@
 //! ```
 
 pub mod builder;
+pub mod channel_view;
+pub mod crop;
+pub mod editor;
+pub mod floating_origin;
+mod generate;
+pub mod journal;
 pub mod lod_view;
+pub mod merge;
+pub mod partition;
+pub mod prefab;
 pub mod sampling;
 
+#[cfg(feature = "dot_vox")]
+mod dot_vox_conversions;
+
 pub use builder::*;
+pub use channel_view::*;
+pub use crop::*;
+pub use editor::*;
+pub use floating_origin::*;
+pub use journal::*;
 pub use lod_view::*;
+pub use merge::*;
+pub use partition::*;
+pub use prefab::*;
 pub use sampling::*;
 
 use crate::{
-    Array, ChunkIndexer, ChunkKey, ChunkReadStorage, ChunkWriteStorage, FillExtent, ForEach, Get,
-    GetMut, GetRef, IterChunkKeys, MultiRef,
+    Array, ArrayCopySrc, AsyncChunkReader, Channels, ChunkIndexer, ChunkKey, ChunkReadStorage,
+    ChunkWriteStorage, FillExtent, ForEach, Get, GetMut, GetRef, IterChunkKeys, MultiRef,
+    TransformMap, TranslateChunkKeys, WriteExtent, WritePoints,
 };
 
 use building_blocks_core::{bounding_extent, ExtentN, IntegerPoint, PointN};
 
 use either::Either;
+use futures::future::join_all;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 /// One piece of a chunked lattice map.
 pub trait Chunk {
@@ -153,6 +199,10 @@ pub trait Chunk {
     /// from existing array types like `Array`.
     type Array;
 
+    /// The value at a single point, i.e. the `Channels::Data` of an `Array`. Storage like `UniformChunkFolding` can represent a
+    /// chunk that has the same value at every point with just one `Data`.
+    type Data;
+
     /// Borrow the inner array.
     fn array(&self) -> &Self::Array;
 
@@ -160,8 +210,12 @@ pub trait Chunk {
     fn array_mut(&mut self) -> &mut Self::Array;
 }
 
-impl<N, Chan> Chunk for Array<N, Chan> {
+impl<N, Chan> Chunk for Array<N, Chan>
+where
+    Chan: Channels,
+{
     type Array = Self;
+    type Data = Chan::Data;
 
     #[inline]
     fn array(&self) -> &Self::Array {
@@ -190,6 +244,9 @@ impl<N, Chan> Chunk for Array<N, Chan> {
 /// - `GetMut`
 /// - `ForEachMut`
 /// - `WriteExtent`
+///
+/// Cloning a `ChunkMap` clones its storage. With `SharedChunkStorage`, that only clones a pointer to each chunk.
+#[derive(Clone)]
 pub struct ChunkMap<N, T, Bldr, Store> {
     /// Translates from lattice coordinates to chunk key space.
     pub indexer: ChunkIndexer<N>,
@@ -272,6 +329,20 @@ impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store> {
             lod,
         }
     }
+
+    /// Get an immutable view of the channel selected by `Sel` (like `Ch0`) at level of detail `lod`, in order to use the access
+    /// traits as if this were a single-channel map.
+    #[inline]
+    pub fn lod_channel_view<Sel>(&self, lod: u8) -> ChunkMapChannelView<&'_ Self, Sel> {
+        ChunkMapChannelView::new(self, lod)
+    }
+
+    /// Get a mutable view of the channel selected by `Sel` (like `Ch0`) at level of detail `lod`, in order to use the access
+    /// traits as if this were a single-channel map. The other channels are left unchanged.
+    #[inline]
+    pub fn lod_channel_view_mut<Sel>(&mut self, lod: u8) -> ChunkMapChannelView<&'_ mut Self, Sel> {
+        ChunkMapChannelView::new(self, lod)
+    }
 }
 
 impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
@@ -295,7 +366,7 @@ where
     Bldr: ChunkMapBuilder<N, T>,
     Store: ChunkReadStorage<N, Bldr::Chunk>,
 {
-    /// Borrow the chunk at `key`.
+    /// Borrow the chunk at `key`. Returns `None` for a chunk that the storage folded into a single value; see `get_uniform_chunk`.
     ///
     /// In debug mode only, asserts that `key` is valid.
     #[inline]
@@ -305,7 +376,18 @@ where
         self.storage.get(key)
     }
 
-    /// Get the values at point `p` in level of detail `lod`.
+    /// Borrow the value of every point in the chunk at `key`, if the storage folded that chunk into a single value.
+    ///
+    /// In debug mode only, asserts that `key` is valid.
+    #[inline]
+    pub fn get_uniform_chunk(&self, key: ChunkKey<N>) -> Option<&T> {
+        debug_assert!(self.indexer.chunk_min_is_valid(key.minimum));
+
+        self.storage.get_uniform(key)
+    }
+
+    /// Get the values at point `p` in level of detail `lod`. If the chunk containing `p` is missing, it is synthesized by the
+    /// builder's chunk generator, if there is one, without being stored.
     #[inline]
     pub fn clone_point(&self, lod: u8, p: PointN<N>) -> T
     where
@@ -313,10 +395,20 @@ where
         <Bldr::Chunk as Chunk>::Array: Get<PointN<N>, Item = T>,
     {
         let chunk_min = self.indexer.min_of_chunk_containing_point(p);
-
-        self.get_chunk(ChunkKey::new(lod, chunk_min))
-            .map(|chunk| chunk.array().get(p))
-            .unwrap_or_else(|| self.ambient_value.clone())
+        let key = ChunkKey::new(lod, chunk_min);
+
+        if let Some(chunk) = self.get_chunk(key) {
+            chunk.array().get(p)
+        } else if let Some(value) = self.get_uniform_chunk(key) {
+            value.clone()
+        } else if let Some(chunk) = self
+            .builder
+            .generate_chunk(key, self.indexer.extent_for_chunk_with_min(chunk_min))
+        {
+            chunk.array().get(p)
+        } else {
+            self.ambient_value.clone()
+        }
     }
 
     /// Get a reference to the values at point `p` in level of detail `lod`.
@@ -327,34 +419,55 @@ where
         Ref: MultiRef<'a, Data = T>,
     {
         let chunk_min = self.indexer.min_of_chunk_containing_point(p);
-
-        self.get_chunk(ChunkKey::new(lod, chunk_min))
-            .map(|chunk| chunk.array().get_ref(p))
-            .unwrap_or_else(|| Ref::from_data_ref(&self.ambient_value))
+        let key = ChunkKey::new(lod, chunk_min);
+
+        if let Some(chunk) = self.get_chunk(key) {
+            chunk.array().get_ref(p)
+        } else if let Some(value) = self.get_uniform_chunk(key) {
+            Ref::from_data_ref(value)
+        } else {
+            Ref::from_data_ref(&self.ambient_value)
+        }
     }
 
-    /// Call `visitor` on all chunks that overlap `extent`. Vacant chunks will be represented by an `AmbientExtent`.
+    /// Call `visitor` on all chunks that overlap `extent`. Vacant chunks will be synthesized by the builder's chunk generator,
+    /// without being stored, or else represented by an `AmbientExtent`. Chunks that the storage folded into a single value are
+    /// also represented by an `AmbientExtent` with that value.
     #[inline]
     pub fn visit_chunks(
         &self,
         lod: u8,
         extent: &ExtentN<N>,
         mut visitor: impl FnMut(Either<&Bldr::Chunk, (&ExtentN<N>, AmbientExtent<N, T>)>),
-    ) {
+    ) where
+        T: Clone,
+    {
         for chunk_min in self.indexer.chunk_mins_for_extent(extent) {
-            if let Some(chunk) = self.get_chunk(ChunkKey::new(lod, chunk_min)) {
+            let key = ChunkKey::new(lod, chunk_min);
+            if let Some(chunk) = self.get_chunk(key) {
                 visitor(Either::Left(chunk))
-            } else {
+            } else if let Some(value) = self.get_uniform_chunk(key) {
                 let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
                 visitor(Either::Right((
                     &chunk_extent,
-                    AmbientExtent::new(self.builder.ambient_value()),
+                    AmbientExtent::new(value.clone()),
                 )))
+            } else {
+                let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+                if let Some(chunk) = self.builder.generate_chunk(key, chunk_extent) {
+                    visitor(Either::Left(&chunk))
+                } else {
+                    visitor(Either::Right((
+                        &chunk_extent,
+                        AmbientExtent::new(self.builder.ambient_value()),
+                    )))
+                }
             }
         }
     }
 
-    /// Call `visitor` on all occupied chunks that overlap `extent`.
+    /// Call `visitor` on all occupied chunks that overlap `extent`. Chunks that the storage folded into a single value are
+    /// skipped, since there is no chunk to borrow.
     #[inline]
     pub fn visit_occupied_chunks(
         &self,
@@ -420,7 +533,8 @@ where
         self.storage.get_mut_or_insert_with(key, create_chunk)
     }
 
-    /// Mutably borrow the chunk at `key`. If the chunk doesn't exist, a new chunk is created with the ambient value.
+    /// Mutably borrow the chunk at `key`. If the chunk doesn't exist, a new chunk is created by the builder's chunk generator,
+    /// or with the ambient value if there is no generator.
     ///
     /// In debug mode only, asserts that `key` is valid.
     #[inline]
@@ -436,10 +550,61 @@ where
         let chunk_min = key.minimum;
 
         storage.get_mut_or_insert_with(key, || {
-            builder.new_ambient(indexer.extent_for_chunk_with_min(chunk_min))
+            builder.new_vacant(key, indexer.extent_for_chunk_with_min(chunk_min))
         })
     }
 
+    /// Mutably borrow the chunk at `key`. If the chunk doesn't exist, it is synthesized by the builder's chunk generator and
+    /// written into storage. Returns `None` if the chunk doesn't exist and the generator doesn't produce it.
+    ///
+    /// In debug mode only, asserts that `key` is valid.
+    #[inline]
+    pub fn get_mut_chunk_or_generate(&mut self, key: ChunkKey<N>) -> Option<&mut Bldr::Chunk> {
+        debug_assert!(self.indexer.chunk_min_is_valid(key.minimum));
+
+        if self.storage.get_mut(key).is_none() {
+            trace_span!("generate_chunk", lod = key.lod);
+
+            let chunk_extent = self.indexer.extent_for_chunk_with_min(key.minimum);
+            if let Some(chunk) = self.builder.generate_chunk(key, chunk_extent) {
+                self.storage.write(key, chunk);
+            } else {
+                return None;
+            }
+        }
+
+        self.storage.get_mut(key)
+    }
+
+    /// Synthesizes all missing chunks that overlap `extent` with the builder's chunk generator and writes them into storage, so
+    /// they don't need to be generated again on every read. Returns the keys of the generated chunks.
+    pub fn generate_missing_chunks(&mut self, lod: u8, extent: &ExtentN<N>) -> Vec<ChunkKey<N>> {
+        trace_span!("generate_missing_chunks", lod);
+
+        let Self {
+            indexer,
+            storage,
+            builder,
+            ..
+        } = self;
+
+        let mut generated_keys = Vec::new();
+        for chunk_min in indexer.chunk_mins_for_extent(extent) {
+            let key = ChunkKey::new(lod, chunk_min);
+            if storage.get_mut(key).is_some() {
+                continue;
+            }
+            if let Some(chunk) =
+                builder.generate_chunk(key, indexer.extent_for_chunk_with_min(chunk_min))
+            {
+                storage.write(key, chunk);
+                generated_keys.push(key);
+            }
+        }
+
+        generated_keys
+    }
+
     /// Get a mutable reference to the values at point `p` in level of detail `lod`.
     #[inline]
     pub fn get_mut_point<'a, Mut>(&'a mut self, lod: u8, p: PointN<N>) -> Mut
@@ -452,7 +617,8 @@ where
         chunk.array_mut().get_mut(p)
     }
 
-    /// Call `visitor` on all chunks that overlap `extent`. Vacant chunks will be created first with ambient value.
+    /// Call `visitor` on all chunks that overlap `extent`. Vacant chunks will be created first, like in
+    /// `get_mut_chunk_or_insert_ambient`.
     #[inline]
     pub fn visit_mut_chunks(
         &mut self,
@@ -494,6 +660,241 @@ where
     }
 }
 
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+{
+    /// Mutably borrow the chunk at `key`. If the chunk is not in storage, it will first be loaded from `reader` and written into
+    /// storage. Returns `None` if neither the storage nor the `reader` has the chunk.
+    ///
+    /// In debug mode only, asserts that `key` is valid.
+    pub async fn get_or_load_chunk_async<Rd>(
+        &mut self,
+        key: ChunkKey<N>,
+        reader: &Rd,
+    ) -> Result<Option<&mut Bldr::Chunk>, Rd::Error>
+    where
+        Rd: AsyncChunkReader<N, Bldr::Chunk>,
+    {
+        debug_assert!(self.indexer.chunk_min_is_valid(key.minimum));
+
+        if self.storage.get_mut(key).is_none() {
+            if let Some(chunk) = reader.read_chunk(key).await? {
+                self.storage.write(key, chunk);
+            } else {
+                return Ok(None);
+            }
+        }
+
+        Ok(self.storage.get_mut(key))
+    }
+
+    /// Loads every chunk in `keys` that is not already in storage from `reader`, and writes them into storage. All of the reads
+    /// are in flight concurrently.
+    ///
+    /// Every chunk that was read successfully is written, even if some reads fail. In that case, the first error is returned.
+    pub async fn load_missing_chunks_async<Rd>(
+        &mut self,
+        keys: impl IntoIterator<Item = ChunkKey<N>>,
+        reader: &Rd,
+    ) -> Result<(), Rd::Error>
+    where
+        Rd: AsyncChunkReader<N, Bldr::Chunk>,
+    {
+        let Self {
+            indexer, storage, ..
+        } = self;
+        let missing_keys: Vec<_> = keys
+            .into_iter()
+            .filter(|key| {
+                debug_assert!(indexer.chunk_min_is_valid(key.minimum));
+
+                storage.get_mut(*key).is_none()
+            })
+            .collect();
+
+        let loaded_chunks = join_all(
+            missing_keys
+                .into_iter()
+                .map(|key| async move { (key, reader.read_chunk(key).await) }),
+        )
+        .await;
+
+        let mut first_error = None;
+        for (key, result) in loaded_chunks.into_iter() {
+            match result {
+                Ok(Some(chunk)) => storage.write(key, chunk),
+                Ok(None) => {}
+                Err(e) => {
+                    if first_error.is_none() {
+                        first_error = Some(e);
+                    }
+                }
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    Self: Sync,
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+    Bldr: ChunkMapBuilder<N, T>,
+    Store: ChunkReadStorage<N, Bldr::Chunk>,
+{
+    /// Like `visit_chunks`, but chunks are visited in parallel on the `rayon` thread pool.
+    #[inline]
+    pub fn par_visit_chunks(
+        &self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        visitor: impl Fn(Either<&Bldr::Chunk, (&ExtentN<N>, AmbientExtent<N, T>)>) + Sync,
+    ) where
+        T: Clone,
+    {
+        let chunk_mins: Vec<_> = self.indexer.chunk_mins_for_extent(extent).collect();
+        chunk_mins.into_par_iter().for_each(|chunk_min| {
+            let key = ChunkKey::new(lod, chunk_min);
+            if let Some(chunk) = self.get_chunk(key) {
+                visitor(Either::Left(chunk))
+            } else if let Some(value) = self.get_uniform_chunk(key) {
+                let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+                visitor(Either::Right((
+                    &chunk_extent,
+                    AmbientExtent::new(value.clone()),
+                )))
+            } else {
+                let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+                if let Some(chunk) = self.builder.generate_chunk(key, chunk_extent) {
+                    visitor(Either::Left(&chunk))
+                } else {
+                    visitor(Either::Right((
+                        &chunk_extent,
+                        AmbientExtent::new(self.builder.ambient_value()),
+                    )))
+                }
+            }
+        });
+    }
+
+    /// Like `visit_occupied_chunks`, but chunks are visited in parallel on the `rayon` thread pool.
+    #[inline]
+    pub fn par_visit_occupied_chunks(
+        &self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        visitor: impl Fn(&Bldr::Chunk) + Sync,
+    ) {
+        let chunk_mins: Vec<_> = self.indexer.chunk_mins_for_extent(extent).collect();
+        chunk_mins.into_par_iter().for_each(|chunk_min| {
+            if let Some(chunk) = self.get_chunk(ChunkKey::new(lod, chunk_min)) {
+                visitor(chunk)
+            }
+        });
+    }
+
+    /// Like `ForEach::for_each` on a `ChunkMapLodView`, but each chunk overlapping `extent` is iterated in parallel on the
+    /// `rayon` thread pool.
+    #[inline]
+    pub fn par_for_each(&self, lod: u8, extent: &ExtentN<N>, f: impl Fn(PointN<N>, T) + Sync)
+    where
+        T: Clone,
+        <Bldr::Chunk as Chunk>::Array: ForEach<N, PointN<N>, Item = T>,
+    {
+        self.par_visit_chunks(lod, extent, |chunk| match chunk {
+            Either::Left(chunk) => {
+                chunk.array().for_each(extent, |p, value| f(p, value));
+            }
+            Either::Right((chunk_extent, ambient)) => {
+                ambient.for_each(&extent.intersection(chunk_extent), |p, value| f(p, value))
+            }
+        });
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N> + Send,
+    Bldr: ChunkMapBuilder<N, T>,
+    Bldr::Chunk: Send,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+{
+    /// Like `visit_mut_chunks`, but chunks are visited in parallel on the `rayon` thread pool. Vacant chunks will be created
+    /// first, like in `get_mut_chunk_or_insert_ambient`.
+    ///
+    /// The chunks are temporarily popped out of storage so they can be borrowed mutably by separate threads.
+    #[inline]
+    pub fn par_visit_mut_chunks(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        visitor: impl Fn(&mut Bldr::Chunk) + Sync,
+    ) {
+        let Self {
+            indexer,
+            storage,
+            builder,
+            ..
+        } = self;
+        let mut chunks: Vec<_> = indexer
+            .chunk_mins_for_extent(extent)
+            .map(|chunk_min| {
+                let key = ChunkKey::new(lod, chunk_min);
+                let chunk = storage.pop(key).unwrap_or_else(|| {
+                    builder.new_vacant(key, indexer.extent_for_chunk_with_min(chunk_min))
+                });
+
+                (key, chunk)
+            })
+            .collect();
+
+        chunks
+            .par_iter_mut()
+            .for_each(|(_key, chunk)| visitor(chunk));
+
+        for (key, chunk) in chunks.into_iter() {
+            storage.write(key, chunk);
+        }
+    }
+
+    /// Like `visit_occupied_mut_chunks`, but chunks are visited in parallel on the `rayon` thread pool.
+    ///
+    /// The chunks are temporarily popped out of storage so they can be borrowed mutably by separate threads.
+    #[inline]
+    pub fn par_visit_occupied_mut_chunks(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        visitor: impl Fn(&mut Bldr::Chunk) + Sync,
+    ) {
+        let Self {
+            indexer, storage, ..
+        } = self;
+        let mut chunks: Vec<_> = indexer
+            .chunk_mins_for_extent(extent)
+            .filter_map(|chunk_min| {
+                let key = ChunkKey::new(lod, chunk_min);
+
+                storage.pop(key).map(|chunk| (key, chunk))
+            })
+            .collect();
+
+        chunks
+            .par_iter_mut()
+            .for_each(|(_key, chunk)| visitor(chunk));
+
+        for (key, chunk) in chunks.into_iter() {
+            storage.write(key, chunk);
+        }
+    }
+}
+
 impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
 where
     for<'r> ChunkMapLodView<&'r mut Self>: FillExtent<N, Item = T>,
@@ -505,6 +906,72 @@ where
     }
 }
 
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    for<'r> ChunkMapLodView<&'r mut Self>: WritePoints<N, Item = T>,
+{
+    /// Write the value of each `(point, value)` pair in level of detail `lod`. See `WritePoints`.
+    #[inline]
+    pub fn write_points(&mut self, lod: u8, points: impl IntoIterator<Item = (PointN<N>, T)>) {
+        self.lod_view_mut(lod).write_points(points)
+    }
+}
+
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+{
+    /// Copies `extent` of level of detail `lod` from the occupied chunks of `src` into `self`, mapping each value with
+    /// `transform`. Unlike `copy_extent`, vacant chunks of `src` are skipped instead of being written as ambient values, so a
+    /// sparse map stays sparse. `src` can have a different chunk shape and number of channels than `self`, e.g. for migrating
+    /// a world from `16^3` chunks to `32^3` chunks.
+    ///
+    /// Points of a destination chunk that aren't covered by any occupied source chunk keep their values, which are ambient if
+    /// the chunk was inserted by this copy. Chunks that `src` folded into a single value are skipped like vacant chunks.
+    pub fn copy_occupied_chunks_from<'a, SrcT, SrcBldr, SrcStore, F>(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        src: &'a ChunkMap<N, SrcT, SrcBldr, SrcStore>,
+        transform: &'a F,
+    ) where
+        SrcBldr: ChunkMapBuilder<N, SrcT>,
+        SrcStore: ChunkReadStorage<N, SrcBldr::Chunk>,
+        F: Fn(SrcT) -> T,
+        <Bldr::Chunk as Chunk>::Array:
+            WriteExtent<N, ArrayCopySrc<TransformMap<'a, <SrcBldr::Chunk as Chunk>::Array, &'a F>>>,
+    {
+        for chunk_min in src.indexer.chunk_mins_for_extent(extent) {
+            if let Some(src_chunk) = src.get_chunk(ChunkKey::new(lod, chunk_min)) {
+                let copy_extent =
+                    extent.intersection(&src.indexer.extent_for_chunk_with_min(chunk_min));
+                let src_array = TransformMap::new(src_chunk.array(), transform);
+                self.visit_mut_chunks(lod, &copy_extent, |dst_chunk| {
+                    dst_chunk
+                        .array_mut()
+                        .write_extent(&copy_extent, ArrayCopySrc(src_array))
+                });
+            }
+        }
+    }
+}
+
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    Store: TranslateChunkKeys<N>,
+{
+    /// Moves every chunk by `lod0_delta`, which is in LOD0 coordinates, so a chunk at level of detail `lod` moves by
+    /// `lod0_delta >> lod`. The storage moves its chunks in place, without copying any voxels. This is useful for stitching
+    /// worlds together or for a floating origin (see `FloatingOrigin3`).
+    ///
+    /// `lod0_delta` must be a multiple of the chunk shape at every level of detail in the map.
+    pub fn translate_keys(&mut self, lod0_delta: PointN<N>) {
+        self.storage.translate_chunk_keys(lod0_delta)
+    }
+}
+
 impl<'a, N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
 where
     PointN<N>: IntegerPoint<N>,
@@ -573,7 +1040,7 @@ where
 mod tests {
     use super::*;
 
-    use crate::{access_traits::*, Array3x1};
+    use crate::{access_traits::*, Array3x1, OctreeChunkIndex};
 
     use building_blocks_core::prelude::*;
 
@@ -603,6 +1070,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn write_sparse_points() {
+        let mut map = BUILDER.build_with_hash_map_storage();
+
+        let points = [[0, 0, 0], [1, 2, 3], [16, 0, 0], [-15, 0, 0], [17, 1, 1]];
+        map.write_points(
+            0,
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (PointN(*p), i as i32 + 1)),
+        );
+
+        // Only the chunks that contain a point are inserted.
+        assert_eq!(map.storage().len(), 3);
+
+        let lod0 = map.lod_view(0);
+        for (i, p) in points.iter().enumerate() {
+            assert_eq!(lod0.get(PointN(*p)), i as i32 + 1);
+        }
+        assert_eq!(lod0.get(PointN([2, 2, 3])), 0);
+    }
+
     #[test]
     fn write_extent_with_for_each_then_read() {
         let mut map = BUILDER.build_with_hash_map_storage();
@@ -669,6 +1159,119 @@ mod tests {
         map.fill_extent(0, &extent, (1, 'b'));
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_visit_mut_chunks_then_par_for_each() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut map = BUILDER.build_with_hash_map_storage();
+
+        let write_extent = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        map.par_visit_mut_chunks(0, &write_extent, |chunk| {
+            chunk.for_each_mut(&write_extent, |_: (), value| *value = 1)
+        });
+
+        let num_occupied_chunks = AtomicUsize::new(0);
+        map.par_visit_occupied_chunks(0, &write_extent, |_chunk| {
+            num_occupied_chunks.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(num_occupied_chunks.into_inner(), 8);
+
+        let read_extent = write_extent.padded(1);
+        let num_written = AtomicUsize::new(0);
+        map.par_for_each(0, &read_extent, |p, value| {
+            if write_extent.contains(p) {
+                assert_eq!(value, 1);
+                num_written.fetch_add(1, Ordering::Relaxed);
+            } else {
+                assert_eq!(value, 0);
+            }
+        });
+        assert_eq!(num_written.into_inner(), write_extent.num_points());
+    }
+
+    struct HashMapReader(std::collections::HashMap<ChunkKey3, Array3x1<i32>>);
+
+    impl AsyncChunkReader<[i32; 3], Array3x1<i32>> for HashMapReader {
+        type Error = ();
+
+        fn read_chunk(
+            &self,
+            key: ChunkKey3,
+        ) -> futures::future::BoxFuture<'_, Result<Option<Array3x1<i32>>, Self::Error>> {
+            let chunk = self.0.get(&key).cloned();
+
+            Box::pin(async move { Ok(chunk) })
+        }
+    }
+
+    #[test]
+    fn load_missing_chunks_from_async_reader() {
+        let loaded_key = ChunkKey::new(0, Point3i::ZERO);
+        let missing_key = ChunkKey::new(0, CHUNK_SHAPE);
+        let mut source_chunks = std::collections::HashMap::new();
+        source_chunks.insert(
+            loaded_key,
+            Array3x1::fill(Extent3i::from_min_and_shape(Point3i::ZERO, CHUNK_SHAPE), 1),
+        );
+        let reader = HashMapReader(source_chunks);
+
+        let mut map = BUILDER.build_with_hash_map_storage();
+
+        futures::executor::block_on(async {
+            let chunk = map
+                .get_or_load_chunk_async(loaded_key, &reader)
+                .await
+                .unwrap()
+                .unwrap();
+            *chunk.get_mut(Point3i::ZERO) = 2;
+
+            // Chunks that are already in storage are not reloaded.
+            map.load_missing_chunks_async(vec![loaded_key, missing_key], &reader)
+                .await
+                .unwrap();
+
+            assert!(map
+                .get_or_load_chunk_async(missing_key, &reader)
+                .await
+                .unwrap()
+                .is_none());
+        });
+
+        assert_eq!(map.lod_view(0).get(Point3i::ZERO), 2);
+        assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 1);
+        assert!(map.get_chunk(missing_key).is_none());
+    }
+
+    #[test]
+    fn copy_occupied_chunks_to_different_chunk_shape_and_channels() {
+        let src_builder = ChunkMapBuilder3x2::new(CHUNK_SHAPE, (0u8, 0.0f32));
+        let mut src = src_builder.build_with_hash_map_storage();
+        // Spans two source chunks.
+        let filled = Extent3i::from_min_and_shape(PointN([-8, 0, 0]), PointN([16, 4, 4]));
+        src.fill_extent(0, &filled, (3, 1.0));
+        let far_point = Point3i::fill(100);
+        *src.lod_view_mut(0).get_mut(far_point).0 = 5;
+
+        let dst_builder = ChunkMapBuilder3x1::new(Point3i::fill(32), 0);
+        let mut dst = dst_builder.build_with_hash_map_storage();
+        let transform = |(n, _): (u8, f32)| n as i32;
+        dst.copy_occupied_chunks_from(0, &src.bounding_extent(0), &src, &transform);
+
+        // Only the destination chunks that overlap occupied source chunks were inserted.
+        assert_eq!(dst.storage().len(), 3);
+
+        let dst_lod0 = dst.lod_view(0);
+        dst_lod0.for_each(&filled.padded(1), |p: Point3i, value| {
+            if filled.contains(p) {
+                assert_eq!(value, 3);
+            } else {
+                assert_eq!(value, 0);
+            }
+        });
+        assert_eq!(dst_lod0.get(far_point), 5);
+    }
+
     #[cfg(feature = "lz4")]
     #[test]
     fn multichannel_compressed_accessors() {
@@ -701,4 +1304,62 @@ mod tests {
             assert_eq!(letter, b'b');
         });
     }
+
+    #[test]
+    fn generator_synthesizes_missing_chunks() {
+        let mut map = BUILDER
+            .with_generator(|_key, extent: Extent3i| {
+                if extent.minimum.y() < 0 {
+                    Some(Array3x1::fill(extent, 1))
+                } else {
+                    None
+                }
+            })
+            .build_with_hash_map_storage();
+
+        // Reads through a shared reference don't store the generated chunks.
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        map.lod_view(0).for_each(&extent, |p: Point3i, value| {
+            assert_eq!(value, if p.y() < 0 { 1 } else { 0 });
+        });
+        assert_eq!(map.clone_point(0, PointN([0, -1, 0])), 1);
+        assert!(map.storage().is_empty());
+
+        // Mutable access stores the generated chunk.
+        *map.lod_view_mut(0).get_mut(PointN([0, -1, 0])) = 5;
+        assert_eq!(map.storage().len(), 1);
+        assert_eq!(map.clone_point(0, PointN([0, -1, 0])), 5);
+        assert_eq!(map.clone_point(0, PointN([1, -1, 0])), 1);
+
+        let generated_keys = map.generate_missing_chunks(0, &extent);
+        assert_eq!(generated_keys.len(), 3);
+        assert_eq!(map.storage().len(), 4);
+        assert_eq!(map.clone_point(0, PointN([0, -1, 0])), 5);
+
+        assert!(map
+            .get_mut_chunk_or_generate(ChunkKey::new(0, Point3i::ZERO))
+            .is_none());
+    }
+
+    #[test]
+    fn translate_keys_moves_chunks_and_index() {
+        let mut map = BUILDER.build_with_hash_map_storage();
+        *map.lod_view_mut(0).get_mut(PointN([1, 2, 3])) = 1;
+        *map.lod_view_mut(1).get_mut(PointN([-20, 0, 5])) = 2;
+        let mut index = OctreeChunkIndex::index_chunk_map(6, 2, &map);
+
+        let delta = PointN([64, -128, 0]);
+        map.translate_keys(delta);
+        index.translate(delta);
+
+        assert_eq!(map.clone_point(0, PointN([65, -126, 3])), 1);
+        assert_eq!(map.clone_point(1, PointN([12, -64, 5])), 2);
+        assert_eq!(map.clone_point(0, PointN([1, 2, 3])), 0);
+        for key in map.storage().keys() {
+            assert_eq!(map.get_chunk(*key).unwrap().extent().minimum, key.minimum);
+        }
+
+        assert!(index.contains_chunk(ChunkKey::new(0, PointN([64, -128, 0]))));
+        assert!(!index.contains_chunk(ChunkKey::new(0, Point3i::ZERO)));
+    }
 }
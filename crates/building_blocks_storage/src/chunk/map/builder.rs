@@ -9,7 +9,7 @@ use core::hash::Hash;
 
 /// An object that knows how to construct chunks for a `ChunkMap`.
 pub trait ChunkMapBuilder<N, T>: Sized {
-    type Chunk: Chunk;
+    type Chunk: Chunk<Data = T>;
 
     fn chunk_shape(&self) -> PointN<N>;
 
@@ -18,6 +18,33 @@ pub trait ChunkMapBuilder<N, T>: Sized {
     /// Construct a new chunk with entirely ambient values.
     fn new_ambient(&self, extent: ExtentN<N>) -> Self::Chunk;
 
+    /// Synthesize the chunk at `key`, which is missing from storage. Returns `None` if the chunk should take the ambient value
+    /// everywhere, which is always the case for builders without a chunk generator.
+    fn generate_chunk(&self, _key: ChunkKey<N>, _extent: ExtentN<N>) -> Option<Self::Chunk> {
+        None
+    }
+
+    /// Construct the chunk at `key`, which is missing from storage, with `generate_chunk`, falling back to `new_ambient`.
+    fn new_vacant(&self, key: ChunkKey<N>, extent: ExtentN<N>) -> Self::Chunk
+    where
+        PointN<N>: IntegerPoint<N>,
+    {
+        self.generate_chunk(key, extent)
+            .unwrap_or_else(|| self.new_ambient(extent))
+    }
+
+    /// Use `generator` to synthesize chunks that are missing from storage, instead of treating them as ambient. See
+    /// `ChunkMapBuilderWithGenerator`.
+    fn with_generator<G>(self, generator: G) -> ChunkMapBuilderWithGenerator<Self, G>
+    where
+        G: Fn(ChunkKey<N>, ExtentN<N>) -> Option<Self::Chunk>,
+    {
+        ChunkMapBuilderWithGenerator {
+            builder: self,
+            generator,
+        }
+    }
+
     /// Create a new `ChunkMap` with the given `storage` which must implement both `ChunkReadStorage` and `ChunkWriteStorage`.
     fn build_with_rw_storage<Store>(self, storage: Store) -> ChunkMap<N, T, Self, Store>
     where
@@ -92,6 +119,8 @@ pub mod multichannel_aliases {
     builder_type_alias!(ChunkMapBuilder2x4, [i32; 2], A, B, C, D);
     builder_type_alias!(ChunkMapBuilder2x5, [i32; 2], A, B, C, D, E);
     builder_type_alias!(ChunkMapBuilder2x6, [i32; 2], A, B, C, D, E, F);
+    builder_type_alias!(ChunkMapBuilder2x7, [i32; 2], A, B, C, D, E, F, G);
+    builder_type_alias!(ChunkMapBuilder2x8, [i32; 2], A, B, C, D, E, F, G, H);
 
     /// A `ChunkMapBuilder` for `Array3x1` chunks.
     pub type ChunkMapBuilder3x1<A> = ChunkMapBuilderNxM<[i32; 3], A, Channel<A>>;
@@ -100,6 +129,8 @@ pub mod multichannel_aliases {
     builder_type_alias!(ChunkMapBuilder3x4, [i32; 3], A, B, C, D);
     builder_type_alias!(ChunkMapBuilder3x5, [i32; 3], A, B, C, D, E);
     builder_type_alias!(ChunkMapBuilder3x6, [i32; 3], A, B, C, D, E, F);
+    builder_type_alias!(ChunkMapBuilder3x7, [i32; 3], A, B, C, D, E, F, G);
+    builder_type_alias!(ChunkMapBuilder3x8, [i32; 3], A, B, C, D, E, F, G, H);
 }
 
 pub use multichannel_aliases::*;
@@ -124,3 +155,44 @@ where
         Array::fill(extent, self.ambient_value())
     }
 }
+
+/// A `ChunkMapBuilder` that lazily synthesizes chunks with a `generator` function, e.g. for an infinite procedural world. The
+/// generator is given the key and extent of a chunk that is missing from storage, and it returns `None` for chunks that
+/// should stay ambient.
+///
+/// Reads through a shared `ChunkMap` reference, like `clone_point` and `visit_chunks`, generate missing chunks on every
+/// access without storing them. Mutable accesses, like `get_mut_chunk_or_insert_ambient`, write the generated chunk into
+/// storage, so it only needs to be generated once. To cache chunks before reading them, use
+/// `ChunkMap::generate_missing_chunks` or `ChunkMap::get_mut_chunk_or_generate`.
+///
+/// `GetRef` and `ReadExtent` (and thus `copy_extent`) on a `ChunkMapLodView` can't borrow a temporary chunk, so they still
+/// see missing chunks as ambient.
+#[derive(Clone, Copy)]
+pub struct ChunkMapBuilderWithGenerator<Bldr, G> {
+    pub builder: Bldr,
+    pub generator: G,
+}
+
+impl<N, T, Bldr, G> ChunkMapBuilder<N, T> for ChunkMapBuilderWithGenerator<Bldr, G>
+where
+    Bldr: ChunkMapBuilder<N, T>,
+    G: Fn(ChunkKey<N>, ExtentN<N>) -> Option<Bldr::Chunk>,
+{
+    type Chunk = Bldr::Chunk;
+
+    fn chunk_shape(&self) -> PointN<N> {
+        self.builder.chunk_shape()
+    }
+
+    fn ambient_value(&self) -> T {
+        self.builder.ambient_value()
+    }
+
+    fn new_ambient(&self, extent: ExtentN<N>) -> Self::Chunk {
+        self.builder.new_ambient(extent)
+    }
+
+    fn generate_chunk(&self, key: ChunkKey<N>, extent: ExtentN<N>) -> Option<Self::Chunk> {
+        (self.generator)(key, extent)
+    }
+}
@@ -0,0 +1,278 @@
+//! Views of a single channel of a multichannel `ChunkMap`.
+//!
+//! A `ChunkMapChannelView` projects every point of one level of detail onto the channel chosen by a `SelectChannel` selector,
+//! like `Ch0` or `Ch1`. Nothing is copied; reads and writes go straight to the chunks of the delegate map, so any algorithm
+//! written against the access traits of a single-channel map can run on one channel of a multichannel map.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Ch0, Ch1};
+//!
+//! let builder = ChunkMapBuilder3x2::new(Point3i::fill(16), (Sd8::ONE, 0u8));
+//! let mut map = builder.build_with_hash_map_storage();
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+//!
+//! // Write only the second channel.
+//! let mut light = map.lod_channel_view_mut::<Ch1>(0);
+//! light.fill_extent(&extent, 15);
+//! *light.get_mut(Point3i::fill(1)) = 0;
+//!
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), (Sd8::ONE, 0));
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(2)), (Sd8::ONE, 15));
+//!
+//! // Read only the first channel.
+//! let sdf = map.lod_channel_view::<Ch0>(0);
+//! let mut num_points = 0;
+//! sdf.for_each(&extent, |_p: Point3i, d: Sd8| {
+//!     assert_eq!(d, Sd8::ONE);
+//!     num_points += 1;
+//! });
+//! assert_eq!(num_points, extent.num_points());
+//! ```
+//!
+//! For a single `Array`, the same selectors work with `Array::borrow_channels`, e.g. `array.borrow_channels(Ch1::select)`
+//! borrows the second channel as an array with one channel.
+
+use crate::{
+    Chunk, ChunkMap, ChunkMapBuilder, ChunkReadStorage, ChunkWriteStorage, FillExtent, ForEach,
+    ForEachMut, ForEachMutPtr, Get, GetMut, GetRef, IntoMultiMut, MultiMutPtr, MultiRef,
+    SelectChannel,
+};
+
+use building_blocks_core::{ExtentN, IntegerPoint, PointN};
+
+use core::marker::PhantomData;
+use either::Either;
+use std::ops::{Deref, DerefMut};
+
+/// A view of a single channel, chosen by `Sel`, at a single level of detail of a multichannel `ChunkMap`. See the
+/// [module docs](self) for an example.
+pub struct ChunkMapChannelView<Delegate, Sel> {
+    pub delegate: Delegate,
+    pub lod: u8,
+    marker: PhantomData<Sel>,
+}
+
+impl<Delegate, Sel> ChunkMapChannelView<Delegate, Sel> {
+    #[inline]
+    pub fn new(delegate: Delegate, lod: u8) -> Self {
+        Self {
+            delegate,
+            lod,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn lod(&self) -> u8 {
+        self.lod
+    }
+}
+
+//  ██████╗ ███████╗████████╗████████╗███████╗██████╗ ███████╗
+// ██╔════╝ ██╔════╝╚══██╔══╝╚══██╔══╝██╔════╝██╔══██╗██╔════╝
+// ██║  ███╗█████╗     ██║      ██║   █████╗  ██████╔╝███████╗
+// ██║   ██║██╔══╝     ██║      ██║   ██╔══╝  ██╔══██╗╚════██║
+// ╚██████╔╝███████╗   ██║      ██║   ███████╗██║  ██║███████║
+//  ╚═════╝ ╚══════╝   ╚═╝      ╚═╝   ╚══════╝╚═╝  ╚═╝╚══════╝
+
+impl<Delegate, N, T, Bldr, Store, Sel> Get<PointN<N>> for ChunkMapChannelView<Delegate, Sel>
+where
+    Delegate: Deref<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    T: Clone,
+    Bldr: ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: Get<PointN<N>, Item = T>,
+    Store: ChunkReadStorage<N, Bldr::Chunk>,
+    Sel: SelectChannel<T>,
+{
+    type Item = Sel::Selected;
+
+    #[inline]
+    fn get(&self, p: PointN<N>) -> Self::Item {
+        Sel::select(self.delegate.clone_point(self.lod, p))
+    }
+}
+
+impl<'a, Delegate, N, T: 'a, Bldr, Store, Sel, Ref> GetRef<'a, PointN<N>>
+    for ChunkMapChannelView<Delegate, Sel>
+where
+    Delegate: Deref<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    Bldr: 'a + ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: GetRef<'a, PointN<N>, Item = Ref>,
+    Store: 'a + ChunkReadStorage<N, Bldr::Chunk>,
+    Ref: MultiRef<'a, Data = T>,
+    Sel: SelectChannel<Ref>,
+{
+    type Item = Sel::Selected;
+
+    #[inline]
+    fn get_ref(&'a self, p: PointN<N>) -> Self::Item {
+        Sel::select(self.delegate.get_point(self.lod, p))
+    }
+}
+
+impl<'a, Delegate, N, T: 'a, Bldr, Store, Sel, Mut> GetMut<'a, PointN<N>>
+    for ChunkMapChannelView<Delegate, Sel>
+where
+    Delegate: DerefMut<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    Bldr: 'a + ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: GetMut<'a, PointN<N>, Item = Mut>,
+    Store: 'a + ChunkWriteStorage<N, Bldr::Chunk>,
+    Sel: SelectChannel<Mut>,
+{
+    type Item = Sel::Selected;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: PointN<N>) -> Self::Item {
+        Sel::select(self.delegate.get_mut_point(self.lod, p))
+    }
+}
+
+// ███████╗ ██████╗ ██████╗     ███████╗ █████╗  ██████╗██╗  ██╗
+// ██╔════╝██╔═══██╗██╔══██╗    ██╔════╝██╔══██╗██╔════╝██║  ██║
+// █████╗  ██║   ██║██████╔╝    █████╗  ███████║██║     ███████║
+// ██╔══╝  ██║   ██║██╔══██╗    ██╔══╝  ██╔══██║██║     ██╔══██║
+// ██║     ╚██████╔╝██║  ██║    ███████╗██║  ██║╚██████╗██║  ██║
+// ╚═╝      ╚═════╝ ╚═╝  ╚═╝    ╚══════╝╚═╝  ╚═╝ ╚═════╝╚═╝  ╚═╝
+
+impl<Delegate, N, T, Bldr, Store, Sel> ForEach<N, PointN<N>> for ChunkMapChannelView<Delegate, Sel>
+where
+    Delegate: Deref<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: ForEach<N, PointN<N>, Item = T>,
+    T: Clone,
+    Store: ChunkReadStorage<N, Bldr::Chunk>,
+    Sel: SelectChannel<T>,
+{
+    type Item = Sel::Selected;
+
+    #[inline]
+    fn for_each(&self, extent: &ExtentN<N>, mut f: impl FnMut(PointN<N>, Self::Item)) {
+        self.delegate
+            .visit_chunks(self.lod, extent, |chunk| match chunk {
+                Either::Left(chunk) => {
+                    chunk
+                        .array()
+                        .for_each(extent, |p, value| f(p, Sel::select(value)));
+                }
+                Either::Right((chunk_extent, ambient)) => ambient
+                    .for_each(&extent.intersection(&chunk_extent), |p, value| {
+                        f(p, Sel::select(value))
+                    }),
+            });
+    }
+}
+
+impl<Delegate, N, T, Bldr, Store, Sel, MutPtr> ForEachMutPtr<N, PointN<N>>
+    for ChunkMapChannelView<Delegate, Sel>
+where
+    Delegate: DerefMut<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: ForEachMutPtr<N, PointN<N>, Item = MutPtr>,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+    Sel: SelectChannel<MutPtr>,
+{
+    type Item = Sel::Selected;
+
+    #[inline]
+    unsafe fn for_each_mut_ptr(
+        &mut self,
+        extent: &ExtentN<N>,
+        mut f: impl FnMut(PointN<N>, Self::Item),
+    ) {
+        self.delegate.visit_mut_chunks(self.lod, extent, |chunk| {
+            chunk
+                .array_mut()
+                .for_each_mut_ptr(extent, |p, ptr| f(p, Sel::select(ptr)))
+        });
+    }
+}
+
+impl<'a, Delegate, N, Sel, Mut, MutPtr> ForEachMut<'a, N, PointN<N>>
+    for ChunkMapChannelView<Delegate, Sel>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = MutPtr>,
+    MutPtr: IntoMultiMut<'a, MultiMut = Mut>,
+{
+    type Item = Mut;
+
+    #[inline]
+    fn for_each_mut(&'a mut self, extent: &ExtentN<N>, mut f: impl FnMut(PointN<N>, Self::Item)) {
+        unsafe { self.for_each_mut_ptr(extent, |p, ptr| f(p, ptr.into_multi_mut())) }
+    }
+}
+
+impl<Delegate, N, Sel, T, MutPtr> FillExtent<N> for ChunkMapChannelView<Delegate, Sel>
+where
+    Self: ForEachMutPtr<N, PointN<N>, Item = MutPtr>,
+    MutPtr: MultiMutPtr<Data = T>,
+    T: Clone,
+{
+    type Item = T;
+
+    /// Fill all of `extent` with the same `value`, leaving the other channels unchanged.
+    #[inline]
+    fn fill_extent(&mut self, extent: &ExtentN<N>, value: T) {
+        unsafe {
+            self.for_each_mut_ptr(extent, |_p, ptr| ptr.write(value.clone()));
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{Ch0, Ch2};
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn write_one_channel_and_read_it_back() {
+        let ambient = (0u8, 1.0f32, 'a');
+        let builder = ChunkMapBuilder3x3::new(Point3i::fill(4), ambient);
+        let mut map = builder.build_with_hash_map_storage();
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(2), Point3i::fill(4));
+
+        let mut letters = map.lod_channel_view_mut::<Ch2>(0);
+        letters.fill_extent(&extent, 'b');
+        letters.for_each_mut(&extent, |p: Point3i, c: &mut char| {
+            if p == Point3i::fill(3) {
+                *c = 'c';
+            }
+        });
+        *letters.get_mut(Point3i::fill(4)) = 'd';
+
+        assert_eq!(map.storage().len(), 8);
+        assert_eq!(map.lod_view(0).get(Point3i::fill(2)), (0, 1.0, 'b'));
+        assert_eq!(map.lod_view(0).get(Point3i::fill(3)), (0, 1.0, 'c'));
+        assert_eq!(map.lod_view(0).get(Point3i::fill(4)), (0, 1.0, 'd'));
+        assert_eq!(map.lod_view(0).get(Point3i::ZERO), ambient);
+
+        let letters = map.lod_channel_view::<Ch2>(0);
+        assert_eq!(letters.get(Point3i::fill(3)), 'c');
+        assert_eq!(letters.get_ref(Point3i::fill(4)), &'d');
+        assert_eq!(letters.get(Point3i::fill(100)), 'a');
+
+        // Only the selected channel is visited, including in vacant chunks.
+        let ids = map.lod_channel_view::<Ch0>(0);
+        let visit_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(12));
+        let mut num_visited = 0;
+        ids.for_each(&visit_extent, |_p: Point3i, id: u8| {
+            assert_eq!(id, 0);
+            num_visited += 1;
+        });
+        assert_eq!(num_visited, visit_extent.num_points());
+    }
+}
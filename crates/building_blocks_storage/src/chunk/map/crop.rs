@@ -0,0 +1,247 @@
+//! Cropping a `ChunkMap` to an extent, at every level of detail.
+//!
+//! `ChunkMap::extract_extent` copies the data in an extent into a new `ChunkHashMap`, e.g. for exporting a build, and
+//! `ChunkMap::discard_outside` trims everything outside of an extent, e.g. for pruning far-away data. Both take the extent in
+//! LOD0 coordinates and keep the points of each coarser level of detail that overlap it. Chunks on the boundary of the extent
+//! are kept, but their points outside of the extent are set to the ambient value.
+//!
+//! To extract one level of detail into a single `Array`, use `copy_extent` from a `ChunkMapLodView` instead.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let mut map = builder.build_with_hash_map_storage();
+//! map.fill_extent(0, &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(64)), 1);
+//!
+//! let build_extent = Extent3i::from_min_and_shape(Point3i::fill(10), Point3i::fill(10));
+//! let build = map.extract_extent(&build_extent);
+//! assert_eq!(build.storage().len(), 8);
+//! assert_eq!(build.clone_point(0, PointN([10, 10, 10])), 1);
+//! assert_eq!(build.clone_point(0, PointN([9, 10, 10])), 0);
+//!
+//! let removed = map.discard_outside(&build_extent);
+//! assert_eq!(removed.len(), 64 - 8);
+//! ```
+
+use crate::{
+    Array, ChunkHashMap, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkReadStorage, ChunkWriteStorage,
+    FillExtent, IterChunkKeys,
+};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+
+impl<N, T, Chan, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Array<N, Chan>: FillExtent<N, Item = T>,
+    T: Clone,
+{
+    /// Copies all chunks that overlap `lod0_extent`, at every level of detail, into a new map with the same builder. Chunks that
+    /// the storage folded into a single value, like `UniformChunkFolding` does, are written out as filled chunks.
+    pub fn extract_extent(&self, lod0_extent: &ExtentN<N>) -> ChunkHashMap<N, T, Bldr>
+    where
+        ChunkKey<N>: Eq + Hash,
+        Bldr: Clone,
+        Chan: Clone,
+        Store: ChunkReadStorage<N, Array<N, Chan>> + for<'r> IterChunkKeys<'r, N>,
+    {
+        let mut extracted = self.builder().clone().build_with_hash_map_storage();
+        for &key in self.storage().chunk_keys() {
+            let keep_extent = lod_extent(lod0_extent, key.lod);
+            let chunk_extent = self.indexer.extent_for_chunk_with_min(key.minimum);
+            if chunk_extent.intersection(&keep_extent).is_empty() {
+                continue;
+            }
+            let mut chunk = if let Some(chunk) = self.get_chunk(key) {
+                chunk.clone()
+            } else if let Some(value) = self.get_uniform_chunk(key) {
+                let mut chunk = self.builder().new_ambient(chunk_extent);
+                chunk.fill_extent(&chunk_extent, value.clone());
+                chunk
+            } else {
+                continue;
+            };
+            fill_outside(&mut chunk, &keep_extent, self.ambient_value());
+            extracted.write_chunk(key, chunk);
+        }
+
+        extracted
+    }
+
+    /// Removes all data outside of `lod0_extent`, at every level of detail. Returns the keys of the chunks that were removed, so
+    /// that an `OctreeChunkIndex` of the map can be updated.
+    pub fn discard_outside(&mut self, lod0_extent: &ExtentN<N>) -> Vec<ChunkKey<N>>
+    where
+        Store: ChunkWriteStorage<N, Array<N, Chan>> + for<'r> IterChunkKeys<'r, N>,
+    {
+        let keys: Vec<ChunkKey<N>> = self.storage().chunk_keys().cloned().collect();
+
+        let ambient_value = self.ambient_value();
+        let mut removed = Vec::new();
+        for key in keys.into_iter() {
+            let keep_extent = lod_extent(lod0_extent, key.lod);
+            let chunk_extent = self.indexer.extent_for_chunk_with_min(key.minimum);
+            let overlap = chunk_extent.intersection(&keep_extent);
+            if overlap.is_empty() {
+                self.delete_chunk(key);
+                removed.push(key);
+            } else if overlap != chunk_extent {
+                if let Some(chunk) = self.get_mut_chunk(key) {
+                    fill_outside(chunk, &keep_extent, ambient_value.clone());
+                }
+            }
+        }
+
+        removed
+    }
+}
+
+/// The points at level of detail `lod` that overlap `lod0_extent`.
+fn lod_extent<N>(lod0_extent: &ExtentN<N>, lod: u8) -> ExtentN<N>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    ExtentN::from_min_and_max(
+        lod0_extent.minimum >> lod as i32,
+        lod0_extent.max() >> lod as i32,
+    )
+}
+
+/// Fills the points of `chunk` outside of `keep_extent` with `value`, one slab at a time.
+fn fill_outside<N, Chan, T>(chunk: &mut Array<N, Chan>, keep_extent: &ExtentN<N>, value: T)
+where
+    PointN<N>: IntegerPoint<N>,
+    Array<N, Chan>: FillExtent<N, Item = T>,
+    T: Clone,
+{
+    let keep_extent = keep_extent.intersection(chunk.extent());
+    if keep_extent.is_empty() {
+        let chunk_extent = *chunk.extent();
+        chunk.fill_extent(&chunk_extent, value);
+        return;
+    }
+
+    // Peel off the slabs below and above `keep_extent` along each axis, until only `keep_extent` remains.
+    let mut remaining = *chunk.extent();
+    for (axis, unit) in PointN::<N>::basis().into_iter().enumerate() {
+        let with_component = |p: PointN<N>, c: i32| p + unit * (c - p.at(axis));
+        let keep_min = keep_extent.minimum.at(axis);
+        let keep_lub = keep_extent.least_upper_bound().at(axis);
+
+        let below = ExtentN::from_min_and_lub(
+            remaining.minimum,
+            with_component(remaining.least_upper_bound(), keep_min),
+        );
+        let above = ExtentN::from_min_and_lub(
+            with_component(remaining.minimum, keep_lub),
+            remaining.least_upper_bound(),
+        );
+        for slab in [below, above].iter() {
+            if !slab.is_empty() {
+                chunk.fill_extent(slab, value.clone());
+            }
+        }
+
+        remaining = ExtentN::from_min_and_lub(
+            with_component(remaining.minimum, keep_min),
+            with_component(remaining.least_upper_bound(), keep_lub),
+        );
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, UniformChunkFolding};
+
+    #[test]
+    fn extract_extent_crops_every_lod() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        map.fill_extent(
+            0,
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16)),
+            1,
+        );
+        map.fill_extent(
+            1,
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8)),
+            2,
+        );
+
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(3), Point3i::fill(6));
+        let extracted = map.extract_extent(&extent);
+
+        for p in Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16)).iter_points() {
+            let expected = if extent.contains(p) { 1 } else { 0 };
+            assert_eq!(extracted.clone_point(0, p), expected);
+        }
+        // [3, 8] in LOD0 overlaps [1, 4] in LOD1.
+        let lod1_extent = Extent3i::from_min_and_max(Point3i::fill(1), Point3i::fill(4));
+        for p in Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8)).iter_points() {
+            let expected = if lod1_extent.contains(p) { 2 } else { 0 };
+            assert_eq!(extracted.clone_point(1, p), expected);
+        }
+    }
+
+    #[test]
+    fn extract_extent_includes_folded_chunks() {
+        let chunk_shape = Point3i::fill(4);
+        let builder = ChunkMapBuilder3x1::new(chunk_shape, 0);
+        let mut map = builder.build_with_rw_storage(UniformChunkFolding::new(
+            SmallKeyHashMap::default(),
+            chunk_shape,
+        ));
+        let full_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        map.fill_extent(0, &full_extent, 1);
+        *map.get_mut_point(0, Point3i::ZERO) = 2;
+        map.storage_mut().fold_all_chunks();
+        assert!(map.get_chunk(ChunkKey::new(0, Point3i::fill(4))).is_none());
+
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(6));
+        let extracted = map.extract_extent(&extent);
+
+        assert_eq!(extracted.storage().len(), 8);
+        for p in full_extent.iter_points() {
+            let expected = if p == Point3i::ZERO {
+                2
+            } else if extent.contains(p) {
+                1
+            } else {
+                0
+            };
+            assert_eq!(extracted.clone_point(0, p), expected);
+        }
+    }
+
+    #[test]
+    fn discard_outside_removes_and_trims_chunks() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        let full_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        map.fill_extent(0, &full_extent, 1);
+
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(2), Point3i::fill(4));
+        let removed = map.discard_outside(&extent);
+
+        assert_eq!(removed.len(), 64 - 8);
+        assert_eq!(map.storage().len(), 8);
+        for p in full_extent.iter_points() {
+            let expected = if extent.contains(p) { 1 } else { 0 };
+            assert_eq!(map.clone_point(0, p), expected);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{vox_model_voxels, ArrayNx1, ChunkMap3, ChunkMapBuilder, ChunkWriteStorage, VoxColor};
+
+use building_blocks_core::prelude::*;
+
+use dot_vox::DotVoxData;
+use std::convert::TryFrom;
+
+impl<T, Bldr, Store> ChunkMap3<T, Bldr, Store>
+where
+    T: TryFrom<VoxColor>,
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = ArrayNx1<[i32; 3], T>>,
+    Store: ChunkWriteStorage<[i32; 3], ArrayNx1<[i32; 3], T>>,
+{
+    /// Writes the non-empty voxels of the VOX model at `model_index` into level of detail `lod`, with the model's origin
+    /// translated to `minimum`. Only chunks that contain a voxel of the model will be inserted.
+    ///
+    /// Stops at the first voxel whose color can't be converted into `T`, like `VoxColor::Color(255)` for raw `u8` indices.
+    pub fn write_vox_model(
+        &mut self,
+        lod: u8,
+        vox_data: &DotVoxData,
+        model_index: usize,
+        minimum: Point3i,
+    ) -> Result<(), T::Error> {
+        for (p, color) in vox_model_voxels(vox_data, model_index) {
+            *self.get_mut_point(lod, minimum + p) = T::try_from(color)?;
+        }
+
+        Ok(())
+    }
+}
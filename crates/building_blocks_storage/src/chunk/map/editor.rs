@@ -0,0 +1,411 @@
+//! Brush-based editing of a `ChunkMap3`.
+//!
+//! A `ChunkMapEditor` applies a `Brush` to one level of detail of a map, visiting only the chunks that intersect the brush.
+//! Every edit returns the keys of the chunks whose values actually changed, which is usually the set of chunks that need to be
+//! remeshed. Chunks are only inserted if the edit changes them from their ambient (or generated) values.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Brush, ChunkMapEditor};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), Sd8::ONE);
+//! let mut map = builder.build_with_hash_map_storage();
+//!
+//! let mut editor = ChunkMapEditor::new(&mut map, 0);
+//! let modified = editor.add(&Brush::Sphere {
+//!     center: Point3f::fill(8.0),
+//!     radius: 4.0,
+//! });
+//! assert_eq!(modified.len(), 1);
+//!
+//! let modified = editor.subtract(&Brush::Capsule {
+//!     a: PointN([0.0, 8.0, 8.0]),
+//!     b: PointN([16.0, 8.0, 8.0]),
+//!     radius: 2.0,
+//! });
+//! assert!(modified.contains(&ChunkKey::new(0, Point3i::ZERO)));
+//!
+//! assert!(!map.clone_point(0, Point3i::fill(8)).is_negative());
+//! assert!(map.clone_point(0, PointN([8, 11, 8])).is_negative());
+//! ```
+//!
+//! A `Brush` can also be used on its own to visit the lattice points inside of it, e.g. to write them sparsely into any map:
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Brush};
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut array = Array3x1::fill(extent, 0u8);
+//!
+//! let blast = Brush::Sphere { center: Point3f::fill(8.0), radius: 3.0 };
+//! // Damage falls off toward the edge of the blast.
+//! array.write_points(blast.iter_points_with_distance().map(|(p, d)| (p, (-d * 80.0) as u8)));
+//! assert_eq!(array.get(Point3i::fill(8)), 240);
+//! assert_eq!(array.get(Point3i::fill(12)), 0);
+//!
+//! let pillar = Brush::Cylinder { a: PointN([4.0, 0.0, 4.0]), b: PointN([4.0, 16.0, 4.0]), radius: 1.0 };
+//! assert_eq!(pillar.iter_points().count(), 5 * 17);
+//! ```
+
+use crate::{
+    sdf::{axis_aligned_box, capsule, cone, cylinder, sphere, CsgOperation},
+    Array3x1, ChunkKey, ChunkKey3, ChunkMap3, ChunkMapBuilder, ChunkWriteStorage, ForEachMut,
+    SmallKeyHashSet,
+};
+
+use building_blocks_core::prelude::*;
+
+/// The shape of an edit. All coordinates are in voxel units of the level of detail being edited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Brush {
+    Sphere {
+        center: Point3f,
+        radius: f32,
+    },
+    Box {
+        center: Point3f,
+        half_extents: Point3f,
+    },
+    Capsule {
+        a: Point3f,
+        b: Point3f,
+        radius: f32,
+    },
+    Cylinder {
+        a: Point3f,
+        b: Point3f,
+        radius: f32,
+    },
+    Cone {
+        base: Point3f,
+        apex: Point3f,
+        radius: f32,
+    },
+}
+
+impl Brush {
+    /// The signed distance from `p` to the surface of the brush.
+    #[inline]
+    pub fn dist(&self, p: Point3f) -> f32 {
+        match *self {
+            Brush::Sphere { center, radius } => sphere(center, radius)(p),
+            Brush::Box {
+                center,
+                half_extents,
+            } => axis_aligned_box(center, half_extents)(p),
+            Brush::Capsule { a, b, radius } => capsule(a, b, radius)(p),
+            Brush::Cylinder { a, b, radius } => cylinder(a, b, radius)(p),
+            Brush::Cone { base, apex, radius } => cone(base, apex, radius)(p),
+        }
+    }
+
+    /// The smallest extent that contains every voxel inside of the brush.
+    pub fn bounding_extent(&self) -> Extent3i {
+        let (min, max) = match *self {
+            Brush::Sphere { center, radius } => (
+                center - Point3f::fill(radius),
+                center + Point3f::fill(radius),
+            ),
+            Brush::Box {
+                center,
+                half_extents,
+            } => (center - half_extents, center + half_extents),
+            // The end caps of a cylinder can also stick out past its axis by at most `radius`.
+            Brush::Capsule { a, b, radius } | Brush::Cylinder { a, b, radius } => (
+                a.meet(b) - Point3f::fill(radius),
+                a.join(b) + Point3f::fill(radius),
+            ),
+            // The base disk can stick out past the apex by at most `radius`.
+            Brush::Cone { base, apex, radius } => (
+                base.meet(apex) - Point3f::fill(radius),
+                base.join(apex) + Point3f::fill(radius),
+            ),
+        };
+
+        Extent3i::from_min_and_max(min.floor_int(), max.ceil().into_int())
+    }
+
+    /// All lattice points inside of the brush, i.e. with a signed distance of at most zero.
+    pub fn iter_points(&self) -> impl Iterator<Item = Point3i> {
+        self.iter_points_with_distance().map(|(p, _)| p)
+    }
+
+    /// All lattice points inside of the brush, along with their (non-positive) signed distances from the surface. This is
+    /// useful for effects that fall off with depth, like the damage of an explosion.
+    pub fn iter_points_with_distance(&self) -> impl Iterator<Item = (Point3i, f32)> {
+        let brush = *self;
+
+        self.bounding_extent().iter_points().filter_map(move |p| {
+            let dist = brush.dist(Point3f::from(p));
+
+            if dist <= 0.0 {
+                Some((p, dist))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Applies `Brush` edits to a single level of detail of a `ChunkMap3`.
+pub struct ChunkMapEditor<'a, T, Bldr, Store> {
+    map: &'a mut ChunkMap3<T, Bldr, Store>,
+    lod: u8,
+}
+
+impl<'a, T, Bldr, Store> ChunkMapEditor<'a, T, Bldr, Store>
+where
+    T: Clone + PartialEq,
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = Array3x1<T>>,
+    Store: ChunkWriteStorage<[i32; 3], Array3x1<T>>,
+{
+    pub fn new(map: &'a mut ChunkMap3<T, Bldr, Store>, lod: u8) -> Self {
+        Self { map, lod }
+    }
+
+    /// The level of detail being edited.
+    pub fn lod(&self) -> u8 {
+        self.lod
+    }
+
+    /// Sets every voxel inside of `brush` to `value`.
+    pub fn paint(&mut self, brush: &Brush, value: T) -> SmallKeyHashSet<ChunkKey3> {
+        self.edit_extent(&brush.bounding_extent(), |p, voxel| {
+            if *voxel != value && brush.dist(Point3f::from(p)) <= 0.0 {
+                *voxel = value.clone();
+
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Calls `edit_voxel` on every voxel in `extent`. `edit_voxel` must return `true` iff it changed the voxel.
+    ///
+    /// Returns the keys of all chunks that were changed. Chunks that don't exist yet are only inserted if they were changed.
+    pub fn edit_extent(
+        &mut self,
+        extent: &Extent3i,
+        mut edit_voxel: impl FnMut(Point3i, &mut T) -> bool,
+    ) -> SmallKeyHashSet<ChunkKey3> {
+        let mut modified_keys = SmallKeyHashSet::default();
+
+        let chunk_mins: Vec<_> = self.map.indexer.chunk_mins_for_extent(extent).collect();
+        for chunk_min in chunk_mins.into_iter() {
+            let key = ChunkKey::new(self.lod, chunk_min);
+            let chunk_extent = self.map.indexer.extent_for_chunk_with_min(chunk_min);
+            let edit_extent = extent.intersection(&chunk_extent);
+
+            let mut changed = false;
+            if let Some(chunk) = self.map.get_mut_chunk(key) {
+                chunk.for_each_mut(&edit_extent, |p: Point3i, voxel| {
+                    changed |= edit_voxel(p, voxel)
+                });
+            } else {
+                let mut chunk = self.map.builder().new_vacant(key, chunk_extent);
+                chunk.for_each_mut(&edit_extent, |p: Point3i, voxel| {
+                    changed |= edit_voxel(p, voxel)
+                });
+                if changed {
+                    self.map.write_chunk(key, chunk);
+                }
+            }
+
+            if changed {
+                modified_keys.insert(key);
+            }
+        }
+
+        modified_keys
+    }
+}
+
+impl<'a, T, Bldr, Store> ChunkMapEditor<'a, T, Bldr, Store>
+where
+    T: Copy + PartialEq + From<f32> + Into<f32>,
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = Array3x1<T>>,
+    Store: ChunkWriteStorage<[i32; 3], Array3x1<T>>,
+{
+    /// Takes the union of the signed distance field with `brush`.
+    pub fn add(&mut self, brush: &Brush) -> SmallKeyHashSet<ChunkKey3> {
+        self.apply_csg(brush, CsgOperation::Union, 0.0)
+    }
+
+    /// Subtracts `brush` from the signed distance field.
+    pub fn subtract(&mut self, brush: &Brush) -> SmallKeyHashSet<ChunkKey3> {
+        self.apply_csg(brush, CsgOperation::Subtraction, 0.0)
+    }
+
+    /// Like `add`, but the seam is blended over a distance of `k` voxels.
+    pub fn smooth_add(&mut self, brush: &Brush, k: f32) -> SmallKeyHashSet<ChunkKey3> {
+        self.apply_csg(brush, CsgOperation::SmoothUnion(k), k)
+    }
+
+    /// Like `subtract`, but the seam is blended over a distance of `k` voxels.
+    pub fn smooth_subtract(&mut self, brush: &Brush, k: f32) -> SmallKeyHashSet<ChunkKey3> {
+        self.apply_csg(brush, CsgOperation::SmoothSubtraction(k), k)
+    }
+
+    fn apply_csg(
+        &mut self,
+        brush: &Brush,
+        operation: CsgOperation,
+        blend_distance: f32,
+    ) -> SmallKeyHashSet<ChunkKey3> {
+        // Signed distances saturate at one voxel from the surface, so voxels any farther from the brush (and its blended seam)
+        // can't change.
+        let extent = brush
+            .bounding_extent()
+            .padded(1 + blend_distance.ceil() as i32);
+
+        self.edit_extent(&extent, |p, voxel| {
+            let old_dist = (*voxel).into();
+            let new_dist = operation.apply(old_dist, brush.dist(Point3f::from(p)));
+            if new_dist == old_dist {
+                // Avoid round-tripping through the quantized representation, which isn't always lossless.
+                return false;
+            }
+
+            let new_value = T::from(new_dist);
+            if new_value != *voxel {
+                *voxel = new_value;
+
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn add_then_subtract_sphere() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), Sd8::ONE);
+        let mut map = builder.build_with_hash_map_storage();
+        let mut editor = ChunkMapEditor::new(&mut map, 0);
+
+        // Centered on the corner shared by 8 chunks.
+        let big_sphere = Brush::Sphere {
+            center: Point3f::ZERO,
+            radius: 5.0,
+        };
+        let modified = editor.add(&big_sphere);
+        assert_eq!(modified.len(), 8);
+
+        // Adding the same brush again doesn't change anything.
+        assert!(editor.add(&big_sphere).is_empty());
+
+        // This sphere only touches the positive octant chunk.
+        let small_sphere = Brush::Sphere {
+            center: Point3f::fill(2.0),
+            radius: 1.0,
+        };
+        let modified = editor.subtract(&small_sphere);
+        let expected: SmallKeyHashSet<_> =
+            [ChunkKey::new(0, Point3i::ZERO)].iter().cloned().collect();
+        assert_eq!(modified, expected);
+
+        assert!(map.clone_point(0, PointN([-2, -2, -2])).is_negative());
+        assert!(!map.clone_point(0, Point3i::fill(2)).is_negative());
+        assert!(!map.clone_point(0, Point3i::fill(6)).is_negative());
+    }
+
+    #[test]
+    fn subtract_from_empty_space_inserts_no_chunks() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), Sd8::ONE);
+        let mut map = builder.build_with_hash_map_storage();
+
+        let modified = ChunkMapEditor::new(&mut map, 0).subtract(&Brush::Cone {
+            base: Point3f::ZERO,
+            apex: PointN([0.0, 20.0, 0.0]),
+            radius: 4.0,
+        });
+
+        assert!(modified.is_empty());
+        assert!(map.storage().is_empty());
+    }
+
+    #[test]
+    fn brush_points_match_distance() {
+        let brushes = [
+            Brush::Sphere {
+                center: PointN([0.5, 1.0, -2.0]),
+                radius: 3.5,
+            },
+            Brush::Capsule {
+                a: Point3f::ZERO,
+                b: PointN([4.0, 3.0, -1.0]),
+                radius: 1.5,
+            },
+            Brush::Cylinder {
+                a: Point3f::ZERO,
+                b: PointN([0.0, 5.0, 2.0]),
+                radius: 2.0,
+            },
+            Brush::Cone {
+                base: Point3f::ZERO,
+                apex: PointN([-3.0, 0.0, 4.0]),
+                radius: 2.5,
+            },
+        ];
+
+        for brush in brushes.iter() {
+            let points: SmallKeyHashSet<_> = brush
+                .iter_points_with_distance()
+                .map(|(p, d)| {
+                    assert_eq!(d, brush.dist(Point3f::from(p)));
+                    p
+                })
+                .collect();
+            assert!(!points.is_empty());
+
+            for p in brush.bounding_extent().padded(2).iter_points() {
+                assert_eq!(
+                    points.contains(&p),
+                    brush.dist(Point3f::from(p)) <= 0.0,
+                    "{:?} at {:?}",
+                    brush,
+                    p
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn paint_box() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0u8);
+        let mut map = builder.build_with_hash_map_storage();
+
+        let brush = Brush::Box {
+            center: PointN([16.0, 8.0, 8.0]),
+            half_extents: PointN([2.0, 2.0, 2.0]),
+        };
+        let modified = ChunkMapEditor::new(&mut map, 0).paint(&brush, 7);
+        assert_eq!(modified.len(), 2);
+
+        let lod0 = map.lod_view(0);
+        let painted_extent = Extent3i::from_min_and_max(PointN([14, 6, 6]), PointN([18, 10, 10]));
+        lod0.for_each(&painted_extent.padded(1), |p: Point3i, value| {
+            if painted_extent.contains(p) {
+                assert_eq!(value, 7);
+            } else {
+                assert_eq!(value, 0);
+            }
+        });
+    }
+}
@@ -0,0 +1,213 @@
+//! Keeping the lattice coordinates of a `ChunkMap` close to a moving focal point, so `f32` positions stay precise.
+//!
+//! An `f32` only has 24 bits of mantissa, so mesh vertices and transforms lose sub-voxel precision once they get a few million
+//! voxels away from the origin, which causes jittery rendering and physics in long play sessions. With a "floating origin," the
+//! lattice origin periodically jumps to the player, so everything near the player has small coordinates. `FloatingOrigin3`
+//! remembers where the origin is in the (64-bit) world coordinates, and `ChunkMap::shift_origin` moves every chunk so that the
+//! data stays in the same place in the world.
+//!
+//! Shifts are always multiples of the largest chunk shape, so chunk keys stay aligned at every level of detail and no voxels
+//! need to be copied. Other state in lattice coordinates, like meshes, clipmap centers, and entity transforms, must be moved by
+//! the same delta, e.g. by subtracting it from your render transforms.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, FloatingOrigin3};
+//!
+//! let chunk_shape = Point3i::fill(16);
+//! let builder = ChunkMapBuilder3x1::new(chunk_shape, 0);
+//! let mut map = builder.build_with_hash_map_storage();
+//! let mut origin = FloatingOrigin3::new(chunk_shape, 2, 1000);
+//!
+//! let mut player = PointN([1500.0, 10.0, -3.0]);
+//! *map.lod_view_mut(0).get_mut(PointN([1500, 10, -3])) = 1;
+//!
+//! if let Some(delta) = origin.rebase_delta(player) {
+//!     map.shift_origin(delta);
+//!     origin.rebase(delta);
+//!     player -= Point3f::from(delta);
+//! }
+//!
+//! assert_eq!(origin.world_offset(), [1504, 0, 0]);
+//! assert_eq!(origin.local_to_world(player), [1500.0, 10.0, -3.0]);
+//! assert_eq!(map.clone_point(0, player.in_voxel()), 1);
+//! ```
+
+use crate::{Array, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkWriteStorage, IterChunkKeys};
+
+use building_blocks_core::prelude::*;
+
+/// The location of the lattice origin in world coordinates, which are 64-bit so they can address a much larger world than the
+/// `i32` lattice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FloatingOrigin3 {
+    world_offset: [i64; 3],
+    alignment: Point3i,
+    rebase_distance: i32,
+}
+
+impl FloatingOrigin3 {
+    /// Starts with the origin at the world origin. `chunk_shape` and `num_lods` should match the `ChunkMap`, so that every shift
+    /// keeps the chunks aligned. The origin is only moved when the focal point is more than `rebase_distance` voxels from it
+    /// along some axis.
+    pub fn new(chunk_shape: Point3i, num_lods: u8, rebase_distance: i32) -> Self {
+        assert!(chunk_shape.dimensions_are_powers_of_2());
+        assert!(num_lods > 0);
+
+        Self {
+            world_offset: [0; 3],
+            alignment: chunk_shape << (num_lods as i32 - 1),
+            rebase_distance,
+        }
+    }
+
+    /// The world coordinates of the lattice origin.
+    pub fn world_offset(&self) -> [i64; 3] {
+        self.world_offset
+    }
+
+    /// Every shift of the origin is a multiple of this shape, which is the shape of a chunk at the highest level of detail.
+    pub fn alignment(&self) -> Point3i {
+        self.alignment
+    }
+
+    /// Converts a point in lattice coordinates to world coordinates.
+    pub fn local_to_world(&self, p: Point3f) -> [f64; 3] {
+        let mut world = [0.0; 3];
+        for ((w, &offset), &c) in world
+            .iter_mut()
+            .zip(self.world_offset.iter())
+            .zip(p.0.iter())
+        {
+            *w = offset as f64 + c as f64;
+        }
+
+        world
+    }
+
+    /// Converts a point in world coordinates to lattice coordinates. This is only precise near the origin.
+    pub fn world_to_local(&self, p: [f64; 3]) -> Point3f {
+        let mut local = Point3f::ZERO;
+        for ((l, &offset), &c) in local
+            .0
+            .iter_mut()
+            .zip(self.world_offset.iter())
+            .zip(p.iter())
+        {
+            *l = (c - offset as f64) as f32;
+        }
+
+        local
+    }
+
+    /// If `focus` (in lattice coordinates) is farther than the rebase distance from the origin, returns the aligned point
+    /// nearest to `focus`, which should become the new origin. Pass it to `ChunkMap::shift_origin` and `rebase`.
+    pub fn rebase_delta(&self, focus: Point3f) -> Option<Point3i> {
+        if focus.abs().max_component() <= self.rebase_distance as f32 {
+            return None;
+        }
+
+        let delta =
+            (focus.in_voxel() + (self.alignment >> 1)) & !(self.alignment - Point3i::fill(1));
+
+        if delta == Point3i::ZERO {
+            None
+        } else {
+            Some(delta)
+        }
+    }
+
+    /// Moves the origin to `delta`, in the current lattice coordinates.
+    pub fn rebase(&mut self, delta: Point3i) {
+        assert_eq!(delta % self.alignment, Point3i::ZERO);
+
+        for (offset, &d) in self.world_offset.iter_mut().zip(delta.0.iter()) {
+            *offset += d as i64;
+        }
+    }
+}
+
+impl<N, T, Chan, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>> + for<'r> IterChunkKeys<'r, N>,
+{
+    /// Moves every chunk by `-delta` (in LOD0 coordinates), so that the data at `delta` ends up at the origin. The chunk arrays
+    /// are moved, not copied.
+    ///
+    /// `delta` must be a multiple of the chunk shape at every level of detail in the map, e.g. a multiple of
+    /// `FloatingOrigin3::alignment`.
+    ///
+    /// This works with any storage by popping and rewriting every chunk. If the storage implements `TranslateChunkKeys`,
+    /// `translate_keys` does the same thing in place with the negated delta.
+    pub fn shift_origin(&mut self, delta: PointN<N>) {
+        let keys: Vec<ChunkKey<N>> = self.storage().chunk_keys().cloned().collect();
+
+        // Pop every chunk first, so chunks can move into keys that were occupied.
+        let chunk_shape = self.chunk_shape();
+        let mut chunks = Vec::with_capacity(keys.len());
+        for key in keys.into_iter() {
+            assert!(
+                (delta >> key.lod as i32) % chunk_shape == PointN::ZERO,
+                "Shift is not aligned to the chunks at LOD {}",
+                key.lod
+            );
+            if let Some(chunk) = self.pop_chunk(key) {
+                chunks.push((key.translated(PointN::ZERO - delta), chunk));
+            }
+        }
+        for (key, mut chunk) in chunks.into_iter() {
+            chunk.set_minimum(key.minimum);
+            self.write_chunk(key, chunk);
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn shift_origin_moves_chunks_at_every_lod() {
+        let chunk_shape = Point3i::fill(16);
+        let builder = ChunkMapBuilder3x1::new(chunk_shape, 0);
+        let mut map = builder.build_with_hash_map_storage();
+
+        *map.lod_view_mut(0).get_mut(PointN([40, 1, -70])) = 1;
+        *map.lod_view_mut(0).get_mut(PointN([8, 0, 0])) = 2;
+        *map.lod_view_mut(1).get_mut(PointN([20, 1, -35])) = 3;
+
+        let delta = PointN([32, 0, -64]);
+        map.shift_origin(delta);
+
+        assert_eq!(map.clone_point(0, PointN([8, 1, -6])), 1);
+        assert_eq!(map.clone_point(0, PointN([-24, 0, 64])), 2);
+        assert_eq!(map.clone_point(0, PointN([8, 0, 0])), 0);
+        assert_eq!(map.clone_point(1, PointN([4, 1, -3])), 3);
+        for key in map.storage().keys() {
+            assert_eq!(map.get_chunk(*key).unwrap().extent().minimum, key.minimum);
+        }
+    }
+
+    #[test]
+    fn rebase_delta_is_aligned_and_nearest_to_focus() {
+        let origin = FloatingOrigin3::new(Point3i::fill(16), 3, 100);
+        assert_eq!(origin.alignment(), Point3i::fill(64));
+
+        assert_eq!(origin.rebase_delta(PointN([99.0, -50.0, 0.0])), None);
+        assert_eq!(
+            origin.rebase_delta(PointN([120.0, -100.5, 31.0])),
+            Some(PointN([128, -128, 0]))
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Procedural generation of `ChunkMap` values from a function of the lattice point, like a noise function or an SDF.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), Sd8::ONE);
+//! let mut map = builder.build_with_hash_map_storage();
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-32), Point3i::fill(64));
+//! map.fill_extent_with(0, &extent, |p: Point3i| Sd8::from((p.norm() - 20.0) / 4.0));
+//!
+//! assert!(map.clone_point(0, Point3i::ZERO).is_negative());
+//! assert!(!map.clone_point(0, Point3i::fill(30)).is_negative());
+//! ```
+
+use crate::{
+    Array, ArrayIndexer, ArrayNx1, Channels, ChunkKey, ChunkMap, ChunkMapBuilder,
+    ChunkMapBuilderNx1, ChunkMapBuilderNxM, ChunkWriteStorage, FillChannels, ForEachMutPtr,
+    IntoMultiMutPtr, MultiMutPtr, UninitChannels,
+};
+
+use building_blocks_core::prelude::*;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+impl<N, T, Chan, UninitChan, Store> ChunkMap<N, T, ChunkMapBuilderNxM<N, T, Chan>, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    T: Clone,
+    Chan: FillChannels<Data = T> + Channels<UninitSelf = UninitChan>,
+    UninitChan: UninitChannels<InitSelf = Chan>,
+    UninitChan::Ptr: IntoMultiMutPtr<Data = T>,
+    Array<N, Chan>: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    Array<N, UninitChan>: ForEachMutPtr<N, PointN<N>, Item = UninitChan::Ptr>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    /// Writes `f(p)` to every point `p` of `extent` in level of detail `lod`.
+    ///
+    /// Chunks that are entirely inside of `extent` are generated directly into newly allocated arrays, replacing any existing
+    /// chunks, so their values are never filled with the ambient value or read back. Only the chunks on the boundary of
+    /// `extent` are modified in place.
+    pub fn fill_extent_with(&mut self, lod: u8, extent: &ExtentN<N>, f: impl Fn(PointN<N>) -> T) {
+        let (full_chunk_mins, boundary_chunk_mins) = self.split_chunk_mins_by_coverage(extent);
+
+        for chunk_min in full_chunk_mins.into_iter() {
+            let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+            self.storage.write(
+                ChunkKey::new(lod, chunk_min),
+                Array::fill_with(chunk_extent, &f),
+            );
+        }
+        for chunk_min in boundary_chunk_mins.into_iter() {
+            let chunk = self.get_mut_chunk_or_insert_ambient(ChunkKey::new(lod, chunk_min));
+            write_func_into_chunk(chunk, extent, &f);
+        }
+    }
+
+    // Returns the minimums of the chunks that overlap `extent`, split into those that are entirely inside of `extent` and those
+    // that are only partially covered.
+    fn split_chunk_mins_by_coverage(
+        &self,
+        extent: &ExtentN<N>,
+    ) -> (Vec<PointN<N>>, Vec<PointN<N>>) {
+        self.indexer
+            .chunk_mins_for_extent(extent)
+            .partition(|&chunk_min| {
+                self.indexer
+                    .extent_for_chunk_with_min(chunk_min)
+                    .is_subset_of(extent)
+            })
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, T, Chan, UninitChan, Store> ChunkMap<N, T, ChunkMapBuilderNxM<N, T, Chan>, Store>
+where
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+    T: Clone,
+    Chan: FillChannels<Data = T> + Channels<UninitSelf = UninitChan> + Send,
+    UninitChan: UninitChannels<InitSelf = Chan>,
+    UninitChan::Ptr: IntoMultiMutPtr<Data = T>,
+    Array<N, Chan>: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    Array<N, UninitChan>: ForEachMutPtr<N, PointN<N>, Item = UninitChan::Ptr>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    /// Like `fill_extent_with`, but chunks are generated in parallel on the `rayon` thread pool.
+    ///
+    /// The boundary chunks are temporarily popped out of storage so they can be borrowed mutably by separate threads.
+    pub fn par_fill_extent_with(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        f: impl Fn(PointN<N>) -> T + Sync,
+    ) {
+        let (full_chunk_mins, boundary_chunk_mins) = self.split_chunk_mins_by_coverage(extent);
+
+        let Self {
+            indexer,
+            storage,
+            builder,
+            ..
+        } = self;
+
+        let mut boundary_chunks: Vec<_> = boundary_chunk_mins
+            .into_iter()
+            .map(|chunk_min| {
+                let key = ChunkKey::new(lod, chunk_min);
+                let chunk = storage.pop(key).unwrap_or_else(|| {
+                    builder.new_ambient(indexer.extent_for_chunk_with_min(chunk_min))
+                });
+
+                (key, chunk)
+            })
+            .collect();
+        boundary_chunks
+            .par_iter_mut()
+            .for_each(|(_key, chunk)| write_func_into_chunk(chunk, extent, &f));
+
+        let indexer = &*indexer;
+        let full_chunks: Vec<_> = full_chunk_mins
+            .into_par_iter()
+            .map(|chunk_min| {
+                let chunk_extent = indexer.extent_for_chunk_with_min(chunk_min);
+
+                (
+                    ChunkKey::new(lod, chunk_min),
+                    Array::fill_with(chunk_extent, &f),
+                )
+            })
+            .collect();
+
+        for (key, chunk) in boundary_chunks.into_iter().chain(full_chunks.into_iter()) {
+            storage.write(key, chunk);
+        }
+    }
+}
+
+impl<N, T, Store> ChunkMap<N, T, ChunkMapBuilderNx1<N, T>, Store>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    T: Clone + Default,
+    Store: ChunkWriteStorage<N, ArrayNx1<N, T>>,
+{
+    /// Like `fill_extent_with`, but `filler` writes one X-strip of values at a time, as in `Array::fill_with_strips`. This lets
+    /// a SIMD noise function generate many values at once.
+    pub fn fill_extent_with_strips(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        mut filler: impl FnMut(PointN<N>, &mut [T]),
+    ) {
+        let chunk_mins: Vec<_> = self.indexer.chunk_mins_for_extent(extent).collect();
+        for chunk_min in chunk_mins.into_iter() {
+            let key = ChunkKey::new(lod, chunk_min);
+            let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+            if chunk_extent.is_subset_of(extent) {
+                self.storage
+                    .write(key, Array::fill_with_strips(chunk_extent, &mut filler));
+            } else {
+                self.get_mut_chunk_or_insert_ambient(key)
+                    .write_strips(extent, &mut filler);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, T, Store> ChunkMap<N, T, ChunkMapBuilderNx1<N, T>, Store>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+    T: Clone + Default + Send,
+    Store: ChunkWriteStorage<N, ArrayNx1<N, T>>,
+{
+    /// Like `fill_extent_with_strips`, but chunks are generated in parallel on the `rayon` thread pool.
+    pub fn par_fill_extent_with_strips(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        filler: impl Fn(PointN<N>, &mut [T]) + Sync,
+    ) {
+        let Self {
+            indexer,
+            storage,
+            builder,
+            ..
+        } = self;
+
+        // Pop the boundary chunks so they can be borrowed mutably by separate threads.
+        let mut chunks: Vec<_> = indexer
+            .chunk_mins_for_extent(extent)
+            .map(|chunk_min| {
+                let key = ChunkKey::new(lod, chunk_min);
+                let chunk_extent = indexer.extent_for_chunk_with_min(chunk_min);
+                let chunk = if chunk_extent.is_subset_of(extent) {
+                    None
+                } else {
+                    Some(
+                        storage
+                            .pop(key)
+                            .unwrap_or_else(|| builder.new_ambient(chunk_extent)),
+                    )
+                };
+
+                (key, chunk_extent, chunk)
+            })
+            .collect();
+
+        chunks
+            .par_iter_mut()
+            .for_each(|(_key, chunk_extent, chunk)| match chunk {
+                Some(chunk) => chunk.write_strips(extent, &filler),
+                None => *chunk = Some(Array::fill_with_strips(*chunk_extent, &filler)),
+            });
+
+        for (key, _chunk_extent, chunk) in chunks.into_iter() {
+            storage.write(key, chunk.unwrap());
+        }
+    }
+}
+
+fn write_func_into_chunk<N, T, Chan>(
+    chunk: &mut Array<N, Chan>,
+    extent: &ExtentN<N>,
+    f: impl Fn(PointN<N>) -> T,
+) where
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels<Data = T>,
+    Array<N, Chan>: ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+{
+    let write_extent = extent.intersection(chunk.extent());
+    unsafe {
+        chunk.for_each_mut_ptr(&write_extent, |p, ptr| ptr.write(f(p)));
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    const BUILDER: ChunkMapBuilder3x1<i32> = ChunkMapBuilder3x1::new(PointN([16; 3]), 0);
+
+    fn func(p: Point3i) -> i32 {
+        p.x() + 100 * p.y() + 10000 * p.z()
+    }
+
+    #[test]
+    fn fill_extent_with_writes_only_inside_of_extent() {
+        let mut map = BUILDER.build_with_hash_map_storage();
+        let outer = Extent3i::from_min_and_shape(Point3i::fill(-20), Point3i::fill(60));
+        map.fill_extent(0, &outer, -1);
+
+        // Covers some chunks entirely and others partially.
+        let extent = Extent3i::from_min_and_shape(PointN([-5, -16, 0]), PointN([40, 32, 33]));
+        map.fill_extent_with(0, &extent, func);
+
+        for p in outer.iter_points() {
+            let expected = if extent.contains(p) { func(p) } else { -1 };
+            assert_eq!(map.clone_point(0, p), expected);
+        }
+    }
+
+    #[test]
+    fn fill_extent_with_creates_vacant_chunks_with_ambient_value() {
+        let mut map = BUILDER.build_with_hash_map_storage();
+
+        let extent = Extent3i::from_min_and_shape(PointN([8, 0, 0]), PointN([24, 16, 16]));
+        map.fill_extent_with(0, &extent, func);
+
+        assert_eq!(map.storage().len(), 2);
+        assert_eq!(map.clone_point(0, PointN([7, 1, 1])), 0);
+        assert_eq!(
+            map.clone_point(0, PointN([8, 1, 1])),
+            func(PointN([8, 1, 1]))
+        );
+        assert_eq!(
+            map.clone_point(0, PointN([31, 15, 15])),
+            func(PointN([31, 15, 15]))
+        );
+    }
+
+    #[test]
+    fn fill_extent_with_strips_matches_fill_extent_with() {
+        let extent = Extent3i::from_min_and_shape(PointN([-5, -16, 0]), PointN([40, 32, 33]));
+
+        let mut expected = BUILDER.build_with_hash_map_storage();
+        expected.fill_extent_with(0, &extent, func);
+
+        let mut strips = BUILDER.build_with_hash_map_storage();
+        strips.fill_extent_with_strips(0, &extent, |strip_min: Point3i, strip: &mut [i32]| {
+            for (i, value) in strip.iter_mut().enumerate() {
+                *value = func(strip_min + PointN([i as i32, 0, 0]));
+            }
+        });
+
+        for p in extent.padded(2).iter_points() {
+            assert_eq!(strips.clone_point(0, p), expected.clone_point(0, p));
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_fill_extent_with_matches_serial() {
+        let extent = Extent3i::from_min_and_shape(PointN([-5, -16, 3]), PointN([40, 32, 20]));
+
+        let mut serial = BUILDER.build_with_hash_map_storage();
+        serial.fill_extent(0, &extent, 7);
+        serial.fill_extent_with(0, &extent.padded(-2), func);
+
+        let mut parallel = BUILDER.build_with_hash_map_storage();
+        parallel.fill_extent(0, &extent, 7);
+        parallel.par_fill_extent_with(0, &extent.padded(-2), func);
+
+        for p in extent.padded(2).iter_points() {
+            assert_eq!(serial.clone_point(0, p), parallel.clone_point(0, p));
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! Undo and redo of `ChunkMap` edits.
+//!
+//! An `EditJournal` keeps the history of a map as a stack of transactions, where each transaction holds the inverse patches of
+//! the chunks it changed. Edits are made through a `JournaledChunkMap`, which saves the old version of each chunk the first
+//! time it's mutated, then diffs it against the new version when the transaction is committed. So the journal only stores the
+//! runs of values that actually changed, as `ChunkDelta`s. Every write between two commits is undone as a group.
+//!
+//! The journal assumes that the map is only mutated through a `JournaledChunkMap` between undos and redos. It's serializable
+//! with `serde`, so an editor can save the undo history along with a document.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, EditJournal};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0u8);
+//! let mut map = builder.build_with_hash_map_storage();
+//! let mut journal = EditJournal::default();
+//!
+//! // Both writes are undone together.
+//! journal.transaction(&mut map, |edit| {
+//!     *edit.get_mut_point(0, Point3i::fill(1)) = 1;
+//!     edit.fill_extent(0, &Extent3i::from_min_and_shape(Point3i::fill(20), Point3i::fill(4)), 2);
+//! });
+//! journal.transaction(&mut map, |edit| *edit.get_mut_point(0, Point3i::fill(1)) = 3);
+//!
+//! journal.undo(&mut map);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 1);
+//! journal.undo(&mut map);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 0);
+//! // Chunks that were inserted by an edit are removed again.
+//! assert!(map.get_chunk(ChunkKey::new(0, Point3i::fill(16))).is_none());
+//!
+//! journal.redo(&mut map);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(21)), 2);
+//!
+//! let bytes = bincode::serialize(&journal).unwrap();
+//! let journal: EditJournal<[i32; 3], u8> = bincode::deserialize(&bytes).unwrap();
+//! assert_eq!(journal.num_undo_transactions(), 1);
+//! assert_eq!(journal.num_redo_transactions(), 1);
+//! ```
+
+use crate::{
+    Array, Channels, ChunkDelta, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkMapLodView,
+    ChunkWriteStorage, FillExtent, Get, GetMut, GetMutPtr, SmallKeyHashMap,
+};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use serde::{Deserialize, Serialize};
+
+/// How to restore a single chunk.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ChunkPatch<N, T> {
+    /// Apply the delta to the chunk in the map.
+    Modify(ChunkDelta<N, T>),
+    /// Insert the chunk made by applying this full delta to an ambient chunk.
+    Insert(ChunkDelta<N, T>),
+    /// Remove the chunk from the map.
+    Remove,
+}
+
+/// The patches for every chunk changed by a group of edits.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EditTransaction<N, T> {
+    patches: Vec<(ChunkKey<N>, ChunkPatch<N, T>)>,
+}
+
+impl<N, T> EditTransaction<N, T> {
+    pub fn patches(&self) -> &[(ChunkKey<N>, ChunkPatch<N, T>)] {
+        &self.patches
+    }
+
+    /// The keys of the chunks that this transaction changes.
+    pub fn keys(&self) -> impl Iterator<Item = &ChunkKey<N>> {
+        self.patches.iter().map(|(key, _)| key)
+    }
+}
+
+/// The undo and redo history of a `ChunkMap`. See the [module docs](self).
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EditJournal<N, T> {
+    undo_stack: Vec<EditTransaction<N, T>>,
+    redo_stack: Vec<EditTransaction<N, T>>,
+    max_transactions: usize,
+}
+
+impl<N, T> Default for EditJournal<N, T> {
+    fn default() -> Self {
+        Self::with_max_transactions(usize::MAX)
+    }
+}
+
+impl<N, T> EditJournal<N, T> {
+    /// A journal that forgets the oldest transaction once it can undo more than `max_transactions`.
+    pub fn with_max_transactions(max_transactions: usize) -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            max_transactions,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn num_undo_transactions(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn num_redo_transactions(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Forget the whole history.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Records a new transaction. This makes the redo history unreachable, so it's dropped.
+    fn push(&mut self, transaction: EditTransaction<N, T>) {
+        self.redo_stack.clear();
+        self.undo_stack.push(transaction);
+        if self.undo_stack.len() > self.max_transactions {
+            let num_forgotten = self.undo_stack.len() - self.max_transactions;
+            self.undo_stack.drain(..num_forgotten);
+        }
+    }
+}
+
+impl<N, T> EditJournal<N, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    T: Clone + PartialEq,
+{
+    /// Calls `edit` with a `JournaledChunkMap`, then commits all of its writes as a single transaction.
+    pub fn transaction<'m, Chan, Bldr, Store, R>(
+        &'m mut self,
+        map: &'m mut ChunkMap<N, T, Bldr, Store>,
+        edit: impl FnOnce(&mut JournaledChunkMap<'m, N, T, Bldr, Store>) -> R,
+    ) -> R
+    where
+        Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+        Array<N, Chan>: Clone,
+        Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+        Store: ChunkWriteStorage<N, Array<N, Chan>>,
+    {
+        let mut journaled = JournaledChunkMap::new(map, self);
+        let result = edit(&mut journaled);
+        journaled.commit();
+
+        result
+    }
+
+    /// Reverts the most recent transaction and moves it to the redo history. Returns the keys of the chunks that changed, or
+    /// `None` if there was nothing to undo.
+    ///
+    /// # Panics
+    ///
+    /// If the transaction has a `ChunkDelta` that doesn't fit its chunk, which can only happen if the journal was deserialized
+    /// from corrupt data.
+    pub fn undo<Chan, Bldr, Store>(
+        &mut self,
+        map: &mut ChunkMap<N, T, Bldr, Store>,
+    ) -> Option<Vec<ChunkKey<N>>>
+    where
+        Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+        Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+        Store: ChunkWriteStorage<N, Array<N, Chan>>,
+    {
+        let transaction = self.undo_stack.pop()?;
+        let inverse = apply_transaction(map, transaction);
+        let keys = inverse.keys().cloned().collect();
+        self.redo_stack.push(inverse);
+
+        Some(keys)
+    }
+
+    /// Reapplies the most recently undone transaction. Returns the keys of the chunks that changed, or `None` if there was
+    /// nothing to redo.
+    ///
+    /// # Panics
+    ///
+    /// See `undo`.
+    pub fn redo<Chan, Bldr, Store>(
+        &mut self,
+        map: &mut ChunkMap<N, T, Bldr, Store>,
+    ) -> Option<Vec<ChunkKey<N>>>
+    where
+        Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+        Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+        Store: ChunkWriteStorage<N, Array<N, Chan>>,
+    {
+        let transaction = self.redo_stack.pop()?;
+        let inverse = apply_transaction(map, transaction);
+        let keys = inverse.keys().cloned().collect();
+        self.undo_stack.push(inverse);
+
+        Some(keys)
+    }
+}
+
+/// Applies all patches of `transaction` to `map` and returns the transaction that reverts them.
+fn apply_transaction<N, T, Chan, Bldr, Store>(
+    map: &mut ChunkMap<N, T, Bldr, Store>,
+    transaction: EditTransaction<N, T>,
+) -> EditTransaction<N, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    T: Clone,
+    Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    let mut inverse_patches = Vec::with_capacity(transaction.patches.len());
+    for (key, patch) in transaction.patches.into_iter() {
+        let inverse = match patch {
+            ChunkPatch::Modify(delta) => {
+                let chunk = map.get_mut_chunk_or_insert_ambient(key);
+                ChunkPatch::Modify(
+                    delta
+                        .apply_and_invert(chunk)
+                        .expect("Journal has a delta that doesn't fit its chunk"),
+                )
+            }
+            ChunkPatch::Insert(delta) => {
+                let mut chunk = map.builder().new_ambient(*delta.extent());
+                delta
+                    .apply(&mut chunk)
+                    .expect("Journal has a delta that doesn't fit its chunk");
+                map.write_chunk(key, chunk);
+                ChunkPatch::Remove
+            }
+            ChunkPatch::Remove => match map.pop_chunk(key) {
+                Some(chunk) => ChunkPatch::Insert(ChunkDelta::full(&chunk)),
+                None => continue,
+            },
+        };
+        inverse_patches.push((key, inverse));
+    }
+
+    EditTransaction {
+        patches: inverse_patches,
+    }
+}
+
+/// Borrows a `ChunkMap` and records the edits made through it into an `EditJournal`.
+///
+/// Writes are only undoable if they're made with the methods of this type. The edits are recorded when `commit` is called, and
+/// `EditJournal::transaction` commits automatically.
+pub struct JournaledChunkMap<'a, N, T, Bldr, Store>
+where
+    Bldr: ChunkMapBuilder<N, T>,
+{
+    map: &'a mut ChunkMap<N, T, Bldr, Store>,
+    journal: &'a mut EditJournal<N, T>,
+    /// The version of each chunk before its first mutation in the open transaction.
+    saved_chunks: SmallKeyHashMap<ChunkKey<N>, Option<Bldr::Chunk>>,
+}
+
+impl<'a, N, T, Chan, Bldr, Store> JournaledChunkMap<'a, N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    T: Clone + PartialEq,
+    Chan: Channels<Data = T> + Get<usize, Item = T> + GetMutPtr<usize, Item = Chan::Ptr>,
+    Array<N, Chan>: Clone,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    pub fn new(
+        map: &'a mut ChunkMap<N, T, Bldr, Store>,
+        journal: &'a mut EditJournal<N, T>,
+    ) -> Self {
+        Self {
+            map,
+            journal,
+            saved_chunks: SmallKeyHashMap::default(),
+        }
+    }
+
+    /// Borrow the map for reading.
+    pub fn map(&self) -> &ChunkMap<N, T, Bldr, Store> {
+        self.map
+    }
+
+    /// Saves the current version of the chunk at `key`, unless it was already saved in the open transaction.
+    fn save_chunk(&mut self, key: ChunkKey<N>) {
+        if self.saved_chunks.contains_key(&key) {
+            return;
+        }
+        let chunk = self.map.get_mut_chunk(key).cloned();
+        self.saved_chunks.insert(key, chunk);
+    }
+
+    /// Like `ChunkMap::get_mut_chunk`.
+    pub fn get_mut_chunk(&mut self, key: ChunkKey<N>) -> Option<&mut Array<N, Chan>> {
+        self.save_chunk(key);
+
+        self.map.get_mut_chunk(key)
+    }
+
+    /// Like `ChunkMap::get_mut_chunk_or_insert_ambient`.
+    pub fn get_mut_chunk_or_insert_ambient(&mut self, key: ChunkKey<N>) -> &mut Array<N, Chan> {
+        self.save_chunk(key);
+
+        self.map.get_mut_chunk_or_insert_ambient(key)
+    }
+
+    /// Like `ChunkMap::get_mut_point`.
+    pub fn get_mut_point<'b, Mut>(&'b mut self, lod: u8, p: PointN<N>) -> Mut
+    where
+        Array<N, Chan>: GetMut<'b, PointN<N>, Item = Mut>,
+    {
+        let chunk_min = self.map.indexer.min_of_chunk_containing_point(p);
+
+        self.get_mut_chunk_or_insert_ambient(ChunkKey::new(lod, chunk_min))
+            .get_mut(p)
+    }
+
+    /// Like `ChunkMap::write_chunk`.
+    pub fn write_chunk(&mut self, key: ChunkKey<N>, chunk: Array<N, Chan>) {
+        self.save_chunk(key);
+        self.map.write_chunk(key, chunk);
+    }
+
+    /// Like `ChunkMap::delete_chunk`.
+    pub fn delete_chunk(&mut self, key: ChunkKey<N>) {
+        self.save_chunk(key);
+        self.map.delete_chunk(key);
+    }
+
+    /// Calls `edit` with a mutable view of level of detail `lod`, after saving all chunks that overlap `extent`. Only writes
+    /// inside of `extent` can be undone.
+    pub fn edit_extent<R>(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        edit: impl FnOnce(ChunkMapLodView<&mut ChunkMap<N, T, Bldr, Store>>) -> R,
+    ) -> R {
+        let chunk_mins: Vec<_> = self.map.indexer.chunk_mins_for_extent(extent).collect();
+        for chunk_min in chunk_mins.into_iter() {
+            self.save_chunk(ChunkKey::new(lod, chunk_min));
+        }
+
+        edit(self.map.lod_view_mut(lod))
+    }
+
+    /// Like `ChunkMap::fill_extent`.
+    pub fn fill_extent(&mut self, lod: u8, extent: &ExtentN<N>, value: T)
+    where
+        for<'r> ChunkMapLodView<&'r mut ChunkMap<N, T, Bldr, Store>>: FillExtent<N, Item = T>,
+    {
+        self.edit_extent(lod, extent, |mut view| view.fill_extent(extent, value))
+    }
+
+    /// Records the edits made since the last commit as one transaction of the journal. Chunks that were mutably borrowed but
+    /// didn't change are left out. Returns `false` if nothing changed, in which case no transaction is recorded.
+    pub fn commit(&mut self) -> bool {
+        let Self {
+            map,
+            journal,
+            saved_chunks,
+        } = self;
+
+        let mut patches = Vec::new();
+        for (key, old_chunk) in saved_chunks.drain() {
+            let patch = match (old_chunk, map.get_mut_chunk(key)) {
+                (Some(old_chunk), Some(new_chunk)) => {
+                    let delta = ChunkDelta::diff(new_chunk, &old_chunk);
+                    if delta.is_empty() {
+                        continue;
+                    }
+                    ChunkPatch::Modify(delta)
+                }
+                (Some(old_chunk), None) => ChunkPatch::Insert(ChunkDelta::full(&old_chunk)),
+                (None, Some(_)) => ChunkPatch::Remove,
+                (None, None) => continue,
+            };
+            patches.push((key, patch));
+        }
+
+        if patches.is_empty() {
+            return false;
+        }
+        journal.push(EditTransaction { patches });
+
+        true
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn undo_and_redo_multichannel_edits() {
+        let builder = ChunkMapBuilder3x2::new(Point3i::fill(4), (0u8, 0u16));
+        let mut map = builder.build_with_hash_map_storage();
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4));
+        map.fill_extent(0, &extent, (1, 1));
+
+        let mut journal = EditJournal::with_max_transactions(2);
+        let mut edit = JournaledChunkMap::new(&mut map, &mut journal);
+        // Mutably borrowing without changing anything doesn't record a transaction.
+        edit.get_mut_chunk(key);
+        assert!(!edit.commit());
+
+        let (a, b) = edit.get_mut_point(0, Point3i::fill(1));
+        *a = 2;
+        *b = 2;
+        edit.delete_chunk(ChunkKey::new(0, Point3i::fill(4)));
+        assert!(edit.commit());
+
+        edit.delete_chunk(key);
+        assert!(edit.commit());
+        edit.write_chunk(key, Array3x2::fill(extent, (3, 3)));
+        assert!(edit.commit());
+        assert_eq!(journal.num_undo_transactions(), 2);
+
+        assert_eq!(journal.undo(&mut map), Some(vec![key]));
+        assert!(map.get_chunk(key).is_none());
+        journal.undo(&mut map);
+        assert_eq!(map.lod_view(0).get(Point3i::fill(1)), (2, 2));
+        assert_eq!(map.lod_view(0).get(Point3i::ZERO), (1, 1));
+        // The oldest transaction was forgotten.
+        assert_eq!(journal.undo(&mut map), None);
+
+        journal.redo(&mut map);
+        journal.redo(&mut map);
+        assert_eq!(map.lod_view(0).get(Point3i::fill(1)), (3, 3));
+        assert!(!journal.can_redo());
+
+        // A new transaction drops the redo history.
+        journal.undo(&mut map);
+        journal.transaction(&mut map, |edit| {
+            *edit.get_mut_point(0, Point3i::fill(5)).0 = 4
+        });
+        assert!(!journal.can_redo());
+        journal.undo(&mut map);
+        assert!(map.get_chunk(ChunkKey::new(0, Point3i::fill(4))).is_none());
+        journal.undo(&mut map);
+        assert_eq!(map.lod_view(0).get(Point3i::fill(1)), (2, 2));
+    }
+}
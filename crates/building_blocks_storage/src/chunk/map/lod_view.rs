@@ -1,7 +1,7 @@
 use crate::{
     AmbientExtent, ArrayCopySrc, Chunk, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkReadStorage,
     ChunkWriteStorage, FillExtent, ForEach, ForEachMut, ForEachMutPtr, Get, GetMut, GetRef,
-    IntoMultiMut, MultiMutPtr, MultiRef, ReadExtent, WriteExtent,
+    IntoMultiMut, MultiMutPtr, MultiRef, ReadExtent, SmallKeyHashMap, WriteExtent, WritePoints,
 };
 
 use building_blocks_core::{ExtentN, IntegerPoint, PointN};
@@ -166,6 +166,41 @@ where
     }
 }
 
+impl<Delegate, N, T, Bldr, Store> WritePoints<N> for ChunkMapLodView<Delegate>
+where
+    Delegate: DerefMut<Target = ChunkMap<N, T, Bldr, Store>>,
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    <Bldr::Chunk as Chunk>::Array: WritePoints<N, Item = T>,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+{
+    type Item = T;
+
+    /// The points are grouped by chunk, so each chunk is only looked up once. Vacant chunks will be created first, like in
+    /// `ChunkMap::get_mut_chunk_or_insert_ambient`.
+    fn write_points<I>(&mut self, points: I)
+    where
+        I: IntoIterator<Item = (PointN<N>, T)>,
+    {
+        let mut points_by_chunk: SmallKeyHashMap<PointN<N>, Vec<(PointN<N>, T)>> =
+            SmallKeyHashMap::default();
+        for (p, value) in points.into_iter() {
+            let chunk_min = self.delegate.indexer.min_of_chunk_containing_point(p);
+            points_by_chunk
+                .entry(chunk_min)
+                .or_insert_with(Vec::new)
+                .push((p, value));
+        }
+
+        for (chunk_min, chunk_points) in points_by_chunk.into_iter() {
+            self.delegate
+                .get_mut_chunk_or_insert_ambient(ChunkKey::new(self.lod, chunk_min))
+                .array_mut()
+                .write_points(chunk_points);
+        }
+    }
+}
+
 //  ██████╗ ██████╗ ██████╗ ██╗   ██╗
 // ██╔════╝██╔═══██╗██╔══██╗╚██╗ ██╔╝
 // ██║     ██║   ██║██████╔╝ ╚████╔╝
@@ -193,15 +228,16 @@ where
                 let chunk_extent = self.delegate.indexer.extent_for_chunk_with_min(chunk_min);
                 let intersection = extent.intersection(&chunk_extent);
 
-                (
-                    intersection,
-                    self.delegate
-                        .get_chunk(ChunkKey::new(self.lod, chunk_min))
-                        .map(|chunk| Either::Left(ArrayCopySrc(chunk)))
-                        .unwrap_or_else(|| {
-                            Either::Right(AmbientExtent::new(self.delegate.builder.ambient_value()))
-                        }),
-                )
+                let key = ChunkKey::new(self.lod, chunk_min);
+                let src = if let Some(chunk) = self.delegate.get_chunk(key) {
+                    Either::Left(ArrayCopySrc(chunk))
+                } else if let Some(value) = self.delegate.get_uniform_chunk(key) {
+                    Either::Right(AmbientExtent::new(value.clone()))
+                } else {
+                    Either::Right(AmbientExtent::new(self.delegate.builder.ambient_value()))
+                };
+
+                (intersection, src)
             })
             .collect::<Vec<_>>();
 
@@ -0,0 +1,280 @@
+//! Merging the chunks of one `ChunkMap3` into another, e.g. to stitch together world sections that were generated separately.
+//!
+//! `ChunkMap::merge_from` consumes the chunks of the other map, so chunks that only exist in the other map are just moved over.
+//! Chunks that exist in both maps are resolved by a `ChunkMergePolicy`:
+//!
+//! - `PreferExisting` and `PreferOther` pick one of the two chunks without looking at any voxels
+//! - `PerVoxel` combines every pair of voxels with a `StampMerge` policy, where the other map's voxel is the "stamped" one,
+//!   e.g. `PerVoxel(KeepSolid)` to prefer solid voxels, or `PerVoxel(BlendSdf(CsgOperation::Union))` for the minimum of two
+//!   signed distance fields
+//!
+//! When both maps use a `CompressibleChunkStorage`, `ChunkMap::merge_compressed_from` also moves the other map's compressed
+//! chunks without decompressing them, unless they conflict.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, KeepSolid, PerVoxel};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), false);
+//! let mut west = builder.build_with_hash_map_storage();
+//! let mut east = builder.build_with_hash_map_storage();
+//! *west.lod_view_mut(0).get_mut(PointN([1, 1, 1])) = true;
+//! *east.lod_view_mut(0).get_mut(PointN([2, 2, 2])) = true;
+//! *east.lod_view_mut(0).get_mut(PointN([20, 1, 1])) = true;
+//!
+//! // The chunk at the origin is merged, and the other chunk is moved.
+//! let changed = west.merge_from(east.take_storage(), &PerVoxel(KeepSolid));
+//! assert_eq!(changed.len(), 2);
+//!
+//! let lod0 = west.lod_view(0);
+//! assert!(lod0.get(PointN([1, 1, 1])));
+//! assert!(lod0.get(PointN([2, 2, 2])));
+//! assert!(lod0.get(PointN([20, 1, 1])));
+//! ```
+
+use crate::{
+    Array3x1, CacheEntry, ChunkKey3, ChunkMap, ChunkMapBuilder, ChunkWriteStorage,
+    CompressibleChunkMap, CompressibleChunkStorage, Compression, StampMerge,
+};
+
+/// Decides how `ChunkMap::merge_from` combines two chunks with the same key.
+pub trait ChunkMergePolicy<T> {
+    /// Merges the `other` chunk into the `existing` chunk. Returns `true` iff `existing` changed.
+    fn merge_chunk(&self, existing: &mut Array3x1<T>, other: Array3x1<T>) -> bool;
+}
+
+/// Keeps the chunk that's already in the map and drops the other chunk.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PreferExisting;
+
+impl<T> ChunkMergePolicy<T> for PreferExisting {
+    fn merge_chunk(&self, _existing: &mut Array3x1<T>, _other: Array3x1<T>) -> bool {
+        false
+    }
+}
+
+/// Replaces the chunk that's already in the map with the other chunk.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PreferOther;
+
+impl<T> ChunkMergePolicy<T> for PreferOther {
+    fn merge_chunk(&self, existing: &mut Array3x1<T>, other: Array3x1<T>) -> bool {
+        *existing = other;
+
+        true
+    }
+}
+
+/// Merges every voxel of the other chunk into the existing chunk with a `StampMerge` policy.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PerVoxel<M>(pub M);
+
+impl<T, M> ChunkMergePolicy<T> for PerVoxel<M>
+where
+    T: PartialEq,
+    M: StampMerge<T>,
+{
+    fn merge_chunk(&self, existing: &mut Array3x1<T>, other: Array3x1<T>) -> bool {
+        assert_eq!(existing.extent(), other.extent());
+
+        // Both chunks have the same extent, so their voxels are in the same order.
+        let mut changed = false;
+        let other_voxels = other.channels().store();
+        for (voxel, other_voxel) in existing
+            .channels_mut()
+            .store_mut()
+            .iter_mut()
+            .zip(other_voxels.iter())
+        {
+            let new_value = self.0.merge(voxel, other_voxel);
+            if new_value != *voxel {
+                *voxel = new_value;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+impl<T, Bldr, Store> ChunkMap<[i32; 3], T, Bldr, Store>
+where
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = Array3x1<T>>,
+    Store: ChunkWriteStorage<[i32; 3], Array3x1<T>>,
+{
+    /// Moves all of the `other_chunks`, at any level of detail, into this map. Usually `other_chunks` is the storage of another
+    /// map, from `ChunkMap::take_storage`, which must have the same chunk shape. Chunks that are already in this map are
+    /// merged with `policy`.
+    ///
+    /// Returns the keys of all chunks that were inserted or changed.
+    pub fn merge_from(
+        &mut self,
+        other_chunks: impl IntoIterator<Item = (ChunkKey3, Array3x1<T>)>,
+        policy: &impl ChunkMergePolicy<T>,
+    ) -> Vec<ChunkKey3> {
+        let mut changed = Vec::new();
+        for (key, chunk) in other_chunks.into_iter() {
+            if self.merge_chunk(key, chunk, policy) {
+                changed.push(key);
+            }
+        }
+
+        changed
+    }
+
+    fn merge_chunk(
+        &mut self,
+        key: ChunkKey3,
+        chunk: Array3x1<T>,
+        policy: &impl ChunkMergePolicy<T>,
+    ) -> bool {
+        assert_eq!(chunk.extent().minimum, key.minimum);
+        assert_eq!(chunk.extent().shape, self.chunk_shape());
+
+        if let Some(existing) = self.get_mut_chunk(key) {
+            policy.merge_chunk(existing, chunk)
+        } else {
+            self.write_chunk(key, chunk);
+
+            true
+        }
+    }
+}
+
+impl<T, Bldr, Compr> CompressibleChunkMap<[i32; 3], T, Bldr, Compr>
+where
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = Array3x1<T>>,
+    Compr: Compression<Data = Array3x1<T>>,
+{
+    /// Like `merge_from`, but the compressed chunks of `other` that don't conflict with this map are moved over without
+    /// being decompressed. `other` must use the same compression as this map.
+    pub fn merge_compressed_from(
+        &mut self,
+        other: CompressibleChunkStorage<[i32; 3], Compr>,
+        policy: &impl ChunkMergePolicy<T>,
+    ) -> Vec<ChunkKey3> {
+        let CompressibleChunkStorage {
+            cache,
+            mut compressed,
+            ..
+        } = other;
+
+        let mut changed = Vec::new();
+        for (key, entry) in cache.into_iter() {
+            let chunk = match entry {
+                CacheEntry::Evicted(location) => {
+                    let compressed_chunk = compressed.remove(location.0);
+                    if self.storage().cache.get(&key).is_none() {
+                        self.storage_mut().insert_compressed(key, compressed_chunk);
+                        changed.push(key);
+                        continue;
+                    }
+
+                    compressed_chunk.decompress()
+                }
+                CacheEntry::Cached(chunk) => chunk,
+            };
+            if self.merge_chunk(key, chunk, policy) {
+                changed.push(key);
+            }
+        }
+
+        changed
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, sdf::CsgOperation, BlendSdf};
+
+    use building_blocks_core::prelude::*;
+
+    const CHUNK_SHAPE: Point3i = PointN([4; 3]);
+
+    #[test]
+    fn chunk_policies_pick_one_chunk() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 0);
+        let make_map = |value| {
+            let mut map = builder.build_with_hash_map_storage();
+            map.lod_view_mut(0).fill_extent(
+                &Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 4, 4])),
+                value,
+            );
+            map
+        };
+
+        let mut map = make_map(1);
+        assert!(map
+            .merge_from(make_map(2).take_storage(), &PreferExisting)
+            .is_empty());
+        assert_eq!(map.clone_point(0, Point3i::ZERO), 1);
+
+        assert_eq!(
+            map.merge_from(make_map(2).take_storage(), &PreferOther),
+            vec![ChunkKey::new(0, Point3i::ZERO)]
+        );
+        assert_eq!(map.clone_point(0, Point3i::ZERO), 2);
+    }
+
+    #[test]
+    fn per_voxel_sdf_union_takes_the_minimum() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 1.0f32);
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, CHUNK_SHAPE);
+        let sphere_at = |center: Point3i| move |p: Point3i| Point3f::from(p - center).norm() - 1.5;
+        let mut map = builder.build_with_hash_map_storage();
+        map.fill_extent_with(0, &extent, sphere_at(Point3i::ZERO));
+        let mut other = builder.build_with_hash_map_storage();
+        other.fill_extent_with(0, &extent, sphere_at(Point3i::fill(3)));
+
+        let policy = PerVoxel(BlendSdf(CsgOperation::Union));
+        map.merge_from(other.take_storage(), &policy);
+
+        for p in extent.iter_points() {
+            let expected = sphere_at(Point3i::ZERO)(p).min(sphere_at(Point3i::fill(3))(p));
+            assert_eq!(map.clone_point(0, p), expected);
+        }
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn merge_keeps_nonconflicting_chunks_compressed() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 0);
+        let storage = || FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        let mut map = builder.build_with_write_storage(storage());
+        let mut other = builder.build_with_write_storage(storage());
+
+        *map.lod_view_mut(0).get_mut(Point3i::ZERO) = 1;
+        *other.lod_view_mut(0).get_mut(PointN([1, 0, 0])) = 2;
+        *other.lod_view_mut(0).get_mut(PointN([4, 0, 0])) = 3;
+        other.storage_mut().compress_lru();
+        other.storage_mut().compress_lru();
+
+        let max = |existing: &u8, other: &u8| *existing.max(other);
+        let mut changed = map.merge_compressed_from(other.take_storage(), &PerVoxel(max));
+        changed.sort_by_key(|key| key.minimum.x());
+        assert_eq!(
+            changed,
+            vec![
+                ChunkKey::new(0, Point3i::ZERO),
+                ChunkKey::new(0, PointN([4, 0, 0]))
+            ]
+        );
+        assert_eq!(map.storage().len_compressed(), 1);
+
+        let local_cache = LocalChunkCache3::new();
+        let reader = map.reader(&local_cache);
+        let lod0 = reader.lod_view(0);
+        assert_eq!(lod0.get(Point3i::ZERO), 1);
+        assert_eq!(lod0.get(PointN([1, 0, 0])), 2);
+        assert_eq!(lod0.get(PointN([4, 0, 0])), 3);
+    }
+}
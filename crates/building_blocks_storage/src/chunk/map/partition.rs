@@ -0,0 +1,253 @@
+//! Splitting a `ChunkMap` into disjoint sets of chunks that can be mutated on separate threads.
+//!
+//! The chunks of each `ChunkPartition` are popped out of storage, so each partition owns its chunks and can be sent to
+//! another thread, e.g. for parallel terrain generation. When the work is done, `ChunkMap::merge_chunks` writes the chunks
+//! back. Since no two chunks of the same checkerboard partition touch, even at corners, a job that only needs to read the
+//! neighbors of its chunks can pop one checkerboard color at a time while reading the neighbors from the rest of the map.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let mut map = builder.build_with_hash_map_storage();
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(64));
+//! let partitions = map.split_chunks_checkerboard(0, &extent);
+//! assert_eq!(partitions.len(), 8);
+//!
+//! let workers: Vec<_> = partitions
+//!     .into_iter()
+//!     .map(|mut partition| {
+//!         std::thread::spawn(move || {
+//!             for (_key, chunk) in partition.chunks.iter_mut() {
+//!                 let chunk_extent = *chunk.extent();
+//!                 chunk.fill_extent(&chunk_extent, 1);
+//!             }
+//!             partition
+//!         })
+//!     })
+//!     .collect();
+//! map.merge_chunks(workers.into_iter().map(|worker| worker.join().unwrap()));
+//!
+//! assert_eq!(map.clone_point(0, Point3i::fill(63)), 1);
+//! ```
+
+use crate::{ChunkKey, ChunkMap, ChunkMapBuilder, ChunkWriteStorage};
+
+use building_blocks_core::prelude::*;
+
+/// Chunks that have been popped out of a `ChunkMap` by one of its `split_chunks*` methods. No chunk is in more than one
+/// partition.
+#[derive(Clone, Debug)]
+pub struct ChunkPartition<N, Ch> {
+    pub chunks: Vec<(ChunkKey<N>, Ch)>,
+}
+
+impl<N, Ch> ChunkPartition<N, Ch> {
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ChunkKey<N>> {
+        self.chunks.iter().map(|(key, _chunk)| key)
+    }
+}
+
+impl<N, Ch> Default for ChunkPartition<N, Ch> {
+    fn default() -> Self {
+        Self { chunks: Vec::new() }
+    }
+}
+
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    Bldr: ChunkMapBuilder<N, T>,
+    Store: ChunkWriteStorage<N, Bldr::Chunk>,
+{
+    /// Pops all chunks at level of detail `lod` that overlap `extent` into `num_partitions` disjoint partitions, where
+    /// `partition_of` returns the index of the partition for each chunk key. Chunks for which `partition_of` returns `None`
+    /// stay in the map. Vacant chunks are created first, like in `get_mut_chunk_or_insert_ambient`.
+    ///
+    /// The chunks must be written back with `merge_chunks`, or they will be missing from the map.
+    pub fn split_chunks(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        num_partitions: usize,
+        partition_of: impl Fn(ChunkKey<N>) -> Option<usize>,
+    ) -> Vec<ChunkPartition<N, Bldr::Chunk>> {
+        let mut partitions: Vec<_> = (0..num_partitions)
+            .map(|_| ChunkPartition::default())
+            .collect();
+
+        let Self {
+            indexer,
+            storage,
+            builder,
+            ..
+        } = self;
+        for chunk_min in indexer.chunk_mins_for_extent(extent) {
+            let key = ChunkKey::new(lod, chunk_min);
+            if let Some(i) = partition_of(key) {
+                let chunk = storage.pop(key).unwrap_or_else(|| {
+                    builder.new_vacant(key, indexer.extent_for_chunk_with_min(chunk_min))
+                });
+                partitions[i].chunks.push((key, chunk));
+            }
+        }
+
+        partitions
+    }
+
+    /// Like `split_chunks`, but with one partition for each `checkerboard_color`, so there are `2^D` partitions for a
+    /// `D`-dimensional map.
+    pub fn split_chunks_checkerboard(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+    ) -> Vec<ChunkPartition<N, Bldr::Chunk>> {
+        let num_colors = 1 << PointN::<N>::basis().len();
+        let chunk_shape = self.chunk_shape();
+
+        self.split_chunks(lod, extent, num_colors, |key| {
+            Some(checkerboard_color(chunk_shape, key.minimum))
+        })
+    }
+
+    /// Writes all of the chunks in `partitions` back into the map.
+    pub fn merge_chunks(
+        &mut self,
+        partitions: impl IntoIterator<Item = ChunkPartition<N, Bldr::Chunk>>,
+    ) {
+        for partition in partitions.into_iter() {
+            for (key, chunk) in partition.chunks.into_iter() {
+                self.storage.write(key, chunk);
+            }
+        }
+    }
+}
+
+/// The color of the chunk at `chunk_min` in a checkerboard of chunks with shape `chunk_shape`. There are `2^D` colors in `D`
+/// dimensions, and no two chunks of the same color are adjacent, even diagonally.
+pub fn checkerboard_color<N>(chunk_shape: PointN<N>, chunk_min: PointN<N>) -> usize
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    let chunk_coords = chunk_min.vector_div_floor(chunk_shape);
+
+    (0..PointN::<N>::basis().len())
+        .map(|i| ((chunk_coords.at(i) & 1) as usize) << i)
+        .sum()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    const CHUNK_SHAPE: Point3i = PointN([4; 3]);
+
+    #[test]
+    fn checkerboard_partitions_have_no_adjacent_chunks() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 0);
+        let mut map = builder.build_with_hash_map_storage();
+
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+        let partitions = map.split_chunks_checkerboard(0, &extent);
+        assert_eq!(partitions.len(), 8);
+
+        let mut num_chunks = 0;
+        for partition in partitions.iter() {
+            assert_eq!(partition.len(), 8);
+            num_chunks += partition.len();
+            for key in partition.keys() {
+                for offset in Point3i::MOORE_OFFSETS.iter() {
+                    let neighbor = ChunkKey::new(0, key.minimum + *offset * CHUNK_SHAPE);
+                    assert!(!partition.keys().any(|k| *k == neighbor));
+                }
+            }
+        }
+        assert_eq!(num_chunks, 64);
+    }
+
+    #[test]
+    fn mutate_partitions_on_separate_threads() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 0);
+        let mut map = builder.build_with_hash_map_storage();
+        map.fill_extent(
+            0,
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)),
+            5,
+        );
+
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let partitions = map.split_chunks(0, &extent, 2, |key| {
+            if key.minimum.x() < 8 {
+                Some(0)
+            } else {
+                Some(1)
+            }
+        });
+        assert!(map.storage().is_empty());
+
+        let workers: Vec<_> = partitions
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut partition)| {
+                std::thread::spawn(move || {
+                    for (_key, chunk) in partition.chunks.iter_mut() {
+                        let chunk_extent = *chunk.extent();
+                        chunk.for_each_mut(&chunk_extent, |_p: Point3i, value| {
+                            *value += 10 * (i as i32 + 1)
+                        });
+                    }
+
+                    partition
+                })
+            })
+            .collect();
+        map.merge_chunks(workers.into_iter().map(|worker| worker.join().unwrap()));
+
+        assert_eq!(map.storage().len(), 64);
+        assert_eq!(map.clone_point(0, Point3i::ZERO), 15);
+        assert_eq!(map.clone_point(0, PointN([4, 0, 0])), 10);
+        assert_eq!(map.clone_point(0, PointN([15, 15, 15])), 20);
+    }
+
+    #[test]
+    fn unpartitioned_chunks_stay_in_the_map() {
+        let builder = ChunkMapBuilder3x1::new(CHUNK_SHAPE, 0);
+        let mut map = builder.build_with_hash_map_storage();
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        map.fill_extent(0, &extent, 1);
+
+        let partitions = map.split_chunks(0, &extent, 1, |key| {
+            if checkerboard_color(CHUNK_SHAPE, key.minimum) == 0 {
+                Some(0)
+            } else {
+                None
+            }
+        });
+        assert_eq!(partitions[0].len(), 1);
+        assert_eq!(map.storage().len(), 7);
+
+        map.merge_chunks(partitions);
+        assert_eq!(map.storage().len(), 8);
+    }
+}
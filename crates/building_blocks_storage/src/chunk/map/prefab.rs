@@ -0,0 +1,356 @@
+//! Stamping prefabricated structures into a `ChunkMap3`.
+//!
+//! A `Prefab` is an array of voxels with an anchor point and an optional palette remapping. It can be stamped into a map at
+//! any position with any `OrthoTransform3`, using a `StampMerge` policy to decide how each stamped voxel combines with the
+//! voxel that's already in the map:
+//!
+//! - `Replace`: the prefab voxel always wins
+//! - `KeepSolid`: the prefab only fills voxels of the map that are empty
+//! - `SkipEmpty`: empty prefab voxels (like the air around a tree) don't overwrite the map
+//! - `BlendSdf`: combines signed distances with a `CsgOperation`, e.g. a (smooth) union
+//!
+//! Stamping goes through a `ChunkMapEditor`, so it returns the keys of the chunks that changed.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, ChunkMapEditor, Prefab, Replace};
+//!
+//! // A 1x3x1 "tree" with a trunk (1) and some leaves (2), anchored at the bottom of its trunk.
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([1, 3, 1]));
+//! let tree = Array3x1::fill_with(extent, |p| if p.y() < 2 { 1u8 } else { 2 });
+//! let prefab = Prefab::new(tree, Point3i::ZERO);
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0u8);
+//! let mut map = builder.build_with_hash_map_storage();
+//! let mut editor = ChunkMapEditor::new(&mut map, 0);
+//!
+//! // Place a tree lying on its side.
+//! let position = PointN([5, 5, 5]);
+//! let modified = editor.stamp(&prefab, position, OrthoTransform3::rotate_270(Axis3::Z), &Replace);
+//! assert_eq!(modified.len(), 1);
+//!
+//! // An autumn tree: the same prefab with different leaves. Any `Fn(&T, &T) -> T` is also a merge policy.
+//! let autumn_tree = prefab.clone().with_remap(2, 3);
+//! let keep_trunks = |existing: &u8, stamped: &u8| if *existing == 1 { 1 } else { *stamped };
+//! editor.stamp(&autumn_tree, PointN([8, 5, 5]), OrthoTransform3::IDENTITY, &keep_trunks);
+//!
+//! let lod0 = map.lod_view(0);
+//! assert_eq!(lod0.get(PointN([5, 5, 5])), 1);
+//! assert_eq!(lod0.get(PointN([7, 5, 5])), 2);
+//! assert_eq!(lod0.get(PointN([8, 7, 5])), 3);
+//! ```
+
+use crate::{
+    sdf::CsgOperation, Array3x1, ChunkKey3, ChunkMapBuilder, ChunkMapEditor, ChunkWriteStorage,
+    GetRef, IsEmpty, SmallKeyHashSet,
+};
+
+use building_blocks_core::prelude::*;
+
+/// A reusable structure that can be stamped into a map.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Prefab<T> {
+    /// The voxels of the structure.
+    pub voxels: Array3x1<T>,
+    /// The point of `voxels` that is placed at the stamp position. Rotations and mirrors are also about this point.
+    pub anchor: Point3i,
+    /// Pairs of `(from, to)` values. Any voxel equal to `from` is stamped as `to`.
+    pub palette: Vec<(T, T)>,
+}
+
+impl<T> Prefab<T> {
+    pub fn new(voxels: Array3x1<T>, anchor: Point3i) -> Self {
+        Self {
+            voxels,
+            anchor,
+            palette: Vec::new(),
+        }
+    }
+
+    /// Stamps voxels equal to `from` as `to` instead.
+    pub fn with_remap(mut self, from: T, to: T) -> Self {
+        self.palette.push((from, to));
+
+        self
+    }
+
+    /// The extent covered by this prefab when it's stamped at `position` with `transform`.
+    pub fn placed_extent(&self, position: Point3i, transform: OrthoTransform3) -> Extent3i {
+        transform.transform_extent(&(*self.voxels.extent() - self.anchor)) + position
+    }
+
+    /// The point of `voxels` that is stamped at `p` when this prefab is stamped at `position` with `transform`.
+    pub fn source_point(
+        &self,
+        p: Point3i,
+        position: Point3i,
+        transform: OrthoTransform3,
+    ) -> Point3i {
+        transform.inverse().transform_point(p - position) + self.anchor
+    }
+}
+
+impl<T> Prefab<T>
+where
+    T: Clone + PartialEq,
+{
+    /// The value stamped for the prefab voxel `value`, after applying the palette.
+    pub fn remap(&self, value: &T) -> T {
+        self.palette
+            .iter()
+            .find(|(from, _)| from == value)
+            .map(|(_, to)| to.clone())
+            .unwrap_or_else(|| value.clone())
+    }
+}
+
+/// Decides how a stamped voxel combines with the voxel that's already in the map.
+pub trait StampMerge<T> {
+    /// The new value of a voxel that was `existing` before `stamped` was stamped on it.
+    fn merge(&self, existing: &T, stamped: &T) -> T;
+}
+
+/// Every stamped voxel overwrites the map.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Replace;
+
+impl<T: Clone> StampMerge<T> for Replace {
+    fn merge(&self, _existing: &T, stamped: &T) -> T {
+        stamped.clone()
+    }
+}
+
+/// Voxels of the map that aren't empty are kept, so the prefab only fills empty space.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct KeepSolid;
+
+impl<T: Clone + IsEmpty> StampMerge<T> for KeepSolid {
+    fn merge(&self, existing: &T, stamped: &T) -> T {
+        if existing.is_empty() {
+            stamped.clone()
+        } else {
+            existing.clone()
+        }
+    }
+}
+
+/// Empty voxels of the prefab don't overwrite the map.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SkipEmpty;
+
+impl<T: Clone + IsEmpty> StampMerge<T> for SkipEmpty {
+    fn merge(&self, existing: &T, stamped: &T) -> T {
+        if stamped.is_empty() {
+            existing.clone()
+        } else {
+            stamped.clone()
+        }
+    }
+}
+
+/// Combines the signed distances of the map (`a`) and the prefab (`b`) with a `CsgOperation`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendSdf(pub CsgOperation);
+
+impl<T> StampMerge<T> for BlendSdf
+where
+    T: Copy + From<f32> + Into<f32>,
+{
+    fn merge(&self, existing: &T, stamped: &T) -> T {
+        let old_dist = (*existing).into();
+        let new_dist = self.0.apply(old_dist, (*stamped).into());
+        if new_dist == old_dist {
+            // Avoid round-tripping through the quantized representation, which isn't always lossless.
+            *existing
+        } else {
+            T::from(new_dist)
+        }
+    }
+}
+
+impl<T, F> StampMerge<T> for F
+where
+    F: Fn(&T, &T) -> T,
+{
+    fn merge(&self, existing: &T, stamped: &T) -> T {
+        (self)(existing, stamped)
+    }
+}
+
+impl<'a, T, Bldr, Store> ChunkMapEditor<'a, T, Bldr, Store>
+where
+    T: Clone + PartialEq,
+    Bldr: ChunkMapBuilder<[i32; 3], T, Chunk = Array3x1<T>>,
+    Store: ChunkWriteStorage<[i32; 3], Array3x1<T>>,
+{
+    /// Stamps `prefab` so that its anchor lands on `position`, after rotating and/or mirroring it about the anchor with
+    /// `transform`. Each voxel is combined with the map using `merge`.
+    ///
+    /// Returns the keys of all chunks that were changed.
+    pub fn stamp(
+        &mut self,
+        prefab: &Prefab<T>,
+        position: Point3i,
+        transform: OrthoTransform3,
+        merge: &impl StampMerge<T>,
+    ) -> SmallKeyHashSet<ChunkKey3> {
+        self.edit_extent(&prefab.placed_extent(position, transform), |p, voxel| {
+            let src = prefab.source_point(p, position, transform);
+            let stamped = prefab.remap(prefab.voxels.get_ref(src));
+            let new_value = merge.merge(voxel, &stamped);
+            if new_value != *voxel {
+                *voxel = new_value;
+
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct Voxel(u8);
+
+    impl IsEmpty for Voxel {
+        fn is_empty(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    fn l_shape() -> Prefab<Voxel> {
+        // An L made of 3 voxels in the XY plane, with the corner at the anchor.
+        let extent = Extent3i::from_min_and_shape(PointN([-1, -1, 0]), PointN([3, 3, 1]));
+        let voxels = Array3x1::fill_with(extent, |p| {
+            Voxel(match (p.x(), p.y()) {
+                (0, 0) => 1,
+                (1, 0) => 2,
+                (0, 1) => 3,
+                _ => 0,
+            })
+        });
+
+        Prefab::new(voxels, Point3i::ZERO)
+    }
+
+    #[test]
+    fn stamp_rotated_about_anchor() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Voxel(0));
+        let mut map = builder.build_with_hash_map_storage();
+        let prefab = l_shape();
+
+        let position = PointN([4, 4, 4]);
+        let transform = OrthoTransform3::rotate_90(Axis3::Z);
+        assert_eq!(
+            prefab.placed_extent(position, transform),
+            Extent3i::from_min_and_shape(PointN([3, 3, 4]), PointN([3, 3, 1]))
+        );
+
+        let modified =
+            ChunkMapEditor::new(&mut map, 0).stamp(&prefab, position, transform, &Replace);
+        // The voxel at (3, 4, 4) is one chunk over, along -X.
+        let expected: SmallKeyHashSet<_> = [
+            ChunkKey::new(0, PointN([4, 4, 4])),
+            ChunkKey::new(0, PointN([0, 4, 4])),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(modified, expected);
+
+        let lod0 = map.lod_view(0);
+        assert_eq!(lod0.get(PointN([4, 4, 4])), Voxel(1));
+        // +X rotates to +Y, and +Y rotates to -X.
+        assert_eq!(lod0.get(PointN([4, 5, 4])), Voxel(2));
+        assert_eq!(lod0.get(PointN([3, 4, 4])), Voxel(3));
+        assert_eq!(lod0.get(PointN([5, 4, 4])), Voxel(0));
+    }
+
+    #[test]
+    fn merge_policies() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Voxel(0));
+        let mut map = builder.build_with_hash_map_storage();
+        let filled = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([1, 2, 1]));
+        map.lod_view_mut(0).fill_extent(&filled, Voxel(9));
+
+        let prefab = l_shape().with_remap(Voxel(3), Voxel(4));
+        let mut editor = ChunkMapEditor::new(&mut map, 0);
+
+        // Only (1, 0, 0) is empty in the map.
+        editor.stamp(
+            &prefab,
+            Point3i::ZERO,
+            OrthoTransform3::IDENTITY,
+            &KeepSolid,
+        );
+        {
+            let lod0 = map.lod_view(0);
+            assert_eq!(lod0.get(PointN([0, 0, 0])), Voxel(9));
+            assert_eq!(lod0.get(PointN([1, 0, 0])), Voxel(2));
+            assert_eq!(lod0.get(PointN([0, 1, 0])), Voxel(9));
+        }
+
+        let mut editor = ChunkMapEditor::new(&mut map, 0);
+        editor.stamp(
+            &prefab,
+            Point3i::ZERO,
+            OrthoTransform3::IDENTITY,
+            &SkipEmpty,
+        );
+        {
+            let lod0 = map.lod_view(0);
+            assert_eq!(lod0.get(PointN([0, 0, 0])), Voxel(1));
+            assert_eq!(lod0.get(PointN([0, 1, 0])), Voxel(4));
+        }
+
+        // Stamping with `Replace` also writes the empty voxels around the L.
+        let mut editor = ChunkMapEditor::new(&mut map, 0);
+        editor.stamp(
+            &prefab,
+            PointN([1, 1, 0]),
+            OrthoTransform3::IDENTITY,
+            &Replace,
+        );
+        assert_eq!(map.lod_view(0).get(PointN([0, 1, 0])), Voxel(0));
+    }
+
+    #[test]
+    fn blend_sdf_takes_union() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Sd8::ONE);
+        let mut map = builder.build_with_hash_map_storage();
+        map.lod_view_mut(0).fill_extent(
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::ONES),
+            Sd8::from(-0.5),
+        );
+
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([2, 1, 1]));
+        let voxels =
+            Array3x1::fill_with(extent, |p| Sd8::from(if p.x() == 0 { 0.5 } else { -1.0 }));
+        let prefab = Prefab::new(voxels, Point3i::ZERO);
+
+        let modified = ChunkMapEditor::new(&mut map, 0).stamp(
+            &prefab,
+            Point3i::ZERO,
+            OrthoTransform3::IDENTITY,
+            &BlendSdf(CsgOperation::Union),
+        );
+        assert_eq!(modified.len(), 1);
+
+        let lod0 = map.lod_view(0);
+        assert_eq!(lod0.get(PointN([0, 0, 0])), Sd8::from(-0.5));
+        assert_eq!(lod0.get(PointN([1, 0, 0])), Sd8::from(-1.0));
+    }
+}
@@ -1,12 +1,15 @@
+pub mod kernel;
 pub mod point;
 pub mod sdf_mean;
 
+pub use kernel::*;
 pub use point::*;
 pub use sdf_mean::*;
 
-use crate::{prelude::*, ArrayIndexer, ChunkMap, ChunkMap3, LockStepArrayForEach};
+use crate::{prelude::*, ArrayIndexer, ChunkMap, ChunkMap3, LockStepArrayForEach, SmallKeyHashSet};
 
 use building_blocks_core::prelude::*;
+use core::hash::Hash;
 use std::borrow::Borrow;
 
 pub trait ChunkDownsampler<N, T, Src, Dst> {
@@ -32,6 +35,8 @@ where
     ) where
         Samp: ChunkDownsampler<N, T, Bldr::Chunk, Bldr::Chunk>,
     {
+        trace_span!("downsample_chunk", src_lod = src_chunk_key.lod, dst_lod);
+
         // PERF: Unforunately we have to remove the chunk and put it back to satisfy the borrow checker.
         if let Some(src_chunk) = self.pop_chunk(src_chunk_key) {
             self.downsample_external_chunk(sampler, src_chunk_key, &src_chunk, dst_lod);
@@ -62,6 +67,44 @@ where
         sampler.downsample(src_chunk, dst_chunk, dst.dst_offset, lod_delta);
     }
 
+    /// Downsamples only the ancestors of the `edited_chunks` from LOD0, up to destination level `num_lods - 1`. This is much
+    /// cheaper than downsampling all chunks after every edit. A LOD0 chunk that was removed also counts as edited, since its
+    /// destination samples become ambient.
+    ///
+    /// Returns the keys of all chunks that were updated at LOD1 and above, e.g. so they can be remeshed.
+    pub fn downsample_edited_chunks<Samp>(
+        &mut self,
+        sampler: &Samp,
+        edited_chunks: impl IntoIterator<Item = ChunkKey<N>>,
+        num_lods: u8,
+    ) -> Vec<ChunkKey<N>>
+    where
+        ChunkKey<N>: Hash + Eq,
+        Samp: ChunkDownsampler<N, T, Bldr::Chunk, Bldr::Chunk>,
+    {
+        trace_span!("downsample_edited_chunks", num_lods);
+
+        let chunk_shape = self.chunk_shape();
+
+        let mut updated_keys = Vec::new();
+        // Each level only needs to be resampled where its sources changed.
+        let mut src_keys: SmallKeyHashSet<ChunkKey<N>> = edited_chunks.into_iter().collect();
+        for dst_lod in 1..num_lods {
+            let mut dst_keys = SmallKeyHashSet::default();
+            for src_key in src_keys.into_iter() {
+                assert_eq!(src_key.lod + 1, dst_lod, "Edited chunks must be in LOD0");
+
+                let dst = DownsampleDestination::for_source_chunk(chunk_shape, src_key.minimum, 1);
+                self.downsample_chunk(sampler, src_key, dst_lod);
+                dst_keys.insert(ChunkKey::new(dst_lod, dst.dst_chunk_min));
+            }
+            updated_keys.extend(dst_keys.iter().cloned());
+            src_keys = dst_keys;
+        }
+
+        updated_keys
+    }
+
     /// Fill the destination samples with the ambient value.
     pub fn downsample_ambient_chunk(&mut self, src_chunk_key: ChunkKey<N>, dst_lod: u8) {
         assert!(dst_lod > src_chunk_key.lod);
@@ -101,6 +144,8 @@ where
     ) where
         Samp: ChunkDownsampler<[i32; 3], T, Bldr::Chunk, Bldr::Chunk>,
     {
+        trace_span!("downsample_chunks_with_index");
+
         let chunk_shape = self.chunk_shape();
         let chunk_log2 = chunk_shape.map_components_unary(|c| c.trailing_zeros() as i32);
 
@@ -141,6 +186,8 @@ where
         Samp: ChunkDownsampler<[i32; 3], T, Bldr::Chunk, Bldr::Chunk>
             + ChunkDownsampler<[i32; 3], T, Lod0ChBorrow, Bldr::Chunk>,
     {
+        trace_span!("downsample_chunks_with_index");
+
         let chunk_shape = self.chunk_shape();
         let chunk_log2 = chunk_shape.map_components_unary(|c| c.trailing_zeros() as i32);
 
@@ -237,7 +284,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Sd8, SdfMeanDownsampler};
+    use crate::{PointDownsampler, Sd8, SdfMeanDownsampler};
 
     #[test]
     fn downsample_destination_for_one_level_up() {
@@ -323,4 +370,53 @@ mod tests {
             &lod0_extent,
         );
     }
+
+    #[test]
+    fn downsample_edited_chunks_matches_downsampling_everything() {
+        let num_lods = 3;
+        let chunk_shape = Point3i::fill(16);
+        let lod0_extent =
+            Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)) * chunk_shape;
+        let edit_extent = Extent3i::from_min_and_shape(Point3i::fill(20), Point3i::fill(4));
+
+        let build_map = |edit: bool| {
+            let builder = ChunkMapBuilder3x1::new(chunk_shape, 0u8);
+            let mut map = builder.build_with_hash_map_storage();
+            map.fill_extent(0, &lod0_extent, 1);
+            if edit {
+                map.fill_extent(0, &edit_extent, 2);
+            }
+            let index = OctreeChunkIndex::index_chunk_map(9, num_lods, &map);
+            map.downsample_chunks_with_index(&index, &PointDownsampler, &lod0_extent);
+
+            map
+        };
+
+        let mut map = build_map(false);
+        map.fill_extent(0, &edit_extent, 2);
+        let mut updated_keys = map.downsample_edited_chunks(
+            &PointDownsampler,
+            vec![ChunkKey::new(0, chunk_shape)],
+            num_lods,
+        );
+        updated_keys.sort_by_key(|k| k.lod);
+        assert_eq!(
+            updated_keys,
+            vec![
+                ChunkKey::new(1, Point3i::ZERO),
+                ChunkKey::new(2, Point3i::ZERO)
+            ]
+        );
+
+        let expected_map = build_map(true);
+        for lod in 0..num_lods {
+            for key in expected_map.storage().chunk_keys().filter(|k| k.lod == lod) {
+                assert!(
+                    map.get_chunk(*key) == expected_map.get_chunk(*key),
+                    "key = {:?}",
+                    key
+                );
+            }
+        }
+    }
 }
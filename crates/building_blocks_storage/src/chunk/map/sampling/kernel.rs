@@ -0,0 +1,217 @@
+use super::chunk_downsample_for_each;
+use crate::{
+    ArrayForEach, ArrayIndexer, ChunkDownsampler, Get, GetMut, IndexedArray, Local, Stride,
+};
+
+use building_blocks_core::prelude::*;
+
+/// Reduces all of the samples in one `2x2x2` region (assuming `lod_delta=1`) to a single value. This is the easiest way to
+/// write a custom downsampler; wrap it in a `KernelDownsampler` to use it as a `ChunkDownsampler`.
+pub trait Downsampler<T> {
+    /// `samples` is never empty.
+    fn reduce(&self, samples: &[T]) -> T;
+}
+
+/// Adapts any `Downsampler` into a `ChunkDownsampler`.
+pub struct KernelDownsampler<D>(pub D);
+
+impl<N, Src, Dst, T, D> ChunkDownsampler<N, T, Src, Dst> for KernelDownsampler<D>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    ArrayForEach<N>: Clone,
+    Src: Get<Stride, Item = T> + IndexedArray<N>,
+    Dst: for<'r> GetMut<'r, Stride, Item = &'r mut T>,
+    D: Downsampler<T>,
+{
+    fn downsample(&self, src_chunk: &Src, dst_chunk: &mut Dst, dst_min: Local<N>, lod_delta: u8) {
+        downsample_kernels(&self.0, src_chunk, dst_chunk, dst_min, lod_delta)
+    }
+}
+
+/// A `Downsampler` that takes the mean of each region, e.g. for density fields. Unlike the `SdfMeanDownsampler`, the values are
+/// not renormalized.
+pub struct MeanDownsampler;
+
+impl<T> Downsampler<T> for MeanDownsampler
+where
+    T: Clone + From<f32>,
+    f32: From<T>,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        let sum: f32 = samples.iter().cloned().map(f32::from).sum();
+
+        T::from(sum / samples.len() as f32)
+    }
+}
+
+/// A `Downsampler` that takes the most common value in each region, e.g. for material or label fields, where values can't be
+/// averaged. Ties go to the value that comes first in array order.
+pub struct MajorityDownsampler;
+
+impl<T> Downsampler<T> for MajorityDownsampler
+where
+    T: Clone + PartialEq,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        let mut majority = &samples[0];
+        let mut majority_count = 0;
+        for (i, candidate) in samples.iter().enumerate() {
+            if samples[..i].contains(candidate) {
+                // Already counted.
+                continue;
+            }
+            let count = samples[i..].iter().filter(|s| *s == candidate).count();
+            if count > majority_count {
+                majority = candidate;
+                majority_count = count;
+            }
+        }
+
+        majority.clone()
+    }
+}
+
+/// A `Downsampler` that takes the maximum value in each region, e.g. so any occupied voxel makes the downsampled voxel occupied.
+pub struct MaxDownsampler;
+
+impl<T> Downsampler<T> for MaxDownsampler
+where
+    T: Clone + PartialOrd,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        samples
+            .iter()
+            .skip(1)
+            .fold(&samples[0], |max, s| if *s > *max { s } else { max })
+            .clone()
+    }
+}
+
+/// A `Downsampler` that takes the minimum value in each region, e.g. so the downsampled voxel is only occupied if it's fully
+/// occupied.
+pub struct MinDownsampler;
+
+impl<T> Downsampler<T> for MinDownsampler
+where
+    T: Clone + PartialOrd,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        samples
+            .iter()
+            .skip(1)
+            .fold(&samples[0], |min, s| if *s < *min { s } else { min })
+            .clone()
+    }
+}
+
+macro_rules! impl_chunk_downsampler_for_kernel {
+    ($downsampler:ty) => {
+        impl<N, Src, Dst, T> ChunkDownsampler<N, T, Src, Dst> for $downsampler
+        where
+            N: ArrayIndexer<N>,
+            PointN<N>: IntegerPoint<N>,
+            ArrayForEach<N>: Clone,
+            Src: Get<Stride, Item = T> + IndexedArray<N>,
+            Dst: for<'r> GetMut<'r, Stride, Item = &'r mut T>,
+            $downsampler: Downsampler<T>,
+        {
+            fn downsample(
+                &self,
+                src_chunk: &Src,
+                dst_chunk: &mut Dst,
+                dst_min: Local<N>,
+                lod_delta: u8,
+            ) {
+                downsample_kernels(self, src_chunk, dst_chunk, dst_min, lod_delta)
+            }
+        }
+    };
+}
+
+impl_chunk_downsampler_for_kernel!(MeanDownsampler);
+impl_chunk_downsampler_for_kernel!(MajorityDownsampler);
+impl_chunk_downsampler_for_kernel!(MaxDownsampler);
+impl_chunk_downsampler_for_kernel!(MinDownsampler);
+
+fn downsample_kernels<N, Src, Dst, T>(
+    downsampler: &impl Downsampler<T>,
+    src_chunk: &Src,
+    dst_chunk: &mut Dst,
+    dst_min: Local<N>,
+    lod_delta: u8,
+) where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    ArrayForEach<N>: Clone,
+    Src: Get<Stride, Item = T> + IndexedArray<N>,
+    Dst: for<'r> GetMut<'r, Stride, Item = &'r mut T>,
+{
+    let chunk_shape = src_chunk.extent().shape; // Doesn't matter which chunk we choose, they should have the same shape.
+
+    debug_assert!(lod_delta > 0);
+    let lod_delta = lod_delta as i32;
+
+    let src_shape_per_point = PointN::fill(1 << lod_delta);
+    let kernel_for_each = ArrayForEach::new_local_unchecked(
+        chunk_shape,
+        Local(PointN::ZERO),
+        ExtentN::from_min_and_shape(PointN::ZERO, src_shape_per_point),
+    );
+
+    // Reuse the same buffer for every kernel.
+    let mut samples = Vec::with_capacity(src_shape_per_point.volume() as usize);
+
+    let for_each = chunk_downsample_for_each(chunk_shape, dst_min, lod_delta);
+    N::for_each_lockstep_unchecked(for_each, |_p, (s_dst, s_src)| {
+        samples.clear();
+        N::for_each(kernel_for_each.clone(), |_p, neighbor_offset| {
+            samples.push(src_chunk.get(s_src + neighbor_offset));
+        });
+        *dst_chunk.get_mut(s_dst) = downsampler.reduce(&samples);
+    });
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Array3x1, ForEach};
+
+    #[test]
+    fn kernel_reductions() {
+        let samples = [3u8, 1, 3, 2, 1, 3, 0, 2];
+        assert_eq!(MajorityDownsampler.reduce(&samples[..]), 3);
+        assert_eq!(MajorityDownsampler.reduce(&[1u8, 2, 2, 1][..]), 1);
+        assert_eq!(MaxDownsampler.reduce(&samples[..]), 3);
+        assert_eq!(MinDownsampler.reduce(&samples[..]), 0);
+        assert_eq!(MeanDownsampler.reduce(&[1.0f32, 2.0, 3.0, 6.0][..]), 3.0);
+    }
+
+    #[test]
+    fn max_downsample_keeps_any_occupied_voxel() {
+        let lod_delta = 1;
+        let chunk_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+
+        // Only one point in each 2x2x2 region is occupied, and it's never the one that the `PointDownsampler` would choose.
+        let src_chunk = Array3x1::fill_with(chunk_extent, |p| {
+            p.x() % 2 == 1 && p.y() % 2 == 1 && p.z() % 2 == 1
+        });
+
+        let mut dst_chunk = Array3x1::fill(chunk_extent, false);
+        let dst_min = Local(Point3i::ZERO);
+        MaxDownsampler.downsample(&src_chunk, &mut dst_chunk, dst_min, lod_delta);
+
+        let dst_extent = chunk_extent >> lod_delta;
+        dst_chunk.for_each(&dst_extent, |p: Point3i, x| assert!(x, "p = {:?}", p));
+
+        MinDownsampler.downsample(&src_chunk, &mut dst_chunk, dst_min, lod_delta);
+        dst_chunk.for_each(&dst_extent, |p: Point3i, x| assert!(!x, "p = {:?}", p));
+    }
+}
@@ -1,5 +1,7 @@
 use super::chunk_downsample_for_each;
-use crate::{ArrayIndexer, ChunkDownsampler, Get, GetMut, IndexedArray, Local, Stride};
+use crate::{
+    ArrayIndexer, ChunkDownsampler, Downsampler, Get, GetMut, IndexedArray, Local, Stride,
+};
 
 use building_blocks_core::prelude::*;
 
@@ -24,6 +26,16 @@ where
     }
 }
 
+/// Selects the first sample, i.e. the one with the least coordinates in the region.
+impl<T> Downsampler<T> for PointDownsampler
+where
+    T: Clone,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        samples[0].clone()
+    }
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -1,18 +1,39 @@
+#[cfg(feature = "rayon")]
+mod background_compression;
 pub mod compressible;
 pub mod compressible_reader;
+pub mod concurrent;
+pub mod dirty_tracking;
 pub mod hash_map;
+pub mod mapped;
+pub mod occupancy_tracking;
+pub mod shared;
+pub mod stats;
+pub mod uniform_folding;
+pub mod versioned;
 
 pub use compressible::*;
 pub use compressible_reader::*;
+pub use concurrent::*;
+pub use dirty_tracking::*;
 pub use hash_map::*;
+pub use mapped::*;
+pub use occupancy_tracking::*;
+pub use shared::*;
+pub use stats::*;
+pub use uniform_folding::*;
+pub use versioned::*;
+
+use crate::Chunk;
 
 use building_blocks_core::prelude::*;
 
 use auto_impl::auto_impl;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 /// The key for a chunk at a particular level of detail.
-#[derive(Clone, Copy, Debug, Deserialize, Hash, Eq, PartialEq, Serialize)]
+#[derive(Debug, Deserialize, Hash, Eq, PartialEq, Serialize)]
 pub struct ChunkKey<N> {
     /// The minimum point of the chunk.
     pub minimum: PointN<N>,
@@ -25,6 +46,21 @@ pub type ChunkKey2 = ChunkKey<[i32; 2]>;
 /// A 3-dimensional `ChunkKey`.
 pub type ChunkKey3 = ChunkKey<[i32; 3]>;
 
+// Not derived, because the derives would require `N: Copy`, which generic code only knows through `PointN<N>: IntegerPoint<N>`.
+impl<N> Clone for ChunkKey<N>
+where
+    PointN<N>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            minimum: self.minimum.clone(),
+            lod: self.lod,
+        }
+    }
+}
+
+impl<N> Copy for ChunkKey<N> where PointN<N>: Copy {}
+
 impl<N> ChunkKey<N> {
     pub fn new(lod: u8, chunk_minimum: PointN<N>) -> Self {
         Self {
@@ -34,11 +70,30 @@ impl<N> ChunkKey<N> {
     }
 }
 
+impl<N> ChunkKey<N>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    /// The key of this chunk after every chunk of the map is moved by `lod0_delta`, which is in LOD0 coordinates.
+    pub fn translated(self, lod0_delta: PointN<N>) -> Self {
+        Self::new(self.lod, self.minimum + (lod0_delta >> self.lod as i32))
+    }
+}
+
 /// Methods for reading chunks from storage.
 #[auto_impl(&, &mut)]
 pub trait ChunkReadStorage<N, Ch> {
     /// Borrow the chunk at `key`.
     fn get(&self, key: ChunkKey<N>) -> Option<&Ch>;
+
+    /// Borrow the value of every point in the chunk at `key`, if the storage folded that chunk into a single value, like
+    /// `UniformChunkFolding` does. `get` returns `None` for folded chunks.
+    fn get_uniform(&self, _key: ChunkKey<N>) -> Option<&Ch::Data>
+    where
+        Ch: Chunk,
+    {
+        None
+    }
 }
 
 /// Methods for writing chunks from storage.
@@ -67,6 +122,15 @@ pub trait ChunkWriteStorage<N, Ch> {
     fn pop(&mut self, key: ChunkKey<N>) -> Option<Ch>;
 }
 
+/// Methods for reading chunks asynchronously from some external source, like a database or a network connection. This lets a
+/// `ChunkMap` load missing chunks on demand without blocking, e.g. with `ChunkMap::get_or_load_chunk_async`.
+pub trait AsyncChunkReader<N, Ch> {
+    type Error;
+
+    /// Read the chunk at `key`. Returns `None` if the source doesn't have that chunk.
+    fn read_chunk(&self, key: ChunkKey<N>) -> BoxFuture<'_, Result<Option<Ch>, Self::Error>>;
+}
+
 #[auto_impl(&, &mut)]
 pub trait IterChunkKeys<'a, N>
 where
@@ -76,3 +140,10 @@ where
 
     fn chunk_keys(&'a self) -> Self::Iter;
 }
+
+/// Storage that can move all of its chunks to new keys at once, without reading or writing any voxels. See
+/// `ChunkMap::translate_keys`.
+pub trait TranslateChunkKeys<N> {
+    /// Moves every chunk to `key.translated(lod0_delta)`, including the extent of the chunk's array.
+    fn translate_chunk_keys(&mut self, lod0_delta: PointN<N>);
+}
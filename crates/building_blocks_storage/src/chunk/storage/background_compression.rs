@@ -0,0 +1,217 @@
+use crate::{ChunkKey, Compressed, Compression, SmallKeyHashMap};
+
+use core::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+/// Compresses chunks for a `CompressibleChunkStorage` on a `rayon` thread pool, so the thread that evicts a chunk doesn't have
+/// to wait for the compression.
+///
+/// Each job compresses a copy of the chunk, and the original stays cached until the finished job is collected. A job is
+/// cancelled if its chunk is accessed mutably before then, since the compressed copy would be stale.
+pub(crate) struct BackgroundCompression<N, Compr>
+where
+    Compr: Compression,
+{
+    pool: Arc<rayon::ThreadPool>,
+    finished: Arc<FinishedJobs<N, Compr>>,
+    pending: SmallKeyHashMap<ChunkKey<N>, u64>,
+    next_job: u64,
+    spawn_job: SpawnJobFn<N, Compr>,
+}
+
+type SpawnJobFn<N, Compr> = fn(
+    &rayon::ThreadPool,
+    &Arc<FinishedJobs<N, Compr>>,
+    ChunkKey<N>,
+    u64,
+    &Compr,
+    &<Compr as Compression>::Data,
+);
+
+struct FinishedJobs<N, Compr> {
+    queue: Mutex<FinishedQueue<N, Compr>>,
+    none_running: Condvar,
+}
+
+struct FinishedQueue<N, Compr> {
+    jobs: Vec<(ChunkKey<N>, u64, Compressed<Compr>)>,
+    num_running: usize,
+}
+
+impl<N, Compr> BackgroundCompression<N, Compr>
+where
+    ChunkKey<N>: Clone + Eq + Hash,
+    Compr: Compression,
+{
+    pub fn new(pool: Arc<rayon::ThreadPool>) -> Self
+    where
+        N: 'static + Send,
+        Compr: 'static + Clone + Send,
+        Compr::Data: 'static + Clone + Send,
+    {
+        Self {
+            pool,
+            finished: Arc::new(FinishedJobs {
+                queue: Mutex::new(FinishedQueue {
+                    jobs: Vec::new(),
+                    num_running: 0,
+                }),
+                none_running: Condvar::new(),
+            }),
+            pending: SmallKeyHashMap::default(),
+            next_job: 0,
+            spawn_job: spawn_job::<N, Compr>,
+        }
+    }
+
+    /// The number of chunks that have been queued but not collected yet.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_pending(&self, key: &ChunkKey<N>) -> bool {
+        self.pending.contains_key(key)
+    }
+
+    /// Starts compressing a copy of `chunk`, replacing any job that's already pending for `key`.
+    pub fn queue(&mut self, key: ChunkKey<N>, compression: &Compr, chunk: &Compr::Data) {
+        let job = self.next_job;
+        self.next_job += 1;
+        self.pending.insert(key.clone(), job);
+
+        self.finished.queue.lock().unwrap().num_running += 1;
+        (self.spawn_job)(&self.pool, &self.finished, key, job, compression, chunk);
+    }
+
+    /// Returns `true` iff a job was pending for `key`. The result of a cancelled job is discarded when it finishes.
+    pub fn cancel(&mut self, key: &ChunkKey<N>) -> bool {
+        self.pending.remove(key).is_some()
+    }
+
+    /// Takes the results of all finished jobs that weren't cancelled. If `wait`, this first blocks until there are no running
+    /// jobs.
+    pub fn take_finished(&mut self, wait: bool) -> Vec<(ChunkKey<N>, Compressed<Compr>)> {
+        let finished_jobs = {
+            let mut queue = self.finished.queue.lock().unwrap();
+            if wait {
+                while queue.num_running > 0 {
+                    queue = self.finished.none_running.wait(queue).unwrap();
+                }
+            }
+            std::mem::take(&mut queue.jobs)
+        };
+
+        let pending = &mut self.pending;
+        finished_jobs
+            .into_iter()
+            .filter_map(|(key, job, compressed)| {
+                if pending.get(&key) == Some(&job) {
+                    pending.remove(&key);
+
+                    Some((key, compressed))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn spawn_job<N, Compr>(
+    pool: &rayon::ThreadPool,
+    finished: &Arc<FinishedJobs<N, Compr>>,
+    key: ChunkKey<N>,
+    job: u64,
+    compression: &Compr,
+    chunk: &Compr::Data,
+) where
+    N: 'static + Send,
+    Compr: 'static + Clone + Compression + Send,
+    Compr::Data: 'static + Clone + Send,
+{
+    let finished = finished.clone();
+    let compression = compression.clone();
+    let chunk = chunk.clone();
+    pool.spawn(move || {
+        let mut running = RunningJob {
+            finished: &finished,
+            result: None,
+        };
+        running.result = Some((key, job, compression.compress(&chunk)));
+    });
+}
+
+/// Marks a job as no longer running when dropped, even if the compression panicked, so that `take_finished(true)` can't wait
+/// forever. A job that panicked has no result and stays pending until it's cancelled or replaced.
+struct RunningJob<'a, N, Compr> {
+    finished: &'a FinishedJobs<N, Compr>,
+    result: Option<(ChunkKey<N>, u64, Compressed<Compr>)>,
+}
+
+impl<'a, N, Compr> Drop for RunningJob<'a, N, Compr> {
+    fn drop(&mut self) {
+        let mut queue = self
+            .finished
+            .queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(result) = self.result.take() {
+            queue.jobs.push(result);
+        }
+        queue.num_running -= 1;
+        if queue.num_running == 0 {
+            self.finished.none_running.notify_all();
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_core::prelude::*;
+    use std::io;
+
+    #[derive(Clone)]
+    struct PanickingCompression;
+
+    impl Compression for PanickingCompression {
+        type Data = u32;
+
+        fn compress_to_writer(
+            &self,
+            _data: &u32,
+            _compressed_bytes: impl io::Write,
+        ) -> io::Result<()> {
+            panic!("compression failed")
+        }
+
+        fn decompress_from_reader(_compressed_bytes: impl io::Read) -> io::Result<u32> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn panicking_job_stops_running() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .panic_handler(|_| {})
+            .build()
+            .unwrap();
+        let mut background = BackgroundCompression::new(Arc::new(pool));
+
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        background.queue(key, &PanickingCompression, &1);
+
+        assert!(background.take_finished(true).is_empty());
+        assert!(background.is_pending(&key));
+        assert!(background.cancel(&key));
+    }
+}
@@ -1,17 +1,31 @@
 use crate::{
-    CacheEntry, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkWriteStorage, Compressed,
-    CompressibleChunkMapReader, CompressibleChunkStorageReader, Compression, FastArrayCompression,
-    FastChannelsCompression, FromBytesCompression, IterChunkKeys, LocalChunkCache, LruCacheEntries,
-    LruCacheIntoIter, LruCacheKeys, MaybeCompressed, SmallKeyLruCache,
+    Array, CacheCounters, CacheEntry, Channels, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkMapStats,
+    ChunkWriteStorage, Compressed, CompressibleChunkMapReader, CompressibleChunkStorageReader,
+    Compression, EvictionPolicy, FastArrayCompression, FastChannelsCompression,
+    FromBytesCompression, IterChunkKeys, LocalChunkCache, LruCacheEntries, LruCacheIntoIter,
+    LruCacheKeys, MaybeCompressed, SmallKeyHashMap, SmallKeyLruCache,
 };
 
+#[cfg(feature = "rayon")]
+use super::background_compression::BackgroundCompression;
+
 use building_blocks_core::prelude::*;
 
+use core::cell::Cell;
 use core::hash::Hash;
 use slab::Slab;
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
 
 /// A two-tier chunk storage. The first tier is an LRU cache of uncompressed chunks. The second tier is a `Slab` of compressed
 /// chunks.
+///
+/// By default, chunks are only compressed when you explicitly call methods like `compress_lru`. Alternatively, you can set a
+/// `MemoryBudget` with `set_memory_budget`, and chunks will be compressed (or dropped) automatically to keep the cache within
+/// the budget.
+///
+/// With the `rayon` feature, `enable_background_compression` moves the compression work onto a thread pool, so a burst of
+/// writes doesn't stall the calling thread.
 pub struct CompressibleChunkStorage<N, Compr>
 where
     Compr: Compression,
@@ -19,6 +33,10 @@ where
     pub(crate) cache: SmallKeyLruCache<ChunkKey<N>, Compr::Data, CompressedLocation>,
     pub(crate) compression: Compr,
     pub(crate) compressed: CompressedChunks<Compr>,
+    pub(crate) cache_counters: CacheCounters,
+    budget: Option<BudgetTracker<N, Compr::Data>>,
+    #[cfg(feature = "rayon")]
+    background: Option<BackgroundCompression<N, Compr>>,
 }
 
 pub type FastCompressibleChunkStorage<N, By, Chan> =
@@ -58,6 +76,117 @@ where
             cache: SmallKeyLruCache::default(),
             compression,
             compressed: Slab::new(),
+            cache_counters: CacheCounters::default(),
+            budget: None,
+            #[cfg(feature = "rayon")]
+            background: None,
+        }
+    }
+
+    /// Limits the memory used by cached chunks. Whenever the estimated size of all cached chunks exceeds `budget`, chunks
+    /// chosen by `policy` are compressed or dropped until the cache fits in the budget again. This happens inline when a chunk
+    /// is inserted or decompressed, and the chunk being accessed is never chosen.
+    ///
+    /// All chunks that are already cached will be tracked by `policy`, in arbitrary order.
+    pub fn set_memory_budget(
+        &mut self,
+        budget: MemoryBudget,
+        policy: impl 'static + EvictionPolicy<ChunkKey<N>> + Send + Sync,
+    ) where
+        Compr::Data: EstimateBytes,
+    {
+        let mut tracker = BudgetTracker {
+            budget,
+            policy: Box::new(policy),
+            estimate_bytes: <Compr::Data as EstimateBytes>::estimate_bytes,
+            chunk_bytes: SmallKeyHashMap::default(),
+            cached_bytes: 0,
+        };
+        for (key, entry) in self.cache.entries() {
+            if let CacheEntry::Cached(chunk) = entry {
+                tracker.insert(key.clone(), chunk);
+            }
+        }
+        self.budget = Some(tracker);
+
+        self.enforce_memory_budget(None);
+    }
+
+    /// Removes the memory budget, so chunks will only be compressed explicitly.
+    pub fn clear_memory_budget(&mut self) {
+        self.budget = None;
+    }
+
+    pub fn memory_budget(&self) -> Option<MemoryBudget> {
+        self.budget.as_ref().map(|tracker| tracker.budget)
+    }
+
+    /// The estimated number of bytes used by all cached chunks. Only available while a memory budget is set.
+    pub fn cached_bytes(&self) -> Option<usize> {
+        self.budget.as_ref().map(|tracker| tracker.cached_bytes)
+    }
+
+    /// Evicts chunks chosen by the eviction policy until the cache fits in the memory budget. The `protected` chunk will not be
+    /// evicted.
+    fn enforce_memory_budget(&mut self, protected: Option<&ChunkKey<N>>) {
+        #[cfg(feature = "rayon")]
+        self.collect_background_compression();
+
+        let Self {
+            cache,
+            compression,
+            compressed,
+            cache_counters,
+            budget,
+            ..
+        } = self;
+        let tracker = if let Some(tracker) = budget {
+            tracker
+        } else {
+            return;
+        };
+
+        let mut skipped_protected = false;
+        while tracker.is_over_budget() {
+            let victim = if let Some(victim) = tracker.policy.pop_victim() {
+                victim
+            } else {
+                break;
+            };
+            if Some(&victim) == protected {
+                skipped_protected = true;
+                continue;
+            }
+            tracker.remove(&victim);
+
+            if let Some(CacheEntry::Cached(chunk)) = cache.get(&victim) {
+                match tracker.budget.action {
+                    BudgetAction::Compress => {
+                        #[cfg(feature = "rayon")]
+                        if let Some(background) = &mut self.background {
+                            background.queue(victim.clone(), compression, chunk);
+                            // Queued chunks stay cached until they're collected, so make sure `compress_lru` doesn't
+                            // choose this one.
+                            cache.touch_if_cached(victim);
+                            continue;
+                        }
+
+                        let compressed_chunk = compression.compress(chunk);
+                        let compressed_entry = compressed.vacant_entry();
+                        cache.evict(victim, CompressedLocation(compressed_entry.key()));
+                        compressed_entry.insert(compressed_chunk);
+                        cache_counters.record_eviction();
+                    }
+                    BudgetAction::Drop => {
+                        cache.remove(&victim);
+                        cache_counters.record_eviction();
+                    }
+                }
+            }
+        }
+
+        if let (true, Some(protected)) = (skipped_protected, protected) {
+            tracker.policy.insert(protected.clone());
         }
     }
 
@@ -77,6 +206,45 @@ where
         self.len_total() == 0
     }
 
+    /// Takes a snapshot of the chunk counts and sizes in each LOD, along with the cache hits and misses since the last
+    /// `reset_cache_stats`.
+    ///
+    /// Every access through `ChunkWriteStorage` or a `CompressibleChunkStorageReader` that finds a chunk is counted, as a miss
+    /// if the chunk had to be decompressed. This takes time linear in the number of chunks.
+    pub fn stats(&self) -> ChunkMapStats
+    where
+        Compr::Data: EstimateBytes,
+    {
+        let mut stats = ChunkMapStats {
+            cache: self.cache_counters.snapshot(),
+            ..Default::default()
+        };
+        for (key, entry) in self.cache.entries() {
+            let lod_stats = stats.lods.entry(key.lod).or_default();
+            match entry {
+                CacheEntry::Cached(chunk) => {
+                    let bytes = chunk.estimate_bytes();
+                    lod_stats.num_cached += 1;
+                    lod_stats.cached_bytes += bytes;
+                    stats.cached_size_histogram.insert(bytes);
+                }
+                CacheEntry::Evicted(location) => {
+                    let bytes = self.compressed[location.0].compressed_bytes.len();
+                    lod_stats.num_compressed += 1;
+                    lod_stats.compressed_bytes += bytes;
+                    stats.compressed_size_histogram.insert(bytes);
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Sets the cache hit and miss counters back to zero.
+    pub fn reset_cache_stats(&self) {
+        self.cache_counters.reset();
+    }
+
     /// Returns a reader that implements `ChunkReadStorage`.
     pub fn reader<'a>(
         &'a self,
@@ -113,6 +281,11 @@ where
         &mut self,
         key: ChunkKey<N>,
     ) -> Option<MaybeCompressed<Compr::Data, Compressed<Compr>>> {
+        self.cancel_background_compression(&key);
+        if let Some(tracker) = &mut self.budget {
+            tracker.remove(&key);
+        }
+
         self.cache.remove(&key).map(|entry| match entry {
             CacheEntry::Cached(chunk) => MaybeCompressed::Decompressed(chunk),
             CacheEntry::Evicted(location) => {
@@ -123,13 +296,25 @@ where
 
     /// Compress the least-recently-used, cached chunk. On access, compressed chunks will be
     /// decompressed and cached.
+    ///
+    /// With background compression enabled, this only queues the chunk for compression.
     pub fn compress_lru(&mut self) {
+        #[cfg(feature = "rayon")]
+        if self.background.is_some() {
+            self.compress_lru_in_background();
+            return;
+        }
+
         let compressed_entry = self.compressed.vacant_entry();
-        if let Some((_, lru_chunk)) = self
+        if let Some((key, lru_chunk)) = self
             .cache
             .evict_lru(CompressedLocation(compressed_entry.key()))
         {
+            if let Some(tracker) = &mut self.budget {
+                tracker.remove(&key);
+            }
             compressed_entry.insert(self.compression.compress(&lru_chunk));
+            self.cache_counters.record_eviction();
         }
     }
 
@@ -138,7 +323,15 @@ where
     /// This is useful for removing a batch of chunks at a time before compressing them in parallel. Then call
     /// `insert_compressed`.
     pub fn remove_lru(&mut self) -> Option<(ChunkKey<N>, Compr::Data)> {
-        self.cache.remove_lru()
+        let removed = self.cache.remove_lru();
+        if let Some((key, _)) = &removed {
+            self.cancel_background_compression(key);
+        }
+        if let (Some(tracker), Some((key, _))) = (&mut self.budget, &removed) {
+            tracker.remove(key);
+        }
+
+        removed
     }
 
     /// Insert a compressed chunk. Returns the old chunk if one exists.
@@ -147,6 +340,11 @@ where
         key: ChunkKey<N>,
         compressed_chunk: Compressed<Compr>,
     ) -> Option<MaybeCompressed<Compr::Data, Compressed<Compr>>> {
+        self.cancel_background_compression(&key);
+        if let Some(tracker) = &mut self.budget {
+            tracker.remove(&key);
+        }
+
         let compressed_entry = self.compressed.vacant_entry();
         let old_entry = self
             .cache
@@ -175,14 +373,167 @@ where
         key: ChunkKey<N>,
         chunk: Compr::Data,
     ) -> Option<MaybeCompressed<Compr::Data, Compressed<Compr>>> {
-        self.cache
-            .insert(key, chunk)
+        self.cancel_background_compression(&key);
+        if let Some(tracker) = &mut self.budget {
+            tracker.insert(key.clone(), &chunk);
+        }
+
+        let old_chunk = self
+            .cache
+            .insert(key.clone(), chunk)
             .map(|old_entry| match old_entry {
                 CacheEntry::Cached(old_chunk) => MaybeCompressed::Decompressed(old_chunk),
                 CacheEntry::Evicted(location) => {
                     MaybeCompressed::Compressed(self.compressed.remove(location.0))
                 }
-            })
+            });
+        self.enforce_memory_budget(Some(&key));
+
+        old_chunk
+    }
+
+    /// Cancels any background compression of `key`, since the chunk is about to be modified or removed.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    fn cancel_background_compression(&mut self, key: &ChunkKey<N>) {
+        if let Some(background) = &mut self.background {
+            if background.cancel(key) {
+                // The chunk stopped counting against the budget when it was queued, but now it's staying in the cache.
+                if let (Some(tracker), Some(CacheEntry::Cached(chunk))) =
+                    (&mut self.budget, self.cache.get(key))
+                {
+                    tracker.insert(key.clone(), chunk);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    #[inline]
+    fn cancel_background_compression(&mut self, _key: &ChunkKey<N>) {}
+
+    /// Notifies the memory budget that `key` was accessed, then enforces the budget. `populated` must be `true` iff the chunk
+    /// was just inserted or decompressed.
+    fn track_budgeted_access(&mut self, key: &ChunkKey<N>, populated: bool) -> &mut Compr::Data {
+        let Self { cache, budget, .. } = self;
+        let tracker = budget.as_mut().unwrap();
+        if populated {
+            let chunk = cache
+                .get(key)
+                .and_then(|entry| entry.some_if_cached())
+                .unwrap();
+            tracker.insert(key.clone(), chunk);
+        } else {
+            tracker.touch(key);
+        }
+
+        self.enforce_memory_budget(Some(key));
+
+        self.cache
+            .get_mut(key)
+            .and_then(|entry| entry.some_if_cached())
+            .unwrap()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, Compr> CompressibleChunkStorage<N, Compr>
+where
+    ChunkKey<N>: Clone + Eq + Hash,
+    Compr: Compression,
+{
+    /// Compresses chunks on `pool` instead of the calling thread, whether they're evicted by the memory budget or by
+    /// `compress_lru`. The caller only pays for copying the chunk.
+    ///
+    /// A queued chunk stays cached, and it still counts towards `len_cached`, until its compressed copy is collected by
+    /// `collect_background_compression` or `flush_background_compression`. Finished compressions are also collected whenever
+    /// the memory budget is enforced. Modifying or removing a queued chunk cancels its compression.
+    pub fn enable_background_compression(&mut self, pool: Arc<rayon::ThreadPool>)
+    where
+        N: 'static + Send,
+        Compr: 'static + Clone + Send,
+        Compr::Data: 'static + Clone + Send,
+    {
+        self.flush_background_compression();
+        self.background = Some(BackgroundCompression::new(pool));
+    }
+
+    /// Waits for all queued chunks to be compressed, then goes back to compressing on the calling thread.
+    pub fn disable_background_compression(&mut self) {
+        self.flush_background_compression();
+        self.background = None;
+    }
+
+    pub fn background_compression_enabled(&self) -> bool {
+        self.background.is_some()
+    }
+
+    /// The number of chunks that are queued for background compression and haven't been collected yet.
+    pub fn len_compressing(&self) -> usize {
+        self.background
+            .as_ref()
+            .map(|background| background.num_pending())
+            .unwrap_or(0)
+    }
+
+    /// Replaces the cached chunks that have finished compressing in the background with their compressed versions. This
+    /// doesn't wait for any unfinished compression.
+    pub fn collect_background_compression(&mut self) {
+        self.store_background_compression(false)
+    }
+
+    /// Waits for all queued chunks to finish compressing in the background, then replaces them with their compressed
+    /// versions.
+    pub fn flush_background_compression(&mut self) {
+        self.store_background_compression(true)
+    }
+
+    fn store_background_compression(&mut self, wait: bool) {
+        let finished = if let Some(background) = &mut self.background {
+            background.take_finished(wait)
+        } else {
+            return;
+        };
+
+        for (key, compressed_chunk) in finished {
+            // Cancelled compressions were already discarded, so the chunk is still cached and unchanged.
+            if let Some(CacheEntry::Cached(_)) = self.cache.get(&key) {
+                let compressed_entry = self.compressed.vacant_entry();
+                self.cache
+                    .evict(key, CompressedLocation(compressed_entry.key()));
+                compressed_entry.insert(compressed_chunk);
+                self.cache_counters.record_eviction();
+            }
+        }
+    }
+
+    fn compress_lru_in_background(&mut self) {
+        self.collect_background_compression();
+
+        let Self {
+            cache,
+            compression,
+            budget,
+            background,
+            ..
+        } = self;
+        let background = background.as_mut().unwrap();
+        let key = match cache.lru_key() {
+            Some(key) if !background.is_pending(key) => key.clone(),
+            // The least-recently used chunk is already being compressed.
+            _ => return,
+        };
+
+        if let Some(tracker) = budget {
+            tracker.remove(&key);
+        }
+        let chunk = cache
+            .get(&key)
+            .and_then(|entry| entry.some_if_cached())
+            .unwrap();
+        background.queue(key.clone(), compression, chunk);
+        // Queued chunks stay cached until they're collected, so make sure this one isn't chosen again.
+        cache.touch_if_cached(key);
     }
 }
 
@@ -193,11 +544,42 @@ where
 {
     #[inline]
     fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Compr::Data> {
-        let Self {
-            cache, compressed, ..
-        } = self;
+        self.cancel_background_compression(&key);
+        let decompressed = Cell::new(false);
+        if self.budget.is_none() {
+            let Self {
+                cache,
+                compressed,
+                cache_counters,
+                ..
+            } = self;
+
+            let chunk = cache.get_mut_or_repopulate_with(key, |location| {
+                decompressed.set(true);
+                compressed.remove(location.0).decompress()
+            });
+            if chunk.is_some() {
+                cache_counters.record_access(!decompressed.get());
+            }
+
+            return chunk;
+        }
+
+        {
+            let Self {
+                cache,
+                compressed,
+                cache_counters,
+                ..
+            } = self;
+            cache.get_mut_or_repopulate_with(key.clone(), |location| {
+                decompressed.set(true);
+                compressed.remove(location.0).decompress()
+            })?;
+            cache_counters.record_access(!decompressed.get());
+        }
 
-        cache.get_mut_or_repopulate_with(key, |location| compressed.remove(location.0).decompress())
+        Some(self.track_budgeted_access(&key, decompressed.get()))
     }
 
     #[inline]
@@ -206,14 +588,59 @@ where
         key: ChunkKey<N>,
         create_chunk: impl FnOnce() -> Compr::Data,
     ) -> &mut Compr::Data {
-        let Self {
-            cache, compressed, ..
-        } = self;
-        cache.get_mut_or_insert_with(
-            key,
-            |location| compressed.remove(location.0).decompress(),
-            create_chunk,
-        )
+        self.cancel_background_compression(&key);
+        let decompressed = Cell::new(false);
+        let created = Cell::new(false);
+        if self.budget.is_none() {
+            let Self {
+                cache,
+                compressed,
+                cache_counters,
+                ..
+            } = self;
+
+            let chunk = cache.get_mut_or_insert_with(
+                key,
+                |location| {
+                    decompressed.set(true);
+                    compressed.remove(location.0).decompress()
+                },
+                || {
+                    created.set(true);
+                    create_chunk()
+                },
+            );
+            if !created.get() {
+                cache_counters.record_access(!decompressed.get());
+            }
+
+            return chunk;
+        }
+
+        {
+            let Self {
+                cache,
+                compressed,
+                cache_counters,
+                ..
+            } = self;
+            cache.get_mut_or_insert_with(
+                key.clone(),
+                |location| {
+                    decompressed.set(true);
+                    compressed.remove(location.0).decompress()
+                },
+                || {
+                    created.set(true);
+                    create_chunk()
+                },
+            );
+            if !created.get() {
+                cache_counters.record_access(!decompressed.get());
+            }
+        }
+
+        self.track_budgeted_access(&key, decompressed.get() || created.get())
     }
 
     #[inline]
@@ -295,6 +722,80 @@ where
     }
 }
 
+/// A limit on the memory used by the uncompressed chunks in a `CompressibleChunkStorage`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryBudget {
+    /// The maximum total `EstimateBytes::estimate_bytes` of all cached (uncompressed) chunks. Compressed chunks don't count
+    /// against the budget.
+    pub max_cached_bytes: usize,
+    /// What happens to chunks that are evicted from the cache.
+    pub action: BudgetAction,
+}
+
+/// What happens to chunks that are evicted to enforce a `MemoryBudget`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BudgetAction {
+    /// Compress the chunks. They will be decompressed and cached again on the next mutable access.
+    Compress,
+    /// Remove the chunks from storage entirely. This is only appropriate if they can be recovered from somewhere else, like a
+    /// `ChunkDb`.
+    Drop,
+}
+
+/// An estimate of the number of bytes of memory used by a chunk.
+pub trait EstimateBytes {
+    fn estimate_bytes(&self) -> usize;
+}
+
+impl<N, Chan> EstimateBytes for Array<N, Chan>
+where
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels,
+{
+    /// Asks the channels how much memory they use; see `Channels::estimate_bytes`.
+    fn estimate_bytes(&self) -> usize {
+        self.channels().estimate_bytes(self.extent().num_points())
+    }
+}
+
+/// Tracks the sizes of all cached chunks and the eviction order for enforcing a `MemoryBudget`.
+struct BudgetTracker<N, Ch> {
+    budget: MemoryBudget,
+    policy: Box<dyn EvictionPolicy<ChunkKey<N>> + Send + Sync>,
+    estimate_bytes: fn(&Ch) -> usize,
+    chunk_bytes: SmallKeyHashMap<ChunkKey<N>, usize>,
+    cached_bytes: usize,
+}
+
+impl<N, Ch> BudgetTracker<N, Ch>
+where
+    ChunkKey<N>: Clone + Eq + Hash,
+{
+    fn insert(&mut self, key: ChunkKey<N>, chunk: &Ch) {
+        let bytes = (self.estimate_bytes)(chunk);
+        if let Some(old_bytes) = self.chunk_bytes.insert(key.clone(), bytes) {
+            self.cached_bytes -= old_bytes;
+        }
+        self.cached_bytes += bytes;
+        self.policy.insert(key);
+    }
+
+    fn touch(&mut self, key: &ChunkKey<N>) {
+        self.policy.touch(key);
+    }
+
+    fn remove(&mut self, key: &ChunkKey<N>) {
+        if let Some(bytes) = self.chunk_bytes.remove(key) {
+            self.cached_bytes -= bytes;
+        }
+        self.policy.remove(key);
+    }
+
+    fn is_over_budget(&self) -> bool {
+        self.cached_bytes > self.budget.max_cached_bytes
+    }
+}
+
 /// An index into a compressed chunk slab.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct CompressedLocation(pub usize);
@@ -324,6 +825,10 @@ pub mod multichannel_aliases {
         CompressibleChunkStorage<N, FastArrayCompressionNx5<N, By, A, B, C, D, E>>;
     pub type FastCompressibleChunkStorageNx6<N, By, A, B, C, D, E, F> =
         CompressibleChunkStorage<N, FastArrayCompressionNx6<N, By, A, B, C, D, E, F>>;
+    pub type FastCompressibleChunkStorageNx7<N, By, A, B, C, D, E, F, G> =
+        CompressibleChunkStorage<N, FastArrayCompressionNx7<N, By, A, B, C, D, E, F, G>>;
+    pub type FastCompressibleChunkStorageNx8<N, By, A, B, C, D, E, F, G, H> =
+        CompressibleChunkStorage<N, FastArrayCompressionNx8<N, By, A, B, C, D, E, F, G, H>>;
 
     macro_rules! compressible_map_type_alias {
         ($name:ident, $dim:ty, $( $chan:ident ),+ ) => {
@@ -349,6 +854,8 @@ pub mod multichannel_aliases {
     compressible_map_type_alias!(CompressibleChunkMap2x4, [i32; 2], A, B, C, D);
     compressible_map_type_alias!(CompressibleChunkMap2x5, [i32; 2], A, B, C, D, E);
     compressible_map_type_alias!(CompressibleChunkMap2x6, [i32; 2], A, B, C, D, E, F);
+    compressible_map_type_alias!(CompressibleChunkMap2x7, [i32; 2], A, B, C, D, E, F, G);
+    compressible_map_type_alias!(CompressibleChunkMap2x8, [i32; 2], A, B, C, D, E, F, G, H);
 
     pub type CompressibleChunkMap3x1<By, A> = CompressibleChunkMapNx1<[i32; 3], By, A>;
     compressible_map_type_alias!(CompressibleChunkMap3x2, [i32; 3], A, B);
@@ -356,6 +863,290 @@ pub mod multichannel_aliases {
     compressible_map_type_alias!(CompressibleChunkMap3x4, [i32; 3], A, B, C, D);
     compressible_map_type_alias!(CompressibleChunkMap3x5, [i32; 3], A, B, C, D, E);
     compressible_map_type_alias!(CompressibleChunkMap3x6, [i32; 3], A, B, C, D, E, F);
+    compressible_map_type_alias!(CompressibleChunkMap3x7, [i32; 3], A, B, C, D, E, F, G);
+    compressible_map_type_alias!(CompressibleChunkMap3x8, [i32; 3], A, B, C, D, E, F, G, H);
 }
 
 pub use multichannel_aliases::*;
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, Array3x1, BitChannel, CacheStats, Channel, LfuPolicy, LruPolicy};
+
+    // Each of these chunks is 64 * 4 = 256 bytes.
+    fn chunk_key_and_value(i: i32) -> (ChunkKey<[i32; 3]>, Array3x1<u32>) {
+        let min = PointN([4 * i, 0, 0]);
+
+        (
+            ChunkKey::new(0, min),
+            Array3x1::fill(Extent3i::from_min_and_shape(min, Point3i::fill(4)), 1),
+        )
+    }
+
+    #[test]
+    fn budget_compresses_least_recently_used_chunks() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        storage.set_memory_budget(
+            MemoryBudget {
+                max_cached_bytes: 512,
+                action: BudgetAction::Compress,
+            },
+            LruPolicy::default(),
+        );
+
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        let (key2, chunk2) = chunk_key_and_value(2);
+
+        storage.write(key0, chunk0);
+        storage.write(key1, chunk1);
+        assert_eq!(storage.cached_bytes(), Some(512));
+        assert_eq!(storage.len_compressed(), 0);
+
+        // Access the first chunk so the second becomes least recently used.
+        storage.get_mut(key0).unwrap();
+        storage.write(key2, chunk2);
+        assert_eq!(storage.cached_bytes(), Some(512));
+        assert_eq!(storage.len_cached(), 2);
+        assert_eq!(storage.len_compressed(), 1);
+        assert!(matches!(
+            storage.cache.get(&key1),
+            Some(CacheEntry::Evicted(_))
+        ));
+
+        // Decompressing a chunk on access makes room by compressing another.
+        storage.get_mut(key1).unwrap();
+        assert!(matches!(
+            storage.cache.get(&key0),
+            Some(CacheEntry::Evicted(_))
+        ));
+        assert_eq!(storage.cached_bytes(), Some(512));
+        assert_eq!(storage.len_total(), 3);
+    }
+
+    #[test]
+    fn budget_drops_chunks() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        let (key2, chunk2) = chunk_key_and_value(2);
+
+        // Chunks that were cached before setting the budget are also subject to it.
+        storage.write(key0, chunk0);
+        storage.write(key1, chunk1);
+        storage.set_memory_budget(
+            MemoryBudget {
+                max_cached_bytes: 512,
+                action: BudgetAction::Drop,
+            },
+            LfuPolicy::default(),
+        );
+        assert_eq!(storage.cached_bytes(), Some(512));
+
+        // The first chunk is used most frequently, so the second gets dropped.
+        storage.get_mut(key0).unwrap();
+        storage.get_mut(key0).unwrap();
+        storage.write(key2, chunk2);
+
+        assert_eq!(storage.len_total(), 2);
+        assert!(storage.get_mut(key0).is_some());
+        assert!(storage.get_mut(key1).is_none());
+        assert!(storage.get_mut(key2).is_some());
+    }
+
+    #[test]
+    fn stats_count_reader_hits_and_misses() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        let key2 = ChunkKey::new(2, Point3i::ZERO);
+
+        storage.write(key0, chunk0);
+        storage.write(key1, chunk1);
+        storage.write(
+            key2,
+            Array3x1::fill(
+                Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)),
+                1,
+            ),
+        );
+        storage.compress_lru();
+
+        let local_cache = LocalChunkCache::new();
+        let reader = storage.reader(&local_cache);
+        assert!(reader.get(key0).is_some());
+        assert!(reader.get(key1).is_some());
+        assert!(reader.get(key2).is_some());
+        assert!(reader.get(ChunkKey::new(1, Point3i::ZERO)).is_none());
+
+        let stats = storage.stats();
+        assert_eq!((stats.cache.hits, stats.cache.misses), (2, 1));
+        assert_eq!(stats.cache.evictions, 1);
+        assert_eq!(stats.lods[&0].num_cached, 1);
+        assert_eq!(stats.lods[&0].num_compressed, 1);
+        assert_eq!(stats.lods[&2].num_cached, 1);
+        assert_eq!(stats.total().cached_bytes, 512);
+        assert_eq!(stats.cached_size_histogram.counts()[8], 2);
+        assert!(stats.lods.get(&1).is_none());
+
+        storage.reset_cache_stats();
+        assert_eq!(storage.stats().cache, CacheStats::default());
+    }
+
+    #[test]
+    fn stats_count_evictions() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        let (key2, chunk2) = chunk_key_and_value(2);
+
+        storage.write(key0, chunk0.clone());
+        storage.write(key1, chunk1);
+
+        // Removing a chunk doesn't count as an eviction.
+        assert!(storage.remove_lru().is_some());
+        assert_eq!(storage.stats().cache.evictions, 0);
+
+        storage.compress_lru();
+        assert_eq!(storage.stats().cache.evictions, 1);
+
+        storage.write(key0, chunk0);
+        storage.write(key2, chunk2);
+        storage.set_memory_budget(
+            MemoryBudget {
+                max_cached_bytes: 256,
+                action: BudgetAction::Drop,
+            },
+            LruPolicy::default(),
+        );
+        assert_eq!(storage.len_cached(), 1);
+        assert_eq!(storage.len_compressed(), 1);
+        assert_eq!(storage.stats().cache.evictions, 2);
+
+        storage.reset_cache_stats();
+        assert_eq!(storage.stats().cache.evictions, 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    fn thread_pool() -> Arc<rayon::ThreadPool> {
+        Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn budget_compresses_in_background() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        storage.set_memory_budget(
+            MemoryBudget {
+                max_cached_bytes: 512,
+                action: BudgetAction::Compress,
+            },
+            LruPolicy::default(),
+        );
+        storage.enable_background_compression(thread_pool());
+
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        let (key2, chunk2) = chunk_key_and_value(2);
+        storage.write(key0, chunk0);
+        storage.write(key1, chunk1);
+        storage.write(key2, chunk2.clone());
+
+        // The evicted chunk is still cached and readable while it's being compressed.
+        assert_eq!(storage.len_compressing(), 1);
+        assert_eq!(storage.len_cached(), 3);
+        assert_eq!(storage.cached_bytes(), Some(512));
+        assert!(matches!(
+            storage.cache.get(&key0),
+            Some(CacheEntry::Cached(_))
+        ));
+
+        assert_eq!(storage.stats().cache.evictions, 0);
+
+        storage.flush_background_compression();
+        assert_eq!(storage.stats().cache.evictions, 1);
+        assert_eq!(storage.len_compressing(), 0);
+        assert_eq!(storage.len_cached(), 2);
+        assert_eq!(storage.len_compressed(), 1);
+        assert!(matches!(
+            storage.cache.get(&key0),
+            Some(CacheEntry::Evicted(_))
+        ));
+        assert_eq!(storage.get_mut(key2).unwrap(), &chunk2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn writing_cancels_background_compression() {
+        let mut storage =
+            FastCompressibleChunkStorageNx1::with_bytes_compression(Lz4 { level: 10 });
+        let pool = thread_pool();
+        storage.enable_background_compression(pool.clone());
+
+        // Keep the only worker busy so no compression can finish until we say so.
+        let (unblock_tx, unblock_rx) = std::sync::mpsc::channel::<()>();
+        pool.spawn(move || {
+            let _ = unblock_rx.recv();
+        });
+
+        let (key0, chunk0) = chunk_key_and_value(0);
+        let (key1, chunk1) = chunk_key_and_value(1);
+        storage.write(key0, chunk0);
+        storage.write(key1, chunk1);
+
+        storage.compress_lru();
+        assert_eq!(storage.len_compressing(), 1);
+        // The next LRU chunk is queued, not the same one again.
+        storage.compress_lru();
+        assert_eq!(storage.len_compressing(), 2);
+
+        // Modify the first chunk while it's being compressed.
+        *storage.get_mut(key0).unwrap().get_mut(Point3i::ZERO) = 5;
+        assert_eq!(storage.len_compressing(), 1);
+
+        unblock_tx.send(()).unwrap();
+        storage.disable_background_compression();
+        assert_eq!(storage.len_cached(), 1);
+        assert_eq!(storage.len_compressed(), 1);
+        assert!(matches!(
+            storage.cache.get(&key1),
+            Some(CacheEntry::Evicted(_))
+        ));
+        assert_eq!(storage.get_mut(key0).unwrap().get(Point3i::ZERO), 5);
+    }
+
+    #[test]
+    fn estimate_bytes_of_packed_channels() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        assert_eq!(Array3x1::fill(extent, 0u32).estimate_bytes(), 4096 * 4);
+        assert_eq!(BitArray3::fill(extent, false).estimate_bytes(), 512);
+
+        let mut blocks = PaletteArray3::fill(extent, 0u16);
+        assert_eq!(blocks.estimate_bytes(), 2);
+        blocks.get_mut(Point3i::ZERO).set(1);
+        // 2 palette values and 4096 1-bit indices.
+        assert_eq!(blocks.estimate_bytes(), 2 * 2 + 512);
+
+        let mixed: Array<[i32; 3], (Channel<u8>, BitChannel)> = Array::fill(extent, (0, false));
+        assert_eq!(mixed.estimate_bytes(), 4096 + 512);
+    }
+}
@@ -26,16 +26,26 @@ where
     #[inline]
     fn get(&self, key: ChunkKey<N>) -> Option<&Compr::Data> {
         let Self {
-            storage: CompressibleChunkStorage {
-                cache, compressed, ..
-            },
+            storage:
+                CompressibleChunkStorage {
+                    cache,
+                    compressed,
+                    cache_counters,
+                    ..
+                },
             local_cache,
         } = self;
 
         cache.get(&key).map(|entry| match entry {
-            CacheEntry::Cached(value) => value,
-            CacheEntry::Evicted(location) => local_cache
-                .get_or_insert_with(key, || compressed.get(location.0).unwrap().decompress()),
+            CacheEntry::Cached(value) => {
+                cache_counters.record_access(true);
+                value
+            }
+            CacheEntry::Evicted(location) => {
+                cache_counters.record_access(false);
+                local_cache
+                    .get_or_insert_with(key, || compressed.get(location.0).unwrap().decompress())
+            }
         })
     }
 }
@@ -147,6 +157,8 @@ pub mod multichannel_aliases {
     compressible_map_reader_type_alias!(CompressibleChunkMapReader2x4, [i32; 2], A, B, C, D);
     compressible_map_reader_type_alias!(CompressibleChunkMapReader2x5, [i32; 2], A, B, C, D, E);
     compressible_map_reader_type_alias!(CompressibleChunkMapReader2x6, [i32; 2], A, B, C, D, E, F);
+    compressible_map_reader_type_alias!(CompressibleChunkMapReader2x7, [i32; 2], A, B, C, D, E, F, G);
+    compressible_map_reader_type_alias!(CompressibleChunkMapReader2x8, [i32; 2], A, B, C, D, E, F, G, H);
 
     pub type CompressibleChunkMapReader3x1<'a, By, A> =
         CompressibleChunkMapReaderNx1<'a, [i32; 3], By, A>;
@@ -155,6 +167,8 @@ pub mod multichannel_aliases {
     compressible_map_reader_type_alias!(CompressibleChunkMapReader3x4, [i32; 3], A, B, C, D);
     compressible_map_reader_type_alias!(CompressibleChunkMapReader3x5, [i32; 3], A, B, C, D, E);
     compressible_map_reader_type_alias!(CompressibleChunkMapReader3x6, [i32; 3], A, B, C, D, E, F);
+    compressible_map_reader_type_alias!(CompressibleChunkMapReader3x7, [i32; 3], A, B, C, D, E, F, G);
+    compressible_map_reader_type_alias!(CompressibleChunkMapReader3x8, [i32; 3], A, B, C, D, E, F, G, H);
 }
 
 pub use multichannel_aliases::*;
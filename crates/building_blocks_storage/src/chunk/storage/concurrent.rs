@@ -0,0 +1,344 @@
+//! Chunk storage that can be read from many threads while new chunks are inserted.
+//!
+//! Wrapping a whole `ChunkHashMap` in a `RwLock` serializes every reader against every writer. `ConcurrentChunkStorage`
+//! instead splits the chunks into many shards, each with its own lock, which is only held for the duration of a single hash
+//! map lookup or insertion. Chunks can be inserted with only `&self`, so e.g. meshing worker threads can keep reading chunks
+//! from a shared `ChunkMap` while the main thread inserts newly generated chunks.
+//!
+//! To make this possible, a chunk can only be inserted with `&self` if its key is vacant. Overwriting or removing chunks
+//! requires `&mut self`, via `ChunkWriteStorage`, so any references to chunks handed out by `ChunkReadStorage::get` stay
+//! valid for as long as the storage is borrowed.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, ConcurrentChunkStorage};
+//!
+//! use std::sync::Arc;
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let map = Arc::new(builder.build_with_rw_storage(ConcurrentChunkStorage::default()));
+//!
+//! let chunk_extent = map.indexer.extent_for_chunk_with_min(Point3i::ZERO);
+//! let key = ChunkKey::new(0, Point3i::ZERO);
+//!
+//! let reader_map = map.clone();
+//! let reader = std::thread::spawn(move || {
+//!     // This chunk may or may not be inserted yet, but reading never blocks on the whole map.
+//!     reader_map.clone_point(0, Point3i::fill(1))
+//! });
+//!
+//! assert!(map.storage().insert_if_vacant(key, Array3x1::fill(chunk_extent, 1)));
+//! assert_eq!(map.clone_point(0, Point3i::fill(1)), 1);
+//!
+//! let value = reader.join().unwrap();
+//! assert!(value == 0 || value == 1);
+//! ```
+
+use crate::{ChunkMap, ChunkMapBuilder, SmallKeyBuildHasher, SmallKeyHashMap};
+
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage};
+
+use core::hash::{BuildHasher, Hash, Hasher};
+use std::sync::RwLock;
+
+/// Chunk storage with interior mutability, for concurrently reading chunks while new chunks are inserted. See the `concurrent`
+/// module docs for details.
+pub struct ConcurrentChunkStorage<N, Ch> {
+    // Chunks are boxed so their addresses stay the same when a shard reallocates.
+    shards: Vec<RwLock<SmallKeyHashMap<ChunkKey<N>, Box<Ch>>>>,
+    shard_hasher: SmallKeyBuildHasher,
+}
+
+impl<N, Ch> ConcurrentChunkStorage<N, Ch> {
+    /// The number of shards used by `default`.
+    pub const DEFAULT_NUM_SHARDS: usize = 64;
+
+    /// Creates an empty storage with `num_shards` independently locked shards. More shards means less contention between
+    /// threads.
+    pub fn with_num_shards(num_shards: usize) -> Self {
+        assert!(num_shards > 0);
+
+        Self {
+            shards: (0..num_shards)
+                .map(|_| RwLock::new(SmallKeyHashMap::default()))
+                .collect(),
+            shard_hasher: SmallKeyBuildHasher::default(),
+        }
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The total number of chunks in storage. This may be stale by the time it returns if other threads are inserting chunks.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a snapshot of all chunk keys, in no particular order.
+    pub fn chunk_keys(&self) -> Vec<ChunkKey<N>>
+    where
+        ChunkKey<N>: Clone,
+    {
+        let mut keys = Vec::new();
+        for shard in self.shards.iter() {
+            keys.extend(shard.read().unwrap().keys().cloned());
+        }
+
+        keys
+    }
+
+    /// Consumes `self` and returns all of the chunks in a single hash map.
+    pub fn into_hash_map(self) -> SmallKeyHashMap<ChunkKey<N>, Ch>
+    where
+        ChunkKey<N>: Eq + Hash,
+    {
+        let mut map = SmallKeyHashMap::default();
+        for shard in self.shards.into_iter() {
+            map.extend(
+                shard
+                    .into_inner()
+                    .unwrap()
+                    .into_iter()
+                    .map(|(key, chunk)| (key, *chunk)),
+            );
+        }
+
+        map
+    }
+}
+
+impl<N, Ch> Default for ConcurrentChunkStorage<N, Ch> {
+    fn default() -> Self {
+        Self::with_num_shards(Self::DEFAULT_NUM_SHARDS)
+    }
+}
+
+impl<N, Ch> ConcurrentChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Eq + Hash,
+{
+    fn shard_index(&self, key: &ChunkKey<N>) -> usize {
+        let mut hasher = self.shard_hasher.build_hasher();
+        key.hash(&mut hasher);
+
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    fn shard_mut(&mut self, key: &ChunkKey<N>) -> &mut SmallKeyHashMap<ChunkKey<N>, Box<Ch>> {
+        let i = self.shard_index(key);
+
+        self.shards[i].get_mut().unwrap()
+    }
+
+    /// Returns `true` iff there is a chunk at `key`.
+    pub fn contains(&self, key: ChunkKey<N>) -> bool {
+        self.shards[self.shard_index(&key)]
+            .read()
+            .unwrap()
+            .contains_key(&key)
+    }
+
+    /// Inserts `chunk` at `key` iff there is no chunk there already. Only `&self` is required, so this can be called while
+    /// other threads are reading. Returns `true` iff the chunk was inserted; otherwise `chunk` is dropped.
+    pub fn insert_if_vacant(&self, key: ChunkKey<N>, chunk: Ch) -> bool {
+        self.insert_if_vacant_with(key, || chunk)
+    }
+
+    /// Like `insert_if_vacant`, but `create_chunk` is only called if there is no chunk at `key`. The shard lock is held while
+    /// `create_chunk` runs, so it should be cheap.
+    pub fn insert_if_vacant_with(
+        &self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Ch,
+    ) -> bool {
+        let mut shard = self.shards[self.shard_index(&key)].write().unwrap();
+        if shard.contains_key(&key) {
+            return false;
+        }
+        shard.insert(key, Box::new(create_chunk()));
+
+        true
+    }
+}
+
+impl<N, Ch> ChunkReadStorage<N, Ch> for ConcurrentChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Eq + Hash,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Ch> {
+        let shard = self.shards[self.shard_index(&key)].read().unwrap();
+
+        shard.get(&key).map(|chunk| {
+            let chunk: *const Ch = &**chunk;
+
+            // SAFE: The chunk is boxed, so it doesn't move when the shard reallocates, and it can only be dropped or replaced
+            // with `&mut self`, which can't happen while the returned reference borrows `self`.
+            unsafe { &*chunk }
+        })
+    }
+}
+
+impl<N, Ch> ChunkWriteStorage<N, Ch> for ConcurrentChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Eq + Hash,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Ch> {
+        self.shard_mut(&key).get_mut(&key).map(|chunk| &mut **chunk)
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Ch,
+    ) -> &mut Ch {
+        self.shard_mut(&key)
+            .entry(key)
+            .or_insert_with(|| Box::new(create_chunk()))
+    }
+
+    #[inline]
+    fn replace(&mut self, key: ChunkKey<N>, chunk: Ch) -> Option<Ch> {
+        self.shard_mut(&key)
+            .insert(key, Box::new(chunk))
+            .map(|old| *old)
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey<N>, chunk: Ch) {
+        self.shard_mut(&key).insert(key, Box::new(chunk));
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey<N>) {
+        self.shard_mut(&key).remove(&key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey<N>) -> Option<Ch> {
+        self.shard_mut(&key).remove(&key).map(|chunk| *chunk)
+    }
+}
+
+/// A `ChunkMap` using `ConcurrentChunkStorage` as chunk storage.
+pub type ConcurrentChunkMap<N, T, Bldr> =
+    ChunkMap<N, T, Bldr, ConcurrentChunkStorage<N, <Bldr as ChunkMapBuilder<N, T>>::Chunk>>;
+/// A 2-dimensional `ConcurrentChunkMap`.
+pub type ConcurrentChunkMap2<T, Bldr> = ConcurrentChunkMap<[i32; 2], T, Bldr>;
+/// A 3-dimensional `ConcurrentChunkMap`.
+pub type ConcurrentChunkMap3<T, Bldr> = ConcurrentChunkMap<[i32; 3], T, Bldr>;
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn readers_see_chunks_inserted_by_another_thread() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0);
+        let map =
+            Arc::new(builder.build_with_rw_storage(ConcurrentChunkStorage::with_num_shards(4)));
+        let num_chunks = 64;
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                std::thread::spawn(move || {
+                    // Keep reading until every chunk shows up. Chunks never disappear once they're visible.
+                    let mut num_visible = 0;
+                    while num_visible < num_chunks {
+                        let visible = (0..num_chunks)
+                            .filter(|i| {
+                                map.get_chunk(ChunkKey::new(0, PointN([4 * i, 0, 0])))
+                                    .is_some()
+                            })
+                            .count();
+                        assert!(visible >= num_visible);
+                        num_visible = visible;
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..num_chunks {
+            let chunk_min = PointN([4 * i, 0, 0]);
+            let extent = map.indexer.extent_for_chunk_with_min(chunk_min);
+            assert!(map
+                .storage()
+                .insert_if_vacant(ChunkKey::new(0, chunk_min), Array3x1::fill(extent, i)));
+        }
+
+        for reader in readers.into_iter() {
+            reader.join().unwrap();
+        }
+
+        assert_eq!(map.storage().len(), num_chunks as usize);
+        assert_eq!(map.clone_point(0, PointN([4 * 10 + 1, 2, 3])), 10);
+    }
+
+    #[test]
+    fn insert_if_vacant_does_not_overwrite() {
+        let storage = ConcurrentChunkStorage::<[i32; 3], i32>::default();
+        let key = ChunkKey::new(0, Point3i::ZERO);
+
+        assert!(storage.insert_if_vacant(key, 1));
+        assert!(!storage.insert_if_vacant(key, 2));
+        assert!(!storage.insert_if_vacant_with(key, || panic!("Should not be called")));
+        assert_eq!(storage.get(key), Some(&1));
+        assert!(storage.contains(key));
+    }
+
+    #[test]
+    fn write_storage_through_mut() {
+        let mut storage = ConcurrentChunkStorage::<[i32; 3], i32>::with_num_shards(2);
+        let keys: Vec<_> = (0..10)
+            .map(|i| ChunkKey::new(0, PointN([i, 0, 0])))
+            .collect();
+
+        for (i, key) in keys.iter().enumerate() {
+            storage.write(*key, i as i32);
+        }
+        assert_eq!(storage.replace(keys[3], 30), Some(3));
+        *storage.get_mut(keys[4]).unwrap() = 40;
+        assert_eq!(storage.pop(keys[5]), Some(5));
+        storage.delete(keys[6]);
+        assert_eq!(*storage.get_mut_or_insert_with(keys[6], || 60), 60);
+
+        let mut sorted_keys = storage.chunk_keys();
+        sorted_keys.sort_by_key(|key| key.minimum.x());
+        assert_eq!(
+            sorted_keys,
+            keys.iter()
+                .cloned()
+                .filter(|key| *key != keys[5])
+                .collect::<Vec<_>>()
+        );
+
+        let map = storage.into_hash_map();
+        assert_eq!(map.len(), 9);
+        assert_eq!(map[&keys[3]], 30);
+        assert_eq!(map[&keys[4]], 40);
+    }
+}
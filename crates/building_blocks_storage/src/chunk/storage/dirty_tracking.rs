@@ -0,0 +1,246 @@
+//! A chunk storage wrapper that tracks which chunks have been mutated.
+//!
+//! Downstream systems like meshing, persistence, and networking usually only care about the chunks that changed since they
+//! last ran. Wrapping the storage of a `ChunkMap` with `DirtyTracking` records the key of every chunk that gets mutably
+//! borrowed, written, replaced, or removed, so those systems can simply call `ChunkMap::drain_dirty`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, DirtyTracking, SmallKeyHashMap};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let mut map = builder.build_with_rw_storage(DirtyTracking::new(SmallKeyHashMap::default()));
+//!
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(1)) = 1;
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(20), Point3i::fill(4));
+//! map.lod_view_mut(0).fill_extent(&extent, 2);
+//!
+//! let mut dirty: Vec<_> = map.drain_dirty().collect();
+//! dirty.sort_by_key(|key| key.minimum.x());
+//! assert_eq!(dirty, vec![ChunkKey::new(0, Point3i::ZERO), ChunkKey::new(0, Point3i::fill(16))]);
+//!
+//! // Reads don't make a chunk dirty.
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 1);
+//! assert_eq!(map.drain_dirty().count(), 0);
+//! ```
+
+use crate::{Chunk, ChunkMap, SmallKeyHashSet};
+
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys, TranslateChunkKeys};
+
+use building_blocks_core::{IntegerPoint, PointN};
+use core::hash::Hash;
+
+/// Wraps a chunk storage `Store` and records the key of every chunk that is mutated through the `ChunkWriteStorage` methods.
+///
+/// A chunk is considered dirty as soon as it is mutably borrowed, even if none of its values actually change. Mutations made
+/// directly to the inner storage via `inner_mut` are not tracked.
+pub struct DirtyTracking<N, Store> {
+    storage: Store,
+    dirty: SmallKeyHashSet<ChunkKey<N>>,
+}
+
+impl<N, Store> DirtyTracking<N, Store> {
+    pub fn new(storage: Store) -> Self {
+        Self {
+            storage,
+            dirty: SmallKeyHashSet::default(),
+        }
+    }
+
+    /// Borrow the inner storage.
+    pub fn inner(&self) -> &Store {
+        &self.storage
+    }
+
+    /// Mutably borrow the inner storage. Mutations made this way are not tracked.
+    pub fn inner_mut(&mut self) -> &mut Store {
+        &mut self.storage
+    }
+
+    /// Consumes the wrapper, dropping the dirty set.
+    pub fn into_inner(self) -> Store {
+        self.storage
+    }
+
+    /// The keys of all chunks mutated since the last call to `drain_dirty`.
+    pub fn dirty_keys(&self) -> impl Iterator<Item = &ChunkKey<N>> {
+        self.dirty.iter()
+    }
+
+    /// Removes and returns the keys of all chunks mutated since the last call to `drain_dirty`.
+    pub fn drain_dirty(&mut self) -> impl Iterator<Item = ChunkKey<N>> + '_ {
+        self.dirty.drain()
+    }
+}
+
+impl<N, Store> DirtyTracking<N, Store>
+where
+    ChunkKey<N>: Hash + Eq,
+{
+    /// Returns `true` iff the chunk at `key` was mutated since the last call to `drain_dirty`.
+    pub fn is_dirty(&self, key: ChunkKey<N>) -> bool {
+        self.dirty.contains(&key)
+    }
+
+    /// Marks the chunk at `key` as dirty, e.g. after mutating it via `inner_mut`.
+    pub fn mark_dirty(&mut self, key: ChunkKey<N>) {
+        self.dirty.insert(key);
+    }
+}
+
+impl<N, Ch, Store> ChunkReadStorage<N, Ch> for DirtyTracking<N, Store>
+where
+    Store: ChunkReadStorage<N, Ch>,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Ch> {
+        self.storage.get(key)
+    }
+
+    #[inline]
+    fn get_uniform(&self, key: ChunkKey<N>) -> Option<&Ch::Data>
+    where
+        Ch: Chunk,
+    {
+        self.storage.get_uniform(key)
+    }
+}
+
+impl<N, Ch, Store> ChunkWriteStorage<N, Ch> for DirtyTracking<N, Store>
+where
+    ChunkKey<N>: Clone + Hash + Eq,
+    Store: ChunkWriteStorage<N, Ch>,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Ch> {
+        let chunk = self.storage.get_mut(key.clone());
+        if chunk.is_some() {
+            self.dirty.insert(key);
+        }
+
+        chunk
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Ch,
+    ) -> &mut Ch {
+        self.dirty.insert(key.clone());
+
+        self.storage.get_mut_or_insert_with(key, create_chunk)
+    }
+
+    #[inline]
+    fn replace(&mut self, key: ChunkKey<N>, chunk: Ch) -> Option<Ch> {
+        self.dirty.insert(key.clone());
+
+        self.storage.replace(key, chunk)
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey<N>, chunk: Ch) {
+        self.dirty.insert(key.clone());
+        self.storage.write(key, chunk);
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey<N>) {
+        self.dirty.insert(key.clone());
+        self.storage.delete(key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey<N>) -> Option<Ch> {
+        let chunk = self.storage.pop(key.clone());
+        if chunk.is_some() {
+            self.dirty.insert(key);
+        }
+
+        chunk
+    }
+}
+
+impl<'a, N, Store> IterChunkKeys<'a, N> for DirtyTracking<N, Store>
+where
+    ChunkKey<N>: 'a,
+    Store: IterChunkKeys<'a, N>,
+{
+    type Iter = Store::Iter;
+
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.storage.chunk_keys()
+    }
+}
+
+/// Dirty chunks stay dirty at their new keys, but the translation itself doesn't make any chunks dirty.
+impl<N, Store> TranslateChunkKeys<N> for DirtyTracking<N, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    Store: TranslateChunkKeys<N>,
+{
+    fn translate_chunk_keys(&mut self, lod0_delta: PointN<N>) {
+        self.storage.translate_chunk_keys(lod0_delta);
+        self.dirty = self
+            .dirty
+            .drain()
+            .map(|key| key.translated(lod0_delta))
+            .collect();
+    }
+}
+
+impl<N, T, Bldr, Store> ChunkMap<N, T, Bldr, DirtyTracking<N, Store>> {
+    /// Removes and returns the keys of all chunks mutated since the last call to `drain_dirty`.
+    pub fn drain_dirty(&mut self) -> impl Iterator<Item = ChunkKey<N>> + '_ {
+        self.storage_mut().drain_dirty()
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn writes_mark_chunks_dirty() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+        let mut map = builder.build_with_rw_storage(DirtyTracking::new(SmallKeyHashMap::default()));
+
+        let key1 = ChunkKey::new(0, Point3i::ZERO);
+        let key2 = ChunkKey::new(0, Point3i::fill(16));
+        let key3 = ChunkKey::new(1, Point3i::ZERO);
+
+        *map.get_mut_point(0, Point3i::fill(1)) = 1;
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(2), Point3i::fill(2));
+        copy_extent(&extent, &Func(|_: Point3i| 2), &mut map.lod_view_mut(1));
+        assert!(map.storage().is_dirty(key1));
+        assert!(!map.storage().is_dirty(key2));
+        assert!(map.storage().is_dirty(key3));
+
+        let drained: SmallKeyHashSet<_> = map.drain_dirty().collect();
+        assert_eq!(drained.len(), 2);
+        assert!(drained.contains(&key1) && drained.contains(&key3));
+        assert_eq!(map.storage().dirty_keys().count(), 0);
+
+        // Missing chunks aren't dirtied by a failed `get_mut_chunk`.
+        assert!(map.get_mut_chunk(key2).is_none());
+        assert_eq!(map.storage().dirty_keys().count(), 0);
+
+        // Deleting a chunk makes it dirty.
+        map.delete_chunk(key1);
+        assert_eq!(map.drain_dirty().collect::<Vec<_>>(), vec![key1]);
+    }
+}
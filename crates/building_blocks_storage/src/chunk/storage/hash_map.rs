@@ -1,6 +1,8 @@
-use crate::{ChunkMap, ChunkMapBuilder, SmallKeyHashMap};
+use crate::{Array, ChunkMap, ChunkMapBuilder, SmallKeyHashMap};
 
-use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys};
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys, TranslateChunkKeys};
+
+use building_blocks_core::prelude::*;
 
 use core::hash::Hash;
 use std::collections::hash_map;
@@ -66,6 +68,27 @@ where
     }
 }
 
+impl<N, Chan> TranslateChunkKeys<N> for SmallKeyHashMap<ChunkKey<N>, Array<N, Chan>>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+{
+    fn translate_chunk_keys(&mut self, lod0_delta: PointN<N>) {
+        let chunks = std::mem::take(self);
+        self.reserve(chunks.len());
+        for (key, mut chunk) in chunks.into_iter() {
+            assert!(
+                (lod0_delta >> key.lod as i32) % chunk.extent().shape == PointN::ZERO,
+                "Translation is not aligned to the chunks at LOD {}",
+                key.lod
+            );
+            let new_key = key.translated(lod0_delta);
+            chunk.set_minimum(new_key.minimum);
+            self.insert(new_key, chunk);
+        }
+    }
+}
+
 /// A `ChunkMap` using `HashMap` as chunk storage.
 pub type ChunkHashMap<N, T, Bldr> =
     ChunkMap<N, T, Bldr, SmallKeyHashMap<ChunkKey<N>, <Bldr as ChunkMapBuilder<N, T>>::Chunk>>;
@@ -96,6 +119,10 @@ pub mod multichannel_aliases {
     /// A 2-dimensional, 6-channel `ChunkHashMap`.
     pub type ChunkHashMap2x6<A, B, C, D, E, F> =
         ChunkHashMap2<(A, B, C, D, E, F), ChunkMapBuilder2x6<A, B, C, D, E, F>>;
+    pub type ChunkHashMap2x7<A, B, C, D, E, F, G> =
+        ChunkHashMap2<(A, B, C, D, E, F, G), ChunkMapBuilder2x7<A, B, C, D, E, F, G>>;
+    pub type ChunkHashMap2x8<A, B, C, D, E, F, G, H> =
+        ChunkHashMap2<(A, B, C, D, E, F, G, H), ChunkMapBuilder2x8<A, B, C, D, E, F, G, H>>;
 
     /// A 3-dimensional, 1-channel `ChunkHashMap`.
     pub type ChunkHashMap3x1<A> = ChunkHashMap3<A, ChunkMapBuilder3x1<A>>;
@@ -112,6 +139,10 @@ pub mod multichannel_aliases {
     /// A 3-dimensional, 6-channel `ChunkHashMap`.
     pub type ChunkHashMap3x6<A, B, C, D, E, F> =
         ChunkHashMap3<(A, B, C, D, E, F), ChunkMapBuilder3x6<A, B, C, D, E, F>>;
+    pub type ChunkHashMap3x7<A, B, C, D, E, F, G> =
+        ChunkHashMap3<(A, B, C, D, E, F, G), ChunkMapBuilder3x7<A, B, C, D, E, F, G>>;
+    pub type ChunkHashMap3x8<A, B, C, D, E, F, G, H> =
+        ChunkHashMap3<(A, B, C, D, E, F, G, H), ChunkMapBuilder3x8<A, B, C, D, E, F, G, H>>;
 }
 
 pub use multichannel_aliases::*;
@@ -0,0 +1,455 @@
+//! Read-only chunk storage over a single file, which can be memory-mapped, so a world can be much larger than RAM.
+//!
+//! `MappedChunkStorage::write_file` packs a set of chunks into one file, each compressed separately, followed by an index that
+//! is sorted by chunk key. `MappedChunkStorage::new` then wraps the bytes of that file without parsing or copying them, so
+//! with a memory-mapped file (e.g. `memmap2::Mmap`, or `MappedChunkStorage::open` with the "memmap2" feature), the OS pages
+//! chunks in and out on demand. Finding a chunk is a binary search of the index, and chunks are only decompressed when read,
+//! into a `LocalChunkCache`, just like reading from a `CompressibleChunkStorage`.
+//!
+//! To store raw chunks, use `NoCompression` as the bytes compression.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, FastArrayCompressionNx1, MappedChunkStorage3};
+//!
+//! let chunk_shape = Point3i::fill(16);
+//! let builder = ChunkMapBuilder3x1::new(chunk_shape, 0);
+//! let mut map = builder.build_with_hash_map_storage();
+//! map.fill_extent(0, &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32)), 1);
+//!
+//! let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+//! let mut file = Vec::new();
+//! MappedChunkStorage3::write_file(&mut file, &compression, map.storage().iter())?;
+//!
+//! // Usually the bytes would be memory-mapped from a file instead.
+//! let storage = MappedChunkStorage3::new(file, compression)?;
+//! assert_eq!(storage.len(), 8);
+//!
+//! let local_cache = LocalChunkCache3::new();
+//! let reader = builder.build_with_read_storage(storage.reader(&local_cache));
+//! assert_eq!(reader.clone_point(0, Point3i::fill(20)), 1);
+//! assert_eq!(reader.clone_point(0, Point3i::fill(40)), 0);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! # File Format
+//!
+//! All integers are little-endian.
+//!
+//! - the magic bytes `BBMC`
+//! - the compressed chunks, back to back
+//! - the index: one entry per chunk, sorted by the big-endian DB key of the chunk (see `DatabaseKey`), with the key bytes
+//!   followed by the `u64` byte offset and `u64` length of the compressed chunk
+//! - the footer: the `u64` byte offset of the index and the `u64` number of chunks
+//!
+//! The chunk values are only portable if the `compression` used respects endianness of the current machine. Use
+//! `BincodeCompression` if you absolutely need portability across machines with different endianness.
+
+use crate::{ChunkKey, ChunkReadStorage, Compression, DatabaseKey, LocalChunkCache};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use std::borrow::Borrow;
+use std::convert::TryInto;
+use std::io::{self, Write};
+
+const MAPPED_MAGIC: [u8; 4] = *b"BBMC";
+const FOOTER_BYTES: usize = 16;
+/// The offset and length that follow the key in each index entry.
+const ENTRY_LOCATION_BYTES: usize = 16;
+
+/// Read-only chunk storage over the bytes of a file written by `MappedChunkStorage::write_file`.
+pub struct MappedChunkStorage<N, Compr, Bytes = Vec<u8>> {
+    bytes: Bytes,
+    compression: Compr,
+    index_offset: usize,
+    num_chunks: usize,
+    key_bytes: usize,
+    marker: PhantomData<N>,
+}
+
+/// A 2D `MappedChunkStorage`.
+pub type MappedChunkStorage2<Compr, Bytes = Vec<u8>> = MappedChunkStorage<[i32; 2], Compr, Bytes>;
+/// A 3D `MappedChunkStorage`.
+pub type MappedChunkStorage3<Compr, Bytes = Vec<u8>> = MappedChunkStorage<[i32; 3], Compr, Bytes>;
+
+impl<N, Compr, Bytes> MappedChunkStorage<N, Compr, Bytes>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression,
+    Bytes: Deref<Target = [u8]>,
+{
+    /// Wraps the `bytes` of a file written by `write_file` with the same `compression`. Only the footer is read, so this is fast
+    /// even for huge files. Corrupt chunks are only detected when they're read.
+    pub fn new(bytes: Bytes, compression: Compr) -> io::Result<Self> {
+        let key_bytes = num_key_bytes::<N>();
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        if bytes.len() < MAPPED_MAGIC.len() + FOOTER_BYTES || bytes[..4] != MAPPED_MAGIC {
+            return Err(invalid("not a mapped chunk file"));
+        }
+        let footer_start = bytes.len() - FOOTER_BYTES;
+        let index_offset = read_u64(&bytes[footer_start..]) as usize;
+        let num_chunks = read_u64(&bytes[footer_start + 8..]) as usize;
+        let index_bytes = num_chunks
+            .checked_mul(key_bytes + ENTRY_LOCATION_BYTES)
+            .ok_or_else(|| invalid("corrupt chunk index"))?;
+        if index_offset < MAPPED_MAGIC.len()
+            || index_offset.checked_add(index_bytes) != Some(footer_start)
+        {
+            return Err(invalid("corrupt chunk index"));
+        }
+
+        Ok(Self {
+            bytes,
+            compression,
+            index_offset,
+            num_chunks,
+            key_bytes,
+            marker: PhantomData,
+        })
+    }
+
+    /// The number of chunks in the file.
+    pub fn len(&self) -> usize {
+        self.num_chunks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_chunks == 0
+    }
+
+    /// The raw bytes of the file.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    pub fn compression(&self) -> &Compr {
+        &self.compression
+    }
+
+    /// Iterates over the keys of all chunks, in DB key order.
+    pub fn chunk_keys(&self) -> impl Iterator<Item = ChunkKey<N>> + '_ {
+        (0..self.num_chunks).map(move |i| {
+            ChunkKey::<N>::from_ord_key(ChunkKey::<N>::ord_key_from_be_bytes(self.entry_key(i)))
+        })
+    }
+
+    /// The compressed bytes of the chunk at `key`, if it's in the file.
+    pub fn get_compressed_bytes(&self, key: ChunkKey<N>) -> Option<&[u8]> {
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+        let key_bytes = key_bytes.as_ref();
+
+        // Binary search the sorted index.
+        let (mut lo, mut hi) = (0, self.num_chunks);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match self.entry_key(mid).cmp(key_bytes) {
+                core::cmp::Ordering::Less => lo = mid + 1,
+                core::cmp::Ordering::Greater => hi = mid,
+                core::cmp::Ordering::Equal => {
+                    let location = &self.entry(mid)[self.key_bytes..];
+                    let offset = read_u64(location) as usize;
+                    let len = read_u64(&location[8..]) as usize;
+
+                    return self.bytes.get(offset..offset.checked_add(len)?);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Reads and decompresses the chunk at `key`, if it's in the file. This does not use any cache.
+    ///
+    /// Returns an error if the chunk can't be decompressed, e.g. because the file is corrupt.
+    pub fn read_chunk(&self, key: ChunkKey<N>) -> io::Result<Option<Compr::Data>> {
+        self.get_compressed_bytes(key)
+            .map(Compr::decompress_from_reader)
+            .transpose()
+    }
+
+    /// Returns a `ChunkReadStorage` that decompresses chunks into `local_cache` the first time they are read.
+    pub fn reader<'a>(
+        &'a self,
+        local_cache: &'a LocalChunkCache<N, Compr::Data>,
+    ) -> MappedChunkStorageReader<'a, N, Compr, Bytes> {
+        MappedChunkStorageReader {
+            storage: self,
+            local_cache,
+        }
+    }
+
+    fn entry(&self, i: usize) -> &[u8] {
+        let entry_bytes = self.key_bytes + ENTRY_LOCATION_BYTES;
+        let start = self.index_offset + i * entry_bytes;
+
+        &self.bytes[start..start + entry_bytes]
+    }
+
+    fn entry_key(&self, i: usize) -> &[u8] {
+        &self.entry(i)[..self.key_bytes]
+    }
+}
+
+#[cfg(feature = "memmap2")]
+impl<N, Compr> MappedChunkStorage<N, Compr, memmap2::Mmap>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression,
+{
+    /// Memory-maps the file at `path`.
+    ///
+    /// # Safety
+    /// The file must not be modified while it is mapped, or the chunks read from it are undefined.
+    pub unsafe fn open(path: impl AsRef<std::path::Path>, compression: Compr) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        Self::new(memmap2::Mmap::map(&file)?, compression)
+    }
+}
+
+impl<N, Compr> MappedChunkStorage<N, Compr>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression,
+{
+    /// Compresses all of the `chunks` with `compression` and writes them to `writer` in the format read by `new`. Only the
+    /// compressed chunks are kept in memory while writing. If the same key appears more than once, only the last chunk is kept.
+    pub fn write_file<Data>(
+        mut writer: impl Write,
+        compression: &Compr,
+        chunks: impl IntoIterator<Item = (impl Borrow<ChunkKey<N>>, Data)>,
+    ) -> io::Result<()>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        writer.write_all(&MAPPED_MAGIC)?;
+
+        let mut index = Vec::new();
+        let mut offset = MAPPED_MAGIC.len() as u64;
+        for (key, chunk) in chunks.into_iter() {
+            let compressed = compression.compress(chunk.borrow()).take_bytes();
+            writer.write_all(&compressed)?;
+            index.push((
+                ChunkKey::<N>::into_ord_key(*key.borrow()),
+                offset,
+                compressed.len() as u64,
+            ));
+            offset += compressed.len() as u64;
+        }
+
+        // A stable sort keeps duplicate keys in order, so the last one wins.
+        index.sort_by_key(|(key, _, _)| *key);
+        index.reverse();
+        index.dedup_by_key(|(key, _, _)| *key);
+        index.reverse();
+
+        for (key, chunk_offset, len) in index.iter() {
+            writer.write_all(ChunkKey::<N>::ord_key_to_be_bytes(*key).as_ref())?;
+            writer.write_all(&chunk_offset.to_le_bytes())?;
+            writer.write_all(&len.to_le_bytes())?;
+        }
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&(index.len() as u64).to_le_bytes())?;
+
+        writer.flush()
+    }
+}
+
+/// A `ChunkReadStorage` for a `MappedChunkStorage`, from `MappedChunkStorage::reader`.
+///
+/// # Panics
+///
+/// `ChunkReadStorage::get` can't return an error, so reading a chunk that fails to decompress panics. Use
+/// `MappedChunkStorage::read_chunk` to handle corrupt files gracefully.
+pub struct MappedChunkStorageReader<'a, N, Compr, Bytes>
+where
+    Compr: Compression,
+{
+    pub storage: &'a MappedChunkStorage<N, Compr, Bytes>,
+    pub local_cache: &'a LocalChunkCache<N, Compr::Data>,
+}
+
+impl<'a, N, Compr, Bytes> ChunkReadStorage<N, Compr::Data>
+    for MappedChunkStorageReader<'a, N, Compr, Bytes>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N> + Eq + Hash,
+    Compr: Compression,
+    Bytes: Deref<Target = [u8]>,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Compr::Data> {
+        let Self {
+            storage,
+            local_cache,
+        } = self;
+
+        local_cache.get(&key).or_else(|| {
+            let compressed_bytes = storage.get_compressed_bytes(key)?;
+
+            Some(local_cache.get_or_insert_with(key, || {
+                Compr::decompress_from_reader(compressed_bytes)
+                    .expect("Failed to decompress a chunk from a mapped chunk file")
+            }))
+        })
+    }
+}
+
+fn num_key_bytes<N>() -> usize
+where
+    ChunkKey<N>: DatabaseKey<N>,
+{
+    ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(0))
+        .as_ref()
+        .len()
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, FastArrayCompressionNx1};
+
+    #[test]
+    fn round_trip_chunks_at_every_lod() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        map.fill_extent_with(
+            0,
+            &Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16)),
+            |p: Point3i| p.x() + 10 * p.y() + 100 * p.z(),
+        );
+        map.fill_extent(
+            2,
+            &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)),
+            7,
+        );
+
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let mut file = Vec::new();
+        MappedChunkStorage3::write_file(&mut file, &compression, map.storage().iter()).unwrap();
+        let storage = MappedChunkStorage3::new(file, compression).unwrap();
+
+        assert_eq!(storage.len(), map.storage().len());
+        let mut keys: Vec<_> = storage.chunk_keys().collect();
+        let mut expected_keys: Vec<_> = map.storage().keys().cloned().collect();
+        keys.sort_by_key(|k| (k.lod, k.minimum.0));
+        expected_keys.sort_by_key(|k| (k.lod, k.minimum.0));
+        assert_eq!(keys, expected_keys);
+
+        for key in expected_keys.into_iter() {
+            assert_eq!(
+                storage.read_chunk(key).unwrap().as_ref(),
+                map.get_chunk(key)
+            );
+        }
+        assert!(storage
+            .read_chunk(ChunkKey::new(0, Point3i::fill(100)))
+            .unwrap()
+            .is_none());
+
+        let local_cache = LocalChunkCache3::new();
+        let reader = builder.build_with_read_storage(storage.reader(&local_cache));
+        assert_eq!(reader.clone_point(0, PointN([-1, 2, 3])), -1 + 20 + 300);
+        assert_eq!(reader.clone_point(2, Point3i::fill(1)), 7);
+        assert_eq!(reader.clone_point(0, Point3i::fill(50)), 0);
+    }
+
+    #[test]
+    fn last_duplicate_key_wins() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let chunks = vec![
+            (key, Array3x1::fill(extent, 1)),
+            (key, Array3x1::fill(extent, 2)),
+        ];
+
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let mut file = Vec::new();
+        MappedChunkStorage3::write_file(&mut file, &compression, chunks).unwrap();
+        let storage = MappedChunkStorage3::new(file, compression).unwrap();
+
+        assert_eq!(storage.len(), 1);
+        assert_eq!(
+            storage.read_chunk(key).unwrap(),
+            Some(Array3x1::fill(extent, 2))
+        );
+    }
+
+    #[test]
+    fn corrupt_files_are_rejected() {
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let new_storage = |file| MappedChunkStorage3::new(file, compression);
+        assert!(new_storage(b"BBMC".to_vec()).is_err());
+
+        let mut file = Vec::new();
+        let no_chunks: Vec<(ChunkKey3, Array3x1<i32>)> = Vec::new();
+        MappedChunkStorage3::write_file(&mut file, &compression, no_chunks).unwrap();
+        assert!(new_storage(file.clone()).unwrap().is_empty());
+
+        file[0] = b'X';
+        assert!(new_storage(file).is_err());
+    }
+
+    // Writes one chunk, then truncates it by overwriting its length in the index, which is the last field before the footer.
+    fn file_with_truncated_chunk(key: ChunkKey3) -> Vec<u8> {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(2));
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let mut file = Vec::new();
+        MappedChunkStorage3::write_file(
+            &mut file,
+            &compression,
+            vec![(key, Array3x1::fill(extent, 1))],
+        )
+        .unwrap();
+
+        let len_start = file.len() - FOOTER_BYTES - 8;
+        file[len_start..len_start + 8].copy_from_slice(&1u64.to_le_bytes());
+
+        file
+    }
+
+    #[test]
+    fn corrupt_chunks_are_errors() {
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let storage =
+            MappedChunkStorage3::new(file_with_truncated_chunk(key), compression).unwrap();
+
+        assert_eq!(storage.get_compressed_bytes(key).map(|b| b.len()), Some(1));
+        let result: io::Result<Option<Array3x1<i32>>> = storage.read_chunk(key);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to decompress a chunk from a mapped chunk file")]
+    fn reader_panics_on_corrupt_chunks() {
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let storage =
+            MappedChunkStorage3::new(file_with_truncated_chunk(key), compression).unwrap();
+
+        let local_cache: LocalChunkCache3<Array3x1<i32>> = LocalChunkCache3::new();
+        let reader = storage.reader(&local_cache);
+        reader.get(key);
+    }
+}
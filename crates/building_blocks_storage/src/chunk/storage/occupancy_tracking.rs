@@ -0,0 +1,529 @@
+//! A chunk storage wrapper that keeps a small occupancy summary of every 3D chunk.
+//!
+//! Culling, ray marching, and "is this chunk all air" tests don't need to know the value of every voxel. They only need to know
+//! which parts of a chunk have anything in them. Wrapping the storage of a `ChunkMap3` with `OccupancyTracking` maintains a
+//! `ChunkOccupancy` for every chunk, which divides the chunk into `4x4x4` subcells and stores one bit per subcell for whether
+//! it has any non-empty voxels, and another for whether all of its voxels are non-empty. These summaries are only 16 bytes
+//! each, and they can be read without touching (or decompressing) the chunks.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, OccupancyTracking, SmallKeyHashMap};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), false);
+//! let mut map = builder.build_with_rw_storage(OccupancyTracking::new(SmallKeyHashMap::default()));
+//!
+//! let key = ChunkKey::new(0, Point3i::ZERO);
+//! let chunk_extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! map.write_chunk(key, Array3x1::fill(chunk_extent, false));
+//! assert!(map.chunk_occupancy(key).unwrap().is_empty());
+//!
+//! // Chunks that are mutated in place need to be refreshed.
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(5)) = true;
+//! assert_eq!(map.chunk_occupancy(key), None);
+//! assert_eq!(map.storage_mut().refresh_stale_chunks(), 1);
+//!
+//! let occupancy = map.chunk_occupancy(key).unwrap();
+//! assert!(!occupancy.is_empty());
+//! assert!(occupancy.is_subcell_occupied(PointN([1, 1, 1])));
+//!
+//! // A ray marcher can skip over empty subcells.
+//! assert_eq!(
+//!     map.empty_subcell_containing(0, Point3i::fill(1)),
+//!     Some(Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)))
+//! );
+//! assert_eq!(map.empty_subcell_containing(0, Point3i::fill(5)), None);
+//! ```
+
+use crate::{
+    Array, Channels, Chunk, ChunkKey, ChunkKey3, ChunkMap, Get, IsEmpty, SmallKeyHashMap,
+    SmallKeyHashSet, Stride,
+};
+
+use super::{ChunkReadStorage, ChunkWriteStorage, IterChunkKeys, TranslateChunkKeys};
+
+use building_blocks_core::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Which of the `4x4x4` subcells of a chunk have any non-empty voxels, and which are entirely non-empty, as defined by
+/// `IsEmpty`.
+///
+/// The bit for subcell `s` is `1 << (s.x + 4 * s.y + 16 * s.z)`. Every dimension of the chunk shape must be at least `4`, so
+/// each subcell has a shape of `chunk_shape / 4`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ChunkOccupancy {
+    occupied: u64,
+    solid: u64,
+}
+
+impl ChunkOccupancy {
+    /// Every voxel is empty.
+    pub const EMPTY: Self = Self {
+        occupied: 0,
+        solid: 0,
+    };
+
+    /// No voxel is empty.
+    pub const SOLID: Self = Self {
+        occupied: u64::MAX,
+        solid: u64::MAX,
+    };
+
+    /// Summarizes all of the voxels in `chunk`.
+    pub fn from_chunk<Chan>(chunk: &Array<[i32; 3], Chan>) -> Self
+    where
+        Chan: Channels,
+        Chan::Data: IsEmpty,
+        Array<[i32; 3], Chan>: Get<Stride, Item = Chan::Data>,
+    {
+        let shape = chunk.extent().shape;
+        let subcell_shape = Self::subcell_shape(shape);
+        let [sx, sy, sz] = [
+            subcell_shape.x().trailing_zeros(),
+            subcell_shape.y().trailing_zeros(),
+            subcell_shape.z().trailing_zeros(),
+        ];
+
+        let mut occupied = 0;
+        let mut has_empty = 0;
+        let mut stride = 0;
+        for z in 0..shape.z() {
+            for y in 0..shape.y() {
+                let row_index = ((z >> sz) << 4) | ((y >> sy) << 2);
+                for x in 0..shape.x() {
+                    let bit = 1 << (row_index | (x >> sx));
+                    if chunk.get(Stride(stride)).is_empty() {
+                        has_empty |= bit;
+                    } else {
+                        occupied |= bit;
+                    }
+                    stride += 1;
+                }
+            }
+        }
+
+        Self {
+            occupied,
+            solid: !has_empty,
+        }
+    }
+
+    /// Summarizes a chunk with `value` at every point.
+    pub fn from_uniform_value<T: IsEmpty>(value: &T) -> Self {
+        if value.is_empty() {
+            Self::EMPTY
+        } else {
+            Self::SOLID
+        }
+    }
+
+    /// The shape of each subcell of a chunk with `chunk_shape`.
+    pub fn subcell_shape(chunk_shape: Point3i) -> Point3i {
+        assert!(chunk_shape.x() >= 4 && chunk_shape.y() >= 4 && chunk_shape.z() >= 4);
+
+        chunk_shape >> 2
+    }
+
+    /// The subcell of a chunk with `chunk_extent` that contains `p`.
+    pub fn subcell_containing(chunk_extent: &Extent3i, p: Point3i) -> Point3i {
+        debug_assert!(chunk_extent.contains(p));
+        let subcell_shape = Self::subcell_shape(chunk_extent.shape);
+        let offset = p - chunk_extent.minimum;
+
+        PointN([
+            offset.x() / subcell_shape.x(),
+            offset.y() / subcell_shape.y(),
+            offset.z() / subcell_shape.z(),
+        ])
+    }
+
+    /// The extent of `subcell` in a chunk with `chunk_extent`.
+    pub fn subcell_extent(chunk_extent: &Extent3i, subcell: Point3i) -> Extent3i {
+        let subcell_shape = Self::subcell_shape(chunk_extent.shape);
+        let offset = PointN([
+            subcell.x() * subcell_shape.x(),
+            subcell.y() * subcell_shape.y(),
+            subcell.z() * subcell_shape.z(),
+        ]);
+
+        Extent3i::from_min_and_shape(chunk_extent.minimum + offset, subcell_shape)
+    }
+
+    /// One bit per subcell that has any non-empty voxels.
+    pub fn occupied_mask(&self) -> u64 {
+        self.occupied
+    }
+
+    /// One bit per subcell that has no empty voxels.
+    pub fn solid_mask(&self) -> u64 {
+        self.solid
+    }
+
+    /// Every voxel of the chunk is empty.
+    pub fn is_empty(&self) -> bool {
+        self.occupied == 0
+    }
+
+    /// No voxel of the chunk is empty.
+    pub fn is_solid(&self) -> bool {
+        self.solid == u64::MAX
+    }
+
+    pub fn is_subcell_occupied(&self, subcell: Point3i) -> bool {
+        self.occupied & subcell_bit(subcell) != 0
+    }
+
+    pub fn is_subcell_solid(&self, subcell: Point3i) -> bool {
+        self.solid & subcell_bit(subcell) != 0
+    }
+
+    /// Returns `false` if every voxel of `extent` in the chunk with `chunk_extent` is known to be empty, i.e. anything inside of
+    /// `extent` can be culled. This is conservative, so `true` only means that some overlapping subcell is occupied.
+    pub fn may_intersect(&self, chunk_extent: &Extent3i, extent: &Extent3i) -> bool {
+        let overlap = chunk_extent.intersection(extent);
+        if overlap.is_empty() {
+            return false;
+        }
+        let min_subcell = Self::subcell_containing(chunk_extent, overlap.minimum);
+        let max_subcell = Self::subcell_containing(chunk_extent, overlap.max());
+
+        Extent3i::from_min_and_max(min_subcell, max_subcell)
+            .iter_points()
+            .any(|subcell| self.is_subcell_occupied(subcell))
+    }
+}
+
+fn subcell_bit(subcell: Point3i) -> u64 {
+    debug_assert!(subcell.x() < 4 && subcell.y() < 4 && subcell.z() < 4);
+
+    1 << (subcell.x() | (subcell.y() << 2) | (subcell.z() << 4))
+}
+
+/// Wraps a chunk storage `Store` of 3D `Array` chunks and keeps a `ChunkOccupancy` of every chunk.
+///
+/// Chunks that are written or replaced whole are summarized immediately. Chunks that are mutably borrowed become "stale," since
+/// the borrow can outlive any call into the storage, so they have no summary until `refresh_chunk` or `refresh_stale_chunks`
+/// is called. Mutations made directly to the inner storage via `inner_mut` are not tracked.
+pub struct OccupancyTracking<Store> {
+    storage: Store,
+    occupancy: SmallKeyHashMap<ChunkKey3, ChunkOccupancy>,
+    stale: SmallKeyHashSet<ChunkKey3>,
+}
+
+impl<Store> OccupancyTracking<Store> {
+    /// Wraps `storage`, which must be empty, because its chunks would not be summarized.
+    pub fn new(storage: Store) -> Self {
+        Self {
+            storage,
+            occupancy: SmallKeyHashMap::default(),
+            stale: SmallKeyHashSet::default(),
+        }
+    }
+
+    /// Borrow the inner storage.
+    pub fn inner(&self) -> &Store {
+        &self.storage
+    }
+
+    /// Mutably borrow the inner storage. Mutations made this way are not tracked.
+    pub fn inner_mut(&mut self) -> &mut Store {
+        &mut self.storage
+    }
+
+    /// Consumes the wrapper, dropping the summaries.
+    pub fn into_inner(self) -> Store {
+        self.storage
+    }
+
+    /// The summary of the chunk at `key`, unless there is no such chunk or it's stale.
+    pub fn occupancy(&self, key: ChunkKey3) -> Option<ChunkOccupancy> {
+        self.occupancy.get(&key).cloned()
+    }
+
+    /// Returns `true` iff the chunk at `key` was mutably borrowed since it was last summarized.
+    pub fn is_stale(&self, key: ChunkKey3) -> bool {
+        self.stale.contains(&key)
+    }
+
+    /// The keys of all stale chunks.
+    pub fn stale_keys(&self) -> impl Iterator<Item = &ChunkKey3> {
+        self.stale.iter()
+    }
+
+    fn mark_stale(&mut self, key: ChunkKey3) {
+        self.occupancy.remove(&key);
+        self.stale.insert(key);
+    }
+
+    fn forget(&mut self, key: ChunkKey3) {
+        self.occupancy.remove(&key);
+        self.stale.remove(&key);
+    }
+}
+
+impl<Store> OccupancyTracking<Store> {
+    /// Summarizes the chunk at `key` again, e.g. after mutating it in place. Returns the new summary, or `None` if there is no
+    /// such chunk.
+    pub fn refresh_chunk<Chan>(&mut self, key: ChunkKey3) -> Option<ChunkOccupancy>
+    where
+        Chan: Channels,
+        Chan::Data: IsEmpty,
+        Array<[i32; 3], Chan>: Get<Stride, Item = Chan::Data>,
+        Store: ChunkReadStorage<[i32; 3], Array<[i32; 3], Chan>>,
+    {
+        self.stale.remove(&key);
+        let occupancy = self
+            .storage
+            .get(key)
+            .map(ChunkOccupancy::from_chunk)
+            .or_else(|| {
+                self.storage
+                    .get_uniform(key)
+                    .map(ChunkOccupancy::from_uniform_value)
+            });
+        match occupancy {
+            Some(occupancy) => self.occupancy.insert(key, occupancy),
+            None => self.occupancy.remove(&key),
+        };
+
+        occupancy
+    }
+
+    /// Summarizes every stale chunk again. Returns the number of chunks that were refreshed.
+    pub fn refresh_stale_chunks<Chan>(&mut self) -> usize
+    where
+        Chan: Channels,
+        Chan::Data: IsEmpty,
+        Array<[i32; 3], Chan>: Get<Stride, Item = Chan::Data>,
+        Store: ChunkReadStorage<[i32; 3], Array<[i32; 3], Chan>>,
+    {
+        let keys: Vec<_> = self.stale.iter().cloned().collect();
+        for &key in keys.iter() {
+            self.refresh_chunk(key);
+        }
+
+        keys.len()
+    }
+
+    fn summarize(&mut self, key: ChunkKey3, occupancy: ChunkOccupancy) {
+        self.stale.remove(&key);
+        self.occupancy.insert(key, occupancy);
+    }
+}
+
+impl<Ch, Store> ChunkReadStorage<[i32; 3], Ch> for OccupancyTracking<Store>
+where
+    Store: ChunkReadStorage<[i32; 3], Ch>,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey3) -> Option<&Ch> {
+        self.storage.get(key)
+    }
+
+    #[inline]
+    fn get_uniform(&self, key: ChunkKey3) -> Option<&Ch::Data>
+    where
+        Ch: Chunk,
+    {
+        self.storage.get_uniform(key)
+    }
+}
+
+impl<Chan, Store> ChunkWriteStorage<[i32; 3], Array<[i32; 3], Chan>> for OccupancyTracking<Store>
+where
+    Chan: Channels,
+    Chan::Data: IsEmpty,
+    Array<[i32; 3], Chan>: Get<Stride, Item = Chan::Data>,
+    Store: ChunkWriteStorage<[i32; 3], Array<[i32; 3], Chan>>,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey3) -> Option<&mut Array<[i32; 3], Chan>> {
+        let chunk = self.storage.get_mut(key);
+        if chunk.is_some() {
+            self.occupancy.remove(&key);
+            self.stale.insert(key);
+        }
+
+        chunk
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey3,
+        create_chunk: impl FnOnce() -> Array<[i32; 3], Chan>,
+    ) -> &mut Array<[i32; 3], Chan> {
+        self.mark_stale(key);
+
+        self.storage.get_mut_or_insert_with(key, create_chunk)
+    }
+
+    #[inline]
+    fn replace(
+        &mut self,
+        key: ChunkKey3,
+        chunk: Array<[i32; 3], Chan>,
+    ) -> Option<Array<[i32; 3], Chan>> {
+        self.summarize(key, ChunkOccupancy::from_chunk(&chunk));
+
+        self.storage.replace(key, chunk)
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey3, chunk: Array<[i32; 3], Chan>) {
+        self.summarize(key, ChunkOccupancy::from_chunk(&chunk));
+        self.storage.write(key, chunk);
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey3) {
+        self.forget(key);
+        self.storage.delete(key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey3) -> Option<Array<[i32; 3], Chan>> {
+        self.forget(key);
+
+        self.storage.pop(key)
+    }
+}
+
+impl<'a, Store> IterChunkKeys<'a, [i32; 3]> for OccupancyTracking<Store>
+where
+    Store: IterChunkKeys<'a, [i32; 3]>,
+{
+    type Iter = Store::Iter;
+
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.storage.chunk_keys()
+    }
+}
+
+/// The summaries move with their chunks, since the chunk data is unchanged.
+impl<Store> TranslateChunkKeys<[i32; 3]> for OccupancyTracking<Store>
+where
+    Store: TranslateChunkKeys<[i32; 3]>,
+{
+    fn translate_chunk_keys(&mut self, lod0_delta: Point3i) {
+        self.storage.translate_chunk_keys(lod0_delta);
+        self.occupancy = self
+            .occupancy
+            .drain()
+            .map(|(key, occupancy)| (key.translated(lod0_delta), occupancy))
+            .collect();
+        self.stale = self
+            .stale
+            .drain()
+            .map(|key| key.translated(lod0_delta))
+            .collect();
+    }
+}
+
+impl<T, Bldr, Store> ChunkMap<[i32; 3], T, Bldr, OccupancyTracking<Store>> {
+    /// The summary of the chunk at `key`, unless there is no such chunk or it's stale.
+    pub fn chunk_occupancy(&self, key: ChunkKey3) -> Option<ChunkOccupancy> {
+        self.storage().occupancy(key)
+    }
+
+    /// If `p` is in an empty subcell of a summarized chunk at level of detail `lod`, returns the extent of that subcell, which
+    /// a ray can skip over entirely. Missing and stale chunks return `None`, since nothing is known about them.
+    pub fn empty_subcell_containing(&self, lod: u8, p: Point3i) -> Option<Extent3i> {
+        let chunk_min = self.indexer.min_of_chunk_containing_point(p);
+        let occupancy = self.chunk_occupancy(ChunkKey::new(lod, chunk_min))?;
+        let chunk_extent = self.indexer.extent_for_chunk_with_min(chunk_min);
+        let subcell = ChunkOccupancy::subcell_containing(&chunk_extent, p);
+
+        if occupancy.is_subcell_occupied(subcell) {
+            None
+        } else {
+            Some(ChunkOccupancy::subcell_extent(&chunk_extent, subcell))
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, UniformChunkFolding};
+
+    #[test]
+    fn summaries_follow_writes_and_refreshes() {
+        let chunk_shape = PointN([8, 16, 4]);
+        let builder = ChunkMapBuilder3x1::new(chunk_shape, false);
+        let mut map =
+            builder.build_with_rw_storage(OccupancyTracking::new(SmallKeyHashMap::default()));
+
+        let key = ChunkKey::new(0, Point3i::ZERO);
+        let chunk_extent = Extent3i::from_min_and_shape(Point3i::ZERO, chunk_shape);
+        // Subcells have shape 2x4x1. Fill the whole subcell (1, 2, 3) and one voxel of (0, 0, 0).
+        let solid_subcell = Extent3i::from_min_and_shape(PointN([2, 8, 3]), PointN([2, 4, 1]));
+        let chunk = Array3x1::fill_with(chunk_extent, |p: Point3i| {
+            solid_subcell.contains(p) || p == PointN([1, 3, 0])
+        });
+        map.write_chunk(key, chunk);
+
+        let occupancy = map.chunk_occupancy(key).unwrap();
+        assert_eq!(occupancy.occupied_mask().count_ones(), 2);
+        assert!(occupancy.is_subcell_solid(PointN([1, 2, 3])));
+        assert!(occupancy.is_subcell_occupied(PointN([0, 0, 0])));
+        assert!(!occupancy.is_subcell_solid(PointN([0, 0, 0])));
+        assert!(!occupancy.may_intersect(
+            &chunk_extent,
+            &Extent3i::from_min_and_shape(PointN([2, 0, 0]), PointN([6, 8, 3]))
+        ));
+        assert!(occupancy.may_intersect(
+            &chunk_extent,
+            &Extent3i::from_min_and_shape(PointN([3, 11, 3]), Point3i::fill(10))
+        ));
+
+        map.lod_view_mut(0).fill_extent(&chunk_extent, true);
+        assert!(map.storage().is_stale(key));
+        assert_eq!(map.chunk_occupancy(key), None);
+        map.storage_mut().refresh_chunk(key);
+        assert!(map.chunk_occupancy(key).unwrap().is_solid());
+
+        map.delete_chunk(key);
+        assert_eq!(map.chunk_occupancy(key), None);
+        assert_eq!(map.storage().stale_keys().count(), 0);
+    }
+
+    #[test]
+    fn folded_chunks_are_summarized_by_their_value() {
+        let chunk_shape = Point3i::fill(4);
+        let builder = ChunkMapBuilder3x1::new(chunk_shape, false);
+        let mut map = builder.build_with_rw_storage(OccupancyTracking::new(
+            UniformChunkFolding::new(SmallKeyHashMap::default(), chunk_shape),
+        ));
+
+        let key = ChunkKey::new(2, Point3i::fill(-4));
+        let extent = Extent3i::from_min_and_shape(key.minimum, chunk_shape);
+        map.write_chunk(key, Array3x1::fill(extent, true));
+        assert!(map.get_chunk(key).is_none());
+        assert_eq!(map.chunk_occupancy(key), Some(ChunkOccupancy::SOLID));
+
+        *map.get_mut_point(2, Point3i::fill(-1)) = false;
+        map.storage_mut().inner_mut().fold_all_chunks();
+        assert_eq!(map.storage_mut().refresh_stale_chunks(), 1);
+        let occupancy = map.chunk_occupancy(key).unwrap();
+        assert!(!occupancy.is_solid());
+        assert!(!occupancy.is_subcell_solid(Point3i::fill(3)));
+        assert_eq!(
+            map.empty_subcell_containing(2, Point3i::fill(-1)),
+            Some(Extent3i::from_min_and_shape(
+                Point3i::fill(-1),
+                Point3i::fill(1)
+            ))
+        );
+        assert_eq!(map.empty_subcell_containing(2, Point3i::fill(-3)), None);
+    }
+}
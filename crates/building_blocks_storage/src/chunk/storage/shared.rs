@@ -0,0 +1,219 @@
+//! An in-memory chunk storage whose chunks are shared between clones with copy-on-write semantics.
+//!
+//! Cloning a `ChunkMap` with `SharedChunkStorage` only clones an `Arc` for each chunk, so it's cheap to take a snapshot of a
+//! simulation or to make a speculative edit that might be thrown away. A chunk is only copied when it's mutated while another
+//! clone of the storage still shares it.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, SharedChunkStorage};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let mut map = builder.build_with_rw_storage(SharedChunkStorage::default());
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(1)) = 1;
+//!
+//! let snapshot = map.clone();
+//! let key = ChunkKey::new(0, Point3i::ZERO);
+//! assert!(map.storage().is_shared(key));
+//!
+//! // The first mutation copies the chunk, so the snapshot is unaffected.
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(1)) = 2;
+//! assert!(!map.storage().is_shared(key));
+//! assert_eq!(snapshot.lod_view(0).get(Point3i::fill(1)), 1);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 2);
+//! ```
+
+use crate::{ChunkMap, ChunkMapBuilder, SmallKeyHashMap};
+
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys};
+
+use core::hash::Hash;
+use std::collections::hash_map;
+use std::sync::Arc;
+
+/// A chunk storage that keeps every chunk behind an `Arc`. Clones of the storage share their chunks until they are mutated.
+/// See the [module docs](self) for an example.
+pub struct SharedChunkStorage<N, Ch> {
+    chunks: SmallKeyHashMap<ChunkKey<N>, Arc<Ch>>,
+}
+
+impl<N, Ch> Default for SharedChunkStorage<N, Ch> {
+    fn default() -> Self {
+        Self {
+            chunks: SmallKeyHashMap::default(),
+        }
+    }
+}
+
+impl<N, Ch> Clone for SharedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Clone,
+{
+    /// Only clones the `Arc` of each chunk.
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+        }
+    }
+}
+
+impl<N, Ch> SharedChunkStorage<N, Ch> {
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+impl<N, Ch> SharedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Hash + Eq,
+{
+    /// Borrow the shared pointer to the chunk at `key`, e.g. to store the same chunk in another map without copying it.
+    pub fn get_arc(&self, key: ChunkKey<N>) -> Option<&Arc<Ch>> {
+        self.chunks.get(&key)
+    }
+
+    /// Insert an already shared chunk at `key`, returning the old pointer.
+    pub fn replace_arc(&mut self, key: ChunkKey<N>, chunk: Arc<Ch>) -> Option<Arc<Ch>> {
+        self.chunks.insert(key, chunk)
+    }
+
+    /// Returns `true` iff the chunk at `key` is shared with another clone of this storage, so mutating it would copy it first.
+    pub fn is_shared(&self, key: ChunkKey<N>) -> bool {
+        self.chunks
+            .get(&key)
+            .map_or(false, |chunk| Arc::strong_count(chunk) > 1)
+    }
+}
+
+/// Takes the chunk out of `chunk` without copying it, unless it's still shared.
+fn unwrap_or_clone<Ch: Clone>(chunk: Arc<Ch>) -> Ch {
+    Arc::try_unwrap(chunk).unwrap_or_else(|shared| (*shared).clone())
+}
+
+impl<N, Ch> ChunkReadStorage<N, Ch> for SharedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Ch> {
+        self.chunks.get(&key).map(Arc::as_ref)
+    }
+}
+
+impl<N, Ch> ChunkWriteStorage<N, Ch> for SharedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Hash + Eq,
+    Ch: Clone,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Ch> {
+        self.chunks.get_mut(&key).map(Arc::make_mut)
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Ch,
+    ) -> &mut Ch {
+        Arc::make_mut(
+            self.chunks
+                .entry(key)
+                .or_insert_with(|| Arc::new(create_chunk())),
+        )
+    }
+
+    #[inline]
+    fn replace(&mut self, key: ChunkKey<N>, chunk: Ch) -> Option<Ch> {
+        self.chunks
+            .insert(key, Arc::new(chunk))
+            .map(unwrap_or_clone)
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey<N>, chunk: Ch) {
+        self.chunks.insert(key, Arc::new(chunk));
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey<N>) {
+        self.chunks.remove(&key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey<N>) -> Option<Ch> {
+        self.chunks.remove(&key).map(unwrap_or_clone)
+    }
+}
+
+impl<'a, N, Ch> IterChunkKeys<'a, N> for SharedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: 'a,
+    Ch: 'a,
+{
+    type Iter = hash_map::Keys<'a, ChunkKey<N>, Arc<Ch>>;
+
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.chunks.keys()
+    }
+}
+
+/// A `ChunkMap` using `SharedChunkStorage`, so clones of the map share chunks until they're mutated.
+pub type SharedChunkMap<N, T, Bldr> =
+    ChunkMap<N, T, Bldr, SharedChunkStorage<N, <Bldr as ChunkMapBuilder<N, T>>::Chunk>>;
+/// A 2-dimensional `SharedChunkMap`.
+pub type SharedChunkMap2<T, Bldr> = SharedChunkMap<[i32; 2], T, Bldr>;
+/// A 3-dimensional `SharedChunkMap`.
+pub type SharedChunkMap3<T, Bldr> = SharedChunkMap<[i32; 3], T, Bldr>;
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    use building_blocks_core::prelude::*;
+
+    #[test]
+    fn clones_share_chunks_until_mutated() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0);
+        let mut map = builder.build_with_rw_storage(SharedChunkStorage::default());
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        map.lod_view_mut(0).fill_extent(&extent, 1);
+        assert_eq!(map.storage().len(), 8);
+
+        let mut speculative = map.clone();
+        let key1 = ChunkKey::new(0, Point3i::ZERO);
+        let key2 = ChunkKey::new(0, Point3i::fill(4));
+        assert!(Arc::ptr_eq(
+            map.storage().get_arc(key1).unwrap(),
+            speculative.storage().get_arc(key1).unwrap()
+        ));
+
+        // Only the mutated chunk is copied.
+        *speculative.lod_view_mut(0).get_mut(Point3i::fill(5)) = 2;
+        assert!(!speculative.storage().is_shared(key2));
+        assert!(speculative.storage().is_shared(key1));
+        assert_eq!(map.lod_view(0).get(Point3i::fill(5)), 1);
+
+        // Popping a shared chunk copies it, and the other clone keeps its own.
+        let popped = speculative.pop_chunk(key1).unwrap();
+        assert_eq!(popped.get(Point3i::fill(1)), 1);
+        assert!(!map.storage().is_shared(key1));
+        assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 1);
+
+        drop(speculative);
+        assert!(!map.storage().is_shared(key2));
+    }
+}
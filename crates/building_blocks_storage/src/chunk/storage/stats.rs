@@ -0,0 +1,216 @@
+//! Statistics for tuning the memory use of chunk storage.
+//!
+//! `CompressibleChunkStorage::stats` takes a snapshot of how many chunks are cached and compressed in each LOD, how many bytes
+//! they use, and how often accesses hit the cache or chunks were evicted since the counters were last reset. For a
+//! `CompressibleChunkMap`, use `map.storage().stats()`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::prelude::*;
+//!
+//! let mut storage = FastCompressibleChunkStorageNx1::with_bytes_compression(NoCompression);
+//! for i in 0..4 {
+//!     let min = PointN([16 * i, 0, 0]);
+//!     let chunk = Array3x1::fill(Extent3i::from_min_and_shape(min, Point3i::fill(16)), 1u16);
+//!     storage.write(ChunkKey::new(0, min), chunk);
+//! }
+//! for _ in 0..4 {
+//!     storage.compress_lru();
+//! }
+//!
+//! // Mutable access decompresses the chunk, then it's cached for the next access.
+//! storage.get_mut(ChunkKey::new(0, Point3i::ZERO));
+//! storage.get_mut(ChunkKey::new(0, Point3i::ZERO));
+//!
+//! let stats = storage.stats();
+//! assert_eq!(stats.lods[&0].num_cached, 1);
+//! assert_eq!(stats.lods[&0].num_compressed, 3);
+//! assert_eq!(stats.lods[&0].cached_bytes, 16 * 16 * 16 * 2);
+//! assert_eq!((stats.cache.hits, stats.cache.misses), (1, 1));
+//! assert_eq!(stats.cache.evictions, 4);
+//! assert_eq!(stats.compressed_size_histogram.num_chunks(), 3);
+//! ```
+
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::collections::BTreeMap;
+
+/// A snapshot of the memory use and cache performance of a `CompressibleChunkStorage`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChunkMapStats {
+    /// The chunk counts and sizes of each LOD that has any chunks.
+    pub lods: BTreeMap<u8, LodStats>,
+    /// Cache hits, misses, and evictions since the counters were last reset.
+    pub cache: CacheStats,
+    /// The estimated sizes of all cached (uncompressed) chunks.
+    pub cached_size_histogram: SizeHistogram,
+    /// The sizes of all compressed chunks.
+    pub compressed_size_histogram: SizeHistogram,
+}
+
+impl ChunkMapStats {
+    /// The sum of the stats for all LODs.
+    pub fn total(&self) -> LodStats {
+        let mut total = LodStats::default();
+        for lod in self.lods.values() {
+            total.num_cached += lod.num_cached;
+            total.num_compressed += lod.num_compressed;
+            total.cached_bytes += lod.cached_bytes;
+            total.compressed_bytes += lod.compressed_bytes;
+        }
+
+        total
+    }
+}
+
+/// The chunk counts and sizes of a single LOD.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LodStats {
+    pub num_cached: usize,
+    pub num_compressed: usize,
+    /// The total `EstimateBytes::estimate_bytes` of the cached chunks.
+    pub cached_bytes: usize,
+    /// The total length of the compressed chunks' bytes.
+    pub compressed_bytes: usize,
+}
+
+/// Counts of chunk accesses that found the chunk cached (hits) or had to decompress it (misses). Accesses of chunks that
+/// don't exist are not counted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// The number of chunks that were compressed or dropped to make room in the cache.
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// The fraction of counted accesses that were hits, or `None` if there weren't any.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let accesses = self.hits + self.misses;
+        if accesses == 0 {
+            None
+        } else {
+            Some(self.hits as f64 / accesses as f64)
+        }
+    }
+}
+
+/// A histogram of byte sizes with power-of-two buckets.
+///
+/// Bucket `i` counts the sizes in `2^i..2^(i+1)`, except that bucket 0 also counts empty sizes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeHistogram {
+    counts: Vec<usize>,
+}
+
+impl SizeHistogram {
+    pub fn insert(&mut self, bytes: usize) {
+        let bucket = Self::bucket_of(bytes);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+    }
+
+    /// The number of sizes in each bucket. Trailing empty buckets are omitted.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// The range of sizes counted by `bucket`.
+    pub fn bucket_range(bucket: usize) -> Range<usize> {
+        let start = if bucket == 0 { 0 } else { 1 << bucket };
+
+        start..1 << (bucket + 1)
+    }
+
+    /// The bucket that counts `bytes`.
+    pub fn bucket_of(bytes: usize) -> usize {
+        if bytes == 0 {
+            0
+        } else {
+            (0usize.leading_zeros() - 1 - bytes.leading_zeros()) as usize
+        }
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// Atomic cache counters, so reads through `&self` can be counted.
+#[derive(Debug, Default)]
+pub(crate) struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl CacheCounters {
+    #[inline]
+    pub fn record_access(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_eviction(&self) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.evictions.store(0, Ordering::Relaxed);
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_histogram_buckets() {
+        let mut histogram = SizeHistogram::default();
+        for &bytes in [0, 1, 2, 3, 4, 1000, 1024].iter() {
+            histogram.insert(bytes);
+            assert!(SizeHistogram::bucket_range(SizeHistogram::bucket_of(bytes)).contains(&bytes));
+        }
+
+        assert_eq!(histogram.counts(), &[2, 2, 1, 0, 0, 0, 0, 0, 0, 1, 1][..]);
+        assert_eq!(histogram.num_chunks(), 7);
+    }
+
+    #[test]
+    fn cache_hit_rate() {
+        let counters = CacheCounters::default();
+        assert_eq!(counters.snapshot().hit_rate(), None);
+
+        counters.record_access(true);
+        counters.record_access(true);
+        counters.record_access(true);
+        counters.record_access(false);
+        counters.record_eviction();
+        assert_eq!(counters.snapshot().hit_rate(), Some(0.75));
+        assert_eq!(counters.snapshot().evictions, 1);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), CacheStats::default());
+    }
+}
@@ -0,0 +1,373 @@
+//! A chunk storage wrapper that folds chunks with the same value at every point into just that value.
+//!
+//! Most of a typical world is made of chunks that are entirely air or entirely stone. Wrapping the storage of a `ChunkMap`
+//! with `UniformChunkFolding` stores each of those chunks as a single value, without allocating its channels, which can cut
+//! the memory used by the map by an order of magnitude. Folded chunks are still read through the `ChunkMap` like any other
+//! chunk, and they're materialized again on the first mutable access.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, SmallKeyHashMap, UniformChunkFolding};
+//!
+//! let chunk_shape = Point3i::fill(16);
+//! let builder = ChunkMapBuilder3x1::new(chunk_shape, 0u8);
+//! let mut map =
+//!     builder.build_with_rw_storage(UniformChunkFolding::new(SmallKeyHashMap::default(), chunk_shape));
+//!
+//! // A whole chunk of stone is folded as soon as it's written.
+//! let key = ChunkKey::new(0, Point3i::ZERO);
+//! map.write_chunk(key, Array3x1::fill(Extent3i::from_min_and_shape(Point3i::ZERO, chunk_shape), 1));
+//! assert!(map.get_chunk(key).is_none());
+//! assert_eq!(map.get_uniform_chunk(key), Some(&1));
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(5)), 1);
+//!
+//! // The first mutable access materializes the chunk.
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(5)) = 2;
+//! assert!(map.get_chunk(key).is_some());
+//!
+//! // Chunks that become uniform while mutably borrowed need to be folded explicitly.
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(5)) = 1;
+//! assert_eq!(map.storage_mut().fold_all_chunks(), 1);
+//! assert_eq!(map.get_uniform_chunk(key), Some(&1));
+//! ```
+
+use crate::{Array, Channels, ChunkMap, FillChannels, Get, SmallKeyHashMap, Stride};
+
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys, TranslateChunkKeys};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use core::iter::Chain;
+use std::collections::hash_map;
+
+/// Wraps a chunk storage `Store` of `Array<N, Chan>` chunks, replacing every chunk that has the same value `Chan::Data` at all
+/// of its points with just that value.
+///
+/// Chunks that are written or replaced whole are checked immediately. Chunks that are mutated in place through a mutable
+/// borrow are not checked, since the borrow can outlive any call into the storage; use `fold_chunk` or `fold_all_chunks`
+/// after editing them.
+///
+/// `ChunkMap` point accessors, `ForEach`, `copy_extent`, and `visit_chunks` see folded chunks, but `get_chunk` and
+/// `visit_occupied_chunks` don't, because there is no chunk to borrow. Mutable accesses materialize the folded chunk first.
+pub struct UniformChunkFolding<N, Chan, Store>
+where
+    Chan: Channels,
+{
+    storage: Store,
+    uniform: SmallKeyHashMap<ChunkKey<N>, Chan::Data>,
+    chunk_shape: PointN<N>,
+}
+
+impl<N, Chan, Store> UniformChunkFolding<N, Chan, Store>
+where
+    Chan: Channels,
+{
+    /// `chunk_shape` must match the chunk shape of the `ChunkMap`, so folded chunks can be materialized.
+    pub fn new(storage: Store, chunk_shape: PointN<N>) -> Self {
+        Self {
+            storage,
+            uniform: SmallKeyHashMap::default(),
+            chunk_shape,
+        }
+    }
+
+    /// Borrow the inner storage, which doesn't contain the folded chunks.
+    pub fn inner(&self) -> &Store {
+        &self.storage
+    }
+
+    /// Mutably borrow the inner storage, which doesn't contain the folded chunks.
+    pub fn inner_mut(&mut self) -> &mut Store {
+        &mut self.storage
+    }
+
+    /// The number of chunks that are currently folded.
+    pub fn num_uniform_chunks(&self) -> usize {
+        self.uniform.len()
+    }
+}
+
+impl<N, Chan, Store> UniformChunkFolding<N, Chan, Store>
+where
+    ChunkKey<N>: Hash + Eq,
+    Chan: Channels,
+{
+    /// The value of every point in the chunk at `key`, if that chunk is folded.
+    pub fn uniform_value(&self, key: ChunkKey<N>) -> Option<&Chan::Data> {
+        self.uniform.get(&key)
+    }
+}
+
+impl<N, Chan, Store> UniformChunkFolding<N, Chan, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    Chan: FillChannels,
+    Chan::Data: Clone + PartialEq,
+    Array<N, Chan>: Get<Stride, Item = Chan::Data>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    /// Folds the chunk at `key` if all of its points have the same value. Returns `true` iff the chunk is folded afterwards.
+    pub fn fold_chunk(&mut self, key: ChunkKey<N>) -> bool {
+        if self.uniform.contains_key(&key) {
+            return true;
+        }
+
+        let value = if let Some(value) = self
+            .storage
+            .get_mut(key)
+            .and_then(|chunk| value_if_uniform(&*chunk))
+        {
+            value
+        } else {
+            return false;
+        };
+        self.storage.delete(key);
+        self.uniform.insert(key, value);
+
+        true
+    }
+
+    /// Folds every chunk in the inner storage whose points all have the same value. Returns the number of chunks that were
+    /// folded by this call.
+    pub fn fold_all_chunks(&mut self) -> usize
+    where
+        Store: for<'r> IterChunkKeys<'r, N>,
+    {
+        let keys: Vec<_> = self.storage.chunk_keys().copied().collect();
+
+        keys.into_iter().filter(|&key| self.fold_chunk(key)).count()
+    }
+
+    fn materialize(&self, key: ChunkKey<N>, value: Chan::Data) -> Array<N, Chan> {
+        Array::fill(
+            ExtentN::from_min_and_shape(key.minimum, self.chunk_shape),
+            value,
+        )
+    }
+
+    /// Writes `chunk` to the inner storage, or folds it, returning the previous chunk from the inner storage.
+    fn fold_or_replace(
+        &mut self,
+        key: ChunkKey<N>,
+        chunk: Array<N, Chan>,
+    ) -> Option<Array<N, Chan>> {
+        if let Some(value) = value_if_uniform(&chunk) {
+            self.uniform.insert(key, value);
+
+            self.storage.pop(key)
+        } else {
+            self.storage.replace(key, chunk)
+        }
+    }
+}
+
+/// Returns the value of every point in `chunk`, if they are all equal.
+fn value_if_uniform<N, Chan>(chunk: &Array<N, Chan>) -> Option<Chan::Data>
+where
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels,
+    Chan::Data: PartialEq,
+    Array<N, Chan>: Get<Stride, Item = Chan::Data>,
+{
+    let first = chunk.get(Stride(0));
+    for i in 1..chunk.extent().num_points() {
+        if chunk.get(Stride(i)) != first {
+            return None;
+        }
+    }
+
+    Some(first)
+}
+
+impl<N, Chan, Store> ChunkReadStorage<N, Array<N, Chan>> for UniformChunkFolding<N, Chan, Store>
+where
+    ChunkKey<N>: Hash + Eq,
+    Chan: Channels,
+    Store: ChunkReadStorage<N, Array<N, Chan>>,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Array<N, Chan>> {
+        self.storage.get(key)
+    }
+
+    #[inline]
+    fn get_uniform(&self, key: ChunkKey<N>) -> Option<&Chan::Data> {
+        self.uniform.get(&key)
+    }
+}
+
+impl<N, Chan, Store> ChunkWriteStorage<N, Array<N, Chan>> for UniformChunkFolding<N, Chan, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    Chan: FillChannels,
+    Chan::Data: Clone + PartialEq,
+    Array<N, Chan>: Get<Stride, Item = Chan::Data>,
+    Store: ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Array<N, Chan>> {
+        if let Some(value) = self.uniform.remove(&key) {
+            let chunk = self.materialize(key, value);
+
+            return Some(self.storage.get_mut_or_insert_with(key, || chunk));
+        }
+
+        self.storage.get_mut(key)
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Array<N, Chan>,
+    ) -> &mut Array<N, Chan> {
+        if let Some(value) = self.uniform.remove(&key) {
+            let chunk = self.materialize(key, value);
+
+            return self.storage.get_mut_or_insert_with(key, || chunk);
+        }
+
+        self.storage.get_mut_or_insert_with(key, create_chunk)
+    }
+
+    #[inline]
+    fn replace(&mut self, key: ChunkKey<N>, chunk: Array<N, Chan>) -> Option<Array<N, Chan>> {
+        let old_value = self.uniform.remove(&key);
+        let old_chunk = self.fold_or_replace(key, chunk);
+
+        old_chunk.or_else(|| old_value.map(|value| self.materialize(key, value)))
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey<N>, chunk: Array<N, Chan>) {
+        self.uniform.remove(&key);
+        self.fold_or_replace(key, chunk);
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey<N>) {
+        self.uniform.remove(&key);
+        self.storage.delete(key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey<N>) -> Option<Array<N, Chan>> {
+        if let Some(value) = self.uniform.remove(&key) {
+            return Some(self.materialize(key, value));
+        }
+
+        self.storage.pop(key)
+    }
+}
+
+impl<'a, N, Chan, Store> IterChunkKeys<'a, N> for UniformChunkFolding<N, Chan, Store>
+where
+    ChunkKey<N>: 'a,
+    Chan: Channels,
+    Chan::Data: 'a,
+    Store: IterChunkKeys<'a, N>,
+{
+    type Iter = Chain<Store::Iter, hash_map::Keys<'a, ChunkKey<N>, Chan::Data>>;
+
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.storage.chunk_keys().chain(self.uniform.keys())
+    }
+}
+
+impl<N, Chan, Store> TranslateChunkKeys<N> for UniformChunkFolding<N, Chan, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: Hash + Eq,
+    Chan: Channels,
+    Store: TranslateChunkKeys<N>,
+{
+    fn translate_chunk_keys(&mut self, lod0_delta: PointN<N>) {
+        self.storage.translate_chunk_keys(lod0_delta);
+        self.uniform = self
+            .uniform
+            .drain()
+            .map(|(key, value)| (key.translated(lod0_delta), value))
+            .collect();
+    }
+}
+
+impl<N, T, Bldr, Chan, Store> ChunkMap<N, T, Bldr, UniformChunkFolding<N, Chan, Store>>
+where
+    Chan: Channels,
+{
+    /// The number of chunks that the storage currently folds into a single value.
+    pub fn num_uniform_chunks(&self) -> usize {
+        self.storage().num_uniform_chunks()
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn folded_chunks_are_read_and_materialized() {
+        let chunk_shape = Point3i::fill(4);
+        let builder = ChunkMapBuilder3x1::new(chunk_shape, 0u8);
+        let mut map = builder.build_with_rw_storage(UniformChunkFolding::new(
+            SmallKeyHashMap::default(),
+            chunk_shape,
+        ));
+
+        let stone_key = ChunkKey::new(0, Point3i::ZERO);
+        let stone_extent = Extent3i::from_min_and_shape(Point3i::ZERO, chunk_shape);
+        let mixed_key = ChunkKey::new(0, PointN([4, 0, 0]));
+        let mixed_extent = stone_extent + PointN([4, 0, 0]);
+        let mut mixed_chunk = Array3x1::fill(mixed_extent, 1);
+        *mixed_chunk.get_mut(PointN([5, 1, 1])) = 2;
+
+        assert!(map
+            .replace_chunk(stone_key, Array3x1::fill(stone_extent, 1))
+            .is_none());
+        map.write_chunk(mixed_key, mixed_chunk);
+        assert_eq!(map.num_uniform_chunks(), 1);
+        assert_eq!(map.storage().inner().len(), 1);
+
+        // Reads see the folded value without materializing it.
+        let read_extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 4, 4]));
+        assert_eq!(map.bounding_extent(0), read_extent);
+        let mut copy = Array3x1::fill(read_extent, 0);
+        copy_extent(&read_extent, &map.lod_view(0), &mut copy);
+        assert_eq!(copy.get(PointN([1, 2, 3])), 1);
+        assert_eq!(copy.get(PointN([5, 1, 1])), 2);
+        let mut num_points = 0;
+        map.lod_view(0).for_each(&stone_extent, |_p, value| {
+            assert_eq!(value, 1);
+            num_points += 1;
+        });
+        assert_eq!(num_points, 64);
+        assert_eq!(map.num_uniform_chunks(), 1);
+
+        // Replacing a folded chunk returns it materialized.
+        let old = map.replace_chunk(stone_key, Array3x1::fill(stone_extent, 0));
+        assert_eq!(old, Some(Array3x1::fill(stone_extent, 1)));
+        assert_eq!(map.get_uniform_chunk(stone_key), Some(&0));
+
+        // A dense chunk that's edited into a uniform chunk is only folded on request.
+        *map.lod_view_mut(0).get_mut(PointN([5, 1, 1])) = 1;
+        assert!(map.storage_mut().fold_chunk(mixed_key));
+        assert_eq!(map.storage().inner().len(), 0);
+        assert_eq!(
+            map.pop_chunk(mixed_key),
+            Some(Array3x1::fill(mixed_extent, 1))
+        );
+        assert_eq!(map.num_uniform_chunks(), 1);
+    }
+}
@@ -0,0 +1,405 @@
+//! An in-memory chunk storage that keeps a history of committed versions.
+//!
+//! `VersionedChunkStorage` behaves like a `SmallKeyHashMap` of chunks (the "head"), but every call to `commit` records the
+//! chunks that changed since the last commit under a version ID. Any committed version can be read or checked out later, which
+//! makes it easy to implement undo/redo for editable worlds. Checking out an old version and committing new changes discards
+//! the newer versions, like an undo stack.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, VersionedChunkStorage};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! let mut map = builder.build_with_rw_storage(VersionedChunkStorage::default());
+//!
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(1)) = 1;
+//! map.storage_mut().commit(1);
+//!
+//! *map.lod_view_mut(0).get_mut(Point3i::fill(1)) = 2;
+//! map.storage_mut().commit(2);
+//!
+//! // Undo.
+//! map.storage_mut().checkout(1);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 1);
+//!
+//! // Redo.
+//! map.storage_mut().checkout(2);
+//! assert_eq!(map.lod_view(0).get(Point3i::fill(1)), 2);
+//! ```
+
+use crate::{SmallKeyHashMap, SmallKeyHashSet};
+
+use super::{ChunkKey, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys};
+
+use core::hash::Hash;
+use std::collections::{hash_map, BTreeMap, BTreeSet};
+
+/// A chunk storage that can snapshot its contents into numbered versions. See the [module docs](self) for an example.
+///
+/// Each version only stores (a clone of) the chunks that changed in that version, and `None` for chunks that were removed. Use
+/// `collect_garbage` to bound the memory used by old versions.
+pub struct VersionedChunkStorage<N, Ch> {
+    head: SmallKeyHashMap<ChunkKey<N>, Ch>,
+    head_version: Option<u64>,
+    uncommitted: SmallKeyHashSet<ChunkKey<N>>,
+    history: SmallKeyHashMap<ChunkKey<N>, BTreeMap<u64, Option<Ch>>>,
+    versions: BTreeSet<u64>,
+}
+
+impl<N, Ch> Default for VersionedChunkStorage<N, Ch> {
+    fn default() -> Self {
+        Self {
+            head: SmallKeyHashMap::default(),
+            head_version: None,
+            uncommitted: SmallKeyHashSet::default(),
+            history: SmallKeyHashMap::default(),
+            versions: BTreeSet::new(),
+        }
+    }
+}
+
+impl<N, Ch> VersionedChunkStorage<N, Ch> {
+    /// The current chunks, including uncommitted changes.
+    pub fn head(&self) -> &SmallKeyHashMap<ChunkKey<N>, Ch> {
+        &self.head
+    }
+
+    /// The version that the head was last committed as or checked out from.
+    pub fn head_version(&self) -> Option<u64> {
+        self.head_version
+    }
+
+    /// All committed versions that have not been discarded or garbage collected, in ascending order.
+    pub fn versions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.versions.iter().cloned()
+    }
+
+    pub fn latest_version(&self) -> Option<u64> {
+        self.versions.iter().next_back().cloned()
+    }
+
+    /// Returns `true` iff any chunks have been mutated since the last `commit` or `checkout`.
+    pub fn has_uncommitted_changes(&self) -> bool {
+        !self.uncommitted.is_empty()
+    }
+}
+
+impl<N, Ch> VersionedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Clone + Hash + Eq,
+    Ch: Clone,
+{
+    /// Records all chunks changed since the last `commit` or `checkout` as `version`, which becomes the head version. Any
+    /// versions newer than the current head version are discarded.
+    ///
+    /// # Panics
+    ///
+    /// If `version` is not greater than the head version.
+    pub fn commit(&mut self, version: u64) {
+        if let Some(head_version) = self.head_version {
+            assert!(
+                version > head_version,
+                "Version {} must be greater than the head version {}",
+                version,
+                head_version
+            );
+            self.discard_versions_after(head_version);
+        }
+
+        for key in self.uncommitted.drain() {
+            let chunk = self.head.get(&key).cloned();
+            self.history
+                .entry(key)
+                .or_insert_with(BTreeMap::new)
+                .insert(version, chunk);
+        }
+        self.versions.insert(version);
+        self.head_version = Some(version);
+    }
+
+    /// Resets the head to the chunks as of `version`. Uncommitted changes are dropped.
+    ///
+    /// Only the chunks that differ between the current head and `version` are cloned.
+    ///
+    /// # Panics
+    ///
+    /// If `version` was never committed, or it was discarded or garbage collected.
+    pub fn checkout(&mut self, version: u64) {
+        assert!(
+            self.versions.contains(&version),
+            "Version {} is not available",
+            version
+        );
+
+        let Self {
+            head,
+            head_version,
+            uncommitted,
+            history,
+            ..
+        } = self;
+
+        // Chunks with uncommitted changes must always be reset.
+        let reset_keys = std::mem::take(uncommitted);
+        for key in reset_keys.iter() {
+            if !history.contains_key(key) {
+                head.remove(key);
+            }
+        }
+        for (key, chunk_versions) in history.iter() {
+            let head_chunk_version = (*head_version)
+                .and_then(|v| latest_version_at(chunk_versions, v))
+                .map(|(v, _)| v);
+            let target_chunk = latest_version_at(chunk_versions, version);
+            if !reset_keys.contains(key) && head_chunk_version == target_chunk.map(|(v, _)| v) {
+                continue;
+            }
+
+            match target_chunk.and_then(|(_, chunk)| chunk.as_ref()) {
+                Some(chunk) => {
+                    head.insert(key.clone(), chunk.clone());
+                }
+                None => {
+                    head.remove(key);
+                }
+            }
+        }
+        *head_version = Some(version);
+    }
+
+    /// Borrow the chunk at `key` as of `version`. Reading a version older than the last garbage collection may give the
+    /// wrong chunk.
+    pub fn get_at_version(&self, key: ChunkKey<N>, version: u64) -> Option<&Ch> {
+        self.history
+            .get(&key)
+            .and_then(|chunk_versions| latest_version_at(chunk_versions, version))
+            .and_then(|(_, chunk)| chunk.as_ref())
+    }
+
+    /// Drops all history that isn't needed to read or check out `oldest_kept` or any newer version.
+    ///
+    /// # Panics
+    ///
+    /// If `oldest_kept` is newer than the head version.
+    pub fn collect_garbage(&mut self, oldest_kept: u64) {
+        if let Some(head_version) = self.head_version {
+            assert!(
+                oldest_kept <= head_version,
+                "Can't collect versions newer than the head version {}",
+                head_version
+            );
+        }
+
+        self.history.retain(|_, chunk_versions| {
+            if let Some(keep_from) = latest_version_at(chunk_versions, oldest_kept).map(|(v, _)| v)
+            {
+                *chunk_versions = chunk_versions.split_off(&keep_from);
+                // A chunk removed at or before the oldest kept version doesn't need history.
+                if chunk_versions[&keep_from].is_none() {
+                    chunk_versions.remove(&keep_from);
+                }
+            }
+
+            !chunk_versions.is_empty()
+        });
+        self.versions = self.versions.split_off(&oldest_kept);
+    }
+
+    fn discard_versions_after(&mut self, version: u64) {
+        let first_discarded = if let Some(v) = version.checked_add(1) {
+            v
+        } else {
+            return;
+        };
+
+        self.history.retain(|_, chunk_versions| {
+            chunk_versions.split_off(&first_discarded);
+
+            !chunk_versions.is_empty()
+        });
+        self.versions.split_off(&first_discarded);
+    }
+}
+
+/// The latest entry in `chunk_versions` that is no newer than `version`.
+fn latest_version_at<Ch>(
+    chunk_versions: &BTreeMap<u64, Option<Ch>>,
+    version: u64,
+) -> Option<(u64, &Option<Ch>)> {
+    chunk_versions
+        .range(..=version)
+        .next_back()
+        .map(|(v, chunk)| (*v, chunk))
+}
+
+impl<N, Ch> ChunkReadStorage<N, Ch> for VersionedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: ChunkKey<N>) -> Option<&Ch> {
+        self.head.get(&key)
+    }
+}
+
+impl<N, Ch> ChunkWriteStorage<N, Ch> for VersionedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: Clone + Hash + Eq,
+{
+    #[inline]
+    fn get_mut(&mut self, key: ChunkKey<N>) -> Option<&mut Ch> {
+        let chunk = self.head.get_mut(&key);
+        if chunk.is_some() {
+            self.uncommitted.insert(key);
+        }
+
+        chunk
+    }
+
+    #[inline]
+    fn get_mut_or_insert_with(
+        &mut self,
+        key: ChunkKey<N>,
+        create_chunk: impl FnOnce() -> Ch,
+    ) -> &mut Ch {
+        self.uncommitted.insert(key.clone());
+
+        self.head.entry(key).or_insert_with(create_chunk)
+    }
+
+    #[inline]
+    fn replace(&mut self, key: ChunkKey<N>, chunk: Ch) -> Option<Ch> {
+        self.uncommitted.insert(key.clone());
+
+        self.head.insert(key, chunk)
+    }
+
+    #[inline]
+    fn write(&mut self, key: ChunkKey<N>, chunk: Ch) {
+        self.uncommitted.insert(key.clone());
+        self.head.insert(key, chunk);
+    }
+
+    #[inline]
+    fn delete(&mut self, key: ChunkKey<N>) {
+        self.uncommitted.insert(key.clone());
+        self.head.remove(&key);
+    }
+
+    #[inline]
+    fn pop(&mut self, key: ChunkKey<N>) -> Option<Ch> {
+        let chunk = self.head.remove(&key);
+        if chunk.is_some() {
+            self.uncommitted.insert(key);
+        }
+
+        chunk
+    }
+}
+
+impl<'a, N, Ch> IterChunkKeys<'a, N> for VersionedChunkStorage<N, Ch>
+where
+    ChunkKey<N>: 'a,
+    Ch: 'a,
+{
+    type Iter = hash_map::Keys<'a, ChunkKey<N>, Ch>;
+
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.head.keys()
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use building_blocks_core::prelude::*;
+
+    type Storage = VersionedChunkStorage<[i32; 3], i32>;
+
+    fn key(x: i32) -> ChunkKey<[i32; 3]> {
+        ChunkKey::new(0, PointN([x, 0, 0]))
+    }
+
+    #[test]
+    fn checkout_restores_old_versions() {
+        let mut storage = Storage::default();
+        storage.write(key(0), 1);
+        storage.write(key(1), 1);
+        storage.commit(1);
+
+        storage.write(key(0), 2);
+        storage.delete(key(1));
+        storage.write(key(2), 2);
+        storage.commit(2);
+        assert!(!storage.has_uncommitted_changes());
+
+        // Uncommitted changes are dropped by a checkout.
+        storage.write(key(3), 3);
+        storage.checkout(1);
+        assert_eq!(storage.get(key(0)), Some(&1));
+        assert_eq!(storage.get(key(1)), Some(&1));
+        assert_eq!(storage.get(key(2)), None);
+        assert_eq!(storage.get(key(3)), None);
+        assert_eq!(storage.head().len(), 2);
+
+        storage.checkout(2);
+        assert_eq!(storage.get(key(0)), Some(&2));
+        assert_eq!(storage.get(key(1)), None);
+        assert_eq!(storage.get(key(2)), Some(&2));
+        assert_eq!(storage.head().len(), 2);
+
+        assert_eq!(storage.get_at_version(key(1), 1), Some(&1));
+        assert_eq!(storage.get_at_version(key(1), 2), None);
+    }
+
+    #[test]
+    fn commit_after_checkout_discards_newer_versions() {
+        let mut storage = Storage::default();
+        storage.write(key(0), 1);
+        storage.commit(1);
+        storage.write(key(0), 2);
+        storage.commit(2);
+
+        storage.checkout(1);
+        storage.write(key(1), 3);
+        storage.commit(3);
+
+        assert_eq!(storage.versions().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(storage.get_at_version(key(0), 3), Some(&1));
+        assert_eq!(storage.get_at_version(key(1), 3), Some(&3));
+    }
+
+    #[test]
+    fn garbage_collection_keeps_newer_versions_readable() {
+        let mut storage = Storage::default();
+        storage.write(key(0), 1);
+        storage.write(key(1), 1);
+        storage.commit(1);
+        storage.write(key(0), 2);
+        storage.delete(key(1));
+        storage.commit(2);
+        storage.write(key(0), 3);
+        storage.commit(3);
+
+        storage.collect_garbage(2);
+
+        assert_eq!(storage.versions().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(storage.get_at_version(key(0), 2), Some(&2));
+        assert_eq!(storage.get_at_version(key(0), 3), Some(&3));
+        assert_eq!(storage.get_at_version(key(1), 2), None);
+        // Chunk 1 was deleted before the oldest kept version, so it has no history left.
+        assert!(!storage.history.contains_key(&key(1)));
+
+        storage.checkout(2);
+        assert_eq!(storage.get(key(0)), Some(&2));
+        assert_eq!(storage.get(key(1)), None);
+    }
+}
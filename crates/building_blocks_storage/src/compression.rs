@@ -1,16 +1,22 @@
 mod compressed_bincode;
+mod rle_compression;
 
 #[cfg(feature = "lz4")]
 mod lz4_compression;
 #[cfg(feature = "snap")]
 mod snappy_compression;
+#[cfg(feature = "zstd")]
+mod zstd_compression;
 
 pub use compressed_bincode::BincodeCompression;
+pub use rle_compression::Rle;
 
 #[cfg(feature = "lz4")]
 pub use lz4_compression::Lz4;
 #[cfg(feature = "snap")]
 pub use snappy_compression::Snappy;
+#[cfg(feature = "zstd")]
+pub use zstd_compression::Zstd;
 
 use serde::{Deserialize, Serialize};
 use std::io;
@@ -78,9 +84,49 @@ pub trait BytesCompression {
         compressed_bytes: impl io::Write,
     ) -> io::Result<()>;
 
+    /// Same as `compress_bytes`, but the caller knows that `bytes` is a sequence of `value_size`-byte values, like the
+    /// elements of a `Channel`. Algorithms that work on whole values (like `Rle`) can override this.
+    fn compress_values(
+        &self,
+        bytes: impl io::Read,
+        _value_size: usize,
+        compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        self.compress_bytes(bytes, compressed_bytes)
+    }
+
     fn decompress_bytes(compressed_bytes: impl io::Read, bytes: impl io::Write) -> io::Result<()>;
 }
 
+/// A `BytesCompression` that copies the bytes unchanged, after a length prefix so that decompression knows where the stream
+/// ends. This is mostly useful as the last stage of a chained compression, like `Rle`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct NoCompression;
+
+impl BytesCompression for NoCompression {
+    fn compress_bytes(
+        &self,
+        mut bytes: impl io::Read,
+        mut compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut all_bytes = Vec::new();
+        bytes.read_to_end(&mut all_bytes)?;
+        compressed_bytes.write_all(&(all_bytes.len() as u64).to_le_bytes())?;
+        compressed_bytes.write_all(&all_bytes)
+    }
+
+    fn decompress_bytes(
+        mut compressed_bytes: impl io::Read,
+        mut bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut len_bytes = [0; 8];
+        compressed_bytes.read_exact(&mut len_bytes)?;
+        let len = u64::from_le_bytes(len_bytes);
+        io::copy(&mut compressed_bytes.take(len), &mut bytes)?;
+        Ok(())
+    }
+}
+
 /// A value that is either compressed or decompressed.
 pub enum MaybeCompressed<D, C> {
     Decompressed(D),
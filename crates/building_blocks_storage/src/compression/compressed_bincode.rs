@@ -45,6 +45,8 @@ where
         data: &Self::Data,
         compressed_bytes: impl io::Write,
     ) -> io::Result<()> {
+        trace_span!("compress_bincode");
+
         self.compression.compress_bytes(
             bincode::serialize(data).unwrap().as_slice(),
             compressed_bytes,
@@ -52,6 +54,8 @@ where
     }
 
     fn decompress_from_reader(compressed_bytes: impl io::Read) -> io::Result<Self::Data> {
+        trace_span!("decompress_bincode");
+
         let mut decompressed_bytes = Vec::new();
         A::decompress_bytes(compressed_bytes, &mut decompressed_bytes)?;
 
@@ -0,0 +1,235 @@
+use super::{BytesCompression, NoCompression};
+
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// [Run-length encoding](https://en.wikipedia.org/wiki/Run-length_encoding) of fixed-size values. Voxel channels are often
+/// made of long runs of the same value (air, stone, a constant material), which RLE collapses very cheaply. It's pure Rust,
+/// so it works on WASM.
+///
+/// The runs are then compressed with `then`, e.g. `Rle { then: Lz4 { level: 10 } }`, which mostly helps with channels that
+/// have many short runs. Use `Rle::new(NoCompression)` to skip the second pass.
+///
+/// When used in a `FastChannelsCompression`, each run is measured in channel values, not bytes. Elsewhere, i.e. when only
+/// `compress_bytes` is called, each byte is a value.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Rle<By = NoCompression> {
+    pub then: By,
+}
+
+impl<By> Rle<By> {
+    pub fn new(then: By) -> Self {
+        Self { then }
+    }
+}
+
+impl<By> BytesCompression for Rle<By>
+where
+    By: BytesCompression,
+{
+    fn compress_bytes(
+        &self,
+        bytes: impl io::Read,
+        compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        self.compress_values(bytes, 1, compressed_bytes)
+    }
+
+    fn compress_values(
+        &self,
+        mut bytes: impl io::Read,
+        value_size: usize,
+        mut compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut all_bytes = Vec::new();
+        bytes.read_to_end(&mut all_bytes)?;
+
+        // Fall back to single bytes if the values don't divide the input evenly.
+        let value_size = if value_size > 0 && all_bytes.len() % value_size == 0 {
+            value_size
+        } else {
+            1
+        };
+
+        let encoded = encode_runs(&all_bytes, value_size);
+        let mut then_bytes = Vec::new();
+        self.then
+            .compress_bytes(encoded.as_slice(), &mut then_bytes)?;
+
+        // Prefix the length so that decompression doesn't read past the end of this stream, e.g. when several channels are
+        // written to the same writer.
+        compressed_bytes.write_all(&(then_bytes.len() as u64).to_le_bytes())?;
+        compressed_bytes.write_all(&then_bytes)
+    }
+
+    fn decompress_bytes(
+        mut compressed_bytes: impl io::Read,
+        mut bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut len_bytes = [0; 8];
+        compressed_bytes.read_exact(&mut len_bytes)?;
+        let then_len = u64::from_le_bytes(len_bytes);
+
+        let mut encoded = Vec::new();
+        By::decompress_bytes(compressed_bytes.take(then_len), &mut encoded)?;
+
+        decode_runs(&encoded, &mut bytes)
+    }
+}
+
+// The encoding (before `then` is applied) is a little-endian `u32` value size, followed by any number of runs. Each run is a little-endian `u32`
+// length followed by the bytes of the repeated value.
+fn encode_runs(bytes: &[u8], value_size: usize) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(&(value_size as u32).to_le_bytes());
+
+    let mut values = bytes.chunks_exact(value_size).peekable();
+    while let Some(value) = values.next() {
+        let mut run_length = 1u32;
+        while run_length < u32::MAX && values.peek() == Some(&value) {
+            values.next();
+            run_length += 1;
+        }
+        encoded.extend_from_slice(&run_length.to_le_bytes());
+        encoded.extend_from_slice(value);
+    }
+
+    encoded
+}
+
+fn decode_runs(encoded: &[u8], mut bytes: impl io::Write) -> io::Result<()> {
+    let (value_size, mut runs) = split_u32(encoded)?;
+    let value_size = value_size as usize;
+    if value_size == 0 {
+        return Err(invalid_data("RLE value size is zero"));
+    }
+
+    while !runs.is_empty() {
+        let (run_length, rest) = split_u32(runs)?;
+        if rest.len() < value_size {
+            return Err(invalid_data("RLE run is missing its value"));
+        }
+        let (value, rest) = rest.split_at(value_size);
+        for _ in 0..run_length {
+            bytes.write_all(value)?;
+        }
+        runs = rest;
+    }
+
+    Ok(())
+}
+
+fn split_u32(bytes: &[u8]) -> io::Result<(u32, &[u8])> {
+    if bytes.len() < 4 {
+        return Err(invalid_data("RLE stream ended early"));
+    }
+    let (head, rest) = bytes.split_at(4);
+    let mut word = [0; 4];
+    word.copy_from_slice(head);
+
+    Ok((u32::from_le_bytes(word), rest))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Channel, Compression, FastChannelsCompression, FastChannelsCompression2};
+
+    #[test]
+    fn compress_and_decompress_bytes() {
+        let bytes: Vec<u8> = [0u8; 50]
+            .iter()
+            .chain((0u8..10).collect::<Vec<_>>().iter())
+            .chain([7u8; 40].iter())
+            .cloned()
+            .collect();
+
+        let mut compressed_bytes = Vec::new();
+        Rle::<NoCompression>::default()
+            .compress_bytes(bytes.as_slice(), &mut compressed_bytes)
+            .unwrap();
+        // Two length prefixes, header, then 11 runs of (length, 1-byte value). The ramp's `0` joins the first run.
+        assert_eq!(compressed_bytes.len(), 8 + 8 + 4 + 11 * 5);
+
+        let mut decompressed_bytes = Vec::new();
+        Rle::<NoCompression>::decompress_bytes(
+            compressed_bytes.as_slice(),
+            &mut decompressed_bytes,
+        )
+        .unwrap();
+
+        assert_eq!(bytes, decompressed_bytes);
+    }
+
+    #[test]
+    fn runs_are_measured_in_channel_values() {
+        let mut values = vec![256u16; 1000];
+        values.extend_from_slice(&[1, 2, 3]);
+        let channel = Channel::new(values);
+
+        let compression =
+            FastChannelsCompression::<_, Channel<u16>>::new(Rle::<NoCompression>::default());
+        let compressed = compression.compress(&channel);
+        // Number of values, two length prefixes, header, then 4 runs of (length, 2-byte value).
+        let num_values_size = std::mem::size_of::<usize>();
+        assert_eq!(
+            compressed.compressed_bytes.len(),
+            num_values_size + 8 + 8 + 4 + 4 * 6
+        );
+
+        assert_eq!(compressed.decompress().store(), channel.store());
+    }
+
+    #[test]
+    fn decompress_truncated_stream_is_an_error() {
+        let mut compressed_bytes = Vec::new();
+        Rle::<NoCompression>::default()
+            .compress_values(&[1u8, 0, 1, 0][..], 2, &mut compressed_bytes)
+            .unwrap();
+        compressed_bytes.pop();
+
+        let mut decompressed_bytes = Vec::new();
+        assert!(Rle::<NoCompression>::decompress_bytes(
+            compressed_bytes.as_slice(),
+            &mut decompressed_bytes
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn multichannel_stream_ends_after_each_channel() {
+        let channels = (Channel::fill(1u32, 100), Channel::fill(b'a', 100));
+
+        let compression =
+            FastChannelsCompression2::<_, u32, u8>::new(Rle::<NoCompression>::default());
+        let compressed = compression.compress(&channels);
+
+        assert_eq!(compressed.decompress(), channels);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn chained_with_lz4() {
+        use crate::Lz4;
+
+        let values: Vec<u32> = (0..4096).map(|i| (i / 100) % 3).collect();
+        let channel = Channel::new(values);
+
+        let compression =
+            FastChannelsCompression::<_, Channel<u32>>::new(Rle::new(Lz4 { level: 10 }));
+        let compressed = compression.compress(&channel);
+
+        assert_eq!(compressed.decompress().store(), channel.store());
+    }
+}
@@ -0,0 +1,70 @@
+use super::BytesCompression;
+
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// The [Zstandard compression algorithm](https://en.wikipedia.org/wiki/Zstandard). Slower than `Lz4`, but usually gives
+/// better compression ratios, which makes it a good choice for archived worlds.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Zstd {
+    /// The compression level, from 1 to 22. 1 is fastest and least aggressive. 22 is slowest and most aggressive, and levels
+    /// above 19 use much more memory. 0 means "use the zstd default level" (currently 3). Negative levels are even faster
+    /// than 1, trading away more of the compression ratio. Levels outside of the range supported by the linked zstd library
+    /// are clamped by zstd.
+    pub level: i32,
+}
+
+impl Default for Zstd {
+    fn default() -> Self {
+        Self { level: 0 }
+    }
+}
+
+impl BytesCompression for Zstd {
+    fn compress_bytes(
+        &self,
+        mut bytes: impl io::Read,
+        compressed_bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut encoder = zstd::Encoder::new(compressed_bytes, self.level)?;
+        io::copy(&mut bytes, &mut encoder)?;
+        encoder.finish()?;
+
+        Ok(())
+    }
+
+    fn decompress_bytes(
+        compressed_bytes: impl io::Read,
+        mut bytes: impl io::Write,
+    ) -> io::Result<()> {
+        let mut decoder = zstd::Decoder::new(compressed_bytes)?;
+        io::copy(&mut decoder, &mut bytes)?;
+        Ok(())
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_and_decompress_serializable_type() {
+        let bytes: Vec<u8> = (0u8..100).collect();
+
+        let mut compressed_bytes = Vec::new();
+        Zstd { level: 19 }
+            .compress_bytes(bytes.as_slice(), &mut compressed_bytes)
+            .unwrap();
+        let mut decompressed_bytes = Vec::new();
+        Zstd::decompress_bytes(compressed_bytes.as_slice(), &mut decompressed_bytes).unwrap();
+
+        assert_eq!(bytes, decompressed_bytes);
+    }
+}
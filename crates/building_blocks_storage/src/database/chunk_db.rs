@@ -0,0 +1,330 @@
+use super::{decompress_in_batches, DatabaseKey};
+
+use crate::{AsyncChunkReader, ChunkKey, Compression};
+
+use building_blocks_core::{orthants_covering_extent, prelude::*};
+
+use core::ops::RangeInclusive;
+use futures::future::{join_all, BoxFuture};
+use std::borrow::Borrow;
+
+/// An ordered key-value store that a `BackendChunkDb` can keep its chunks in.
+///
+/// Keys and values are opaque byte strings, and keys must be ordered lexicographically, like `memcmp`. That is all a backend
+/// has to provide; the key encoding, compression, and batching are handled by `BackendChunkDb`, so any ordered store can be
+/// plugged in, like an object store or a remote server. See the `sled::Tree` and `MemoryBackend` implementations.
+pub trait ChunkDbBackend {
+    type Error;
+    type Bytes: AsRef<[u8]>;
+
+    /// Atomically writes all of the key-value pairs in `kvs`, overwriting existing values.
+    fn write_batch(&self, kvs: Vec<(Vec<u8>, Vec<u8>)>) -> BoxFuture<'_, Result<(), Self::Error>>;
+
+    /// Reads the value at `key`, if it exists.
+    fn read(&self, key: Vec<u8>) -> BoxFuture<'_, Result<Option<Self::Bytes>, Self::Error>>;
+
+    /// Reads the key-value pairs with keys in `start..=end`, in key order. If `limit` is `Some(n)`, only the first `n` pairs are
+    /// read.
+    fn read_range(
+        &self,
+        start: Vec<u8>,
+        end: Vec<u8>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'_, Result<Vec<(Self::Bytes, Self::Bytes)>, Self::Error>>;
+}
+
+/// A database of compressed chunks, stored in any `ChunkDbBackend`.
+///
+/// The keys are Morton codes for the corresponding chunk coordinates, prefixed by the LOD (see `DatabaseKey`). This ensures
+/// that all of the chunks in an orthant are stored in a contiguous key space.
+///
+/// The DB values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub struct BackendChunkDb<N, Compr, Backend> {
+    backend: Backend,
+    compression: Compr,
+    marker: std::marker::PhantomData<N>,
+}
+
+/// Reading an extent fetches this many key-value pairs at a time, so it can skip ahead between pages.
+const EXTENT_PAGE_SIZE: usize = 64;
+
+impl<N, Compr, Backend> BackendChunkDb<N, Compr, Backend> {
+    pub fn new(backend: Backend, compression: Compr) -> Self {
+        Self {
+            backend,
+            compression,
+            marker: Default::default(),
+        }
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+}
+
+impl<N, Compr, Backend> BackendChunkDb<N, Compr, Backend>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+    Backend: ChunkDbBackend,
+{
+    /// Insert a set of chunks. This will compress all of the chunks asynchronously then insert them into the database.
+    /// Pre-existing chunks will be overwritten.
+    pub async fn write_chunks<Data>(
+        &self,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Data)>,
+    ) -> Result<(), Backend::Error>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let mut compressed_chunks = Vec::new();
+        for (key, compressed_chunk) in join_all(chunks.map(|(key, chunk)| async move {
+            (
+                ChunkKey::<N>::into_ord_key(key),
+                self.compression.compress(chunk.borrow()),
+            )
+        }))
+        .await
+        .into_iter()
+        {
+            compressed_chunks.push((key, compressed_chunk));
+        }
+        // Sort them by the Ord key.
+        compressed_chunks.sort_by_key(|(k, _)| *k);
+
+        // Then atomically write them all to the database.
+        let kvs = compressed_chunks
+            .into_iter()
+            .map(|(db_key, chunk)| {
+                let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(db_key);
+
+                (key_bytes.as_ref().to_vec(), chunk.take_bytes())
+            })
+            .collect();
+
+        trace_future!(self.backend.write_batch(kvs), "write_batch").await
+    }
+
+    /// Reads and decompresses the chunk at `key`, if it exists.
+    pub async fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+    ) -> Result<Option<Compr::Data>, Backend::Error> {
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+        let compressed_chunk =
+            trace_future!(self.backend.read(key_bytes.as_ref().to_vec()), "read_chunk").await?;
+
+        Ok(compressed_chunk.map(|bytes| Compr::decompress_from_reader(bytes.as_ref()).unwrap()))
+    }
+
+    /// Scans the given orthant for chunks, decompresses them, then passes them to `chunk_rx`. Because chunk keys are stored in
+    /// Morton order, the chunks in any orthant are guaranteed to be contiguous.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Backend::Error> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    /// This is like `read_chunks_in_orthant`, but it works for the given `extent`. Since Morton order only guarantees
+    /// contiguity within a single `Orthant`, we should not naively scan from the Morton of `extent.minimum` to `extent.max()`.
+    /// Rather, we scan a set of `Orthant`s that covers `extent`. This covering is *at least* sufficient to cover the extent,
+    /// and it gets more exact as `orthant_exponent` (log2 of the side length) gets smaller. However, for exactness, you must
+    /// necessarily do more scans.
+    pub async fn read_orthants_covering_extent(
+        &self,
+        lod: u8,
+        orthant_exponent: i32,
+        extent: ExtentN<N>,
+        mut chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Backend::Error> {
+        // PERF: more parallelism?
+        for orthant in orthants_covering_extent(extent, orthant_exponent) {
+            self.read_chunks_in_orthant(lod, orthant, &mut chunk_rx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads every chunk in `lod` whose minimum lies in `extent`, passing them to `chunk_rx` in Morton order.
+    ///
+    /// This scans the Morton range of `extent` in pages. Whenever a page ends with a key outside of `extent`, the next page
+    /// skips ahead to the next key that could be inside (BIGMIN), and chunks outside of `extent` are never decompressed. To
+    /// read all the chunks that intersect some voxel extent `e`, use the extent from
+    /// `indexer.min_of_chunk_containing_point(e.minimum)` to `e.max()`.
+    pub async fn read_chunks_in_extent(
+        &self,
+        lod: u8,
+        extent: ExtentN<N>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Backend::Error> {
+        if extent.is_empty() {
+            return Ok(());
+        }
+
+        let key_to_vec = |ord_key| {
+            ChunkKey::<N>::ord_key_to_be_bytes(ord_key)
+                .as_ref()
+                .to_vec()
+        };
+        let end = key_to_vec(ChunkKey::<N>::into_ord_key(ChunkKey::new(
+            lod,
+            extent.max(),
+        )));
+        let mut start = Some(key_to_vec(ChunkKey::<N>::into_ord_key(ChunkKey::new(
+            lod,
+            extent.minimum,
+        ))));
+
+        let mut read_kvs = Vec::new();
+        while let Some(start_bytes) = start.take() {
+            let page = trace_future!(
+                self.backend
+                    .read_range(start_bytes, end.clone(), Some(EXTENT_PAGE_SIZE)),
+                "read_range",
+                lod
+            )
+            .await?;
+            let page_is_full = page.len() == EXTENT_PAGE_SIZE;
+
+            let mut last_ord_key = None;
+            for (key_bytes, chunk) in page.into_iter() {
+                let ord_key = ChunkKey::<N>::ord_key_from_be_bytes(key_bytes.as_ref());
+                let inside = extent.contains(ChunkKey::<N>::from_ord_key(ord_key).minimum);
+                if inside {
+                    read_kvs.push((key_bytes, chunk));
+                }
+                last_ord_key = Some((ord_key, inside));
+            }
+
+            if page_is_full {
+                start = match last_ord_key {
+                    Some((ord_key, true)) => {
+                        // The least byte string that sorts after the last key.
+                        let mut next = key_to_vec(ord_key);
+                        next.push(0);
+                        Some(next)
+                    }
+                    Some((ord_key, false)) => {
+                        ChunkKey::<N>::bigmin_in_extent(ord_key, &extent).map(key_to_vec)
+                    }
+                    None => None,
+                };
+            }
+        }
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+
+    /// Reads all chunks in the given `lod`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Backend::Error> {
+        let range = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod))
+            ..=ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    async fn read_range(
+        &self,
+        range: RangeInclusive<<ChunkKey<N> as DatabaseKey<N>>::KeyBytes>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Backend::Error> {
+        let (start, end) = range.into_inner();
+        let read_kvs = trace_future!(
+            self.backend
+                .read_range(start.as_ref().to_vec(), end.as_ref().to_vec(), None),
+            "read_range"
+        )
+        .await?;
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+}
+
+impl<N, Compr, Backend> AsyncChunkReader<N, Compr::Data> for BackendChunkDb<N, Compr, Backend>
+where
+    N: Send + Sync,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy + Send + Sync,
+    Compr::Data: Send,
+    Backend: ChunkDbBackend + Sync,
+    Backend::Bytes: Send,
+{
+    type Error = Backend::Error;
+
+    fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+    ) -> BoxFuture<'_, Result<Option<Compr::Data>, Backend::Error>> {
+        Box::pin(BackendChunkDb::read_chunk(self, key))
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Array2x1, ChunkIndexer, ChunkKey2, FastArrayCompressionNx1, FromBytesCompression,
+        MemoryBackend, MemoryChunkDb2, NoCompression,
+    };
+
+    use super::*;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn read_chunks_in_extent_across_many_pages() {
+        let indexer = ChunkIndexer::new(Point2i::fill(2));
+        let all_chunks = Extent2i::from_min_and_shape(Point2i::fill(-32), Point2i::fill(64));
+        let write_chunks: Vec<_> = indexer
+            .chunk_mins_for_extent(&all_chunks)
+            .map(|min| {
+                (
+                    ChunkKey2::new(0, min),
+                    Array2x1::fill(indexer.extent_for_chunk_with_min(min), min.x() as u8),
+                )
+            })
+            .collect();
+
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let chunk_db = MemoryChunkDb2::new(MemoryBackend::default(), compression);
+        block_on(chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v)))).unwrap();
+
+        // The Morton range of this extent has many more keys than one page, most of them outside of the extent.
+        let extent = Extent2i::from_min_and_shape(PointN([-11, -5]), PointN([19, 23]));
+        let mut read_chunks = Vec::new();
+        block_on(chunk_db.read_chunks_in_extent(0, extent, |k, v| read_chunks.push((k, v))))
+            .unwrap();
+
+        let mut expected_chunks: Vec<_> = write_chunks
+            .into_iter()
+            .filter(|(k, _)| extent.contains(k.minimum))
+            .collect();
+        expected_chunks.sort_by_key(|(k, _)| ChunkKey2::into_ord_key(*k));
+        assert!(expected_chunks.len() > EXTENT_PAGE_SIZE);
+        assert_eq!(read_chunks, expected_chunks);
+    }
+}
@@ -0,0 +1,324 @@
+use super::{ChunkDb, DatabaseKey};
+
+use crate::{ChunkKey, Compression, SmallKeyHashMap};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Settings for the batching of a `ChunkDbWriter`.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkDbWriterConfig {
+    /// A batch is written once no new chunks have been queued for this long.
+    pub debounce: Duration,
+    /// A batch is written as soon as it has this many distinct chunks, even if chunks are still being queued.
+    pub max_batch_size: usize,
+    /// The number of writes that can be queued before `write_chunk` has to wait for the background task. This is the
+    /// backpressure that keeps a slow disk from growing the queue without bound.
+    pub queue_capacity: usize,
+}
+
+impl Default for ChunkDbWriterConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(250),
+            max_batch_size: 256,
+            queue_capacity: 1024,
+        }
+    }
+}
+
+/// Writes chunks to a `ChunkDb` from a background `tokio` task, so the caller only pays for sending the chunk over a channel.
+///
+/// Chunks are collected into batches, and each batch is compressed and written atomically with `ChunkDb::write_chunks`. When
+/// the same chunk is queued more than once before its batch is written, only the latest version is written. Call
+/// `flush_and_wait` before shutting down to make sure all queued chunks are durably stored; any remaining chunks are also
+/// written when the `ChunkDbWriter` is dropped, but nothing waits for them.
+///
+/// ```
+/// # use building_blocks_core::prelude::*;
+/// # use building_blocks_storage::{prelude::*, FastArrayCompressionNx1};
+/// # use std::sync::Arc;
+/// # let tmp = tempdir::TempDir::new("bb-doc").unwrap();
+/// # let db = sled::Config::default().path(&tmp).open().unwrap();
+/// let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+/// let chunk_db = Arc::new(ChunkDb3::new(db.open_tree("chunks").unwrap(), compression));
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+/// runtime.block_on(async {
+///     let writer = ChunkDbWriter::spawn(chunk_db.clone(), ChunkDbWriterConfig::default());
+///
+///     let key = ChunkKey3::new(0, Point3i::ZERO);
+///     let chunk = Array3x1::fill(Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16)), 1u8);
+///     writer.write_chunk(key, chunk).await;
+///
+///     writer.flush_and_wait().await.unwrap();
+///     assert!(chunk_db.read_chunk(key).await.unwrap().is_some());
+/// });
+/// ```
+pub struct ChunkDbWriter<N, Compr>
+where
+    Compr: Compression,
+{
+    sender: mpsc::Sender<WriterMessage<N, Compr::Data>>,
+    task: JoinHandle<()>,
+}
+
+enum WriterMessage<N, Data> {
+    Write(ChunkKey<N>, Data),
+    Flush(oneshot::Sender<sled::Result<()>>),
+}
+
+impl<N, Compr> ChunkDbWriter<N, Compr>
+where
+    N: 'static + Send + Sync,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N> + Eq + Hash,
+    <ChunkKey<N> as DatabaseKey<N>>::Key: Send,
+    Compr: 'static + Compression + Copy + Send + Sync,
+    Compr::Data: 'static + Send,
+{
+    /// Spawns the background task on the current `tokio` runtime.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a `tokio` runtime.
+    pub fn spawn(db: Arc<ChunkDb<N, Compr>>, config: ChunkDbWriterConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let task = tokio::spawn(run_writer(db, receiver, config));
+
+        Self { sender, task }
+    }
+
+    /// Queues `chunk` to be written at `key`, waiting for room in the queue if it's full.
+    pub async fn write_chunk(&self, key: ChunkKey<N>, chunk: Compr::Data) {
+        self.send(WriterMessage::Write(key, chunk)).await
+    }
+
+    /// Queues `chunk` to be written at `key` only if there is room in the queue. Otherwise the chunk is returned, so callers
+    /// that can't wait (like a simulation tick) can try again later.
+    pub fn try_write_chunk(
+        &self,
+        key: ChunkKey<N>,
+        chunk: Compr::Data,
+    ) -> Result<(), (ChunkKey<N>, Compr::Data)> {
+        match self.sender.try_send(WriterMessage::Write(key, chunk)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(WriterMessage::Write(key, chunk))) => {
+                Err((key, chunk))
+            }
+            Err(mpsc::error::TrySendError::Full(WriterMessage::Flush(_))) => unreachable!(),
+            Err(mpsc::error::TrySendError::Closed(_)) => panic!("ChunkDbWriter task stopped"),
+        }
+    }
+
+    /// Writes all chunks queued before this call and flushes the database to disk.
+    ///
+    /// Errors from batches that were written in the background since the last flush are also returned here.
+    pub async fn flush_and_wait(&self) -> sled::Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.send(WriterMessage::Flush(done_tx)).await;
+
+        done_rx.await.expect("ChunkDbWriter task stopped")
+    }
+
+    /// Flushes all queued chunks, then stops the background task.
+    pub async fn shutdown(self) -> sled::Result<()> {
+        let result = self.flush_and_wait().await;
+        drop(self.sender);
+        self.task.await.expect("ChunkDbWriter task panicked");
+
+        result
+    }
+
+    async fn send(&self, message: WriterMessage<N, Compr::Data>) {
+        if self.sender.send(message).await.is_err() {
+            panic!("ChunkDbWriter task stopped");
+        }
+    }
+}
+
+async fn run_writer<N, Compr>(
+    db: Arc<ChunkDb<N, Compr>>,
+    mut receiver: mpsc::Receiver<WriterMessage<N, Compr::Data>>,
+    config: ChunkDbWriterConfig,
+) where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N> + Eq + Hash,
+    Compr: Compression + Copy,
+{
+    let mut batch = SmallKeyHashMap::default();
+    let mut error = None;
+
+    loop {
+        let message = if batch.is_empty() {
+            receiver.recv().await
+        } else {
+            match tokio::time::timeout(config.debounce, receiver.recv()).await {
+                Ok(message) => message,
+                Err(_elapsed) => {
+                    write_batch(&db, &mut batch, &mut error).await;
+                    continue;
+                }
+            }
+        };
+
+        match message {
+            Some(WriterMessage::Write(key, chunk)) => {
+                batch.insert(key, chunk);
+                if batch.len() >= config.max_batch_size {
+                    write_batch(&db, &mut batch, &mut error).await;
+                }
+            }
+            Some(WriterMessage::Flush(done_tx)) => {
+                write_batch(&db, &mut batch, &mut error).await;
+                let result = match error.take() {
+                    Some(e) => Err(e),
+                    None => db.tree().flush_async().await.map(|_| ()),
+                };
+                // The caller might have stopped waiting.
+                let _ = done_tx.send(result);
+            }
+            None => {
+                // All senders are gone.
+                write_batch(&db, &mut batch, &mut error).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn write_batch<N, Compr>(
+    db: &ChunkDb<N, Compr>,
+    batch: &mut SmallKeyHashMap<ChunkKey<N>, Compr::Data>,
+    error: &mut Option<sled::Error>,
+) where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N> + Eq + Hash,
+    Compr: Compression + Copy,
+{
+    if batch.is_empty() {
+        return;
+    }
+
+    if let Err(e) = db.write_chunks(batch.drain()).await {
+        // Keep the first error until the next flush reports it.
+        error.get_or_insert(e);
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use super::*;
+    use crate::{Array3x1, ChunkKey3, FastArrayCompressionNx1, FromBytesCompression, Lz4};
+
+    use tempdir::TempDir;
+
+    #[test]
+    fn queued_writes_are_batched_and_flushed() -> sled::Result<()> {
+        let tmp = TempDir::new("bb-test").unwrap();
+        let db = sled::Config::default()
+            .path(&tmp)
+            .use_compression(false)
+            .mode(sled::Mode::LowSpace)
+            .open()?;
+        let compression = FastArrayCompressionNx1::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = Arc::new(ChunkDb::new(db.open_tree("chunks")?, compression));
+
+        let chunk_shape = Point3i::fill(4);
+        let make_chunk = |min: Point3i, value: u8| {
+            Array3x1::fill(Extent3i::from_min_and_shape(min, chunk_shape), value)
+        };
+        let keys: Vec<_> = (0..10)
+            .map(|i| ChunkKey3::new(0, PointN([4 * i, 0, 0])))
+            .collect();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let config = ChunkDbWriterConfig {
+                debounce: Duration::from_secs(60),
+                max_batch_size: 4,
+                queue_capacity: 2,
+            };
+            let writer = ChunkDbWriter::spawn(chunk_db.clone(), config);
+
+            for &key in keys.iter() {
+                writer.write_chunk(key, make_chunk(key.minimum, 1)).await;
+            }
+            // Overwrite one chunk before it's written.
+            writer
+                .write_chunk(keys[9], make_chunk(keys[9].minimum, 2))
+                .await;
+
+            writer.flush_and_wait().await?;
+            for &key in keys.iter() {
+                let expected = make_chunk(key.minimum, if key == keys[9] { 2 } else { 1 });
+                assert_eq!(chunk_db.read_chunk(key).await?, Some(expected));
+            }
+
+            // The queue is tiny, so a burst of writes must fill it before the background task catches up.
+            let mut num_rejected = 0;
+            for &key in keys.iter() {
+                if writer
+                    .try_write_chunk(key, make_chunk(key.minimum, 3))
+                    .is_err()
+                {
+                    num_rejected += 1;
+                }
+            }
+            assert!(num_rejected > 0);
+
+            writer.shutdown().await
+        })
+    }
+
+    #[test]
+    fn debounced_batch_is_written_without_flush() -> sled::Result<()> {
+        let tmp = TempDir::new("bb-test").unwrap();
+        let db = sled::Config::default()
+            .path(&tmp)
+            .use_compression(false)
+            .mode(sled::Mode::LowSpace)
+            .open()?;
+        let compression = FastArrayCompressionNx1::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = Arc::new(ChunkDb::new(db.open_tree("chunks")?, compression));
+
+        let key = ChunkKey3::new(0, Point3i::ZERO);
+        let chunk = Array3x1::fill(
+            Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)),
+            7u8,
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let config = ChunkDbWriterConfig {
+                debounce: Duration::from_millis(10),
+                ..Default::default()
+            };
+            let writer = ChunkDbWriter::spawn(chunk_db.clone(), config);
+            writer.write_chunk(key, chunk.clone()).await;
+
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(chunk_db.read_chunk(key).await?, Some(chunk));
+
+            writer.shutdown().await
+        })
+    }
+}
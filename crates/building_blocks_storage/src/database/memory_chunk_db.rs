@@ -0,0 +1,377 @@
+use super::{decompress_in_batches, BackendChunkDb, ChunkDbBackend, DatabaseKey};
+
+use crate::{ChunkKey, Compression};
+
+use building_blocks_core::prelude::*;
+
+use core::convert::Infallible;
+use futures::future::{join_all, BoxFuture};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// A `ChunkDbBackend` that only lives in memory.
+#[derive(Default)]
+pub struct MemoryBackend {
+    map: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    /// The number of values stored.
+    pub fn len(&self) -> usize {
+        self.map.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ChunkDbBackend for MemoryBackend {
+    type Error = Infallible;
+    type Bytes = Vec<u8>;
+
+    fn write_batch(&self, kvs: Vec<(Vec<u8>, Vec<u8>)>) -> BoxFuture<'_, Result<(), Infallible>> {
+        Box::pin(async move {
+            self.map.write().unwrap().extend(kvs);
+
+            Ok(())
+        })
+    }
+
+    fn read(&self, key: Vec<u8>) -> BoxFuture<'_, Result<Option<Vec<u8>>, Infallible>> {
+        Box::pin(async move { Ok(self.map.read().unwrap().get(&key).cloned()) })
+    }
+
+    fn read_range(
+        &self,
+        start: Vec<u8>,
+        end: Vec<u8>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'_, Result<Vec<(Vec<u8>, Vec<u8>)>, Infallible>> {
+        Box::pin(async move {
+            let map = self.map.read().unwrap();
+            let kvs = map
+                .range(start..=end)
+                .map(|(key, value)| (key.clone(), value.clone()));
+
+            Ok(match limit {
+                Some(limit) => kvs.take(limit).collect(),
+                None => kvs.collect(),
+            })
+        })
+    }
+}
+
+/// A database of chunks that only lives in memory, for unit tests and headless servers that don't need persistence.
+///
+/// This has the same API and semantics as the `sled`-backed `ChunkDb`: chunks are compressed with `compression`, batches are
+/// written atomically, and all reads are in Morton order. None of the methods can fail, but they return `Result`s so code
+/// written for a persistent database can use a `MemoryChunkDb` unchanged.
+pub type MemoryChunkDb<N, Compr> = BackendChunkDb<N, Compr, MemoryBackend>;
+
+/// A 2D `MemoryChunkDb`.
+pub type MemoryChunkDb2<Compr> = MemoryChunkDb<[i32; 2], Compr>;
+/// A 3D `MemoryChunkDb`.
+pub type MemoryChunkDb3<Compr> = MemoryChunkDb<[i32; 3], Compr>;
+
+/// A `VersionedChunkDb` that only lives in memory. See `MemoryChunkDb`.
+///
+/// This keeps every version of each chunk with the same semantics as `VersionedChunkDb`, including removal, rollback with
+/// `discard_versions_after`, and `collect_garbage`.
+pub struct VersionedMemoryChunkDb<N, Compr> {
+    // A `None` value is a "tombstone" for a chunk that was removed in that version.
+    map: RwLock<BTreeMap<(Vec<u8>, u64), Option<Vec<u8>>>>,
+    compression: Compr,
+    marker: std::marker::PhantomData<N>,
+}
+
+/// A 2D `VersionedMemoryChunkDb`.
+pub type VersionedMemoryChunkDb2<Compr> = VersionedMemoryChunkDb<[i32; 2], Compr>;
+/// A 3D `VersionedMemoryChunkDb`.
+pub type VersionedMemoryChunkDb3<Compr> = VersionedMemoryChunkDb<[i32; 3], Compr>;
+
+impl<N, Compr> VersionedMemoryChunkDb<N, Compr> {
+    pub fn new(compression: Compr) -> Self {
+        Self {
+            map: Default::default(),
+            compression,
+            marker: Default::default(),
+        }
+    }
+
+    /// The number of stored chunk versions, including removals.
+    pub fn num_versions_stored(&self) -> usize {
+        self.map.read().unwrap().len()
+    }
+}
+
+impl<N, Compr> VersionedMemoryChunkDb<N, Compr>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+{
+    /// Atomically writes a batch of chunks tagged with `version`. A `None` chunk is removed as of `version`.
+    pub async fn write_chunks<Data>(
+        &self,
+        version: u64,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Option<Data>)>,
+    ) -> Result<(), Infallible>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let compressed_chunks = join_all(chunks.map(|(key, chunk)| async move {
+            (
+                ChunkKey::<N>::into_ord_key(key),
+                chunk.map(|chunk| self.compression.compress(chunk.borrow())),
+            )
+        }))
+        .await;
+
+        // Then atomically write them all to the map.
+        let mut map = self.map.write().unwrap();
+        for (ord_key, chunk) in compressed_chunks.into_iter() {
+            let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ord_key);
+            map.insert(
+                (key_bytes.as_ref().to_vec(), version),
+                chunk.map(|c| c.take_bytes()),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Reads and decompresses the chunk at `key` as of `version`, if it exists.
+    pub async fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+        version: u64,
+    ) -> Result<Option<Compr::Data>, Infallible> {
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key))
+            .as_ref()
+            .to_vec();
+        let map = self.map.read().unwrap();
+        let latest = map
+            .range((key_bytes.clone(), 0)..=(key_bytes, version))
+            .next_back();
+
+        Ok(latest
+            .and_then(|(_, value)| value.as_ref())
+            .map(|bytes| Compr::decompress_from_reader(bytes.as_slice()).unwrap()))
+    }
+
+    /// Scans the given orthant for chunks as of `version`, decompresses them, then passes them to `chunk_rx` in Morton order.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Infallible> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+        let (start, end) = range.into_inner();
+
+        self.read_range(start.as_ref(), end.as_ref(), version, chunk_rx)
+            .await
+    }
+
+    /// Reads all chunks in the given `lod` as of `version`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Infallible> {
+        let start = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod));
+        let end = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(start.as_ref(), end.as_ref(), version, chunk_rx)
+            .await
+    }
+
+    async fn read_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), Infallible> {
+        // Keep the latest value no newer than `version` for each chunk.
+        let mut latest: BTreeMap<Vec<u8>, Option<Vec<u8>>> = BTreeMap::new();
+        {
+            let map = self.map.read().unwrap();
+            for ((key_bytes, value_version), value) in
+                map.range((start.to_vec(), 0)..=(end.to_vec(), u64::MAX))
+            {
+                if *value_version <= version {
+                    latest.insert(key_bytes.clone(), value.clone());
+                }
+            }
+        }
+        let read_kvs: Vec<_> = latest
+            .into_iter()
+            .filter_map(|(key_bytes, value)| value.map(|v| (key_bytes, v)))
+            .collect();
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+
+    /// Atomically removes all versions newer than `version`, rolling the database back to that version.
+    pub fn discard_versions_after(&self, version: u64) -> Result<(), Infallible> {
+        let mut map = self.map.write().unwrap();
+        let discarded: Vec<_> = map.keys().filter(|(_, v)| *v > version).cloned().collect();
+        for key in discarded.iter() {
+            map.remove(key);
+        }
+
+        Ok(())
+    }
+
+    /// Atomically removes all chunk versions that aren't needed to read `oldest_kept` or any newer version.
+    pub fn collect_garbage(&self, oldest_kept: u64) -> Result<(), Infallible> {
+        let mut map = self.map.write().unwrap();
+
+        let mut removed = Vec::new();
+        // The latest version of the current chunk that is no newer than `oldest_kept`, and whether it's a tombstone.
+        let mut kept: Option<(&(Vec<u8>, u64), bool)> = None;
+        for (key, value) in map.iter() {
+            let same_chunk = kept.map_or(false, |(kept_key, _)| kept_key.0 == key.0);
+            if !same_chunk {
+                // A chunk removed at or before the oldest kept version doesn't need history.
+                if let Some((kept_key, true)) = kept.take() {
+                    removed.push(kept_key.clone());
+                }
+            }
+
+            if key.1 <= oldest_kept {
+                // Superseded by this older-than-kept version.
+                if let Some((kept_key, _)) = kept.take() {
+                    removed.push(kept_key.clone());
+                }
+                kept = Some((key, value.is_none()));
+            }
+        }
+        if let Some((kept_key, true)) = kept {
+            removed.push(kept_key.clone());
+        }
+
+        for key in removed.iter() {
+            map.remove(key);
+        }
+
+        Ok(())
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Array3x1, ChunkKey3, FastArrayCompressionNx1, FromBytesCompression, NoCompression,
+    };
+
+    use super::*;
+
+    use futures::executor::block_on;
+
+    fn chunk(min: Point3i, value: u8) -> Array3x1<u8> {
+        Array3x1::fill(Extent3i::from_min_and_shape(min, Point3i::fill(16)), value)
+    }
+
+    #[test]
+    fn db_round_trip() -> Result<(), Infallible> {
+        let chunk_mins = [
+            PointN([16, 0, 0]),
+            PointN([0, 16, 0]),
+            PointN([0, 0, 16]),
+            PointN([0, -16, 0]),
+        ];
+        let write_chunks: Vec<_> = chunk_mins
+            .iter()
+            .map(|&min| (ChunkKey3::new(0, min), chunk(min, 1)))
+            .collect();
+
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let chunk_db = MemoryChunkDb::new(MemoryBackend::default(), compression);
+        block_on(chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))))?;
+        assert_eq!(chunk_db.backend().len(), 4);
+
+        // This octant should contain the chunks in the positive octant, but not the other chunk.
+        let octant = Octant::new_unchecked(Point3i::ZERO, 32);
+        let mut read_chunks = Vec::new();
+        block_on(chunk_db.read_chunks_in_orthant(0, octant, |k, v| read_chunks.push((k, v))))?;
+        assert_eq!(read_chunks, write_chunks[..3].to_vec());
+
+        let extent = Extent3i::from_min_and_shape(PointN([0, -16, 0]), PointN([1, 17, 1]));
+        let mut read_chunks = Vec::new();
+        block_on(chunk_db.read_chunks_in_extent(0, extent, |k, v| read_chunks.push((k, v))))?;
+        assert_eq!(read_chunks, vec![write_chunks[3].clone()]);
+
+        assert_eq!(
+            block_on(chunk_db.read_chunk(ChunkKey3::new(1, Point3i::ZERO)))?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn versioned_reads_rollback_and_garbage_collection() -> Result<(), Infallible> {
+        let key = ChunkKey3::new(0, Point3i::ZERO);
+        let other_key = ChunkKey3::new(0, PointN([16, 0, 0]));
+
+        let compression = FastArrayCompressionNx1::from_bytes_compression(NoCompression);
+        let chunk_db = VersionedMemoryChunkDb::new(compression);
+        block_on(
+            chunk_db.write_chunks(
+                1,
+                vec![
+                    (key, Some(chunk(key.minimum, 1))),
+                    (other_key, Some(chunk(other_key.minimum, 1))),
+                ]
+                .into_iter(),
+            ),
+        )?;
+        block_on(chunk_db.write_chunks(2, vec![(key, Some(chunk(key.minimum, 2)))].into_iter()))?;
+        block_on(chunk_db.write_chunks(3, vec![(other_key, None::<Array3x1<u8>>)].into_iter()))?;
+
+        assert_eq!(block_on(chunk_db.read_chunk(key, 0))?, None);
+        assert_eq!(
+            block_on(chunk_db.read_chunk(key, 1))?,
+            Some(chunk(key.minimum, 1))
+        );
+        assert_eq!(
+            block_on(chunk_db.read_chunk(key, 5))?,
+            Some(chunk(key.minimum, 2))
+        );
+        assert_eq!(block_on(chunk_db.read_chunk(other_key, 3))?, None);
+
+        let mut num_chunks_at_2 = 0;
+        block_on(chunk_db.read_all_chunks(0, 2, |_, _| num_chunks_at_2 += 1))?;
+        assert_eq!(num_chunks_at_2, 2);
+
+        // Only the latest versions are needed to read version 3.
+        chunk_db.collect_garbage(3)?;
+        assert_eq!(chunk_db.num_versions_stored(), 1);
+        assert_eq!(
+            block_on(chunk_db.read_chunk(key, 3))?,
+            Some(chunk(key.minimum, 2))
+        );
+
+        chunk_db.discard_versions_after(1)?;
+        assert_eq!(chunk_db.num_versions_stored(), 0);
+
+        Ok(())
+    }
+}
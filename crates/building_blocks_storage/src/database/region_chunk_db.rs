@@ -0,0 +1,645 @@
+use super::{decompress_in_batches, DatabaseKey};
+
+use crate::{ArrayIndexer, AsyncChunkReader, ChunkKey, Compression, Local};
+
+use building_blocks_core::{orthants_covering_extent, prelude::*};
+
+use core::ops::RangeInclusive;
+use futures::future::{join_all, BoxFuture};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Chunk data in a region file is allocated in units of this many bytes, so chunks can grow a little without moving.
+pub const REGION_SECTOR_BYTES: u32 = 512;
+
+const REGION_MAGIC: [u8; 4] = *b"BBRF";
+const PREAMBLE_BYTES: u64 = 8;
+const SLOT_BYTES: u64 = 16;
+
+/// A persistent database of chunks that packs many chunks into each file, like the "region files" of Minecraft's Anvil format.
+///
+/// This has the same API as the `sled`-backed `ChunkDb`, but instead of storing one small key-value pair per chunk, each
+/// region of `2^region_exponent` chunks along every axis is stored in a single file under the `root` directory. Batching chunks
+/// this way avoids the fragmentation of millions of tiny keys, keeps I/O sequential, and makes backups as easy as copying a
+/// directory.
+///
+/// # File Format
+///
+/// A region file is named by the hex-encoded DB key of the least chunk in the region, with the `.region` extension. It starts
+/// with an index header:
+///
+/// - the magic bytes `BBRF`
+/// - the number of chunk slots as a little-endian `u32`
+/// - one 16-byte entry per slot, in array order: the `u64` byte offset, `u32` length, and `u32` capacity of the compressed
+///   chunk, all little-endian (an offset of 0 means the slot is empty)
+///
+/// Each chunk is compressed separately with `compression` and stored in `REGION_SECTOR_BYTES`-aligned space after the header.
+/// When a chunk is rewritten and still fits in its allocated capacity, it is rewritten in place. Otherwise it is appended to
+/// the end of the file, and its old space is abandoned.
+///
+/// Unlike `ChunkDb`, writes are not transactional. Chunk data is synced before the header that points to it, so appended chunks
+/// are never lost to a torn header, but a crash during an in-place rewrite can corrupt that chunk.
+///
+/// The chunk values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub struct RegionChunkDb<N, Compr> {
+    root: PathBuf,
+    chunk_shape: PointN<N>,
+    region_exponent: u8,
+    compression: Compr,
+    write_lock: Mutex<()>,
+}
+
+/// A 2D `RegionChunkDb`.
+pub type RegionChunkDb2<Compr> = RegionChunkDb<[i32; 2], Compr>;
+/// A 3D `RegionChunkDb`.
+pub type RegionChunkDb3<Compr> = RegionChunkDb<[i32; 3], Compr>;
+
+impl<N, Compr> RegionChunkDb<N, Compr> {
+    /// The directory containing all of the region files.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Each region has `2^region_exponent` chunks along every axis.
+    pub fn region_exponent(&self) -> u8 {
+        self.region_exponent
+    }
+}
+
+impl<N, Compr> RegionChunkDb<N, Compr>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+{
+    /// Opens (or creates) the database in the `root` directory. The `chunk_shape` and `region_exponent` must be the same every
+    /// time the same database is opened.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_shape` is not a cube with power of 2 edge length.
+    pub fn open(
+        root: impl AsRef<Path>,
+        chunk_shape: PointN<N>,
+        region_exponent: u8,
+        compression: Compr,
+    ) -> io::Result<Self> {
+        assert!(chunk_shape.is_cube() && chunk_shape.dimensions_are_powers_of_2());
+
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        Ok(Self {
+            root,
+            chunk_shape,
+            region_exponent,
+            compression,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    pub fn chunk_shape(&self) -> PointN<N> {
+        self.chunk_shape
+    }
+
+    /// Insert a set of chunks. This will compress all of the chunks asynchronously then write them into their region files.
+    /// Pre-existing chunks will be overwritten.
+    pub async fn write_chunks<Data>(
+        &self,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Data)>,
+    ) -> io::Result<()>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let compressed_chunks =
+            join_all(chunks.map(|(key, chunk)| async move {
+                (key, self.compression.compress(chunk.borrow()))
+            }))
+            .await;
+
+        // Group them by region, so each region file is only opened once.
+        let mut regions = BTreeMap::new();
+        for (key, chunk) in compressed_chunks.into_iter() {
+            let (region_key, slot) = self.region_key_and_slot(key);
+            regions
+                .entry(ChunkKey::<N>::into_ord_key(region_key))
+                .or_insert_with(Vec::new)
+                .push((slot, chunk.take_bytes()));
+        }
+
+        let _lock = self.write_lock.lock().unwrap();
+        for (region_ord_key, region_chunks) in regions.into_iter() {
+            self.write_region(ChunkKey::<N>::from_ord_key(region_ord_key), region_chunks)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_region(
+        &self,
+        region_key: ChunkKey<N>,
+        chunks: Vec<(usize, Vec<u8>)>,
+    ) -> io::Result<()> {
+        let num_slots = self.slots_per_region();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(self.region_path(&region_key))?;
+
+        let file_len = file.metadata()?.len();
+        let mut header = if file_len == 0 {
+            // Write an empty header first, so the file is valid even if we fail before writing the final header.
+            let header = vec![SlotEntry::default(); num_slots];
+            write_header(&mut file, &header)?;
+
+            header
+        } else {
+            read_header(&mut file, num_slots)?
+        };
+
+        let mut end = file_len.max(header_bytes(num_slots));
+        for (slot, bytes) in chunks.into_iter() {
+            let len = u32::try_from(bytes.len()).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Compressed chunk is too large")
+            })?;
+
+            let entry = &mut header[slot];
+            if entry.is_empty() || len > entry.capacity {
+                let num_sectors = (len.max(1) + REGION_SECTOR_BYTES - 1) / REGION_SECTOR_BYTES;
+                let offset = round_up_to_sector(end);
+                *entry = SlotEntry {
+                    offset,
+                    len,
+                    capacity: num_sectors * REGION_SECTOR_BYTES,
+                };
+                end = offset + entry.capacity as u64;
+            } else {
+                entry.len = len;
+            }
+
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.write_all(&bytes)?;
+        }
+        if end > file_len {
+            file.set_len(end)?;
+        }
+        file.sync_data()?;
+
+        write_header(&mut file, &header)?;
+        file.sync_data()
+    }
+
+    /// Reads and decompresses the chunk at `key`, if it exists.
+    pub async fn read_chunk(&self, key: ChunkKey<N>) -> io::Result<Option<Compr::Data>> {
+        let (region_key, slot) = self.region_key_and_slot(key);
+        let mut file = match File::open(self.region_path(&region_key)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let entry = read_entry(&mut file, self.slots_per_region(), slot)?;
+        if entry.is_empty() {
+            return Ok(None);
+        }
+        let compressed_chunk = read_chunk_bytes(&mut file, entry)?;
+
+        Compr::decompress_from_reader(compressed_chunk.as_slice()).map(Some)
+    }
+
+    /// Scans the given orthant for chunks, decompresses them, then passes them to `chunk_rx` in Morton order. Only the region
+    /// files that overlap the orthant are opened.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> io::Result<()> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    /// This is like `read_chunks_in_orthant`, but it works for the given `extent`. See `ChunkDb::read_orthants_covering_extent`
+    /// for details about the covering.
+    pub async fn read_orthants_covering_extent(
+        &self,
+        lod: u8,
+        orthant_exponent: i32,
+        extent: ExtentN<N>,
+        mut chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> io::Result<()> {
+        for orthant in orthants_covering_extent(extent, orthant_exponent) {
+            self.read_chunks_in_orthant(lod, orthant, &mut chunk_rx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads all chunks in the given `lod`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> io::Result<()> {
+        let range = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod))
+            ..=ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    async fn read_range(
+        &self,
+        range: RangeInclusive<<ChunkKey<N> as DatabaseKey<N>>::KeyBytes>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> io::Result<()> {
+        let (start, end) = (range.start().as_ref(), range.end().as_ref());
+        let num_slots = self.slots_per_region();
+
+        let mut read_kvs = Vec::new();
+        for region_key in self.region_keys()?.into_iter() {
+            // Every region is an orthant, so its chunks are contiguous in key space.
+            let region_range = ChunkKey::<N>::orthant_range(
+                region_key.lod,
+                Orthant::new_unchecked(region_key.minimum, self.region_edge_length()),
+            );
+            if region_range.end().as_ref() < start || region_range.start().as_ref() > end {
+                continue;
+            }
+
+            let mut file = File::open(self.region_path(&region_key))?;
+            let header = read_header(&mut file, num_slots)?;
+            let region_chunk_extent = ExtentN::from_min_and_shape(
+                region_key.minimum.vector_div_floor(self.chunk_shape),
+                self.region_shape(),
+            );
+            for chunk_coords in region_chunk_extent.iter_points() {
+                let key = ChunkKey::new(region_key.lod, chunk_coords * self.chunk_shape);
+                let key_bytes =
+                    ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+                if key_bytes.as_ref() < start || key_bytes.as_ref() > end {
+                    continue;
+                }
+
+                let entry = header[self.slot(chunk_coords)];
+                if !entry.is_empty() {
+                    read_kvs.push((key_bytes, read_chunk_bytes(&mut file, entry)?));
+                }
+            }
+        }
+        read_kvs.sort_by(|(k1, _), (k2, _)| k1.as_ref().cmp(k2.as_ref()));
+
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+
+    /// Returns the key of the least chunk in the region containing `key`, and the slot of `key` in that region.
+    fn region_key_and_slot(&self, key: ChunkKey<N>) -> (ChunkKey<N>, usize) {
+        let chunk_coords = key.minimum.vector_div_floor(self.chunk_shape);
+
+        (
+            ChunkKey::new(
+                key.lod,
+                self.region_min_coords(chunk_coords) * self.chunk_shape,
+            ),
+            self.slot(chunk_coords),
+        )
+    }
+
+    fn region_min_coords(&self, chunk_coords: PointN<N>) -> PointN<N> {
+        let exponent = self.region_exponent as i32;
+
+        (chunk_coords >> exponent) << exponent
+    }
+
+    /// The index of the chunk at `chunk_coords` (in chunk units) within its region.
+    fn slot(&self, chunk_coords: PointN<N>) -> usize {
+        let local = Local(chunk_coords - self.region_min_coords(chunk_coords));
+
+        N::stride_from_local_point(self.region_shape(), local).0
+    }
+
+    fn region_path(&self, region_key: &ChunkKey<N>) -> PathBuf {
+        let ord_key =
+            ChunkKey::<N>::into_ord_key(ChunkKey::new(region_key.lod, region_key.minimum));
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ord_key);
+        let mut file_name: String = key_bytes
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        file_name.push_str(".region");
+
+        self.root.join(file_name)
+    }
+
+    /// The keys of all regions that have a file in the root directory.
+    fn region_keys(&self) -> io::Result<Vec<ChunkKey<N>>> {
+        let key_len = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(0))
+            .as_ref()
+            .len();
+
+        let mut keys = Vec::new();
+        for dir_entry in fs::read_dir(&self.root)? {
+            let path = dir_entry?.path();
+            if path.extension().map_or(true, |ext| ext != "region") {
+                continue;
+            }
+            let key_bytes = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(decode_hex);
+            if let Some(key_bytes) = key_bytes.filter(|bytes| bytes.len() == key_len) {
+                keys.push(ChunkKey::<N>::from_ord_key(
+                    ChunkKey::<N>::ord_key_from_be_bytes(&key_bytes),
+                ));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    fn region_shape(&self) -> PointN<N> {
+        PointN::fill(1 << self.region_exponent)
+    }
+
+    fn region_edge_length(&self) -> i32 {
+        self.chunk_shape.at(0) << self.region_exponent
+    }
+
+    fn slots_per_region(&self) -> usize {
+        self.region_shape().volume() as usize
+    }
+}
+
+impl<N, Compr> AsyncChunkReader<N, Compr::Data> for RegionChunkDb<N, Compr>
+where
+    N: ArrayIndexer<N> + Send + Sync,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy + Sync,
+    Compr::Data: Send,
+{
+    type Error = io::Error;
+
+    fn read_chunk(&self, key: ChunkKey<N>) -> BoxFuture<'_, io::Result<Option<Compr::Data>>> {
+        Box::pin(RegionChunkDb::read_chunk(self, key))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct SlotEntry {
+    offset: u64,
+    len: u32,
+    capacity: u32,
+}
+
+impl SlotEntry {
+    fn is_empty(&self) -> bool {
+        self.offset == 0
+    }
+
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let mut offset = [0; 8];
+        let mut len = [0; 4];
+        let mut capacity = [0; 4];
+        offset.copy_from_slice(&bytes[0..8]);
+        len.copy_from_slice(&bytes[8..12]);
+        capacity.copy_from_slice(&bytes[12..16]);
+
+        Self {
+            offset: u64::from_le_bytes(offset),
+            len: u32::from_le_bytes(len),
+            capacity: u32::from_le_bytes(capacity),
+        }
+    }
+
+    fn write_le_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&self.len.to_le_bytes());
+        bytes.extend_from_slice(&self.capacity.to_le_bytes());
+    }
+}
+
+fn header_bytes(num_slots: usize) -> u64 {
+    PREAMBLE_BYTES + num_slots as u64 * SLOT_BYTES
+}
+
+fn round_up_to_sector(offset: u64) -> u64 {
+    let sector = REGION_SECTOR_BYTES as u64;
+
+    (offset + sector - 1) / sector * sector
+}
+
+fn check_preamble(file: &mut File, num_slots: usize) -> io::Result<()> {
+    let mut preamble = [0; PREAMBLE_BYTES as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut preamble)?;
+
+    let mut file_num_slots = [0; 4];
+    file_num_slots.copy_from_slice(&preamble[4..8]);
+    if preamble[0..4] != REGION_MAGIC || u32::from_le_bytes(file_num_slots) as usize != num_slots {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a region file with the expected number of chunk slots",
+        ));
+    }
+
+    Ok(())
+}
+
+fn read_header(file: &mut File, num_slots: usize) -> io::Result<Vec<SlotEntry>> {
+    check_preamble(file, num_slots)?;
+
+    let mut bytes = vec![0; num_slots * SLOT_BYTES as usize];
+    file.read_exact(&mut bytes)?;
+
+    Ok(bytes
+        .chunks_exact(SLOT_BYTES as usize)
+        .map(SlotEntry::from_le_bytes)
+        .collect())
+}
+
+fn read_entry(file: &mut File, num_slots: usize, slot: usize) -> io::Result<SlotEntry> {
+    check_preamble(file, num_slots)?;
+
+    let mut bytes = [0; SLOT_BYTES as usize];
+    file.seek(SeekFrom::Start(PREAMBLE_BYTES + slot as u64 * SLOT_BYTES))?;
+    file.read_exact(&mut bytes)?;
+
+    Ok(SlotEntry::from_le_bytes(&bytes))
+}
+
+fn write_header(file: &mut File, header: &[SlotEntry]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(header_bytes(header.len()) as usize);
+    bytes.extend_from_slice(&REGION_MAGIC);
+    bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    for entry in header.iter() {
+        entry.write_le_bytes(&mut bytes);
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&bytes)
+}
+
+fn read_chunk_bytes(file: &mut File, entry: SlotEntry) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; entry.len as usize];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use crate::{
+        Array3x1, Array3x2, ChunkKey3, FastArrayCompressionNx1, FastArrayCompressionNx2,
+        FromBytesCompression, Lz4,
+    };
+
+    use super::*;
+
+    use tempdir::TempDir;
+
+    #[test]
+    fn db_round_trip() -> io::Result<()> {
+        let chunk_mins = [
+            PointN([16, 0, 0]),
+            PointN([0, 16, 0]),
+            PointN([0, 0, 16]),
+            PointN([0, -16, 0]),
+            PointN([64, 0, 0]),
+        ];
+        let chunk_shape = Point3i::fill(16);
+        let write_chunks: Vec<_> = chunk_mins
+            .iter()
+            .map(|&min| {
+                (
+                    ChunkKey3::new(0, min),
+                    Array3x2::fill(Extent3i::from_min_and_shape(min, chunk_shape), (1u16, b'a')),
+                )
+            })
+            .collect();
+
+        let tmp = TempDir::new("bb-test").unwrap();
+
+        // NOTE: This compression is not portable because it is naive to endianness.
+        let compression = FastArrayCompressionNx2::from_bytes_compression(Lz4 { level: 10 });
+        // Regions of 2x2x2 chunks.
+        let chunk_db = RegionChunkDb::open(&tmp, chunk_shape, 1, compression)?;
+
+        futures::executor::block_on(
+            chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))),
+        )?;
+
+        // The first 3 chunks share a region, and the other 2 chunks each get their own.
+        assert_eq!(fs::read_dir(&tmp)?.count(), 3);
+
+        // This octant should contain the chunks in the positive octant, but not the other chunks.
+        let octant = Octant::new_unchecked(Point3i::ZERO, 32);
+
+        let mut read_chunks = Vec::new();
+        futures::executor::block_on(
+            chunk_db.read_chunks_in_orthant(0, octant, |k, v| read_chunks.push((k, v))),
+        )?;
+
+        assert_eq!(
+            read_chunks,
+            vec![
+                write_chunks[0].clone(),
+                write_chunks[1].clone(),
+                write_chunks[2].clone()
+            ]
+        );
+
+        // Reopening the database finds all of the chunks.
+        drop(chunk_db);
+        let chunk_db = RegionChunkDb::open(&tmp, chunk_shape, 1, compression)?;
+        let mut num_chunks = 0;
+        futures::executor::block_on(chunk_db.read_all_chunks(0, |_, _| num_chunks += 1))?;
+        assert_eq!(num_chunks, 5);
+
+        let mut num_lod1_chunks = 0;
+        futures::executor::block_on(chunk_db.read_all_chunks(1, |_, _| num_lod1_chunks += 1))?;
+        assert_eq!(num_lod1_chunks, 0);
+
+        assert_eq!(
+            futures::executor::block_on(chunk_db.read_chunk(write_chunks[3].0))?,
+            Some(write_chunks[3].1.clone())
+        );
+        assert_eq!(
+            futures::executor::block_on(chunk_db.read_chunk(ChunkKey3::new(0, Point3i::ZERO)))?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rewrite_chunk_in_place_when_it_fits() -> io::Result<()> {
+        let key = ChunkKey3::new(0, Point3i::ZERO);
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+
+        let tmp = TempDir::new("bb-test").unwrap();
+        let compression = FastArrayCompressionNx1::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = RegionChunkDb::open(&tmp, extent.shape, 2, compression)?;
+        let region_path = chunk_db.region_path(&key);
+
+        let write = |chunk: &Array3x1<u16>| {
+            futures::executor::block_on(chunk_db.write_chunks(std::iter::once((key, chunk))))
+        };
+        let read = || futures::executor::block_on(chunk_db.read_chunk(key));
+
+        let chunk1 = Array3x1::fill(extent, 1u16);
+        write(&chunk1)?;
+        let file_len = fs::metadata(&region_path)?.len();
+        assert_eq!(read()?, Some(chunk1));
+
+        // A chunk with the same compressed size is rewritten in place.
+        let chunk2 = Array3x1::fill(extent, 2u16);
+        write(&chunk2)?;
+        assert_eq!(fs::metadata(&region_path)?.len(), file_len);
+        assert_eq!(read()?, Some(chunk2));
+
+        // A chunk that doesn't compress as well must be moved to the end of the file.
+        let chunk3 = Array3x1::fill_with(extent, |p| {
+            ((p.x() * 73_856_093) ^ (p.y() * 19_349_663) ^ (p.z() * 83_492_791)) as u16
+        });
+        write(&chunk3)?;
+        assert!(fs::metadata(&region_path)?.len() > file_len);
+        assert_eq!(read()?, Some(chunk3));
+
+        Ok(())
+    }
+}
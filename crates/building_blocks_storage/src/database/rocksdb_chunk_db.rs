@@ -0,0 +1,295 @@
+use super::{decompress_in_batches, DatabaseKey};
+
+use crate::{AsyncChunkReader, ChunkKey, Compression};
+
+use building_blocks_core::{orthants_covering_extent, prelude::*};
+
+use core::ops::RangeInclusive;
+use futures::future::{join_all, BoxFuture};
+use rocksdb::{ColumnFamilyDescriptor, Direction, IteratorMode, Options, WriteBatch, DB};
+use std::borrow::Borrow;
+use std::path::Path;
+
+pub use rocksdb;
+
+/// A persistent database of chunks, backed by the `rocksdb` crate.
+///
+/// This has the same API as the `sled`-backed `ChunkDb`, but each LOD is stored in its own column family, named by
+/// `lod_column_family_name`. The keys are Morton codes for the corresponding chunk coordinates. This ensures that all of the
+/// chunks in an orthant are stored in a contiguous key space.
+///
+/// The DB values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub struct RocksChunkDb<N, Compr> {
+    db: DB,
+    compression: Compr,
+    marker: std::marker::PhantomData<N>,
+}
+
+/// A 2D `RocksChunkDb`.
+pub type RocksChunkDb2<Compr> = RocksChunkDb<[i32; 2], Compr>;
+/// A 3D `RocksChunkDb`.
+pub type RocksChunkDb3<Compr> = RocksChunkDb<[i32; 3], Compr>;
+
+/// The name of the column family that stores the chunks for `lod`.
+pub fn lod_column_family_name(lod: u8) -> String {
+    format!("lod{}", lod)
+}
+
+impl<N, Compr> RocksChunkDb<N, Compr> {
+    /// `db` must already have a column family for each LOD that will be written.
+    pub fn new(db: DB, compression: Compr) -> Self {
+        Self {
+            db,
+            compression,
+            marker: Default::default(),
+        }
+    }
+
+    /// Opens (or creates) the database at `path` with a column family for each of LODs `0..num_lods`.
+    pub fn open(
+        path: impl AsRef<Path>,
+        num_lods: u8,
+        compression: Compr,
+    ) -> Result<Self, rocksdb::Error> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let column_families = (0..num_lods).map(|lod| {
+            ColumnFamilyDescriptor::new(lod_column_family_name(lod), Options::default())
+        });
+        let db = DB::open_cf_descriptors(&options, path, column_families)?;
+
+        Ok(Self::new(db, compression))
+    }
+
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+}
+
+impl<N, Compr> RocksChunkDb<N, Compr>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+{
+    /// Insert a set of chunks. This will compress all of the chunks asynchronously then insert them into the database.
+    /// Pre-existing chunks will be overwritten.
+    ///
+    /// # Panics
+    ///
+    /// If the database doesn't have a column family for the LOD of any of the chunks.
+    pub async fn write_chunks<Data>(
+        &self,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Data)>,
+    ) -> Result<(), rocksdb::Error>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let mut compressed_chunks = Vec::new();
+        for (key, compressed_chunk) in join_all(chunks.map(|(key, chunk)| async move {
+            (
+                ChunkKey::<N>::into_ord_key(key),
+                self.compression.compress(chunk.borrow()),
+            )
+        }))
+        .await
+        .into_iter()
+        {
+            compressed_chunks.push((key, compressed_chunk));
+        }
+        // Sort them by the Ord key.
+        compressed_chunks.sort_by_key(|(k, _)| *k);
+
+        // Then atomically write them all to the database.
+        let mut batch = WriteBatch::default();
+        for (db_key, chunk) in compressed_chunks.into_iter() {
+            let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(db_key);
+            let lod = key_bytes.as_ref()[0];
+            let cf = self
+                .db
+                .cf_handle(&lod_column_family_name(lod))
+                .unwrap_or_else(|| panic!("No column family for LOD {}", lod));
+            batch.put_cf(cf, key_bytes.as_ref(), chunk.take_bytes());
+        }
+        self.db.write(batch)?;
+
+        Ok(())
+    }
+
+    /// Reads and decompresses the chunk at `key`, if it exists.
+    pub async fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+    ) -> Result<Option<Compr::Data>, rocksdb::Error> {
+        let cf = if let Some(cf) = self.db.cf_handle(&lod_column_family_name(key.lod)) {
+            cf
+        } else {
+            return Ok(None);
+        };
+
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+        let compressed_chunk = self.db.get_cf(cf, key_bytes.as_ref())?;
+
+        Ok(compressed_chunk.map(|bytes| Compr::decompress_from_reader(bytes.as_slice()).unwrap()))
+    }
+
+    /// Scans the given orthant for chunks, decompresses them, then passes them to `chunk_rx`. Because chunk keys are stored in
+    /// Morton order, the chunks in any orthant are guaranteed to be contiguous.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), rocksdb::Error> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+
+        self.read_range(lod, range, chunk_rx).await
+    }
+
+    /// This is like `read_chunks_in_orthant`, but it works for the given `extent`. See `ChunkDb::read_orthants_covering_extent`
+    /// for details about the covering.
+    pub async fn read_orthants_covering_extent(
+        &self,
+        lod: u8,
+        orthant_exponent: i32,
+        extent: ExtentN<N>,
+        mut chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), rocksdb::Error> {
+        for orthant in orthants_covering_extent(extent, orthant_exponent) {
+            self.read_chunks_in_orthant(lod, orthant, &mut chunk_rx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads all chunks in the given `lod`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), rocksdb::Error> {
+        let range = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod))
+            ..=ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(lod, range, chunk_rx).await
+    }
+
+    async fn read_range(
+        &self,
+        lod: u8,
+        range: RangeInclusive<<ChunkKey<N> as DatabaseKey<N>>::KeyBytes>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> Result<(), rocksdb::Error> {
+        let cf = if let Some(cf) = self.db.cf_handle(&lod_column_family_name(lod)) {
+            cf
+        } else {
+            // Nothing has ever been written to this LOD.
+            return Ok(());
+        };
+
+        let (start, end) = range.into_inner();
+        let read_kvs: Vec<_> = self
+            .db
+            .iterator_cf(cf, IteratorMode::From(start.as_ref(), Direction::Forward))
+            .take_while(|(key, _)| &**key <= end.as_ref())
+            .collect();
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+}
+
+impl<N, Compr> AsyncChunkReader<N, Compr::Data> for RocksChunkDb<N, Compr>
+where
+    N: Send + Sync,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy + Sync,
+    Compr::Data: Send,
+{
+    type Error = rocksdb::Error;
+
+    fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+    ) -> BoxFuture<'_, Result<Option<Compr::Data>, rocksdb::Error>> {
+        Box::pin(RocksChunkDb::read_chunk(self, key))
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use crate::{Array3x2, ChunkKey3, FastArrayCompressionNx2, FromBytesCompression, Lz4};
+
+    use super::*;
+
+    use tempdir::TempDir;
+
+    #[test]
+    fn db_round_trip() -> Result<(), rocksdb::Error> {
+        let chunk_mins = [
+            PointN([16, 0, 0]),
+            PointN([0, 16, 0]),
+            PointN([0, 0, 16]),
+            PointN([0, -16, 0]),
+        ];
+        let chunk_shape = Point3i::fill(16);
+        let write_chunks: Vec<_> = chunk_mins
+            .iter()
+            .map(|&min| {
+                (
+                    ChunkKey3::new(0, min),
+                    Array3x2::fill(Extent3i::from_min_and_shape(min, chunk_shape), (1u16, b'a')),
+                )
+            })
+            .collect();
+
+        let tmp = TempDir::new("bb-test").unwrap();
+
+        // NOTE: This compression is not portable because it is naive to endianness.
+        let compression = FastArrayCompressionNx2::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = RocksChunkDb::open(&tmp, 2, compression)?;
+
+        futures::executor::block_on(
+            chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))),
+        )?;
+
+        // This octant should contain the chunks in the positive octant, but not the other chunk.
+        let octant = Octant::new_unchecked(Point3i::ZERO, 32);
+
+        let mut read_chunks = Vec::new();
+        futures::executor::block_on(
+            chunk_db.read_chunks_in_orthant(0, octant, |k, v| read_chunks.push((k, v))),
+        )?;
+
+        assert_eq!(
+            read_chunks,
+            vec![
+                write_chunks[0].clone(),
+                write_chunks[1].clone(),
+                write_chunks[2].clone()
+            ]
+        );
+
+        // The other LOD is empty.
+        let mut num_lod1_chunks = 0;
+        futures::executor::block_on(chunk_db.read_all_chunks(1, |_, _| num_lod1_chunks += 1))?;
+        assert_eq!(num_lod1_chunks, 0);
+
+        Ok(())
+    }
+}
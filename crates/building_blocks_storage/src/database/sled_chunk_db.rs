@@ -0,0 +1,208 @@
+use super::{BackendChunkDb, ChunkDbBackend};
+
+use futures::future::BoxFuture;
+use sled::{IVec, Tree};
+
+pub use sled;
+
+/// A persistent, transactional, crash-consistent database of chunks.
+///
+/// This is essentially a B+ tree of compressed chunks (backed by the `sled` crate). The keys are Morton codes for the
+/// corresponding chunk coordinates. This ensures that all of the chunks in an orthant are stored in a contiguous key space.
+///
+/// The DB values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub type ChunkDb<N, Compr> = BackendChunkDb<N, Compr, Tree>;
+
+/// A 2D `ChunkDb`.
+pub type ChunkDb2<Compr> = ChunkDb<[i32; 2], Compr>;
+/// A 3D `ChunkDb`.
+pub type ChunkDb3<Compr> = ChunkDb<[i32; 3], Compr>;
+
+impl<N, Compr> ChunkDb<N, Compr> {
+    pub fn tree(&self) -> &Tree {
+        self.backend()
+    }
+}
+
+impl ChunkDbBackend for Tree {
+    type Error = sled::Error;
+    type Bytes = IVec;
+
+    fn write_batch(&self, kvs: Vec<(Vec<u8>, Vec<u8>)>) -> BoxFuture<'_, sled::Result<()>> {
+        Box::pin(async move {
+            let mut batch = sled::Batch::default();
+            for (key, value) in kvs.into_iter() {
+                // PERF: IVec will copy the bytes instead of moving, because it needs to also allocate room for an internal
+                // header
+                batch.insert(key, value);
+            }
+
+            self.apply_batch(batch)
+        })
+    }
+
+    fn read(&self, key: Vec<u8>) -> BoxFuture<'_, sled::Result<Option<IVec>>> {
+        Box::pin(async move { self.get(key) })
+    }
+
+    fn read_range(
+        &self,
+        start: Vec<u8>,
+        end: Vec<u8>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'_, sled::Result<Vec<(IVec, IVec)>>> {
+        Box::pin(async move {
+            let kvs = self.range(start..=end);
+            match limit {
+                Some(limit) => kvs.take(limit).collect(),
+                None => kvs.collect(),
+            }
+        })
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use crate::{
+        Array3x2, ChunkIndexer, ChunkKey3, DatabaseKey, FastArrayCompressionNx2,
+        FromBytesCompression, Lz4,
+    };
+
+    use super::*;
+
+    use building_blocks_core::prelude::*;
+
+    use tempdir::TempDir;
+
+    #[test]
+    fn db_round_trip() -> sled::Result<()> {
+        let chunk_mins = [
+            PointN([16, 0, 0]),
+            PointN([0, 16, 0]),
+            PointN([0, 0, 16]),
+            PointN([0, -16, 0]),
+        ];
+        let chunk_shape = Point3i::fill(16);
+        let write_chunks: Vec<_> = chunk_mins
+            .iter()
+            .map(|&min| {
+                (
+                    ChunkKey3::new(0, min),
+                    Array3x2::fill(Extent3i::from_min_and_shape(min, chunk_shape), (1u16, b'a')),
+                )
+            })
+            .collect();
+
+        let tmp = TempDir::new("bb-test").unwrap();
+        let db = sled::Config::default()
+            .path(&tmp)
+            .use_compression(false)
+            .mode(sled::Mode::LowSpace)
+            .open()?;
+        let tree = db.open_tree("chunks")?;
+
+        // NOTE: This compression is not portable because it is naive to endianness.
+        let compression = FastArrayCompressionNx2::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = ChunkDb::new(tree, compression);
+
+        futures::executor::block_on(
+            chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))),
+        )?;
+
+        // This octant should contain the chunks in the positive octant, but not the other chunk.
+        let octant = Octant::new_unchecked(Point3i::ZERO, 32);
+
+        let mut read_chunks = Vec::new();
+        futures::executor::block_on(
+            chunk_db.read_chunks_in_orthant(0, octant, |k, v| read_chunks.push((k, v))),
+        )?;
+
+        let read_keys: Vec<_> = read_chunks.iter().map(|(k, _)| k.clone()).collect();
+        let expected_read_keys: Vec<_> =
+            [PointN([16, 0, 0]), PointN([0, 16, 0]), PointN([0, 0, 16])]
+                .iter()
+                .cloned()
+                .map(|min| ChunkKey3::new(0, min))
+                .collect();
+        assert_eq!(read_keys, expected_read_keys);
+
+        assert_eq!(
+            read_chunks,
+            vec![
+                write_chunks[0].clone(),
+                write_chunks[1].clone(),
+                write_chunks[2].clone()
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_chunks_in_extent_skips_keys_outside() -> sled::Result<()> {
+        let chunk_shape = Point3i::fill(4);
+        let indexer = ChunkIndexer::new(chunk_shape);
+        let all_chunks = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let write_chunks: Vec<_> = indexer
+            .chunk_mins_for_extent(&all_chunks)
+            .map(|min| {
+                (
+                    ChunkKey3::new(0, min),
+                    Array3x2::fill(
+                        indexer.extent_for_chunk_with_min(min),
+                        (min.x() as u16, b'a'),
+                    ),
+                )
+            })
+            .collect();
+
+        let tmp = TempDir::new("bb-test").unwrap();
+        let db = sled::Config::default()
+            .path(&tmp)
+            .use_compression(false)
+            .mode(sled::Mode::LowSpace)
+            .open()?;
+        let tree = db.open_tree("chunks")?;
+        let compression = FastArrayCompressionNx2::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = ChunkDb::new(tree, compression);
+
+        futures::executor::block_on(
+            chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))),
+        )?;
+
+        // This view straddles the origin, so its Morton range contains many chunks outside of it.
+        let view = Extent3i::from_min_and_shape(PointN([-5, -2, 3]), PointN([9, 6, 7]));
+        let chunk_min_extent = Extent3i::from_min_and_max(
+            indexer.min_of_chunk_containing_point(view.minimum),
+            view.max(),
+        );
+
+        let mut read_chunks = Vec::new();
+        futures::executor::block_on(chunk_db.read_chunks_in_extent(
+            0,
+            chunk_min_extent,
+            |k, v| read_chunks.push((k, v)),
+        ))?;
+
+        let mut expected_chunks: Vec<_> = write_chunks
+            .into_iter()
+            .filter(|(k, _)| {
+                !view
+                    .intersection(&indexer.extent_for_chunk_with_min(k.minimum))
+                    .is_empty()
+            })
+            .collect();
+        expected_chunks.sort_by_key(|(k, _)| ChunkKey3::into_ord_key(*k));
+        assert_eq!(read_chunks, expected_chunks);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+use super::{decompress_in_batches, DatabaseKey};
+
+use crate::{AsyncChunkReader, ChunkKey, Compression};
+
+use building_blocks_core::{orthants_covering_extent, prelude::*};
+
+use core::ops::RangeInclusive;
+use futures::future::{join_all, BoxFuture};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::borrow::Borrow;
+use std::path::Path;
+use std::sync::Mutex;
+
+pub use rusqlite;
+
+/// A persistent database of chunks in a single SQLite file, backed by the `rusqlite` crate.
+///
+/// This has the same API as the `sled`-backed `ChunkDb`. All chunks are stored in the `chunks` table, with a primary key of
+/// `(lod, morton)`, where `morton` is the big-endian Morton code of the chunk minimum as a `BLOB`. SQLite compares `BLOB`s
+/// with `memcmp`, so the chunks in an orthant are still contiguous in the primary key index. The table is created by `new` if
+/// it doesn't exist yet.
+///
+/// The DB values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub struct SqliteChunkDb<N, Compr> {
+    // `Connection` is not `Sync`, so the async methods could not be shared across threads without the lock.
+    connection: Mutex<Connection>,
+    compression: Compr,
+    marker: std::marker::PhantomData<N>,
+}
+
+/// A 2D `SqliteChunkDb`.
+pub type SqliteChunkDb2<Compr> = SqliteChunkDb<[i32; 2], Compr>;
+/// A 3D `SqliteChunkDb`.
+pub type SqliteChunkDb3<Compr> = SqliteChunkDb<[i32; 3], Compr>;
+
+impl<N, Compr> SqliteChunkDb<N, Compr> {
+    /// Creates the `chunks` table in `connection` if it doesn't exist.
+    pub fn new(connection: Connection, compression: Compr) -> rusqlite::Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS chunks (
+                lod INTEGER NOT NULL,
+                morton BLOB NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (lod, morton)
+            ) WITHOUT ROWID;",
+        )?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            compression,
+            marker: Default::default(),
+        })
+    }
+
+    /// Opens (or creates) the database file at `path`.
+    pub fn open(path: impl AsRef<Path>, compression: Compr) -> rusqlite::Result<Self> {
+        Self::new(Connection::open(path)?, compression)
+    }
+
+    /// Opens a new database that only exists in memory.
+    pub fn open_in_memory(compression: Compr) -> rusqlite::Result<Self> {
+        Self::new(Connection::open_in_memory()?, compression)
+    }
+
+    pub fn connection(&self) -> &Mutex<Connection> {
+        &self.connection
+    }
+}
+
+impl<N, Compr> SqliteChunkDb<N, Compr>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+{
+    /// Insert a set of chunks. This will compress all of the chunks asynchronously then insert them into the database in a
+    /// single transaction. Pre-existing chunks will be overwritten.
+    pub async fn write_chunks<Data>(
+        &self,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Data)>,
+    ) -> rusqlite::Result<()>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let mut compressed_chunks = Vec::new();
+        for (key, compressed_chunk) in join_all(chunks.map(|(key, chunk)| async move {
+            (
+                ChunkKey::<N>::into_ord_key(key),
+                self.compression.compress(chunk.borrow()),
+            )
+        }))
+        .await
+        .into_iter()
+        {
+            compressed_chunks.push((key, compressed_chunk));
+        }
+        // Sort them by the Ord key.
+        compressed_chunks.sort_by_key(|(k, _)| *k);
+
+        // Then atomically write them all to the database.
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO chunks (lod, morton, data) VALUES (?1, ?2, ?3)",
+            )?;
+            for (db_key, chunk) in compressed_chunks.into_iter() {
+                let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(db_key);
+                let (lod, morton) = split_key_bytes(key_bytes.as_ref());
+                insert.execute(params![lod, morton, chunk.take_bytes()])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Reads and decompresses the chunk at `key`, if it exists.
+    pub async fn read_chunk(&self, key: ChunkKey<N>) -> rusqlite::Result<Option<Compr::Data>> {
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+        let (lod, morton) = split_key_bytes(key_bytes.as_ref());
+
+        let compressed_chunk: Option<Vec<u8>> = self
+            .connection
+            .lock()
+            .unwrap()
+            .prepare_cached("SELECT data FROM chunks WHERE lod = ?1 AND morton = ?2")?
+            .query_row(params![lod, morton], |row| row.get(0))
+            .optional()?;
+
+        Ok(compressed_chunk.map(|bytes| Compr::decompress_from_reader(bytes.as_slice()).unwrap()))
+    }
+
+    /// Scans the given orthant for chunks, decompresses them, then passes them to `chunk_rx`. Because chunk keys are stored in
+    /// Morton order, the chunks in any orthant are guaranteed to be contiguous.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> rusqlite::Result<()> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    /// This is like `read_chunks_in_orthant`, but it works for the given `extent`. See `ChunkDb::read_orthants_covering_extent`
+    /// for details about the covering.
+    pub async fn read_orthants_covering_extent(
+        &self,
+        lod: u8,
+        orthant_exponent: i32,
+        extent: ExtentN<N>,
+        mut chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> rusqlite::Result<()> {
+        for orthant in orthants_covering_extent(extent, orthant_exponent) {
+            self.read_chunks_in_orthant(lod, orthant, &mut chunk_rx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads all chunks in the given `lod`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> rusqlite::Result<()> {
+        let range = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod))
+            ..=ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(range, chunk_rx).await
+    }
+
+    async fn read_range(
+        &self,
+        range: RangeInclusive<<ChunkKey<N> as DatabaseKey<N>>::KeyBytes>,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> rusqlite::Result<()> {
+        let (start, end) = range.into_inner();
+        let (lod, start_morton) = split_key_bytes(start.as_ref());
+        let (_, end_morton) = split_key_bytes(end.as_ref());
+
+        let read_kvs = {
+            let connection = self.connection.lock().unwrap();
+            let mut select = connection.prepare_cached(
+                "SELECT morton, data FROM chunks WHERE lod = ?1 AND morton BETWEEN ?2 AND ?3 ORDER BY morton",
+            )?;
+            let rows = select.query_map(params![lod, start_morton, end_morton], |row| {
+                let mut key_bytes = vec![lod];
+                key_bytes.extend_from_slice(&row.get::<_, Vec<u8>>(0)?);
+                let chunk: Vec<u8> = row.get(1)?;
+
+                Ok((key_bytes, chunk))
+            })?;
+
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+}
+
+/// Splits the LOD byte from the Morton code bytes.
+fn split_key_bytes(key_bytes: &[u8]) -> (u8, &[u8]) {
+    (key_bytes[0], &key_bytes[1..])
+}
+
+impl<N, Compr> AsyncChunkReader<N, Compr::Data> for SqliteChunkDb<N, Compr>
+where
+    N: Send + Sync,
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy + Sync,
+    Compr::Data: Send,
+{
+    type Error = rusqlite::Error;
+
+    fn read_chunk(&self, key: ChunkKey<N>) -> BoxFuture<'_, rusqlite::Result<Option<Compr::Data>>> {
+        Box::pin(SqliteChunkDb::read_chunk(self, key))
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use crate::{Array3x2, ChunkKey3, FastArrayCompressionNx2, FromBytesCompression, Lz4};
+
+    use super::*;
+
+    use tempdir::TempDir;
+
+    #[test]
+    fn db_round_trip() -> rusqlite::Result<()> {
+        let chunk_mins = [
+            PointN([16, 0, 0]),
+            PointN([0, 16, 0]),
+            PointN([0, 0, 16]),
+            PointN([0, -16, 0]),
+        ];
+        let chunk_shape = Point3i::fill(16);
+        let write_chunks: Vec<_> = chunk_mins
+            .iter()
+            .map(|&min| {
+                (
+                    ChunkKey3::new(0, min),
+                    Array3x2::fill(Extent3i::from_min_and_shape(min, chunk_shape), (1u16, b'a')),
+                )
+            })
+            .collect();
+
+        let tmp = TempDir::new("bb-test").unwrap();
+
+        // NOTE: This compression is not portable because it is naive to endianness.
+        let compression = FastArrayCompressionNx2::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = SqliteChunkDb::open(tmp.path().join("chunks.sqlite"), compression)?;
+
+        futures::executor::block_on(
+            chunk_db.write_chunks(write_chunks.iter().map(|(k, v)| (*k, v))),
+        )?;
+
+        // This octant should contain the chunks in the positive octant, but not the other chunk.
+        let octant = Octant::new_unchecked(Point3i::ZERO, 32);
+
+        let mut read_chunks = Vec::new();
+        futures::executor::block_on(
+            chunk_db.read_chunks_in_orthant(0, octant, |k, v| read_chunks.push((k, v))),
+        )?;
+
+        assert_eq!(
+            read_chunks,
+            vec![
+                write_chunks[0].clone(),
+                write_chunks[1].clone(),
+                write_chunks[2].clone()
+            ]
+        );
+
+        assert_eq!(
+            futures::executor::block_on(chunk_db.read_chunk(write_chunks[3].0))?,
+            Some(write_chunks[3].1.clone())
+        );
+        assert_eq!(
+            futures::executor::block_on(chunk_db.read_chunk(ChunkKey3::new(1, Point3i::ZERO)))?,
+            None
+        );
+
+        // The other LOD is empty.
+        let mut num_lod1_chunks = 0;
+        futures::executor::block_on(chunk_db.read_all_chunks(1, |_, _| num_lod1_chunks += 1))?;
+        assert_eq!(num_lod1_chunks, 0);
+
+        Ok(())
+    }
+}
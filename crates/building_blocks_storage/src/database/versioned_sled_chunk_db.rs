@@ -0,0 +1,332 @@
+use super::{decompress_in_batches, DatabaseKey};
+
+use crate::{ChunkKey, Compression};
+
+use building_blocks_core::prelude::*;
+
+use futures::future::join_all;
+use sled::{IVec, Tree};
+use std::borrow::Borrow;
+
+/// A `ChunkDb` that keeps every version of each chunk, so the map can be read as of any version that hasn't been garbage
+/// collected. This enables undo/redo and server rollback for editable worlds.
+///
+/// Each DB key is the Morton-ordered chunk key (as in `ChunkDb`) followed by the big-endian `u64` version, so all versions of a
+/// chunk are adjacent, and all chunks in an orthant are still contiguous. A chunk that is removed in some version is stored as
+/// an empty "tombstone" value.
+///
+/// The DB values are only portable if the `compression` used respects endianness of the current machine. Use
+/// `BincodeCompression` if you absolutely need portability across machines with different endianness.
+pub struct VersionedChunkDb<N, Compr> {
+    tree: Tree,
+    compression: Compr,
+    marker: std::marker::PhantomData<N>,
+}
+
+/// A 2D `VersionedChunkDb`.
+pub type VersionedChunkDb2<Compr> = VersionedChunkDb<[i32; 2], Compr>;
+/// A 3D `VersionedChunkDb`.
+pub type VersionedChunkDb3<Compr> = VersionedChunkDb<[i32; 3], Compr>;
+
+impl<N, Compr> VersionedChunkDb<N, Compr> {
+    pub fn new(tree: Tree, compression: Compr) -> Self {
+        Self {
+            tree,
+            compression,
+            marker: Default::default(),
+        }
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+}
+
+impl<N, Compr> VersionedChunkDb<N, Compr>
+where
+    PointN<N>: IntegerPoint<N>,
+    ChunkKey<N>: DatabaseKey<N>,
+    Compr: Compression + Copy,
+{
+    /// Atomically writes a batch of chunks tagged with `version`. A `None` chunk is removed as of `version`.
+    ///
+    /// Versions don't need to be written in order, but writing a version older than the last garbage collection can give
+    /// surprising results.
+    pub async fn write_chunks<Data>(
+        &self,
+        version: u64,
+        chunks: impl Iterator<Item = (ChunkKey<N>, Option<Data>)>,
+    ) -> sled::Result<()>
+    where
+        Data: Borrow<Compr::Data>,
+    {
+        // First compress all of the chunks in parallel.
+        let compressed_chunks = join_all(chunks.map(|(key, chunk)| async move {
+            (
+                ChunkKey::<N>::into_ord_key(key),
+                chunk.map(|chunk| self.compression.compress(chunk.borrow())),
+            )
+        }))
+        .await;
+
+        // Then atomically write them all to the database.
+        let mut batch = sled::Batch::default();
+        for (ord_key, chunk) in compressed_chunks.into_iter() {
+            let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ord_key);
+            let value = chunk.map(|c| c.take_bytes()).unwrap_or_default();
+            batch.insert(versioned_key(key_bytes.as_ref(), version), value);
+        }
+        self.tree.apply_batch(batch)?;
+
+        Ok(())
+    }
+
+    /// Reads and decompresses the chunk at `key` as of `version`, if it exists.
+    pub async fn read_chunk(
+        &self,
+        key: ChunkKey<N>,
+        version: u64,
+    ) -> sled::Result<Option<Compr::Data>> {
+        let key_bytes = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::into_ord_key(key));
+        let latest = self
+            .tree
+            .range(
+                versioned_key(key_bytes.as_ref(), 0)..=versioned_key(key_bytes.as_ref(), version),
+            )
+            .next_back()
+            .transpose()?;
+
+        Ok(latest
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(_, value)| Compr::decompress_from_reader(value.as_ref()).unwrap()))
+    }
+
+    /// Scans the given orthant for chunks as of `version`, decompresses them, then passes them to `chunk_rx` in Morton order.
+    ///
+    /// The `orthant` is expected in voxel units, not chunk units.
+    pub async fn read_chunks_in_orthant(
+        &self,
+        lod: u8,
+        orthant: Orthant<N>,
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> sled::Result<()> {
+        let range = ChunkKey::<N>::orthant_range(lod, orthant);
+        let (start, end) = range.into_inner();
+
+        self.read_range(start.as_ref(), end.as_ref(), version, chunk_rx)
+            .await
+    }
+
+    /// Reads all chunks in the given `lod` as of `version`, passing them to `chunk_rx`.
+    pub async fn read_all_chunks(
+        &self,
+        lod: u8,
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> sled::Result<()> {
+        let start = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(lod));
+        let end = ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::max_key(lod));
+
+        self.read_range(start.as_ref(), end.as_ref(), version, chunk_rx)
+            .await
+    }
+
+    async fn read_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        version: u64,
+        chunk_rx: impl FnMut(ChunkKey<N>, Compr::Data),
+    ) -> sled::Result<()> {
+        let key_len = start.len();
+
+        // Keep the latest value no newer than `version` for each chunk.
+        let mut read_kvs: Vec<(Vec<u8>, IVec)> = Vec::new();
+        let mut latest: Option<(Vec<u8>, IVec)> = None;
+        for kv in self
+            .tree
+            .range(versioned_key(start, 0)..=versioned_key(end, u64::MAX))
+        {
+            let (db_key, value) = kv?;
+            let (key_bytes, value_version) = split_versioned_key(&db_key, key_len);
+            if latest
+                .as_ref()
+                .map_or(false, |(latest_key, _)| latest_key.as_slice() != key_bytes)
+            {
+                read_kvs.extend(latest.take());
+            }
+            if value_version <= version {
+                latest = Some((key_bytes.to_vec(), value));
+            }
+        }
+        read_kvs.extend(latest);
+        read_kvs.retain(|(_, value)| !value.is_empty());
+
+        decompress_in_batches::<N, Compr, _, _, _>(read_kvs, chunk_rx).await;
+
+        Ok(())
+    }
+
+    /// Atomically removes all versions newer than `version`, rolling the database back to that version.
+    pub fn discard_versions_after(&self, version: u64) -> sled::Result<()> {
+        let key_len = self.key_len();
+
+        let mut batch = sled::Batch::default();
+        for db_key in self.tree.iter().keys() {
+            let db_key = db_key?;
+            if split_versioned_key(&db_key, key_len).1 > version {
+                batch.remove(db_key);
+            }
+        }
+
+        self.tree.apply_batch(batch)
+    }
+
+    /// Atomically removes all chunk versions that aren't needed to read `oldest_kept` or any newer version.
+    pub fn collect_garbage(&self, oldest_kept: u64) -> sled::Result<()> {
+        let key_len = self.key_len();
+
+        let mut batch = sled::Batch::default();
+        // The latest version of the current chunk that is no newer than `oldest_kept`.
+        let mut kept: Option<(IVec, bool)> = None;
+        for kv in self.tree.iter() {
+            let (db_key, value) = kv?;
+            let (key_bytes, value_version) = split_versioned_key(&db_key, key_len);
+
+            let same_chunk = kept.as_ref().map_or(false, |(kept_key, _)| {
+                split_versioned_key(kept_key, key_len).0 == key_bytes
+            });
+            if !same_chunk {
+                // A chunk removed at or before the oldest kept version doesn't need history.
+                if let Some((kept_key, true)) = kept.take() {
+                    batch.remove(kept_key);
+                }
+            }
+
+            if value_version <= oldest_kept {
+                // Superseded by this older-than-kept version.
+                if let Some((kept_key, _)) = kept.take() {
+                    batch.remove(kept_key);
+                }
+                kept = Some((db_key, value.is_empty()));
+            }
+        }
+        if let Some((kept_key, true)) = kept {
+            batch.remove(kept_key);
+        }
+
+        self.tree.apply_batch(batch)
+    }
+
+    fn key_len(&self) -> usize {
+        ChunkKey::<N>::ord_key_to_be_bytes(ChunkKey::<N>::min_key(0))
+            .as_ref()
+            .len()
+    }
+}
+
+fn versioned_key(key_bytes: &[u8], version: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(key_bytes.len() + 8);
+    bytes.extend_from_slice(key_bytes);
+    bytes.extend_from_slice(&version.to_be_bytes());
+
+    bytes
+}
+
+fn split_versioned_key(bytes: &[u8], key_len: usize) -> (&[u8], u64) {
+    let (key_bytes, version_bytes) = bytes.split_at(key_len);
+    let mut version = [0; 8];
+    version.copy_from_slice(version_bytes);
+
+    (key_bytes, u64::from_be_bytes(version))
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(all(test, feature = "lz4"))]
+mod test {
+    use crate::{Array3x1, ChunkKey3, FastArrayCompressionNx1, FromBytesCompression, Lz4};
+
+    use super::*;
+
+    use futures::executor::block_on;
+    use tempdir::TempDir;
+
+    #[test]
+    fn read_as_of_version() -> sled::Result<()> {
+        let chunk_shape = Point3i::fill(16);
+        let key1 = ChunkKey3::new(0, Point3i::ZERO);
+        let key2 = ChunkKey3::new(0, PointN([16, 0, 0]));
+        let chunk =
+            |min, value| Array3x1::fill(Extent3i::from_min_and_shape(min, chunk_shape), value);
+
+        let tmp = TempDir::new("bb-test").unwrap();
+        let db = sled::Config::default()
+            .path(&tmp)
+            .use_compression(false)
+            .mode(sled::Mode::LowSpace)
+            .open()?;
+        let tree = db.open_tree("chunks")?;
+
+        // NOTE: This compression is not portable because it is naive to endianness.
+        let compression = FastArrayCompressionNx1::from_bytes_compression(Lz4 { level: 10 });
+        let chunk_db = VersionedChunkDb::new(tree, compression);
+
+        let write = |version, chunks: Vec<(ChunkKey3, Option<Array3x1<u16>>)>| {
+            block_on(chunk_db.write_chunks(version, chunks.into_iter()))
+        };
+        write(
+            1,
+            vec![
+                (key1, Some(chunk(key1.minimum, 1))),
+                (key2, Some(chunk(key2.minimum, 1))),
+            ],
+        )?;
+        write(2, vec![(key1, Some(chunk(key1.minimum, 2)))])?;
+        write(3, vec![(key2, None)])?;
+
+        let read_all = |version| -> sled::Result<Vec<(ChunkKey3, Array3x1<u16>)>> {
+            let mut chunks = Vec::new();
+            block_on(chunk_db.read_all_chunks(0, version, |k, v| chunks.push((k, v))))?;
+
+            Ok(chunks)
+        };
+        assert!(read_all(0)?.is_empty());
+        assert_eq!(
+            read_all(1)?,
+            vec![
+                (key1, chunk(key1.minimum, 1)),
+                (key2, chunk(key2.minimum, 1))
+            ]
+        );
+        assert_eq!(
+            read_all(2)?,
+            vec![
+                (key1, chunk(key1.minimum, 2)),
+                (key2, chunk(key2.minimum, 1))
+            ]
+        );
+        assert_eq!(read_all(3)?, vec![(key1, chunk(key1.minimum, 2))]);
+        assert_eq!(block_on(chunk_db.read_chunk(key2, 3))?, None);
+
+        chunk_db.collect_garbage(2)?;
+        assert_eq!(read_all(2)?.len(), 2);
+        assert_eq!(read_all(3)?, vec![(key1, chunk(key1.minimum, 2))]);
+        // Only key1@2, key2@1, and the key2@3 tombstone are left.
+        assert_eq!(chunk_db.tree().len(), 3);
+
+        chunk_db.discard_versions_after(2)?;
+        assert_eq!(
+            block_on(chunk_db.read_chunk(key2, 3))?,
+            Some(chunk(key2.minimum, 1))
+        );
+
+        Ok(())
+    }
+}
@@ -5,22 +5,74 @@ pub use dot_vox;
 use building_blocks_core::prelude::*;
 
 use dot_vox::*;
+use std::convert::TryFrom;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+/// The color of a voxel in a VOX model. `Color(i)` refers to the RGBA value at `DotVoxData::palette[i]`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum VoxColor {
     Color(u8),
     Empty,
 }
 
+impl VoxColor {
+    /// Looks up the RGBA value of this color in `palette`, which is usually `DotVoxData::palette`. Returns `None` for
+    /// `VoxColor::Empty` or if the index is out of bounds of `palette`.
+    pub fn rgba(self, palette: &[u32]) -> Option<[u8; 4]> {
+        match self {
+            VoxColor::Color(i) => palette.get(i as usize).map(|c| c.to_le_bytes()),
+            VoxColor::Empty => None,
+        }
+    }
+}
+
 impl IsEmpty for VoxColor {
     fn is_empty(&self) -> bool {
         matches!(self, VoxColor::Empty)
     }
 }
 
-pub fn encode_vox<Map>(map: &Map, map_extent: Extent3i) -> DotVoxData
+/// Converts from the raw color index used in the VOX file format, where 0 is empty and `i > 0` is palette entry `i - 1`.
+impl From<u8> for VoxColor {
+    fn from(raw_index: u8) -> Self {
+        match raw_index {
+            0 => VoxColor::Empty,
+            i => VoxColor::Color(i - 1),
+        }
+    }
+}
+
+/// Converts to the raw color index used in the VOX file format, where 0 is empty and `i > 0` is palette entry `i - 1`.
+///
+/// Returns `Err(color)` for `VoxColor::Color(255)`, since it has no raw index.
+impl TryFrom<VoxColor> for u8 {
+    type Error = VoxColor;
+
+    fn try_from(color: VoxColor) -> Result<Self, Self::Error> {
+        match color {
+            VoxColor::Color(i) => i.checked_add(1).ok_or(color),
+            VoxColor::Empty => Ok(0),
+        }
+    }
+}
+
+/// Encodes the points of `map` in `map_extent` as a single VOX model. The file will use the default MagicaVoxel palette.
+pub fn encode_vox<T, Map>(map: &Map, map_extent: Extent3i) -> DotVoxData
+where
+    T: Into<VoxColor>,
+    Map: Get<Point3i, Item = T>,
+{
+    encode_vox_with_palette(map, map_extent, Vec::new())
+}
+
+/// Like `encode_vox`, but stores the given `palette` of RGBA colors (in the same format as `DotVoxData::palette`).
+pub fn encode_vox_with_palette<T, Map>(
+    map: &Map,
+    map_extent: Extent3i,
+    palette: Vec<u32>,
+) -> DotVoxData
 where
-    Map: Get<Point3i, Item = VoxColor>,
+    T: Into<VoxColor>,
+    Map: Get<Point3i, Item = T>,
 {
     let shape = map_extent.shape;
     let vox_extent = map_extent - map_extent.minimum;
@@ -36,7 +88,7 @@ where
 
     let mut voxels = Vec::new();
     for (vox_p, map_p) in vox_extent.iter_points().zip(map_extent.iter_points()) {
-        if let VoxColor::Color(i) = map.get(map_p) {
+        if let VoxColor::Color(i) = map.get(map_p).into() {
             voxels.push(dot_vox::Voxel {
                 x: vox_p.x() as u8,
                 y: vox_p.y() as u8,
@@ -51,7 +103,82 @@ where
     DotVoxData {
         version: 150,
         models: vec![model],
-        palette: Vec::new(),
+        palette,
         materials: Vec::new(),
     }
 }
+
+/// The extent of the VOX model at `model_index`, with its minimum at the origin.
+pub fn vox_model_extent(vox_data: &DotVoxData, model_index: usize) -> Extent3i {
+    let Size { x, y, z } = vox_data.models[model_index].size;
+
+    Extent3i::from_min_and_shape(Point3i::ZERO, PointN([x as i32, y as i32, z as i32]))
+}
+
+/// Iterates over the non-empty voxels of the VOX model at `model_index`.
+pub fn vox_model_voxels(
+    vox_data: &DotVoxData,
+    model_index: usize,
+) -> impl Iterator<Item = (Point3i, VoxColor)> + '_ {
+    vox_data.models[model_index]
+        .voxels
+        .iter()
+        .map(|Voxel { x, y, z, i }| {
+            (
+                PointN([*x as i32, *y as i32, *z as i32]),
+                VoxColor::Color(*i),
+            )
+        })
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_raw_color_indices() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-4), Point3i::fill(8));
+        let array = Array3x1::fill_with(extent, |p| if p.x() < 0 { 0u8 } else { 3 });
+
+        let vox_data = encode_vox(&array, extent);
+        assert_eq!(vox_model_extent(&vox_data, 0).shape, extent.shape);
+        assert_eq!(vox_data.models[0].voxels.len(), extent.num_points() / 2);
+
+        let decoded = Array3x1::<u8>::decode_vox_raw_indices(&vox_data, 0).unwrap();
+        assert_eq!(decoded.extent().shape, extent.shape);
+        decoded.for_each(decoded.extent(), |p: Point3i, value| {
+            assert_eq!(value, array.get(p + extent.minimum));
+        });
+
+        for raw in 0..=u8::MAX {
+            assert_eq!(u8::try_from(VoxColor::from(raw)), Ok(raw));
+        }
+        assert_eq!(
+            u8::try_from(VoxColor::Color(u8::MAX)),
+            Err(VoxColor::Color(u8::MAX))
+        );
+
+        // The last palette entry doesn't fit in a raw index.
+        let last_color = Array3x1::fill(extent, VoxColor::Color(u8::MAX));
+        let vox_data = encode_vox(&last_color, extent);
+        assert_eq!(
+            Array3x1::<u8>::decode_vox_raw_indices(&vox_data, 0),
+            Err(VoxColor::Color(u8::MAX))
+        );
+        let decoded = Array3x1::decode_vox(&vox_data, 0);
+        decoded.for_each(decoded.extent(), |_: Point3i, color| {
+            assert_eq!(color, VoxColor::Color(u8::MAX));
+        });
+
+        let palette = vec![0x04030201; 256];
+        assert_eq!(VoxColor::from(3u8).rgba(&palette), Some([1, 2, 3, 4]));
+        assert_eq!(VoxColor::from(0u8).rgba(&palette), None);
+    }
+}
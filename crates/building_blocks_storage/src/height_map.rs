@@ -0,0 +1,588 @@
+//! A 2D height field for terrain pipelines that work on height maps before voxelizing.
+//!
+//! A `HeightMap` wraps an `Array2x1` of `f32` or `i32` heights. Like `triangulate_height_map` in `building_blocks_mesh`, the 2D
+//! (x, y) coordinates of the height map are the (x, z) coordinates of the world, and +Y is up. Each height is a sample of the
+//! terrain surface at an integer (x, z) point, and the surface between the samples is bilinearly interpolated.
+//!
+//! A `HeightMap<f32>` can be eroded with `ThermalErosion`, which slides material down slopes that are steeper than the talus
+//! angle, and `HydraulicErosion`, which carves channels by simulating rain, water flow, and sediment transport. Both erosion
+//! passes conserve the total amount of material.
+//!
+//! `HeightMap::to_voxel_columns` voxelizes the height map into a 3D array, and `HeightMap::from_voxel_columns` finds the top of
+//! each column of voxels. A voxel at height `y` is below the surface iff `y < height`, so integer height maps round trip
+//! exactly.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, HeightMap, ThermalErosion};
+//!
+//! // A single tall spike in the middle of flat ground.
+//! let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(16));
+//! let mut terrain = HeightMap::new(Array2x1::fill_with(extent, |p: Point2i| {
+//!     if p == PointN([8, 8]) { 10.0 } else { 2.0 }
+//! }));
+//! terrain.thermal_erosion(&ThermalErosion::default(), 20);
+//! assert!(terrain.height(PointN([8, 8])) < 10.0);
+//!
+//! // Cast a ray straight down onto the terrain.
+//! let hit = terrain.raycast(PointN([3.5, 20.0, 4.5]), PointN([0.0, -1.0, 0.0]), 100.0).unwrap();
+//! assert!((hit.position.y() - 2.0).abs() < 0.001);
+//!
+//! // Voxelize it into columns of solid (1) and air (0) voxels.
+//! let voxels = terrain.to_voxel_columns(0, 15, |_p, dist| if dist < 0.0 { 1u8 } else { 0 });
+//! assert_eq!(voxels.get(PointN([3, 1, 4])), 1);
+//! assert_eq!(voxels.get(PointN([3, 2, 4])), 0);
+//! ```
+
+use crate::{Array2x1, Array3x1, Get, GetMut};
+
+use building_blocks_core::prelude::*;
+
+/// A value that can be stored in a `HeightMap`.
+pub trait HeightValue: Copy {
+    fn to_f32(self) -> f32;
+}
+
+impl HeightValue for f32 {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+impl HeightValue for i32 {
+    #[inline]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+/// A height field, stored as an `Array2x1` of heights. See the module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightMap<H> {
+    heights: Array2x1<H>,
+}
+
+/// Where a ray hit a `HeightMap`, from `HeightMap::raycast`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeightMapRayHit {
+    /// The ray parameter of the hit, i.e. `position = start + t * direction`.
+    pub t: f32,
+    pub position: Point3f,
+}
+
+impl<H> HeightMap<H> {
+    pub fn new(heights: Array2x1<H>) -> Self {
+        Self { heights }
+    }
+
+    pub fn heights(&self) -> &Array2x1<H> {
+        &self.heights
+    }
+
+    pub fn heights_mut(&mut self) -> &mut Array2x1<H> {
+        &mut self.heights
+    }
+
+    pub fn into_heights(self) -> Array2x1<H> {
+        self.heights
+    }
+
+    /// The (x, z) extent of the height samples.
+    pub fn extent(&self) -> &Extent2i {
+        self.heights.extent()
+    }
+}
+
+impl<H> HeightMap<H>
+where
+    H: HeightValue,
+{
+    /// The height at sample `p`, which must be in the extent.
+    #[inline]
+    pub fn height(&self, p: Point2i) -> f32 {
+        self.heights.get(p).to_f32()
+    }
+
+    /// The bilinearly interpolated height at `(x, z)`, or `None` if it's outside of the extent.
+    pub fn interpolated_height(&self, xz: Point2f) -> Option<f32> {
+        let extent = self.extent();
+        let min = Point2f::from(extent.minimum);
+        let max = Point2f::from(extent.max());
+        if xz.x() < min.x() || xz.y() < min.y() || xz.x() > max.x() || xz.y() > max.y() {
+            return None;
+        }
+
+        let p0 = xz.floor_int();
+        let p1 = (p0 + Point2i::fill(1)).meet(extent.max());
+        let frac = xz - Point2f::from(p0);
+        let lerp = |a: f32, b: f32, s: f32| a + s * (b - a);
+        let bottom = lerp(
+            self.height(p0),
+            self.height(PointN([p1.x(), p0.y()])),
+            frac.x(),
+        );
+        let top = lerp(
+            self.height(PointN([p0.x(), p1.y()])),
+            self.height(p1),
+            frac.x(),
+        );
+
+        Some(lerp(bottom, top, frac.y()))
+    }
+
+    /// The unit surface normal at sample `p`, estimated with central differencing. Samples on the boundary of the extent use
+    /// a one-sided difference.
+    pub fn normal(&self, p: Point2i) -> Point3f {
+        let extent = *self.extent();
+        let clamp = |q: Point2i| q.join(extent.minimum).meet(extent.max());
+        let slope = |axis: Point2i| {
+            let lo = clamp(p - axis);
+            let hi = clamp(p + axis);
+            let run = (hi - lo).dot(axis);
+            if run == 0 {
+                0.0
+            } else {
+                (self.height(hi) - self.height(lo)) / run as f32
+            }
+        };
+        let dh_dx = slope(PointN([1, 0]));
+        let dh_dz = slope(PointN([0, 1]));
+
+        // The gradient of f(x, y, z) = y - h(x, z).
+        let gradient = PointN([-dh_dx, 1.0, -dh_dz]);
+
+        gradient / gradient.norm()
+    }
+
+    /// Finds the first point where the ray from `start` along `direction` goes below the surface, within `max_t` of the start.
+    /// If `start` is already below the surface, it's the hit. Parts of the ray outside of the extent never hit, and where the
+    /// ray enters the extent below the surface, the hit is only accurate to a step.
+    ///
+    /// The ray is marched in steps of half a sample, then the crossing is refined by bisection, so features that are thinner
+    /// than a step can be missed.
+    pub fn raycast(
+        &self,
+        start: Point3f,
+        direction: Point3f,
+        max_t: f32,
+    ) -> Option<HeightMapRayHit> {
+        let height_above_surface = |t: f32| {
+            let p = start + direction * t;
+
+            self.interpolated_height(PointN([p.x(), p.z()]))
+                .map(|h| p.y() - h)
+        };
+
+        let horizontal_speed = PointN([direction.x(), direction.z()]).norm();
+        let step = if horizontal_speed > 0.0 {
+            0.5 / horizontal_speed
+        } else {
+            max_t
+        };
+
+        let mut t0 = 0.0;
+        let mut above0 = height_above_surface(t0);
+        if let Some(a) = above0 {
+            if a < 0.0 {
+                return Some(HeightMapRayHit {
+                    t: 0.0,
+                    position: start,
+                });
+            }
+        }
+        while t0 < max_t {
+            let t1 = (t0 + step).min(max_t);
+            let above1 = height_above_surface(t1);
+            if let Some(b) = above1 {
+                if b < 0.0 {
+                    // Refine the crossing, unless the ray just entered the extent below the surface.
+                    let (mut lo, mut hi) = (t0, t1);
+                    if above0.is_some() {
+                        for _ in 0..24 {
+                            let mid = 0.5 * (lo + hi);
+                            match height_above_surface(mid) {
+                                Some(a) if a >= 0.0 => lo = mid,
+                                _ => hi = mid,
+                            }
+                        }
+                    }
+
+                    return Some(HeightMapRayHit {
+                        t: hi,
+                        position: start + direction * hi,
+                    });
+                }
+            }
+            t0 = t1;
+            above0 = above1;
+        }
+
+        None
+    }
+
+    /// Voxelizes the columns between `min_y` and `max_y` (inclusive) above the extent. `f` is called with each voxel and its
+    /// vertical signed distance to the surface, measured from the voxel's center, which is negative below the surface.
+    pub fn to_voxel_columns<T>(
+        &self,
+        min_y: i32,
+        max_y: i32,
+        f: impl Fn(Point3i, f32) -> T,
+    ) -> Array3x1<T> {
+        let extent = self.extent();
+        let voxel_extent = Extent3i::from_min_and_max(
+            PointN([extent.minimum.x(), min_y, extent.minimum.y()]),
+            PointN([extent.max().x(), max_y, extent.max().y()]),
+        );
+
+        Array3x1::fill_with(voxel_extent, |p: Point3i| {
+            let height = self.height(PointN([p.x(), p.z()]));
+
+            f(p, p.y() as f32 + 0.5 - height)
+        })
+    }
+}
+
+impl HeightMap<i32> {
+    /// The height of each column of `extent` is one above its top-most voxel for which `is_solid` is true, or the bottom of
+    /// `extent` if no voxel is solid.
+    pub fn from_voxel_columns<A, T>(
+        voxels: &A,
+        extent: &Extent3i,
+        is_solid: impl Fn(T) -> bool,
+    ) -> Self
+    where
+        A: Get<Point3i, Item = T>,
+    {
+        let min_y = extent.minimum.y();
+        let max_y = extent.max().y();
+        let columns = Extent2i::from_min_and_shape(
+            PointN([extent.minimum.x(), extent.minimum.z()]),
+            PointN([extent.shape.x(), extent.shape.z()]),
+        );
+
+        Self::new(Array2x1::fill_with(columns, |p: Point2i| {
+            (min_y..=max_y)
+                .rev()
+                .find(|&y| is_solid(voxels.get(PointN([p.x(), y, p.y()]))))
+                .map(|y| y + 1)
+                .unwrap_or(min_y)
+        }))
+    }
+}
+
+/// Parameters for `HeightMap::thermal_erosion`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThermalErosion {
+    /// The largest height difference between neighboring samples that is stable.
+    pub talus: f32,
+    /// The fraction of the excess height difference that slides down on each iteration. Should be at most `0.5`.
+    pub rate: f32,
+}
+
+impl Default for ThermalErosion {
+    fn default() -> Self {
+        Self {
+            talus: 1.0,
+            rate: 0.5,
+        }
+    }
+}
+
+/// Parameters for `HeightMap::hydraulic_erosion`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HydraulicErosion {
+    /// The amount of water that rains on each sample on every iteration.
+    pub rain: f32,
+    /// The amount of material dissolved by each unit of water on every iteration.
+    pub solubility: f32,
+    /// The fraction of water that evaporates on every iteration.
+    pub evaporation: f32,
+    /// The amount of sediment that each unit of water can carry.
+    pub sediment_capacity: f32,
+}
+
+impl Default for HydraulicErosion {
+    fn default() -> Self {
+        Self {
+            rain: 0.01,
+            solubility: 0.01,
+            evaporation: 0.5,
+            sediment_capacity: 0.01,
+        }
+    }
+}
+
+impl HeightMap<f32> {
+    /// Runs `iterations` of thermal erosion. Material moves from each sample to its lower (Von Neumann) neighbors where the
+    /// height difference exceeds `params.talus`.
+    pub fn thermal_erosion(&mut self, params: &ThermalErosion, iterations: usize) {
+        let shape = self.extent().shape;
+        let heights = self.heights.channels_mut().store_mut();
+        let mut deltas = vec![0.0; heights.len()];
+
+        for _ in 0..iterations {
+            for delta in deltas.iter_mut() {
+                *delta = 0.0;
+            }
+            for i in 0..heights.len() {
+                let h = heights[i];
+                let mut total_excess = 0.0;
+                let mut max_excess = 0.0f32;
+                for_each_neighbor(shape, i, |j| {
+                    let excess = h - heights[j] - params.talus;
+                    if excess > 0.0 {
+                        total_excess += excess;
+                        max_excess = max_excess.max(excess);
+                    }
+                });
+                if total_excess <= 0.0 {
+                    continue;
+                }
+
+                let moved = params.rate * max_excess;
+                for_each_neighbor(shape, i, |j| {
+                    let excess = h - heights[j] - params.talus;
+                    if excess > 0.0 {
+                        let share = moved * excess / total_excess;
+                        deltas[j] += share;
+                        deltas[i] -= share;
+                    }
+                });
+            }
+            for (h, delta) in heights.iter_mut().zip(deltas.iter()) {
+                *h += delta;
+            }
+        }
+    }
+
+    /// Runs `iterations` of hydraulic erosion. On each iteration, rain falls on every sample and dissolves some material,
+    /// then the water flows to lower neighbors and carries its sediment with it. Water evaporates, and sediment beyond the
+    /// capacity of the remaining water is deposited. All remaining sediment is deposited at the end.
+    pub fn hydraulic_erosion(&mut self, params: &HydraulicErosion, iterations: usize) {
+        let shape = self.extent().shape;
+        let heights = self.heights.channels_mut().store_mut();
+        let mut cells = vec![WaterCell::default(); heights.len()];
+
+        for _ in 0..iterations {
+            for (h, cell) in heights.iter_mut().zip(cells.iter_mut()) {
+                cell.water += params.rain;
+                let dissolved = params.solubility * cell.water;
+                *h -= dissolved;
+                cell.sediment += dissolved;
+                cell.water_delta = 0.0;
+                cell.sediment_delta = 0.0;
+            }
+
+            for i in 0..cells.len() {
+                let WaterCell {
+                    water, sediment, ..
+                } = cells[i];
+                if water <= 0.0 {
+                    continue;
+                }
+
+                // Level the water surface with the lower neighbors, as far as the water allows.
+                let surface = heights[i] + water;
+                let drop_to = |j: usize| surface - (heights[j] + cells[j].water);
+                let mut total_drop = 0.0;
+                let mut num_lower = 0;
+                for_each_neighbor(shape, i, |j| {
+                    let drop = drop_to(j);
+                    if drop > 0.0 {
+                        total_drop += drop;
+                        num_lower += 1;
+                    }
+                });
+                if num_lower == 0 {
+                    continue;
+                }
+
+                let mean_surface = surface - total_drop / (num_lower + 1) as f32;
+                let moved_water = water.min(surface - mean_surface);
+                let moved_sediment = sediment * moved_water / water;
+                let mut shares = [(0, 0.0); 4];
+                let mut num_shares = 0;
+                for_each_neighbor(shape, i, |j| {
+                    let drop = drop_to(j);
+                    if drop > 0.0 {
+                        shares[num_shares] = (j, drop / total_drop);
+                        num_shares += 1;
+                    }
+                });
+                for &(j, share) in shares[..num_shares].iter() {
+                    cells[j].water_delta += moved_water * share;
+                    cells[j].sediment_delta += moved_sediment * share;
+                }
+                cells[i].water_delta -= moved_water;
+                cells[i].sediment_delta -= moved_sediment;
+            }
+
+            for (h, cell) in heights.iter_mut().zip(cells.iter_mut()) {
+                cell.water = (cell.water + cell.water_delta) * (1.0 - params.evaporation);
+                cell.sediment += cell.sediment_delta;
+                let capacity = params.sediment_capacity * cell.water;
+                if cell.sediment > capacity {
+                    *h += cell.sediment - capacity;
+                    cell.sediment = capacity;
+                }
+            }
+        }
+
+        for (h, cell) in heights.iter_mut().zip(cells.iter()) {
+            *h += cell.sediment;
+        }
+    }
+}
+
+/// The state of one sample during `HeightMap::hydraulic_erosion`.
+#[derive(Clone, Copy, Default)]
+struct WaterCell {
+    water: f32,
+    sediment: f32,
+    water_delta: f32,
+    sediment_delta: f32,
+}
+
+/// Calls `f` with the index of each Von Neumann neighbor of the sample at `index` in an array of `shape`.
+#[inline]
+fn for_each_neighbor(shape: Point2i, index: usize, mut f: impl FnMut(usize)) {
+    let width = shape.x() as usize;
+    let x = index % width;
+    let y = index / width;
+    if x > 0 {
+        f(index - 1);
+    }
+    if x + 1 < width {
+        f(index + 1);
+    }
+    if y > 0 {
+        f(index - width);
+    }
+    if y + 1 < shape.y() as usize {
+        f(index + width);
+    }
+}
+
+impl<H> Get<Point2i> for HeightMap<H>
+where
+    H: Copy,
+{
+    type Item = H;
+
+    #[inline]
+    fn get(&self, p: Point2i) -> H {
+        self.heights.get(p)
+    }
+}
+
+impl<'a, H> GetMut<'a, Point2i> for HeightMap<H>
+where
+    H: 'a,
+{
+    type Item = &'a mut H;
+
+    #[inline]
+    fn get_mut(&'a mut self, p: Point2i) -> Self::Item {
+        self.heights.get_mut(p)
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_height(map: &HeightMap<f32>) -> f32 {
+        map.heights().channels().store().iter().sum()
+    }
+
+    fn max_slope(map: &HeightMap<f32>) -> f32 {
+        let mut max = 0.0f32;
+        for p in map.extent().iter_points() {
+            for q in [p + PointN([1, 0]), p + PointN([0, 1])].iter() {
+                if map.extent().contains(*q) {
+                    max = max.max((map.height(p) - map.height(*q)).abs());
+                }
+            }
+        }
+
+        max
+    }
+
+    fn cone() -> HeightMap<f32> {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(16));
+
+        HeightMap::new(Array2x1::fill_with(extent, |p: Point2i| {
+            (10.0 - 3.0 * Point2f::from(p - Point2i::fill(8)).norm()).max(0.0)
+        }))
+    }
+
+    #[test]
+    fn thermal_erosion_conserves_material_and_flattens_slopes() {
+        let mut map = cone();
+        let before = total_height(&map);
+        assert!(max_slope(&map) > 2.0);
+
+        let params = ThermalErosion {
+            talus: 1.0,
+            rate: 0.5,
+        };
+        map.thermal_erosion(&params, 200);
+
+        assert!((total_height(&map) - before).abs() < 0.01 * before);
+        assert!(max_slope(&map) < 1.1);
+    }
+
+    #[test]
+    fn hydraulic_erosion_conserves_material_and_lowers_the_peak() {
+        let mut map = cone();
+        let before = total_height(&map);
+        let peak = map.height(Point2i::fill(8));
+
+        map.hydraulic_erosion(&HydraulicErosion::default(), 100);
+
+        assert!((total_height(&map) - before).abs() < 0.01 * before);
+        assert!(map.height(Point2i::fill(8)) < peak);
+    }
+
+    #[test]
+    fn raycast_hits_interpolated_surface() {
+        // A ramp h = x / 2.
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, PointN([8, 2]));
+        let map = HeightMap::new(Array2x1::fill_with(extent, |p: Point2i| p.x() as f32 / 2.0));
+
+        // A horizontal ray at y = 2 hits the ramp at x = 4.
+        let hit = map
+            .raycast(PointN([0.0, 2.0, 0.5]), PointN([1.0, 0.0, 0.0]), 10.0)
+            .unwrap();
+        assert!((hit.position.x() - 4.0).abs() < 0.001);
+        assert!((hit.t - 4.0).abs() < 0.001);
+
+        // A ray that goes over the top of the ramp and leaves the extent misses.
+        assert_eq!(
+            map.raycast(PointN([0.0, 5.0, 0.5]), PointN([1.0, 0.0, 0.0]), 10.0),
+            None
+        );
+
+        let n = map.normal(PointN([4, 0]));
+        assert!((n - PointN([-0.5, 1.0, 0.0]) / 1.25f32.sqrt()).norm() < 0.001);
+    }
+
+    #[test]
+    fn voxel_columns_round_trip() {
+        let extent = Extent2i::from_min_and_shape(PointN([-2, 3]), PointN([5, 4]));
+        let map = HeightMap::new(Array2x1::fill_with(extent, |p: Point2i| p.x() + p.y()));
+
+        let voxels = map.to_voxel_columns(-4, 12, |_p, dist| dist < 0.0);
+        let round_trip = HeightMap::from_voxel_columns(&voxels, voxels.extent(), |solid| solid);
+
+        assert_eq!(round_trip, map);
+    }
+}
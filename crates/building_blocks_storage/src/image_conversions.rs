@@ -4,7 +4,8 @@ pub use image;
 
 use building_blocks_core::prelude::*;
 
-use image::{ImageBuffer, Pixel};
+use image::{ImageBuffer, ImageFormat, ImageResult, Luma, Pixel};
+use std::path::Path;
 
 pub fn encode_image<T, P, Map>(
     map: &Map,
@@ -29,3 +30,72 @@ where
 
     img
 }
+
+/// Saves the values of `map` in `map_extent` as a 16-bit grayscale PNG at `path`, e.g. to visually inspect a height map or an
+/// `Array3Slice` of signed distances. The values are normalized so that the least value is black and the greatest is white.
+pub fn save_as_png<T, Map>(
+    map: &Map,
+    map_extent: Extent2i,
+    path: impl AsRef<Path>,
+) -> ImageResult<()>
+where
+    T: Into<f32>,
+    Map: Get<Point2i, Item = T>,
+{
+    let values: Vec<f32> = map_extent
+        .iter_points()
+        .map(|p| map.get(p).into())
+        .collect();
+    let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+    let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let scale = if max > min {
+        f32::from(u16::MAX) / (max - min)
+    } else {
+        0.0
+    };
+
+    let img_extent = map_extent - map_extent.minimum;
+    let pixels = values
+        .into_iter()
+        .map(|v| ((v - min) * scale).round() as u16)
+        .collect();
+    let img: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_raw(
+        img_extent.shape.x() as u32,
+        img_extent.shape.y() as u32,
+        pixels,
+    )
+    .unwrap();
+
+    img.save_with_format(path, ImageFormat::Png)
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_height_map_as_png() {
+        let extent = Extent2i::from_min_and_shape(PointN([-4, -4]), PointN([8, 6]));
+        let height_map = Array2x1::fill_with(extent, |p| (p.x() + p.y()) as f32 * 0.5);
+
+        let path = std::env::temp_dir().join("building_blocks_save_height_map_as_png.png");
+        save_as_png(&height_map, extent, &path).unwrap();
+
+        let img = match image::open(&path).unwrap() {
+            image::DynamicImage::ImageLuma16(img) => img,
+            _ => panic!("Expected a 16-bit grayscale image"),
+        };
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(img.dimensions(), (8, 6));
+        assert_eq!(img.get_pixel(0, 0).0, [0]);
+        assert_eq!(img.get_pixel(7, 5).0, [u16::MAX]);
+    }
+}
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(
     clippy::type_complexity,
     clippy::needless_collect,
@@ -7,49 +8,115 @@
 //! Various types of storage and indexing for voxels in 2 or 3 dimensions.
 //!
 //! If you need to store signed distance values in your voxels, consider using the `Sd8` and `Sd16` fixed-precision types which
-//! implement the `SignedDistance` trait required for smooth meshing.
+//! implement the `SignedDistance` trait required for smooth meshing. They are aliases of `FixedSd`, which can be given another
+//! integer type or scale when you need more range than `[-1.0, 1.0]`.
 //!
 //! The core storage types are:
 //!   - [Array](crate::Array): N-dimensional, single resolution, bounded, dense array
 //!   - [ChunkMap](crate::ChunkMap): N-dimensional, multiple resolution, unbounded, sparse array
-//!     - Backed by generic chunk storage, with `HashMap` or `CompressibleChunkStorage` implementations
+//!     - Backed by generic chunk storage, with `HashMap`, `ConcurrentChunkStorage`, or `CompressibleChunkStorage`
+//!       implementations
 //!
 //! Then there are "meta" lattice maps that provide some extra utility:
 //!   - [TransformMap](crate::TransformMap): a wrapper of any kind of lattice map that performs an arbitrary transformation
+//!     - [TransformMapMut](crate::TransformMapMut): also writes through the transformation with an inverse transformation
 //!   - [Func](crate::Func): some lattice map traits are implemented for closures (like SDFs)
 //!
+//! The [sdf](crate::sdf) module provides SDF primitives and CSG operations that can be sampled into arrays with `Func` or
+//! applied to existing arrays of signed distances.
+//!
 //! For hierarchical indexing and level of detail:
 //!   - [OctreeSet](crate::OctreeSet): bounded bitset of points
 //!   - [ChunkedOctreeSet](crate::ChunkedOctreeSet): unbounded bitset of points
 //!   - [OctreeChunkIndex](crate::OctreeChunkIndex): just a `ChunkedOctreeSet` that tracks chunks and provides clipmap functionality
+//!
+//! # `no_std`
+//!
+//! The "std" feature is enabled by default. Without it, this crate is `no_std` and only needs `alloc`, but only the `Array`
+//! types, their channels, the access traits, `Func`, and the signed distance types are available. Compression, chunk maps,
+//! octrees, and databases all require "std", as does `#[derive(Channels)]`.
+
+/// Enters a `tracing` span until the end of the enclosing scope. This expands to nothing without the "tracing" feature.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        let _span = tracing::trace_span!($($args)*).entered();
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($args:tt)*) => {};
+}
+
+/// Instruments a future with a `tracing` span. This is just the future without the "tracing" feature.
+#[cfg(feature = "tracing")]
+macro_rules! trace_future {
+    ($future:expr, $($args:tt)*) => {
+        tracing::Instrument::instrument($future, tracing::trace_span!($($args)*))
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_future {
+    ($future:expr, $($args:tt)*) => {
+        $future
+    };
+}
+
+extern crate alloc;
+// Lets the code generated by `building_blocks_derive` refer to this crate from the inside, e.g. in tests.
+extern crate self as building_blocks_storage;
 
 #[macro_use]
 pub mod access_traits;
 pub mod array;
+#[cfg(feature = "std")]
 pub mod caching;
+#[cfg(feature = "std")]
 pub mod chunk;
+#[cfg(feature = "std")]
 pub mod compression;
 pub mod func;
+#[cfg(feature = "std")]
+pub mod height_map;
 pub mod multi_ptr;
+#[cfg(feature = "std")]
 pub mod octree;
+#[cfg(feature = "std")]
+pub mod sdf;
 pub mod signed_distance;
+#[cfg(feature = "std")]
+pub mod simulation;
+#[cfg(feature = "std")]
+pub mod texture;
+#[cfg(feature = "std")]
 pub mod transform_map;
 
 pub use access_traits::*;
 pub use array::*;
+#[cfg(feature = "std")]
 pub use caching::*;
+#[cfg(feature = "std")]
 pub use chunk::*;
+#[cfg(feature = "std")]
 pub use compression::*;
 pub use func::*;
+#[cfg(feature = "std")]
+pub use height_map::*;
 pub use multi_ptr::*;
+#[cfg(feature = "std")]
 pub use octree::*;
 pub use signed_distance::*;
+#[cfg(feature = "std")]
+pub use simulation::*;
+#[cfg(feature = "std")]
+pub use texture::*;
+#[cfg(feature = "std")]
 pub use transform_map::*;
 
-#[cfg(feature = "sled")]
+#[cfg(feature = "std")]
 pub mod database;
 
-#[cfg(feature = "sled")]
+#[cfg(feature = "std")]
 pub use database::*;
 
 /// Used in many generic algorithms to check if a voxel is considered empty.
@@ -64,34 +131,54 @@ impl IsEmpty for bool {
 }
 
 // Hash types to use for small keys like `PointN`.
+#[cfg(feature = "std")]
 pub type SmallKeyHashMap<K, V> = ahash::AHashMap<K, V>;
+#[cfg(feature = "std")]
 pub type SmallKeyHashSet<K> = ahash::AHashSet<K>;
+#[cfg(feature = "std")]
 pub type SmallKeyBuildHasher = ahash::RandomState;
 
 pub mod prelude {
     pub use super::{
-        copy_extent, Chunk, ChunkKey, ChunkKey2, ChunkKey3, ChunkMapBuilder, ChunkReadStorage,
-        ChunkUnits, ChunkWriteStorage, Compressed, CompressibleChunkMap,
+        copy_extent, ChunkUnits, FillExtent, FixedSd, Func, IndexedArray, IsEmpty, Local, Sd16,
+        Sd8, SignedDistance, Stride,
+    };
+    #[cfg(feature = "std")]
+    pub use super::{
+        BackendChunkDb, Chunk, ChunkDbBackend, ChunkKey, ChunkKey2, ChunkKey3, ChunkMapBuilder,
+        ChunkReadStorage, ChunkWriteStorage, Compressed, CompressibleChunkMap,
         CompressibleChunkMapReader, CompressibleChunkStorage, CompressibleChunkStorageReader,
-        Compression, FastCompressibleChunkStorage, FillExtent, FromBytesCompression, Func,
-        IndexedArray, IsEmpty, IterChunkKeys, Local, LocalChunkCache2, LocalChunkCache3,
-        OctreeChunkIndex, OctreeNode, OctreeSet, PointDownsampler, Sd16, Sd8, SdfMeanDownsampler,
-        SignedDistance, SmallKeyHashMap, Stride, TransformMap, VisitStatus,
+        Compression, FastCompressibleChunkStorage, FromBytesCompression, IterChunkKeys,
+        LocalChunkCache2, LocalChunkCache3, MemoryBackend, MemoryChunkDb, MemoryChunkDb2,
+        MemoryChunkDb3, NoCompression, OctreeChunkIndex, OctreeNode, OctreeSet, PointDownsampler,
+        Rle, SdfMeanDownsampler, SmallKeyHashMap, TransformMap, TransformMapMut, VisitStatus,
     };
 
     pub use super::access_traits::*;
     pub use super::array::multichannel_aliases::*;
+    #[cfg(feature = "std")]
     pub use super::chunk::map::multichannel_aliases::*;
+    #[cfg(feature = "std")]
     pub use super::chunk::storage::compressible::multichannel_aliases::*;
+    #[cfg(feature = "std")]
     pub use super::chunk::storage::compressible_reader::multichannel_aliases::*;
+    #[cfg(feature = "std")]
     pub use super::chunk::storage::hash_map::multichannel_aliases::*;
 
     #[cfg(feature = "lz4")]
     pub use super::Lz4;
     #[cfg(feature = "snap")]
     pub use super::Snappy;
+    #[cfg(feature = "zstd")]
+    pub use super::Zstd;
     #[cfg(feature = "sled")]
     pub use super::{ChunkDb, ChunkDb2, ChunkDb3};
+    #[cfg(all(feature = "sled", feature = "tokio"))]
+    pub use super::{ChunkDbWriter, ChunkDbWriterConfig};
+    #[cfg(feature = "rocksdb")]
+    pub use super::{RocksChunkDb, RocksChunkDb2, RocksChunkDb3};
+    #[cfg(feature = "rusqlite")]
+    pub use super::{SqliteChunkDb, SqliteChunkDb2, SqliteChunkDb3};
 }
 
 #[cfg(feature = "dot_vox")]
@@ -17,7 +17,8 @@ impl<'a, T> MultiRef<'a> for &'a T {
     }
 }
 
-/// Used for variadic conversion from `(*mut A, *mut B, ...)` to `(&'a mut A, &'a mut B, ...)`.
+/// Used for variadic conversion from `(*mut A, *mut B, ...)` to `(&'a mut A, &'a mut B, ...)`, or more generally from
+/// pointers to the mutable references (or proxies) they point to.
 #[doc(hidden)]
 pub trait IntoMultiMut<'a> {
     type MultiMut;
@@ -93,17 +94,17 @@ macro_rules! impl_tuple {
             }
         }
 
-        impl<'a, $($t),+> IntoMultiMut<'a> for ($(*mut $t,)+)
+        impl<'a, $($t),+> IntoMultiMut<'a> for ($($t,)+)
         where
-            $($t: 'a,)+
+            $($t: IntoMultiMut<'a>,)+
         {
-            type MultiMut = ($(&'a mut $t,)+);
+            type MultiMut = ($($t::MultiMut,)+);
 
             #[inline]
             fn into_multi_mut(self) -> Self::MultiMut {
                 let ($($var1,)+) = self;
 
-                unsafe { ($(&mut *$var1,)+) }
+                ($($var1.into_multi_mut(),)+)
             }
         }
 
@@ -145,3 +146,5 @@ impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C }
 impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D }
 impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E }
 impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F }
+impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F, g1, g2: G }
+impl_tuple! { a1, a2: A, b1, b2: B, c1, c2: C, d1, d2: D, e1, e2: E, f1, f2: F, g1, g2: G, h1, h2: H }
@@ -9,9 +9,15 @@
 pub mod chunk_index;
 pub mod chunked_set;
 pub mod clipmap;
+pub mod query_volume;
+pub mod ray;
 pub mod set;
+pub mod svo;
 
 pub use chunk_index::*;
 pub use chunked_set::*;
 pub use clipmap::*;
+pub use query_volume::*;
+pub use ray::OctreeRayHit;
 pub use set::*;
+pub use svo::*;
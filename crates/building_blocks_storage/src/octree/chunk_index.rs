@@ -50,11 +50,29 @@
 //! // works on any voxels types that implement `SignedDistance`. Or you can define your own downsampler!
 //! map.downsample_chunks_with_index(&index, &PointDownsampler, &extent);
 //! ```
+//!
+//! ## Culling Chunks
+//!
+//! Instead of testing every chunk key against a view frustum, the index can prune whole octants of chunks at once.
+//!
+//! ```
+//! # use building_blocks_core::prelude::*;
+//! # use building_blocks_storage::{prelude::*, Sphere};
+//! # let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+//! # let mut map = builder.build_with_hash_map_storage();
+//! # map.fill_extent(0, &Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(100)), 1);
+//! # let index = OctreeChunkIndex::index_chunk_map(9, 5, &map);
+//! let sphere = Sphere { center: Point3f::fill(8.0), radius: 4.0 };
+//! let mut visible_keys = Vec::new();
+//! index.visit_chunks_intersecting_sphere(&sphere, |key, _containment| visible_keys.push(key));
+//! assert_eq!(visible_keys, vec![ChunkKey::new(0, Point3i::ZERO)]);
+//! ```
 
 use crate::{
-    active_clipmap_lod_chunks, Array3x1, ChunkKey3, ChunkMap3, ChunkUnits, ChunkedOctreeSet,
-    ClipMapConfig3, ClipMapUpdate3, GetMut, IterChunkKeys, LodChunkUpdate3, OctreeSet,
-    SmallKeyHashMap,
+    active_clipmap_lod_chunks, Array3x1, ChunkKey, ChunkKey3, ChunkMap3, ChunkUnits,
+    ChunkedOctreeSet, ClipMapConfig3, ClipMapDiff3, ClipMapTracker3, ClipMapUpdate3, Containment,
+    Frustum, GetMut, IterChunkKeys, LodChunkUpdate3, Obb, OctreeNode, OctreeSet, QueryVolume,
+    SmallKeyHashMap, Sphere, VisitStatus,
 };
 
 use building_blocks_core::prelude::*;
@@ -210,6 +228,13 @@ impl OctreeChunkIndex {
         self.superchunk_octrees.pop_chunk(superchunk_min)
     }
 
+    /// Moves every chunk in the index by `lod0_delta`, in voxel coordinates, to follow `ChunkMap::translate_keys`. The octrees
+    /// are moved without being rebuilt, so `lod0_delta` must be a multiple of the superchunk shape.
+    pub fn translate(&mut self, lod0_delta: Point3i) {
+        self.superchunk_octrees
+            .translate_keys_and_octrees(lod0_delta, lod0_delta >> self.chunk_exponent as i32);
+    }
+
     pub fn clipmap_config(&self, clip_box_radius: u16) -> ClipMapConfig3 {
         assert!(self.superchunk_octrees.indexer.chunk_shape().is_cube());
         assert!(self.chunk_shape().is_cube());
@@ -252,6 +277,37 @@ impl OctreeChunkIndex {
             });
     }
 
+    /// Updates `tracker` with all octree nodes overlapping `extent` and returns how the active chunks have changed since the
+    /// last update. See `ClipMapTracker3`.
+    pub fn update_clipmap_tracker(
+        &self,
+        tracker: &mut ClipMapTracker3,
+        extent: &Extent3i,
+        lod0_centers: &[ChunkUnits<Point3i>],
+    ) -> ClipMapDiff3 {
+        tracker.update(lod0_centers, |visitor| {
+            self.superchunk_octrees
+                .visit_octrees(extent, &mut |octree| visitor(octree))
+        })
+    }
+
+    /// Returns `true` iff the LOD0 chunk at `key` is in the index. Useful for skipping empty chunks quickly, e.g. while
+    /// casting rays.
+    pub fn contains_chunk(&self, key: ChunkKey3) -> bool {
+        assert_eq!(key.lod, 0);
+
+        let superchunk_min = self
+            .superchunk_octrees
+            .indexer
+            .min_of_chunk_containing_point(key.minimum);
+
+        self.superchunk_octrees
+            .get_chunk(superchunk_min)
+            .map_or(false, |octree| {
+                octree.contains_point(key.minimum >> self.chunk_exponent)
+            })
+    }
+
     pub fn add_extent(&mut self, extent: ChunkUnits<Extent3i>) {
         self.superchunk_octrees.add_extent(&extent.0)
     }
@@ -260,6 +316,63 @@ impl OctreeChunkIndex {
         self.superchunk_octrees.subtract_extent(&extent.0)
     }
 
+    /// Visit the key of every LOD0 chunk in the index that intersects `volume`, which is given in voxel coordinates. Whole
+    /// octants of chunks are culled at once, so this is much faster than testing every chunk key individually. The visitor is
+    /// also given the `Containment` of each chunk, which is never `Outside`.
+    pub fn visit_chunks_intersecting(
+        &self,
+        volume: &impl QueryVolume,
+        mut visitor: impl FnMut(ChunkKey3, Containment),
+    ) {
+        let chunk_shape = self.chunk_shape();
+        // The octrees are in chunk units.
+        let volume_in_chunks = volume.scaled(1.0 / chunk_shape.x() as f32);
+
+        self.superchunk_octrees
+            .visit_all_octrees(&mut |octree: &OctreeSet| {
+                octree.visit_octants_intersecting(
+                    &volume_in_chunks,
+                    &mut |node: &OctreeNode, containment| {
+                        if node.octant().is_single_voxel() {
+                            visitor(
+                                ChunkKey::new(0, node.octant().minimum() * chunk_shape),
+                                containment,
+                            );
+                        }
+
+                        VisitStatus::Continue
+                    },
+                );
+            });
+    }
+
+    /// Same as `visit_chunks_intersecting` for a view frustum, i.e. frustum culling.
+    pub fn visit_chunks_intersecting_frustum(
+        &self,
+        frustum: &Frustum,
+        visitor: impl FnMut(ChunkKey3, Containment),
+    ) {
+        self.visit_chunks_intersecting(frustum, visitor)
+    }
+
+    /// Same as `visit_chunks_intersecting` for a sphere.
+    pub fn visit_chunks_intersecting_sphere(
+        &self,
+        sphere: &Sphere,
+        visitor: impl FnMut(ChunkKey3, Containment),
+    ) {
+        self.visit_chunks_intersecting(sphere, visitor)
+    }
+
+    /// Same as `visit_chunks_intersecting` for an oriented bounding box.
+    pub fn visit_chunks_intersecting_obb(
+        &self,
+        obb: &Obb,
+        visitor: impl FnMut(ChunkKey3, Containment),
+    ) {
+        self.visit_chunks_intersecting(obb, visitor)
+    }
+
     /// Visit all superchunk octrees that overlap `extent`.
     pub fn visit_octrees(&self, extent: &Extent3i, visitor: &mut impl FnMut(&OctreeSet)) {
         self.superchunk_octrees.visit_octrees(extent, visitor)
@@ -30,10 +30,31 @@ impl ChunkedOctreeSet {
         self.octrees.insert(chunk_min, octree)
     }
 
+    pub fn get_chunk(&self, chunk_min: Point3i) -> Option<&OctreeSet> {
+        self.octrees.get(&chunk_min)
+    }
+
     pub fn pop_chunk(&mut self, chunk_min: Point3i) -> Option<OctreeSet> {
         self.octrees.remove(&chunk_min)
     }
 
+    /// Moves every octree by `delta`, which must be a multiple of the chunk shape.
+    pub fn translate(&mut self, delta: Point3i) {
+        self.translate_keys_and_octrees(delta, delta)
+    }
+
+    // Moves the octree keys by `key_delta` and the octrees by `octree_delta`, for when they are in different units.
+    pub(crate) fn translate_keys_and_octrees(&mut self, key_delta: Point3i, octree_delta: Point3i) {
+        assert_eq!(key_delta % self.indexer.chunk_shape(), Point3i::ZERO);
+
+        let octrees = std::mem::take(&mut self.octrees);
+        self.octrees.reserve(octrees.len());
+        for (chunk_min, mut octree) in octrees.into_iter() {
+            octree.translate(octree_delta);
+            self.octrees.insert(chunk_min + key_delta, octree);
+        }
+    }
+
     pub fn visit_octrees(&self, extent: &Extent3i, visitor: &mut impl FnMut(&OctreeSet)) {
         for chunk_min in self.indexer.chunk_mins_for_extent(extent) {
             if let Some(octree) = self.octrees.get(&chunk_min) {
@@ -42,6 +63,13 @@ impl ChunkedOctreeSet {
         }
     }
 
+    /// Visit every octree in the set, in no particular order.
+    pub fn visit_all_octrees(&self, visitor: &mut impl FnMut(&OctreeSet)) {
+        for octree in self.octrees.values() {
+            (visitor)(octree);
+        }
+    }
+
     pub fn add_extent(&mut self, extent: &Extent3i) {
         let Self {
             octrees, indexer, ..
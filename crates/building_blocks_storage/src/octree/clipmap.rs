@@ -1,4 +1,7 @@
-use crate::{ChunkKey, ChunkKey3, ChunkUnits, OctreeNode, OctreeSet, VisitStatus};
+use crate::{
+    ChunkKey, ChunkKey3, ChunkUnits, OctreeNode, OctreeSet, SmallKeyHashMap, SmallKeyHashSet,
+    VisitStatus,
+};
 
 use building_blocks_core::prelude::*;
 
@@ -32,6 +35,32 @@ impl ClipMapConfig3 {
 
         self.chunk_shape.x().trailing_zeros() as i32
     }
+
+    /// The weight for blending a vertex at `lod` toward its morph target in the parent LOD, for geomorphing. `lod0_center` and
+    /// `p` are in LOD0 voxel coordinates.
+    ///
+    /// Chunks at `lod` extend to about `clip_box_radius` chunks from the center (in chunks of `lod`). The factor grows from 0 to
+    /// 1 over the outer quarter of that range, reaching 1 a chunk before the parent LOD takes over, so chunks are fully morphed
+    /// by the time they are merged. It's always 0 at the last LOD, since there is no parent.
+    pub fn geomorph_factor(&self, lod: u8, lod0_center: Point3f, p: Point3f) -> f32 {
+        if lod + 1 >= self.num_lods {
+            return 0.0;
+        }
+
+        let lod_chunk_edge_length = (self.chunk_shape.x() << lod) as f32;
+        let offset = (p - lod0_center).abs().max_component() / lod_chunk_edge_length;
+
+        let morph_end = (self.clip_box_radius - 1) as f32;
+        let morph_start =
+            ((self.clip_box_radius >> 1) as f32).max(morph_end - self.clip_box_radius as f32 / 4.0);
+        if morph_end <= morph_start {
+            return if offset >= morph_end { 1.0 } else { 0.0 };
+        }
+
+        ((offset - morph_start) / (morph_end - morph_start))
+            .max(0.0)
+            .min(1.0)
+    }
 }
 
 /// Traverse `octree` to find the `ChunkKey3`s that are "active" when the clipmap is centered at `lod0_center`. `active_rx`
@@ -40,10 +69,22 @@ pub fn active_clipmap_lod_chunks(
     config: &ClipMapConfig3,
     octree: &OctreeSet,
     lod0_center: ChunkUnits<Point3i>,
+    active_rx: impl FnMut(ChunkKey3),
+) {
+    active_clipmap_lod_chunks_for_centers(config, octree, &[lod0_center], active_rx)
+}
+
+/// Like `active_clipmap_lod_chunks`, but with a clipmap centered at each of `lod0_centers`, e.g. for split-screen or a server
+/// with many players. Each octant gets the highest level of detail that any of the centers would give it, so every chunk is
+/// active exactly once, even where the clip boxes overlap. If there are no centers, only the lowest level of detail is active.
+pub fn active_clipmap_lod_chunks_for_centers(
+    config: &ClipMapConfig3,
+    octree: &OctreeSet,
+    lod0_centers: &[ChunkUnits<Point3i>],
     mut active_rx: impl FnMut(ChunkKey3),
 ) {
     let chunk_log2 = config.chunk_edge_length_log2();
-    let centers = all_lod_centers(lod0_center.0, config.num_lods);
+    let centers = all_lod_centers(lod0_centers, config.num_lods);
 
     let high_lod_boundary = config.clip_box_radius >> 1;
 
@@ -101,8 +142,8 @@ pub struct ClipMapUpdate3 {
     num_lods: u8,
     low_lod_boundary: i32,
     high_lod_boundary: i32,
-    old_centers: Vec<Point3i>,
-    new_centers: Vec<Point3i>,
+    old_centers: Vec<Vec<Point3i>>,
+    new_centers: Vec<Vec<Point3i>>,
 }
 
 impl ClipMapUpdate3 {
@@ -112,14 +153,24 @@ impl ClipMapUpdate3 {
         config: &ClipMapConfig3,
         old_lod0_center: ChunkUnits<Point3i>,
         new_lod0_center: ChunkUnits<Point3i>,
+    ) -> Self {
+        Self::new_for_centers(config, &[old_lod0_center], &[new_lod0_center])
+    }
+
+    /// Like `new`, but for a clipmap with many centers, as in `active_clipmap_lod_chunks_for_centers`. The number of centers
+    /// can change between the old and new sets, e.g. when a player joins or leaves.
+    pub fn new_for_centers(
+        config: &ClipMapConfig3,
+        old_lod0_centers: &[ChunkUnits<Point3i>],
+        new_lod0_centers: &[ChunkUnits<Point3i>],
     ) -> Self {
         Self {
             chunk_log2: config.chunk_shape.x().trailing_zeros() as i32,
             num_lods: config.num_lods,
             low_lod_boundary: config.clip_box_radius,
             high_lod_boundary: config.clip_box_radius >> 1,
-            old_centers: all_lod_centers(old_lod0_center.0, config.num_lods),
-            new_centers: all_lod_centers(new_lod0_center.0, config.num_lods),
+            old_centers: all_lod_centers(old_lod0_centers, config.num_lods),
+            new_centers: all_lod_centers(new_lod0_centers, config.num_lods),
         }
     }
 
@@ -190,20 +241,239 @@ impl ClipMapUpdate3 {
     }
 }
 
-fn all_lod_centers(lod0_center: Point3i, num_lods: u8) -> Vec<Point3i> {
-    let mut centers = vec![lod0_center; num_lods as usize];
-    for i in 1..num_lods as usize {
-        centers[i] = centers[i - 1] >> 1;
+/// The changes to the set of active chunks since the last call to `ClipMapTracker3::update`.
+///
+/// Chunks that replace other chunks at a different level of detail are reported in `lod_changes`, while `load` and `unload`
+/// only contain chunks whose space was not covered by any active chunk before (or after), e.g. when the octree grows or
+/// shrinks, or on the first update.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClipMapDiff3 {
+    /// Newly active chunks that don't replace any old chunks.
+    pub load: Vec<ChunkKey3>,
+    /// Formerly active chunks that aren't replaced by any new chunks.
+    pub unload: Vec<ChunkKey3>,
+    /// Chunks that were split into higher detail or merged into lower detail.
+    pub lod_changes: Vec<LodChunkUpdate3>,
+}
+
+impl ClipMapDiff3 {
+    pub fn is_empty(&self) -> bool {
+        self.load.is_empty() && self.unload.is_empty() && self.lod_changes.is_empty()
     }
+}
 
-    centers
+/// Remembers the active chunks of a clipmap between frames, so each update only needs to report what changed as a
+/// `ClipMapDiff3`.
+///
+/// Unlike `ClipMapUpdate3`, the tracker supports a `hysteresis` (in chunks) around the boundary between levels of detail. An
+/// octant that is already split stays split until it moves `hysteresis` chunks past the boundary, and an octant that is not
+/// split doesn't split until it moves `hysteresis` chunks inside of the boundary. This prevents chunks from flickering between
+/// levels of detail when a center moves back and forth across the boundary. With a `hysteresis` of `0`, the active chunks are
+/// the same as those from `active_clipmap_lod_chunks_for_centers`.
+pub struct ClipMapTracker3 {
+    config: ClipMapConfig3,
+    hysteresis: i32,
+    active: SmallKeyHashSet<ChunkKey3>,
+    split: SmallKeyHashSet<ChunkKey3>,
+}
+
+impl ClipMapTracker3 {
+    /// Creates a tracker with no active chunks. `hysteresis` must be less than half of the clip box radius.
+    pub fn new(config: ClipMapConfig3, hysteresis: u16) -> Self {
+        let hysteresis = hysteresis as i32;
+        assert!(hysteresis < config.clip_box_radius >> 1);
+
+        Self {
+            config,
+            hysteresis,
+            active: SmallKeyHashSet::default(),
+            split: SmallKeyHashSet::default(),
+        }
+    }
+
+    pub fn config(&self) -> &ClipMapConfig3 {
+        &self.config
+    }
+
+    /// The chunks that were active after the last update.
+    pub fn active_chunks(&self) -> &SmallKeyHashSet<ChunkKey3> {
+        &self.active
+    }
+
+    /// Finds the chunks that are active for a clipmap centered at each of `lod0_centers` and returns how they differ from the
+    /// previously active chunks. `visit_octrees` must call the given visitor on every octree that should be part of the
+    /// clipmap, e.g. `|visitor| visitor(&octree)`. Any chunks in octrees that aren't visited will be unloaded.
+    pub fn update(
+        &mut self,
+        lod0_centers: &[ChunkUnits<Point3i>],
+        visit_octrees: impl FnOnce(&mut dyn FnMut(&OctreeSet)),
+    ) -> ClipMapDiff3 {
+        let chunk_log2 = self.config.chunk_edge_length_log2();
+        let num_lods = self.config.num_lods;
+        let centers = all_lod_centers(lod0_centers, num_lods);
+        let high_lod_boundary = self.config.clip_box_radius >> 1;
+
+        let mut active = SmallKeyHashSet::default();
+        let mut split = SmallKeyHashSet::default();
+        visit_octrees(&mut |octree: &OctreeSet| {
+            octree.visit_all_octants_in_preorder(&mut |node: &OctreeNode| {
+                let octant = node.octant();
+                let lod = octant.exponent();
+                if lod >= num_lods {
+                    return VisitStatus::Continue;
+                }
+
+                let key = octant_chunk_key(chunk_log2, &octant);
+                if lod == 0 {
+                    active.insert(key);
+
+                    return VisitStatus::Stop;
+                }
+
+                let split_boundary = if self.split.contains(&key) {
+                    high_lod_boundary + self.hysteresis
+                } else if self.is_covered_by_active(chunk_log2, key) {
+                    high_lod_boundary - self.hysteresis
+                } else {
+                    high_lod_boundary
+                };
+
+                if get_offset_from_lod_center(&octant, &centers) <= split_boundary {
+                    // This octant should be rendered with more detail.
+                    split.insert(key);
+
+                    VisitStatus::Continue
+                } else {
+                    active.insert(key);
+
+                    VisitStatus::Stop
+                }
+            });
+        });
+
+        let diff = self.diff(chunk_log2, &active);
+        self.active = active;
+        self.split = split;
+
+        diff
+    }
+
+    // Returns `true` iff `key` or one of its ancestors was active.
+    fn is_covered_by_active(&self, chunk_log2: i32, key: ChunkKey3) -> bool {
+        (key.lod..self.config.num_lods).any(|lod| {
+            self.active
+                .contains(&ancestor_chunk_key(chunk_log2, key, lod))
+        })
+    }
+
+    fn diff(&self, chunk_log2: i32, new_active: &SmallKeyHashSet<ChunkKey3>) -> ClipMapDiff3 {
+        let num_lods = self.config.num_lods;
+        let added: SmallKeyHashSet<ChunkKey3> =
+            new_active.difference(&self.active).cloned().collect();
+        let removed: SmallKeyHashSet<ChunkKey3> =
+            self.active.difference(new_active).cloned().collect();
+
+        // Group every changed chunk under the coarser chunk that it replaces or is replaced by, if any.
+        let find_ancestor = |key: ChunkKey3, candidates: &SmallKeyHashSet<ChunkKey3>| {
+            (key.lod + 1..num_lods)
+                .map(|lod| ancestor_chunk_key(chunk_log2, key, lod))
+                .find(|ancestor| candidates.contains(ancestor))
+        };
+        let mut splits = SmallKeyHashMap::<ChunkKey3, Vec<ChunkKey3>>::default();
+        let mut merges = SmallKeyHashMap::<ChunkKey3, Vec<ChunkKey3>>::default();
+        let mut maybe_load = Vec::new();
+        let mut maybe_unload = Vec::new();
+        for &key in added.iter() {
+            if let Some(old_chunk) = find_ancestor(key, &removed) {
+                splits.entry(old_chunk).or_default().push(key);
+            } else {
+                maybe_load.push(key);
+            }
+        }
+        for &key in removed.iter() {
+            if let Some(new_chunk) = find_ancestor(key, &added) {
+                merges.entry(new_chunk).or_default().push(key);
+            } else {
+                maybe_unload.push(key);
+            }
+        }
+
+        let mut diff = ClipMapDiff3 {
+            load: maybe_load
+                .into_iter()
+                .filter(|key| !merges.contains_key(key))
+                .collect(),
+            unload: maybe_unload
+                .into_iter()
+                .filter(|key| !splits.contains_key(key))
+                .collect(),
+            lod_changes: Vec::with_capacity(splits.len() + merges.len()),
+        };
+        for (old_chunk, mut new_chunks) in splits.into_iter() {
+            sort_chunk_keys(&mut new_chunks);
+            diff.lod_changes.push(LodChunkUpdate::Split(SplitChunk {
+                old_chunk,
+                new_chunks,
+            }));
+        }
+        for (new_chunk, mut old_chunks) in merges.into_iter() {
+            sort_chunk_keys(&mut old_chunks);
+            diff.lod_changes.push(LodChunkUpdate::Merge(MergeChunks {
+                old_chunks,
+                new_chunk,
+            }));
+        }
+        sort_chunk_keys(&mut diff.load);
+        sort_chunk_keys(&mut diff.unload);
+        diff.lod_changes.sort_by_key(|update| match update {
+            LodChunkUpdate::Split(split) => chunk_key_order(&split.old_chunk),
+            LodChunkUpdate::Merge(merge) => chunk_key_order(&merge.new_chunk),
+        });
+
+        diff
+    }
+}
+
+// The key of the chunk at `lod` that contains the chunk at `key`, where `lod >= key.lod`.
+fn ancestor_chunk_key(chunk_log2: i32, key: ChunkKey3, lod: u8) -> ChunkKey3 {
+    let octant_min = (key.minimum << key.lod) >> chunk_log2;
+    let ancestor_min = (octant_min >> lod) << lod;
+
+    ChunkKey {
+        lod,
+        minimum: (ancestor_min << chunk_log2) >> lod,
+    }
+}
+
+// Sorting keeps diffs deterministic, even though they're built from hash sets.
+fn sort_chunk_keys(keys: &mut [ChunkKey3]) {
+    keys.sort_by_key(chunk_key_order);
+}
+
+fn chunk_key_order(key: &ChunkKey3) -> (u8, [i32; 3]) {
+    (key.lod, key.minimum.0)
+}
+
+// Returns the center of each clipmap at each level of detail, indexed by `[clipmap][lod]`.
+fn all_lod_centers(lod0_centers: &[ChunkUnits<Point3i>], num_lods: u8) -> Vec<Vec<Point3i>> {
+    lod0_centers
+        .iter()
+        .map(|lod0_center| {
+            let mut centers = vec![lod0_center.0; num_lods as usize];
+            for i in 1..num_lods as usize {
+                centers[i] = centers[i - 1] >> 1;
+            }
+
+            centers
+        })
+        .collect()
 }
 
 fn find_merge_or_split_descendants(
     chunk_log2: i32,
     octree: &OctreeSet,
     node: &OctreeNode,
-    centers: &[Point3i],
+    centers: &[Vec<Point3i>],
     high_lod_boundary: i32,
 ) -> Vec<ChunkKey3> {
     let mut matching_chunks = Vec::with_capacity(8);
@@ -222,7 +492,16 @@ fn find_merge_or_split_descendants(
     matching_chunks
 }
 
-fn get_offset_from_lod_center(octant: &Octant, centers: &[Point3i]) -> i32 {
+// The offset from the nearest of the clipmap centers.
+fn get_offset_from_lod_center(octant: &Octant, centers: &[Vec<Point3i>]) -> i32 {
+    centers
+        .iter()
+        .map(|clipmap_centers| get_offset_from_single_lod_center(octant, clipmap_centers))
+        .min()
+        .unwrap_or(i32::MAX)
+}
+
+fn get_offset_from_single_lod_center(octant: &Octant, centers: &[Point3i]) -> i32 {
     let lod = octant.exponent();
     let lod_p = octant.minimum() >> lod;
     let lod_center = centers[lod as usize];
@@ -360,6 +639,190 @@ mod test {
         );
     }
 
+    #[test]
+    fn overlapping_clipmaps_cover_each_chunk_once() {
+        let config = ClipMapConfig3::new(NUM_LODS, CLIP_BOX_RADIUS, CHUNK_SHAPE);
+
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let octree = OctreeSet::new_full(domain);
+
+        let centers = [
+            ChunkUnits(PointN([-8, 0, 0])),
+            ChunkUnits(PointN([-7, 0, 0])),
+            ChunkUnits(PointN([8, 4, 0])),
+        ];
+        let active_chunks = ActiveChunks::for_centers(&config, &octree, &centers);
+
+        // Each LOD0 chunk is covered by exactly one active chunk.
+        let covered_volume: i32 = active_chunks
+            .keys
+            .iter()
+            .map(|key| 1 << (3 * key.lod as i32))
+            .sum();
+        assert_eq!(covered_volume, domain.num_points() as i32);
+
+        // There is full detail around every center.
+        for center in centers.iter() {
+            assert!(active_chunks
+                .keys
+                .contains(&ChunkKey::new(0, center.0 * CHUNK_SHAPE)));
+        }
+
+        // A single center is the same as the single-center version.
+        assert_eq!(
+            ActiveChunks::for_centers(&config, &octree, &centers[..1]),
+            ActiveChunks::new(&config, &octree, centers[0])
+        );
+    }
+
+    #[test]
+    fn updates_are_consistent_with_active_chunks_for_many_centers() {
+        let config = ClipMapConfig3::new(NUM_LODS, CLIP_BOX_RADIUS, CHUNK_SHAPE);
+
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let octree = OctreeSet::new_full(domain);
+
+        let path: &[&[[i32; 3]]] = &[
+            &[[-8, 0, 0], [8, 0, 0]],
+            &[[-7, 0, 0], [8, 0, 0]],
+            &[[-7, 0, 0], [7, 1, 0]],
+            // Both centers in the same place.
+            &[[0, 0, 0], [0, 0, 0]],
+            &[[0, 0, 0], [1, 0, 0]],
+            // A center joins, then leaves.
+            &[[0, 0, 0], [1, 0, 0], [-6, -6, -6]],
+            &[[0, 0, 0]],
+            &[],
+            &[[2, 2, 2]],
+        ];
+
+        let to_centers = |points: &[[i32; 3]]| -> Vec<ChunkUnits<Point3i>> {
+            points.iter().map(|p| ChunkUnits(PointN(*p))).collect()
+        };
+
+        let mut active_chunks = ActiveChunks::for_centers(&config, &octree, &to_centers(path[0]));
+        for (p1, p2) in path.iter().cloned().tuple_windows() {
+            let (old_centers, new_centers) = (to_centers(p1), to_centers(p2));
+
+            ClipMapUpdate3::new_for_centers(&config, &old_centers, &new_centers)
+                .find_chunk_updates(&octree, |update| active_chunks.apply_update(update));
+
+            assert_eq!(
+                active_chunks,
+                ActiveChunks::for_centers(&config, &octree, &new_centers),
+                "Failed on edge: {:?} --> {:?}",
+                p1,
+                p2
+            );
+        }
+    }
+
+    #[test]
+    fn tracker_diffs_are_consistent_with_active_chunks() {
+        let config = ClipMapConfig3::new(NUM_LODS, CLIP_BOX_RADIUS, CHUNK_SHAPE);
+
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let octree = OctreeSet::new_full(domain);
+
+        let mut tracker = ClipMapTracker3::new(config, 0);
+
+        // The first update only loads chunks.
+        let first_center = [ChunkUnits(Point3i::ZERO)];
+        let diff = tracker.update(&first_center, |visitor| visitor(&octree));
+        assert!(diff.unload.is_empty() && diff.lod_changes.is_empty());
+        let mut active_chunks = ActiveChunks {
+            keys: diff.load.into_iter().collect(),
+        };
+        assert_eq!(
+            active_chunks,
+            ActiveChunks::for_centers(&config, &octree, &first_center)
+        );
+
+        for p in [[1, 0, 0], [3, -2, 0], [3, -2, 0], [-5, 4, 1], [0, 0, 0]].iter() {
+            let centers = [ChunkUnits(PointN(*p))];
+            let diff = tracker.update(&centers, |visitor| visitor(&octree));
+            assert!(diff.load.is_empty() && diff.unload.is_empty());
+            for update in diff.lod_changes.into_iter() {
+                active_chunks.apply_update(update);
+            }
+
+            assert_eq!(
+                active_chunks,
+                ActiveChunks::for_centers(&config, &octree, &centers),
+                "Failed at {:?}",
+                p
+            );
+            assert_eq!(&active_chunks.keys, tracker.active_chunks());
+        }
+    }
+
+    #[test]
+    fn tracker_hysteresis_prevents_flicker() {
+        let config = ClipMapConfig3::new(3, 4, CHUNK_SHAPE);
+
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-32), Point3i::fill(64));
+        let octree = OctreeSet::new_full(domain);
+
+        let count_lod_changes = |hysteresis| {
+            let mut tracker = ClipMapTracker3::new(config, hysteresis);
+            tracker.update(&[ChunkUnits(PointN([1, 0, 0]))], |visitor| visitor(&octree));
+
+            // Oscillate across a boundary of LOD1 chunks, which are at half resolution.
+            let mut num_changes = 0;
+            for p in [[2, 0, 0], [1, 0, 0], [2, 0, 0], [1, 0, 0]].iter() {
+                let diff = tracker.update(&[ChunkUnits(PointN(*p))], |visitor| visitor(&octree));
+                assert!(diff.load.is_empty() && diff.unload.is_empty());
+                num_changes += diff.lod_changes.len();
+            }
+
+            num_changes
+        };
+
+        assert!(count_lod_changes(0) > 0);
+        assert_eq!(count_lod_changes(1), 0);
+    }
+
+    #[test]
+    fn tracker_unloads_chunks_of_removed_octrees() {
+        let config = ClipMapConfig3::new(NUM_LODS, CLIP_BOX_RADIUS, CHUNK_SHAPE);
+
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let octree = OctreeSet::new_full(domain);
+        let center = [ChunkUnits(Point3i::ZERO)];
+
+        let mut tracker = ClipMapTracker3::new(config, 0);
+        let loaded = tracker.update(&center, |visitor| visitor(&octree)).load;
+
+        assert!(tracker
+            .update(&center, |visitor| visitor(&octree))
+            .is_empty());
+
+        let diff = tracker.update(&center, |_visitor| {});
+        assert!(diff.load.is_empty() && diff.lod_changes.is_empty());
+        assert_eq!(diff.unload, loaded);
+        assert!(tracker.active_chunks().is_empty());
+    }
+
+    #[test]
+    fn geomorph_factor_ramps_up_before_parent_lod() {
+        let config = ClipMapConfig3::new(3, 8, CHUNK_SHAPE);
+        let center = Point3f::fill(8.0);
+        let at_chunk_offset = |lod: u8, chunks: f32| {
+            let p = center + PointN([0.0, -chunks * (16 << lod) as f32, 1.0]);
+
+            config.geomorph_factor(lod, center, p)
+        };
+
+        assert_eq!(at_chunk_offset(0, 2.0), 0.0);
+        assert_eq!(at_chunk_offset(0, 5.0), 0.0);
+        assert_eq!(at_chunk_offset(0, 6.0), 0.5);
+        assert_eq!(at_chunk_offset(0, 7.0), 1.0);
+        assert_eq!(at_chunk_offset(0, 12.0), 1.0);
+        assert_eq!(at_chunk_offset(1, 6.0), 0.5);
+        // There is no parent of the last LOD.
+        assert_eq!(at_chunk_offset(2, 7.0), 0.0);
+    }
+
     fn validate_update_path(config: &ClipMapConfig3, octree: &OctreeSet, path: &[[i32; 3]]) {
         let mut active_chunks = ActiveChunks::new(&config, &octree, ChunkUnits(PointN(path[0])));
 
@@ -403,6 +866,19 @@ mod test {
             Self { keys }
         }
 
+        fn for_centers(
+            config: &ClipMapConfig3,
+            octree: &OctreeSet,
+            lod0_centers: &[ChunkUnits<Point3i>],
+        ) -> Self {
+            let mut keys = SmallKeyHashSet::new();
+            active_clipmap_lod_chunks_for_centers(&config, &octree, lod0_centers, |key| {
+                keys.insert(key);
+            });
+
+            Self { keys }
+        }
+
         fn apply_update(&mut self, update: LodChunkUpdate3) {
             match update {
                 LodChunkUpdate::Merge(MergeChunks {
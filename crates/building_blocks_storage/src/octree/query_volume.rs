@@ -0,0 +1,385 @@
+//! Convex volumes used to query octrees, like view frustums for culling.
+//!
+//! Each `QueryVolume` can classify an axis-aligned box as being outside, inside, or partially inside of the volume. This lets
+//! octree traversals prune entire subtrees that are outside of the volume, and skip testing the descendants of octants that are
+//! entirely inside.
+
+use building_blocks_core::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// How an axis-aligned box relates to a `QueryVolume`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Containment {
+    /// The box is entirely outside of the volume.
+    Outside,
+    /// The box might overlap the volume. Some tests are conservative, so the box could actually be just outside.
+    Intersects,
+    /// The box is entirely inside of the volume.
+    Inside,
+}
+
+/// A volume that can be tested against axis-aligned boxes.
+pub trait QueryVolume {
+    fn classify_extent(&self, extent: &Extent3f) -> Containment;
+
+    /// The same volume after scaling space by `factor` about the origin, e.g. to convert from voxel to chunk coordinates.
+    fn scaled(&self, factor: f32) -> Self
+    where
+        Self: Sized;
+}
+
+/// The half-space of points `p` where `normal.dot(p) + distance >= 0`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Plane {
+    pub normal: Point3f,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// The signed distance from `p` to the plane, assuming the normal is a unit vector. Positive inside of the half-space.
+    #[inline]
+    pub fn signed_distance(&self, p: Point3f) -> f32 {
+        self.normal.dot(p) + self.distance
+    }
+
+    /// Scales the plane equation so that the normal is a unit vector.
+    #[inline]
+    pub fn normalized(&self) -> Self {
+        let norm = self.normal.norm();
+
+        Self {
+            normal: self.normal / norm,
+            distance: self.distance / norm,
+        }
+    }
+}
+
+impl QueryVolume for Plane {
+    #[inline]
+    fn classify_extent(&self, extent: &Extent3f) -> Containment {
+        let half_shape = extent.shape / 2.0;
+        let center = extent.minimum + half_shape;
+        // The largest distance from the center to any corner, projected onto the normal.
+        let radius = half_shape.dot(self.normal.abs());
+        let center_dist = self.signed_distance(center);
+
+        if center_dist < -radius {
+            Containment::Outside
+        } else if center_dist >= radius {
+            Containment::Inside
+        } else {
+            Containment::Intersects
+        }
+    }
+
+    #[inline]
+    fn scaled(&self, factor: f32) -> Self {
+        // n . (p / factor) + d = 0  <=>  n . p + factor * d = 0
+        Self {
+            normal: self.normal,
+            distance: self.distance * factor,
+        }
+    }
+}
+
+/// The intersection of 6 half-spaces, usually the view volume of a camera.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of the view frustum from a view-projection matrix, given as columns (the layout used by `glam`,
+    /// `nalgebra`, and `mint::ColumnMatrix4`). The projection is assumed to map depth into `[0, 1]`, as do the perspective
+    /// matrices of `glam` and the conventions of Vulkan, Metal, D3D, and wgpu.
+    pub fn from_view_projection(columns: [[f32; 4]; 4]) -> Self {
+        let row = |i: usize| [columns[0][i], columns[1][i], columns[2][i], columns[3][i]];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let plane = |c: [f32; 4]| {
+            Plane {
+                normal: PointN([c[0], c[1], c[2]]),
+                distance: c[3],
+            }
+            .normalized()
+        };
+        let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+        Self {
+            planes: [
+                plane(add(r3, r0)), // left
+                plane(sub(r3, r0)), // right
+                plane(add(r3, r1)), // bottom
+                plane(sub(r3, r1)), // top
+                plane(r2),          // near
+                plane(sub(r3, r2)), // far
+            ],
+        }
+    }
+}
+
+impl QueryVolume for Frustum {
+    /// This test is conservative: boxes near the edges of the frustum that don't intersect it may be classified as
+    /// `Intersects`.
+    #[inline]
+    fn classify_extent(&self, extent: &Extent3f) -> Containment {
+        let mut all_inside = true;
+        for plane in self.planes.iter() {
+            match plane.classify_extent(extent) {
+                Containment::Outside => return Containment::Outside,
+                Containment::Intersects => all_inside = false,
+                Containment::Inside => (),
+            }
+        }
+
+        if all_inside {
+            Containment::Inside
+        } else {
+            Containment::Intersects
+        }
+    }
+
+    #[inline]
+    fn scaled(&self, factor: f32) -> Self {
+        let mut planes = self.planes;
+        for plane in planes.iter_mut() {
+            *plane = plane.scaled(factor);
+        }
+
+        Self { planes }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Sphere {
+    pub center: Point3f,
+    pub radius: f32,
+}
+
+impl QueryVolume for Sphere {
+    #[inline]
+    fn classify_extent(&self, extent: &Extent3f) -> Containment {
+        let min = extent.minimum;
+        let max = extent.least_upper_bound();
+
+        let mut nearest_dist_sq = 0.0;
+        let mut farthest_dist_sq = 0.0;
+        for i in 0..3 {
+            let c = self.center.at(i);
+            let (lo, hi) = (min.at(i), max.at(i));
+            let nearest = if c < lo {
+                lo - c
+            } else if c > hi {
+                c - hi
+            } else {
+                0.0
+            };
+            let farthest = (c - lo).abs().max((hi - c).abs());
+            nearest_dist_sq += nearest * nearest;
+            farthest_dist_sq += farthest * farthest;
+        }
+
+        let radius_sq = self.radius * self.radius;
+        if nearest_dist_sq > radius_sq {
+            Containment::Outside
+        } else if farthest_dist_sq <= radius_sq {
+            Containment::Inside
+        } else {
+            Containment::Intersects
+        }
+    }
+
+    #[inline]
+    fn scaled(&self, factor: f32) -> Self {
+        Self {
+            center: self.center * factor,
+            radius: self.radius * factor,
+        }
+    }
+}
+
+/// An oriented bounding box. The `axes` must be orthonormal.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Obb {
+    pub center: Point3f,
+    pub axes: [Point3f; 3],
+    /// The half-length of the box along each of the `axes`.
+    pub half_extents: Point3f,
+}
+
+impl Obb {
+    /// Returns `true` iff `p` is inside of the box.
+    #[inline]
+    pub fn contains(&self, p: Point3f) -> bool {
+        let offset = p - self.center;
+
+        self.axes
+            .iter()
+            .enumerate()
+            .all(|(i, axis)| axis.dot(offset).abs() <= self.half_extents.at(i))
+    }
+}
+
+impl QueryVolume for Obb {
+    /// Uses the separating axis theorem, so the result is exact.
+    #[inline]
+    fn classify_extent(&self, extent: &Extent3f) -> Containment {
+        let aabb_half_shape = extent.shape / 2.0;
+        let aabb_center = extent.minimum + aabb_half_shape;
+        let offset = self.center - aabb_center;
+
+        let is_separating_axis = |axis: Point3f| {
+            let aabb_radius = aabb_half_shape.dot(axis.abs());
+            let obb_radius: f32 = self
+                .axes
+                .iter()
+                .enumerate()
+                .map(|(i, obb_axis)| self.half_extents.at(i) * obb_axis.dot(axis).abs())
+                .sum();
+
+            offset.dot(axis).abs() > aabb_radius + obb_radius
+        };
+
+        let aabb_axes = [
+            PointN([1.0, 0.0, 0.0]),
+            PointN([0.0, 1.0, 0.0]),
+            PointN([0.0, 0.0, 1.0]),
+        ];
+        for axis in aabb_axes.iter().chain(self.axes.iter()) {
+            if is_separating_axis(*axis) {
+                return Containment::Outside;
+            }
+        }
+        for aabb_axis in aabb_axes.iter() {
+            for obb_axis in self.axes.iter() {
+                let axis = aabb_axis.cross(*obb_axis);
+                // Parallel edges don't give a new axis.
+                if axis.dot(axis) > std::f32::EPSILON && is_separating_axis(axis) {
+                    return Containment::Outside;
+                }
+            }
+        }
+
+        if extent.corners().iter().all(|c| self.contains(*c)) {
+            Containment::Inside
+        } else {
+            Containment::Intersects
+        }
+    }
+
+    #[inline]
+    fn scaled(&self, factor: f32) -> Self {
+        Self {
+            center: self.center * factor,
+            axes: self.axes,
+            half_extents: self.half_extents * factor,
+        }
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(min: Point3f) -> Extent3f {
+        Extent3f::from_min_and_shape(min, Point3f::fill(1.0))
+    }
+
+    #[test]
+    fn sphere_classification() {
+        let sphere = Sphere {
+            center: Point3f::ZERO,
+            radius: 2.0,
+        };
+
+        assert_eq!(
+            sphere.classify_extent(&unit_box(Point3f::ZERO)),
+            Containment::Inside
+        );
+        assert_eq!(
+            sphere.classify_extent(&unit_box(PointN([1.5, 0.0, 0.0]))),
+            Containment::Intersects
+        );
+        assert_eq!(
+            sphere.classify_extent(&unit_box(Point3f::fill(1.5))),
+            Containment::Outside
+        );
+    }
+
+    #[test]
+    fn obb_classification() {
+        // A box rotated 45 degrees about the Z axis.
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let obb = Obb {
+            center: Point3f::ZERO,
+            axes: [
+                PointN([s, s, 0.0]),
+                PointN([-s, s, 0.0]),
+                PointN([0.0, 0.0, 1.0]),
+            ],
+            half_extents: PointN([2.0, 1.0, 1.0]),
+        };
+
+        assert_eq!(
+            obb.classify_extent(&Extent3f::from_min_and_shape(
+                PointN([-0.25, -0.25, -0.5]),
+                Point3f::fill(0.5)
+            )),
+            Containment::Inside
+        );
+        assert_eq!(
+            obb.classify_extent(&unit_box(PointN([0.0, 0.0, 0.5]))),
+            Containment::Intersects
+        );
+        // This box is inside of the OBB's bounding AABB, but separated along one of the OBB's axes.
+        assert_eq!(
+            obb.classify_extent(&unit_box(PointN([1.0, -2.0, 0.0]))),
+            Containment::Outside
+        );
+    }
+
+    #[test]
+    fn frustum_from_orthographic_projection() {
+        // Orthographic projection of the box [-1, 1] x [-1, 1] x [0, 10], with depth mapped to [0, 1].
+        let frustum = Frustum::from_view_projection([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.1, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        let small_box = |min| Extent3f::from_min_and_shape(min, Point3f::fill(0.5));
+        assert_eq!(
+            frustum.classify_extent(&small_box(PointN([0.0, 0.0, 1.0]))),
+            Containment::Inside
+        );
+        assert_eq!(
+            frustum.classify_extent(&small_box(PointN([0.75, 0.0, 1.0]))),
+            Containment::Intersects
+        );
+        assert_eq!(
+            frustum.classify_extent(&small_box(PointN([0.0, 0.0, 11.0]))),
+            Containment::Outside
+        );
+        assert_eq!(
+            frustum.classify_extent(&small_box(PointN([0.0, 0.0, -1.0]))),
+            Containment::Outside
+        );
+
+        // Scaling space by 2 doubles the size of the frustum.
+        assert_eq!(
+            frustum
+                .scaled(2.0)
+                .classify_extent(&small_box(PointN([1.25, 0.0, 1.0]))),
+            Containment::Inside
+        );
+    }
+}
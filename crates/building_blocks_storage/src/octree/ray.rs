@@ -0,0 +1,121 @@
+//! Ray casting that descends the hierarchy of an octree directly, for `OctreeSet::cast_ray` and
+//! `SparseVoxelOctree::cast_ray`.
+//!
+//! At every branch, the children that the ray passes through are visited in the order that the ray enters them. Since the
+//! children are disjoint boxes, the first leaf that's hit is the nearest one, and whole octants that the ray misses are never
+//! visited. This is much faster for occupancy queries than casting against the bounding volumes of an `OctreeDbvt`.
+
+use building_blocks_core::prelude::*;
+
+/// The first leaf octant hit by a ray cast against an octree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OctreeRayHit {
+    /// The ray parameter where the ray enters the leaf, in units of the ray's direction.
+    pub t: f32,
+    /// The voxel where the ray enters the leaf.
+    pub voxel: Point3i,
+    /// The normal of the face of the leaf that the ray entered through, or zero if the ray started inside of the leaf.
+    pub normal: Point3i,
+    /// The extent of the whole leaf octant.
+    pub leaf_extent: Extent3i,
+}
+
+pub(crate) struct OctreeRay {
+    origin: Point3f,
+    direction: Point3f,
+    max_t: f32,
+}
+
+/// Where a ray enters a box.
+#[derive(Clone, Copy)]
+pub(crate) struct RayEntry {
+    t: f32,
+    normal: Point3i,
+}
+
+impl OctreeRay {
+    pub(crate) fn new(origin: Point3f, direction: Point3f, max_t: f32) -> Self {
+        Self {
+            origin,
+            direction,
+            max_t,
+        }
+    }
+
+    /// Where the ray enters `extent`, unless it misses, or it only overlaps outside of `[0, max_t]`.
+    pub(crate) fn enter_extent(&self, extent: &Extent3i) -> Option<RayEntry> {
+        let min = Point3f::from(extent.minimum);
+        let max = Point3f::from(extent.least_upper_bound());
+
+        let mut entry = RayEntry {
+            t: 0.0,
+            normal: Point3i::ZERO,
+        };
+        let mut t_exit = self.max_t;
+        let axes = self
+            .origin
+            .0
+            .iter()
+            .zip(self.direction.0.iter())
+            .zip(min.0.iter().zip(max.0.iter()));
+        for (axis, ((&o, &d), (&lo, &hi))) in axes.enumerate() {
+            if d == 0.0 {
+                if o < lo || o >= hi {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (lo - o) / d;
+            let t1 = (hi - o) / d;
+            let t_near = t0.min(t1);
+            if t_near > entry.t {
+                entry.t = t_near;
+                entry.normal = Point3i::ZERO;
+                entry.normal.0[axis] = if d > 0.0 { -1 } else { 1 };
+            }
+            t_exit = t_exit.min(t0.max(t1));
+        }
+
+        if entry.t <= t_exit {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn hit_leaf(&self, entry: RayEntry, leaf_extent: Extent3i) -> OctreeRayHit {
+        let entry_point = self.origin + self.direction * entry.t;
+        // Rounding can put the entry point just outside of the leaf.
+        let voxel = entry_point
+            .in_voxel()
+            .join(leaf_extent.minimum)
+            .meet(leaf_extent.max());
+
+        OctreeRayHit {
+            t: entry.t,
+            voxel,
+            normal: entry.normal,
+            leaf_extent,
+        }
+    }
+
+    /// Calls `cast_child` on each of the `children` that the ray passes through, in the order that the ray enters them,
+    /// until one of them returns a hit.
+    pub(crate) fn find_first_hit_in_children<C, H>(
+        &self,
+        children: impl Iterator<Item = (C, Extent3i)>,
+        mut cast_child: impl FnMut(C, Extent3i, RayEntry) -> Option<H>,
+    ) -> Option<H> {
+        let mut entered: Vec<(RayEntry, C, Extent3i)> = children
+            .filter_map(|(child, extent)| {
+                self.enter_extent(&extent)
+                    .map(|entry| (entry, child, extent))
+            })
+            .collect();
+        entered.sort_by(|a, b| a.0.t.partial_cmp(&b.0.t).unwrap());
+
+        entered
+            .into_iter()
+            .find_map(|(entry, child, extent)| cast_child(child, extent, entry))
+    }
+}
@@ -55,13 +55,43 @@
 //!
 //! The other form of traversal is "node-based," which is slightly less efficient and more manual but also more flexible. See
 //! the `OctreeSet::root_node`, `OctreeSet::child_node`, and `OctreeNode` documentation for details.
+//!
+//! # Set Operations
+//!
+//! Two `OctreeSet`s with the same extent can be combined with `union`, `intersection`, `difference`, and
+//! `symmetric_difference`. These work directly on the tree structure, without ever visiting the individual points.
+//!
+//! ```
+//! # use building_blocks_core::prelude::*;
+//! # use building_blocks_storage::{octree::set::*, prelude::*};
+//! let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! let mut a = OctreeSet::new_empty(domain);
+//! a.add_extent(&Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8)));
+//! let mut b = OctreeSet::new_empty(domain);
+//! b.add_extent(&Extent3i::from_min_and_shape(Point3i::fill(4), Point3i::fill(8)));
+//!
+//! let overlap = a.intersection(&b);
+//! assert_eq!(overlap.collect_all_points().len(), 4 * 4 * 4);
+//! assert_eq!(a.union(&b).difference(&overlap), a.symmetric_difference(&b));
+//! ```
+//!
+//! # Serialization
+//!
+//! Besides the serde representation, an `OctreeSet` can be encoded with `OctreeSet::to_compact_bytes`, which only stores the
+//! child bitmasks of the branches. See the `compact` module for the format.
+
+pub mod compact;
+
+pub use compact::{COMPACT_OCTREE_SET_MAGIC, COMPACT_OCTREE_SET_VERSION};
 
-use crate::{prelude::*, IsEmpty, SmallKeyHashMap};
+use super::ray::{OctreeRay, OctreeRayHit, RayEntry};
+use crate::{prelude::*, Containment, IsEmpty, QueryVolume, SmallKeyHashMap};
 
 use building_blocks_core::prelude::*;
 
 use core::ops::Deref;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt::Formatter;
 
 /// A sparse set of voxel coordinates (3D integer points). Supports spatial queries.
@@ -232,6 +262,12 @@ impl OctreeSet {
         &self.extent
     }
 
+    /// Moves the whole set by `delta`. This only changes the extent, since the nodes are relative to it. Keep `delta` a
+    /// multiple of the edge length if the octants must stay aligned to the lattice, e.g. for clipmaps.
+    pub fn translate(&mut self, delta: Point3i) {
+        self.extent = self.extent + delta;
+    }
+
     /// Returns `true` iff the octree contains zero points.
     pub fn is_empty(&self) -> bool {
         !self.root_exists
@@ -302,6 +338,100 @@ impl OctreeSet {
         self.visit_all_octants_in_postorder(&|node| Self::extent_predicate(extent, node), visitor)
     }
 
+    /// Visits the branches and fat leaves level by level (breadth-first), from the root at level `0` down to single voxels at
+    /// level `self.power()`, so octants at level `L` have an edge length of `self.edge_length() >> L`. The visitor is given the
+    /// level, the extent, and whether the octant is full, i.e. a fat leaf. Returning `VisitStatus::Stop` skips the descendants
+    /// of an octant.
+    pub fn visit_octants_in_level_order(
+        &self,
+        mut visitor: impl FnMut(u8, &Extent3i, bool) -> VisitStatus,
+    ) -> VisitStatus {
+        let mut queue = VecDeque::new();
+        if let Some(root) = self.root_node() {
+            queue.push_back((0, root));
+        }
+
+        while let Some((level, node)) = queue.pop_front() {
+            // Only full nodes (including single voxels) have no children.
+            let is_full = node.child_bitmask == 0;
+            match visitor(level, &Extent3i::from(*node.octant()), is_full) {
+                VisitStatus::Continue => {}
+                VisitStatus::Stop => continue,
+                VisitStatus::ExitEarly => return VisitStatus::ExitEarly,
+            }
+            for child_index in 0..8 {
+                if let Some(child) = self.get_child(&node, child_index) {
+                    queue.push_back((level + 1, child));
+                }
+            }
+        }
+
+        VisitStatus::Continue
+    }
+
+    /// Every octant at `level` that contains any points, and whether it's full. Fat leaves above `level` are split into full
+    /// octants, so the octants cover the whole set, e.g. to make one minimap tile or coarse collision box per octant.
+    pub fn octants_at_level(&self, level: u8) -> Vec<(Extent3i, bool)> {
+        assert!(level <= self.power);
+
+        let edge_length = self.edge_length() >> level;
+        let mut octants = Vec::new();
+        self.visit_octants_in_level_order(|octant_level, extent, is_full| {
+            if octant_level == level {
+                octants.push((*extent, is_full));
+
+                return VisitStatus::Stop;
+            }
+            if is_full {
+                let octants_per_edge = extent.shape >> (level - octant_level) as i32;
+                for p in Extent3i::from_min_and_shape(Point3i::ZERO, octants_per_edge).iter_points()
+                {
+                    octants.push((
+                        Extent3i::from_min_and_shape(
+                            extent.minimum + p * edge_length,
+                            Point3i::fill(edge_length),
+                        ),
+                        true,
+                    ));
+                }
+            }
+
+            VisitStatus::Continue
+        });
+
+        octants
+    }
+
+    /// Visit every octant that intersects `volume`, in pre-order. Subtrees entirely outside of `volume` are skipped, and the
+    /// descendants of octants entirely inside of `volume` are visited without testing them again. The visitor is also given
+    /// the `Containment` of each octant, which is never `Outside`.
+    pub fn visit_octants_intersecting(
+        &self,
+        volume: &impl QueryVolume,
+        visitor: &mut impl FnMut(&OctreeNode, Containment) -> VisitStatus,
+    ) -> VisitStatus {
+        self.visit_all_octants_in_preorder(&mut |node: &OctreeNode| {
+            let octant_extent = Extent3f::from(Extent3i::from(node.octant.0));
+            match volume.classify_extent(&octant_extent) {
+                Containment::Outside => VisitStatus::Stop,
+                Containment::Intersects => visitor(node, Containment::Intersects),
+                Containment::Inside => {
+                    let status = node
+                        .visit_all_octants_in_preorder(self, &mut |descendant: &OctreeNode| {
+                            visitor(descendant, Containment::Inside)
+                        });
+
+                    // Either way, the descendants have already been visited.
+                    if status == VisitStatus::ExitEarly {
+                        VisitStatus::ExitEarly
+                    } else {
+                        VisitStatus::Stop
+                    }
+                }
+            }
+        })
+    }
+
     fn extent_visitor(
         extent: &Extent3i,
         visitor: &mut impl OctreeVisitor,
@@ -563,6 +693,69 @@ impl OctreeSet {
         })
     }
 
+    /// Returns `true` iff `point` is in the set. This only descends along the path from the root to `point`.
+    pub fn contains_point(&self, point: Point3i) -> bool {
+        if !self.extent.contains(point) {
+            return false;
+        }
+
+        let mut node = if let Some(root) = self.root_node() {
+            root
+        } else {
+            return false;
+        };
+        // Only full nodes (including single voxels) have no children.
+        while node.child_bitmask != 0 {
+            let half_edge_length = node.octant.edge_length() >> 1;
+            let offset = point - node.octant.minimum();
+            let child_index = (offset.x() >= half_edge_length) as u8
+                | ((offset.y() >= half_edge_length) as u8) << 1
+                | ((offset.z() >= half_edge_length) as u8) << 2;
+            node = if let Some(child) = self.get_child(&node, child_index) {
+                child
+            } else {
+                return false;
+            };
+        }
+
+        true
+    }
+
+    /// Finds the first voxel in the set along the ray from `origin` in `direction`, with `0 <= t <= max_t`. This descends the
+    /// hierarchy directly, skipping every empty octant that the ray passes through.
+    pub fn cast_ray(
+        &self,
+        origin: Point3f,
+        direction: Point3f,
+        max_t: f32,
+    ) -> Option<OctreeRayHit> {
+        let root = self.root_node()?;
+        let ray = OctreeRay::new(origin, direction, max_t);
+        let entry = ray.enter_extent(&self.extent)?;
+
+        self.cast_ray_recursive(root, entry, &ray)
+    }
+
+    fn cast_ray_recursive(
+        &self,
+        node: OctreeNode,
+        entry: RayEntry,
+        ray: &OctreeRay,
+    ) -> Option<OctreeRayHit> {
+        // Only full nodes (including single voxels) have no children.
+        if node.child_bitmask == 0 {
+            return Some(ray.hit_leaf(entry, Extent3i::from(*node.octant())));
+        }
+
+        let children = (0..8)
+            .filter_map(|child_index| self.get_child(&node, child_index))
+            .map(|child| (child, Extent3i::from(*child.octant())));
+
+        ray.find_first_hit_in_children(children, |child, _extent, child_entry| {
+            self.cast_ray_recursive(child, child_entry, ray)
+        })
+    }
+
     /// Add all points from `extent` to the set.
     pub fn add_extent(&mut self, add_extent: &Extent3i) {
         let (root_exists, _full) = self._add_extent(
@@ -745,6 +938,208 @@ impl OctreeSet {
 
         points
     }
+
+    /// Returns the set of points in either `self` or `other`.
+    ///
+    /// # Panics
+    /// If the sets have different extents.
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, SetOperation::Union)
+    }
+
+    /// Returns the set of points in both `self` and `other`.
+    ///
+    /// # Panics
+    /// If the sets have different extents.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, SetOperation::Intersection)
+    }
+
+    /// Returns the set of points in `self` but not in `other`.
+    ///
+    /// # Panics
+    /// If the sets have different extents.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, SetOperation::Difference)
+    }
+
+    /// Returns the set of points in exactly one of `self` and `other`.
+    ///
+    /// # Panics
+    /// If the sets have different extents.
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.combine(other, SetOperation::SymmetricDifference)
+    }
+
+    /// Merges the trees node by node, so the cost is proportional to the number of branch nodes in the operands rather than
+    /// the number of points. Octants that end up full or empty are collapsed, so the result has the same minimal structure
+    /// as a set built with `from_array3`.
+    fn combine(&self, other: &Self, operation: SetOperation) -> Self {
+        assert_eq!(self.extent, other.extent);
+
+        let mut nodes = SmallKeyHashMap::default();
+        let root = Self::combine_nodes(
+            self,
+            other,
+            operation,
+            LocationCode::ROOT,
+            self.power,
+            self.root_contents(),
+            other.root_contents(),
+            &mut nodes,
+        );
+
+        Self {
+            extent: self.extent,
+            power: self.power,
+            root_exists: root != NodeContents::Empty,
+            nodes,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn combine_nodes(
+        a: &Self,
+        b: &Self,
+        operation: SetOperation,
+        code: LocationCode,
+        exponent: u8,
+        a_node: NodeContents,
+        b_node: NodeContents,
+        nodes: &mut SmallKeyHashMap<LocationCode, ChildBitMask>,
+    ) -> NodeContents {
+        // If either operand is full or empty, it might determine the result without looking at the other operand.
+        match (a_node.full(), b_node.full()) {
+            (Some(a_full), Some(b_full)) => {
+                return NodeContents::constant(operation.apply(a_full, b_full));
+            }
+            (Some(a_full), None) => {
+                let result = operation.apply(a_full, false);
+                if result == operation.apply(a_full, true) {
+                    return NodeContents::constant(result);
+                }
+            }
+            (None, Some(b_full)) => {
+                let result = operation.apply(false, b_full);
+                if result == operation.apply(true, b_full) {
+                    return NodeContents::constant(result);
+                }
+            }
+            (None, None) => (),
+        }
+
+        // At least one operand is a mixed branch, so this can't be a single voxel.
+        let mut child_bitmask = 0;
+        let mut all_children_full = true;
+        let extended_code = code.extend();
+        for child_index in 0..8 {
+            let child_code = extended_code.with_lowest_octant(child_index as u16);
+            let a_child = a.child_contents(a_node, child_code, child_index, exponent - 1);
+            let b_child = b.child_contents(b_node, child_code, child_index, exponent - 1);
+            let child = Self::combine_nodes(
+                a,
+                b,
+                operation,
+                child_code,
+                exponent - 1,
+                a_child,
+                b_child,
+                nodes,
+            );
+            child_bitmask |= ((child != NodeContents::Empty) as u8) << child_index;
+            all_children_full &= child == NodeContents::Full;
+        }
+
+        if all_children_full {
+            NodeContents::Full
+        } else if child_bitmask == 0 {
+            NodeContents::Empty
+        } else {
+            nodes.insert(code, child_bitmask);
+
+            NodeContents::Mixed(child_bitmask)
+        }
+    }
+
+    fn root_contents(&self) -> NodeContents {
+        if !self.root_exists {
+            NodeContents::Empty
+        } else if let Some(&child_bitmask) = self.nodes.get(&LocationCode::ROOT) {
+            NodeContents::Mixed(child_bitmask)
+        } else {
+            NodeContents::Full
+        }
+    }
+
+    fn child_contents(
+        &self,
+        parent: NodeContents,
+        child_code: LocationCode,
+        child_index: u8,
+        child_exponent: u8,
+    ) -> NodeContents {
+        let parent_bitmask = match parent {
+            NodeContents::Mixed(bitmask) => bitmask,
+            // Children of full or empty octants are also full or empty.
+            _ => return parent,
+        };
+
+        if parent_bitmask & (1 << child_index) == 0 {
+            NodeContents::Empty
+        } else if child_exponent == 0 {
+            NodeContents::Full
+        } else if let Some(&child_bitmask) = self.nodes.get(&child_code) {
+            NodeContents::Mixed(child_bitmask)
+        } else {
+            NodeContents::Full
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SetOperation {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+impl SetOperation {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            SetOperation::Union => a || b,
+            SetOperation::Intersection => a && b,
+            SetOperation::Difference => a && !b,
+            SetOperation::SymmetricDifference => a != b,
+        }
+    }
+}
+
+/// The contents of an octant in one of the operands of a `SetOperation`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum NodeContents {
+    Empty,
+    Full,
+    Mixed(ChildBitMask),
+}
+
+impl NodeContents {
+    fn constant(full: bool) -> Self {
+        if full {
+            NodeContents::Full
+        } else {
+            NodeContents::Empty
+        }
+    }
+
+    /// `Some(true)` if full, `Some(false)` if empty, and `None` if mixed.
+    fn full(self) -> Option<bool> {
+        match self {
+            NodeContents::Empty => Some(false),
+            NodeContents::Full => Some(true),
+            NodeContents::Mixed(_) => None,
+        }
+    }
 }
 
 /// Represents a single non-empty octant in the octree. Can be used for manual traversal by calling `OctreeSet::get_child`.
@@ -1003,9 +1398,104 @@ pub enum VisitStatus {
 mod tests {
     use super::*;
 
+    use crate::Sphere;
+
     use rand::Rng;
     use std::collections::HashSet;
 
+    #[test]
+    fn cast_ray_finds_nearest_occupied_voxel() {
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+        let mut set = OctreeSet::new_empty(domain);
+        let far = Extent3i::from_min_and_shape(PointN([8, -4, -4]), Point3i::fill(8));
+        set.add_extent(&far);
+        set.add_extent(&Extent3i::from_min_and_shape(
+            PointN([-2, 0, 0]),
+            Point3i::fill(1),
+        ));
+
+        // Enters the single voxel through its -X face.
+        let hit = set
+            .cast_ray(PointN([-10.0, 0.5, 0.5]), PointN([1.0, 0.0, 0.0]), f32::MAX)
+            .unwrap();
+        assert_eq!(hit.voxel, PointN([-2, 0, 0]));
+        assert_eq!(hit.normal, PointN([-1, 0, 0]));
+        assert_eq!(hit.t, 8.0);
+
+        // Passes next to the voxel and hits the far extent through its -Y face.
+        let hit = set
+            .cast_ray(
+                PointN([10.5, -12.0, 0.5]),
+                PointN([0.0, 2.0, 0.0]),
+                f32::MAX,
+            )
+            .unwrap();
+        assert_eq!(hit.voxel, PointN([10, -4, 0]));
+        assert_eq!(hit.normal, PointN([0, -1, 0]));
+        assert_eq!(hit.t, 4.0);
+        assert!(far.contains(hit.leaf_extent.minimum));
+
+        // Starting inside of the set.
+        let hit = set
+            .cast_ray(PointN([9.5, 0.5, 0.5]), PointN([0.0, 0.0, -1.0]), f32::MAX)
+            .unwrap();
+        assert_eq!(hit.voxel, PointN([9, 0, 0]));
+        assert_eq!(hit.normal, Point3i::ZERO);
+        assert_eq!(hit.t, 0.0);
+
+        assert!(set
+            .cast_ray(PointN([-10.0, 0.5, 0.5]), PointN([1.0, 0.0, 0.0]), 7.0)
+            .is_none());
+        assert!(set
+            .cast_ray(PointN([-10.0, 5.5, 0.5]), PointN([1.0, 0.0, 0.0]), f32::MAX)
+            .is_none());
+        assert!(OctreeSet::new_empty(domain)
+            .cast_ray(PointN([0.5, 0.5, 0.5]), PointN([1.0, 0.0, 0.0]), f32::MAX)
+            .is_none());
+    }
+
+    #[test]
+    fn visit_octants_level_by_level() {
+        let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let mut set = OctreeSet::new_empty(domain);
+        let fat_leaf = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        set.add_extent(&fat_leaf);
+        set.add_extent(&Extent3i::from_min_and_shape(
+            Point3i::fill(15),
+            Point3i::fill(1),
+        ));
+
+        let mut visited = Vec::new();
+        set.visit_octants_in_level_order(|level, extent, is_full| {
+            visited.push((level, *extent, is_full));
+
+            VisitStatus::Continue
+        });
+        let cube = |min: i32, edge: i32| {
+            Extent3i::from_min_and_shape(Point3i::fill(min), Point3i::fill(edge))
+        };
+        assert_eq!(
+            visited,
+            vec![
+                (0, domain, false),
+                (1, fat_leaf, true),
+                (1, cube(8, 8), false),
+                (2, cube(12, 4), false),
+                (3, cube(14, 2), false),
+                (4, cube(15, 1), true),
+            ]
+        );
+
+        let level2 = set.octants_at_level(2);
+        assert_eq!(level2.len(), 9);
+        assert_eq!(level2.iter().filter(|(_, is_full)| *is_full).count(), 8);
+        assert!(level2.contains(&(cube(4, 4), true)));
+        assert!(level2.contains(&(cube(12, 4), false)));
+
+        assert_eq!(set.octants_at_level(0), vec![(domain, false)]);
+        assert_eq!(set.octants_at_level(4).len(), 8 * 8 * 8 + 1);
+    }
+
     #[test]
     fn add_extents() {
         let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
@@ -1132,6 +1622,125 @@ mod tests {
         }
     }
 
+    #[test]
+    fn set_operations_match_pointwise_operations() {
+        let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+
+        // Mix some large full octants with noise so all of the shortcuts get exercised.
+        let mut a = OctreeSet::from_array3(&random_bool_array(domain), domain);
+        a.add_extent(&Extent3i::from_min_and_max(
+            Point3i::ZERO,
+            Point3i::fill(17),
+        ));
+        a.subtract_extent(&Extent3i::from_min_and_max(
+            PointN([20, 0, 0]),
+            Point3i::fill(31),
+        ));
+        let mut b = OctreeSet::from_array3(&random_bool_array(domain), domain);
+        b.add_extent(&Extent3i::from_min_and_max(
+            Point3i::fill(8),
+            Point3i::fill(31),
+        ));
+        b.subtract_extent(&Extent3i::from_min_and_max(Point3i::ZERO, Point3i::fill(5)));
+
+        let mut a_array = Array3x1::fill(domain, false);
+        UpdateExtentTest::fill_bool_array(&a, &mut a_array);
+        let mut b_array = Array3x1::fill(domain, false);
+        UpdateExtentTest::fill_bool_array(&b, &mut b_array);
+
+        let assert_operation = |result: OctreeSet, operation: fn(bool, bool) -> bool| {
+            result.assert_all_nodes_reachable();
+
+            let expected_array =
+                Array3x1::fill_with(domain, |p| operation(a_array.get(p), b_array.get(p)));
+            assert_eq!(result, OctreeSet::from_array3(&expected_array, domain));
+        };
+
+        assert_operation(a.union(&b), |a, b| a || b);
+        assert_operation(a.intersection(&b), |a, b| a && b);
+        assert_operation(a.difference(&b), |a, b| a && !b);
+        assert_operation(b.difference(&a), |a, b| b && !a);
+        assert_operation(a.symmetric_difference(&b), |a, b| a != b);
+    }
+
+    #[test]
+    fn set_operations_with_trivial_sets() {
+        let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let empty = OctreeSet::new_empty(domain);
+        let full = OctreeSet::new_full(domain);
+        let set = OctreeSet::from_array3(&random_bool_array(domain), domain);
+
+        assert_eq!(set.union(&empty), set);
+        assert_eq!(set.union(&full), full);
+        assert_eq!(set.intersection(&full), set);
+        assert!(set.intersection(&empty).is_empty());
+        assert!(set.difference(&set).is_empty());
+        assert!(set.symmetric_difference(&set).is_empty());
+        assert_eq!(full.difference(&set).union(&set), full);
+        assert_eq!(full.difference(&empty), full);
+    }
+
+    #[test]
+    fn contains_point_matches_array() {
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+        let mut array = random_bool_array(domain);
+        // Make sure there is a full branch.
+        array.fill_extent(
+            &Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(8)),
+            true,
+        );
+        let set = OctreeSet::from_array3(&array, domain);
+
+        for p in domain.iter_points() {
+            assert_eq!(set.contains_point(p), array.get(p), "p = {:?}", p);
+        }
+        assert!(!set.contains_point(Point3i::fill(8)));
+        assert!(OctreeSet::new_full(domain).contains_point(Point3i::ZERO));
+        assert!(!OctreeSet::new_empty(domain).contains_point(Point3i::ZERO));
+    }
+
+    #[test]
+    fn visit_octants_intersecting_sphere() {
+        let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+        let mut set = OctreeSet::from_array3(&random_bool_array(domain), domain);
+        set.add_extent(&Extent3i::from_min_and_shape(
+            Point3i::fill(8),
+            Point3i::fill(8),
+        ));
+
+        let sphere = Sphere {
+            center: PointN([10.0, 12.0, 9.0]),
+            radius: 7.0,
+        };
+
+        let mut visited_points = HashSet::new();
+        set.visit_octants_intersecting(&sphere, &mut |node: &OctreeNode, containment| {
+            assert_ne!(containment, Containment::Outside);
+            if node.octant().is_single_voxel() {
+                visited_points.insert(node.octant().minimum());
+            }
+
+            VisitStatus::Continue
+        });
+
+        let expected_points: HashSet<_> = set
+            .collect_all_points()
+            .into_iter()
+            .filter(|p| {
+                let voxel = Extent3f::from_min_and_shape(Point3f::from(*p), Point3f::fill(1.0));
+
+                sphere.classify_extent(&voxel) != Containment::Outside
+            })
+            .collect();
+        assert_eq!(visited_points, expected_points);
+    }
+
+    fn random_bool_array(extent: Extent3i) -> Array3x1<bool> {
+        let mut rng = rand::thread_rng();
+
+        Array3x1::fill_with(extent, |_| rng.gen())
+    }
+
     #[test]
     fn octants_occupied_iff_not_empty() {
         let voxels = random_voxels();
@@ -0,0 +1,320 @@
+//! A compact binary encoding of `OctreeSet`, for storage and networking.
+//!
+//! The serde representation of an `OctreeSet` is its hash map of branch nodes, so every node costs at least a 2-byte
+//! `LocationCode` and a 1-byte child bitmask, and much more in self-describing formats. But the location codes are implied by
+//! the shape of the tree, so this encoding only stores the bitmasks, in a pre-order traversal of the branch nodes:
+//!
+//! - The header is the `COMPACT_OCTREE_SET_MAGIC` bytes, a version byte, the extent minimum as three little-endian `i32`s, the
+//!   power of the edge length, and a root byte that's `0` for an empty set, `1` for a full set, or `2` for a root branch.
+//! - Then every branch writes 8 bits for the children that exist, followed by 1 bit for each existing child that says
+//!   whether it's a branch or a fat leaf. Children of the lowest branches are single voxels, so they don't need that bit.
+//!   Each branch child is written right after its parent.
+//!
+//! The bits are packed least significant first, and the last byte is padded with zeros.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{octree::set::*, prelude::*};
+//!
+//! let domain = Extent3i::from_min_and_shape(Point3i::fill(-32), Point3i::fill(64));
+//! let mut set = OctreeSet::new_empty(domain);
+//! set.add_extent(&Extent3i::from_min_and_shape(Point3i::fill(-5), Point3i::fill(13)));
+//!
+//! let bytes = set.to_compact_bytes();
+//! assert!(bytes.len() < bincode::serialize(&set).unwrap().len() / 2);
+//! assert_eq!(OctreeSet::from_compact_bytes(&bytes).unwrap(), set);
+//! ```
+
+use super::{LocationCode, OctreeSet};
+use crate::SmallKeyHashMap;
+
+use building_blocks_core::prelude::*;
+
+use std::io;
+
+/// The first bytes of every compact `OctreeSet` encoding.
+pub const COMPACT_OCTREE_SET_MAGIC: [u8; 4] = *b"BBOS";
+
+/// The version of the compact encoding written by `OctreeSet::to_compact_bytes`. Decoding rejects any other version.
+pub const COMPACT_OCTREE_SET_VERSION: u8 = 1;
+
+const HEADER_BYTES: usize = 4 + 1 + 3 * 4 + 1 + 1;
+
+const ROOT_EMPTY: u8 = 0;
+const ROOT_FULL: u8 = 1;
+const ROOT_BRANCH: u8 = 2;
+
+impl OctreeSet {
+    /// Encodes this set in the compact format described in the `compact` module. It's usually less than half the size of the
+    /// bincode serialization.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES + self.nodes.len() * 2);
+        bytes.extend_from_slice(&COMPACT_OCTREE_SET_MAGIC);
+        bytes.push(COMPACT_OCTREE_SET_VERSION);
+        for c in self.extent.minimum.0.iter() {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+        bytes.push(self.power);
+
+        if !self.root_exists {
+            bytes.push(ROOT_EMPTY);
+        } else if self.nodes.contains_key(&LocationCode::ROOT) {
+            bytes.push(ROOT_BRANCH);
+            let mut writer = BitWriter::new(bytes);
+            self.write_branch(LocationCode::ROOT, self.power, &mut writer);
+            bytes = writer.finish();
+        } else {
+            bytes.push(ROOT_FULL);
+        }
+
+        bytes
+    }
+
+    fn write_branch(&self, code: LocationCode, level: u8, writer: &mut BitWriter) {
+        let child_bitmask = self.nodes[&code];
+        writer.write_bits(child_bitmask, 8);
+        if level == 1 {
+            return;
+        }
+
+        let mut branch_children = 0;
+        for child_index in 0..8 {
+            if child_bitmask & (1 << child_index) == 0 {
+                continue;
+            }
+            let child_code = code.extend().with_lowest_octant(child_index as u16);
+            let is_branch = self.nodes.contains_key(&child_code);
+            writer.write_bits(is_branch as u8, 1);
+            branch_children |= (is_branch as u8) << child_index;
+        }
+        for child_index in 0..8 {
+            if branch_children & (1 << child_index) != 0 {
+                let child_code = code.extend().with_lowest_octant(child_index as u16);
+                self.write_branch(child_code, level - 1, writer);
+            }
+        }
+    }
+
+    /// Decodes a set from bytes written by `to_compact_bytes`.
+    ///
+    /// Returns an error of kind `InvalidData` if the bytes are not a complete encoding of a set with a supported version.
+    pub fn from_compact_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_BYTES || bytes[0..4] != COMPACT_OCTREE_SET_MAGIC {
+            return Err(invalid_data("Not a compact OctreeSet encoding"));
+        }
+        if bytes[4] != COMPACT_OCTREE_SET_VERSION {
+            return Err(invalid_data("Unsupported compact OctreeSet version"));
+        }
+
+        let mut minimum = Point3i::ZERO;
+        for (c, c_bytes) in minimum.0.iter_mut().zip(bytes[5..17].chunks(4)) {
+            let mut le_bytes = [0; 4];
+            le_bytes.copy_from_slice(c_bytes);
+            *c = i32::from_le_bytes(le_bytes);
+        }
+        let power = bytes[17];
+        if power == 0 || power > 6 {
+            return Err(invalid_data("Compact OctreeSet power is out of range"));
+        }
+        let extent = Extent3i::from_min_and_shape(minimum, Point3i::fill(1 << power));
+
+        let body = &bytes[HEADER_BYTES..];
+        let (root_exists, nodes) = match bytes[HEADER_BYTES - 1] {
+            ROOT_EMPTY | ROOT_FULL if !body.is_empty() => {
+                return Err(invalid_data("Trailing bytes after compact OctreeSet"));
+            }
+            ROOT_EMPTY => (false, SmallKeyHashMap::default()),
+            ROOT_FULL => (true, SmallKeyHashMap::default()),
+            ROOT_BRANCH => {
+                let mut nodes = SmallKeyHashMap::default();
+                let mut reader = BitReader::new(body);
+                Self::read_branch(LocationCode::ROOT, power, &mut reader, &mut nodes)?;
+                if reader.bytes_read() != body.len() {
+                    return Err(invalid_data("Trailing bytes after compact OctreeSet"));
+                }
+
+                (true, nodes)
+            }
+            _ => return Err(invalid_data("Invalid compact OctreeSet root")),
+        };
+
+        Ok(Self {
+            extent,
+            power,
+            root_exists,
+            nodes,
+        })
+    }
+
+    fn read_branch(
+        code: LocationCode,
+        level: u8,
+        reader: &mut BitReader,
+        nodes: &mut SmallKeyHashMap<LocationCode, u8>,
+    ) -> io::Result<()> {
+        let child_bitmask = reader.read_bits(8)?;
+        nodes.insert(code, child_bitmask);
+        if level == 1 {
+            return Ok(());
+        }
+
+        let mut branch_children = 0;
+        for child_index in 0..8 {
+            if child_bitmask & (1 << child_index) != 0 {
+                branch_children |= reader.read_bits(1)? << child_index;
+            }
+        }
+        for child_index in 0..8 {
+            if branch_children & (1 << child_index) != 0 {
+                let child_code = code.extend().with_lowest_octant(child_index as u16);
+                Self::read_branch(child_code, level - 1, reader, nodes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Appends bits to a byte buffer, least significant first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    num_bits: u32,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, num_bits: 0 }
+    }
+
+    /// Writes the lowest `count` bits of `value`, where `count <= 8`.
+    fn write_bits(&mut self, value: u8, count: u32) {
+        let value = u16::from(value) & ((1 << count) - 1);
+        let offset = self.num_bits % 8;
+        if offset == 0 {
+            self.bytes.push(0);
+        }
+        let shifted = value << offset;
+        *self.bytes.last_mut().unwrap() |= shifted as u8;
+        if offset + count > 8 {
+            self.bytes.push((shifted >> 8) as u8);
+        }
+        self.num_bits += count;
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    num_bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, num_bits: 0 }
+    }
+
+    /// Reads the next `count` bits, where `count <= 8`.
+    fn read_bits(&mut self, count: usize) -> io::Result<u8> {
+        if self.num_bits + count > self.bytes.len() * 8 {
+            return Err(invalid_data("Compact OctreeSet ended unexpectedly"));
+        }
+        let byte_index = self.num_bits / 8;
+        let offset = self.num_bits % 8;
+        let mut window = u16::from(self.bytes[byte_index]);
+        if offset + count > 8 {
+            window |= u16::from(self.bytes[byte_index + 1]) << 8;
+        }
+        self.num_bits += count;
+
+        Ok(((window >> offset) & ((1 << count) - 1)) as u8)
+    }
+
+    fn bytes_read(&self) -> usize {
+        (self.num_bits + 7) / 8
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn round_trips_every_root_state() {
+        let domain = Extent3i::from_min_and_shape(PointN([-8, 0, 24]), Point3i::fill(16));
+
+        let empty = OctreeSet::new_empty(domain);
+        let full = OctreeSet::new_full(domain);
+        let mut mixed = OctreeSet::new_empty(domain);
+        mixed.add_extent(&Extent3i::from_min_and_shape(
+            PointN([-7, 3, 30]),
+            PointN([9, 5, 2]),
+        ));
+
+        for set in [empty, full, mixed].iter() {
+            let bytes = set.to_compact_bytes();
+            assert_eq!(&OctreeSet::from_compact_bytes(&bytes).unwrap(), set);
+        }
+        assert_eq!(
+            OctreeSet::new_full(domain).to_compact_bytes().len(),
+            HEADER_BYTES
+        );
+    }
+
+    #[test]
+    fn sphere_is_much_smaller_than_bincode() {
+        let domain = Extent3i::from_min_and_shape(Point3i::fill(-32), Point3i::fill(64));
+        let voxels = Array3x1::fill_with(domain, |p: Point3i| p.dot(p) < 25 * 25);
+        let set = OctreeSet::from_array3(&voxels, domain);
+
+        let bytes = set.to_compact_bytes();
+        assert_eq!(OctreeSet::from_compact_bytes(&bytes).unwrap(), set);
+        assert!(bytes.len() * 2 < bincode::serialize(&set).unwrap().len());
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        let domain = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut set = OctreeSet::new_empty(domain);
+        set.add_extent(&Extent3i::from_min_and_shape(
+            Point3i::fill(1),
+            Point3i::fill(3),
+        ));
+        let bytes = set.to_compact_bytes();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = COMPACT_OCTREE_SET_VERSION + 1;
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let truncated = &bytes[..bytes.len() - 1];
+
+        for invalid in [
+            &wrong_version[..],
+            &wrong_magic[..],
+            &trailing[..],
+            truncated,
+        ]
+        .iter()
+        {
+            let error = OctreeSet::from_compact_bytes(invalid).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,511 @@
+//! A sparse voxel octree (SVO) that stores a value for every voxel, unlike the `OctreeSet`, which only stores whether each
+//! voxel is present.
+//!
+//! Any octant where all voxels have the same value is collapsed into a single leaf node, so big uniform regions like air or
+//! solid rock are cheap. The nodes are stored in one flat `Vec`, where each branch refers to its children by index, which makes
+//! the whole tree easy to upload into a GPU buffer for ray tracing.
+//!
+//! # DAG Compression
+//!
+//! Static scenes often contain many identical subtrees, e.g. repeated building blocks or the same patch of terrain.
+//! `SparseVoxelOctree::dag_compressed` merges all identical subtrees into one, turning the tree into a directed acyclic graph
+//! (DAG). All queries work the same on the compressed octree.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, SparseVoxelOctree};
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! // A checkerboard of 4x4x4 blocks, where every black block has the same pattern inside.
+//! let voxels = Array3x1::fill_with(extent, |p| {
+//!     let block = p >> 2;
+//!     let black = (block.x() + block.y() + block.z()) % 2 == 0;
+//!
+//!     if black && p.x() % 2 == 0 { 2u8 } else if black { 1 } else { 0 }
+//! });
+//!
+//! let svo = SparseVoxelOctree::from_map(&voxels, extent);
+//! let dag = svo.dag_compressed();
+//! assert!(dag.num_nodes() < svo.num_nodes());
+//! assert_eq!(dag.get(PointN([4, 4, 0])), Some(&2));
+//! assert_eq!(dag.to_array3(), voxels);
+//!
+//! // Find the first voxel that isn't empty.
+//! let hit = dag
+//!     .cast_ray(PointN([0.5, 4.5, 0.5]), PointN([1.0, 0.0, 0.0]), f32::MAX, |v| *v != 0)
+//!     .unwrap();
+//! assert_eq!(hit.voxel, PointN([4, 4, 0]));
+//! assert_eq!(hit.normal, PointN([-1, 0, 0]));
+//! assert_eq!(hit.t, 3.5);
+//! ```
+//!
+//! Any map that implements `Get` can be converted into an octree, including a `ChunkMapLodView`, and the octree can be written
+//! back into any `FillExtent` map with `SparseVoxelOctree::copy_into`.
+
+use super::ray::{OctreeRay, OctreeRayHit, RayEntry};
+use crate::{Array3x1, FillExtent, Get, SmallKeyHashMap};
+
+use building_blocks_core::prelude::*;
+
+use core::hash::Hash;
+use serde::{Deserialize, Serialize};
+
+/// A node of a `SparseVoxelOctree`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum SvoNode<T> {
+    /// An octant where every voxel has the same value.
+    Leaf(T),
+    /// The indices of the 8 child nodes, in the order of `Point3i::CUBE_CORNER_OFFSETS`.
+    Branch([u32; 8]),
+}
+
+/// A sparse voxel octree whose leaves carry values. See the [module docs](self) for details.
+///
+/// The octree is a cube shape and the edge length can only be a power of 2.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SparseVoxelOctree<T> {
+    extent: Extent3i,
+    power: u8,
+    root: u32,
+    nodes: Vec<SvoNode<T>>,
+}
+
+/// The first leaf that satisfied the predicate of `SparseVoxelOctree::cast_ray`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvoRayHit<'a, T> {
+    /// The ray parameter where the ray enters the leaf, in units of the ray's direction.
+    pub t: f32,
+    /// The voxel where the ray enters the leaf.
+    pub voxel: Point3i,
+    /// The normal of the face of the leaf that the ray entered through, or zero if the ray started inside of the leaf.
+    pub normal: Point3i,
+    /// The extent of the whole leaf octant.
+    pub leaf_extent: Extent3i,
+    pub value: &'a T,
+}
+
+/// The result of building an octant, before it's known whether the parent collapses.
+enum Built<T> {
+    Uniform(T),
+    Node(u32),
+}
+
+impl<T> SparseVoxelOctree<T> {
+    /// The exponent P such that `self.edge_length() = 2 ^ P`.
+    pub fn power(&self) -> u8 {
+        self.power
+    }
+
+    /// The length of any edge of the root octant.
+    pub fn edge_length(&self) -> i32 {
+        1 << self.power
+    }
+
+    pub fn extent(&self) -> &Extent3i {
+        &self.extent
+    }
+
+    /// All nodes, including any that are shared by multiple parents after DAG compression.
+    pub fn nodes(&self) -> &[SvoNode<T>] {
+        &self.nodes
+    }
+
+    /// The index of the root node in `nodes`.
+    pub fn root_index(&self) -> u32 {
+        self.root
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The value at `p`, or `None` if `p` is outside of the octree.
+    pub fn get(&self, p: Point3i) -> Option<&T> {
+        if !self.extent.contains(p) {
+            return None;
+        }
+
+        let local = p - self.extent.minimum;
+        let mut half_edge = self.edge_length() >> 1;
+        let mut node = self.root;
+        loop {
+            match &self.nodes[node as usize] {
+                SvoNode::Leaf(value) => return Some(value),
+                SvoNode::Branch(children) => {
+                    let child = ((local.x() & half_edge) != 0) as usize
+                        | (((local.y() & half_edge) != 0) as usize) << 1
+                        | (((local.z() & half_edge) != 0) as usize) << 2;
+                    node = children[child];
+                    half_edge >>= 1;
+                }
+            }
+        }
+    }
+
+    /// Calls `visitor` on the extent and value of every leaf octant. Leaves that are shared after DAG compression are visited
+    /// once for every octant they occupy.
+    pub fn visit_leaves(&self, mut visitor: impl FnMut(&Extent3i, &T)) {
+        self.visit_leaves_recursive(self.root, self.extent, &mut visitor);
+    }
+
+    fn visit_leaves_recursive(
+        &self,
+        node: u32,
+        extent: Extent3i,
+        visitor: &mut impl FnMut(&Extent3i, &T),
+    ) {
+        match &self.nodes[node as usize] {
+            SvoNode::Leaf(value) => visitor(&extent, value),
+            SvoNode::Branch(children) => {
+                for (&child, child_extent) in children.iter().zip(child_extents(&extent).iter()) {
+                    self.visit_leaves_recursive(child, *child_extent, visitor);
+                }
+            }
+        }
+    }
+
+    /// Writes every value into `dst`, filling each leaf's whole extent at once.
+    pub fn copy_into<Dst>(&self, dst: &mut Dst)
+    where
+        Dst: FillExtent<[i32; 3], Item = T>,
+        T: Clone,
+    {
+        self.visit_leaves(|extent, value| dst.fill_extent(extent, value.clone()));
+    }
+
+    /// Copies the whole octree into a new array.
+    pub fn to_array3(&self) -> Array3x1<T>
+    where
+        T: Clone,
+    {
+        // Any value works for initializing the array, since every point gets overwritten.
+        let mut first_leaf = self.root;
+        while let SvoNode::Branch(children) = &self.nodes[first_leaf as usize] {
+            first_leaf = children[0];
+        }
+        let fill_value = match &self.nodes[first_leaf as usize] {
+            SvoNode::Leaf(value) => value.clone(),
+            SvoNode::Branch(_) => unreachable!(),
+        };
+
+        let mut array = Array3x1::fill(self.extent, fill_value);
+        self.copy_into(&mut array);
+
+        array
+    }
+
+    /// Finds the first leaf along the ray from `origin` in `direction` whose value satisfies `predicate`, e.g. the first
+    /// voxel that isn't empty. Only hits with `0 <= t <= max_t` are returned, and uniform leaves that don't satisfy
+    /// `predicate` are skipped without visiting their voxels.
+    pub fn cast_ray(
+        &self,
+        origin: Point3f,
+        direction: Point3f,
+        max_t: f32,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Option<SvoRayHit<T>> {
+        let ray = OctreeRay::new(origin, direction, max_t);
+        let entry = ray.enter_extent(&self.extent)?;
+
+        self.cast_ray_recursive(self.root, self.extent, entry, &ray, &mut predicate)
+    }
+
+    fn cast_ray_recursive(
+        &self,
+        node: u32,
+        extent: Extent3i,
+        entry: RayEntry,
+        ray: &OctreeRay,
+        predicate: &mut impl FnMut(&T) -> bool,
+    ) -> Option<SvoRayHit<T>> {
+        match &self.nodes[node as usize] {
+            SvoNode::Leaf(value) => {
+                if !predicate(value) {
+                    return None;
+                }
+                let OctreeRayHit {
+                    t,
+                    voxel,
+                    normal,
+                    leaf_extent,
+                } = ray.hit_leaf(entry, extent);
+
+                Some(SvoRayHit {
+                    t,
+                    voxel,
+                    normal,
+                    leaf_extent,
+                    value,
+                })
+            }
+            SvoNode::Branch(children) => ray.find_first_hit_in_children(
+                children
+                    .iter()
+                    .cloned()
+                    .zip(child_extents(&extent).iter().cloned()),
+                |child, child_extent, child_entry| {
+                    self.cast_ray_recursive(child, child_extent, child_entry, ray, predicate)
+                },
+            ),
+        }
+    }
+}
+
+impl<T> SparseVoxelOctree<T>
+where
+    T: Clone + PartialEq,
+{
+    /// Builds an octree from the values of `map` in `extent`, which must be a cube with an edge length that's a power of 2.
+    pub fn from_map<M>(map: &M, extent: Extent3i) -> Self
+    where
+        M: Get<Point3i, Item = T>,
+    {
+        assert!(extent.shape.dimensions_are_powers_of_2());
+        assert!(extent.shape.is_cube());
+        let power = extent.shape.x().trailing_zeros() as u8;
+
+        let mut nodes = Vec::new();
+        let root = match Self::build(map, extent, &mut nodes) {
+            Built::Uniform(value) => push_node(&mut nodes, SvoNode::Leaf(value)),
+            Built::Node(index) => index,
+        };
+
+        Self {
+            extent,
+            power,
+            root,
+            nodes,
+        }
+    }
+
+    fn build<M>(map: &M, extent: Extent3i, nodes: &mut Vec<SvoNode<T>>) -> Built<T>
+    where
+        M: Get<Point3i, Item = T>,
+    {
+        if extent.shape.x() == 1 {
+            return Built::Uniform(map.get(extent.minimum));
+        }
+
+        let children: Vec<Built<T>> = child_extents(&extent)
+            .iter()
+            .map(|child_extent| Self::build(map, *child_extent, nodes))
+            .collect();
+
+        if let Built::Uniform(first) = &children[0] {
+            let all_same = children.iter().all(|child| match child {
+                Built::Uniform(value) => value == first,
+                Built::Node(_) => false,
+            });
+            if all_same {
+                return Built::Uniform(first.clone());
+            }
+        }
+
+        let mut child_indices = [0; 8];
+        for (dst, child) in child_indices.iter_mut().zip(children.into_iter()) {
+            *dst = match child {
+                Built::Uniform(value) => push_node(nodes, SvoNode::Leaf(value)),
+                Built::Node(index) => index,
+            };
+        }
+
+        Built::Node(push_node(nodes, SvoNode::Branch(child_indices)))
+    }
+}
+
+impl<T> SparseVoxelOctree<T>
+where
+    T: Clone + Eq + Hash,
+{
+    /// Returns an equivalent octree where all identical subtrees are stored only once.
+    pub fn dag_compressed(&self) -> Self {
+        let mut nodes = Vec::new();
+        let mut unique_nodes = SmallKeyHashMap::default();
+        // Nodes that are already shared only need to be compressed once.
+        let mut new_indices = vec![None; self.nodes.len()];
+        let root = self.compress_node(self.root, &mut nodes, &mut unique_nodes, &mut new_indices);
+
+        Self {
+            extent: self.extent,
+            power: self.power,
+            root,
+            nodes,
+        }
+    }
+
+    fn compress_node(
+        &self,
+        node: u32,
+        nodes: &mut Vec<SvoNode<T>>,
+        unique_nodes: &mut SmallKeyHashMap<SvoNode<T>, u32>,
+        new_indices: &mut [Option<u32>],
+    ) -> u32 {
+        if let Some(index) = new_indices[node as usize] {
+            return index;
+        }
+
+        let compressed = match &self.nodes[node as usize] {
+            SvoNode::Leaf(value) => SvoNode::Leaf(value.clone()),
+            SvoNode::Branch(children) => {
+                let mut new_children = [0; 8];
+                for (dst, &child) in new_children.iter_mut().zip(children.iter()) {
+                    *dst = self.compress_node(child, nodes, unique_nodes, new_indices);
+                }
+                SvoNode::Branch(new_children)
+            }
+        };
+        let index = if let Some(&index) = unique_nodes.get(&compressed) {
+            index
+        } else {
+            let index = push_node(nodes, compressed.clone());
+            unique_nodes.insert(compressed, index);
+
+            index
+        };
+        new_indices[node as usize] = Some(index);
+
+        index
+    }
+}
+
+fn push_node<T>(nodes: &mut Vec<SvoNode<T>>, node: SvoNode<T>) -> u32 {
+    let index = nodes.len() as u32;
+    nodes.push(node);
+
+    index
+}
+
+/// The extents of the 8 children of the octant `extent`, in the order of `Point3i::CUBE_CORNER_OFFSETS`.
+fn child_extents(extent: &Extent3i) -> [Extent3i; 8] {
+    let half_shape = extent.shape >> 1;
+    let mut extents = [*extent; 8];
+    for (dst, &offset) in extents.iter_mut().zip(Point3i::CUBE_CORNER_OFFSETS.iter()) {
+        *dst = Extent3i::from_min_and_shape(extent.minimum + offset * half_shape, half_shape);
+    }
+
+    extents
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn uniform_octants_collapse_into_leaves() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+        let mut voxels = Array3x1::fill(extent, 0u16);
+        *voxels.get_mut(PointN([7, 7, 7])) = 5;
+
+        let svo = SparseVoxelOctree::from_map(&voxels, extent);
+        // A branch on each of the 4 levels above the voxel. Each has 7 uniform leaves next to the branch below it, except the
+        // last one, which has 8 single-voxel leaves.
+        assert_eq!(svo.num_nodes(), 4 + 3 * 7 + 8);
+        assert_eq!(svo.get(PointN([7, 7, 7])), Some(&5));
+        assert_eq!(svo.get(PointN([-8, 0, 3])), Some(&0));
+        assert_eq!(svo.get(PointN([8, 0, 0])), None);
+        assert_eq!(svo.to_array3(), voxels);
+
+        let uniform = SparseVoxelOctree::from_map(&Array3x1::fill(extent, 1u16), extent);
+        assert_eq!(uniform.num_nodes(), 1);
+        assert_eq!(uniform.get(Point3i::ZERO), Some(&1));
+    }
+
+    #[test]
+    fn chunk_map_round_trip_through_dag() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+        let voxels = Array3x1::fill_with(extent, |p| ((p.x() / 3 + p.y() / 5 + p.z()) % 4) as u8);
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(8), 0);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &voxels, &mut map.lod_view_mut(0));
+
+        let svo = SparseVoxelOctree::from_map(&map.lod_view(0), extent);
+        let dag = svo.dag_compressed();
+        assert!(dag.num_nodes() <= svo.num_nodes());
+        // Compressing again doesn't find anything new.
+        assert_eq!(dag.dag_compressed().num_nodes(), dag.num_nodes());
+
+        let mut copy = builder.build_with_hash_map_storage();
+        dag.copy_into(&mut copy.lod_view_mut(0));
+        for p in extent.iter_points() {
+            assert_eq!(copy.lod_view(0).get(p), voxels.get(p));
+            assert_eq!(dag.get(p), Some(&voxels.get(p)));
+        }
+    }
+
+    #[test]
+    fn ray_skips_empty_octants_and_respects_max_t() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+        let mut voxels = Array3x1::fill(extent, false);
+        *voxels.get_mut(PointN([12, 3, 9])) = true;
+        *voxels.get_mut(PointN([2, 3, 9])) = true;
+        let svo = SparseVoxelOctree::from_map(&voxels, extent);
+
+        // From outside of the octree, hits the nearer voxel first.
+        let hit = svo
+            .cast_ray(
+                PointN([20.0, 3.5, 9.5]),
+                PointN([-1.0, 0.0, 0.0]),
+                f32::MAX,
+                |v| *v,
+            )
+            .unwrap();
+        assert_eq!(hit.voxel, PointN([12, 3, 9]));
+        assert_eq!(hit.normal, PointN([1, 0, 0]));
+        assert_eq!(hit.t, 7.0);
+        assert_eq!(hit.leaf_extent.shape, Point3i::fill(1));
+
+        // A diagonal ray.
+        let hit = svo
+            .cast_ray(
+                PointN([0.5, 1.5, 7.5]),
+                PointN([1.0, 1.0, 1.0]),
+                f32::MAX,
+                |v| *v,
+            )
+            .unwrap();
+        assert_eq!(hit.voxel, PointN([2, 3, 9]));
+        assert_eq!(hit.t, 1.5);
+
+        // Starting inside of an occupied voxel.
+        let hit = svo
+            .cast_ray(
+                PointN([2.5, 3.2, 9.7]),
+                PointN([0.0, 1.0, 0.0]),
+                f32::MAX,
+                |v| *v,
+            )
+            .unwrap();
+        assert_eq!(hit.t, 0.0);
+        assert_eq!(hit.normal, Point3i::ZERO);
+
+        // Too short.
+        assert!(svo
+            .cast_ray(
+                PointN([20.0, 3.5, 9.5]),
+                PointN([-1.0, 0.0, 0.0]),
+                6.0,
+                |v| *v
+            )
+            .is_none());
+
+        // Misses.
+        assert!(svo
+            .cast_ray(
+                PointN([0.5, 5.5, 9.5]),
+                PointN([1.0, 0.0, 0.0]),
+                f32::MAX,
+                |v| *v
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,410 @@
+//! Composable signed distance field primitives and constructive solid geometry (CSG) operations.
+//!
+//! Every SDF is just a closure `Fn(Point3f) -> f32`, where negative distances are inside of the solid. Primitives can be
+//! combined with the CSG functions like `union` and `smooth_subtraction`, then either sampled with `lattice_sdf` to get a
+//! `Func` lattice map, or applied directly to existing arrays of signed distances with `edit_sdf_in_place`.
+//!
+//! Blocky voxel data can be converted into a signed distance field with `signed_distance_transform`.
+//!
+//! Keep in mind that `Sd8` and `Sd16` saturate outside of `[-1.0, 1.0]`, so only the distances near the surface are preserved.
+//! A `FixedSd` with a smaller scale keeps more of the field at the cost of precision.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, sdf::*};
+//!
+//! // A box with a spherical bite taken out of one corner, smoothly joined with a capsule.
+//! let shape = smooth_union(
+//!     subtraction(
+//!         axis_aligned_box(Point3f::ZERO, Point3f::fill(8.0)),
+//!         sphere(Point3f::fill(8.0), 5.0),
+//!     ),
+//!     capsule(PointN([-8.0, 8.0, 0.0]), PointN([8.0, 8.0, 0.0]), 2.0),
+//!     2.0,
+//! );
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-16), Point3i::fill(32));
+//! let mut samples = Array3x1::fill(extent, Sd8::ONE);
+//! copy_extent(&extent, &lattice_sdf(shape), &mut samples);
+//! assert!(samples.get(Point3i::ZERO).is_negative());
+//!
+//! // Later, carve a tunnel through the existing samples.
+//! let tunnel = capsule(PointN([0.0, 0.0, -20.0]), PointN([0.0, 0.0, 20.0]), 3.0);
+//! edit_sdf_in_place(&mut samples, &extent, CsgOperation::Subtraction, tunnel);
+//! assert!(!samples.get(Point3i::ZERO).is_negative());
+//! ```
+
+mod distance_transform;
+
+pub use distance_transform::*;
+
+use crate::prelude::*;
+
+use building_blocks_core::prelude::*;
+
+/// A binary CSG operation on two signed distances, `a` (the existing solid) and `b` (the solid being applied).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOperation {
+    /// `a` or `b`.
+    Union,
+    /// `a` but not `b`.
+    Subtraction,
+    /// `a` and `b`.
+    Intersection,
+    /// Like `Union`, but the seam is blended over the given distance.
+    SmoothUnion(f32),
+    /// Like `Subtraction`, but the seam is blended over the given distance.
+    SmoothSubtraction(f32),
+    /// Like `Intersection`, but the seam is blended over the given distance.
+    SmoothIntersection(f32),
+}
+
+impl CsgOperation {
+    /// Combine the signed distances `a` and `b`.
+    #[inline]
+    pub fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            CsgOperation::Union => a.min(b),
+            CsgOperation::Subtraction => a.max(-b),
+            CsgOperation::Intersection => a.max(b),
+            CsgOperation::SmoothUnion(k) => {
+                let h = (0.5 + 0.5 * (b - a) / k).max(0.0).min(1.0);
+
+                mix(b, a, h) - k * h * (1.0 - h)
+            }
+            CsgOperation::SmoothSubtraction(k) => {
+                let h = (0.5 - 0.5 * (a + b) / k).max(0.0).min(1.0);
+
+                mix(a, -b, h) + k * h * (1.0 - h)
+            }
+            CsgOperation::SmoothIntersection(k) => {
+                let h = (0.5 - 0.5 * (b - a) / k).max(0.0).min(1.0);
+
+                mix(b, a, h) + k * h * (1.0 - h)
+            }
+        }
+    }
+}
+
+#[inline]
+fn mix(x: f32, y: f32, t: f32) -> f32 {
+    x * (1.0 - t) + y * t
+}
+
+// ██████╗ ██████╗ ██╗███╗   ███╗██╗████████╗██╗██╗   ██╗███████╗███████╗
+// ██╔══██╗██╔══██╗██║████╗ ████║██║╚══██╔══╝██║██║   ██║██╔════╝██╔════╝
+// ██████╔╝██████╔╝██║██╔████╔██║██║   ██║   ██║██║   ██║█████╗  ███████╗
+// ██╔═══╝ ██╔══██╗██║██║╚██╔╝██║██║   ██║   ██║╚██╗ ██╔╝██╔══╝  ╚════██║
+// ██║     ██║  ██║██║██║ ╚═╝ ██║██║   ██║   ██║ ╚████╔╝ ███████╗███████║
+// ╚═╝     ╚═╝  ╚═╝╚═╝╚═╝     ╚═╝╚═╝   ╚═╝   ╚═╝  ╚═══╝  ╚══════╝╚══════╝
+
+/// A sphere.
+pub fn sphere(center: Point3f, radius: f32) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| (p - center).norm() - radius
+}
+
+/// A box aligned with the coordinate axes. `half_extents` is the distance from the center to each face.
+pub fn axis_aligned_box(center: Point3f, half_extents: Point3f) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| {
+        let q = (p - center).abs() - half_extents;
+        let outside = q.map_components_unary(|c| c.max(0.0)).norm();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+
+        outside + inside
+    }
+}
+
+/// A line segment from `a` to `b`, inflated by `radius`. If `a == b`, this is a sphere.
+pub fn capsule(a: Point3f, b: Point3f, radius: f32) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| {
+        let pa = p - a;
+        let ba = b - a;
+        let baba = ba.dot(ba);
+        let h = if baba > 0.0 {
+            (pa.dot(ba) / baba).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+
+        (pa - ba * h).norm() - radius
+    }
+}
+
+/// A cylinder of `radius` around the line segment from `a` to `b`, with flat caps at both ends. If
+/// `a == b`, this is a flat disk of `radius` with no axis to orient it, so the distance is measured
+/// from the ball of `radius` around `a` and is never negative.
+pub fn cylinder(a: Point3f, b: Point3f, radius: f32) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| {
+        let ba = b - a;
+        let pa = p - a;
+        let baba = ba.dot(ba);
+        if baba == 0.0 {
+            return (pa.norm() - radius).max(0.0);
+        }
+        let paba = pa.dot(ba);
+
+        // Radial and axial distances outside of the cylinder, both scaled by `baba`.
+        let x = (pa * baba - ba * paba).norm() - radius * baba;
+        let y = (paba - baba * 0.5).abs() - baba * 0.5;
+        let x2 = x * x;
+        let y2 = y * y * baba;
+
+        let d = if x.max(y) < 0.0 {
+            -x2.min(y2)
+        } else {
+            (if x > 0.0 { x2 } else { 0.0 }) + (if y > 0.0 { y2 } else { 0.0 })
+        };
+
+        d.signum() * d.abs().sqrt() / baba
+    }
+}
+
+/// A cone with a circular base of `radius` centered at `base`, narrowing to a point at `apex`. If
+/// `base == apex`, this is a flat disk, measured the same way as a [`cylinder`] with coincident ends.
+pub fn cone(base: Point3f, apex: Point3f, radius: f32) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| {
+        let ba = apex - base;
+        let pa = p - base;
+        let baba = ba.dot(ba);
+        let paba = if baba > 0.0 { pa.dot(ba) / baba } else { 0.0 };
+        // Distance from the axis.
+        let x = (pa.dot(pa) - paba * paba * baba).max(0.0).sqrt();
+
+        // Vector to the nearest cap, in (radial, axial) coordinates.
+        let cax = (x - if paba < 0.5 { radius } else { 0.0 }).max(0.0);
+        let cay = (paba - 0.5).abs() - 0.5;
+
+        // Vector to the nearest point on the slanted side.
+        let f = ((radius * (radius - x) + paba * baba) / (radius * radius + baba))
+            .max(0.0)
+            .min(1.0);
+        let cbx = x - radius + f * radius;
+        let cby = paba - f;
+
+        let sign = if cbx < 0.0 && cay < 0.0 { -1.0 } else { 1.0 };
+
+        sign * (cax * cax + cay * cay * baba)
+            .min(cbx * cbx + cby * cby * baba)
+            .sqrt()
+    }
+}
+
+/// A torus around the Y axis. `major_radius` is the distance from the center to the middle of the tube, and `minor_radius` is
+/// the radius of the tube.
+pub fn torus(
+    center: Point3f,
+    major_radius: f32,
+    minor_radius: f32,
+) -> impl Fn(Point3f) -> f32 + Copy {
+    move |p| {
+        let p = p - center;
+        let ring_dist = (p.x() * p.x() + p.z() * p.z()).sqrt() - major_radius;
+
+        (ring_dist * ring_dist + p.y() * p.y()).sqrt() - minor_radius
+    }
+}
+
+/// The half-space behind the plane through `point` with the given `normal`. `normal` does not need to be a unit vector.
+pub fn plane(point: Point3f, normal: Point3f) -> impl Fn(Point3f) -> f32 + Copy {
+    let unit_normal = normal / normal.norm();
+
+    move |p| unit_normal.dot(p - point)
+}
+
+//  ██████╗███████╗ ██████╗
+// ██╔════╝██╔════╝██╔════╝
+// ██║     ███████╗██║  ███╗
+// ██║     ╚════██║██║   ██║
+// ╚██████╗███████║╚██████╔╝
+//  ╚═════╝╚══════╝ ╚═════╝
+
+/// Combine two SDFs with the given `operation`.
+pub fn combine<A, B>(operation: CsgOperation, a: A, b: B) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    move |p| operation.apply(a(p), b(p))
+}
+
+/// The set of points in `a` or `b`.
+pub fn union<A, B>(a: A, b: B) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::Union, a, b)
+}
+
+/// The set of points in `a` but not `b`.
+pub fn subtraction<A, B>(a: A, b: B) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::Subtraction, a, b)
+}
+
+/// The set of points in both `a` and `b`.
+pub fn intersection<A, B>(a: A, b: B) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::Intersection, a, b)
+}
+
+/// Like `union`, but the seam is blended over a distance of `k`.
+pub fn smooth_union<A, B>(a: A, b: B, k: f32) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::SmoothUnion(k), a, b)
+}
+
+/// Like `subtraction`, but the seam is blended over a distance of `k`.
+pub fn smooth_subtraction<A, B>(a: A, b: B, k: f32) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::SmoothSubtraction(k), a, b)
+}
+
+/// Like `intersection`, but the seam is blended over a distance of `k`.
+pub fn smooth_intersection<A, B>(a: A, b: B, k: f32) -> impl Fn(Point3f) -> f32 + Copy
+where
+    A: Fn(Point3f) -> f32 + Copy,
+    B: Fn(Point3f) -> f32 + Copy,
+{
+    combine(CsgOperation::SmoothIntersection(k), a, b)
+}
+
+// ███████╗ █████╗ ███╗   ███╗██████╗ ██╗     ██╗███╗   ██╗ ██████╗
+// ██╔════╝██╔══██╗████╗ ████║██╔══██╗██║     ██║████╗  ██║██╔════╝
+// ███████╗███████║██╔████╔██║██████╔╝██║     ██║██╔██╗ ██║██║  ███╗
+// ╚════██║██╔══██║██║╚██╔╝██║██╔═══╝ ██║     ██║██║╚██╗██║██║   ██║
+// ███████║██║  ██║██║ ╚═╝ ██║██║     ███████╗██║██║ ╚████║╚██████╔╝
+// ╚══════╝╚═╝  ╚═╝╚═╝     ╚═╝╚═╝     ╚══════╝╚═╝╚═╝  ╚═══╝ ╚═════╝
+
+/// A lattice map that samples `sdf` at every lattice point, converting the distance to `T`, e.g. `Sd8` or `f32`.
+pub fn lattice_sdf<T, S>(sdf: S) -> Func<impl Fn(Point3i) -> T>
+where
+    T: From<f32>,
+    S: Fn(Point3f) -> f32,
+{
+    Func(move |p: Point3i| T::from(sdf(Point3f::from(p))))
+}
+
+/// Replaces every signed distance `a` of `array` in `extent` with `operation.apply(a, sdf(p))`.
+pub fn edit_sdf_in_place<T>(
+    array: &mut Array3x1<T>,
+    extent: &Extent3i,
+    operation: CsgOperation,
+    sdf: impl Fn(Point3f) -> f32,
+) where
+    T: Copy + From<f32> + Into<f32>,
+{
+    array.for_each_mut(extent, |p: Point3i, value| {
+        *value = T::from(operation.apply((*value).into(), sdf(Point3f::from(p))));
+    });
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_distances() {
+        let s = sphere(Point3f::fill(1.0), 2.0);
+        assert_eq!(s(PointN([1.0, 4.0, 1.0])), 1.0);
+
+        let b = axis_aligned_box(Point3f::ZERO, Point3f::fill(1.0));
+        assert_eq!(b(Point3f::ZERO), -1.0);
+        assert_eq!(b(PointN([3.0, 0.0, 0.0])), 2.0);
+        assert_eq!(b(PointN([4.0, 5.0, 0.0])), 5.0);
+
+        let c = capsule(Point3f::ZERO, PointN([0.0, 10.0, 0.0]), 1.0);
+        assert_eq!(c(PointN([3.0, 5.0, 0.0])), 2.0);
+        assert_eq!(c(PointN([0.0, -3.0, 0.0])), 2.0);
+
+        let degenerate = capsule(Point3f::fill(1.0), Point3f::fill(1.0), 2.0);
+        assert_eq!(degenerate(Point3f::fill(1.0)), -2.0);
+        assert_eq!(
+            degenerate(PointN([1.0, 4.0, 1.0])),
+            s(PointN([1.0, 4.0, 1.0]))
+        );
+
+        let y = cylinder(Point3f::ZERO, PointN([0.0, 10.0, 0.0]), 1.0);
+        assert!((y(PointN([3.0, 5.0, 0.0])) - 2.0).abs() < 1e-5);
+        assert!((y(PointN([0.0, -3.0, 0.0])) - 3.0).abs() < 1e-5);
+        assert!((y(PointN([0.0, 9.5, 0.0])) + 0.5).abs() < 1e-5);
+        assert!((y(PointN([4.0, 14.0, 0.0])) - 5.0).abs() < 1e-5);
+
+        let flat_cylinder = cylinder(Point3f::fill(1.0), Point3f::fill(1.0), 2.0);
+        assert_eq!(flat_cylinder(Point3f::fill(1.0)), 0.0);
+        assert_eq!(flat_cylinder(PointN([1.0, 4.0, 1.0])), 1.0);
+
+        let k = cone(Point3f::ZERO, PointN([0.0, 10.0, 0.0]), 5.0);
+        assert!((k(PointN([0.0, -2.0, 0.0])) - 2.0).abs() < 1e-5);
+        assert!((k(PointN([0.0, 12.0, 0.0])) - 2.0).abs() < 1e-5);
+        assert!((k(PointN([0.0, 1.0, 0.0])) + 1.0).abs() < 1e-5);
+
+        let flat_cone = cone(Point3f::fill(1.0), Point3f::fill(1.0), 2.0);
+        assert_eq!(flat_cone(Point3f::fill(1.0)), 0.0);
+        assert_eq!(flat_cone(PointN([1.0, 4.0, 1.0])), 1.0);
+
+        let t = torus(Point3f::ZERO, 5.0, 1.0);
+        assert_eq!(t(PointN([5.0, 0.0, 0.0])), -1.0);
+        assert_eq!(t(PointN([0.0, 0.0, 8.0])), 2.0);
+
+        let p = plane(Point3f::fill(1.0), PointN([0.0, 2.0, 0.0]));
+        assert_eq!(p(PointN([7.0, 3.0, -2.0])), 2.0);
+    }
+
+    #[test]
+    fn csg_operations() {
+        let a = sphere(Point3f::ZERO, 2.0);
+        let b = sphere(PointN([3.0, 0.0, 0.0]), 2.0);
+        let p = PointN([1.5, 0.0, 0.0]);
+
+        assert_eq!(union(a, b)(p), -0.5);
+        assert_eq!(intersection(a, b)(p), -0.5);
+        assert_eq!(subtraction(a, b)(p), 0.5);
+
+        // Smooth operations agree with the sharp ones far from the seam.
+        let far = PointN([-1.0, 0.0, 0.0]);
+        assert_eq!(smooth_union(a, b, 0.5)(far), union(a, b)(far));
+        assert_eq!(smooth_subtraction(a, b, 0.5)(far), subtraction(a, b)(far));
+        // And blend the seam.
+        assert!(smooth_union(a, b, 1.0)(p) < union(a, b)(p));
+        assert!(smooth_intersection(a, b, 1.0)(p) > intersection(a, b)(p));
+    }
+
+    #[test]
+    fn edit_sd8_array_in_place() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+        let big_sphere = sphere(Point3f::ZERO, 6.0);
+        let mut samples = Array3x1::fill(extent, Sd8::ONE);
+        copy_extent(&extent, &lattice_sdf(big_sphere), &mut samples);
+
+        edit_sdf_in_place(
+            &mut samples,
+            &extent,
+            CsgOperation::Subtraction,
+            sphere(Point3f::ZERO, 3.0),
+        );
+
+        assert!(!samples.get(Point3i::ZERO).is_negative());
+        assert!(samples.get(PointN([4, 0, 0])).is_negative());
+        assert!(!samples.get(PointN([7, 0, 0])).is_negative());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::prelude::*;
+
+use building_blocks_core::prelude::*;
+
+/// Computes the exact Euclidean signed distance field of the binary voxels in `extent`, where `is_solid` determines which
+/// voxels are inside of the solid. This is useful for turning blocky data into smooth terrain with `surface_nets`.
+///
+/// The surface lies halfway between the centers of adjacent solid and empty voxels, so an empty voxel gets the distance from
+/// its center to the nearest solid voxel's center minus `0.5`, and a solid voxel gets the negation of the distance to the
+/// nearest empty voxel's center minus `0.5`. Voxels outside of `extent` are ignored.
+///
+/// Each distance (in voxels) is divided by `distance_scale` before being converted into `T`. Since `Sd8` and `Sd16` saturate
+/// outside of `[-1.0, 1.0]`, this determines how many voxels away from the surface the distance is preserved. Use `1.0` to
+/// get distances in voxels for `f32`.
+///
+/// This uses the separable algorithm of Felzenszwalb and Huttenlocher, "Distance Transforms of Sampled Functions," so it takes
+/// linear time in the number of voxels.
+///
+/// ```
+/// use building_blocks_core::prelude::*;
+/// use building_blocks_storage::{prelude::*, sdf::*};
+///
+/// let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+/// let mut blocks = Array3x1::fill(extent, false);
+/// blocks.fill_extent(&Extent3i::from_min_and_shape(Point3i::fill(4), Point3i::fill(8)), true);
+///
+/// let sdf: Array3x1<Sd8> = signed_distance_transform(&extent, 4.0, |p| blocks.get(p));
+/// assert!(sdf.get(Point3i::fill(8)).is_negative());
+/// assert!(!sdf.get(Point3i::fill(1)).is_negative());
+/// ```
+pub fn signed_distance_transform<T>(
+    extent: &Extent3i,
+    distance_scale: f32,
+    is_solid: impl Fn(Point3i) -> bool,
+) -> Array3x1<T>
+where
+    T: Copy + From<f32>,
+{
+    let solid = Array3x1::fill_with(*extent, is_solid);
+
+    let to_solid = squared_distance_transform(extent, |p| solid.get(p));
+    let to_empty = squared_distance_transform(extent, |p| !solid.get(p));
+
+    let mut sdf = Array3x1::fill(*extent, T::from(0.0));
+    sdf.for_each_mut(extent, |p: Point3i, value| {
+        let i = grid_index(extent, p);
+        let distance = if solid.get(p) {
+            0.5 - to_empty[i].sqrt()
+        } else {
+            to_solid[i].sqrt() - 0.5
+        };
+        *value = T::from(distance / distance_scale);
+    });
+
+    sdf
+}
+
+// A finite stand-in for infinity, so the parabola intersections don't produce NaN.
+const FAR: f32 = 1e20;
+
+fn grid_index(extent: &Extent3i, p: Point3i) -> usize {
+    let local = p - extent.minimum;
+
+    (local.x() + extent.shape.x() * (local.y() + extent.shape.y() * local.z())) as usize
+}
+
+// Returns the squared distance from each point of `extent` to the nearest point where `is_feature`, indexed by `grid_index`.
+// This is the same order as `extent.iter_points()`.
+fn squared_distance_transform(extent: &Extent3i, is_feature: impl Fn(Point3i) -> bool) -> Vec<f32> {
+    let mut grid: Vec<f32> = extent
+        .iter_points()
+        .map(|p| if is_feature(p) { 0.0 } else { FAR })
+        .collect();
+
+    let shape = extent.shape;
+    let (sx, sy, sz) = (shape.x() as usize, shape.y() as usize, shape.z() as usize);
+
+    let mut buffers = LineBuffers::new(sx.max(sy).max(sz));
+
+    // X is the fastest-changing coordinate of the grid.
+    for z in 0..sz {
+        for y in 0..sy {
+            buffers.transform_line(&mut grid, sx * (y + sy * z), 1, sx);
+        }
+    }
+    for z in 0..sz {
+        for x in 0..sx {
+            buffers.transform_line(&mut grid, x + sx * sy * z, sx, sy);
+        }
+    }
+    for y in 0..sy {
+        for x in 0..sx {
+            buffers.transform_line(&mut grid, x + sx * y, sx * sy, sz);
+        }
+    }
+
+    grid
+}
+
+struct LineBuffers {
+    f: Vec<f32>,
+    // Locations of the parabolas in the lower envelope.
+    v: Vec<usize>,
+    // Boundaries between the parabolas in the lower envelope.
+    z: Vec<f32>,
+}
+
+impl LineBuffers {
+    fn new(max_len: usize) -> Self {
+        Self {
+            f: vec![0.0; max_len],
+            v: vec![0; max_len],
+            z: vec![0.0; max_len + 1],
+        }
+    }
+
+    // The 1D squared distance transform of the `len` values starting at `start` and separated by `stride`.
+    fn transform_line(&mut self, grid: &mut [f32], start: usize, stride: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        let Self { f, v, z } = self;
+        for (q, fq) in f[..len].iter_mut().enumerate() {
+            *fq = grid[start + q * stride];
+        }
+
+        let mut k = 0;
+        v[0] = 0;
+        z[0] = -FAR;
+        z[1] = FAR;
+        for q in 1..len {
+            let mut s = parabola_intersection(f, q, v[k]);
+            while s <= z[k] {
+                k -= 1;
+                s = parabola_intersection(f, q, v[k]);
+            }
+            k += 1;
+            v[k] = q;
+            z[k] = s;
+            z[k + 1] = FAR;
+        }
+
+        k = 0;
+        for q in 0..len {
+            while z[k + 1] < q as f32 {
+                k += 1;
+            }
+            let dq = q as f32 - v[k] as f32;
+            grid[start + q * stride] = dq * dq + f[v[k]];
+        }
+    }
+}
+
+// Where the parabolas rooted at `q` and `p` intersect.
+fn parabola_intersection(f: &[f32], q: usize, p: usize) -> f32 {
+    let (qf, pf) = (q as f32, p as f32);
+
+    ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf))
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_around_single_solid_voxel() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(10));
+        let center = Point3i::fill(5);
+
+        let sdf: Array3x1<f32> = signed_distance_transform(&extent, 1.0, |p| p == center);
+
+        assert_eq!(sdf.get(center), -0.5);
+        assert_eq!(sdf.get(center + PointN([1, 0, 0])), 0.5);
+        assert_eq!(sdf.get(center + PointN([3, 4, 0])), 4.5);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([12, 9, 7]));
+        let is_solid = |p: Point3i| {
+            (Point3f::from(p) - PointN([5.0, 4.0, 3.0])).norm() < 3.5
+                || (p.x() + 2 * p.y()) % 11 == 0
+        };
+
+        let sdf: Array3x1<f32> = signed_distance_transform(&extent, 1.0, is_solid);
+
+        for p in extent.iter_points() {
+            let nearest_opposite = extent
+                .iter_points()
+                .filter(|q| is_solid(*q) != is_solid(p))
+                .map(|q| Point3f::from(q - p).norm())
+                .fold(std::f32::INFINITY, f32::min);
+            let expected = if is_solid(p) {
+                0.5 - nearest_opposite
+            } else {
+                nearest_opposite - 0.5
+            };
+
+            assert!((sdf.get(p) - expected).abs() < 1e-4, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn distances_are_scaled_before_saturating() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([10, 1, 1]));
+
+        let sdf: Array3x1<Sd8> = signed_distance_transform(&extent, 2.0, |p| p.x() < 5);
+
+        assert_eq!(sdf.get(PointN([4, 0, 0])), Sd8::from(-0.25));
+        assert_eq!(sdf.get(PointN([6, 0, 0])), Sd8::from(0.75));
+        assert_eq!(sdf.get(PointN([9, 0, 0])), Sd8::ONE);
+        assert_eq!(sdf.get(PointN([0, 0, 0])), Sd8::NEG_ONE);
+    }
+}
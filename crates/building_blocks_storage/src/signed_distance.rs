@@ -1,4 +1,29 @@
+//! Fixed-point signed distance values.
+//!
+//! A `FixedSd<T, SCALE>` stores a distance `d` as the integer `round_toward_zero(d * SCALE)`, saturated to
+//! `[-T::MAX, T::MAX]`. So `SCALE` trades range for precision: with `i8`, a `SCALE` of 127 covers `[-1.0, 1.0]` in steps of
+//! `1 / 127`, while a `SCALE` of 16 covers about `[-7.9, 7.9]` in steps of `1 / 16`. The built-in `Sd8` and `Sd16` types use the
+//! largest scale that still represents `[-1.0, 1.0]`.
+//!
+//! Any `FixedSd` implements `SignedDistance`, so it can be meshed and downsampled just like `Sd8`.
+//!
+//! ```
+//! use building_blocks_storage::prelude::*;
+//!
+//! // Distances up to about 16 voxels from the surface, with 1 / 8 voxel precision.
+//! type Sd4 = FixedSd<i8, 8>;
+//!
+//! assert_eq!(f32::from(Sd4::from(2.5)), 2.5);
+//! assert_eq!(Sd4::from(100.0), Sd4::MAX);
+//! assert_eq!(Sd4::from(1.0) + Sd4::from(0.5), Sd4::from(1.5));
+//! assert_eq!(-Sd4::ONE, Sd4::NEG_ONE);
+//!
+//! // Converting to a type with less range saturates.
+//! assert_eq!(Sd4::from(2.5).rescale::<i8, 127>(), Sd8::ONE);
+//! ```
+
 use bytemuck::{Pod, Zeroable};
+use core::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 use serde::{Deserialize, Serialize};
 
 pub trait SignedDistance: Into<f32> {
@@ -12,71 +37,176 @@ impl SignedDistance for f32 {
     }
 }
 
-/// A signed distance value in the range `[-1.0, 1.0]` with 8 bits of precision.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
-pub struct Sd8(pub i8);
-/// A signed distance value in the range `[-1.0, 1.0]` with 16 bits of precision.
+/// A fixed-point signed distance value, stored as `T` in units of `1 / SCALE`.
+///
+/// `T` can be `i8`, `i16`, or `i32`, and `SCALE` must not be 0, which is checked at compile time by the conversions and `ONE`.
+/// Values saturate at `MIN` and `MAX`, which are symmetric about 0, so negation never overflows.
+///
+/// ```compile_fail
+/// use building_blocks_storage::prelude::*;
+///
+/// let _ = FixedSd::<i8, 0>::from(1.0);
+/// ```
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
-pub struct Sd16(pub i16);
+#[repr(transparent)]
+pub struct FixedSd<T, const SCALE: u32>(pub T);
 
-unsafe impl Zeroable for Sd8 {}
-unsafe impl Pod for Sd8 {}
-
-unsafe impl Zeroable for Sd16 {}
-unsafe impl Pod for Sd16 {}
+/// A signed distance value in the range `[-1.0, 1.0]` with 8 bits of precision.
+pub type Sd8 = FixedSd<i8, { core::i8::MAX as u32 }>;
+/// A signed distance value in the range `[-1.0, 1.0]` with 16 bits of precision.
+pub type Sd16 = FixedSd<i16, { core::i16::MAX as u32 }>;
 
-impl Sd8 {
-    pub const RESOLUTION: f32 = std::i8::MAX as f32;
-    pub const PRECISION: f32 = 1.0 / Self::RESOLUTION;
-    pub const NEG_ONE: Self = Self(-std::i8::MAX);
-    pub const ONE: Self = Self(std::i8::MAX);
+/// Creates an `Sd8` from its raw value, so `Sd8(x)` still works as a constructor now that `Sd8` is an alias of `FixedSd`. This
+/// can't make `Sd8(x)` work as a pattern; match on `FixedSd(x)` instead.
+#[allow(non_snake_case)]
+#[inline]
+pub const fn Sd8(raw: i8) -> Sd8 {
+    FixedSd(raw)
 }
 
-impl Sd16 {
-    pub const RESOLUTION: f32 = std::i16::MAX as f32;
-    pub const PRECISION: f32 = 1.0 / Self::RESOLUTION;
-    pub const NEG_ONE: Self = Self(-std::i16::MAX);
-    pub const ONE: Self = Self(std::i16::MAX);
+/// Creates an `Sd16` from its raw value, like `Sd8(x)`.
+#[allow(non_snake_case)]
+#[inline]
+pub const fn Sd16(raw: i16) -> Sd16 {
+    FixedSd(raw)
 }
 
-impl From<Sd8> for f32 {
-    #[inline]
-    fn from(s: Sd8) -> f32 {
-        s.0 as f32 * Sd8::PRECISION
-    }
-}
-impl From<f32> for Sd8 {
-    #[inline]
-    fn from(s: f32) -> Self {
-        Sd8((Self::RESOLUTION * s.min(1.0).max(-1.0)) as i8)
-    }
-}
-impl SignedDistance for Sd8 {
-    #[inline]
-    fn is_negative(&self) -> bool {
-        self.0 < 0
-    }
-}
+unsafe impl<T: Zeroable, const SCALE: u32> Zeroable for FixedSd<T, SCALE> {}
+unsafe impl<T: Pod, const SCALE: u32> Pod for FixedSd<T, SCALE> {}
 
-impl From<Sd16> for f32 {
+impl<T, const SCALE: u32> FixedSd<T, SCALE>
+where
+    Self: From<f32>,
+    f32: From<Self>,
+{
+    /// Converts to a `FixedSd` with a different representation or scale, saturating if the value is out of range.
     #[inline]
-    fn from(s: Sd16) -> f32 {
-        s.0 as f32 * Sd16::PRECISION
+    pub fn rescale<U, const S: u32>(self) -> FixedSd<U, S>
+    where
+        FixedSd<U, S>: From<f32>,
+    {
+        FixedSd::from(f32::from(self))
     }
 }
-impl From<f32> for Sd16 {
-    #[inline]
-    fn from(s: f32) -> Self {
-        Sd16((Self::RESOLUTION * s.min(1.0).max(-1.0)) as i16)
-    }
-}
-impl SignedDistance for Sd16 {
-    #[inline]
-    fn is_negative(&self) -> bool {
-        self.0 < 0
-    }
+
+macro_rules! impl_fixed_sd {
+    ($t:ty) => {
+        impl<const SCALE: u32> FixedSd<$t, SCALE> {
+            // Fails to compile if any conversion is used with a scale of 0.
+            const CHECKED_SCALE: u32 = {
+                assert!(SCALE != 0, "FixedSd SCALE must not be 0");
+                SCALE
+            };
+
+            /// The number of steps per unit of distance.
+            pub const RESOLUTION: f32 = Self::CHECKED_SCALE as f32;
+            /// The distance between consecutive values.
+            pub const PRECISION: f32 = 1.0 / Self::RESOLUTION;
+            /// The largest representable distance.
+            pub const MAX_DISTANCE: f32 = <$t>::MAX as f32 * Self::PRECISION;
+
+            pub const ZERO: Self = Self(0);
+            pub const MAX: Self = Self(<$t>::MAX);
+            pub const MIN: Self = Self(-<$t>::MAX);
+            /// Saturates to `MAX` if 1.0 is out of range.
+            pub const ONE: Self = if Self::CHECKED_SCALE > <$t>::MAX as u32 {
+                Self::MAX
+            } else {
+                Self(SCALE as $t)
+            };
+            /// Saturates to `MIN` if -1.0 is out of range.
+            pub const NEG_ONE: Self = Self(-Self::ONE.0);
+
+            /// Returns `true` iff the value is `MIN` or `MAX`, meaning the true distance may be even farther from the surface.
+            #[inline]
+            pub fn is_saturated(&self) -> bool {
+                *self == Self::MIN || *self == Self::MAX
+            }
+
+            #[inline]
+            fn clamp_raw(raw: $t) -> Self {
+                Self(raw.max(Self::MIN.0))
+            }
+        }
+
+        impl<const SCALE: u32> From<FixedSd<$t, SCALE>> for f32 {
+            #[inline]
+            fn from(s: FixedSd<$t, SCALE>) -> f32 {
+                s.0 as f32 * FixedSd::<$t, SCALE>::PRECISION
+            }
+        }
+
+        impl<const SCALE: u32> From<f32> for FixedSd<$t, SCALE> {
+            #[inline]
+            fn from(s: f32) -> Self {
+                let max = <$t>::MAX as f32;
+                Self::clamp_raw((Self::RESOLUTION * s).min(max).max(-max) as $t)
+            }
+        }
+
+        impl<const SCALE: u32> SignedDistance for FixedSd<$t, SCALE> {
+            #[inline]
+            fn is_negative(&self) -> bool {
+                self.0 < 0
+            }
+        }
+
+        impl<const SCALE: u32> Add for FixedSd<$t, SCALE> {
+            type Output = Self;
+
+            #[inline]
+            fn add(self, rhs: Self) -> Self {
+                Self::clamp_raw(self.0.saturating_add(rhs.0))
+            }
+        }
+
+        impl<const SCALE: u32> Sub for FixedSd<$t, SCALE> {
+            type Output = Self;
+
+            #[inline]
+            fn sub(self, rhs: Self) -> Self {
+                Self::clamp_raw(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        impl<const SCALE: u32> AddAssign for FixedSd<$t, SCALE> {
+            #[inline]
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl<const SCALE: u32> SubAssign for FixedSd<$t, SCALE> {
+            #[inline]
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl<const SCALE: u32> Neg for FixedSd<$t, SCALE> {
+            type Output = Self;
+
+            #[inline]
+            fn neg(self) -> Self {
+                Self::clamp_raw(self.0.saturating_neg())
+            }
+        }
+
+        impl<const SCALE: u32> Mul<f32> for FixedSd<$t, SCALE> {
+            type Output = Self;
+
+            #[inline]
+            fn mul(self, rhs: f32) -> Self {
+                Self::from(f32::from(self) * rhs)
+            }
+        }
+    };
 }
 
+impl_fixed_sd!(i8);
+impl_fixed_sd!(i16);
+impl_fixed_sd!(i32);
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -97,6 +227,7 @@ mod tests {
         assert_eq!(Sd8::NEG_ONE, Sd8::from(-1.0));
         assert_eq!(Sd8::ONE, Sd8::from(1.0));
         assert_eq!(Sd8(0), Sd8::from(0.0));
+        assert_eq!(Sd8(127), Sd8::ONE);
     }
 
     #[test]
@@ -108,5 +239,52 @@ mod tests {
         assert_eq!(Sd16::NEG_ONE, Sd16::from(-1.0));
         assert_eq!(Sd16::ONE, Sd16::from(1.0));
         assert_eq!(Sd16(0), Sd16::from(0.0));
+        assert_eq!(Sd16(-32767), Sd16::NEG_ONE);
+    }
+
+    #[test]
+    fn custom_scale_trades_precision_for_range() {
+        type Coarse = FixedSd<i8, 4>;
+
+        assert_eq!(Coarse::PRECISION, 0.25);
+        assert_eq!(Coarse::MAX_DISTANCE, 31.75);
+        assert_eq!(f32::from(Coarse::from(-10.25)), -10.25);
+        assert_eq!(Coarse::ONE, FixedSd(4));
+
+        // 1.0 isn't representable with this scale, so `ONE` saturates.
+        type Fine = FixedSd<i8, 1000>;
+        assert_eq!(Fine::ONE, Fine::MAX);
+        assert_eq!(Fine::NEG_ONE, Fine::MIN);
+    }
+
+    #[test]
+    fn conversions_saturate() {
+        assert_eq!(Sd8::from(2.0), Sd8::MAX);
+        assert_eq!(Sd8::from(-2.0), Sd8::MIN);
+        assert_eq!(Sd8::from(f32::INFINITY), Sd8::MAX);
+        assert_eq!(Sd8::from(f32::NEG_INFINITY), Sd8::MIN);
+        assert!(Sd8::from(5.0).is_saturated());
+        assert!(!Sd8::from(0.5).is_saturated());
+
+        let far = FixedSd::<i16, 100>::from(-50.0);
+        assert_eq!(far.rescale::<i8, 127>(), Sd8::NEG_ONE);
+        let finer: Sd16 = Sd8::from(0.5).rescale();
+        assert!((f32::from(finer) - 0.5).abs() <= Sd8::PRECISION);
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        assert_eq!(Sd8::ONE + Sd8::ONE, Sd8::MAX);
+        assert_eq!(Sd8::NEG_ONE - Sd8::ONE, Sd8::MIN);
+        assert_eq!(-Sd8::MIN, Sd8::MAX);
+        assert_eq!(-FixedSd::<i8, 127>(core::i8::MIN), Sd8::MAX);
+        assert_eq!(Sd8::from(0.5) * 4.0, Sd8::MAX);
+
+        let mut d = FixedSd::<i16, 8>::from(1.5);
+        d += FixedSd::from(0.25);
+        assert_eq!(d, FixedSd::from(1.75));
+        d -= FixedSd::from(2.0);
+        assert_eq!(d, FixedSd::from(-0.25));
+        assert!(d.is_negative());
     }
 }
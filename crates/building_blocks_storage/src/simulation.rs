@@ -0,0 +1,576 @@
+//! Double-buffered stepping of cellular automata and other simulations on arrays and chunk maps.
+//!
+//! Every step computes the next value of each point from a `Neighborhood` of values from the previous step, so the order in
+//! which points are updated doesn't matter, and a step can run in parallel. A neighborhood contains every point within the
+//! stencil `radius` of its center along each axis, e.g. radius `1` is the Moore neighborhood.
+//!
+//! An `ArraySimulation` keeps two arrays and swaps them after every step. Points outside of the array have a constant boundary
+//! value.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, ArraySimulation, Channel, Neighborhood};
+//!
+//! // Conway's Game of Life.
+//! let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(5));
+//! let mut cells = Array2x1::fill(extent, false);
+//! for x in 1..4 {
+//!     *cells.get_mut(PointN([x, 2])) = true;
+//! }
+//!
+//! let rule = |cell: &Neighborhood<[i32; 2], Channel<bool>, bool>| {
+//!     let live_neighbors = cell.count_neighbors(|alive| alive);
+//!
+//!     live_neighbors == 3 || (cell.value() && live_neighbors == 2)
+//! };
+//! let mut life = ArraySimulation::new(cells.clone(), 1, false);
+//!
+//! // The blinker oscillates between horizontal and vertical.
+//! life.step(rule);
+//! assert!(life.current().get(PointN([2, 1])));
+//! assert!(!life.current().get(PointN([1, 2])));
+//! life.step(rule);
+//! assert_eq!(life.current(), &cells);
+//! ```
+//!
+//! A `ChunkMap` can be stepped with `ChunkMap::step_simulation`. Each chunk is stepped with a copy of itself that's padded by
+//! `radius` points from the neighboring chunks, so values flow across chunk borders. The new chunks are only written after all
+//! of them are computed, and only the chunks that actually changed are written, so vacant chunks stay vacant until something
+//! flows into them.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Channel, Neighborhood};
+//!
+//! const EMPTY: u8 = 0;
+//! const SAND: u8 = 1;
+//!
+//! let builder = ChunkMapBuilder2x1::new(Point2i::fill(4), EMPTY);
+//! let mut map = builder.build_with_hash_map_storage();
+//! *map.lod_view_mut(0).get_mut(PointN([1, 4])) = SAND;
+//!
+//! let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(8));
+//! let falling_sand = |cell: &Neighborhood<[i32; 2], Channel<u8>, u8>| {
+//!     if cell.value() == SAND {
+//!         cell.get(PointN([0, -1]))
+//!     } else {
+//!         cell.get(PointN([0, 1]))
+//!     }
+//! };
+//!
+//! let changed = map.step_simulation(0, &extent, 1, falling_sand);
+//! assert_eq!(changed.len(), 2);
+//! assert_eq!(map.lod_view(0).get(PointN([1, 3])), SAND);
+//! assert_eq!(map.lod_view(0).get(PointN([1, 4])), EMPTY);
+//! ```
+//!
+//! The `water` module is a reference fluid simulation built with these steps.
+
+pub mod water;
+
+pub use water::*;
+
+use crate::{
+    Array, Channels, ChunkKey, ChunkMap, ChunkMapBuilder, ChunkReadStorage, ChunkWriteStorage,
+    FillChannels, ForEachMutPtr, Get, MultiMutPtr,
+};
+
+use building_blocks_core::prelude::*;
+
+use either::Either;
+
+#[cfg(feature = "rayon")]
+use crate::{array::outer_axis_slabs, ForEach};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// The values of the previous step that are visible to the update rule of a simulation at one point: all points within the
+/// stencil radius of `center`.
+pub struct Neighborhood<'a, N, Chan, T> {
+    center: PointN<N>,
+    radius: i32,
+    offsets: &'a [PointN<N>],
+    src: &'a Array<N, Chan>,
+    boundary: &'a T,
+}
+
+impl<'a, N, Chan, T> Neighborhood<'a, N, Chan, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    Array<N, Chan>: Get<PointN<N>, Item = T>,
+    T: Clone,
+{
+    /// The point being updated.
+    #[inline]
+    pub fn center(&self) -> PointN<N> {
+        self.center
+    }
+
+    #[inline]
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    /// The value at the center.
+    #[inline]
+    pub fn value(&self) -> T {
+        self.get_point(self.center)
+    }
+
+    /// The value at `center + offset`. `offset` should be within the stencil radius, because values any farther away are not
+    /// always available.
+    #[inline]
+    pub fn get(&self, offset: PointN<N>) -> T {
+        self.get_point(self.center + offset)
+    }
+
+    /// Calls `f` with the offset and value of every neighbor, i.e. every point within the stencil radius except the center.
+    #[inline]
+    pub fn for_each_neighbor(&self, mut f: impl FnMut(PointN<N>, T)) {
+        for &offset in self.offsets.iter() {
+            f(offset, self.get(offset))
+        }
+    }
+
+    /// The number of neighbors whose values satisfy `predicate`.
+    #[inline]
+    pub fn count_neighbors(&self, predicate: impl Fn(T) -> bool) -> usize {
+        self.offsets
+            .iter()
+            .filter(|offset| predicate(self.get(**offset)))
+            .count()
+    }
+
+    fn get_point(&self, p: PointN<N>) -> T {
+        if self.src.extent().contains(p) {
+            self.src.get(p)
+        } else {
+            self.boundary.clone()
+        }
+    }
+}
+
+/// Every offset within `radius` along each axis, except for zero.
+fn stencil_offsets<N>(radius: i32) -> Vec<PointN<N>>
+where
+    PointN<N>: IntegerPoint<N>,
+{
+    assert!(radius >= 0, "Stencil radius must not be negative");
+
+    let zero = PointN::fill(0);
+    ExtentN::from_min_and_shape(PointN::fill(-radius), PointN::fill(2 * radius + 1))
+        .iter_points()
+        .filter(|offset| *offset != zero)
+        .collect()
+}
+
+/// A simulation on an `Array` that keeps the current and next state in separate arrays. See the [module docs](self) for an
+/// example.
+pub struct ArraySimulation<N, Chan, T> {
+    current: Array<N, Chan>,
+    next: Array<N, Chan>,
+    boundary: T,
+    radius: i32,
+    offsets: Vec<PointN<N>>,
+}
+
+impl<N, Chan, T> ArraySimulation<N, Chan, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    Array<N, Chan>: Clone,
+{
+    /// Starts simulating from the values in `array`. Neighborhoods contain all points within `radius`, and points outside of
+    /// `array` always have the value `boundary`.
+    pub fn new(array: Array<N, Chan>, radius: i32, boundary: T) -> Self {
+        Self {
+            next: array.clone(),
+            current: array,
+            boundary,
+            radius,
+            offsets: stencil_offsets(radius),
+        }
+    }
+}
+
+impl<N, Chan, T> ArraySimulation<N, Chan, T> {
+    #[inline]
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    /// The state after the most recent step.
+    #[inline]
+    pub fn current(&self) -> &Array<N, Chan> {
+        &self.current
+    }
+
+    /// Mutably borrow the current state, e.g. to make edits between steps.
+    #[inline]
+    pub fn current_mut(&mut self) -> &mut Array<N, Chan> {
+        &mut self.current
+    }
+
+    #[inline]
+    pub fn into_current(self) -> Array<N, Chan> {
+        self.current
+    }
+}
+
+impl<N, Chan, T> ArraySimulation<N, Chan, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    Chan: Channels<Data = T>,
+    Array<N, Chan>: Get<PointN<N>, Item = T> + ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    T: Clone,
+{
+    /// Computes the next value of every point with `rule`, then makes that the current state.
+    pub fn step(&mut self, rule: impl Fn(&Neighborhood<N, Chan, T>) -> T) {
+        let extent = *self.current.extent();
+        let current = &self.current;
+        let boundary = &self.boundary;
+        let radius = self.radius;
+        let offsets = &self.offsets[..];
+        unsafe {
+            self.next.for_each_mut_ptr(&extent, |p: PointN<N>, ptr| {
+                ptr.write(rule(&Neighborhood {
+                    center: p,
+                    radius,
+                    offsets,
+                    src: current,
+                    boundary,
+                }))
+            });
+        }
+
+        std::mem::swap(&mut self.current, &mut self.next);
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, Chan, T> ArraySimulation<N, Chan, T>
+where
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+    Chan: Channels<Data = T>,
+    Array<N, Chan>: ForEach<N, PointN<N>, Item = T>
+        + Get<PointN<N>, Item = T>
+        + ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>
+        + Sync,
+    T: Clone + Send + Sync,
+{
+    /// Like `step`, but the array is split into slabs along the outermost axis, and the slabs are stepped in parallel on the
+    /// `rayon` thread pool.
+    pub fn par_step(&mut self, rule: impl Fn(&Neighborhood<N, Chan, T>) -> T + Sync) {
+        let extent = *self.current.extent();
+        let current = &self.current;
+        let boundary = &self.boundary;
+        let radius = self.radius;
+        let offsets = &self.offsets[..];
+        let slab_values: Vec<(ExtentN<N>, Vec<T>)> = outer_axis_slabs(&extent)
+            .into_par_iter()
+            .map(|slab| {
+                let mut values = Vec::with_capacity(slab.num_points());
+                current.for_each(&slab, |p: PointN<N>, _value| {
+                    values.push(rule(&Neighborhood {
+                        center: p,
+                        radius,
+                        offsets,
+                        src: current,
+                        boundary,
+                    }))
+                });
+
+                (slab, values)
+            })
+            .collect();
+
+        // `next` is visited in the same order as `current` was, because they have the same extent.
+        for (slab, values) in slab_values.into_iter() {
+            let mut values = values.into_iter();
+            unsafe {
+                self.next.for_each_mut_ptr(&slab, |_p: PointN<N>, ptr| {
+                    ptr.write(values.next().unwrap())
+                });
+            }
+        }
+
+        std::mem::swap(&mut self.current, &mut self.next);
+    }
+}
+
+impl<N, T, Chan, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    PointN<N>: IntegerPoint<N>,
+    T: Clone + PartialEq,
+    Chan: Channels<Data = T> + FillChannels,
+    Array<N, Chan>: Get<PointN<N>, Item = T> + ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr>,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Store: ChunkReadStorage<N, Array<N, Chan>> + ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    /// Steps a simulation on every chunk at level of detail `lod` that overlaps `extent`. Each point's next value is computed
+    /// by `rule` from the values within `radius` of it, reading across chunk borders. Vacant chunks are stepped as though they
+    /// were filled with the ambient value.
+    ///
+    /// Returns the keys of the chunks whose values changed. Only those chunks are written.
+    pub fn step_simulation(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        radius: i32,
+        rule: impl Fn(&Neighborhood<N, Chan, T>) -> T,
+    ) -> Vec<ChunkKey<N>> {
+        let keys: Vec<_> = self
+            .indexer
+            .chunk_mins_for_extent(extent)
+            .map(|chunk_min| ChunkKey::new(lod, chunk_min))
+            .collect();
+
+        self.step_simulation_chunks(&keys, radius, rule)
+    }
+
+    /// Like `step_simulation`, but only the chunks at `keys` are stepped, e.g. to skip chunks where the simulation has settled.
+    pub fn step_simulation_chunks(
+        &mut self,
+        keys: &[ChunkKey<N>],
+        radius: i32,
+        rule: impl Fn(&Neighborhood<N, Chan, T>) -> T,
+    ) -> Vec<ChunkKey<N>> {
+        let offsets = stencil_offsets(radius);
+        let new_chunks: Vec<_> = keys
+            .iter()
+            .filter_map(|&key| {
+                self.step_chunk(key, radius, &offsets, &rule)
+                    .map(|chunk| (key, chunk))
+            })
+            .collect();
+
+        self.write_stepped_chunks(new_chunks)
+    }
+
+    /// Returns the next state of the chunk at `key`, unless it didn't change.
+    fn step_chunk(
+        &self,
+        key: ChunkKey<N>,
+        radius: i32,
+        offsets: &[PointN<N>],
+        rule: &impl Fn(&Neighborhood<N, Chan, T>) -> T,
+    ) -> Option<Array<N, Chan>> {
+        let chunk_extent = self.indexer.extent_for_chunk_with_min(key.minimum);
+        let ambient = self.ambient_value();
+
+        // Exchange borders with the neighboring chunks by copying them into a padded chunk.
+        let padded_extent = chunk_extent.padded(radius);
+        let mut padded = Array::fill(padded_extent, ambient.clone());
+        self.visit_chunks(key.lod, &padded_extent, |chunk| match chunk {
+            Either::Left(chunk) => unsafe {
+                padded.for_each_mut_ptr(
+                    &chunk.extent().intersection(&padded_extent),
+                    |p: PointN<N>, ptr| ptr.write(chunk.get(p)),
+                );
+            },
+            Either::Right((chunk_extent, ambient)) => unsafe {
+                padded.for_each_mut_ptr(
+                    &chunk_extent.intersection(&padded_extent),
+                    |_p: PointN<N>, ptr| ptr.write(ambient.get()),
+                );
+            },
+        });
+
+        let mut next = Array::fill(chunk_extent, ambient.clone());
+        let mut changed = false;
+        unsafe {
+            next.for_each_mut_ptr(&chunk_extent, |p: PointN<N>, ptr| {
+                let value = rule(&Neighborhood {
+                    center: p,
+                    radius,
+                    offsets,
+                    src: &padded,
+                    boundary: &ambient,
+                });
+                changed |= value != padded.get(p);
+                ptr.write(value);
+            });
+        }
+
+        if changed {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    fn write_stepped_chunks(
+        &mut self,
+        new_chunks: Vec<(ChunkKey<N>, Array<N, Chan>)>,
+    ) -> Vec<ChunkKey<N>> {
+        new_chunks
+            .into_iter()
+            .map(|(key, chunk)| {
+                self.write_chunk(key, chunk);
+
+                key
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<N, T, Chan, Bldr, Store> ChunkMap<N, T, Bldr, Store>
+where
+    Self: Sync,
+    PointN<N>: IntegerPoint<N> + Send + Sync,
+    T: Clone + PartialEq,
+    Chan: Channels<Data = T> + FillChannels,
+    Array<N, Chan>: Get<PointN<N>, Item = T> + ForEachMutPtr<N, PointN<N>, Item = Chan::Ptr> + Send,
+    Bldr: ChunkMapBuilder<N, T, Chunk = Array<N, Chan>>,
+    Store: ChunkReadStorage<N, Array<N, Chan>> + ChunkWriteStorage<N, Array<N, Chan>>,
+{
+    /// Like `step_simulation`, but the chunks are stepped in parallel on the `rayon` thread pool.
+    pub fn par_step_simulation(
+        &mut self,
+        lod: u8,
+        extent: &ExtentN<N>,
+        radius: i32,
+        rule: impl Fn(&Neighborhood<N, Chan, T>) -> T + Sync,
+    ) -> Vec<ChunkKey<N>> {
+        let keys: Vec<_> = self
+            .indexer
+            .chunk_mins_for_extent(extent)
+            .map(|chunk_min| ChunkKey::new(lod, chunk_min))
+            .collect();
+
+        self.par_step_simulation_chunks(&keys, radius, rule)
+    }
+
+    /// Like `step_simulation_chunks`, but the chunks are stepped in parallel on the `rayon` thread pool.
+    pub fn par_step_simulation_chunks(
+        &mut self,
+        keys: &[ChunkKey<N>],
+        radius: i32,
+        rule: impl Fn(&Neighborhood<N, Chan, T>) -> T + Sync,
+    ) -> Vec<ChunkKey<N>> {
+        let offsets = stencil_offsets(radius);
+        let new_chunks: Vec<_> = keys
+            .par_iter()
+            .filter_map(|&key| {
+                self.step_chunk(key, radius, &offsets, &rule)
+                    .map(|chunk| (key, chunk))
+            })
+            .collect();
+
+        self.write_stepped_chunks(new_chunks)
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    const EMPTY: u8 = 0;
+    const SAND: u8 = 1;
+
+    fn falling_sand<N, Chan>(cell: &Neighborhood<N, Chan, u8>) -> u8
+    where
+        PointN<N>: IntegerPoint<N>,
+        Array<N, Chan>: Get<PointN<N>, Item = u8>,
+    {
+        let down = PointN::<N>::basis()[1] * -1;
+        if cell.value() == SAND {
+            cell.get(down)
+        } else {
+            cell.get(down * -1)
+        }
+    }
+
+    #[test]
+    fn radius_two_neighborhood_reads_boundary_outside_of_array() {
+        let extent = Extent2i::from_min_and_shape(Point2i::ZERO, Point2i::fill(3));
+        let array = Array2x1::fill(extent, 1u32);
+        let mut sim = ArraySimulation::new(array, 2, 0);
+
+        sim.step(|cell| {
+            let mut sum = cell.value();
+            cell.for_each_neighbor(|_offset, value| sum += value);
+            sum
+        });
+
+        assert_eq!(sim.current().get(Point2i::fill(1)), 9);
+        assert_eq!(sim.current().get(Point2i::ZERO), 9);
+        assert_eq!(sim.current().get(PointN([2, 0])), 9);
+
+        sim.step(|cell| cell.count_neighbors(|value| value == 9) as u32);
+        assert_eq!(sim.current().get(Point2i::ZERO), 8);
+    }
+
+    #[test]
+    fn sand_falls_across_chunk_borders() {
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), EMPTY);
+        let mut map = builder.build_with_hash_map_storage();
+        let grain = PointN([1, 9, 1]);
+        *map.lod_view_mut(0).get_mut(grain) = SAND;
+
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(12));
+        for step in 1..=9 {
+            let changed = map.step_simulation(0, &extent, 1, falling_sand);
+            match step {
+                // The grain crosses from one chunk into the next.
+                2 | 6 => assert_eq!(changed.len(), 2),
+                _ => assert_eq!(changed.len(), 1),
+            }
+            assert_eq!(
+                map.lod_view(0).get(grain - PointN([0, step, 0])),
+                SAND,
+                "after step {}",
+                step
+            );
+        }
+
+        // Vacant chunks that nothing flowed into are still vacant.
+        assert_eq!(map.storage().len(), 3);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_steps_match_sequential_steps() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(12));
+        let mut array = Array3x1::fill(extent, EMPTY);
+        for p in [PointN([1, 9, 1]), PointN([5, 6, 7]), PointN([3, 11, 2])].iter() {
+            *array.get_mut(*p) = SAND;
+        }
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), EMPTY);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut map.lod_view_mut(0));
+        let mut par_map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut par_map.lod_view_mut(0));
+
+        let mut sim = ArraySimulation::new(array.clone(), 1, EMPTY);
+        let mut par_sim = ArraySimulation::new(array, 1, EMPTY);
+
+        for _ in 0..5 {
+            sim.step(falling_sand);
+            par_sim.par_step(falling_sand);
+            assert_eq!(sim.current(), par_sim.current());
+
+            let mut changed = map.step_simulation(0, &extent, 1, falling_sand);
+            let mut par_changed = par_map.par_step_simulation(0, &extent, 1, falling_sand);
+            let by_minimum = |key: &ChunkKey3| (key.minimum.x(), key.minimum.y(), key.minimum.z());
+            changed.sort_by_key(by_minimum);
+            par_changed.sort_by_key(by_minimum);
+            assert_eq!(changed, par_changed);
+        }
+
+        for p in extent.iter_points() {
+            assert_eq!(map.lod_view(0).get(p), sim.current().get(p));
+            assert_eq!(par_map.lod_view(0).get(p), sim.current().get(p));
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! A reference water simulation built on the simulation stepping framework.
+//!
+//! Water lives in its own `ChunkMap3x1<Water>`, separate from the voxels that describe the terrain. Obstacles are marked with
+//! `Water::SOLID`, so the terrain has to be mirrored into the water map wherever water can reach it.
+//!
+//! Every cell holds a level of water, where `Water::FULL_LEVEL` is a full voxel. On each tick, water first flows down into the
+//! cell below, then spreads out to the sides towards lower levels, and finally water under pressure is pushed up into the
+//! cell above. A cell can be compressed a little past a full level by the weight of the water above it, which lets water rise
+//! on both sides of a U-shaped pipe. All flows are computed symmetrically from the previous state, so the total amount of
+//! water never changes.
+//!
+//! `WaterSimulation` only ticks the chunks that might still change. After a chunk settles, it stays dormant until one of its
+//! neighbors changes or it's woken up by `WaterSimulation::wake_extent`.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{prelude::*, Water, WaterSimulation};
+//!
+//! let builder = ChunkMapBuilder3x1::new(Point3i::fill(8), Water::EMPTY);
+//! let mut map = builder.build_with_hash_map_storage();
+//!
+//! // An open box with some water hanging in the air.
+//! let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+//! map.lod_view_mut(0).fill_extent(&extent, Water::SOLID);
+//! let inside = Extent3i::from_min_and_shape(PointN([1, 1, 1]), PointN([6, 7, 6]));
+//! map.lod_view_mut(0).fill_extent(&inside, Water::EMPTY);
+//! let pool = Extent3i::from_min_and_shape(PointN([2, 4, 2]), Point3i::fill(2));
+//! map.lod_view_mut(0).fill_extent(&pool, Water::FULL);
+//!
+//! let mut sim = WaterSimulation::new(0);
+//! sim.wake_extent(&map.indexer, &extent);
+//! let mut ticks = 0;
+//! while !sim.is_settled() {
+//!     sim.tick(&mut map);
+//!     ticks += 1;
+//!     assert!(ticks < 1000);
+//! }
+//!
+//! // The water spread out over the floor of the box.
+//! let floor = Extent3i::from_min_and_shape(PointN([1, 1, 1]), PointN([6, 1, 6]));
+//! let mut total = 0;
+//! map.lod_view(0).for_each(&floor, |_p: Point3i, water: Water| {
+//!     assert!(water.level() > 0);
+//!     total += water.level() as u32;
+//! });
+//! assert_eq!(total, 8 * Water::FULL_LEVEL as u32);
+//! ```
+
+use crate::{
+    Array, Array3x1, ChunkIndexer, ChunkKey3, ChunkMap, ChunkMapBuilder, ChunkReadStorage,
+    ChunkWriteStorage, Get, Neighborhood, SmallKeyHashSet,
+};
+
+use building_blocks_core::prelude::*;
+
+/// The amount of water in one voxel, or an obstacle that water can't flow into.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Water(pub u16);
+
+impl Water {
+    /// The level of a voxel that's full of water at the surface. Cells under water can hold a little more.
+    pub const FULL_LEVEL: u16 = 256;
+
+    /// How much more water a cell can hold than the cell above it.
+    pub const COMPRESSION: u16 = 8;
+
+    pub const EMPTY: Self = Water(0);
+    pub const FULL: Self = Water(Self::FULL_LEVEL);
+    pub const SOLID: Self = Water(u16::MAX);
+
+    /// The amount of water, which is always `0` for solid cells.
+    #[inline]
+    pub fn level(self) -> u16 {
+        if self.is_solid() {
+            0
+        } else {
+            self.0
+        }
+    }
+
+    #[inline]
+    pub fn is_solid(self) -> bool {
+        self == Self::SOLID
+    }
+
+    /// How full this cell looks, from `0.0` to `1.0`, e.g. for the height of the water surface in a mesh.
+    #[inline]
+    pub fn fullness(self) -> f32 {
+        self.level().min(Self::FULL_LEVEL) as f32 / Self::FULL_LEVEL as f32
+    }
+}
+
+/// The stencil radius needed by `flow_water`. Water that's pushed up into a cell is computed from two cells below it.
+pub const WATER_STENCIL_RADIUS: i32 = 2;
+
+/// Each side, and the cell above, can receive at most this fraction of the water that doesn't flow down.
+const SPREAD: u32 = 6;
+
+const DOWN: Point3i = PointN([0, -1, 0]);
+const UP: Point3i = PointN([0, 1, 0]);
+const SIDES: [Point3i; 4] = [
+    PointN([1, 0, 0]),
+    PointN([-1, 0, 0]),
+    PointN([0, 0, 1]),
+    PointN([0, 0, -1]),
+];
+
+/// The update rule of the water simulation. It can also be used directly with `ArraySimulation` or
+/// `ChunkMap::step_simulation`, as long as the stencil radius is at least `WATER_STENCIL_RADIUS`.
+pub fn flow_water<Chan>(cell: &Neighborhood<[i32; 3], Chan, Water>) -> Water
+where
+    Array<[i32; 3], Chan>: Get<Point3i, Item = Water>,
+{
+    let water = cell.value();
+    if water.is_solid() {
+        return water;
+    }
+
+    let below = cell.get(DOWN);
+    let above = cell.get(UP);
+    let remaining = remaining_after_down_flow(water, below);
+
+    let mut level = water.0 as u32;
+    level -= down_flow(water, below);
+    level -= up_flow(remaining, above);
+    level += down_flow(above, water);
+    level += up_flow(
+        remaining_after_down_flow(below, cell.get(DOWN + DOWN)),
+        water,
+    );
+    for &side in SIDES.iter() {
+        let neighbor = cell.get(side);
+        if neighbor.is_solid() {
+            continue;
+        }
+        let neighbor_remaining = remaining_after_down_flow(neighbor, cell.get(side + DOWN));
+        level += side_flow(neighbor_remaining, remaining);
+        level -= side_flow(remaining, neighbor_remaining);
+    }
+
+    Water(level as u16)
+}
+
+/// How much of `total` water the lower of two stacked cells holds when they're at rest.
+fn stable_lower_level(total: u32) -> u32 {
+    let full = Water::FULL_LEVEL as u32;
+    let compression = Water::COMPRESSION as u32;
+
+    if total <= full {
+        total
+    } else if total < 2 * full + compression {
+        (full * full + total * compression) / (full + compression)
+    } else {
+        (total + compression) / 2
+    }
+}
+
+fn down_flow(upper: Water, lower: Water) -> u32 {
+    if upper.is_solid() || lower.is_solid() {
+        return 0;
+    }
+
+    stable_lower_level(upper.0 as u32 + lower.0 as u32).saturating_sub(lower.0 as u32)
+}
+
+fn remaining_after_down_flow(water: Water, below: Water) -> u32 {
+    water.level() as u32 - down_flow(water, below)
+}
+
+fn side_flow(from_remaining: u32, to_remaining: u32) -> u32 {
+    from_remaining.saturating_sub(to_remaining) / SPREAD
+}
+
+fn up_flow(from_remaining: u32, above: Water) -> u32 {
+    if above.is_solid() {
+        return 0;
+    }
+
+    from_remaining
+        .saturating_sub(stable_lower_level(from_remaining + above.0 as u32))
+        .min(from_remaining / SPREAD)
+}
+
+/// Ticks the water in a `ChunkMap3x1<Water>` at one level of detail, skipping the chunks that have settled. See the
+/// [module docs](self) for an example.
+pub struct WaterSimulation {
+    lod: u8,
+    active: SmallKeyHashSet<ChunkKey3>,
+}
+
+impl WaterSimulation {
+    /// Creates a simulation with no active chunks.
+    pub fn new(lod: u8) -> Self {
+        Self {
+            lod,
+            active: SmallKeyHashSet::default(),
+        }
+    }
+
+    pub fn lod(&self) -> u8 {
+        self.lod
+    }
+
+    /// The chunks that will be stepped on the next tick.
+    pub fn active_chunks(&self) -> &SmallKeyHashSet<ChunkKey3> {
+        &self.active
+    }
+
+    /// True iff no water can move until the simulation is woken up again.
+    pub fn is_settled(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Activates every chunk whose water could move because of an edit in `extent`. This must be called after editing the
+    /// water map, including when adding or removing obstacles.
+    pub fn wake_extent(&mut self, indexer: &ChunkIndexer<[i32; 3]>, extent: &Extent3i) {
+        let lod = self.lod;
+        self.active.extend(
+            indexer
+                .chunk_mins_for_extent(&extent.padded(WATER_STENCIL_RADIUS))
+                .map(|chunk_min| ChunkKey3::new(lod, chunk_min)),
+        );
+    }
+
+    /// Steps all active chunks and returns the keys of the chunks that changed. Chunks that didn't change, and whose
+    /// neighbors didn't change, become dormant.
+    pub fn tick<Bldr, Store>(
+        &mut self,
+        map: &mut ChunkMap<[i32; 3], Water, Bldr, Store>,
+    ) -> Vec<ChunkKey3>
+    where
+        Bldr: ChunkMapBuilder<[i32; 3], Water, Chunk = Array3x1<Water>>,
+        Store: ChunkReadStorage<[i32; 3], Array3x1<Water>>
+            + ChunkWriteStorage<[i32; 3], Array3x1<Water>>,
+    {
+        let keys: Vec<_> = self.active.drain().collect();
+        let changed = map.step_simulation_chunks(&keys, WATER_STENCIL_RADIUS, flow_water);
+        self.wake_changed_chunks(&map.indexer, &changed);
+
+        changed
+    }
+
+    fn wake_changed_chunks(&mut self, indexer: &ChunkIndexer<[i32; 3]>, changed: &[ChunkKey3]) {
+        for key in changed.iter() {
+            self.wake_extent(indexer, &indexer.extent_for_chunk_with_min(key.minimum));
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl WaterSimulation {
+    /// Like `tick`, but the active chunks are stepped in parallel on the `rayon` thread pool.
+    pub fn par_tick<Bldr, Store>(
+        &mut self,
+        map: &mut ChunkMap<[i32; 3], Water, Bldr, Store>,
+    ) -> Vec<ChunkKey3>
+    where
+        ChunkMap<[i32; 3], Water, Bldr, Store>: Sync,
+        Bldr: ChunkMapBuilder<[i32; 3], Water, Chunk = Array3x1<Water>>,
+        Store: ChunkReadStorage<[i32; 3], Array3x1<Water>>
+            + ChunkWriteStorage<[i32; 3], Array3x1<Water>>,
+    {
+        let keys: Vec<_> = self.active.drain().collect();
+        let changed = map.par_step_simulation_chunks(&keys, WATER_STENCIL_RADIUS, flow_water);
+        self.wake_changed_chunks(&map.indexer, &changed);
+
+        changed
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, ArraySimulation};
+
+    fn total_water(map: &ChunkHashMap3x1<Water>, extent: &Extent3i) -> u32 {
+        let mut total = 0;
+        map.lod_view(0)
+            .for_each(extent, |_p: Point3i, water: Water| {
+                total += water.level() as u32
+            });
+
+        total
+    }
+
+    /// A box with solid walls and floor and an open top.
+    fn water_box(extent: Extent3i, water: Extent3i) -> Array3x1<Water> {
+        let mut array = Array3x1::fill(extent, Water::SOLID);
+        let inside = Extent3i::from_min_and_shape(
+            extent.minimum + PointN([1, 1, 1]),
+            extent.shape - PointN([2, 1, 2]),
+        );
+        array.fill_extent(&inside, Water::EMPTY);
+        array.fill_extent(&water, Water::FULL);
+
+        array
+    }
+
+    #[test]
+    fn water_rises_on_both_sides_of_a_u_pipe() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([3, 8, 1]));
+        let mut array = Array3x1::fill(extent, Water::EMPTY);
+        array.fill_extent(
+            &Extent3i::from_min_and_shape(PointN([1, 1, 0]), PointN([1, 7, 1])),
+            Water::SOLID,
+        );
+        array.fill_extent(
+            &Extent3i::from_min_and_shape(Point3i::ZERO, PointN([1, 7, 1])),
+            Water::FULL,
+        );
+
+        // Outside of the array is a solid wall.
+        let mut sim = ArraySimulation::new(array, WATER_STENCIL_RADIUS, Water::SOLID);
+        for _ in 0..1000 {
+            sim.step(flow_water);
+        }
+
+        let mut total = 0;
+        sim.current()
+            .for_each(&extent, |_p: Point3i, water: Water| {
+                total += water.level() as u32
+            });
+        assert_eq!(total, 7 * Water::FULL_LEVEL as u32);
+
+        // The pressure pushed water above the bottom of the pipe on the empty side.
+        assert!(sim.current().get(PointN([2, 1, 0])).level() > 0);
+        assert!(sim.current().get(PointN([0, 6, 0])).level() < Water::FULL_LEVEL);
+    }
+
+    #[test]
+    fn water_settles_across_chunks_and_conserves_mass() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([12, 8, 12]));
+        let pool = Extent3i::from_min_and_shape(PointN([2, 4, 2]), Point3i::fill(3));
+        let array = water_box(extent, pool);
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Water::EMPTY);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut map.lod_view_mut(0));
+
+        let mut sim = WaterSimulation::new(0);
+        sim.wake_extent(&map.indexer, &extent);
+
+        let mut ticks = 0;
+        while !sim.is_settled() {
+            sim.tick(&mut map);
+            assert_eq!(
+                total_water(&map, &extent),
+                27 * Water::FULL_LEVEL as u32,
+                "after tick {}",
+                ticks
+            );
+            ticks += 1;
+            assert!(ticks < 1000);
+        }
+
+        // Water reached the far corner of the floor, in another chunk.
+        assert!(map.lod_view(0).get(PointN([10, 1, 10])).level() > 0);
+
+        // Settled chunks aren't stepped anymore.
+        assert!(sim.tick(&mut map).is_empty());
+        assert!(sim.is_settled());
+    }
+
+    #[test]
+    fn chunked_simulation_matches_array_simulation() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([12, 8, 12]));
+        let pool = Extent3i::from_min_and_shape(PointN([3, 3, 5]), Point3i::fill(4));
+        let array = water_box(extent, pool);
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Water::EMPTY);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut map.lod_view_mut(0));
+        let mut sim = WaterSimulation::new(0);
+        sim.wake_extent(&map.indexer, &extent);
+
+        // The walls keep the water inside of the array, so the boundary value doesn't matter.
+        let mut array_sim = ArraySimulation::new(array, WATER_STENCIL_RADIUS, Water::SOLID);
+
+        for _ in 0..30 {
+            sim.tick(&mut map);
+            array_sim.step(flow_water);
+        }
+
+        for p in extent.iter_points() {
+            assert_eq!(
+                map.lod_view(0).get(p),
+                array_sim.current().get(p),
+                "at {:?}",
+                p
+            );
+        }
+    }
+
+    #[test]
+    fn waking_an_edit_restarts_a_settled_simulation() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([8, 4, 8]));
+        let array = water_box(
+            extent,
+            Extent3i::from_min_and_shape(Point3i::fill(1), Point3i::fill(1)),
+        );
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), Water::EMPTY);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut map.lod_view_mut(0));
+        let mut sim = WaterSimulation::new(0);
+        sim.wake_extent(&map.indexer, &extent);
+        while !sim.is_settled() {
+            sim.tick(&mut map);
+        }
+
+        let drop = Extent3i::from_min_and_shape(PointN([6, 3, 6]), Point3i::fill(1));
+        map.lod_view_mut(0).fill_extent(&drop, Water::FULL);
+        sim.wake_extent(&map.indexer, &drop);
+        assert!(!sim.is_settled());
+        assert!(!sim.tick(&mut map).is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Packing arrays and chunk maps into buffers for uploading 3D textures to the GPU, e.g. for volume ray marching.
+//!
+//! The texels of a 3D texture buffer are ordered by X, then Y, then Z. So rows of texels along X make up images in the XY
+//! plane, and the images are stacked along Z. This is the layout that both wgpu (`ImageDataLayout`) and Vulkan
+//! (`VkBufferImageCopy`) expect. wgpu also requires every row of a buffer that's copied into a texture to start at a multiple
+//! of `COPY_BYTES_PER_ROW_ALIGNMENT` bytes, so rows can be padded at the end. A `TextureLayout3` describes where every texel
+//! is, and its fields map directly onto the copy parameters of either API.
+//!
+//! Mip levels are generated with any `Downsampler`, halving the shape along each axis at every level.
+//!
+//! ```
+//! use building_blocks_core::prelude::*;
+//! use building_blocks_storage::{
+//!     full_mip_level_count, prelude::*, texture_mip_chain, MaxDownsampler, TextureBuffer3,
+//!     COPY_BYTES_PER_ROW_ALIGNMENT,
+//! };
+//!
+//! let extent = Extent3i::from_min_and_shape(Point3i::fill(-2), Point3i::fill(4));
+//! let density = Array3x1::fill_with(extent, |p| (p.x() + 2) as u8);
+//!
+//! // Every row of 4 bytes is padded to 256 bytes for `wgpu::Queue::write_buffer` + `copy_buffer_to_texture`.
+//! let buffer = TextureBuffer3::pack(&density, &extent, COPY_BYTES_PER_ROW_ALIGNMENT);
+//! assert_eq!(buffer.layout.bytes_per_row, 256);
+//! assert_eq!(buffer.layout.rows_per_image, 4);
+//! assert_eq!(buffer.data.len(), 256 * 4 * 4);
+//! assert_eq!(&buffer.data[..4], &[0, 1, 2, 3]);
+//!
+//! // A full chain of 4x4x4, 2x2x2, and 1x1x1 textures.
+//! let mips = texture_mip_chain(density, full_mip_level_count(extent.shape), &MaxDownsampler);
+//! assert_eq!(mips.len(), 3);
+//! assert_eq!(mips[1].extent().shape, Point3i::fill(2));
+//! assert_eq!(mips[2].get(Point3i::ZERO), 3);
+//!
+//! let mip_buffers: Vec<_> = mips
+//!     .iter()
+//!     .map(|mip| TextureBuffer3::pack(mip, mip.extent(), COPY_BYTES_PER_ROW_ALIGNMENT))
+//!     .collect();
+//! assert_eq!(mip_buffers[2].data.len(), 256);
+//! ```
+//!
+//! Any `ForEach` map can be packed, so an extent of a chunk map, including the borders shared with neighboring chunks, can be
+//! packed straight from a `ChunkMapLodView` without copying it into an array first.
+
+use crate::{Array3x1, Downsampler, ForEach, Get};
+
+use building_blocks_core::prelude::*;
+
+use bytemuck::{bytes_of, Pod};
+
+/// The alignment that wgpu requires for `bytes_per_row` when copying a buffer into a texture.
+pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Where each texel of a 3D texture is in a buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TextureLayout3 {
+    /// The width, height, and depth in texels.
+    pub size: [u32; 3],
+    pub bytes_per_texel: u32,
+    /// The stride from one row to the next, including padding.
+    pub bytes_per_row: u32,
+    /// The stride from one image to the next, in rows. Images are never padded, so this is always the height.
+    pub rows_per_image: u32,
+}
+
+impl TextureLayout3 {
+    /// A layout for a texture with `shape`, where every row is padded to a multiple of `row_alignment` bytes. A `row_alignment`
+    /// of `1` packs the texels tightly.
+    pub fn new(shape: Point3i, bytes_per_texel: u32, row_alignment: u32) -> Self {
+        assert!(shape.x() > 0 && shape.y() > 0 && shape.z() > 0);
+        assert!(row_alignment > 0);
+
+        let size = [shape.x() as u32, shape.y() as u32, shape.z() as u32];
+        let unpadded_bytes_per_row = size[0] * bytes_per_texel;
+        let bytes_per_row =
+            (unpadded_bytes_per_row + row_alignment - 1) / row_alignment * row_alignment;
+
+        Self {
+            size,
+            bytes_per_texel,
+            bytes_per_row,
+            rows_per_image: size[1],
+        }
+    }
+
+    /// The number of bytes in the whole buffer.
+    pub fn buffer_len(&self) -> usize {
+        self.bytes_per_row as usize * self.rows_per_image as usize * self.size[2] as usize
+    }
+
+    /// The offset in bytes of the texel at `texel`, which is relative to the minimum of the texture.
+    pub fn texel_offset(&self, texel: Point3i) -> usize {
+        let row = texel.z() as usize * self.rows_per_image as usize + texel.y() as usize;
+
+        row * self.bytes_per_row as usize + texel.x() as usize * self.bytes_per_texel as usize
+    }
+}
+
+/// The bytes of a 3D texture, ready to be uploaded to the GPU.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextureBuffer3 {
+    pub layout: TextureLayout3,
+    pub data: Vec<u8>,
+}
+
+impl TextureBuffer3 {
+    /// Packs the values of `src` in `extent` into a texture, with rows padded to a multiple of `row_alignment` bytes. All
+    /// padding bytes are zero.
+    pub fn pack<Src, T>(src: &Src, extent: &Extent3i, row_alignment: u32) -> Self
+    where
+        Src: ForEach<[i32; 3], Point3i, Item = T>,
+        T: Pod,
+    {
+        let layout = TextureLayout3::new(
+            extent.shape,
+            core::mem::size_of::<T>() as u32,
+            row_alignment,
+        );
+        let texel_len = layout.bytes_per_texel as usize;
+        let mut data = vec![0; layout.buffer_len()];
+        src.for_each(extent, |p: Point3i, value: T| {
+            let offset = layout.texel_offset(p - extent.minimum);
+            data[offset..offset + texel_len].copy_from_slice(bytes_of(&value));
+        });
+
+        Self { layout, data }
+    }
+}
+
+/// The number of mip levels from a texture with `shape` down to a single texel, including the full resolution level.
+pub fn full_mip_level_count(shape: Point3i) -> u32 {
+    32 - (shape.max_component() as u32).leading_zeros()
+}
+
+/// Generates `mip_level_count` levels of a texture, starting with `base` at level `0`. Each level halves the shape of the
+/// previous level along each axis, rounding down like GPUs do, but never below `1`. Every texel is reduced by `downsampler`
+/// from the (up to) `2x2x2` texels of the previous level that it covers.
+///
+/// All levels have their minimum at the origin, so `base` is translated to the origin too.
+pub fn texture_mip_chain<T, D>(
+    mut base: Array3x1<T>,
+    mip_level_count: u32,
+    downsampler: &D,
+) -> Vec<Array3x1<T>>
+where
+    T: Clone,
+    D: Downsampler<T>,
+{
+    assert!(mip_level_count > 0);
+    assert!(mip_level_count <= full_mip_level_count(base.extent().shape));
+
+    base.set_minimum(Point3i::ZERO);
+
+    let mut mips = Vec::with_capacity(mip_level_count as usize);
+    mips.push(base);
+
+    // Reuse the same buffer for every texel.
+    let mut samples = Vec::with_capacity(8);
+    for _ in 1..mip_level_count {
+        let src = mips.last().unwrap();
+        let src_extent = *src.extent();
+        let dst_shape = (src_extent.shape >> 1).join(Point3i::fill(1));
+        let dst = Array3x1::fill_with(
+            Extent3i::from_min_and_shape(Point3i::ZERO, dst_shape),
+            |p: Point3i| {
+                let covered = Extent3i::from_min_and_shape(p << 1, Point3i::fill(2))
+                    .intersection(&src_extent);
+                samples.clear();
+                for q in covered.iter_points() {
+                    samples.push(src.get(q));
+                }
+
+                downsampler.reduce(&samples)
+            },
+        );
+        mips.push(dst);
+    }
+
+    mips
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{prelude::*, MinDownsampler};
+
+    #[test]
+    fn rows_are_padded_to_alignment() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, PointN([3, 2, 2]));
+        let array = Array3x1::fill_with(extent, |p| (1 + p.x() + 10 * p.y() + 100 * p.z()) as u16);
+
+        let buffer = TextureBuffer3::pack(&array, &extent, 8);
+        assert_eq!(buffer.layout.bytes_per_texel, 2);
+        assert_eq!(buffer.layout.bytes_per_row, 8);
+        assert_eq!(buffer.data.len(), 8 * 2 * 2);
+
+        let texels: Vec<u16> = buffer
+            .data
+            .chunks(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(
+            texels,
+            [1, 2, 3, 0, 11, 12, 13, 0, 101, 102, 103, 0, 111, 112, 113, 0]
+        );
+
+        let tight = TextureBuffer3::pack(&array, &extent, 1);
+        assert_eq!(tight.layout.bytes_per_row, 6);
+        assert_eq!(tight.data.len(), 6 * 2 * 2);
+    }
+
+    #[test]
+    fn chunk_map_extent_packs_like_an_array() {
+        let extent = Extent3i::from_min_and_shape(PointN([-3, -2, -1]), PointN([7, 5, 6]));
+        let array = Array3x1::fill_with(extent, |p| {
+            p.x() as f32 + p.y() as f32 * 0.5 + p.z() as f32 * 0.25
+        });
+
+        let builder = ChunkMapBuilder3x1::new(Point3i::fill(4), 0.0);
+        let mut map = builder.build_with_hash_map_storage();
+        copy_extent(&extent, &array, &mut map.lod_view_mut(0));
+
+        assert_eq!(
+            TextureBuffer3::pack(&map.lod_view(0), &extent, COPY_BYTES_PER_ROW_ALIGNMENT),
+            TextureBuffer3::pack(&array, &extent, COPY_BYTES_PER_ROW_ALIGNMENT)
+        );
+    }
+
+    #[test]
+    fn mips_of_odd_shapes_round_down() {
+        let extent = Extent3i::from_min_and_shape(PointN([10, 0, 0]), PointN([5, 3, 1]));
+        let base = Array3x1::fill_with(extent, |p| (p.x() - 10 + 5 * p.y()) as u8);
+        assert_eq!(full_mip_level_count(extent.shape), 3);
+
+        let mips = texture_mip_chain(base, 3, &MinDownsampler);
+        assert_eq!(mips[0].extent().minimum, Point3i::ZERO);
+        assert_eq!(mips[1].extent().shape, PointN([2, 1, 1]));
+        assert_eq!(mips[2].extent().shape, PointN([1, 1, 1]));
+
+        assert_eq!(mips[1].get(PointN([0, 0, 0])), 0);
+        assert_eq!(mips[1].get(PointN([1, 0, 0])), 2);
+        assert_eq!(mips[2].get(Point3i::ZERO), 0);
+
+        let mips = texture_mip_chain(mips.into_iter().next().unwrap(), 2, &PointDownsampler);
+        assert_eq!(mips[1].get(PointN([1, 0, 0])), 2);
+    }
+}
@@ -37,16 +37,40 @@
 //! let tfm = TransformMap::new(&src, |value: i32| value + 1);
 //! copy_extent(&extent, &tfm, &mut dst.lod_view_mut(0));
 //! ```
+//!
+//! When the transform has an inverse, a `TransformMapMut` can also write through the transformed view. Values are converted
+//! back with the inverse before they are stored in the delegate.
+//!
+//! ```
+//! # use building_blocks_core::prelude::*;
+//! # use building_blocks_storage::prelude::*;
+//! # let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(16));
+//! // Store densities compactly as `u8`, but expose them as `f32` in `[0, 1]`.
+//! let mut compact = Array3x1::fill(extent, 0u8);
+//! let mut densities = TransformMapMut::new(
+//!     &mut compact,
+//!     |d: u8| d as f32 / 255.0,
+//!     |d: f32| (d * 255.0).round() as u8,
+//! );
+//!
+//! *densities.get_mut(Point3i::ZERO) = 1.0;
+//! densities.fill_extent(&Extent3i::from_min_and_shape(Point3i::ONES, Point3i::ONES), 0.5);
+//! assert_eq!(densities.get(Point3i::ZERO), 1.0);
+//!
+//! assert_eq!(compact.get(Point3i::ZERO), 255);
+//! assert_eq!(compact.get(Point3i::ONES), 128);
+//! ```
 
 use crate::{
     AmbientExtent, Array, ArrayCopySrc, ChunkCopySrc, ChunkCopySrcIter, ChunkMap, ChunkMapBuilder,
-    ChunkMapLodView, ChunkReadStorage, ForEach, Get, IndexedArray, ReadExtent,
+    ChunkMapLodView, ChunkReadStorage, FillExtent, ForEach, Get, GetMut, IndexedArray, ReadExtent,
+    WriteExtent,
 };
 
 use building_blocks_core::prelude::*;
 
 use core::iter::{once, Once};
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 /// A lattice map that delegates look-ups to a different lattice map, then transforms the result
 /// using some `Fn(In) -> Out`.
@@ -187,6 +211,175 @@ where
     }
 }
 
+/// A `TransformMap` that can also write to its delegate, using an `inverse` transform `Fn(Out) -> In` to convert values back
+/// to the delegate's type. This is useful when you store values compactly but want to read and write a more convenient type.
+///
+/// `inverse` should actually invert `transform`, otherwise values will change when read and written back unmodified.
+pub struct TransformMapMut<'a, Delegate, F, G> {
+    delegate: &'a mut Delegate,
+    transform: F,
+    inverse: G,
+}
+
+impl<'a, Delegate, F, G> TransformMapMut<'a, Delegate, F, G> {
+    #[inline]
+    pub fn new(delegate: &'a mut Delegate, transform: F, inverse: G) -> Self {
+        Self {
+            delegate,
+            transform,
+            inverse,
+        }
+    }
+
+    /// A read-only `TransformMap` of the same delegate.
+    #[inline]
+    pub fn as_transform_map(&self) -> TransformMap<'_, Delegate, &F> {
+        TransformMap::new(&*self.delegate, &self.transform)
+    }
+}
+
+impl<'a, Delegate, F, G, In, Out, Coord> Get<Coord> for TransformMapMut<'a, Delegate, F, G>
+where
+    F: Fn(In) -> Out,
+    Delegate: Get<Coord, Item = In>,
+{
+    type Item = Out;
+
+    #[inline]
+    fn get(&self, c: Coord) -> Self::Item {
+        (self.transform)(self.delegate.get(c))
+    }
+}
+
+impl<'a, N, Delegate, F, G, In, Out, Coord> ForEach<N, Coord>
+    for TransformMapMut<'a, Delegate, F, G>
+where
+    F: Fn(In) -> Out,
+    Delegate: ForEach<N, Coord, Item = In>,
+{
+    type Item = Out;
+
+    #[inline]
+    fn for_each(&self, extent: &ExtentN<N>, mut f: impl FnMut(Coord, Self::Item)) {
+        self.delegate
+            .for_each(extent, |c, t| f(c, (self.transform)(t)))
+    }
+}
+
+impl<'a, 'b, Delegate, F, G, In, Out, Coord> GetMut<'b, Coord>
+    for TransformMapMut<'a, Delegate, F, G>
+where
+    Delegate: GetMut<'b, Coord, Item = &'b mut In>,
+    F: Fn(In) -> Out,
+    G: 'b + Fn(Out) -> In,
+    In: 'b + Clone,
+    Out: Clone,
+{
+    type Item = TransformRefMut<'b, In, Out, G>;
+
+    #[inline]
+    fn get_mut(&'b mut self, c: Coord) -> Self::Item {
+        let slot = self.delegate.get_mut(c);
+        let value = (self.transform)(slot.clone());
+
+        TransformRefMut {
+            slot,
+            value,
+            inverse: &self.inverse,
+        }
+    }
+}
+
+/// A mutable reference to a transformed value from `TransformMapMut::get_mut`. The value is written back to the delegate with
+/// the inverse transform when this is dropped.
+pub struct TransformRefMut<'a, In, Out, G>
+where
+    G: Fn(Out) -> In,
+    Out: Clone,
+{
+    slot: &'a mut In,
+    value: Out,
+    inverse: &'a G,
+}
+
+impl<'a, In, Out, G> Deref for TransformRefMut<'a, In, Out, G>
+where
+    G: Fn(Out) -> In,
+    Out: Clone,
+{
+    type Target = Out;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'a, In, Out, G> DerefMut for TransformRefMut<'a, In, Out, G>
+where
+    G: Fn(Out) -> In,
+    Out: Clone,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'a, In, Out, G> Drop for TransformRefMut<'a, In, Out, G>
+where
+    G: Fn(Out) -> In,
+    Out: Clone,
+{
+    #[inline]
+    fn drop(&mut self) {
+        *self.slot = (self.inverse)(self.value.clone());
+    }
+}
+
+impl<'a, N, Delegate, F, G, In, Out> FillExtent<N> for TransformMapMut<'a, Delegate, F, G>
+where
+    Delegate: FillExtent<N, Item = In>,
+    G: Fn(Out) -> In,
+{
+    type Item = Out;
+
+    #[inline]
+    fn fill_extent(&mut self, extent: &ExtentN<N>, value: Out) {
+        self.delegate.fill_extent(extent, (self.inverse)(value))
+    }
+}
+
+// Copying from an array into the transformed view is the same as copying from the inverse-transformed array into the delegate.
+impl<'a, 'b, N, Delegate, F, G, Chan> WriteExtent<N, ArrayCopySrc<&'b Array<N, Chan>>>
+    for TransformMapMut<'a, Delegate, F, G>
+where
+    Delegate: WriteExtent<N, ArrayCopySrc<TransformMap<'b, Array<N, Chan>, G>>>,
+    G: Copy,
+{
+    #[inline]
+    fn write_extent(&mut self, extent: &ExtentN<N>, src: ArrayCopySrc<&'b Array<N, Chan>>) {
+        self.delegate
+            .write_extent(extent, ArrayCopySrc(TransformMap::new(src.0, self.inverse)))
+    }
+}
+
+impl<'a, 'b, N, Delegate, F, G, In, Out, Ch> WriteExtent<N, ChunkCopySrc<N, Out, &'b Ch>>
+    for TransformMapMut<'a, Delegate, F, G>
+where
+    Delegate: WriteExtent<N, ChunkCopySrc<N, In, TransformMap<'b, Ch, G>>>,
+    G: Copy + Fn(Out) -> In,
+{
+    #[inline]
+    fn write_extent(&mut self, extent: &ExtentN<N>, src: ChunkCopySrc<N, Out, &'b Ch>) {
+        let inverse = self.inverse;
+        let src = src
+            .map_left(|array_src| ArrayCopySrc(TransformMap::new(array_src.0, inverse)))
+            .map_right(|ambient| AmbientExtent::new((inverse)(ambient.value)));
+        self.delegate.write_extent(extent, src)
+    }
+}
+
 // ████████╗███████╗███████╗████████╗
 // ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
 //    ██║   █████╗  ███████╗   ██║
@@ -251,4 +444,51 @@ mod tests {
         let mut dst = INT_BUILDER.build_with_hash_map_storage();
         copy_extent(&dst_extent, &tfm, &mut dst.lod_view_mut(0));
     }
+
+    #[test]
+    fn write_through_inverse_transform() {
+        let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(8));
+        let mut compact = Array3x1::fill(extent, 0u8);
+        let mut wide = TransformMapMut::new(
+            &mut compact,
+            |x: u8| x as i32 - 128,
+            |x: i32| (x + 128) as u8,
+        );
+
+        assert_eq!(wide.get(Point3i::ZERO), -128);
+        *wide.get_mut(Point3i::ZERO) += 1;
+        assert_eq!(wide.get(Point3i::ZERO), -127);
+
+        let src = Array3x1::fill(extent, 5);
+        let copy_extent_min = Extent3i::from_min_and_shape(Point3i::fill(4), Point3i::fill(4));
+        copy_extent(&copy_extent_min, &src, &mut wide);
+        wide.for_each(&extent, |p: Point3i, x| {
+            if copy_extent_min.contains(p) {
+                assert_eq!(x, 5);
+            } else if p != Point3i::ZERO {
+                assert_eq!(x, -128);
+            }
+        });
+
+        assert_eq!(compact.get(Point3i::ZERO), 1);
+        assert_eq!(compact.get(Point3i::fill(4)), 133);
+    }
+
+    #[test]
+    fn copy_chunk_map_into_transformed_chunk_map() {
+        let extent = Extent3i::from_min_and_shape(Point3i::fill(-6), Point3i::fill(12));
+        let mut src = INT_BUILDER.build_with_hash_map_storage();
+        src.fill_extent(0, &extent, 7);
+
+        let mut dst = FLOAT_BUILDER.build_with_hash_map_storage();
+        let mut dst_lod0 = dst.lod_view_mut(0);
+        let mut as_int = TransformMapMut::new(&mut dst_lod0, |x: f32| x as i32, |x: i32| x as f32);
+        // Reads past the filled extent, so some of the source is ambient.
+        copy_extent(&extent.padded(2), &src.lod_view(0), &mut as_int);
+
+        let dst_lod0 = dst.lod_view(0);
+        dst_lod0.for_each(&extent.padded(2), |p: Point3i, x| {
+            assert_eq!(x, if extent.contains(p) { 7.0 } else { 0.0 });
+        });
+    }
 }
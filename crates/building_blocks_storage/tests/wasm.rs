@@ -0,0 +1,50 @@
+//! Tests for the `wasm32-unknown-unknown` target, run with `wasm-pack test --node -- --test wasm`.
+//!
+//! These need the "snap" feature, which is enabled by default, since the C library behind "lz4" doesn't build for WASM.
+
+#![cfg(all(target_arch = "wasm32", feature = "snap"))]
+
+use building_blocks_core::prelude::*;
+use building_blocks_storage::{
+    prelude::*, BudgetAction, FastArrayCompressionNx1, LruPolicy, MemoryBudget, Snappy,
+};
+
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn snappy_array_compression_round_trip() {
+    let extent = Extent3i::from_min_and_shape(Point3i::fill(-8), Point3i::fill(16));
+    let array = Array3x1::fill_with(extent, |p: Point3i| p.dot(p));
+
+    let compression = FastArrayCompressionNx1::from_bytes_compression(Snappy);
+    let compressed = compression.compress(&array);
+
+    assert_eq!(compressed.decompress(), array);
+}
+
+#[wasm_bindgen_test]
+fn compressible_chunk_map_with_memory_budget() {
+    let builder = ChunkMapBuilder3x1::new(Point3i::fill(16), 0);
+    let mut map = builder.build_with_write_storage(
+        FastCompressibleChunkStorageNx1::with_bytes_compression(Snappy),
+    );
+    // Only one 16^3 chunk of `i32`s fits in the cache.
+    map.storage_mut().set_memory_budget(
+        MemoryBudget {
+            max_cached_bytes: 16 * 16 * 16 * 4,
+            action: BudgetAction::Compress,
+        },
+        LruPolicy::default(),
+    );
+
+    let extent = Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(32));
+    map.fill_extent_with(0, &extent, |p: Point3i| p.x() + 32 * p.y());
+    assert_eq!(map.storage().len_cached(), 1);
+    assert_eq!(map.storage().len_total(), 8);
+
+    let local_cache = LocalChunkCache3::new();
+    let reader = map.reader(&local_cache);
+    for p in extent.iter_points() {
+        assert_eq!(reader.clone_point(0, p), p.x() + 32 * p.y());
+    }
+}
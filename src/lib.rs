@@ -11,26 +11,103 @@
 //!
 //! We focus on generally useful data structures and algorithms. Features include:
 //!
+//! - 2D and 3D points and extents with `i32`, `f32`, or `f64` scalars
+//!   - `f64` variants for world coordinates that exceed `f32` precision
+//!   - extent union and difference as lists of disjoint extents, plus an `ExtentSet` container
+//!   - Morton (Z-order) codes over the full `i32` range, with BIGMIN/LITMAX range queries for scanning extents
+//!   - `no_std` + `alloc` builds of the points, extents, and `Array` types, for embedded targets and GPU tooling
 //! - 2D and 3D data storage
 //!   - [`Array`](crate::storage::array) with structure-of-arrays (SoA) storage of multiple data channels per spatial dimension
+//!     - up to 8 channels per point, in a tuple or in a struct of named channels with `#[derive(Channels)]`
+//!     - single-channel views of multichannel arrays and chunk maps, like `lod_channel_view_mut::<Ch1>`, without copying
+//!     - Morton (Z-order) layout with `MortonArray` for cache-friendly neighborhood access
+//!     - custom 3D axis orderings (e.g. XZY or column-major) with `AxisOrderedArray3`, for meshing imported data without copying it
+//!     - zero-copy arrays over borrowed or boxed slices, or over memory-mapped bytes with `from_bytes`
+//!     - 4D arrays, e.g. for time-varying volumes
+//!     - 2D slice views of 3D arrays, e.g. for per-layer processing
+//!     - rotations and mirrors of 3D arrays, both as lazy views and as copies, e.g. for placing prefabs with any orientation
+//!     - packing arrays or chunk map extents into row-padded 3D texture buffers for wgpu and Vulkan, with mip chains
 //!   - [`ChunkMap`](crate::storage::chunk::map) with generic chunk storage
+//!     - brush-based editing with `ChunkMapEditor`, reporting which chunks changed
+//!       - iterating the lattice points (and distances) inside of sphere, capsule, cylinder, and cone brushes
+//!     - journaled edits with `EditJournal`, for undo/redo of grouped transactions stored as inverse `ChunkDelta` patches
+//!     - stamping prefabs into a `ChunkMap3` with any rotation or mirror, palette remapping, and a per-voxel merge policy
+//!     - fast sparse writes of scattered `(point, value)` pairs with `WritePoints`
+//!     - splitting chunks into disjoint (e.g. checkerboard) partitions that can be mutated on separate threads
+//!     - floating origin support, shifting every chunk by a chunk-aligned delta without copying voxels
+//!     - in-place translation of all chunk keys and `OctreeChunkIndex` octrees, e.g. for stitching worlds together
+//!     - merging another chunk map into a `ChunkMap3` with per-chunk or per-voxel conflict policies, moving compressed chunks as-is
+//!     - cropping with `extract_extent` and `discard_outside`, keeping every level of detail
+//!     - procedural generation with `fill_extent_with`, which evaluates a function directly into new chunks
+//!     - X-strip batch filling with `fill_with_strips` and `fill_extent_with_strips`, for SIMD noise functions
+//!     - lazy procedural chunks from a generator registered with `ChunkMapBuilder::with_generator`, optionally cached in storage
+//!     - optional dirty chunk tracking for remeshing and persistence
+//!     - optional folding of uniform chunks, like all air or all stone, into a single value
+//!     - optional per-chunk occupancy bitmasks of `4x4x4` subcells, for culling, ray skipping, and all-air tests
+//!     - copy-on-write shared chunk storage, for cheap snapshots and speculative edits
+//!     - compressible chunk storage with byte budgets, LRU, LFU, or CLOCK eviction, and optional background compression
+//!     - `ChunkMapStats` for tuning cache budgets: per-LOD chunk counts and bytes, cache hit rates, evictions, and size
+//!       histograms
+//!     - sharded concurrent chunk storage, for reading chunks on worker threads while new chunks are inserted
+//!     - versioned chunk storage with snapshots for undo/redo
+//!     - read-only `MappedChunkStorage` over a memory-mapped file of compressed chunks, for worlds larger than RAM
+//!     - `ChunkDelta` patches for sending only the changed voxels of a chunk over the network
 //!   - [`ChunkDb`](crate::storage::database) for compressed, persistent voxel worlds, backed by the
-//!     [`sled`](https://docs.rs/sled) embedded database
+//!     [`sled`](https://docs.rs/sled), [`rocksdb`](https://docs.rs/rocksdb), or SQLite
+//!     ([`rusqlite`](https://docs.rs/rusqlite)) embedded databases
+//!     - or by Anvil-style region files with many chunks per file using `RegionChunkDb`
+//!     - exact extent queries in Morton-ordered scans that skip the keys outside of the extent
+//!     - `VersionedChunkDb` for reading the world as of older versions, e.g. for server rollback
+//!     - `ChunkDbWriter` for batched, debounced background writes with backpressure (`tokio` feature)
+//!     - `MemoryChunkDb` and `VersionedMemoryChunkDb` with the same semantics, for tests and headless servers
+//!     - `BackendChunkDb` over any ordered key-value store implementing `ChunkDbBackend`, e.g. S3 or Redis
 //!   - [`OctreeSet`](crate::storage::octree::set) hierarchical bitset of voxel points
+//!     - union, intersection, and (symmetric) difference without rasterizing
+//!     - frustum, sphere, and oriented box queries that cull whole subtrees, also for chunks in an `OctreeChunkIndex`
+//!     - ray casting that descends the hierarchy and returns the first occupied leaf, its face normal, and distance
+//!     - level-order visits of octants with their extents and fullness, e.g. for LOD impostors or minimap tiles
+//!     - compact versioned binary encoding that only stores child bitmasks, under half the size of bincode
+//!   - `SparseVoxelOctree` with a value in every uniform leaf, DAG compression of identical subtrees, and ray casting
+//!   - `HeightMap` terrain height fields with ray casting, normals, hydraulic and thermal erosion, and voxel column conversion
+//!   - `FixedSd` fixed-point signed distances with a configurable integer type and scale, like the built-in `Sd8` and `Sd16`
 //! - level of detail
 //!   - `ChunkMap` can downsample chunks into lower resolutions within the same storage
-//!   - dynamic 3D clipmap for keeping high detail close to a focal point
+//!     - incremental updates that only resample the ancestors of edited chunks
+//!     - point, mean, SDF mean, majority, min, and max downsamplers, or custom ones with the `Downsampler` trait
+//!   - dynamic 3D clipmap for keeping high detail close to one or more focal points
+//!     - per-frame diffs of chunks to load, unload, split, and merge, with hysteresis to prevent flicker
 //! - mesh generation
-//!   - Surface Nets isosurface extraction (TODO: multiresolution)
+//!   - Surface Nets isosurface extraction, with seams and geomorphing between levels of detail
+//!   - Marching Cubes isosurface extraction
+//!   - Dual Contouring isosurface extraction, preserving sharp features
 //!   - Minecraft-style greedy meshing
-//!   - height maps
+//!     - per-vertex ambient occlusion
+//!     - texture atlas coordinates that tile across merged quads
+//!     - merging quads across chunk borders, with each quad attributed to the chunk that contains its minimum
+//!   - blocky meshing with one quad per visible face, culled against neighboring chunks, for per-face AO and lighting
+//!   - height maps, with optional skirts to hide seams between tiles
+//!   - mesh simplification with quadric error metrics
+//!   - voxelization of triangle meshes into shells, solids, or signed distance fields
+//!   - binary glTF (`.glb`) export with per-material primitives and vertex colors
+//!   - OBJ and PLY debug dumps of any mesh buffer, with normals and optional per-vertex attributes
 //! - spatial queries
-//!   - ray and ball casting against octrees with [`ncollide3d`](https://www.ncollide.org/)
+//!   - ray, ball, and capsule casting against octrees or directly against voxel predicates with [`ncollide3d`](https://www.ncollide.org/)
 //!   - Amanatides and Woo ray grid traversal
+//!     - hierarchical traversal of a `ChunkMap3` that skips absent or empty chunks
 //!   - pathfinding
+//!     - hierarchical pathfinding (HPA*) across the chunks of big worlds
+//!     - surface pathfinding for agents walking, stepping, and jumping on voxel ground
+//!   - connected component labeling, e.g. for detecting floating islands
+//!   - nearest and k-nearest occupied or surface voxel queries on octrees
+//!   - flood fill with 6, 18, or 26 connectivity and a visited-voxel budget
+//!   - Minecraft-style sunlight and point light propagation with incremental updates when voxels change
 //! - procedural generation
 //!   - sampling signed distance fields
+//!   - SDF primitives and (smooth) constructive solid geometry, sampled or edited in place on arrays
+//!   - exact signed distance fields from blocky voxels with a Euclidean distance transform
 //!   - constructive solid geometry with [`sdfu`](https://docs.rs/sdfu)
+//!   - double-buffered cellular automata on arrays and chunk maps, with any stencil radius and optional `rayon` parallelism
+//!     - a reference water simulation with flow and pressure, where settled chunks stop ticking
 //!
 //! # Short Code Example
 //!
@@ -131,25 +208,81 @@
 //!
 //! ### Math Type Conversions
 //!
-//! The `PointN` types have conversions to/from [`glam`](https://docs.rs/glam), [`nalgebra`](https://nalgebra.org/), and
-//! [`mint`](https://docs.rs/mint) types by enabling the corresponding feature.
+//! The `PointN` types have conversions to/from [`glam`](https://docs.rs/glam), [`nalgebra`](https://nalgebra.org/),
+//! [`cgmath`](https://docs.rs/cgmath), and [`mint`](https://docs.rs/mint) types by enabling the corresponding feature. With the
+//! `ncollide` feature, `Extent3i` and `Extent3f` can also be converted to and from `ncollide3d` AABBs.
 //!
 //! ### Compression Backends and WASM
 //!
 //! Chunk compression supports two backends out of the box: `Lz4` and `Snappy`. They are enabled with the "lz4" and "snappy"
-//! features. "lz4" is the default, but it relies on a C++ library, so it's not compatible with WASM. But Snappy is pure Rust,
-//! so it can! Just use `default-features = false` and add "snappy" to you `features` list.
+//! features. "snappy" is the default, since Snappy is pure Rust and builds for `wasm32-unknown-unknown` like the rest of
+//! `building_blocks_storage`. "lz4" is a bit faster, but it relies on a C library, so it's not compatible with WASM; add it to
+//! your `features` list if you're only targeting native platforms.
+//!
+//! The storage crate's WASM tests can be run with `wasm-pack test --node crates/building_blocks_storage -- --test wasm`. Note
+//! that the "sled", "rocksdb", and "sqlite" databases don't target WASM, so leave those features off there.
+//!
+//! There is also a `Zstd` backend, enabled with the "zstd" feature. It is slower than the others but compresses much better,
+//! which makes it a good fit for archived worlds. Like "lz4", it relies on a C library.
+//!
+//! `Rle` (run-length encoding) is always available. It works on whole channel values, so it's very fast for voxel data
+//! with long runs of the same value, and its output can be chained into another backend, e.g. `Rle { then: Lz4 { level: 10 } }`.
+//!
+//! Each channel of a multichannel chunk can also use its own backend and level by compressing with a tuple of single-channel
+//! compressions, e.g. fast LZ4 for an SDF channel and `Rle` plus high-level LZ4 for a rarely-edited material channel.
+//!
+//! ### `no_std`
+//!
+//! `building_blocks_core` and `building_blocks_storage` have a "std" feature that is enabled by default. Depend on them
+//! directly with `default-features = false` to build them with only `core` and `alloc`. This keeps the points, extents, and
+//! `Array` types with their channels and access traits, but compression, chunk maps, octrees, and databases require "std". The
+//! top-level `building-blocks` crate always uses "std".
 //!
 //! ### VOX Files
 //!
 //! ".VOX" files are supported via the [`dot_vox`](https://docs.rs/dot_vox/) crate. Enable the `dot_vox` feature to expose the
-//! generic `encode_vox` function and `Array3x1::decode_vox` constructor.
+//! generic `encode_vox` function and `Array3x1::decode_vox` constructor. Models can also be decoded into raw `u8` color indices
+//! with `Array3x1::decode_vox_raw_indices` or written into a `ChunkMap3` with `ChunkMap::write_vox_model`. The palette is
+//! available as `DotVoxData::palette`, and `VoxColor::rgba` looks up the color of a voxel.
 //!
 //! ### Images
 //!
 //! Arrays can be converted to `ImageBuffer`s and constructed from `GenericImageView`s from the [`image`](https://docs.rs/image)
 //! crate. Enable the `image` feature to expose the generic `encode_image` function and `From<Im> where Im: GenericImageView`
-//! impl.
+//! impl. Grayscale `Array2x1<u8>` and `Array2x1<u16>` convert to and from `ImageBuffer`s with `to_luma8`/`from_luma8` and
+//! `to_luma16`/`from_luma16`, and arrays of any `Pixel` type convert with `to_image_buffer`. For debugging, `save_as_png`
+//! writes any 2D map of scalars, like a height map or an `Array3Slice`, as a normalized grayscale PNG.
+//!
+//! ### Memory-Mapped Files
+//!
+//! Enable the `memmap2` feature to expose `MappedChunkStorage::open`, which memory-maps a file written by
+//! `MappedChunkStorage::write_file` with the [`memmap2`](https://docs.rs/memmap2) crate. Without the feature,
+//! `MappedChunkStorage` works on any bytes, including a map you create yourself.
+//!
+//! ### Parallel Iteration
+//!
+//! Enable the `rayon` feature to expose `Array::par_for_each` and the `ChunkMap::par_*` methods, which iterate over Z-slabs or
+//! chunks in parallel on the [`rayon`](https://docs.rs/rayon) thread pool. It also enables
+//! `CompressibleChunkStorage::enable_background_compression`, which compresses evicted chunks on a thread pool instead of
+//! the thread that's writing.
+//!
+//! ### GPU Vertex Buffers
+//!
+//! Enable the `bytemuck` feature to expose the `#[repr(C)]` vertex types in `building_blocks::mesh::vertex`. Meshes can be
+//! converted into an `InterleavedMesh`, whose vertex and index buffers can be uploaded to the GPU as bytes without copying
+//! each attribute separately.
+//!
+//! ### Bevy
+//!
+//! The `building_blocks_bevy` crate converts meshes into Bevy `Mesh`es, tracks one mesh entity per chunk, and provides a
+//! `ClipmapLodPlugin` that sends events when chunks change their level of detail as the camera moves. The same `From`
+//! conversions are available in `building_blocks_mesh` with the `bevy` feature.
+//!
+//! ### Tracing
+//!
+//! Enable the `tracing` feature to emit [`tracing`](https://docs.rs/tracing) spans around chunk compression and
+//! decompression, chunk generation, database reads and writes, downsampling, and meshing. The spans are at the `TRACE` level,
+//! so they are cheap unless a subscriber enables them. Cache evictions are counted in `ChunkMapStats` either way.
 //!
 //! ### Signed Distance Field Utilities (sdfu)
 //!